@@ -82,6 +82,11 @@ impl BitUnpacker {
         }
     }
 
+    /// Returns the number of bits used to encode each value.
+    pub fn bit_width(&self) -> u8 {
+        self.num_bits as u8
+    }
+
     #[inline]
     pub fn get(&self, idx: u64, data: &[u8]) -> u64 {
         if self.num_bits == 0 {
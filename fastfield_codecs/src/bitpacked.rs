@@ -1,3 +1,4 @@
+use std::convert::TryInto;
 use std::io::{self, Write};
 
 use common::BinarySerializable;
@@ -14,6 +15,13 @@ pub struct BitpackedFastFieldReader {
     pub max_value_u64: u64,
 }
 
+impl BitpackedFastFieldReader {
+    /// Returns the number of bits used to encode each `(val - min_value)`.
+    pub fn num_bits(&self) -> u8 {
+        self.bit_unpacker.bit_width()
+    }
+}
+
 impl<'data> FastFieldCodecReader for BitpackedFastFieldReader {
     /// Opens a fast field given a file.
     fn open_from_bytes(bytes: &[u8]) -> io::Result<Self> {
@@ -84,6 +92,31 @@ impl<'a, W: Write> BitpackedFastFieldSerializerLegacy<'a, W> {
             .write(val_to_write, self.num_bits, &mut self.write)?;
         Ok(())
     }
+    /// Pushes `num_vals` values that are already bitpacked, as in the data read by a
+    /// `BitpackedFastFieldReader`.
+    ///
+    /// The values are copied 64 bits at a time, without being unpacked, so they must have been
+    /// packed with the same `min_value` and number of bits as this serializer.
+    pub fn add_packed_vals(&mut self, packed_data: &[u8], num_vals: u64) -> io::Result<()> {
+        let num_bits_to_copy = num_vals * self.num_bits as u64;
+        let num_full_words = (num_bits_to_copy / 64) as usize;
+        for word_bytes in packed_data[..num_full_words * 8].chunks_exact(8) {
+            let word = u64::from_le_bytes(word_bytes.try_into().unwrap());
+            self.bit_packer.write(word, 64, &mut self.write)?;
+        }
+        let num_remaining_bits = (num_bits_to_copy % 64) as u8;
+        if num_remaining_bits > 0 {
+            let num_remaining_bytes = (num_remaining_bits as usize + 7) / 8;
+            let mut last_word_bytes = [0u8; 8];
+            last_word_bytes[..num_remaining_bytes]
+                .copy_from_slice(&packed_data[num_full_words * 8..][..num_remaining_bytes]);
+            let mask = (1u64 << num_remaining_bits) - 1;
+            let last_word = u64::from_le_bytes(last_word_bytes) & mask;
+            self.bit_packer
+                .write(last_word, num_remaining_bits, &mut self.write)?;
+        }
+        Ok(())
+    }
     pub fn close_field(mut self) -> io::Result<()> {
         self.bit_packer.close(&mut self.write)?;
         self.min_value.serialize(&mut self.write)?;
@@ -157,6 +190,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_packed_vals() {
+        let vals: Vec<u64> = (0..100).map(|i| (i * 7) % 31).collect();
+        let stats = crate::tests::stats_from_vec(&vals);
+        for split in [0, 1, 9, 13, 64, 100] {
+            let mut first_part = Vec::new();
+            BitpackedFastFieldSerializer::serialize(
+                &mut first_part,
+                &vals,
+                stats.clone(),
+                vals[..split].iter().cloned(),
+                vals[..split].iter().cloned(),
+            )
+            .unwrap();
+            let mut output = Vec::new();
+            let mut serializer = BitpackedFastFieldSerializerLegacy::open(
+                &mut output,
+                stats.min_value,
+                stats.max_value,
+            )
+            .unwrap();
+            serializer
+                .add_packed_vals(&first_part, split as u64)
+                .unwrap();
+            for &val in &vals[split..] {
+                serializer.add_val(val).unwrap();
+            }
+            serializer.close_field().unwrap();
+            let reader = BitpackedFastFieldReader::open_from_bytes(&output).unwrap();
+            for (doc, &val) in vals.iter().enumerate() {
+                assert_eq!(reader.get_u64(doc as u64, &output), val);
+            }
+        }
+    }
+
     #[test]
     fn bitpacked_fast_field_rand() {
         for _ in 0..500 {
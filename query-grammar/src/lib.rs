@@ -10,6 +10,15 @@ pub use crate::user_input_ast::{UserInputAst, UserInputBound, UserInputLeaf, Use
 pub struct Error;
 
 pub fn parse_query(query: &str) -> Result<UserInputAst, Error> {
-    let (user_input_ast, _remaining) = parse_to_ast().parse(query).map_err(|_| Error)?;
+    let (user_input_ast, _remaining) = parse_to_ast(false).parse(query).map_err(|_| Error)?;
+    Ok(user_input_ast)
+}
+
+/// Parses a query, accepting the `filter(...)` syntax.
+///
+/// The clauses of `filter(...)` restrict the documents matched without contributing to their
+/// score. They are returned boosted by 0, e.g. `+a +filter(b)` is parsed as `+a +b^0`.
+pub fn parse_query_with_filters(query: &str) -> Result<UserInputAst, Error> {
+    let (user_input_ast, _remaining) = parse_to_ast(true).parse(query).map_err(|_| Error)?;
     Ok(user_input_ast)
 }
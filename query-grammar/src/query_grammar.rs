@@ -254,15 +254,34 @@ fn negate(expr: UserInputAst) -> UserInputAst {
     expr.unary(Occur::MustNot)
 }
 
-fn leaf<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
-    parser(|input| {
+/// Parses `filter(...)`, if `filter_syntax` is enabled.
+///
+/// The clauses of a filter restrict the documents matched without contributing to their score:
+/// they are represented as clauses boosted by 0.
+fn filter<'a>(filter_syntax: bool) -> impl Parser<&'a str, Output = UserInputAst> {
+    string("filter(")
+        .and_then(move |_| match filter_syntax {
+            true => Ok(()),
+            false => Err(StringStreamError::UnexpectedParse),
+        })
+        .with(ast(filter_syntax))
+        .skip(char(')'))
+        .map(|ast| UserInputAst::Boost(Box::new(ast), 0.0))
+}
+
+fn leaf<'a>(filter_syntax: bool) -> impl Parser<&'a str, Output = UserInputAst> {
+    parser(move |input| {
         char('(')
-            .with(ast())
+            .with(ast(filter_syntax))
             .skip(char(')'))
             .or(char('*').map(|_| UserInputAst::from(UserInputLeaf::All)))
             .or(attempt(
-                string("NOT").skip(spaces1()).with(leaf()).map(negate),
+                string("NOT")
+                    .skip(spaces1())
+                    .with(leaf(filter_syntax))
+                    .map(negate),
             ))
+            .or(attempt(filter(filter_syntax)))
             .or(attempt(range().map(UserInputAst::from)))
             .or(literal().map(UserInputAst::from))
             .parse_stream(input)
@@ -276,8 +295,10 @@ fn occur_symbol<'a>() -> impl Parser<&'a str, Output = Occur> {
         .or(char('+').map(|_| Occur::Must))
 }
 
-fn occur_leaf<'a>() -> impl Parser<&'a str, Output = (Option<Occur>, UserInputAst)> {
-    (optional(occur_symbol()), boosted_leaf())
+fn occur_leaf<'a>(
+    filter_syntax: bool,
+) -> impl Parser<&'a str, Output = (Option<Occur>, UserInputAst)> {
+    (optional(occur_symbol()), boosted_leaf(filter_syntax))
 }
 
 fn positive_float_number<'a>() -> impl Parser<&'a str, Output = f64> {
@@ -297,8 +318,8 @@ fn boost<'a>() -> impl Parser<&'a str, Output = f64> {
     (char('^'), positive_float_number()).map(|(_, boost)| boost)
 }
 
-fn boosted_leaf<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
-    (leaf(), optional(boost())).map(|(leaf, boost_opt)| match boost_opt {
+fn boosted_leaf<'a>(filter_syntax: bool) -> impl Parser<&'a str, Output = UserInputAst> {
+    (leaf(filter_syntax), optional(boost())).map(|(leaf, boost_opt)| match boost_opt {
         Some(boost) if (boost - 1.0).abs() > std::f64::EPSILON => {
             UserInputAst::Boost(Box::new(leaf), boost)
         }
@@ -343,17 +364,22 @@ fn aggregate_binary_expressions(
     }
 }
 
-fn operand_leaf<'a>() -> impl Parser<&'a str, Output = (BinaryOperand, UserInputAst)> {
+fn operand_leaf<'a>(
+    filter_syntax: bool,
+) -> impl Parser<&'a str, Output = (BinaryOperand, UserInputAst)> {
     (
         binary_operand().skip(spaces()),
-        boosted_leaf().skip(spaces()),
+        boosted_leaf(filter_syntax).skip(spaces()),
     )
 }
 
-pub fn ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
-    let boolean_expr = (boosted_leaf().skip(spaces()), many1(operand_leaf()))
+pub fn ast<'a>(filter_syntax: bool) -> impl Parser<&'a str, Output = UserInputAst> {
+    let boolean_expr = (
+        boosted_leaf(filter_syntax).skip(spaces()),
+        many1(operand_leaf(filter_syntax)),
+    )
         .map(|(left, right)| aggregate_binary_expressions(left, right));
-    let whitespace_separated_leaves = many1(occur_leaf().skip(spaces().silent())).map(
+    let whitespace_separated_leaves = many1(occur_leaf(filter_syntax).skip(spaces().silent())).map(
         |subqueries: Vec<(Option<Occur>, UserInputAst)>| {
             if subqueries.len() == 1 {
                 let (occur_opt, ast) = subqueries.into_iter().next().unwrap();
@@ -370,9 +396,10 @@ pub fn ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
     spaces().with(expr).skip(spaces())
 }
 
-pub fn parse_to_ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
+/// Parses a query, with the `filter(...)` syntax if `filter_syntax` is enabled.
+pub fn parse_to_ast<'a>(filter_syntax: bool) -> impl Parser<&'a str, Output = UserInputAst> {
     spaces()
-        .with(optional(ast()).skip(eof()))
+        .with(optional(ast(filter_syntax)).skip(eof()))
         .map(|opt_ast| opt_ast.unwrap_or_else(UserInputAst::empty_query))
 }
 
@@ -440,13 +467,13 @@ mod test {
     }
 
     fn test_parse_query_to_ast_helper(query: &str, expected: &str) {
-        let query = parse_to_ast().parse(query).unwrap().0;
+        let query = parse_to_ast(false).parse(query).unwrap().0;
         let query_str = format!("{:?}", query);
         assert_eq!(query_str, expected);
     }
 
     fn test_is_parse_err(query: &str) {
-        assert!(parse_to_ast().parse(query).is_err());
+        assert!(parse_to_ast(false).parse(query).is_err());
     }
 
     #[test]
@@ -464,7 +491,7 @@ mod test {
     #[test]
     fn test_parse_query_to_ast_not_op() {
         assert_eq!(
-            format!("{:?}", parse_to_ast().parse("NOT")),
+            format!("{:?}", parse_to_ast(false).parse("NOT")),
             "Err(UnexpectedParse)"
         );
         test_parse_query_to_ast_helper("NOTa", "\"NOTa\"");
@@ -473,13 +500,29 @@ mod test {
 
     #[test]
     fn test_boosting() {
-        assert!(parse_to_ast().parse("a^2^3").is_err());
-        assert!(parse_to_ast().parse("a^2^").is_err());
+        assert!(parse_to_ast(false).parse("a^2^3").is_err());
+        assert!(parse_to_ast(false).parse("a^2^").is_err());
         test_parse_query_to_ast_helper("a^3", "(\"a\")^3");
         test_parse_query_to_ast_helper("a^3 b^2", "(*(\"a\")^3 *(\"b\")^2)");
         test_parse_query_to_ast_helper("a^1", "\"a\"");
     }
 
+    #[test]
+    fn test_filter() {
+        let test_parse_filter = |query: &str, expected: &str| {
+            let query = parse_to_ast(true).parse(query).unwrap().0;
+            assert_eq!(format!("{:?}", query), expected);
+        };
+        test_parse_filter("filter(a)", "(\"a\")^0");
+        test_parse_filter("+a +filter(b OR c)", "(+\"a\" +((?\"b\" ?\"c\"))^0)");
+        test_parse_filter("filter(a)^2", "((\"a\")^0)^2");
+        test_parse_filter("filter", "\"filter\"");
+        test_parse_filter("filter:a", "\"filter\":\"a\"");
+        assert!(parse_to_ast(true).parse("filter(a").is_err());
+        // Without the filter syntax, `filter` is a word.
+        test_parse_query_to_ast_helper("filter(a)", "(*\"filter\" *\"a\")");
+    }
+
     #[test]
     fn test_parse_query_to_ast_binary_op() {
         test_parse_query_to_ast_helper("a AND b", "(+\"a\" +\"b\")");
@@ -487,19 +530,19 @@ mod test {
         test_parse_query_to_ast_helper("a OR b AND c", "(?\"a\" ?(+\"b\" +\"c\"))");
         test_parse_query_to_ast_helper("a AND b         AND c", "(+\"a\" +\"b\" +\"c\")");
         assert_eq!(
-            format!("{:?}", parse_to_ast().parse("a OR b aaa")),
+            format!("{:?}", parse_to_ast(false).parse("a OR b aaa")),
             "Err(UnexpectedParse)"
         );
         assert_eq!(
-            format!("{:?}", parse_to_ast().parse("a AND b aaa")),
+            format!("{:?}", parse_to_ast(false).parse("a AND b aaa")),
             "Err(UnexpectedParse)"
         );
         assert_eq!(
-            format!("{:?}", parse_to_ast().parse("aaa a OR b ")),
+            format!("{:?}", parse_to_ast(false).parse("aaa a OR b ")),
             "Err(UnexpectedParse)"
         );
         assert_eq!(
-            format!("{:?}", parse_to_ast().parse("aaa ccc a OR b ")),
+            format!("{:?}", parse_to_ast(false).parse("aaa ccc a OR b ")),
             "Err(UnexpectedParse)"
         );
     }
@@ -525,7 +568,7 @@ mod test {
 
     #[test]
     fn test_occur_leaf() {
-        let ((occur, ast), _) = super::occur_leaf().parse("+abc").unwrap();
+        let ((occur, ast), _) = super::occur_leaf(false).parse("+abc").unwrap();
         assert_eq!(occur, Some(Occur::Must));
         assert_eq!(format!("{:?}", ast), "\"abc\"");
     }
@@ -734,10 +777,10 @@ mod test {
 
     #[test]
     fn test_slop() {
-        assert!(parse_to_ast().parse("\"a b\"~").is_err());
-        assert!(parse_to_ast().parse("foo:\"a b\"~").is_err());
-        assert!(parse_to_ast().parse("\"a b\"~a").is_err());
-        assert!(parse_to_ast().parse("\"a b\"~100000000000000000").is_err());
+        assert!(parse_to_ast(false).parse("\"a b\"~").is_err());
+        assert!(parse_to_ast(false).parse("foo:\"a b\"~").is_err());
+        assert!(parse_to_ast(false).parse("\"a b\"~a").is_err());
+        test_is_parse_err("\"a b\"~100000000000000000");
 
         test_parse_query_to_ast_helper("\"a b\"^2~4", "(*(\"a b\")^2 *\"~4\")");
         test_parse_query_to_ast_helper("\"~Document\"", "\"~Document\"");
@@ -1,7 +1,5 @@
 //! This will enhance the request tree with access to the fastfield and metadata.
 
-use std::rc::Rc;
-use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 
 use super::agg_req::{Aggregation, Aggregations, BucketAggregationType, MetricAggregation};
@@ -71,8 +69,7 @@ impl BucketAggregationWithAccessor {
         bucket: &BucketAggregationType,
         sub_aggregation: &Aggregations,
         reader: &SegmentReader,
-        bucket_count: Rc<AtomicU32>,
-        max_bucket_count: u32,
+        bucket_count: BucketCount,
     ) -> crate::Result<BucketAggregationWithAccessor> {
         let mut inverted_index = None;
         let (accessor, field_type) = match &bucket {
@@ -102,14 +99,10 @@ impl BucketAggregationWithAccessor {
                 &sub_aggregation,
                 reader,
                 bucket_count.clone(),
-                max_bucket_count,
             )?,
             bucket_agg: bucket.clone(),
             inverted_index,
-            bucket_count: BucketCount {
-                bucket_count,
-                max_bucket_count,
-            },
+            bucket_count,
         })
     }
 }
@@ -149,8 +142,7 @@ impl MetricAggregationWithAccessor {
 pub(crate) fn get_aggs_with_accessor_and_validate(
     aggs: &Aggregations,
     reader: &SegmentReader,
-    bucket_count: Rc<AtomicU32>,
-    max_bucket_count: u32,
+    bucket_count: BucketCount,
 ) -> crate::Result<AggregationsWithAccessor> {
     let mut metrics = vec![];
     let mut buckets = vec![];
@@ -162,8 +154,7 @@ pub(crate) fn get_aggs_with_accessor_and_validate(
                     &bucket.bucket_agg,
                     &bucket.sub_aggregation,
                     reader,
-                    Rc::clone(&bucket_count),
-                    max_bucket_count,
+                    bucket_count.clone(),
                 )?,
             )),
             Aggregation::Metric(metric) => metrics.push((
@@ -534,6 +534,9 @@ mod tests {
         exec_request, exec_request_with_query, get_test_index_from_terms,
         get_test_index_from_values_and_terms,
     };
+    use crate::aggregation::AggregationCollector;
+    use crate::collector::MemoryBudget;
+    use crate::query::AllQuery;
 
     #[test]
     fn terms_aggregation_test_single_segment() -> crate::Result<()> {
@@ -1211,6 +1214,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn terms_aggregation_memory_budget() -> crate::Result<()> {
+        let terms: Vec<String> = (0..1_000).map(|el| el.to_string()).collect();
+        let terms_per_segment = vec![terms.iter().map(|el| el.as_str()).collect()];
+
+        let index = get_test_index_from_terms(true, &terms_per_segment)?;
+
+        let agg_req: Aggregations = vec![(
+            "my_texts".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Terms(TermsAggregation {
+                    field: "string_id".to_string(),
+                    ..Default::default()
+                }),
+                sub_aggregation: Default::default(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+
+        let searcher = index.reader()?.searcher();
+        let collector = AggregationCollector::from_aggs(agg_req.clone(), None)
+            .with_memory_budget(MemoryBudget::with_limit(10_000));
+        let err = searcher.search(&AllQuery, &collector).unwrap_err();
+        assert!(matches!(err, TantivyError::MemoryLimitExceeded(_)));
+
+        let memory_budget = MemoryBudget::with_limit(1_000_000);
+        let collector = AggregationCollector::from_aggs(agg_req, None)
+            .with_memory_budget(memory_budget.clone());
+        assert!(searcher.search(&AllQuery, &collector).is_ok());
+        assert!(memory_budget.used_in_bytes() > 10_000);
+
+        Ok(())
+    }
+
     #[test]
     fn test_json_format() -> crate::Result<()> {
         let agg_req: Aggregations = vec![(
@@ -1332,6 +1370,7 @@ mod bench {
         let bucket_count: BucketCount = BucketCount {
             bucket_count: Default::default(),
             max_bucket_count: 1_000_001u32,
+            memory_budget: None,
         };
         b.iter(|| {
             for &val in &vals {
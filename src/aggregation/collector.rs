@@ -1,12 +1,10 @@
-use std::rc::Rc;
-
 use super::agg_req::Aggregations;
 use super::agg_req_with_accessor::AggregationsWithAccessor;
 use super::agg_result::AggregationResults;
 use super::intermediate_agg_result::IntermediateAggregationResults;
-use super::segment_agg_result::SegmentAggregationResultsCollector;
+use super::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
 use crate::aggregation::agg_req_with_accessor::get_aggs_with_accessor_and_validate;
-use crate::collector::{Collector, SegmentCollector};
+use crate::collector::{Collector, MemoryBudget, SegmentCollector};
 use crate::{SegmentReader, TantivyError};
 
 /// The default max bucket count, before the aggregation fails.
@@ -18,6 +16,7 @@ pub const MAX_BUCKET_COUNT: u32 = 65000;
 pub struct AggregationCollector {
     agg: Aggregations,
     max_bucket_count: u32,
+    memory_budget: Option<MemoryBudget>,
 }
 
 impl AggregationCollector {
@@ -29,8 +28,17 @@ impl AggregationCollector {
         Self {
             agg,
             max_bucket_count: max_bucket_count.unwrap_or(MAX_BUCKET_COUNT),
+            memory_budget: None,
         }
     }
+
+    /// Charges `memory_budget` with an estimate of the memory used by the buckets, on top of
+    /// the limit on their number. See [`MemoryBudget`].
+    #[must_use]
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
 }
 
 /// Collector for distributed aggregations.
@@ -44,6 +52,7 @@ impl AggregationCollector {
 pub struct DistributedAggregationCollector {
     agg: Aggregations,
     max_bucket_count: u32,
+    memory_budget: Option<MemoryBudget>,
 }
 
 impl DistributedAggregationCollector {
@@ -54,8 +63,17 @@ impl DistributedAggregationCollector {
         Self {
             agg,
             max_bucket_count: max_bucket_count.unwrap_or(MAX_BUCKET_COUNT),
+            memory_budget: None,
         }
     }
+
+    /// Charges `memory_budget` with an estimate of the memory used by the buckets, on top of
+    /// the limit on their number. See [`MemoryBudget`].
+    #[must_use]
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
 }
 
 impl Collector for DistributedAggregationCollector {
@@ -68,10 +86,10 @@ impl Collector for DistributedAggregationCollector {
         _segment_local_id: crate::SegmentOrdinal,
         reader: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
-        AggregationSegmentCollector::from_agg_req_and_reader(
+        AggregationSegmentCollector::from_agg_req_reader_and_bucket_count(
             &self.agg,
             reader,
-            self.max_bucket_count,
+            BucketCount::new(self.max_bucket_count, self.memory_budget.clone()),
         )
     }
 
@@ -97,10 +115,10 @@ impl Collector for AggregationCollector {
         _segment_local_id: crate::SegmentOrdinal,
         reader: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
-        AggregationSegmentCollector::from_agg_req_and_reader(
+        AggregationSegmentCollector::from_agg_req_reader_and_bucket_count(
             &self.agg,
             reader,
-            self.max_bucket_count,
+            BucketCount::new(self.max_bucket_count, self.memory_budget.clone()),
         )
     }
 
@@ -146,8 +164,19 @@ impl AggregationSegmentCollector {
         reader: &SegmentReader,
         max_bucket_count: u32,
     ) -> crate::Result<Self> {
-        let aggs_with_accessor =
-            get_aggs_with_accessor_and_validate(agg, reader, Rc::default(), max_bucket_count)?;
+        Self::from_agg_req_reader_and_bucket_count(
+            agg,
+            reader,
+            BucketCount::new(max_bucket_count, None),
+        )
+    }
+
+    pub(crate) fn from_agg_req_reader_and_bucket_count(
+        agg: &Aggregations,
+        reader: &SegmentReader,
+        bucket_count: BucketCount,
+    ) -> crate::Result<Self> {
+        let aggs_with_accessor = get_aggs_with_accessor_and_validate(agg, reader, bucket_count)?;
         let result =
             SegmentAggregationResultsCollector::from_req_and_validate(&aggs_with_accessor)?;
         Ok(AggregationSegmentCollector {
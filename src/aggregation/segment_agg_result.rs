@@ -19,6 +19,7 @@ use super::metric::{
 };
 use super::VecWithNames;
 use crate::aggregation::agg_req::BucketAggregationType;
+use crate::collector::MemoryBudget;
 use crate::{DocId, TantivyError};
 
 pub(crate) const DOC_BLOCK_SIZE: usize = 64;
@@ -278,11 +279,16 @@ impl SegmentBucketResultCollector {
     }
 }
 
+// Estimate of the memory used by a bucket, excluding its sub-aggregations.
+const BUCKET_NUM_BYTES: usize = 64;
+
 #[derive(Clone)]
 pub(crate) struct BucketCount {
     /// The counter which is shared between the aggregations for one request.
     pub(crate) bucket_count: Rc<AtomicU32>,
     pub(crate) max_bucket_count: u32,
+    /// The memory budget charged with the buckets, shared between the segments.
+    pub(crate) memory_budget: Option<MemoryBudget>,
 }
 
 impl Default for BucketCount {
@@ -290,22 +296,37 @@ impl Default for BucketCount {
         Self {
             bucket_count: Default::default(),
             max_bucket_count: MAX_BUCKET_COUNT,
+            memory_budget: None,
         }
     }
 }
 
 impl BucketCount {
+    pub(crate) fn new(max_bucket_count: u32, memory_budget: Option<MemoryBudget>) -> Self {
+        Self {
+            bucket_count: Default::default(),
+            max_bucket_count,
+            memory_budget,
+        }
+    }
+
     pub(crate) fn validate_bucket_count(&self) -> crate::Result<()> {
         if self.get_count() > self.max_bucket_count {
             return Err(TantivyError::InvalidArgument(
                 "Aborting aggregation because too many buckets were created".to_string(),
             ));
         }
+        if let Some(memory_budget) = &self.memory_budget {
+            memory_budget.check()?;
+        }
         Ok(())
     }
     pub(crate) fn add_count(&self, count: u32) {
         self.bucket_count
             .fetch_add(count as u32, std::sync::atomic::Ordering::Relaxed);
+        if let Some(memory_budget) = &self.memory_budget {
+            memory_budget.record(count as usize * BUCKET_NUM_BYTES);
+        }
     }
     pub(crate) fn get_count(&self) -> u32 {
         self.bucket_count.load(std::sync::atomic::Ordering::Relaxed)
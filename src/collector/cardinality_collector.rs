@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{
+    type_and_cardinality, DynamicFastFieldReader, FastFieldReader, MultiValuedFastFieldReader,
+};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// HyperLogLog sketch, estimating the number of distinct values inserted in it.
+///
+/// The sketch uses `2^precision` one-byte registers, and its relative error is about
+/// `1.04 / sqrt(2^precision)`: 1.6% for the default precision of 12.
+///
+/// Sketches of the same precision can be merged, e.g. to combine the sketches computed by
+/// several shards, and serialized. Deserializing a sketch whose number of registers does
+/// not match its precision fails.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "HyperLogLogDeser")]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct HyperLogLogDeser {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl TryFrom<HyperLogLogDeser> for HyperLogLog {
+    type Error = String;
+
+    fn try_from(deser: HyperLogLogDeser) -> Result<Self, Self::Error> {
+        if !(4..=16).contains(&deser.precision) {
+            return Err(format!(
+                "The precision {} of the HyperLogLog sketch is not within [4, 16].",
+                deser.precision
+            ));
+        }
+        if deser.registers.len() != 1 << deser.precision {
+            return Err(format!(
+                "A HyperLogLog sketch of precision {} should have {} registers, got {}.",
+                deser.precision,
+                1 << deser.precision,
+                deser.registers.len()
+            ));
+        }
+        // Ranks are bounded by the number of hash bits left after the register id, plus one.
+        let max_rank = 65 - deser.precision;
+        if deser.registers.iter().any(|&register| register > max_rank) {
+            return Err(format!(
+                "The registers of a HyperLogLog sketch of precision {} should not exceed {}.",
+                deser.precision, max_rank
+            ));
+        }
+        Ok(HyperLogLog {
+            precision: deser.precision,
+            registers: deser.registers,
+        })
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog::with_precision(HyperLogLog::DEFAULT_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    /// Precision of the sketches created by `HyperLogLog::default`.
+    pub const DEFAULT_PRECISION: u8 = 12;
+
+    /// Creates an empty sketch with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the precision is not within `[4, 16]`.
+    pub fn with_precision(precision: u8) -> HyperLogLog {
+        assert!(
+            (4..=16).contains(&precision),
+            "The precision should be within [4, 16]."
+        );
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Inserts a value in the sketch.
+    pub fn insert(&mut self, value: u64) {
+        let hash = mix(value);
+        let register_id = (hash >> (64 - self.precision)) as usize;
+        // The remaining bits, followed by a 1 bounding the rank.
+        let remaining_bits = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = remaining_bits.leading_zeros() as u8 + 1;
+        let register = &mut self.registers[register_id];
+        if rank > *register {
+            *register = rank;
+        }
+    }
+
+    /// Merges the values of another sketch into this one.
+    ///
+    /// Returns an error if the sketches have different precisions.
+    pub fn merge(&mut self, other: &HyperLogLog) -> crate::Result<()> {
+        if self.precision != other.precision {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Cannot merge HyperLogLog sketches of precisions {} and {}.",
+                self.precision, other.precision
+            )));
+        }
+        for (register, &other_register) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other_register);
+        }
+        Ok(())
+    }
+
+    /// Returns the estimated number of distinct values inserted in the sketch.
+    pub fn estimate(&self) -> u64 {
+        let num_registers = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / num_registers),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&register| 1.0 / (1u64 << register) as f64)
+            .sum();
+        let raw_estimate = alpha * num_registers * num_registers / sum;
+        let num_zero_registers = self
+            .registers
+            .iter()
+            .filter(|&&register| register == 0)
+            .count();
+        // Linear counting is more accurate for small cardinalities.
+        if raw_estimate <= 2.5 * num_registers && num_zero_registers > 0 {
+            (num_registers * (num_registers / num_zero_registers as f64).ln()).round() as u64
+        } else {
+            raw_estimate.round() as u64
+        }
+    }
+}
+
+// Finalizer of splitmix64, spreading the bits of the values over the whole hash.
+fn mix(value: u64) -> u64 {
+    let mut hash = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^ (hash >> 31)
+}
+
+/// Estimates the number of distinct values of a numeric, boolean or date fast field among
+/// the collected documents, with a [`HyperLogLog`] sketch.
+///
+/// The fruit is the sketch itself, so that the sketches of several indexes can be merged
+/// before the estimate is computed.
+///
+/// ```rust
+/// use tantivy::collector::CardinalityCollector;
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let user_id = schema_builder.add_u64_field("user_id", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for user_id_value in [1u64, 2, 1, 3, 2] {
+///     index_writer.add_document(doc!(user_id => user_id_value))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let sketch = searcher.search(&AllQuery, &CardinalityCollector::for_field(user_id))?;
+/// assert_eq!(sketch.estimate(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CardinalityCollector {
+    field: Field,
+    precision: u8,
+}
+
+impl CardinalityCollector {
+    /// Creates a collector estimating the number of distinct values of `field`
+    /// with the default precision.
+    pub fn for_field(field: Field) -> CardinalityCollector {
+        CardinalityCollector {
+            field,
+            precision: HyperLogLog::DEFAULT_PRECISION,
+        }
+    }
+
+    /// Sets the precision of the sketch. See [`HyperLogLog::with_precision`].
+    #[must_use]
+    pub fn with_precision(mut self, precision: u8) -> CardinalityCollector {
+        // Checks the precision right away.
+        HyperLogLog::with_precision(precision);
+        self.precision = precision;
+        self
+    }
+}
+
+enum ValuesReader {
+    Single(DynamicFastFieldReader<u64>),
+    Multi(MultiValuedFastFieldReader<u64>),
+}
+
+impl Collector for CardinalityCollector {
+    type Fruit = HyperLogLog;
+    type Child = SegmentCardinalityCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<SegmentCardinalityCollector> {
+        let field_entry = segment.schema().get_field_entry(self.field);
+        // The fast field values of text and facet fields are term ordinals,
+        // which are specific to each segment.
+        let cardinality_opt = match field_entry.field_type() {
+            FieldType::Str(_) | FieldType::Facet(_) => None,
+            field_type => type_and_cardinality(field_type).map(|(_, cardinality)| cardinality),
+        };
+        let values_reader = match cardinality_opt {
+            Some(Cardinality::SingleValue) => {
+                ValuesReader::Single(segment.fast_fields().u64_lenient(self.field)?)
+            }
+            Some(Cardinality::MultiValues) => {
+                ValuesReader::Multi(segment.fast_fields().u64s_lenient(self.field)?)
+            }
+            None => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a numeric, boolean or date fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(SegmentCardinalityCollector {
+            sketch: HyperLogLog::with_precision(self.precision),
+            values_reader,
+            vals: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_sketches: Vec<HyperLogLog>) -> crate::Result<HyperLogLog> {
+        let mut sketch = HyperLogLog::with_precision(self.precision);
+        for segment_sketch in &segment_sketches {
+            sketch.merge(segment_sketch)?;
+        }
+        Ok(sketch)
+    }
+}
+
+/// Segment collector of a [`CardinalityCollector`].
+pub struct SegmentCardinalityCollector {
+    sketch: HyperLogLog,
+    values_reader: ValuesReader,
+    vals: Vec<u64>,
+}
+
+impl SegmentCollector for SegmentCardinalityCollector {
+    type Fruit = HyperLogLog;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        match &self.values_reader {
+            ValuesReader::Single(ff_reader) => self.sketch.insert(ff_reader.get(doc)),
+            ValuesReader::Multi(ff_reader) => {
+                ff_reader.get_vals(doc, &mut self.vals);
+                for &val in &self.vals {
+                    self.sketch.insert(val);
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> HyperLogLog {
+        self.sketch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CardinalityCollector, HyperLogLog};
+    use crate::query::AllQuery;
+    use crate::schema::{Cardinality, NumericOptions, Schema, FAST, STRING};
+    use crate::Index;
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        let mut sketch = HyperLogLog::default();
+        assert_eq!(sketch.estimate(), 0);
+        for value in 0..100_000u64 {
+            sketch.insert(value);
+            sketch.insert(value);
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 100_000.0).abs() / 100_000.0 < 0.05);
+
+        let mut sketch_a = HyperLogLog::default();
+        let mut sketch_b = HyperLogLog::default();
+        for value in 0..1_000u64 {
+            sketch_a.insert(value);
+            sketch_b.insert(value + 500);
+        }
+        sketch_a.merge(&sketch_b).unwrap();
+        let estimate = sketch_a.estimate() as f64;
+        assert!((estimate - 1_500.0).abs() / 1_500.0 < 0.05);
+        assert!(sketch_a.merge(&HyperLogLog::with_precision(10)).is_err());
+
+        let sketch_json = serde_json::to_string(&sketch_a).unwrap();
+        let deserialized_sketch: HyperLogLog = serde_json::from_str(&sketch_json).unwrap();
+        assert_eq!(deserialized_sketch, sketch_a);
+    }
+
+    #[test]
+    fn test_hyperloglog_deserialize_invalid() {
+        let truncated_json = r#"{"precision":12,"registers":[0,0,0]}"#;
+        let err = serde_json::from_str::<HyperLogLog>(truncated_json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("should have 4096 registers, got 3"));
+
+        let invalid_precision_json = r#"{"precision":2,"registers":[0,0,0,0]}"#;
+        assert!(serde_json::from_str::<HyperLogLog>(invalid_precision_json).is_err());
+
+        let mut registers = vec![0u8; 16];
+        registers[3] = 62;
+        let invalid_rank_json = serde_json::json!({"precision": 4, "registers": registers});
+        assert!(serde_json::from_value::<HyperLogLog>(invalid_rank_json).is_err());
+    }
+
+    #[test]
+    fn test_cardinality_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let user_id = schema_builder.add_i64_field("user_id", FAST);
+        let tags = schema_builder.add_u64_field(
+            "tags",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let text = schema_builder.add_text_field("text", STRING | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for doc_id in 0..100i64 {
+            index_writer.add_document(doc!(
+                user_id => doc_id % 30,
+                tags => (doc_id % 7) as u64,
+                tags => 7u64,
+            ))?;
+            if doc_id % 40 == 0 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert!(searcher.segment_readers().len() > 1);
+        let user_id_sketch =
+            searcher.search(&AllQuery, &CardinalityCollector::for_field(user_id))?;
+        assert_eq!(user_id_sketch.estimate(), 30);
+        let tags_sketch = searcher.search(
+            &AllQuery,
+            &CardinalityCollector::for_field(tags).with_precision(14),
+        )?;
+        assert_eq!(tags_sketch.precision(), 14);
+        assert_eq!(tags_sketch.estimate(), 8);
+        assert!(searcher
+            .search(&AllQuery, &CardinalityCollector::for_field(text))
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use super::Collector;
 use crate::collector::SegmentCollector;
-use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+use crate::query::Weight;
+use crate::{DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED};
 
 /// `CountCollector` collector only counts how many
 /// documents match the query.
@@ -33,6 +34,7 @@ use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
 ///
 /// assert_eq!(count, 2);
 /// ```
+#[derive(Debug)]
 pub struct Count;
 
 impl Collector for Count {
@@ -69,6 +71,165 @@ impl SegmentCollector for SegmentCountCollector {
         self.count += 1;
     }
 
+    fn accepts_blocks(&self) -> bool {
+        true
+    }
+
+    fn collect_block(&mut self, docs: &[DocId]) {
+        self.count += docs.len();
+    }
+
+    fn harvest(self) -> usize {
+        self.count
+    }
+}
+
+/// Number of documents matching a query, as counted by [`CountUpTo`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HitCount {
+    /// Exactly this number of documents match.
+    Exact(usize),
+    /// At least this number of documents match. Counting stopped there.
+    AtLeast(usize),
+}
+
+impl HitCount {
+    pub(crate) fn with_threshold(count: usize, threshold: usize) -> HitCount {
+        if count > threshold {
+            HitCount::AtLeast(threshold)
+        } else {
+            HitCount::Exact(count)
+        }
+    }
+
+    /// Returns the number of documents, which is a lower bound for `HitCount::AtLeast`.
+    pub fn count(&self) -> usize {
+        match *self {
+            HitCount::Exact(count) | HitCount::AtLeast(count) => count,
+        }
+    }
+
+    /// Returns true if the count is exact.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, HitCount::Exact(_))
+    }
+}
+
+/// `CountUpTo` counts the documents matching a query, stopping once more than `threshold`
+/// documents matched.
+///
+/// This is much cheaper than [`Count`] for queries matching a lot of documents, when only
+/// displaying e.g. "10000+ results". A segment stops being iterated as soon as it has more
+/// than `threshold` matches, so that at most `threshold + 1` documents are visited per segment.
+///
+/// ```rust
+/// use tantivy::collector::{CountUpTo, HitCount};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.add_document(doc!(title => "A Dairy Cow"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// assert_eq!(searcher.search(&AllQuery, &CountUpTo(2))?, HitCount::AtLeast(2));
+/// assert_eq!(searcher.search(&AllQuery, &CountUpTo(3))?, HitCount::Exact(3));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CountUpTo(pub usize);
+
+impl CountUpTo {
+    // A segment counting more than this can stop.
+    fn max_segment_count(&self) -> usize {
+        self.0.saturating_add(1)
+    }
+}
+
+impl Collector for CountUpTo {
+    type Fruit = HitCount;
+
+    type Child = SegmentCountUpToCollector;
+
+    fn for_segment(
+        &self,
+        _: SegmentOrdinal,
+        _: &SegmentReader,
+    ) -> crate::Result<SegmentCountUpToCollector> {
+        Ok(SegmentCountUpToCollector {
+            count: 0,
+            max_count: self.max_segment_count(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_counts: Vec<usize>) -> crate::Result<HitCount> {
+        let count = segment_counts
+            .into_iter()
+            .fold(0usize, |count, segment_count| {
+                count.saturating_add(segment_count)
+            });
+        Ok(HitCount::with_threshold(count, self.0))
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        _: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<usize> {
+        let max_count = self.max_segment_count();
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let alive_bitset_opt = reader.alive_bitset();
+        let mut count = 0;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED && count < max_count {
+            if alive_bitset_opt
+                .map(|alive_bitset| alive_bitset.is_alive(doc))
+                .unwrap_or(true)
+            {
+                count += 1;
+            }
+            doc = scorer.advance();
+        }
+        Ok(count)
+    }
+}
+
+/// Segment collector associated to [`CountUpTo`].
+pub struct SegmentCountUpToCollector {
+    count: usize,
+    max_count: usize,
+}
+
+impl SegmentCollector for SegmentCountUpToCollector {
+    type Fruit = usize;
+
+    fn collect(&mut self, _: DocId, _: Score) {
+        if self.count < self.max_count {
+            self.count += 1;
+        }
+    }
+
+    fn accepts_blocks(&self) -> bool {
+        true
+    }
+
+    fn collect_block(&mut self, docs: &[DocId]) {
+        self.count = self.max_count.min(self.count + docs.len());
+    }
+
     fn harvest(self) -> usize {
         self.count
     }
@@ -76,8 +237,11 @@ impl SegmentCollector for SegmentCountCollector {
 
 #[cfg(test)]
 mod tests {
-    use super::{Count, SegmentCountCollector};
+    use super::{Count, CountUpTo, HitCount, SegmentCountCollector};
     use crate::collector::{Collector, SegmentCollector};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, INDEXED};
+    use crate::{Index, Term};
 
     #[test]
     fn test_count_collect_does_not_requires_scoring() {
@@ -107,4 +271,46 @@ mod tests {
             assert_eq!(count_collector.harvest(), 2);
         }
     }
+    #[test]
+    fn test_count_up_to() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100u64 {
+            index_writer.add_document(doc!(num => i % 2))?;
+            if i == 49 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.delete_term(Term::from_field_u64(num, 0));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let odd_query = TermQuery::new(Term::from_field_u64(num, 1), IndexRecordOption::Basic);
+        assert_eq!(
+            searcher.search(&odd_query, &CountUpTo(10))?,
+            HitCount::AtLeast(10)
+        );
+        assert_eq!(
+            searcher.search(&odd_query, &CountUpTo(49))?,
+            HitCount::AtLeast(49)
+        );
+        assert_eq!(
+            searcher.search(&odd_query, &CountUpTo(50))?,
+            HitCount::Exact(50)
+        );
+        // Deleted documents are not counted.
+        assert_eq!(
+            searcher.search(&AllQuery, &CountUpTo(1000))?,
+            HitCount::Exact(50)
+        );
+        // Collected through `for_segment`, as part of a tuple.
+        let (hit_count, count) = searcher.search(&odd_query, &(CountUpTo(20), Count))?;
+        assert_eq!(hit_count, HitCount::AtLeast(20));
+        assert!(!hit_count.is_exact());
+        assert_eq!(hit_count.count(), 20);
+        assert_eq!(count, 50);
+        Ok(())
+    }
 }
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{type_and_cardinality, DynamicFastFieldReader, FastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Returns the top scored documents, keeping at most one document for each value of a fast
+/// field, e.g. a single article of each group of syndicated copies sharing a `content_hash`.
+///
+/// The document kept for a value is its best scored document. In case of a tie, the first
+/// document is kept.
+///
+/// The field has to be a single valued numeric, boolean or date fast field. If the index is
+/// sorted by this field, see [`IndexSettings::sort_by_field`](crate::IndexSettings), the
+/// documents sharing a value are contiguous in each segment, and the collector only keeps
+/// the best document of the current value in memory. Otherwise, it keeps the best document
+/// of each value of the segment.
+///
+/// ```rust
+/// use tantivy::collector::DistinctCollector;
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, FAST, TEXT};
+/// use tantivy::{doc, DocAddress, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let content_hash = schema_builder.add_u64_field("content_hash", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "diary diary", content_hash => 1u64))?;
+/// index_writer.add_document(doc!(title => "diary", content_hash => 1u64))?;
+/// index_writer.add_document(doc!(title => "diary", content_hash => 2u64))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+/// let top_docs = searcher.search(&query, &DistinctCollector::new(content_hash, 10))?;
+/// assert_eq!(top_docs.len(), 2);
+/// assert_eq!(top_docs[0].1, DocAddress::new(0, 0));
+/// assert_eq!(top_docs[1].1, DocAddress::new(0, 2));
+/// # Ok(())
+/// # }
+/// ```
+pub struct DistinctCollector {
+    field: Field,
+    top_collector: TopCollector<Score>,
+}
+
+impl DistinctCollector {
+    /// Creates a collector returning the `limit` top scored documents, keeping at most one
+    /// document for each value of `field`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is 0.
+    pub fn new(field: Field, limit: usize) -> DistinctCollector {
+        DistinctCollector {
+            field,
+            top_collector: TopCollector::with_limit(limit),
+        }
+    }
+}
+
+impl Collector for DistinctCollector {
+    type Fruit = Vec<(Score, DocAddress)>;
+    type Child = DistinctSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<DistinctSegmentCollector> {
+        let field_entry = segment.schema().get_field_entry(self.field);
+        // The fast field values of text and facet fields are term ordinals,
+        // which are specific to each segment.
+        let cardinality_opt = match field_entry.field_type() {
+            FieldType::Str(_) | FieldType::Facet(_) => None,
+            field_type => type_and_cardinality(field_type).map(|(_, cardinality)| cardinality),
+        };
+        if cardinality_opt != Some(Cardinality::SingleValue) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a single valued numeric, boolean or date fast field.",
+                field_entry.name()
+            )));
+        }
+        let is_sorted = segment
+            .sort_by_field()
+            .map(|sort_by_field| sort_by_field.field == field_entry.name())
+            .unwrap_or(false);
+        Ok(DistinctSegmentCollector {
+            ff_reader: segment.fast_fields().u64_lenient(self.field)?,
+            top_segment_collector: self.top_collector.for_segment(segment_local_id, segment)?,
+            best_docs: if is_sorted {
+                BestDocs::Sorted(None)
+            } else {
+                BestDocs::Unsorted(HashMap::new())
+            },
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<(Score, DocAddress, u64)>>,
+    ) -> crate::Result<Vec<(Score, DocAddress)>> {
+        // A value can be shared by documents of different segments.
+        let mut best_docs: HashMap<u64, (Score, DocAddress)> = HashMap::new();
+        for (score, doc_address, value) in segment_fruits.into_iter().flatten() {
+            let best_doc = best_docs.entry(value).or_insert((score, doc_address));
+            if score > best_doc.0 || (score == best_doc.0 && doc_address < best_doc.1) {
+                *best_doc = (score, doc_address);
+            }
+        }
+        // Among documents of equal scores, the top collector keeps the first ones.
+        let mut best_docs: Vec<(Score, DocAddress)> = best_docs.into_values().collect();
+        best_docs.sort_by_key(|(_, doc_address)| *doc_address);
+        self.top_collector.merge_fruits(vec![best_docs])
+    }
+}
+
+// Best scored document of each value seen so far.
+enum BestDocs {
+    // The documents are sorted by value: only the best document of the current value is kept.
+    Sorted(Option<(u64, Score, DocId)>),
+    Unsorted(HashMap<u64, (Score, DocId)>),
+}
+
+/// Segment collector of a [`DistinctCollector`].
+pub struct DistinctSegmentCollector {
+    ff_reader: DynamicFastFieldReader<u64>,
+    top_segment_collector: TopSegmentCollector<Score>,
+    best_docs: BestDocs,
+}
+
+impl SegmentCollector for DistinctSegmentCollector {
+    type Fruit = Vec<(Score, DocAddress, u64)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let value = self.ff_reader.get(doc);
+        match &mut self.best_docs {
+            BestDocs::Sorted(current_opt) => match current_opt {
+                Some((current_value, best_score, best_doc)) if *current_value == value => {
+                    if score > *best_score {
+                        *best_score = score;
+                        *best_doc = doc;
+                    }
+                }
+                _ => {
+                    if let Some((_, best_score, best_doc)) = current_opt.take() {
+                        self.top_segment_collector.collect(best_doc, best_score);
+                    }
+                    *current_opt = Some((value, score, doc));
+                }
+            },
+            BestDocs::Unsorted(best_docs) => {
+                let best_doc = best_docs.entry(value).or_insert((score, doc));
+                if score > best_doc.0 {
+                    *best_doc = (score, doc);
+                }
+            }
+        }
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        match self.best_docs {
+            BestDocs::Sorted(current_opt) => {
+                if let Some((_, best_score, best_doc)) = current_opt {
+                    self.top_segment_collector.collect(best_doc, best_score);
+                }
+            }
+            BestDocs::Unsorted(best_docs) => {
+                let mut best_docs: Vec<(Score, DocId)> = best_docs.into_values().collect();
+                best_docs.sort_by_key(|(_, doc)| *doc);
+                for (best_score, best_doc) in best_docs {
+                    self.top_segment_collector.collect(best_doc, best_score);
+                }
+            }
+        }
+        let ff_reader = self.ff_reader;
+        self.top_segment_collector
+            .harvest()
+            .into_iter()
+            .map(|(score, doc_address)| (score, doc_address, ff_reader.get(doc_address.doc_id)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DistinctCollector;
+    use crate::collector::TopDocs;
+    use crate::fastfield::FastFieldReader;
+    use crate::query::{AllQuery, QueryParser};
+    use crate::schema::{Schema, FAST, STORED, STRING, TEXT};
+    use crate::{Index, IndexSettings, IndexSortByField, Order};
+
+    fn distinct_top_docs(index_settings: IndexSettings) -> crate::Result<Vec<(u64, String)>> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let group = schema_builder.add_u64_field("group", FAST);
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(index_settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a", group => 2u64))?;
+        index_writer.add_document(doc!(title => "a a a", group => 1u64))?;
+        index_writer.add_document(doc!(title => "a a", group => 3u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "a a a a", group => 2u64))?;
+        index_writer.add_document(doc!(title => "a a", group => 1u64))?;
+        index_writer.add_document(doc!(title => "a", group => 3u64))?;
+        index_writer.add_document(doc!(title => "b", group => 4u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("a")?;
+        let top_docs = searcher.search(&query, &DistinctCollector::new(group, 10))?;
+        top_docs
+            .into_iter()
+            .map(|(_, doc_address)| {
+                let doc = searcher.doc(doc_address)?;
+                let group_value = searcher
+                    .segment_reader(doc_address.segment_ord)
+                    .fast_fields()
+                    .u64(group)?
+                    .get(doc_address.doc_id);
+                Ok((
+                    group_value,
+                    doc.get_first(title).unwrap().as_text().unwrap().to_string(),
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_distinct_collector() -> crate::Result<()> {
+        let expected = vec![
+            (2, "a a a a".to_string()),
+            (1, "a a a".to_string()),
+            (3, "a a".to_string()),
+        ];
+        assert_eq!(distinct_top_docs(IndexSettings::default())?, expected);
+        let sorted_settings = IndexSettings {
+            sort_by_field: Some(IndexSortByField {
+                field: "group".to_string(),
+                order: Order::Desc,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(distinct_top_docs(sorted_settings)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_collector_limit_and_errors() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let group = schema_builder.add_u64_field("group", FAST);
+        let name = schema_builder.add_text_field("name", STRING | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for value in 0u64..20 {
+            index_writer.add_document(doc!(group => value % 5, name => "a"))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs = searcher.search(&AllQuery, &DistinctCollector::new(group, 3))?;
+        assert_eq!(
+            top_docs,
+            searcher.search(&AllQuery, &TopDocs::with_limit(3))?
+        );
+        assert_eq!(
+            searcher
+                .search(&AllQuery, &DistinctCollector::new(group, 10))?
+                .len(),
+            5
+        );
+        assert!(searcher
+            .search(&AllQuery, &DistinctCollector::new(name, 10))
+            .is_err());
+        Ok(())
+    }
+}
@@ -2,9 +2,9 @@ use std::cmp::Ordering;
 use std::collections::{btree_map, BTreeMap, BTreeSet, BinaryHeap};
 use std::iter::Peekable;
 use std::ops::Bound;
-use std::{u64, usize};
+use std::{mem, u64, usize};
 
-use crate::collector::{Collector, SegmentCollector};
+use crate::collector::{allocate, Collector, MemoryBudget, SegmentCollector};
 use crate::fastfield::FacetReader;
 use crate::schema::{Facet, Field};
 use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
@@ -169,6 +169,7 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 pub struct FacetCollector {
     field: Field,
     facets: BTreeSet<Facet>,
+    memory_budget: Option<MemoryBudget>,
 }
 
 pub struct FacetSegmentCollector {
@@ -220,9 +221,20 @@ impl FacetCollector {
         FacetCollector {
             field,
             facets: BTreeSet::default(),
+            memory_budget: None,
         }
     }
 
+    /// Charges `memory_budget` with the memory needed to count the facets of each segment,
+    /// which grows with the number of distinct facets of the segment.
+    ///
+    /// See [`MemoryBudget`].
+    #[must_use]
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> FacetCollector {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
     /// Adds a facet that we want to record counts
     ///
     /// Adding facet `Facet::from("/country")` for instance,
@@ -260,6 +272,10 @@ impl Collector for FacetCollector {
         reader: &SegmentReader,
     ) -> crate::Result<FacetSegmentCollector> {
         let facet_reader = reader.facet_reader(self.field)?;
+        allocate(
+            &self.memory_budget,
+            facet_reader.facet_dict().num_terms() * mem::size_of::<usize>(),
+        )?;
 
         let mut collapse_mapping = Vec::new();
         let mut counts = Vec::new();
@@ -304,6 +320,10 @@ impl Collector for FacetCollector {
             }
         }
 
+        allocate(
+            &self.memory_budget,
+            collapse_facet_ords.len() * 2 * mem::size_of::<u64>(),
+        )?;
         counts.resize(collapse_facet_ords.len(), 0);
 
         Ok(FacetSegmentCollector {
@@ -452,11 +472,11 @@ mod tests {
     use rand::{thread_rng, Rng};
 
     use super::{FacetCollector, FacetCounts};
-    use crate::collector::Count;
+    use crate::collector::{Count, MemoryBudget};
     use crate::core::Index;
     use crate::query::{AllQuery, QueryParser, TermQuery};
     use crate::schema::{Document, Facet, FacetOptions, Field, IndexRecordOption, Schema};
-    use crate::Term;
+    use crate::{TantivyError, Term};
 
     #[test]
     fn test_facet_collector_drilldown() -> crate::Result<()> {
@@ -545,6 +565,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_facet_collector_memory_budget() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let facet_field = schema_builder.add_facet_field("facets", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100 {
+            index_writer.add_document(doc!(
+                facet_field => Facet::from_text(&format!("/subjects/{}", i)).unwrap(),
+            ))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let mut facet_collector = FacetCollector::for_field(facet_field)
+            .with_memory_budget(MemoryBudget::with_limit(100));
+        facet_collector.add_facet("/subjects");
+        let result = searcher.search(&AllQuery, &facet_collector);
+        assert!(matches!(result, Err(TantivyError::MemoryLimitExceeded(_))));
+
+        let memory_budget = MemoryBudget::with_limit(100_000);
+        let mut facet_collector =
+            FacetCollector::for_field(facet_field).with_memory_budget(memory_budget.clone());
+        facet_collector.add_facet("/subjects");
+        let counts = searcher.search(&AllQuery, &facet_collector)?;
+        assert_eq!(counts.get("/subjects").count(), 100);
+        assert!(memory_budget.used_in_bytes() > 100);
+        Ok(())
+    }
+
     #[test]
     fn test_doc_search_by_facet() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
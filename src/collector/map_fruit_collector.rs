@@ -0,0 +1,120 @@
+use crate::collector::{Collector, SegmentCollector};
+use crate::{SegmentOrdinal, SegmentReader};
+
+/// `MapFruit` wraps a collector and transforms its fruit
+/// once the segment fruits have been merged.
+///
+/// It makes it possible to shape the result of a collector
+/// without implementing a custom `Collector`.
+///
+/// ```rust
+/// use tantivy::collector::{Count, MapFruit};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+///
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let has_hits_collector = MapFruit::new(Count, |count: usize| count > 0);
+/// assert!(searcher.search(&AllQuery, &has_hits_collector)?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MapFruit<TCollector, TMap> {
+    collector: TCollector,
+    map: TMap,
+}
+
+impl<TCollector, TMap, TFruit> MapFruit<TCollector, TMap>
+where
+    TCollector: Collector,
+    TMap: Fn(TCollector::Fruit) -> TFruit + Send + Sync,
+    TFruit: crate::collector::Fruit,
+{
+    /// Create a new `MapFruit` applying `map` on the fruit of `collector`.
+    pub fn new(collector: TCollector, map: TMap) -> MapFruit<TCollector, TMap> {
+        MapFruit { collector, map }
+    }
+}
+
+impl<TCollector, TMap, TFruit> Collector for MapFruit<TCollector, TMap>
+where
+    TCollector: Collector,
+    TMap: Fn(TCollector::Fruit) -> TFruit + Send + Sync,
+    TFruit: crate::collector::Fruit,
+{
+    type Fruit = TFruit;
+
+    type Child = TCollector::Child;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<TCollector::Child> {
+        self.collector.for_segment(segment_local_id, segment_reader)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<TCollector::Child as SegmentCollector>::Fruit>,
+    ) -> crate::Result<TFruit> {
+        let fruit = self.collector.merge_fruits(segment_fruits)?;
+        Ok((self.map)(fruit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapFruit;
+    use crate::collector::{Collector, Count, TopDocs};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, Term};
+
+    #[test]
+    fn test_map_fruit_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "diary"))?;
+        index_writer.add_document(doc!(title => "diary of a cow"))?;
+        index_writer.add_document(doc!(title => "the wind"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(title, "diary"),
+            IndexRecordOption::Basic,
+        );
+        let collector = MapFruit::new((Count, TopDocs::with_limit(1)), |(count, top_docs)| {
+            (
+                count,
+                top_docs
+                    .into_iter()
+                    .map(|(_, addr)| addr)
+                    .collect::<Vec<_>>(),
+            )
+        });
+        assert!(collector.requires_scoring());
+        let (count, doc_addresses) = searcher.search(&query, &collector)?;
+        assert_eq!(count, 2);
+        assert_eq!(doc_addresses.len(), 1);
+        Ok(())
+    }
+}
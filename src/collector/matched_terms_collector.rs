@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::postings::SegmentPostings;
+use crate::query::Query;
+use crate::schema::{Field, IndexRecordOption};
+use crate::{DocAddress, DocId, DocSet, Score, SegmentOrdinal, SegmentReader, Term};
+
+/// A document returned by
+/// [`TopDocs::with_matched_terms`](./struct.TopDocs.html#method.with_matched_terms), along with the
+/// query terms it contains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedTermsHit {
+    /// Score of the document.
+    pub score: Score,
+    /// Address of the document.
+    pub doc_address: DocAddress,
+    /// Terms of the query present in the document, in term order.
+    pub matched_terms: Vec<Term>,
+}
+
+impl MatchedTermsHit {
+    /// Returns the fields in which at least one term of the query matched the document.
+    pub fn matched_fields(&self) -> Vec<Field> {
+        let mut fields: Vec<Field> = self.matched_terms.iter().map(Term::field).collect();
+        fields.dedup();
+        fields
+    }
+}
+
+pub(crate) struct MatchedTermsCollector {
+    collector: TopCollector<Score>,
+    terms: Vec<Term>,
+}
+
+impl MatchedTermsCollector {
+    pub fn new(collector: TopCollector<Score>, query: &dyn Query) -> MatchedTermsCollector {
+        let mut term_set = BTreeMap::new();
+        query.query_terms(&mut term_set);
+        MatchedTermsCollector {
+            collector,
+            terms: term_set.into_keys().collect(),
+        }
+    }
+}
+
+impl Collector for MatchedTermsCollector {
+    type Fruit = Vec<MatchedTermsHit>;
+
+    type Child = MatchedTermsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<MatchedTermsSegmentCollector> {
+        let mut term_postings = Vec::with_capacity(self.terms.len());
+        for term in &self.terms {
+            let postings_opt = segment_reader
+                .inverted_index(term.field())?
+                .read_postings(term, IndexRecordOption::Basic)?;
+            if let Some(postings) = postings_opt {
+                term_postings.push((term.clone(), postings));
+            }
+        }
+        Ok(MatchedTermsSegmentCollector {
+            segment_collector: self
+                .collector
+                .for_segment(segment_local_id, segment_reader)?,
+            term_postings,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<MatchedTermsHit>>,
+    ) -> crate::Result<Vec<MatchedTermsHit>> {
+        let mut matched_terms_per_doc: HashMap<DocAddress, Vec<Term>> = HashMap::new();
+        let scored_docs: Vec<Vec<(Score, DocAddress)>> = segment_fruits
+            .into_iter()
+            .map(|segment_hits| {
+                segment_hits
+                    .into_iter()
+                    .map(|hit| {
+                        matched_terms_per_doc.insert(hit.doc_address, hit.matched_terms);
+                        (hit.score, hit.doc_address)
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(self
+            .collector
+            .merge_fruits(scored_docs)?
+            .into_iter()
+            .map(|(score, doc_address)| MatchedTermsHit {
+                score,
+                doc_address,
+                matched_terms: matched_terms_per_doc
+                    .remove(&doc_address)
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+/// Segment collector associated to
+/// [`TopDocs::with_matched_terms`](./struct.TopDocs.html#method.with_matched_terms).
+pub struct MatchedTermsSegmentCollector {
+    segment_collector: TopSegmentCollector<Score>,
+    term_postings: Vec<(Term, SegmentPostings)>,
+}
+
+impl SegmentCollector for MatchedTermsSegmentCollector {
+    type Fruit = Vec<MatchedTermsHit>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.segment_collector.collect(doc, score);
+    }
+
+    fn harvest(mut self) -> Vec<MatchedTermsHit> {
+        let mut hits: Vec<MatchedTermsHit> = self
+            .segment_collector
+            .harvest()
+            .into_iter()
+            .map(|(score, doc_address)| MatchedTermsHit {
+                score,
+                doc_address,
+                matched_terms: Vec::new(),
+            })
+            .collect();
+        // Postings can only move forward, so hits are visited in doc id order.
+        let mut hit_ords: Vec<usize> = (0..hits.len()).collect();
+        hit_ords.sort_by_key(|&hit_ord| hits[hit_ord].doc_address.doc_id);
+        for hit_ord in hit_ords {
+            let doc = hits[hit_ord].doc_address.doc_id;
+            for (term, postings) in &mut self.term_postings {
+                if postings.doc() <= doc && postings.seek(doc) == doc {
+                    hits[hit_ord].matched_terms.push(term.clone());
+                }
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::TopDocs;
+    use crate::query::QueryParser;
+    use crate::schema::{Schema, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_matched_terms() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let tags = schema_builder.add_text_field("tags", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "red shoes", tags => "fashion"))?;
+        index_writer.add_document(doc!(title => "blue hat", tags => "red fashion"))?;
+        index_writer.add_document(doc!(title => "green", tags => "garden"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "red red red", tags => "red"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title, tags]);
+        let query = query_parser.parse_query("red fashion")?;
+        let mut hits =
+            searcher.search(&query, &TopDocs::with_limit(10).with_matched_terms(&query))?;
+        assert_eq!(hits.len(), 3);
+        hits.sort_by_key(|hit| hit.doc_address);
+        assert_eq!(hits[0].doc_address, DocAddress::new(0, 0));
+        assert_eq!(
+            hits[0].matched_terms,
+            vec![
+                Term::from_field_text(title, "red"),
+                Term::from_field_text(tags, "fashion"),
+            ]
+        );
+        assert_eq!(hits[0].matched_fields(), vec![title, tags]);
+        assert_eq!(hits[1].doc_address, DocAddress::new(0, 1));
+        assert_eq!(hits[1].matched_fields(), vec![tags]);
+        assert_eq!(hits[2].doc_address, DocAddress::new(1, 0));
+        assert_eq!(
+            hits[2].matched_terms,
+            vec![
+                Term::from_field_text(title, "red"),
+                Term::from_field_text(tags, "red"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::TantivyError;
+
+/// Memory budget of a query, shared by its collectors.
+///
+/// The collectors given a budget, such as [`TopDocs`](super::TopDocs),
+/// [`FacetCollector`](super::FacetCollector) and the aggregation collectors, charge the budget
+/// with the memory they are about to allocate. Once the total goes over the limit, the search
+/// fails with [`TantivyError::MemoryLimitExceeded`], rather than letting one pathological
+/// query exhaust the memory of the process.
+///
+/// The memory charged is never given back: a budget is meant to be created for each query,
+/// and shared by all of its collectors and segments.
+///
+/// ```rust
+/// use tantivy::collector::{MemoryBudget, TopDocs};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, TantivyError};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let memory_budget = MemoryBudget::with_limit(1_000_000);
+/// let top_docs = TopDocs::with_limit(1_000_000).with_memory_budget(memory_budget);
+/// let err = searcher.search(&AllQuery, &top_docs).unwrap_err();
+/// assert!(matches!(err, TantivyError::MemoryLimitExceeded(_)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MemoryBudget(Arc<InnerMemoryBudget>);
+
+#[derive(Debug)]
+struct InnerMemoryBudget {
+    limit_in_bytes: usize,
+    used_in_bytes: AtomicUsize,
+}
+
+impl MemoryBudget {
+    /// Creates a budget of `limit_in_bytes` bytes.
+    pub fn with_limit(limit_in_bytes: usize) -> MemoryBudget {
+        MemoryBudget(Arc::new(InnerMemoryBudget {
+            limit_in_bytes,
+            used_in_bytes: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the limit of the budget, in bytes.
+    pub fn limit_in_bytes(&self) -> usize {
+        self.0.limit_in_bytes
+    }
+
+    /// Returns the number of bytes charged so far.
+    pub fn used_in_bytes(&self) -> usize {
+        self.0.used_in_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Charges the budget with `num_bytes` bytes.
+    ///
+    /// Returns [`TantivyError::MemoryLimitExceeded`] if the budget is exceeded, in which case
+    /// the caller is expected to abort rather than allocate the memory.
+    pub fn allocate(&self, num_bytes: usize) -> crate::Result<()> {
+        self.record(num_bytes);
+        self.check()
+    }
+
+    /// Charges the budget with `num_bytes` bytes, leaving it to a later call to
+    /// [`MemoryBudget::check`] to fail.
+    pub(crate) fn record(&self, num_bytes: usize) {
+        let _ = self.0.used_in_bytes.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |used_in_bytes| Some(used_in_bytes.saturating_add(num_bytes)),
+        );
+    }
+
+    /// Returns [`TantivyError::MemoryLimitExceeded`] if the budget is exceeded.
+    pub(crate) fn check(&self) -> crate::Result<()> {
+        if self.used_in_bytes() > self.0.limit_in_bytes {
+            return Err(TantivyError::MemoryLimitExceeded(format!(
+                "The query needs more than {} bytes of memory to run.",
+                self.0.limit_in_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Charges the budget, if any, with `num_bytes` bytes.
+pub(crate) fn allocate(
+    memory_budget_opt: &Option<MemoryBudget>,
+    num_bytes: usize,
+) -> crate::Result<()> {
+    if let Some(memory_budget) = memory_budget_opt {
+        memory_budget.allocate(num_bytes)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryBudget;
+    use crate::TantivyError;
+
+    #[test]
+    fn test_memory_budget() {
+        let memory_budget = MemoryBudget::with_limit(100);
+        assert!(memory_budget.allocate(60).is_ok());
+        assert!(memory_budget.clone().allocate(40).is_ok());
+        assert_eq!(memory_budget.used_in_bytes(), 100);
+        assert!(matches!(
+            memory_budget.allocate(1),
+            Err(TantivyError::MemoryLimitExceeded(_))
+        ));
+        assert!(memory_budget.allocate(usize::MAX).is_err());
+        assert_eq!(memory_budget.limit_in_bytes(), 100);
+    }
+}
@@ -77,23 +77,62 @@
 //! you can rely on `MultiCollector`'s.
 //!
 //!
+//! # Adapting existing collectors
+//!
+//! Before implementing a collector from scratch, check whether wrapping an existing one
+//! is enough:
+//! - [`FilterCollector`](./struct.FilterCollector.html) only passes the documents whose fast
+//!   field value matches a predicate,
+//! - [`TopNThen`](./struct.TopNThen.html) only passes the best scored documents of each segment,
+//! - [`MapFruit`](./struct.MapFruit.html) transforms the resulting fruit.
+//!
 //! # Implementing your own collectors.
 //!
 //! See the `custom_collector` example.
 
 use downcast_rs::impl_downcast;
 
-use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, COLLECT_BLOCK_BUFFER_LEN};
 
 mod count_collector;
-pub use self::count_collector::Count;
+pub use self::count_collector::{Count, CountUpTo, HitCount, SegmentCountUpToCollector};
 
 mod histogram_collector;
 pub use histogram_collector::HistogramCollector;
 
+mod score_histogram_collector;
+pub use self::score_histogram_collector::{
+    ScoreHistogram, ScoreHistogramCollector, SegmentScoreHistogramCollector,
+};
+
+mod range_facet_collector;
+pub use self::range_facet_collector::{
+    RangeFacetCollector, RangeFacetCounts, SegmentRangeFacetCollector,
+};
+
+mod cardinality_collector;
+pub use self::cardinality_collector::{
+    CardinalityCollector, HyperLogLog, SegmentCardinalityCollector,
+};
+
+mod percentiles_collector;
+pub use self::percentiles_collector::{PercentilesCollector, SegmentPercentilesCollector, TDigest};
+
+mod distinct_collector;
+pub use self::distinct_collector::{DistinctCollector, DistinctSegmentCollector};
+
+mod top_hits_per_term_collector;
+pub use self::top_hits_per_term_collector::{
+    TermTopHits, TopHitsPerTerm, TopHitsPerTermSegmentCollector,
+};
+
 mod multi_collector;
 pub use self::multi_collector::{FruitHandle, MultiCollector, MultiFruit};
 
+mod memory_budget;
+pub(crate) use self::memory_budget::allocate;
+pub use self::memory_budget::MemoryBudget;
+
 mod top_collector;
 
 mod top_score_collector;
@@ -102,8 +141,17 @@ pub use self::top_score_collector::TopDocs;
 mod custom_score_top_collector;
 pub use self::custom_score_top_collector::{CustomScorer, CustomSegmentScorer};
 
+mod matched_terms_collector;
+pub use self::matched_terms_collector::{MatchedTermsHit, MatchedTermsSegmentCollector};
+
+mod rescore_top_collector;
+pub use self::rescore_top_collector::Rescorer;
+
 mod tweak_score_top_collector;
-pub use self::tweak_score_top_collector::{ScoreSegmentTweaker, ScoreTweaker};
+pub use self::tweak_score_top_collector::{
+    FastFieldColumn, FastFieldColumns, FastFieldSegmentScoreTweaker, ScoreSegmentTweaker,
+    ScoreTweaker,
+};
 
 mod facet_collector;
 pub use self::facet_collector::{FacetCollector, FacetCounts};
@@ -115,6 +163,15 @@ pub use self::docset_collector::DocSetCollector;
 mod filter_collector_wrapper;
 pub use self::filter_collector_wrapper::FilterCollector;
 
+mod sample_collector;
+pub use self::sample_collector::{SampleCollector, SampleSegmentCollector, Sampled};
+
+mod map_fruit_collector;
+pub use self::map_fruit_collector::MapFruit;
+
+mod top_n_then_collector;
+pub use self::top_n_then_collector::{TopNThen, TopNThenSegmentCollector};
+
 /// `Fruit` is the type for the result of our collection.
 /// e.g. `usize` for the `Count` collector.
 pub trait Fruit: Send + downcast_rs::Downcast {}
@@ -172,7 +229,18 @@ pub trait Collector: Sync + Send {
     ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
         let mut segment_collector = self.for_segment(segment_ord as u32, reader)?;
 
-        if let Some(alive_bitset) = reader.alive_bitset() {
+        if !self.requires_scoring() && segment_collector.accepts_blocks() {
+            let mut alive_docs: Vec<DocId> = Vec::with_capacity(COLLECT_BLOCK_BUFFER_LEN);
+            weight.for_each_no_score(reader, &mut |docs| {
+                if let Some(alive_bitset) = reader.alive_bitset() {
+                    alive_docs.clear();
+                    alive_docs.extend(docs.iter().filter(|&&doc| alive_bitset.is_alive(doc)));
+                    segment_collector.collect_block(&alive_docs);
+                } else {
+                    segment_collector.collect_block(docs);
+                }
+            })?;
+        } else if let Some(alive_bitset) = reader.alive_bitset() {
             weight.for_each(reader, &mut |doc, score| {
                 if alive_bitset.is_alive(doc) {
                     segment_collector.collect(doc, score);
@@ -255,6 +323,22 @@ pub trait SegmentCollector: 'static {
     /// The query pushes the scored document to the collector via this method.
     fn collect(&mut self, doc: DocId, score: Score);
 
+    /// Returns true if the collector implements `collect_block`, to be pushed the documents
+    /// by blocks, without their score, when it does not require scoring.
+    ///
+    /// Otherwise the documents are pushed one by one with `collect`, along with their score.
+    fn accepts_blocks(&self) -> bool {
+        false
+    }
+
+    /// Pushes a block of documents to the collector, without their score.
+    ///
+    /// This is used instead of `collect` if the collector does not require scoring and
+    /// `accepts_blocks` returns true.
+    fn collect_block(&mut self, _docs: &[DocId]) {
+        unimplemented!("collect_block is only called on the collectors accepting blocks.")
+    }
+
     /// Extract the fruit of the collection from the `SegmentCollector`.
     fn harvest(self) -> Self::Fruit;
 }
@@ -0,0 +1,573 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::f64_from_fastfield_u64;
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{
+    type_and_cardinality, DynamicFastFieldReader, FastFieldReader, MultiValuedFastFieldReader,
+};
+use crate::schema::{Cardinality, Field, Type};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Centroid {
+    mean: f64,
+    weight: u64,
+}
+
+/// t-digest sketch, estimating the quantiles of the values inserted in it.
+///
+/// The values are summarized by at most about `compression` centroids, which are smaller
+/// close to the extreme quantiles: the estimates of the percentiles close to 0 and 100
+/// are the most accurate ones.
+///
+/// Sketches of the same compression can be merged, e.g. to combine the sketches computed by
+/// several shards, and serialized. Deserializing a sketch whose compression, centroids or
+/// bounds are inconsistent fails.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TDigestDeser")]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    // Values inserted since the centroids were last computed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unmerged: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Deserialize)]
+struct TDigestDeser {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    #[serde(default)]
+    unmerged: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl TryFrom<TDigestDeser> for TDigest {
+    type Error = String;
+
+    fn try_from(deser: TDigestDeser) -> Result<Self, Self::Error> {
+        if !(deser.compression >= 10.0 && deser.compression.is_finite()) {
+            return Err(format!(
+                "The compression {} of the t-digest sketch should be a finite number of at \
+                 least 10.",
+                deser.compression
+            ));
+        }
+        if deser
+            .centroids
+            .iter()
+            .any(|centroid| centroid.weight == 0 || centroid.mean.is_nan())
+        {
+            return Err(
+                "The centroids of a t-digest sketch should have a weight and a mean.".to_string(),
+            );
+        }
+        if deser
+            .centroids
+            .windows(2)
+            .any(|centroids| centroids[0].mean > centroids[1].mean)
+        {
+            return Err("The centroids of a t-digest sketch should be sorted.".to_string());
+        }
+        let num_values = deser
+            .centroids
+            .iter()
+            .try_fold(deser.unmerged.len() as u64, |num_values, centroid| {
+                num_values.checked_add(centroid.weight)
+            });
+        if num_values != Some(deser.count) {
+            return Err(format!(
+                "The centroids and the unmerged values of a t-digest sketch should add up to \
+                 its count {}.",
+                deser.count
+            ));
+        }
+        if deser.count > 0 {
+            let is_within_bounds = |value: f64| deser.min <= value && value <= deser.max;
+            if !deser
+                .centroids
+                .iter()
+                .map(|centroid| centroid.mean)
+                .chain(deser.unmerged.iter().copied())
+                .all(is_within_bounds)
+            {
+                return Err(format!(
+                    "The values of a t-digest sketch should be within its bounds [{}, {}].",
+                    deser.min, deser.max
+                ));
+            }
+        }
+        Ok(TDigest {
+            compression: deser.compression,
+            centroids: deser.centroids,
+            unmerged: deser.unmerged,
+            count: deser.count,
+            min: deser.min,
+            max: deser.max,
+        })
+    }
+}
+
+impl Default for TDigest {
+    fn default() -> TDigest {
+        TDigest::with_compression(TDigest::DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    /// Compression of the sketches created by `TDigest::default`.
+    pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+    /// Creates an empty sketch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compression is lower than 10.
+    pub fn with_compression(compression: f64) -> TDigest {
+        assert!(
+            compression >= 10.0,
+            "The compression should be at least 10."
+        );
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            unmerged: Vec::new(),
+            count: 0,
+            min: 0.0,
+            max: 0.0,
+        }
+    }
+
+    /// Returns the compression of the sketch.
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    /// Returns the number of values inserted in the sketch.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the minimum value inserted in the sketch, or `None` if it is empty.
+    pub fn min(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.min)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the maximum value inserted in the sketch, or `None` if it is empty.
+    pub fn max(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.max)
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a value in the sketch. NaN values are ignored.
+    pub fn insert(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.unmerged.push(value);
+        self.count += 1;
+        if self.unmerged.len() as f64 >= 5.0 * self.compression {
+            self.compress();
+        }
+    }
+
+    /// Merges the values of another sketch into this one.
+    ///
+    /// Returns an error if the sketches have different compressions.
+    pub fn merge(&mut self, other: &TDigest) -> crate::Result<()> {
+        if self.compression != other.compression {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Cannot merge t-digest sketches of compressions {} and {}.",
+                self.compression, other.compression
+            )));
+        }
+        if other.count == 0 {
+            return Ok(());
+        }
+        if self.count == 0 {
+            self.min = other.min;
+            self.max = other.max;
+        } else {
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+        }
+        self.centroids.extend_from_slice(&other.centroids);
+        self.unmerged.extend_from_slice(&other.unmerged);
+        self.count += other.count;
+        self.compress();
+        Ok(())
+    }
+
+    // Merges the unmerged values and the centroids into as few centroids as the
+    // compression allows.
+    fn compress(&mut self) {
+        let mut centroids: Vec<Centroid> = self
+            .unmerged
+            .drain(..)
+            .map(|mean| Centroid { mean, weight: 1 })
+            .chain(self.centroids.drain(..))
+            .collect();
+        if centroids.is_empty() {
+            return;
+        }
+        centroids.sort_by(|left, right| {
+            left.mean
+                .partial_cmp(&right.mean)
+                .expect("The means are not NaN.")
+        });
+        let total_weight = self.count as f64;
+        // Scale function k1 of the t-digest paper, and its inverse.
+        let k = |q: f64| self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin();
+        let q = |k: f64| ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0;
+        let mut merged_centroids = Vec::new();
+        let mut centroids_it = centroids.into_iter();
+        let mut current = centroids_it
+            .next()
+            .expect("There is at least one centroid.");
+        let mut weight_before = 0.0;
+        let mut q_limit = q(k(0.0) + 1.0);
+        for centroid in centroids_it {
+            let weight_after = weight_before + (current.weight + centroid.weight) as f64;
+            if weight_after / total_weight <= q_limit {
+                let weight = current.weight + centroid.weight;
+                current.mean +=
+                    (centroid.mean - current.mean) * centroid.weight as f64 / weight as f64;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight as f64;
+                merged_centroids.push(current);
+                q_limit = q(k(weight_before / total_weight) + 1.0);
+                current = centroid;
+            }
+        }
+        merged_centroids.push(current);
+        self.centroids = merged_centroids;
+    }
+
+    /// Returns the estimated quantile `q` of the values inserted in the sketch,
+    /// or `None` if it is empty. The quantile is clamped to `[0, 1]`.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let mut sketch;
+        let mut digest = self;
+        if !self.unmerged.is_empty() {
+            sketch = self.clone();
+            sketch.compress();
+            digest = &sketch;
+        }
+        let target_weight = q.clamp(0.0, 1.0) * self.count as f64;
+        // Interpolates between the centers of the centroids, the weight of a centroid being
+        // evenly spread around its mean, and between the extreme centroids and the extreme
+        // values.
+        let mut previous_mean = self.min;
+        let mut previous_center = 0.0;
+        let mut weight_before = 0.0;
+        for centroid in &digest.centroids {
+            let center = weight_before + centroid.weight as f64 / 2.0;
+            if target_weight <= center {
+                let ratio = if center > previous_center {
+                    (target_weight - previous_center) / (center - previous_center)
+                } else {
+                    1.0
+                };
+                return Some(previous_mean + ratio * (centroid.mean - previous_mean));
+            }
+            previous_mean = centroid.mean;
+            previous_center = center;
+            weight_before += centroid.weight as f64;
+        }
+        let ratio = if weight_before > previous_center {
+            (target_weight - previous_center) / (weight_before - previous_center)
+        } else {
+            1.0
+        };
+        Some(previous_mean + ratio * (self.max - previous_mean))
+    }
+
+    /// Returns the estimated percentile `percent`, within `[0, 100]`, of the values inserted
+    /// in the sketch, or `None` if it is empty.
+    pub fn percentile(&self, percent: f64) -> Option<f64> {
+        self.quantile(percent / 100.0)
+    }
+}
+
+/// Computes a [`TDigest`] sketch of the values of a numeric fast field among the collected
+/// documents, estimating their percentiles.
+///
+/// The fruit is the sketch itself, so that the sketches of several indexes can be merged
+/// before the percentiles are computed.
+///
+/// ```rust
+/// use tantivy::collector::PercentilesCollector;
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let latency = schema_builder.add_u64_field("latency", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for latency_value in 1u64..=100 {
+///     index_writer.add_document(doc!(latency => latency_value))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let sketch = searcher.search(&AllQuery, &PercentilesCollector::for_field(latency))?;
+/// assert_eq!(sketch.percentile(0.0), Some(1.0));
+/// assert_eq!(sketch.percentile(100.0), Some(100.0));
+/// let median = sketch.percentile(50.0).unwrap();
+/// assert!((median - 50.5).abs() < 1.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PercentilesCollector {
+    field: Field,
+    compression: f64,
+}
+
+impl PercentilesCollector {
+    /// Creates a collector computing a sketch of the values of `field`
+    /// with the default compression.
+    pub fn for_field(field: Field) -> PercentilesCollector {
+        PercentilesCollector {
+            field,
+            compression: TDigest::DEFAULT_COMPRESSION,
+        }
+    }
+
+    /// Sets the compression of the sketch. See [`TDigest::with_compression`].
+    #[must_use]
+    pub fn with_compression(mut self, compression: f64) -> PercentilesCollector {
+        // Checks the compression right away.
+        TDigest::with_compression(compression);
+        self.compression = compression;
+        self
+    }
+}
+
+enum ValuesReader {
+    Single(DynamicFastFieldReader<u64>),
+    Multi(MultiValuedFastFieldReader<u64>),
+}
+
+impl Collector for PercentilesCollector {
+    type Fruit = TDigest;
+    type Child = SegmentPercentilesCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<SegmentPercentilesCollector> {
+        let field_entry = segment.schema().get_field_entry(self.field);
+        let value_type = field_entry.field_type().value_type();
+        let cardinality_opt = match value_type {
+            Type::U64 | Type::I64 | Type::F64 => {
+                type_and_cardinality(field_entry.field_type()).map(|(_, cardinality)| cardinality)
+            }
+            _ => None,
+        };
+        let values_reader = match cardinality_opt {
+            Some(Cardinality::SingleValue) => {
+                ValuesReader::Single(segment.fast_fields().u64_lenient(self.field)?)
+            }
+            Some(Cardinality::MultiValues) => {
+                ValuesReader::Multi(segment.fast_fields().u64s_lenient(self.field)?)
+            }
+            None => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a numeric fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(SegmentPercentilesCollector {
+            sketch: TDigest::with_compression(self.compression),
+            value_type,
+            values_reader,
+            vals: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_sketches: Vec<TDigest>) -> crate::Result<TDigest> {
+        let mut sketch = TDigest::with_compression(self.compression);
+        for segment_sketch in &segment_sketches {
+            sketch.merge(segment_sketch)?;
+        }
+        Ok(sketch)
+    }
+}
+
+/// Segment collector of a [`PercentilesCollector`].
+pub struct SegmentPercentilesCollector {
+    sketch: TDigest,
+    value_type: Type,
+    values_reader: ValuesReader,
+    vals: Vec<u64>,
+}
+
+impl SegmentCollector for SegmentPercentilesCollector {
+    type Fruit = TDigest;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        match &self.values_reader {
+            ValuesReader::Single(ff_reader) => {
+                let value = f64_from_fastfield_u64(ff_reader.get(doc), &self.value_type);
+                self.sketch.insert(value);
+            }
+            ValuesReader::Multi(ff_reader) => {
+                ff_reader.get_vals(doc, &mut self.vals);
+                for &val in &self.vals {
+                    self.sketch
+                        .insert(f64_from_fastfield_u64(val, &self.value_type));
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> TDigest {
+        self.sketch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PercentilesCollector, TDigest};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use crate::{Index, Term};
+
+    #[test]
+    fn test_tdigest_quantiles() {
+        let mut sketch = TDigest::default();
+        assert_eq!(sketch.quantile(0.5), None);
+        // Values inserted in a scrambled order.
+        for i in 0..100_000u64 {
+            sketch.insert(((i * 7919) % 100_000) as f64);
+        }
+        assert_eq!(sketch.count(), 100_000);
+        assert_eq!(sketch.quantile(0.0), Some(0.0));
+        assert_eq!(sketch.quantile(1.0), Some(99_999.0));
+        for q in [0.01, 0.25, 0.5, 0.9, 0.99, 0.999] {
+            let estimate = sketch.quantile(q).unwrap();
+            assert!((estimate - q * 100_000.0).abs() < 500.0);
+        }
+
+        let mut sketch_a = TDigest::default();
+        let mut sketch_b = TDigest::default();
+        for i in 0..1_000 {
+            sketch_a.insert(i as f64);
+            sketch_b.insert((i + 1_000) as f64);
+        }
+        sketch_a.merge(&sketch_b).unwrap();
+        assert_eq!(sketch_a.count(), 2_000);
+        assert!((sketch_a.percentile(50.0).unwrap() - 1_000.0).abs() < 20.0);
+        assert!(sketch_a.merge(&TDigest::with_compression(50.0)).is_err());
+
+        let sketch_json = serde_json::to_string(&sketch_a).unwrap();
+        let deserialized_sketch: TDigest = serde_json::from_str(&sketch_json).unwrap();
+        assert_eq!(deserialized_sketch, sketch_a);
+    }
+
+    #[test]
+    fn test_tdigest_deserialize_invalid() {
+        let valid_json = serde_json::json!({
+            "compression": 100.0,
+            "centroids": [{"mean": 1.0, "weight": 2}, {"mean": 3.0, "weight": 1}],
+            "unmerged": [2.0],
+            "count": 4,
+            "min": 1.0,
+            "max": 3.0,
+        });
+        let sketch: TDigest = serde_json::from_value(valid_json.clone()).unwrap();
+        assert_eq!(sketch.count(), 4);
+        let with = |key: &str, value: serde_json::Value| {
+            let mut invalid_json = valid_json.clone();
+            invalid_json[key] = value;
+            serde_json::from_value::<TDigest>(invalid_json)
+        };
+        assert!(with("compression", serde_json::json!(1.0)).is_err());
+        assert!(with("count", serde_json::json!(5)).is_err());
+        assert!(with("max", serde_json::json!(2.5)).is_err());
+        assert!(with("unmerged", serde_json::json!([0.0])).is_err());
+        assert!(with(
+            "centroids",
+            serde_json::json!([{"mean": 3.0, "weight": 2}, {"mean": 1.0, "weight": 1}])
+        )
+        .is_err());
+        assert!(with(
+            "centroids",
+            serde_json::json!([{"mean": 1.0, "weight": 3}, {"mean": 3.0, "weight": 0}])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_percentiles_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let latency = schema_builder.add_f64_field("latency", FAST);
+        let status = schema_builder.add_text_field("status", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..1_000 {
+            let status_value = if i % 2 == 0 { "ok" } else { "error" };
+            index_writer.add_document(doc!(latency => i as f64 / 10.0, status => status_value))?;
+            if i % 300 == 0 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert!(searcher.segment_readers().len() > 1);
+
+        let sketch = searcher.search(&AllQuery, &PercentilesCollector::for_field(latency))?;
+        assert_eq!(sketch.count(), 1_000);
+        assert_eq!(sketch.min(), Some(0.0));
+        assert_eq!(sketch.max(), Some(99.9));
+        assert!((sketch.percentile(90.0).unwrap() - 90.0).abs() < 1.0);
+
+        let error_query = TermQuery::new(
+            Term::from_field_text(status, "error"),
+            IndexRecordOption::Basic,
+        );
+        let error_sketch =
+            searcher.search(&error_query, &PercentilesCollector::for_field(latency))?;
+        assert_eq!(error_sketch.count(), 500);
+        assert_eq!(error_sketch.min(), Some(0.1));
+
+        assert!(searcher
+            .search(&AllQuery, &PercentilesCollector::for_field(status))
+            .is_err());
+        Ok(())
+    }
+}
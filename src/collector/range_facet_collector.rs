@@ -0,0 +1,244 @@
+use std::marker::PhantomData;
+use std::ops::{Add, Range};
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
+use crate::schema::{Cardinality, Field};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Counts the documents of the collected DocSet in each of a list of contiguous ranges of
+/// the values of a numeric or date fast field, in a single pass.
+///
+/// The ranges are defined by their edges: `n` increasing edges define the `n - 1` buckets
+/// `[edges[0], edges[1])`, ..., `[edges[n - 2], edges[n - 1])`. The documents whose value
+/// falls outside of `[edges[0], edges[n - 1])` are not counted.
+///
+/// The field has to be a single-valued fast field, whose type is `T`.
+///
+/// ```rust
+/// use tantivy::collector::RangeFacetCollector;
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for price_value in [5u64, 15, 25, 150] {
+///     index_writer.add_document(doc!(price => price_value))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let range_facet_collector = RangeFacetCollector::with_edges(price, vec![0u64, 10, 100]);
+/// let range_counts = searcher.search(&AllQuery, &range_facet_collector)?;
+/// assert_eq!(range_counts.counts(), &[1, 2]);
+/// assert_eq!(range_counts.iter().next(), Some((0u64..10, 1)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RangeFacetCollector<T: FastValue> {
+    field: Field,
+    edges: Vec<T>,
+}
+
+impl<T: FastValue> RangeFacetCollector<T> {
+    /// Creates a collector counting the documents in the buckets defined by `edges`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are less than 2 edges, or if they are not strictly increasing.
+    pub fn with_edges(field: Field, edges: Vec<T>) -> RangeFacetCollector<T> {
+        assert!(edges.len() >= 2, "At least 2 edges are required.");
+        assert!(
+            edges
+                .windows(2)
+                .all(|window| window[0].to_u64() < window[1].to_u64()),
+            "The edges should be strictly increasing."
+        );
+        RangeFacetCollector { field, edges }
+    }
+
+    /// Creates a collector counting the documents in `num_buckets` buckets of width `interval`,
+    /// the first one starting at `start`.
+    pub fn with_fixed_intervals(
+        field: Field,
+        start: T,
+        interval: T,
+        num_buckets: usize,
+    ) -> RangeFacetCollector<T>
+    where
+        T: Add<Output = T>,
+    {
+        let mut edges = Vec::with_capacity(num_buckets + 1);
+        let mut edge = start;
+        edges.push(edge);
+        for _ in 0..num_buckets {
+            edge = edge + interval;
+            edges.push(edge);
+        }
+        RangeFacetCollector::with_edges(field, edges)
+    }
+}
+
+/// Number of documents in each of the buckets of a [`RangeFacetCollector`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeFacetCounts<T> {
+    edges: Vec<T>,
+    counts: Vec<u64>,
+}
+
+impl<T: Copy> RangeFacetCounts<T> {
+    /// Returns the number of documents in each bucket, in the order of the buckets.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the buckets, along with their number of documents.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<T>, u64)> + '_ {
+        self.edges
+            .windows(2)
+            .zip(&self.counts)
+            .map(|(window, &count)| (window[0]..window[1], count))
+    }
+}
+
+impl<T: FastValue> Collector for RangeFacetCollector<T> {
+    type Fruit = RangeFacetCounts<T>;
+    type Child = SegmentRangeFacetCollector<T>;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let field_entry = segment.schema().get_field_entry(self.field);
+        if T::fast_field_cardinality(field_entry.field_type()) != Some(Cardinality::SingleValue) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a single-valued {:?} fast field.",
+                field_entry.name(),
+                T::to_type()
+            )));
+        }
+        let ff_reader = segment.fast_fields().u64_lenient(self.field)?;
+        Ok(SegmentRangeFacetCollector {
+            edges: self.edges.iter().map(FastValue::to_u64).collect(),
+            counts: vec![0; self.edges.len() - 1],
+            ff_reader,
+            _marker: PhantomData,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_counts: Vec<Vec<u64>>) -> crate::Result<RangeFacetCounts<T>> {
+        let mut counts = vec![0; self.edges.len() - 1];
+        for segment_counts in segment_counts {
+            for (count, segment_count) in counts.iter_mut().zip(segment_counts) {
+                *count += segment_count;
+            }
+        }
+        Ok(RangeFacetCounts {
+            edges: self.edges.clone(),
+            counts,
+        })
+    }
+}
+
+/// Segment collector of a [`RangeFacetCollector`].
+pub struct SegmentRangeFacetCollector<T> {
+    // The edges, as u64 fast field values.
+    edges: Vec<u64>,
+    counts: Vec<u64>,
+    ff_reader: DynamicFastFieldReader<u64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: FastValue> SegmentCollector for SegmentRangeFacetCollector<T> {
+    type Fruit = Vec<u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let value = self.ff_reader.get(doc);
+        // Number of edges lower or equal to the value.
+        let num_edges_below = self.edges.partition_point(|&edge| edge <= value);
+        if num_edges_below > 0 && num_edges_below < self.edges.len() {
+            self.counts[num_edges_below - 1] += 1;
+        }
+    }
+
+    fn harvest(self) -> Vec<u64> {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeFacetCollector;
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, INDEXED, STRING};
+    use crate::time::{Date, Month};
+    use crate::{DateTime, Index, Term};
+
+    #[test]
+    fn test_range_facet_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let score = schema_builder.add_f64_field("score", FAST);
+        let date = schema_builder.add_date_field("date", FAST);
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let count = schema_builder.add_i64_field("count", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let day = |day: u8| {
+            DateTime::from_primitive(
+                Date::from_calendar_date(2022, Month::March, day)
+                    .unwrap()
+                    .with_hms(0, 0, 0)
+                    .unwrap(),
+            )
+        };
+        for (score_value, day_value, tag_value) in [
+            (-1.5f64, 1, "a"),
+            (0.5, 2, "b"),
+            (1.0, 3, "a"),
+            (2.5, 4, "a"),
+            (10.0, 5, "b"),
+        ] {
+            index_writer.add_document(
+                doc!(score => score_value, date => day(day_value), tag => tag_value),
+            )?;
+            // Several segments.
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 5);
+
+        let score_collector = RangeFacetCollector::with_fixed_intervals(score, -1.0f64, 1.0, 4);
+        let score_counts = searcher.search(&AllQuery, &score_collector)?;
+        assert_eq!(score_counts.counts(), &[0, 1, 1, 1]);
+        let buckets: Vec<_> = score_counts.iter().collect();
+        assert_eq!(buckets[0], (-1.0..0.0, 0));
+        assert_eq!(buckets[3], (2.0..3.0, 1));
+
+        let tag_query = TermQuery::new(Term::from_field_text(tag, "a"), IndexRecordOption::Basic);
+        let date_collector = RangeFacetCollector::with_edges(date, vec![day(1), day(3), day(5)]);
+        let date_counts = searcher.search(&tag_query, &date_collector)?;
+        assert_eq!(date_counts.counts(), &[1, 2]);
+
+        let count_collector = RangeFacetCollector::with_edges(count, vec![0i64, 1]);
+        assert!(searcher.search(&AllQuery, &count_collector).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_facet_collector_unsorted_edges() {
+        let mut schema_builder = Schema::builder();
+        let field = schema_builder.add_u64_field("field", FAST);
+        RangeFacetCollector::with_edges(field, vec![2u64, 1]);
+    }
+}
@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+
+use crate::collector::top_score_collector::TopScoreSegmentCollector;
+use crate::collector::{Collector, SegmentCollector, TopDocs};
+use crate::query::Weight;
+use crate::{DocAddress, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// A `Rescorer` computes a new score for the top documents selected by a first,
+/// cheaper, ranking pass.
+///
+/// It receives the whole window of hits at once, so that implementations can
+/// batch calls to an expensive model, and must return one score per hit, in the same
+/// order.
+///
+/// `Rescorer` is implemented for closures `Fn(DocAddress, Score) -> Score`, receiving
+/// the address of a document and its original score.
+pub trait Rescorer: Sync + Send {
+    /// Returns the new score of each of the `hits`, in the same order.
+    fn rescore(&self, hits: &[(Score, DocAddress)]) -> crate::Result<Vec<Score>>;
+}
+
+impl<F> Rescorer for F
+where F: Fn(DocAddress, Score) -> Score + Sync + Send
+{
+    fn rescore(&self, hits: &[(Score, DocAddress)]) -> crate::Result<Vec<Score>> {
+        Ok(hits
+            .iter()
+            .map(|&(score, doc_address)| (self)(doc_address, score))
+            .collect())
+    }
+}
+
+pub(crate) struct RescoreTopCollector<TRescorer> {
+    top_docs: TopDocs,
+    rescorer: TRescorer,
+}
+
+impl<TRescorer: Rescorer> RescoreTopCollector<TRescorer> {
+    pub fn new(top_docs: TopDocs, rescorer: TRescorer) -> RescoreTopCollector<TRescorer> {
+        RescoreTopCollector { top_docs, rescorer }
+    }
+}
+
+impl<TRescorer: Rescorer> Collector for RescoreTopCollector<TRescorer> {
+    type Fruit = Vec<(Score, DocAddress)>;
+
+    type Child = TopScoreSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        self.top_docs.for_segment(segment_local_id, reader)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        child_fruits: Vec<Vec<(Score, DocAddress)>>,
+    ) -> crate::Result<Self::Fruit> {
+        let top_docs = self.top_docs.merge_fruits(child_fruits)?;
+        let new_scores = self.rescorer.rescore(&top_docs)?;
+        if new_scores.len() != top_docs.len() {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Rescorer returned {} scores for {} hits.",
+                new_scores.len(),
+                top_docs.len()
+            )));
+        }
+        let mut rescored_docs: Vec<(Score, DocAddress)> = new_scores
+            .into_iter()
+            .zip(top_docs.into_iter().map(|(_, doc_address)| doc_address))
+            .collect();
+        // In case of a tie on the score, we sort by ascending `DocAddress`
+        // to ensure a stable sorting, like the first pass does.
+        rescored_docs.sort_by(|(left_score, left_doc), (right_score, right_doc)| {
+            right_score
+                .partial_cmp(left_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left_doc.cmp(right_doc))
+        });
+        Ok(rescored_docs)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        self.top_docs.collect_segment(weight, segment_ord, reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rescorer;
+    use crate::collector::TopDocs;
+    use crate::query::QueryParser;
+    use crate::schema::{Schema, TEXT};
+    use crate::{DocAddress, Index, Score};
+
+    fn make_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "hello"))?;
+        index_writer.add_document(doc!(text => "hello hello world"))?;
+        index_writer.add_document(doc!(text => "hello happy world of wonders"))?;
+        index_writer.add_document(doc!(text => "goodbye"))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_rescorer_reranks_first_pass_window() -> crate::Result<()> {
+        let index = make_index()?;
+        let text = index.schema().get_field("text").unwrap();
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![text]).parse_query("hello")?;
+        let first_pass = searcher.search(&query, &TopDocs::with_limit(2))?;
+        assert_eq!(
+            first_pass
+                .iter()
+                .map(|(_, doc_address)| *doc_address)
+                .collect::<Vec<_>>(),
+            vec![DocAddress::new(0, 0), DocAddress::new(0, 1)]
+        );
+        // Favor documents with a larger doc id, without looking at the
+        // document outside of the window.
+        let rescorer =
+            |doc_address: DocAddress, score: Score| doc_address.doc_id as Score * 10.0 + score;
+        let rescored = searcher.search(&query, &TopDocs::with_rescorer(2, rescorer))?;
+        assert_eq!(
+            rescored
+                .iter()
+                .map(|(_, doc_address)| *doc_address)
+                .collect::<Vec<_>>(),
+            vec![DocAddress::new(0, 1), DocAddress::new(0, 0)]
+        );
+        assert_eq!(rescored[1].0, first_pass[0].0);
+        Ok(())
+    }
+
+    struct ReverseRescorer;
+
+    impl Rescorer for ReverseRescorer {
+        fn rescore(&self, hits: &[(Score, DocAddress)]) -> crate::Result<Vec<Score>> {
+            Ok(hits.iter().map(|(score, _)| -score).collect())
+        }
+    }
+
+    struct BrokenRescorer;
+
+    impl Rescorer for BrokenRescorer {
+        fn rescore(&self, _hits: &[(Score, DocAddress)]) -> crate::Result<Vec<Score>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_batch_rescorer() -> crate::Result<()> {
+        let index = make_index()?;
+        let text = index.schema().get_field("text").unwrap();
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![text]).parse_query("hello")?;
+        let rescored = searcher.search(&query, &TopDocs::with_rescorer(3, ReverseRescorer))?;
+        assert_eq!(
+            rescored
+                .iter()
+                .map(|(_, doc_address)| *doc_address)
+                .collect::<Vec<_>>(),
+            vec![
+                DocAddress::new(0, 2),
+                DocAddress::new(0, 1),
+                DocAddress::new(0, 0)
+            ]
+        );
+        let err = searcher
+            .search(&query, &TopDocs::with_rescorer(3, BrokenRescorer))
+            .unwrap_err();
+        assert!(matches!(err, crate::TantivyError::InvalidArgument(_)));
+        Ok(())
+    }
+}
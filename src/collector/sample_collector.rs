@@ -0,0 +1,279 @@
+use murmurhash32::murmurhash2;
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{type_and_cardinality, DynamicFastFieldReader, FastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+#[derive(Clone, Copy)]
+enum Sampling {
+    EveryNth(u64),
+    Hashed { field: Field, threshold: u64 },
+}
+
+/// The `SampleCollector` only passes a deterministic sample of the collected documents on to
+/// the wrapped collector, trading the accuracy of expensive aggregations for latency.
+///
+/// The documents are sampled either:
+/// - by taking one every `n` matching documents of each segment, see
+///   [`SampleCollector::every_nth`],
+/// - or by hashing the value of a key fast field, see [`SampleCollector::hashed`]: all of the
+///   documents sharing a key, such as a user id, are either sampled or not, in every segment and in
+///   every index.
+///
+/// The fruit reports the sampling rate along with the fruit of the wrapped collector, so that
+/// the counts can be scaled back.
+///
+/// ```rust
+/// use tantivy::collector::{Count, SampleCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let user_id = schema_builder.add_u64_field("user_id", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for user_id_value in 0u64..1_000 {
+///     index_writer.add_document(doc!(user_id => user_id_value))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let sampled_count = searcher.search(&AllQuery, &SampleCollector::every_nth(10, Count))?;
+/// assert_eq!(sampled_count.fruit, 100);
+/// assert_eq!(sampled_count.sampling_rate, 0.1);
+/// assert_eq!(sampled_count.num_matches, 1_000);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SampleCollector<TCollector> {
+    sampling: Sampling,
+    collector: TCollector,
+}
+
+impl<TCollector: Collector> SampleCollector<TCollector> {
+    /// Creates a collector passing the first matching document of each segment, and then one
+    /// every `n`, on to `collector`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn every_nth(n: u64, collector: TCollector) -> SampleCollector<TCollector> {
+        assert!(n >= 1, "The sampling interval must be greater than 0.");
+        SampleCollector {
+            sampling: Sampling::EveryNth(n),
+            collector,
+        }
+    }
+
+    /// Creates a collector passing the documents whose hashed value of the single-valued fast
+    /// field `field` falls within the fraction `sampling_rate` of the hashes on to `collector`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sampling rate is not within `]0, 1]`.
+    pub fn hashed(
+        field: Field,
+        sampling_rate: f64,
+        collector: TCollector,
+    ) -> SampleCollector<TCollector> {
+        assert!(
+            sampling_rate > 0.0 && sampling_rate <= 1.0,
+            "The sampling rate should be within ]0, 1]."
+        );
+        SampleCollector {
+            sampling: Sampling::Hashed {
+                field,
+                threshold: (sampling_rate * (1u64 << 32) as f64) as u64,
+            },
+            collector,
+        }
+    }
+
+    fn sampling_rate(&self) -> f64 {
+        match self.sampling {
+            Sampling::EveryNth(n) => 1.0 / n as f64,
+            Sampling::Hashed { threshold, .. } => threshold as f64 / (1u64 << 32) as f64,
+        }
+    }
+}
+
+/// Fruit of a [`SampleCollector`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sampled<TFruit> {
+    /// Fruit of the wrapped collector, computed over the sampled documents.
+    pub fruit: TFruit,
+    /// Expected fraction of the matching documents that are sampled.
+    pub sampling_rate: f64,
+    /// Number of matching documents.
+    pub num_matches: u64,
+    /// Number of sampled documents.
+    pub num_sampled: u64,
+}
+
+impl<TCollector: Collector> Collector for SampleCollector<TCollector> {
+    type Fruit = Sampled<TCollector::Fruit>;
+    type Child = SampleSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<SampleSegmentCollector<TCollector::Child>> {
+        let key_reader_opt = match self.sampling {
+            Sampling::EveryNth(_) => None,
+            Sampling::Hashed { field, .. } => {
+                let field_entry = segment.schema().get_field_entry(field);
+                let cardinality_opt = match field_entry.field_type() {
+                    FieldType::Str(_) | FieldType::Facet(_) => None,
+                    field_type => {
+                        type_and_cardinality(field_type).map(|(_, cardinality)| cardinality)
+                    }
+                };
+                if cardinality_opt != Some(Cardinality::SingleValue) {
+                    return Err(TantivyError::SchemaError(format!(
+                        "Field {:?} is not a single-valued numeric, boolean or date fast field.",
+                        field_entry.name()
+                    )));
+                }
+                Some(segment.fast_fields().u64_lenient(field)?)
+            }
+        };
+        Ok(SampleSegmentCollector {
+            sampling: self.sampling,
+            key_reader_opt,
+            segment_collector: self.collector.for_segment(segment_local_id, segment)?,
+            num_matches: 0,
+            num_sampled: 0,
+            next_sampled_match: 0,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(<TCollector::Child as SegmentCollector>::Fruit, u64, u64)>,
+    ) -> crate::Result<Sampled<TCollector::Fruit>> {
+        let mut num_matches = 0;
+        let mut num_sampled = 0;
+        let mut child_fruits = Vec::with_capacity(segment_fruits.len());
+        for (child_fruit, segment_num_matches, segment_num_sampled) in segment_fruits {
+            child_fruits.push(child_fruit);
+            num_matches += segment_num_matches;
+            num_sampled += segment_num_sampled;
+        }
+        Ok(Sampled {
+            fruit: self.collector.merge_fruits(child_fruits)?,
+            sampling_rate: self.sampling_rate(),
+            num_matches,
+            num_sampled,
+        })
+    }
+}
+
+/// Segment collector of a [`SampleCollector`].
+pub struct SampleSegmentCollector<TSegmentCollector> {
+    sampling: Sampling,
+    key_reader_opt: Option<DynamicFastFieldReader<u64>>,
+    segment_collector: TSegmentCollector,
+    num_matches: u64,
+    num_sampled: u64,
+    // Position among the matches of the next document sampled by `SampleCollector::every_nth`.
+    next_sampled_match: u64,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for SampleSegmentCollector<TSegmentCollector>
+{
+    type Fruit = (TSegmentCollector::Fruit, u64, u64);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let is_sampled = match (&self.sampling, &self.key_reader_opt) {
+            (Sampling::Hashed { threshold, .. }, Some(key_reader)) => {
+                let key = key_reader.get(doc);
+                (murmurhash2(&key.to_le_bytes()) as u64) < *threshold
+            }
+            (Sampling::EveryNth(n), _) => {
+                let is_sampled = self.num_matches == self.next_sampled_match;
+                if is_sampled {
+                    self.next_sampled_match += n;
+                }
+                is_sampled
+            }
+            _ => true,
+        };
+        self.num_matches += 1;
+        if is_sampled {
+            self.num_sampled += 1;
+            self.segment_collector.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        (
+            self.segment_collector.harvest(),
+            self.num_matches,
+            self.num_sampled,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SampleCollector;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, FAST, STRING};
+    use crate::Index;
+
+    #[test]
+    fn test_sample_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let user_id = schema_builder.add_u64_field("user_id", FAST);
+        let text = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..10_000u64 {
+            // Each user has 4 documents.
+            index_writer.add_document(doc!(user_id => i % 2_500))?;
+            if i % 3_000 == 0 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let num_segments = searcher.segment_readers().len() as u64;
+        assert!(num_segments > 1);
+
+        let sampled = searcher.search(&AllQuery, &SampleCollector::every_nth(100, Count))?;
+        assert_eq!(sampled.num_matches, 10_000);
+        assert!(sampled.num_sampled >= 100 && sampled.num_sampled <= 100 + num_segments);
+        assert_eq!(sampled.fruit as u64, sampled.num_sampled);
+
+        let sampled = searcher.search(&AllQuery, &SampleCollector::hashed(user_id, 0.1, Count))?;
+        assert_eq!(sampled.num_matches, 10_000);
+        assert!((sampled.sampling_rate - 0.1).abs() < 1e-6);
+        // All of the documents of a sampled user are sampled.
+        assert_eq!(sampled.num_sampled % 4, 0);
+        assert!((sampled.num_sampled as f64 - 1_000.0).abs() < 200.0);
+        let resampled =
+            searcher.search(&AllQuery, &SampleCollector::hashed(user_id, 0.1, Count))?;
+        assert_eq!(resampled, sampled);
+
+        let sampled_top_docs = searcher.search(
+            &AllQuery,
+            &SampleCollector::hashed(user_id, 1.0, TopDocs::with_limit(3)),
+        )?;
+        assert_eq!(sampled_top_docs.num_sampled, 10_000);
+        assert_eq!(sampled_top_docs.fruit.len(), 3);
+        assert!(searcher
+            .search(&AllQuery, &SampleCollector::hashed(text, 0.5, Count))
+            .is_err());
+        Ok(())
+    }
+}
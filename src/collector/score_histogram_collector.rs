@@ -0,0 +1,244 @@
+use crate::collector::{Collector, SegmentCollector};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// `ScoreHistogramCollector` builds an histogram of the scores of the matching documents.
+///
+/// At construction, it is given parameters that define a partition of an interval
+/// `[min_score, min_score + num_buckets * bucket_width)` into `num_buckets` buckets of the same
+/// width. The ith bucket is then defined by
+/// `[min_score + i * bucket_width, min_score + (i+1) * bucket_width)`.
+///
+/// The distribution of the scores helps picking a score threshold, or normalizing the scores
+/// of a query before mixing them with another ranking.
+///
+/// ```rust
+/// use tantivy::collector::ScoreHistogramCollector;
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+/// let histogram = searcher.search(&query, &ScoreHistogramCollector::new(0.0, 0.5, 4))?;
+/// assert_eq!(histogram.counts.iter().sum::<u64>(), 2);
+/// assert!(histogram.min_score.unwrap() <= histogram.max_score.unwrap());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScoreHistogramCollector {
+    min_score: Score,
+    bucket_width: Score,
+    num_buckets: usize,
+}
+
+impl ScoreHistogramCollector {
+    /// Builds a new `ScoreHistogramCollector`, with `num_buckets` buckets of width `bucket_width`
+    /// starting at `min_score`.
+    ///
+    /// # Panics
+    /// Panics if `bucket_width` is not strictly positive.
+    pub fn new(min_score: Score, bucket_width: Score, num_buckets: usize) -> Self {
+        assert!(
+            bucket_width > 0.0,
+            "The bucket width must be strictly positive."
+        );
+        ScoreHistogramCollector {
+            min_score,
+            bucket_width,
+            num_buckets,
+        }
+    }
+}
+
+/// Histogram of the scores of the matching documents, see [`ScoreHistogramCollector`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScoreHistogram {
+    /// The number of documents in each bucket.
+    pub counts: Vec<u64>,
+    /// The number of documents scoring below the first bucket.
+    pub num_below: u64,
+    /// The number of documents scoring above the last bucket.
+    pub num_above: u64,
+    /// The lowest score, or `None` if no document matched.
+    pub min_score: Option<Score>,
+    /// The highest score, or `None` if no document matched.
+    pub max_score: Option<Score>,
+}
+
+impl ScoreHistogram {
+    fn empty(num_buckets: usize) -> ScoreHistogram {
+        ScoreHistogram {
+            counts: vec![0; num_buckets],
+            ..Default::default()
+        }
+    }
+
+    fn merge(&mut self, other: ScoreHistogram) {
+        assert_eq!(self.counts.len(), other.counts.len());
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts) {
+            *count += other_count;
+        }
+        self.num_below += other.num_below;
+        self.num_above += other.num_above;
+        self.min_score = combine_scores(self.min_score, other.min_score, Score::min);
+        self.max_score = combine_scores(self.max_score, other.max_score, Score::max);
+    }
+}
+
+// Combines two optional scores with `combine`, e.g. `Score::min`.
+fn combine_scores(
+    left: Option<Score>,
+    right: Option<Score>,
+    combine: fn(Score, Score) -> Score,
+) -> Option<Score> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(combine(left, right)),
+        (left, None) => left,
+        (None, right) => right,
+    }
+}
+
+/// Segment collector associated to [`ScoreHistogramCollector`].
+pub struct SegmentScoreHistogramCollector {
+    histogram: ScoreHistogram,
+    min_score: Score,
+    bucket_width: Score,
+}
+
+impl SegmentScoreHistogramCollector {
+    fn add_score(&mut self, score: Score) {
+        if score.is_nan() {
+            return;
+        }
+        self.histogram.min_score =
+            combine_scores(self.histogram.min_score, Some(score), Score::min);
+        self.histogram.max_score =
+            combine_scores(self.histogram.max_score, Some(score), Score::max);
+        if score < self.min_score {
+            self.histogram.num_below += 1;
+            return;
+        }
+        let bucket_id = ((score - self.min_score) / self.bucket_width) as usize;
+        if let Some(count) = self.histogram.counts.get_mut(bucket_id) {
+            *count += 1;
+        } else {
+            self.histogram.num_above += 1;
+        }
+    }
+}
+
+impl SegmentCollector for SegmentScoreHistogramCollector {
+    type Fruit = ScoreHistogram;
+
+    fn collect(&mut self, _doc: DocId, score: Score) {
+        self.add_score(score);
+    }
+
+    fn harvest(self) -> ScoreHistogram {
+        self.histogram
+    }
+}
+
+impl Collector for ScoreHistogramCollector {
+    type Fruit = ScoreHistogram;
+    type Child = SegmentScoreHistogramCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        _segment: &SegmentReader,
+    ) -> crate::Result<SegmentScoreHistogramCollector> {
+        Ok(SegmentScoreHistogramCollector {
+            histogram: ScoreHistogram::empty(self.num_buckets),
+            min_score: self.min_score,
+            bucket_width: self.bucket_width,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_histograms: Vec<ScoreHistogram>,
+    ) -> crate::Result<ScoreHistogram> {
+        let mut histogram = ScoreHistogram::empty(self.num_buckets);
+        for segment_histogram in segment_histograms {
+            histogram.merge(segment_histogram);
+        }
+        Ok(histogram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScoreHistogram, ScoreHistogramCollector, SegmentScoreHistogramCollector};
+    use crate::collector::{Collector, SegmentCollector};
+    use crate::query::{BoostQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, INDEXED};
+    use crate::{Index, Score, Term};
+
+    #[test]
+    fn test_score_histogram_buckets() -> crate::Result<()> {
+        let collector = ScoreHistogramCollector::new(1.0, 0.5, 4);
+        let mut segment_collector = SegmentScoreHistogramCollector {
+            histogram: ScoreHistogram::empty(4),
+            min_score: 1.0,
+            bucket_width: 0.5,
+        };
+        for score in [0.5, 1.0, 1.2, 1.5, 2.9, 3.0, 10.0, Score::NAN] {
+            segment_collector.collect(0, score);
+        }
+        let histogram = collector.merge_fruits(vec![segment_collector.harvest()])?;
+        assert_eq!(
+            histogram,
+            ScoreHistogram {
+                counts: vec![2, 1, 0, 1],
+                num_below: 1,
+                num_above: 2,
+                min_score: Some(0.5),
+                max_score: Some(10.0),
+            }
+        );
+        assert_eq!(
+            collector.merge_fruits(Vec::new())?,
+            ScoreHistogram::empty(4)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_histogram_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..10u64 {
+            index_writer.add_document(doc!(num => i % 2))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let term_query = TermQuery::new(Term::from_field_u64(num, 1), IndexRecordOption::Basic);
+        let histogram = searcher.search(&term_query, &ScoreHistogramCollector::new(0.0, 1.0, 1))?;
+        let term_score = histogram.max_score.unwrap();
+        assert_eq!(histogram.min_score, Some(term_score));
+        let boosted_query: Box<dyn Query> = Box::new(BoostQuery::new(Box::new(term_query), 1.5));
+        let collector = ScoreHistogramCollector::new(0.0, term_score, 3);
+        let histogram = searcher.search(boosted_query.as_ref(), &collector)?;
+        assert_eq!(histogram.counts, vec![0, 5, 0]);
+        assert_eq!(histogram.num_above, 0);
+        Ok(())
+    }
+}
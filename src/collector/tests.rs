@@ -6,7 +6,7 @@ use crate::query::{AllQuery, QueryParser};
 use crate::schema::{Field, Schema, FAST, TEXT};
 use crate::time::format_description::well_known::Rfc3339;
 use crate::time::OffsetDateTime;
-use crate::{doc, DateTime, DocAddress, DocId, Document, Index, Score, Searcher, SegmentOrdinal};
+use crate::{DateTime, DocAddress, DocId, Document, Index, Score, Searcher, SegmentOrdinal};
 
 pub const TEST_COLLECTOR_WITH_SCORE: TestCollector = TestCollector {
     compute_score: true,
@@ -289,3 +289,19 @@ fn test_option_collector_none() -> crate::Result<()> {
     assert_eq!(counts, None);
     Ok(())
 }
+
+#[test]
+fn test_collector_not_requiring_scoring_gets_scores() -> crate::Result<()> {
+    let searcher = make_test_searcher()?;
+    // The collectors which do not accept blocks keep being pushed the scores.
+    let fruit = searcher.search(
+        &AllQuery,
+        &TestCollector {
+            compute_score: false,
+        },
+    )?;
+    assert_eq!(fruit.docs().len(), 2);
+    assert_eq!(fruit.scores(), &[1.0, 1.0]);
+    assert_eq!(searcher.search(&AllQuery, &Count)?, 2);
+    Ok(())
+}
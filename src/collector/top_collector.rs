@@ -1,7 +1,9 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::marker::PhantomData;
+use std::mem;
 
+use super::{allocate, MemoryBudget};
 use crate::{DocAddress, DocId, SegmentOrdinal, SegmentReader};
 
 /// Contains a feature (field, score, etc.) of a document along with the document address.
@@ -56,6 +58,7 @@ impl<T: PartialOrd, D: PartialOrd> Eq for ComparableDoc<T, D> {}
 pub(crate) struct TopCollector<T> {
     pub limit: usize,
     pub offset: usize,
+    pub memory_budget: Option<MemoryBudget>,
     _marker: PhantomData<T>,
 }
 
@@ -71,6 +74,7 @@ where T: PartialOrd + Clone
         Self {
             limit,
             offset: 0,
+            memory_budget: None,
             _marker: PhantomData,
         }
     }
@@ -84,6 +88,20 @@ where T: PartialOrd + Clone
         self
     }
 
+    /// Charges the memory budget for the documents to be collected.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> TopCollector<T> {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// Charges the memory budget, if any, with a heap of `limit + offset` documents.
+    pub(crate) fn allocate_heap<F, D>(&self) -> crate::Result<()> {
+        allocate(
+            &self.memory_budget,
+            (self.limit + self.offset).saturating_mul(mem::size_of::<ComparableDoc<F, D>>()),
+        )
+    }
+
     pub fn merge_fruits(
         &self,
         children: Vec<Vec<(T, DocAddress)>>,
@@ -91,6 +109,7 @@ where T: PartialOrd + Clone
         if self.limit == 0 {
             return Ok(Vec::new());
         }
+        self.allocate_heap::<T, DocAddress>()?;
         let mut top_collector = BinaryHeap::new();
         for child_fruit in children {
             for (feature, doc) in child_fruit {
@@ -115,8 +134,12 @@ where T: PartialOrd + Clone
         &self,
         segment_id: SegmentOrdinal,
         _: &SegmentReader,
-    ) -> TopSegmentCollector<F> {
-        TopSegmentCollector::new(segment_id, self.limit + self.offset)
+    ) -> crate::Result<TopSegmentCollector<F>> {
+        self.allocate_heap::<F, DocId>()?;
+        Ok(TopSegmentCollector::new(
+            segment_id,
+            self.limit + self.offset,
+        ))
     }
 
     /// Create a new TopCollector with the same limit and offset.
@@ -128,6 +151,7 @@ where T: PartialOrd + Clone
         TopCollector {
             limit: self.limit,
             offset: self.offset,
+            memory_budget: self.memory_budget,
             _marker: PhantomData,
         }
     }
@@ -146,7 +170,7 @@ pub(crate) struct TopSegmentCollector<T> {
 }
 
 impl<T: PartialOrd> TopSegmentCollector<T> {
-    fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
+    pub(crate) fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
         TopSegmentCollector {
             limit,
             heap: BinaryHeap::with_capacity(limit),
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::MultiValuedFastFieldReader;
+use crate::schema::{Field, FieldType};
+use crate::{
+    DocAddress, DocId, InvertedIndexReader, Score, SegmentOrdinal, SegmentReader, TantivyError,
+};
+
+// Top documents of a term, by decreasing score.
+type Hits = Vec<(Score, DocAddress)>;
+
+/// Groups the collected documents by the terms of a fast text field, and returns the top
+/// scored documents of each of the most frequent terms.
+///
+/// This is the terms aggregation with a top hits sub-aggregation: "the best 3 products of
+/// each of the 10 most common brands" is computed in a single query.
+///
+/// The buckets are sorted by decreasing number of documents, then by term. A document with
+/// several terms belongs to each of their buckets.
+///
+/// ```rust
+/// use tantivy::collector::TopHitsPerTerm;
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, FAST, STRING, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let brand = schema_builder.add_text_field("brand", STRING | FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "red shoes", brand => "acme"))?;
+/// index_writer.add_document(doc!(title => "red red shoes", brand => "acme"))?;
+/// index_writer.add_document(doc!(title => "red hat", brand => "globex"))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("red")?;
+/// let buckets = searcher.search(&query, &TopHitsPerTerm::new(brand, 10, 1))?;
+/// assert_eq!(buckets.len(), 2);
+/// assert_eq!(buckets[0].term, "acme");
+/// assert_eq!(buckets[0].doc_count, 2);
+/// assert_eq!(buckets[0].hits.len(), 1);
+/// assert_eq!(buckets[1].term, "globex");
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopHitsPerTerm {
+    field: Field,
+    num_terms: usize,
+    top_collector: TopCollector<Score>,
+}
+
+impl TopHitsPerTerm {
+    /// Creates a collector returning the `hits_per_term` top scored documents of each of the
+    /// `num_terms` most frequent terms of `field`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_terms` or `hits_per_term` is 0.
+    pub fn new(field: Field, num_terms: usize, hits_per_term: usize) -> TopHitsPerTerm {
+        assert!(
+            num_terms >= 1,
+            "The number of terms must be greater than 0."
+        );
+        TopHitsPerTerm {
+            field,
+            num_terms,
+            top_collector: TopCollector::with_limit(hits_per_term),
+        }
+    }
+}
+
+/// Top scored documents of a term, returned by the [`TopHitsPerTerm`] collector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermTopHits {
+    /// The term.
+    pub term: String,
+    /// Number of collected documents containing the term.
+    pub doc_count: u64,
+    /// Top scored documents containing the term, by decreasing score.
+    pub hits: Vec<(Score, DocAddress)>,
+}
+
+impl Collector for TopHitsPerTerm {
+    type Fruit = Vec<TermTopHits>;
+    type Child = TopHitsPerTermSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<TopHitsPerTermSegmentCollector> {
+        let field_entry = segment.schema().get_field_entry(self.field);
+        if !matches!(field_entry.field_type(), FieldType::Str(options) if options.is_fast()) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a fast text field.",
+                field_entry.name()
+            )));
+        }
+        Ok(TopHitsPerTermSegmentCollector {
+            segment_ord: segment_local_id,
+            hits_per_term: self.top_collector.limit,
+            term_ords_reader: segment.fast_fields().u64s_lenient(self.field)?,
+            inverted_index: segment.inverted_index(self.field)?,
+            term_ords: Vec::new(),
+            buckets: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_buckets: Vec<HashMap<String, (u64, Hits)>>,
+    ) -> crate::Result<Vec<TermTopHits>> {
+        let mut merged_buckets: HashMap<String, (u64, Vec<Hits>)> = HashMap::new();
+        for buckets in segment_buckets {
+            for (term, (doc_count, hits)) in buckets {
+                let merged_bucket = merged_buckets.entry(term).or_default();
+                merged_bucket.0 += doc_count;
+                merged_bucket.1.push(hits);
+            }
+        }
+        let mut buckets: Vec<(String, u64, Vec<Hits>)> = merged_buckets
+            .into_iter()
+            .map(|(term, (doc_count, hits))| (term, doc_count, hits))
+            .collect();
+        buckets.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+        buckets.truncate(self.num_terms);
+        buckets
+            .into_iter()
+            .map(|(term, doc_count, segment_hits)| {
+                Ok(TermTopHits {
+                    term,
+                    doc_count,
+                    hits: self.top_collector.merge_fruits(segment_hits)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Segment collector of a [`TopHitsPerTerm`] collector.
+pub struct TopHitsPerTermSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    hits_per_term: usize,
+    term_ords_reader: MultiValuedFastFieldReader<u64>,
+    inverted_index: Arc<InvertedIndexReader>,
+    term_ords: Vec<u64>,
+    // Number of documents and top documents, by term ordinal.
+    buckets: HashMap<u64, (u64, TopSegmentCollector<Score>)>,
+}
+
+impl SegmentCollector for TopHitsPerTermSegmentCollector {
+    type Fruit = HashMap<String, (u64, Hits)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.term_ords_reader.get_vals(doc, &mut self.term_ords);
+        for &term_ord in &self.term_ords {
+            let (doc_count, top_segment_collector) =
+                self.buckets.entry(term_ord).or_insert_with(|| {
+                    (
+                        0,
+                        TopSegmentCollector::new(self.segment_ord, self.hits_per_term),
+                    )
+                });
+            *doc_count += 1;
+            top_segment_collector.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let term_dict = self.inverted_index.terms();
+        let mut term_bytes = Vec::new();
+        let mut buckets = HashMap::with_capacity(self.buckets.len());
+        for (term_ord, (doc_count, top_segment_collector)) in self.buckets {
+            // Term ordinals come from the fast field of the same segment.
+            if term_dict.ord_to_term(term_ord, &mut term_bytes).is_ok() {
+                if let Ok(term) = String::from_utf8(term_bytes.clone()) {
+                    buckets.insert(term, (doc_count, top_segment_collector.harvest()));
+                }
+            }
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopHitsPerTerm;
+    use crate::collector::{DocSetCollector, TopDocs};
+    use crate::query::{AllQuery, QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING, TEXT};
+    use crate::{Index, Term};
+
+    #[test]
+    fn test_top_hits_per_term() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let brand = schema_builder.add_text_field("brand", STRING | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..30 {
+            let brand_value = match i % 6 {
+                0..=2 => "acme",
+                3 | 4 => "globex",
+                _ => "initech",
+            };
+            let title_value = "shoes ".repeat(i % 5 + 1);
+            index_writer.add_document(doc!(title => title_value, brand => brand_value))?;
+            if i % 10 == 0 {
+                index_writer.commit()?;
+            }
+        }
+        // A document belonging to two buckets.
+        index_writer.add_document(doc!(title => "shoes", brand => "acme", brand => "globex"))?;
+        index_writer.add_document(doc!(title => "hat", brand => "initech"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert!(searcher.segment_readers().len() > 1);
+
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let buckets = searcher.search(&query, &TopHitsPerTerm::new(brand, 2, 3))?;
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].term, "acme");
+        assert_eq!(buckets[0].doc_count, 16);
+        assert_eq!(buckets[1].term, "globex");
+        assert_eq!(buckets[1].doc_count, 11);
+
+        // The hits of a bucket are the top documents of the query among the documents of the
+        // bucket.
+        let acme_query = TermQuery::new(
+            Term::from_field_text(brand, "acme"),
+            IndexRecordOption::Basic,
+        );
+        let acme_docs = searcher.search(&acme_query, &DocSetCollector)?;
+        let top_doc_scores: Vec<_> = searcher
+            .search(&query, &TopDocs::with_limit(100))?
+            .into_iter()
+            .filter(|(_, doc)| acme_docs.contains(doc))
+            .map(|(score, _)| score)
+            .take(3)
+            .collect();
+        let hit_scores: Vec<_> = buckets[0].hits.iter().map(|(score, _)| *score).collect();
+        assert_eq!(hit_scores, top_doc_scores);
+
+        let buckets = searcher.search(&AllQuery, &TopHitsPerTerm::new(brand, 10, 1))?;
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[2].term, "initech");
+        assert_eq!(buckets[2].doc_count, 6);
+        assert!(searcher
+            .search(&AllQuery, &TopHitsPerTerm::new(title, 10, 1))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// `TopNThen` only forwards the `limit` best scored documents of each segment
+/// to the collector it wraps.
+///
+/// Documents are forwarded in increasing `DocId` order once the segment
+/// has been entirely collected, so wrapped collectors relying on this
+/// ordering keep working.
+///
+/// Note that the limit is applied per segment: the wrapped collector
+/// may see up to `limit` documents for each segment of the searcher.
+///
+/// ```rust
+/// use tantivy::collector::{Count, TopNThen};
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+///
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.add_document(doc!(title => "A Dairy Cow"))?;
+/// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+/// let query = query_parser.parse_query("diary")?;
+/// let count = searcher.search(&query, &TopNThen::new(1, Count))?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopNThen<TCollector> {
+    top_collector: TopCollector<Score>,
+    collector: TCollector,
+}
+
+impl<TCollector: Collector> TopNThen<TCollector> {
+    /// Create a new `TopNThen` forwarding at most `limit` documents
+    /// per segment to `collector`.
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn new(limit: usize, collector: TCollector) -> TopNThen<TCollector> {
+        TopNThen {
+            top_collector: TopCollector::with_limit(limit),
+            collector,
+        }
+    }
+}
+
+impl<TCollector: Collector> Collector for TopNThen<TCollector> {
+    type Fruit = TCollector::Fruit;
+
+    type Child = TopNThenSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let top_segment_collector = self
+            .top_collector
+            .for_segment(segment_local_id, segment_reader)?;
+        let segment_collector = self
+            .collector
+            .for_segment(segment_local_id, segment_reader)?;
+        Ok(TopNThenSegmentCollector {
+            top_segment_collector,
+            segment_collector,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> crate::Result<TCollector::Fruit> {
+        self.collector.merge_fruits(segment_fruits)
+    }
+}
+
+/// Segment collector associated to [`TopNThen`].
+pub struct TopNThenSegmentCollector<TSegmentCollector> {
+    top_segment_collector: TopSegmentCollector<Score>,
+    segment_collector: TSegmentCollector,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for TopNThenSegmentCollector<TSegmentCollector>
+{
+    type Fruit = TSegmentCollector::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.top_segment_collector.collect(doc, score);
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        let mut top_docs: Vec<(DocId, Score)> = self
+            .top_segment_collector
+            .harvest()
+            .into_iter()
+            .map(|(score, doc_address)| (doc_address.doc_id, score))
+            .collect();
+        top_docs.sort_unstable_by_key(|(doc, _)| *doc);
+        for (doc, score) in top_docs {
+            self.segment_collector.collect(doc, score);
+        }
+        self.segment_collector.harvest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopNThen;
+    use crate::collector::tests::TEST_COLLECTOR_WITH_SCORE;
+    use crate::query::{QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_top_n_then_keeps_best_scored_docs_in_doc_order() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "diary of a long long long title"))?;
+        index_writer.add_document(doc!(title => "diary diary"))?;
+        index_writer.add_document(doc!(title => "the wind"))?;
+        index_writer.add_document(doc!(title => "diary"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(title, "diary"),
+            IndexRecordOption::WithFreqs,
+        );
+        let fruit = searcher.search(&query, &TopNThen::new(2, TEST_COLLECTOR_WITH_SCORE))?;
+        assert_eq!(
+            fruit.docs(),
+            &[DocAddress::new(0, 1), DocAddress::new(0, 3)]
+        );
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let no_match_query = query_parser.parse_query("cow")?;
+        let no_match_fruit = searcher.search(
+            &no_match_query,
+            &TopNThen::new(2, TEST_COLLECTOR_WITH_SCORE),
+        )?;
+        assert!(no_match_fruit.docs().is_empty());
+        Ok(())
+    }
+}
@@ -4,14 +4,23 @@ use std::marker::PhantomData;
 
 use super::Collector;
 use crate::collector::custom_score_top_collector::CustomScoreTopCollector;
+use crate::collector::matched_terms_collector::MatchedTermsCollector;
+use crate::collector::rescore_top_collector::RescoreTopCollector;
 use crate::collector::top_collector::{ComparableDoc, TopCollector, TopSegmentCollector};
-use crate::collector::tweak_score_top_collector::TweakedScoreTopCollector;
+use crate::collector::tweak_score_top_collector::{
+    FastFieldScoreTweaker, TweakedScoreTopCollector,
+};
 use crate::collector::{
-    CustomScorer, CustomSegmentScorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector,
+    CountUpTo, CustomScorer, CustomSegmentScorer, FastFieldColumns, HitCount, MatchedTermsHit,
+    MemoryBudget, Rescorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector,
+    SegmentCountUpToCollector,
+};
+use crate::fastfield::{
+    check_fast_value_type, type_and_cardinality, DynamicFastFieldReader, FastFieldReader,
+    FastValue, MultiValueMode, MultiValuedFastFieldReader,
 };
-use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
-use crate::query::Weight;
-use crate::schema::Field;
+use crate::query::{Query, Weight};
+use crate::schema::{Cardinality, Field};
 use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
 struct FastFieldConvertCollector<
@@ -37,24 +46,7 @@ where
         segment_local_id: crate::SegmentOrdinal,
         segment: &SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let schema = segment.schema();
-        let field_entry = schema.get_field_entry(self.field);
-        if !field_entry.is_fast() {
-            return Err(TantivyError::SchemaError(format!(
-                "Field {:?} is not a fast field.",
-                field_entry.name()
-            )));
-        }
-        let schema_type = TFastValue::to_type();
-        let requested_type = field_entry.field_type().value_type();
-        if schema_type != requested_type {
-            return Err(TantivyError::SchemaError(format!(
-                "Field {:?} is of type {:?}!={:?}",
-                field_entry.name(),
-                schema_type,
-                requested_type
-            )));
-        }
+        check_fast_value_type::<TFastValue>(segment.schema().get_field_entry(self.field))?;
         self.collector.for_segment(segment_local_id, segment)
     }
 
@@ -158,6 +150,58 @@ impl CustomScorer<u64> for ScorerByField {
     }
 }
 
+struct ScorerByMultiValuedFastFieldReader<TFastValue: FastValue> {
+    ff_reader: MultiValuedFastFieldReader<TFastValue>,
+    mode: MultiValueMode,
+}
+
+impl<TFastValue: FastValue> CustomSegmentScorer<Option<TFastValue>>
+    for ScorerByMultiValuedFastFieldReader<TFastValue>
+{
+    fn score(&mut self, doc: DocId) -> Option<TFastValue> {
+        self.ff_reader.get_val_with_mode(doc, self.mode)
+    }
+}
+
+struct ScorerByMultiValuedField<TFastValue> {
+    field: Field,
+    mode: MultiValueMode,
+    fast_value: PhantomData<TFastValue>,
+}
+
+impl<TFastValue: FastValue> CustomScorer<Option<TFastValue>>
+    for ScorerByMultiValuedField<TFastValue>
+{
+    type Child = ScorerByMultiValuedFastFieldReader<TFastValue>;
+
+    fn segment_scorer(&self, segment_reader: &SegmentReader) -> crate::Result<Self::Child> {
+        let field_entry = segment_reader.schema().get_field_entry(self.field);
+        let cardinality_opt =
+            type_and_cardinality(field_entry.field_type()).map(|(_, cardinality)| cardinality);
+        if cardinality_opt != Some(Cardinality::MultiValues) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a multivalued fast field.",
+                field_entry.name()
+            )));
+        }
+        check_fast_value_type::<TFastValue>(field_entry)?;
+        let zero = TFastValue::make_zero();
+        if self.mode == MultiValueMode::Sum && zero.sum(&zero).is_none() {
+            return Err(TantivyError::SchemaError(format!(
+                "The values of field {:?} cannot be summed.",
+                field_entry.name()
+            )));
+        }
+        let ff_reader = segment_reader
+            .fast_fields()
+            .typed_fast_field_multi_reader(self.field)?;
+        Ok(ScorerByMultiValuedFastFieldReader {
+            ff_reader,
+            mode: self.mode,
+        })
+    }
+}
+
 impl TopDocs {
     /// Creates a top score collector, with a number of documents equal to "limit".
     ///
@@ -167,6 +211,48 @@ impl TopDocs {
         TopDocs(TopCollector::with_limit(limit))
     }
 
+    /// Creates a collector re-ranking the `window` documents with the highest
+    /// scores using a [`Rescorer`](./trait.Rescorer.html).
+    ///
+    /// The first pass is as cheap as `TopDocs::with_limit(window)`. The `rescorer` is then
+    /// only called on the resulting `window` documents, which are returned sorted by
+    /// decreasing rescored value. This is typically useful to run an expensive learning-to-rank
+    /// model on a handful of candidates.
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, TEXT};
+    /// # use tantivy::{doc, DocAddress, Index, Score};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::TopDocs;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// # let mut schema_builder = Schema::builder();
+    /// # let title = schema_builder.add_text_field("title", TEXT);
+    /// # let index = Index::create_in_ram(schema_builder.build());
+    /// # let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// # index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+    /// # index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+    /// # index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+    /// # index_writer.commit()?;
+    /// # let searcher = index.reader()?.searcher();
+    /// # let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+    /// // An external model would typically be queried here.
+    /// let model_score = |doc_address: DocAddress, _bm25: Score| doc_address.doc_id as Score;
+    /// let top_docs = searcher.search(&query, &TopDocs::with_rescorer(10, model_score))?;
+    /// assert_eq!(top_docs[0].1, DocAddress::new(0, 2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// The method panics if window is 0
+    pub fn with_rescorer<TRescorer: Rescorer>(
+        window: usize,
+        rescorer: TRescorer,
+    ) -> impl Collector<Fruit = Vec<(Score, DocAddress)>> {
+        RescoreTopCollector::new(TopDocs::with_limit(window), rescorer)
+    }
+
     /// Skip the first "offset" documents when collecting.
     ///
     /// This is equivalent to `OFFSET` in MySQL or PostgreSQL and `start` in
@@ -212,6 +298,97 @@ impl TopDocs {
         TopDocs(self.0.and_offset(offset))
     }
 
+    /// Also counts the matching documents, up to `threshold`.
+    ///
+    /// This is the equivalent of a `(TopDocs, CountUpTo)` tuple, except that once a segment
+    /// counted more than `threshold` documents, only the documents that can make it to the
+    /// top documents are visited, as with a lone `TopDocs`.
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, TEXT};
+    /// # use tantivy::{doc, Index};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::{HitCount, TopDocs};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// # let mut schema_builder = Schema::builder();
+    /// # let title = schema_builder.add_text_field("title", TEXT);
+    /// # let index = Index::create_in_ram(schema_builder.build());
+    /// # let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// # index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+    /// # index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+    /// # index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+    /// # index_writer.commit()?;
+    /// # let searcher = index.reader()?.searcher();
+    /// # let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+    /// let (top_docs, hit_count) =
+    ///     searcher.search(&query, &TopDocs::with_limit(1).and_hit_count_up_to(10_000))?;
+    /// assert_eq!(top_docs.len(), 1);
+    /// assert_eq!(hit_count, HitCount::Exact(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn and_hit_count_up_to(
+        self,
+        threshold: usize,
+    ) -> impl Collector<Fruit = (Vec<(Score, DocAddress)>, HitCount)> {
+        TopDocsAndHitCount {
+            top_docs: self,
+            count_up_to: CountUpTo(threshold),
+        }
+    }
+
+    /// Charges `memory_budget` with the heaps keeping the top documents of each segment,
+    /// failing the search if they do not fit in the budget.
+    ///
+    /// The budget also applies to the collectors ordering by a fast field or by a custom score
+    /// built from this one. See [`MemoryBudget`].
+    #[must_use]
+    pub fn with_memory_budget(self, memory_budget: MemoryBudget) -> TopDocs {
+        TopDocs(self.0.with_memory_budget(memory_budget))
+    }
+
+    /// Returns, along with each of the top documents, the terms of `query`
+    /// present in the document.
+    ///
+    /// The query terms are the ones returned by `Query::query_terms`. Each term is checked
+    /// individually: for a phrase query, a document containing all of the terms of the phrase
+    /// will report them, even if it matched for another reason. The fields that matched are
+    /// available through
+    /// [`MatchedTermsHit::matched_fields`](./struct.MatchedTermsHit.html#method.matched_fields).
+    ///
+    /// Only the postings of the top documents are visited, so this does not require
+    /// running the query again for each hit.
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, TEXT};
+    /// # use tantivy::{doc, Index};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::TopDocs;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let tags = schema_builder.add_text_field("tags", TEXT);
+    /// let index = Index::create_in_ram(schema_builder.build());
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "Diary of a cow", tags => "farm"))?;
+    /// index_writer.commit()?;
+    ///
+    /// let searcher = index.reader()?.searcher();
+    /// let query = QueryParser::for_index(&index, vec![title, tags]).parse_query("cow farm")?;
+    /// let hits = searcher.search(&query, &TopDocs::with_limit(10).with_matched_terms(&query))?;
+    /// assert_eq!(hits[0].matched_fields(), vec![title, tags]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_matched_terms(
+        self,
+        query: &dyn Query,
+    ) -> impl Collector<Fruit = Vec<MatchedTermsHit>> {
+        MatchedTermsCollector::new(self.0, query)
+    }
+
     /// Set top-K to rank documents by a given fast field.
     ///
     /// If the field is not a fast or does not exist, this method returns successfully (it is not
@@ -377,6 +554,65 @@ impl TopDocs {
         }
     }
 
+    /// Set top-K to rank documents by a given multivalued fast field.
+    ///
+    /// The values of each document are reduced to a single value as selected by `mode`, e.g.
+    /// the highest value of the document with [`MultiValueMode::Max`]. The documents without
+    /// any value for the field are ranked last, with a `None` value.
+    ///
+    /// If the field is not a multivalued fast field, if its field type does not match the
+    /// generic type, or if `mode` is [`MultiValueMode::Sum`] and its values cannot be summed,
+    /// an explicit error will be returned at the moment of collection.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Cardinality, NumericOptions, Schema, TEXT};
+    /// # use tantivy::{doc, Index, DocAddress};
+    /// # use tantivy::query::AllQuery;
+    /// use tantivy::collector::TopDocs;
+    /// use tantivy::fastfield::MultiValueMode;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let price = schema_builder.add_u64_field(
+    ///     "price",
+    ///     NumericOptions::default().set_fast(Cardinality::MultiValues),
+    /// );
+    /// let index = Index::create_in_ram(schema_builder.build());
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "Dune", price => 12u64, price => 30u64))?;
+    /// index_writer.add_document(doc!(title => "Emma", price => 20u64))?;
+    /// index_writer.commit()?;
+    ///
+    /// let searcher = index.reader()?.searcher();
+    /// let top_docs_by_min_price = TopDocs::with_limit(2)
+    ///     .order_by_multivalued_fast_field::<u64>(price, MultiValueMode::Min);
+    /// let top_docs = searcher.search(&AllQuery, &top_docs_by_min_price)?;
+    /// assert_eq!(
+    ///     top_docs,
+    ///     vec![(Some(20u64), DocAddress::new(0, 1)), (Some(12u64), DocAddress::new(0, 0))]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn order_by_multivalued_fast_field<TFastValue>(
+        self,
+        fast_field: Field,
+        mode: MultiValueMode,
+    ) -> impl Collector<Fruit = Vec<(Option<TFastValue>, DocAddress)>>
+    where
+        TFastValue: FastValue,
+    {
+        let scorer = ScorerByMultiValuedField {
+            field: fast_field,
+            mode,
+            fast_value: PhantomData,
+        };
+        CustomScoreTopCollector::new(scorer, self.0.into_tscore())
+    }
+
     /// Ranks the documents using a custom score.
     ///
     /// This method offers a convenient way to tweak or replace
@@ -485,6 +721,66 @@ impl TopDocs {
         TweakedScoreTopCollector::new(score_tweaker, self.0.into_tscore())
     }
 
+    /// Ranks the documents using a tweaked score computed from several fast fields.
+    ///
+    /// This is a variant of [`tweak_score(...)`](#method.tweak_score) for which the fast field
+    /// columns are declared up front. The readers over these columns are opened for
+    /// each segment, and handed to `tweak` along with the document id and its original score.
+    ///
+    /// Columns are declared with [`FastFieldColumn`](./struct.FastFieldColumn.html), or with
+    /// a tuple of up to 4 of them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, FAST, TEXT};
+    /// # use tantivy::{doc, Index, DocAddress, DocId, Score};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::{FastFieldColumn, TopDocs};
+    /// use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let product_name = schema_builder.add_text_field("product_name", TEXT);
+    /// let popularity = schema_builder.add_u64_field("popularity", FAST);
+    /// let rating = schema_builder.add_f64_field("rating", FAST);
+    /// let index = Index::create_in_ram(schema_builder.build());
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(product_name => "The Diary of Muadib", popularity => 1u64, rating => 4.5f64))?;
+    /// index_writer.add_document(doc!(product_name => "The Diary of a Young Girl", popularity => 15u64, rating => 2.0f64))?;
+    /// index_writer.commit()?;
+    ///
+    /// let query_parser = QueryParser::for_index(&index, vec![product_name]);
+    /// let query = query_parser.parse_query("diary")?;
+    /// let top_docs_by_custom_score = TopDocs::with_limit(10).tweak_score_with_fast_fields(
+    ///     (FastFieldColumn::<u64>::new(popularity), FastFieldColumn::<f64>::new(rating)),
+    ///     |(popularity_reader, rating_reader): &(DynamicFastFieldReader<u64>, DynamicFastFieldReader<f64>),
+    ///      doc: DocId,
+    ///      original_score: Score| {
+    ///         let popularity_boost = ((2u64 + popularity_reader.get(doc)) as Score).log2();
+    ///         popularity_boost * rating_reader.get(doc) as Score * original_score
+    ///     },
+    /// );
+    /// let searcher = index.reader()?.searcher();
+    /// let resulting_docs: Vec<(Score, DocAddress)> =
+    ///     searcher.search(&query, &top_docs_by_custom_score)?;
+    /// assert_eq!(resulting_docs[0].1, DocAddress::new(0, 0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tweak_score_with_fast_fields<TScore, TColumns, TTweak>(
+        self,
+        columns: TColumns,
+        tweak: TTweak,
+    ) -> impl Collector<Fruit = Vec<(TScore, DocAddress)>>
+    where
+        TScore: 'static + Send + Sync + Clone + PartialOrd,
+        TColumns: FastFieldColumns,
+        TTweak: 'static + Fn(&TColumns::Readers, DocId, Score) -> TScore + Send + Sync,
+    {
+        self.tweak_score(FastFieldScoreTweaker::new(columns, tweak))
+    }
+
     /// Ranks the documents using a custom score.
     ///
     /// This method offers a convenient way to use a different score.
@@ -611,7 +907,7 @@ impl Collector for TopDocs {
         segment_local_id: SegmentOrdinal,
         reader: &SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let collector = self.0.for_segment(segment_local_id, reader);
+        let collector = self.0.for_segment(segment_local_id, reader)?;
         Ok(TopScoreSegmentCollector(collector))
     }
 
@@ -632,49 +928,61 @@ impl Collector for TopDocs {
         segment_ord: u32,
         reader: &SegmentReader,
     ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let (fruit, _) = self.collect_segment_and_count(weight, segment_ord, reader, 0)?;
+        Ok(fruit)
+    }
+}
+
+impl TopDocs {
+    /// Collects the top documents of the segment, along with the number of matching
+    /// documents up to `max_count`.
+    ///
+    /// Until `max_count` documents were counted, all of the documents are visited. The scorer
+    /// is then free to skip the documents that cannot make it to the top documents.
+    fn collect_segment_and_count(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+        max_count: usize,
+    ) -> crate::Result<(Vec<(Score, DocAddress)>, usize)> {
+        self.0.allocate_heap::<Score, DocId>()?;
         let heap_len = self.0.limit + self.0.offset;
         let mut heap: BinaryHeap<ComparableDoc<Score, DocId>> = BinaryHeap::with_capacity(heap_len);
-
-        if let Some(alive_bitset) = reader.alive_bitset() {
-            let mut threshold = Score::MIN;
-            weight.for_each_pruning(threshold, reader, &mut |doc, score| {
-                if alive_bitset.is_deleted(doc) {
-                    return threshold;
+        let alive_bitset_opt = reader.alive_bitset();
+        let mut count = 0;
+        // Lowest score of the heap once full, that documents need to beat to enter it.
+        let mut heap_threshold = Score::MIN;
+        weight.for_each_pruning(Score::MIN, reader, &mut |doc, score| {
+            if alive_bitset_opt
+                .map(|alive_bitset| alive_bitset.is_alive(doc))
+                .unwrap_or(true)
+            {
+                if count < max_count {
+                    count += 1;
                 }
-                let heap_item = ComparableDoc {
-                    feature: score,
-                    doc,
-                };
                 if heap.len() < heap_len {
-                    heap.push(heap_item);
+                    heap.push(ComparableDoc {
+                        feature: score,
+                        doc,
+                    });
                     if heap.len() == heap_len {
-                        threshold = heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
+                        heap_threshold = heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
                     }
-                    return threshold;
+                } else if score > heap_threshold {
+                    *heap.peek_mut().unwrap() = ComparableDoc {
+                        feature: score,
+                        doc,
+                    };
+                    heap_threshold = heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
                 }
-                *heap.peek_mut().unwrap() = heap_item;
-                threshold = heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
-                threshold
-            })?;
-        } else {
-            weight.for_each_pruning(Score::MIN, reader, &mut |doc, score| {
-                let heap_item = ComparableDoc {
-                    feature: score,
-                    doc,
-                };
-                if heap.len() < heap_len {
-                    heap.push(heap_item);
-                    // TODO the threshold is suboptimal for heap.len == heap_len
-                    if heap.len() == heap_len {
-                        return heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
-                    } else {
-                        return Score::MIN;
-                    }
-                }
-                *heap.peek_mut().unwrap() = heap_item;
-                heap.peek().map(|el| el.feature).unwrap_or(Score::MIN)
-            })?;
-        }
+            }
+            if count < max_count {
+                Score::MIN
+            } else {
+                heap_threshold
+            }
+        })?;
 
         let fruit = heap
             .into_sorted_vec()
@@ -689,7 +997,55 @@ impl Collector for TopDocs {
                 )
             })
             .collect();
-        Ok(fruit)
+        Ok((fruit, count))
+    }
+}
+
+struct TopDocsAndHitCount {
+    top_docs: TopDocs,
+    count_up_to: CountUpTo,
+}
+
+impl Collector for TopDocsAndHitCount {
+    type Fruit = (Vec<(Score, DocAddress)>, HitCount);
+
+    type Child = (TopScoreSegmentCollector, SegmentCountUpToCollector);
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        Ok((
+            self.top_docs.for_segment(segment_local_id, reader)?,
+            self.count_up_to.for_segment(segment_local_id, reader)?,
+        ))
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        child_fruits: Vec<(Vec<(Score, DocAddress)>, usize)>,
+    ) -> crate::Result<Self::Fruit> {
+        let (top_docs_fruits, counts): (Vec<_>, Vec<_>) = child_fruits.into_iter().unzip();
+        Ok((
+            self.top_docs.merge_fruits(top_docs_fruits)?,
+            self.count_up_to.merge_fruits(counts)?,
+        ))
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<(Vec<(Score, DocAddress)>, usize)> {
+        let max_count = self.count_up_to.0.saturating_add(1);
+        self.top_docs
+            .collect_segment_and_count(weight, segment_ord, reader, max_count)
     }
 }
 
@@ -711,12 +1067,15 @@ impl SegmentCollector for TopScoreSegmentCollector {
 #[cfg(test)]
 mod tests {
     use super::TopDocs;
-    use crate::collector::Collector;
+    use crate::collector::{Collector, FastFieldColumn, HitCount, MemoryBudget};
+    use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, MultiValueMode};
     use crate::query::{AllQuery, Query, QueryParser};
-    use crate::schema::{Field, Schema, FAST, STORED, TEXT};
+    use crate::schema::{Cardinality, Field, NumericOptions, Schema, FAST, STORED, TEXT};
     use crate::time::format_description::well_known::Rfc3339;
     use crate::time::OffsetDateTime;
-    use crate::{DateTime, DocAddress, DocId, Index, IndexWriter, Score, SegmentReader};
+    use crate::{
+        DateTime, DocAddress, DocId, Index, IndexWriter, Score, SegmentReader, TantivyError,
+    };
 
     fn make_index() -> crate::Result<Index> {
         let mut schema_builder = Schema::builder();
@@ -760,6 +1119,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_top_collector_memory_budget() -> crate::Result<()> {
+        let index = make_index()?;
+        let field = index.schema().get_field("text").unwrap();
+        let query_parser = QueryParser::for_index(&index, vec![field]);
+        let text_query = query_parser.parse_query("droopy tax")?;
+        let searcher = index.reader()?.searcher();
+        let memory_budget = MemoryBudget::with_limit(1_000);
+        let top_docs = TopDocs::with_limit(4).with_memory_budget(memory_budget.clone());
+        assert_eq!(searcher.search(&text_query, &top_docs)?.len(), 3);
+        assert!(memory_budget.used_in_bytes() > 0);
+
+        let top_docs =
+            TopDocs::with_limit(1_000).with_memory_budget(MemoryBudget::with_limit(1_000));
+        let err = searcher.search(&text_query, &top_docs).unwrap_err();
+        assert!(matches!(err, TantivyError::MemoryLimitExceeded(_)));
+        let top_docs_by_score = TopDocs::with_limit(1_000)
+            .with_memory_budget(MemoryBudget::with_limit(1_000))
+            .tweak_score(|_: &SegmentReader| |_doc: DocId, score: Score| score);
+        let err = searcher
+            .search(&text_query, &top_docs_by_score)
+            .unwrap_err();
+        assert!(matches!(err, TantivyError::MemoryLimitExceeded(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_and_hit_count_up_to() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        for i in 0..1_000 {
+            let text = match i % 7 {
+                0 => "droopy tax",
+                1 | 2 => "happy tax payer says droopy",
+                _ => "happy tax payer",
+            };
+            index_writer.add_document(doc!(text_field => text))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![text_field]).parse_query("droopy tax")?;
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(5))?;
+
+        let (top_docs_with_count, hit_count) =
+            searcher.search(&query, &TopDocs::with_limit(5).and_hit_count_up_to(10))?;
+        assert_eq!(top_docs_with_count, top_docs);
+        assert_eq!(hit_count, HitCount::AtLeast(10));
+        let (top_docs_with_count, hit_count) =
+            searcher.search(&query, &TopDocs::with_limit(5).and_hit_count_up_to(10_000))?;
+        assert_eq!(top_docs_with_count, top_docs);
+        assert_eq!(hit_count, HitCount::Exact(1_000));
+        Ok(())
+    }
+
     #[test]
     fn test_top_collector_not_at_capacity_with_offset() {
         let index = make_index().unwrap();
@@ -928,6 +1343,63 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_top_field_collector_multivalued() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let multi_values = NumericOptions::default().set_fast(Cardinality::MultiValues);
+        let score = schema_builder.add_i64_field("score", multi_values.clone());
+        let date = schema_builder.add_date_field("date", multi_values);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(score => 5i64, score => -3i64))?;
+        index_writer.add_document(doc!())?;
+        index_writer.add_document(doc!(score => 3i64, score => 4i64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs_with_mode = |mode| {
+            let top_collector =
+                TopDocs::with_limit(3).order_by_multivalued_fast_field::<i64>(score, mode);
+            searcher.search(&AllQuery, &top_collector)
+        };
+        assert_eq!(
+            top_docs_with_mode(MultiValueMode::Max)?,
+            vec![
+                (Some(5i64), DocAddress::new(0, 0)),
+                (Some(4i64), DocAddress::new(0, 2)),
+                (None, DocAddress::new(0, 1)),
+            ]
+        );
+        assert_eq!(
+            top_docs_with_mode(MultiValueMode::Min)?,
+            vec![
+                (Some(3i64), DocAddress::new(0, 2)),
+                (Some(-3i64), DocAddress::new(0, 0)),
+                (None, DocAddress::new(0, 1)),
+            ]
+        );
+        assert_eq!(
+            top_docs_with_mode(MultiValueMode::Sum)?,
+            vec![
+                (Some(7i64), DocAddress::new(0, 2)),
+                (Some(2i64), DocAddress::new(0, 0)),
+                (None, DocAddress::new(0, 1)),
+            ]
+        );
+        let date_sum_collector = TopDocs::with_limit(3)
+            .order_by_multivalued_fast_field::<DateTime>(date, MultiValueMode::Sum);
+        assert!(matches!(
+            searcher.search(&AllQuery, &date_sum_collector),
+            Err(TantivyError::SchemaError(_))
+        ));
+        let wrong_type_collector = TopDocs::with_limit(3)
+            .order_by_multivalued_fast_field::<u64>(score, MultiValueMode::Max);
+        assert!(matches!(
+            searcher.search(&AllQuery, &wrong_type_collector),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_top_field_collector_i64() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -1028,6 +1500,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_tweak_score_with_fast_fields() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let size = schema_builder.add_u64_field(SIZE, FAST);
+        let weight = schema_builder.add_i64_field("weight", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(size=>1u64, weight=>10i64))?;
+        index_writer.add_document(doc!(size=>5u64, weight=>-10i64))?;
+        index_writer.add_document(doc!(size=>3u64, weight=>0i64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector = TopDocs::with_limit(2).tweak_score_with_fast_fields(
+            (
+                FastFieldColumn::<u64>::new(size),
+                FastFieldColumn::<i64>::new(weight),
+            ),
+            |(size_reader, weight_reader): &(
+                DynamicFastFieldReader<u64>,
+                DynamicFastFieldReader<i64>,
+            ),
+             doc: DocId,
+             score: Score| {
+                size_reader.get(doc) as i64 + weight_reader.get(doc) + score as i64
+            },
+        );
+        let top_docs = searcher.search(&AllQuery, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![(12, DocAddress::new(0, 0)), (4, DocAddress::new(0, 2))]
+        );
+
+        let wrong_type_collector = TopDocs::with_limit(2).tweak_score_with_fast_fields(
+            FastFieldColumn::<i64>::new(size),
+            |_: &DynamicFastFieldReader<i64>, _: DocId, score: Score| score,
+        );
+        let err = searcher
+            .search(&AllQuery, &wrong_type_collector)
+            .unwrap_err();
+        assert!(matches!(err, crate::TantivyError::SchemaError(_)));
+        Ok(())
+    }
+
     #[test]
     fn test_field_wrong_type() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
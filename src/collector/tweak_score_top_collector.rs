@@ -1,5 +1,10 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
 use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
 use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{check_fast_value_type, DynamicFastFieldReader, FastValue};
+use crate::schema::Field;
 use crate::{DocAddress, DocId, Result, Score, SegmentReader};
 
 pub(crate) struct TweakedScoreTopCollector<TScoreTweaker, TScore = Score> {
@@ -60,7 +65,9 @@ where
         segment_reader: &SegmentReader,
     ) -> Result<Self::Child> {
         let segment_scorer = self.score_tweaker.segment_tweaker(segment_reader)?;
-        let segment_collector = self.collector.for_segment(segment_local_id, segment_reader);
+        let segment_collector = self
+            .collector
+            .for_segment(segment_local_id, segment_reader)?;
         Ok(TopTweakedScoreSegmentCollector {
             segment_collector,
             segment_scorer,
@@ -122,3 +129,108 @@ where F: 'static + FnMut(DocId, Score) -> TScore
         (self)(doc, score)
     }
 }
+
+/// A set of fast field columns, declared up front, and opened for each segment by
+/// [`TopDocs::tweak_score_with_fast_fields`](crate::collector::TopDocs::tweak_score_with_fast_fields).
+///
+/// It is implemented by [`FastFieldColumn`] and by tuples of up to 4 `FastFieldColumns`.
+pub trait FastFieldColumns: Send + Sync {
+    /// Readers over the columns for a given segment.
+    type Readers: 'static;
+
+    /// Opens the readers of the columns for a specific segment.
+    fn open_readers(&self, segment_reader: &SegmentReader) -> Result<Self::Readers>;
+}
+
+/// A single valued fast field column of type `TFastValue`.
+pub struct FastFieldColumn<TFastValue> {
+    field: Field,
+    _marker: PhantomData<TFastValue>,
+}
+
+impl<TFastValue: FastValue> FastFieldColumn<TFastValue> {
+    /// Declares the fast field column associated to `field`.
+    pub fn new(field: Field) -> FastFieldColumn<TFastValue> {
+        FastFieldColumn {
+            field,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<TFastValue: FastValue> FastFieldColumns for FastFieldColumn<TFastValue> {
+    type Readers = DynamicFastFieldReader<TFastValue>;
+
+    fn open_readers(&self, segment_reader: &SegmentReader) -> Result<Self::Readers> {
+        check_fast_value_type::<TFastValue>(segment_reader.schema().get_field_entry(self.field))?;
+        segment_reader
+            .fast_fields()
+            .typed_fast_field_reader(self.field)
+    }
+}
+
+macro_rules! impl_fast_field_columns_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: FastFieldColumns),+> FastFieldColumns for ($($name,)+) {
+            type Readers = ($($name::Readers,)+);
+
+            #[allow(non_snake_case)]
+            fn open_readers(&self, segment_reader: &SegmentReader) -> Result<Self::Readers> {
+                let ($($name,)+) = self;
+                Ok(($($name.open_readers(segment_reader)?,)+))
+            }
+        }
+    };
+}
+
+impl_fast_field_columns_for_tuple!(One);
+impl_fast_field_columns_for_tuple!(One, Two);
+impl_fast_field_columns_for_tuple!(One, Two, Three);
+impl_fast_field_columns_for_tuple!(One, Two, Three, Four);
+
+pub(crate) struct FastFieldScoreTweaker<TColumns, TTweak> {
+    columns: TColumns,
+    tweak: Arc<TTweak>,
+}
+
+impl<TColumns, TTweak> FastFieldScoreTweaker<TColumns, TTweak> {
+    pub fn new(columns: TColumns, tweak: TTweak) -> FastFieldScoreTweaker<TColumns, TTweak> {
+        FastFieldScoreTweaker {
+            columns,
+            tweak: Arc::new(tweak),
+        }
+    }
+}
+
+impl<TColumns, TTweak, TScore> ScoreTweaker<TScore> for FastFieldScoreTweaker<TColumns, TTweak>
+where
+    TColumns: FastFieldColumns,
+    TTweak: 'static + Fn(&TColumns::Readers, DocId, Score) -> TScore + Send + Sync,
+{
+    type Child = FastFieldSegmentScoreTweaker<TColumns::Readers, TTweak>;
+
+    fn segment_tweaker(&self, segment_reader: &SegmentReader) -> Result<Self::Child> {
+        Ok(FastFieldSegmentScoreTweaker {
+            readers: self.columns.open_readers(segment_reader)?,
+            tweak: self.tweak.clone(),
+        })
+    }
+}
+
+/// Segment level tweaker built by
+/// [`TopDocs::tweak_score_with_fast_fields`](crate::collector::TopDocs::tweak_score_with_fast_fields).
+pub struct FastFieldSegmentScoreTweaker<TReaders, TTweak> {
+    readers: TReaders,
+    tweak: Arc<TTweak>,
+}
+
+impl<TReaders, TTweak, TScore> ScoreSegmentTweaker<TScore>
+    for FastFieldSegmentScoreTweaker<TReaders, TTweak>
+where
+    TReaders: 'static,
+    TTweak: 'static + Fn(&TReaders, DocId, Score) -> TScore,
+{
+    fn score(&mut self, doc: DocId, score: Score) -> TScore {
+        (self.tweak)(&self.readers, doc, score)
+    }
+}
@@ -0,0 +1,409 @@
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, TimestampMicrosecondType,
+    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    Array, ArrayRef, ArrowPrimitiveType, BinaryArray, BooleanArray, Float64Array, Int64Array,
+    RecordBatch, StringArray, TimestampSecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit};
+
+use super::{export_batches, Column, ColumnBatch};
+use crate::query::Query;
+use crate::schema::{Document, Facet, Field, FieldEntry, FieldType, Value};
+use crate::{DateTime, Searcher, TantivyError};
+
+impl Column {
+    /// Converts the column to an Arrow array.
+    ///
+    /// Dates are converted to timestamps in seconds, in the UTC timezone.
+    pub fn to_arrow_array(&self) -> ArrayRef {
+        match self {
+            Column::U64(values) => Arc::new(values.iter().collect::<UInt64Array>()),
+            Column::I64(values) => Arc::new(values.iter().collect::<Int64Array>()),
+            Column::F64(values) => Arc::new(values.iter().collect::<Float64Array>()),
+            Column::Bool(values) => Arc::new(values.iter().collect::<BooleanArray>()),
+            Column::Date(values) => {
+                let timestamps = values
+                    .iter()
+                    .map(|date_opt| date_opt.map(DateTime::into_unix_timestamp))
+                    .collect::<TimestampSecondArray>();
+                Arc::new(timestamps.with_timezone("UTC"))
+            }
+            Column::Str(values) => {
+                Arc::new(values.iter().map(Option::as_deref).collect::<StringArray>())
+            }
+            Column::Bytes(values) => {
+                Arc::new(values.iter().map(Option::as_deref).collect::<BinaryArray>())
+            }
+        }
+    }
+
+    /// Converts an Arrow array to a column.
+    ///
+    /// Integers and floats are widened to 64 bits, timestamps are truncated to seconds, and
+    /// large strings and binaries are accepted.
+    ///
+    /// Returns `TantivyError::InvalidArgument` if the data type of the array has no
+    /// matching column type.
+    pub fn from_arrow_array(array: &dyn Array) -> crate::Result<Column> {
+        if let Some(values) = unsigned_values(array) {
+            return Ok(Column::U64(values.collect()));
+        }
+        if let Some(values) = signed_values(array) {
+            return Ok(Column::I64(values.collect()));
+        }
+        if let Some(values) = float_values(array) {
+            return Ok(Column::F64(values.collect()));
+        }
+        if let Some(values) = bool_values(array) {
+            return Ok(Column::Bool(values.collect()));
+        }
+        if let Some(values) = date_values(array) {
+            return Ok(Column::Date(values.collect()));
+        }
+        if let Some(values) = str_values(array) {
+            let values = values.map(|text_opt| text_opt.map(str::to_string));
+            return Ok(Column::Str(values.collect()));
+        }
+        if let Some(values) = bytes_values(array) {
+            let values = values.map(|bytes_opt| bytes_opt.map(<[u8]>::to_vec));
+            return Ok(Column::Bytes(values.collect()));
+        }
+        Err(TantivyError::InvalidArgument(format!(
+            "Arrow arrays of type {} cannot be converted to a column.",
+            array.data_type()
+        )))
+    }
+}
+
+/// Values of an Arrow array, `None` marking the null values.
+type Values<'a, T> = Box<dyn Iterator<Item = Option<T>> + 'a>;
+
+fn primitive_values<T: ArrowPrimitiveType, V: 'static>(
+    array: &dyn Array,
+    convert: fn(T::Native) -> V,
+) -> Values<'_, V> {
+    Box::new(
+        array
+            .as_primitive::<T>()
+            .iter()
+            .map(move |val_opt| val_opt.map(convert)),
+    )
+}
+
+fn unsigned_values(array: &dyn Array) -> Option<Values<'_, u64>> {
+    let values = match array.data_type() {
+        DataType::UInt8 => primitive_values::<UInt8Type, _>(array, u64::from),
+        DataType::UInt16 => primitive_values::<UInt16Type, _>(array, u64::from),
+        DataType::UInt32 => primitive_values::<UInt32Type, _>(array, u64::from),
+        DataType::UInt64 => primitive_values::<UInt64Type, _>(array, |val| val),
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn signed_values(array: &dyn Array) -> Option<Values<'_, i64>> {
+    let values = match array.data_type() {
+        DataType::Int8 => primitive_values::<Int8Type, _>(array, i64::from),
+        DataType::Int16 => primitive_values::<Int16Type, _>(array, i64::from),
+        DataType::Int32 => primitive_values::<Int32Type, _>(array, i64::from),
+        DataType::Int64 => primitive_values::<Int64Type, _>(array, |val| val),
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn float_values(array: &dyn Array) -> Option<Values<'_, f64>> {
+    let values = match array.data_type() {
+        DataType::Float32 => primitive_values::<Float32Type, _>(array, f64::from),
+        DataType::Float64 => primitive_values::<Float64Type, _>(array, |val| val),
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn bool_values(array: &dyn Array) -> Option<Values<'_, bool>> {
+    match array.data_type() {
+        DataType::Boolean => Some(Box::new(array.as_boolean().iter())),
+        _ => None,
+    }
+}
+
+fn date_values(array: &dyn Array) -> Option<Values<'_, DateTime>> {
+    let values = match array.data_type() {
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            primitive_values::<TimestampSecondType, _>(array, DateTime::from_unix_timestamp)
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            primitive_values::<TimestampMillisecondType, _>(array, |timestamp| {
+                DateTime::from_unix_timestamp(timestamp.div_euclid(1_000))
+            })
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            primitive_values::<TimestampMicrosecondType, _>(array, |timestamp| {
+                DateTime::from_unix_timestamp(timestamp.div_euclid(1_000_000))
+            })
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            primitive_values::<TimestampNanosecondType, _>(array, |timestamp| {
+                DateTime::from_unix_timestamp(timestamp.div_euclid(1_000_000_000))
+            })
+        }
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn str_values(array: &dyn Array) -> Option<Values<'_, &str>> {
+    match array.data_type() {
+        DataType::Utf8 => Some(Box::new(array.as_string::<i32>().iter())),
+        DataType::LargeUtf8 => Some(Box::new(array.as_string::<i64>().iter())),
+        _ => None,
+    }
+}
+
+fn bytes_values(array: &dyn Array) -> Option<Values<'_, &[u8]>> {
+    match array.data_type() {
+        DataType::Binary => Some(Box::new(array.as_binary::<i32>().iter())),
+        DataType::LargeBinary => Some(Box::new(array.as_binary::<i64>().iter())),
+        _ => None,
+    }
+}
+
+/// Adds the values of `array` to `documents`, the value of each row to the document of the
+/// same rank, converted to the type of `field`.
+///
+/// The type of the array is matched against the type of the field once for the whole array.
+/// Strings are parsed for facet and json object fields.
+///
+/// Returns `TantivyError::SchemaError` if the values of the array cannot be indexed in the
+/// field, and `TantivyError::InvalidArgument` if a string cannot be parsed.
+pub(crate) fn add_array_to_documents(
+    array: &dyn Array,
+    field: Field,
+    field_entry: &FieldEntry,
+    documents: &mut [Document],
+) -> crate::Result<()> {
+    let added_opt = match field_entry.field_type() {
+        FieldType::U64(_) => unsigned_values(array)
+            .map(|values| add_values(values, field, documents, |val| Ok(Value::U64(val)))),
+        FieldType::I64(_) => signed_values(array)
+            .map(|values| add_values(values, field, documents, |val| Ok(Value::I64(val)))),
+        FieldType::F64(_) => float_values(array)
+            .map(|values| add_values(values, field, documents, |val| Ok(Value::F64(val)))),
+        FieldType::Bool(_) => bool_values(array)
+            .map(|values| add_values(values, field, documents, |val| Ok(Value::Bool(val)))),
+        FieldType::Date(_) => date_values(array)
+            .map(|values| add_values(values, field, documents, |val| Ok(Value::Date(val)))),
+        FieldType::Str(_) => str_values(array).map(|values| {
+            add_values(values, field, documents, |text| {
+                Ok(Value::Str(text.to_string()))
+            })
+        }),
+        FieldType::Facet(_) => str_values(array).map(|values| {
+            add_values(values, field, documents, |text| {
+                let facet = Facet::from_text(text)
+                    .map_err(|err| TantivyError::InvalidArgument(err.to_string()))?;
+                Ok(Value::Facet(facet))
+            })
+        }),
+        FieldType::JsonObject(_) => str_values(array).map(|values| {
+            add_values(values, field, documents, |text| {
+                let json_object = serde_json::from_str(text).map_err(|err| {
+                    TantivyError::InvalidArgument(format!(
+                        "Invalid json object {:?}: {}",
+                        text, err
+                    ))
+                })?;
+                Ok(Value::JsonObject(json_object))
+            })
+        }),
+        FieldType::Bytes(_) => bytes_values(array).map(|values| {
+            add_values(values, field, documents, |bytes| {
+                Ok(Value::Bytes(bytes.to_vec()))
+            })
+        }),
+    };
+    added_opt.unwrap_or_else(|| {
+        Err(TantivyError::SchemaError(format!(
+            "Arrow arrays of type {} cannot be indexed in field {:?} of type {:?}.",
+            array.data_type(),
+            field_entry.name(),
+            field_entry.field_type().value_type()
+        )))
+    })
+}
+
+fn add_values<T>(
+    values: Values<'_, T>,
+    field: Field,
+    documents: &mut [Document],
+    convert: impl Fn(T) -> crate::Result<Value>,
+) -> crate::Result<()> {
+    for (document, val_opt) in documents.iter_mut().zip(values) {
+        if let Some(val) = val_opt {
+            document.add_field_value(field, convert(val)?);
+        }
+    }
+    Ok(())
+}
+
+impl ColumnBatch {
+    /// Converts the batch to an Arrow record batch, with one nullable Arrow field per column.
+    ///
+    /// See [`Column::to_arrow_array`] for the conversion of the columns.
+    pub fn to_record_batch(&self) -> crate::Result<RecordBatch> {
+        let arrays: Vec<ArrayRef> = self
+            .columns()
+            .map(|(_, column)| column.to_arrow_array())
+            .collect();
+        let arrow_fields: Vec<ArrowField> = self
+            .columns()
+            .zip(&arrays)
+            .map(|((name, _), array)| ArrowField::new(name, array.data_type().clone(), true))
+            .collect();
+        RecordBatch::try_new(Arc::new(ArrowSchema::new(arrow_fields)), arrays)
+            .map_err(|err| TantivyError::InvalidArgument(err.to_string()))
+    }
+
+    /// Converts an Arrow record batch to a batch, with one column per Arrow field.
+    ///
+    /// See [`Column::from_arrow_array`] for the conversion of the arrays.
+    pub fn from_record_batch(record_batch: &RecordBatch) -> crate::Result<ColumnBatch> {
+        let columns = record_batch
+            .schema()
+            .fields()
+            .iter()
+            .zip(record_batch.columns())
+            .map(|(arrow_field, array)| {
+                Ok((
+                    arrow_field.name().clone(),
+                    Column::from_arrow_array(array.as_ref())?,
+                ))
+            })
+            .collect::<crate::Result<Vec<(String, Column)>>>()?;
+        ColumnBatch::new(columns)
+    }
+}
+
+/// Materializes `fields` for all of the documents matching `query`, as Arrow record batches.
+///
+/// This is [`export_batches`], with each batch converted by
+/// [`ColumnBatch::to_record_batch`]. The record batches can then be handed to analytics
+/// pipelines, or written to Parquet files with the `parquet` crate.
+pub fn export_record_batches(
+    searcher: &Searcher,
+    query: &dyn Query,
+    fields: &[Field],
+) -> crate::Result<Vec<RecordBatch>> {
+    export_batches(searcher, query, fields)?
+        .iter()
+        .map(ColumnBatch::to_record_batch)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Array, Int32Array, RecordBatch, TimestampMillisecondArray};
+    use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit};
+
+    use super::export_record_batches;
+    use crate::columnar::{Column, ColumnBatch};
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, FAST, STORED, TEXT};
+    use crate::{DateTime, Index};
+
+    #[test]
+    fn test_column_batch_record_batch_roundtrip() -> crate::Result<()> {
+        let batch = ColumnBatch::new(vec![
+            ("u64".to_string(), Column::U64(vec![Some(1), None])),
+            ("i64".to_string(), Column::I64(vec![Some(-1), None])),
+            ("f64".to_string(), Column::F64(vec![None, Some(0.5)])),
+            ("bool".to_string(), Column::Bool(vec![Some(true), None])),
+            (
+                "date".to_string(),
+                Column::Date(vec![Some(DateTime::from_unix_timestamp(1_000)), None]),
+            ),
+            (
+                "str".to_string(),
+                Column::Str(vec![None, Some("text".to_string())]),
+            ),
+            (
+                "bytes".to_string(),
+                Column::Bytes(vec![Some(vec![1, 2]), None]),
+            ),
+        ])?;
+        let record_batch = batch.to_record_batch()?;
+        assert_eq!(record_batch.num_rows(), 2);
+        assert_eq!(record_batch.num_columns(), 7);
+        assert_eq!(
+            record_batch.column(4).data_type(),
+            &DataType::Timestamp(TimeUnit::Second, Some("UTC".into()))
+        );
+        assert_eq!(record_batch.column(0).null_count(), 1);
+        assert_eq!(ColumnBatch::from_record_batch(&record_batch)?, batch);
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_from_arrow_array_conversions() -> crate::Result<()> {
+        let ints = Int32Array::from(vec![Some(-3), None]);
+        assert_eq!(
+            Column::from_arrow_array(&ints)?,
+            Column::I64(vec![Some(-3), None])
+        );
+        let timestamps = TimestampMillisecondArray::from(vec![Some(-1), Some(2_500)]);
+        assert_eq!(
+            Column::from_arrow_array(&timestamps)?,
+            Column::Date(vec![
+                Some(DateTime::from_unix_timestamp(-1)),
+                Some(DateTime::from_unix_timestamp(2))
+            ])
+        );
+        let record_batch = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "ints",
+                DataType::Int32,
+                true,
+            )])),
+            vec![Arc::new(ints)],
+        )
+        .unwrap();
+        let batch = ColumnBatch::from_record_batch(&record_batch)?;
+        assert_eq!(
+            batch.column("ints"),
+            Some(&Column::I64(vec![Some(-3), None]))
+        );
+        let nulls = arrow_array::NullArray::new(1);
+        assert!(Column::from_arrow_array(&nulls).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_record_batches() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let year = schema_builder.add_u64_field("year", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "The Name of the Wind", year => 2007u64))?;
+        index_writer.add_document(doc!(title => "The Diary of Muadib", year => 1965u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let record_batches = export_record_batches(&searcher, &AllQuery, &[title, year])?;
+        assert_eq!(record_batches.len(), 1);
+        let record_batch = &record_batches[0];
+        assert_eq!(record_batch.num_rows(), 2);
+        assert_eq!(record_batch.schema().field(0).name(), "title");
+        assert_eq!(record_batch.column(0).data_type(), &DataType::Utf8);
+        assert_eq!(record_batch.column(1).data_type(), &DataType::UInt64);
+        Ok(())
+    }
+}
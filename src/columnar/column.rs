@@ -0,0 +1,204 @@
+use std::mem;
+
+use crate::schema::{Facet, FieldType, Value};
+use crate::{DateTime, TantivyError};
+
+/// Values of a field for the rows of a [`ColumnBatch`].
+///
+/// `None` marks a row without a value for the field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    /// Unsigned 64-bits integers
+    U64(Vec<Option<u64>>),
+    /// Signed 64-bits integers
+    I64(Vec<Option<i64>>),
+    /// 64-bits floats
+    F64(Vec<Option<f64>>),
+    /// Booleans
+    Bool(Vec<Option<bool>>),
+    /// Dates
+    Date(Vec<Option<DateTime>>),
+    /// Strings. Facets are represented by their path, and json objects by their
+    /// serialization.
+    Str(Vec<Option<String>>),
+    /// Byte arrays
+    Bytes(Vec<Option<Vec<u8>>>),
+}
+
+impl Column {
+    /// Creates an empty column of the type matching the field type.
+    pub(crate) fn for_field_type(field_type: &FieldType, capacity: usize) -> Column {
+        match field_type {
+            FieldType::U64(_) => Column::U64(Vec::with_capacity(capacity)),
+            FieldType::I64(_) => Column::I64(Vec::with_capacity(capacity)),
+            FieldType::F64(_) => Column::F64(Vec::with_capacity(capacity)),
+            FieldType::Bool(_) => Column::Bool(Vec::with_capacity(capacity)),
+            FieldType::Date(_) => Column::Date(Vec::with_capacity(capacity)),
+            FieldType::Str(_) | FieldType::Facet(_) | FieldType::JsonObject(_) => {
+                Column::Str(Vec::with_capacity(capacity))
+            }
+            FieldType::Bytes(_) => Column::Bytes(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns the number of rows of the column.
+    pub fn len(&self) -> usize {
+        match self {
+            Column::U64(values) => values.len(),
+            Column::I64(values) => values.len(),
+            Column::F64(values) => values.len(),
+            Column::Bool(values) => values.len(),
+            Column::Date(values) => values.len(),
+            Column::Str(values) => values.len(),
+            Column::Bytes(values) => values.len(),
+        }
+    }
+
+    /// Returns true iff the column has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true iff the values of the column can be indexed in a field of this type.
+    pub(crate) fn is_compatible_with(&self, field_type: &FieldType) -> bool {
+        mem::discriminant(self) == mem::discriminant(&Column::for_field_type(field_type, 0))
+    }
+
+    /// Returns the value of a row, to be indexed in a field of type `field_type`.
+    ///
+    /// Strings are parsed for facet and json object fields.
+    pub(crate) fn value(&self, row: usize, field_type: &FieldType) -> crate::Result<Option<Value>> {
+        let value = match self {
+            Column::U64(values) => values[row].map(Value::U64),
+            Column::I64(values) => values[row].map(Value::I64),
+            Column::F64(values) => values[row].map(Value::F64),
+            Column::Bool(values) => values[row].map(Value::Bool),
+            Column::Date(values) => values[row].map(Value::Date),
+            Column::Str(values) => match (&values[row], field_type) {
+                (None, _) => None,
+                (Some(text), FieldType::Facet(_)) => {
+                    Some(Value::Facet(Facet::from_text(text).map_err(|err| {
+                        TantivyError::InvalidArgument(err.to_string())
+                    })?))
+                }
+                (Some(text), FieldType::JsonObject(_)) => Some(Value::JsonObject(
+                    serde_json::from_str(text).map_err(|err| {
+                        TantivyError::InvalidArgument(format!(
+                            "Invalid json object {:?}: {}",
+                            text, err
+                        ))
+                    })?,
+                )),
+                (Some(text), _) => Some(Value::Str(text.clone())),
+            },
+            Column::Bytes(values) => values[row].clone().map(Value::Bytes),
+        };
+        Ok(value)
+    }
+
+    /// Appends a stored value to the column.
+    ///
+    /// Values which do not match the type of the column are recorded as missing.
+    pub(crate) fn push_value(&mut self, value: Option<&Value>) {
+        match self {
+            Column::U64(values) => values.push(value.and_then(Value::as_u64)),
+            Column::I64(values) => values.push(value.and_then(Value::as_i64)),
+            Column::F64(values) => values.push(value.and_then(Value::as_f64)),
+            Column::Bool(values) => values.push(value.and_then(Value::as_bool)),
+            Column::Date(values) => values.push(value.and_then(Value::as_date)),
+            Column::Str(values) => values.push(value.and_then(|value| match value {
+                Value::Str(text) => Some(text.clone()),
+                Value::PreTokStr(pre_tokenized) => Some(pre_tokenized.text.clone()),
+                Value::Facet(facet) => Some(facet.to_path_string()),
+                Value::JsonObject(json_object) => serde_json::to_string(json_object).ok(),
+                _ => None,
+            })),
+            Column::Bytes(values) => {
+                values.push(value.and_then(Value::as_bytes).map(|bytes| bytes.to_vec()))
+            }
+        }
+    }
+}
+
+/// A set of named columns of the same length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnBatch {
+    columns: Vec<(String, Column)>,
+    num_rows: usize,
+}
+
+impl ColumnBatch {
+    /// Creates a batch from named columns.
+    ///
+    /// Returns an error if the columns do not all have the same number of rows.
+    pub fn new(columns: Vec<(String, Column)>) -> crate::Result<ColumnBatch> {
+        let num_rows = columns.first().map(|(_, column)| column.len()).unwrap_or(0);
+        if let Some((name, column)) = columns.iter().find(|(_, column)| column.len() != num_rows) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Column {:?} has {} rows, expected {}.",
+                name,
+                column.len(),
+                num_rows
+            )));
+        }
+        Ok(ColumnBatch { columns, num_rows })
+    }
+
+    /// Returns the number of rows of the batch.
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Returns the number of columns of the batch.
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the column with the given name, if any.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|(column_name, _)| column_name == name)
+            .map(|(_, column)| column)
+    }
+
+    /// Iterates over the names and the columns of the batch, in order.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &Column)> {
+        self.columns
+            .iter()
+            .map(|(name, column)| (name.as_str(), column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, ColumnBatch};
+
+    #[test]
+    fn test_column_batch_new() -> crate::Result<()> {
+        let batch = ColumnBatch::new(vec![
+            ("id".to_string(), Column::U64(vec![Some(1), Some(2)])),
+            (
+                "title".to_string(),
+                Column::Str(vec![Some("a".to_string()), None]),
+            ),
+        ])?;
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 2);
+        assert_eq!(
+            batch.column("id"),
+            Some(&Column::U64(vec![Some(1), Some(2)]))
+        );
+        assert!(batch.column("missing").is_none());
+        assert_eq!(
+            batch.columns().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec!["id", "title"]
+        );
+        assert!(ColumnBatch::new(vec![
+            ("id".to_string(), Column::U64(vec![Some(1), Some(2)])),
+            ("title".to_string(), Column::Str(vec![None])),
+        ])
+        .is_err());
+        Ok(())
+    }
+}
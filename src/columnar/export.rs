@@ -0,0 +1,354 @@
+use common::BitSet;
+
+use super::{Column, ColumnBatch};
+use crate::fastfield::{BytesFastFieldReader, DynamicFastFieldReader, FastFieldReader};
+use crate::query::Query;
+use crate::schema::{Cardinality, Document, Field, FieldType};
+use crate::{DateTime, DocAddress, DocId, Searcher, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Reader of the values of a field for a segment.
+enum ColumnSource {
+    U64(DynamicFastFieldReader<u64>),
+    I64(DynamicFastFieldReader<i64>),
+    F64(DynamicFastFieldReader<f64>),
+    Bool(DynamicFastFieldReader<bool>),
+    Date(DynamicFastFieldReader<DateTime>),
+    Bytes(BytesFastFieldReader),
+    Store,
+}
+
+impl ColumnSource {
+    fn open(segment_reader: &SegmentReader, field: Field) -> crate::Result<ColumnSource> {
+        let field_entry = segment_reader.schema().get_field_entry(field);
+        let fast_fields = segment_reader.fast_fields();
+        let is_single_valued_fast =
+            |cardinality: Option<Cardinality>| cardinality == Some(Cardinality::SingleValue);
+        let column_source = match field_entry.field_type() {
+            FieldType::U64(options)
+                if is_single_valued_fast(options.get_fastfield_cardinality()) =>
+            {
+                ColumnSource::U64(fast_fields.u64(field)?)
+            }
+            FieldType::I64(options)
+                if is_single_valued_fast(options.get_fastfield_cardinality()) =>
+            {
+                ColumnSource::I64(fast_fields.i64(field)?)
+            }
+            FieldType::F64(options)
+                if is_single_valued_fast(options.get_fastfield_cardinality()) =>
+            {
+                ColumnSource::F64(fast_fields.f64(field)?)
+            }
+            FieldType::Bool(options)
+                if is_single_valued_fast(options.get_fastfield_cardinality()) =>
+            {
+                ColumnSource::Bool(fast_fields.bool(field)?)
+            }
+            FieldType::Date(options)
+                if is_single_valued_fast(options.get_fastfield_cardinality()) =>
+            {
+                ColumnSource::Date(fast_fields.date(field)?)
+            }
+            FieldType::Bytes(options) if options.is_fast() => {
+                ColumnSource::Bytes(fast_fields.bytes(field)?)
+            }
+            _ if field_entry.is_stored() => ColumnSource::Store,
+            _ => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is neither a single valued fast field nor a stored field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(column_source)
+    }
+}
+
+/// Tells apart the documents without a value for a fast field, for which the fast field
+/// holds a default value.
+enum Presence {
+    /// The documents with a value are the documents with a term in the inverted index.
+    Indexed(BitSet),
+    /// The documents with a value are the documents with a stored value.
+    Stored,
+    /// The field is neither indexed nor stored, so every document is assumed to have a value.
+    Unknown,
+}
+
+impl Presence {
+    fn open(segment_reader: &SegmentReader, field: Field) -> crate::Result<Presence> {
+        let field_entry = segment_reader.schema().get_field_entry(field);
+        let presence = if field_entry.is_indexed() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            Presence::Indexed(inverted_index.docs_with_terms(segment_reader.max_doc())?)
+        } else if field_entry.is_stored() {
+            Presence::Stored
+        } else {
+            Presence::Unknown
+        };
+        Ok(presence)
+    }
+
+    fn has_value(&self, doc: DocId, stored_doc_opt: Option<&Document>, field: Field) -> bool {
+        match self {
+            Presence::Indexed(docs_with_value) => docs_with_value.contains(doc),
+            Presence::Stored => stored_doc_opt
+                .and_then(|stored_doc| stored_doc.get_first(field))
+                .is_some(),
+            Presence::Unknown => true,
+        }
+    }
+}
+
+/// Materializes `fields` for all of the documents matching `query`.
+///
+/// One batch is returned per segment, in the order of the segments of the searcher,
+/// with one column per field, named after it. Rows are sorted by doc id, and deleted
+/// documents are skipped.
+///
+/// Single valued fast fields and fast bytes fields are read from their fast field.
+/// Other fields have to be stored, and only their first value is exported.
+///
+/// A fast field holds a default value for the documents without a value. These documents
+/// are exported with a `None` value if the field is indexed or stored, as its postings or
+/// its stored values tell them apart. The fast fields that are neither indexed nor stored
+/// are exported with their default value instead.
+///
+/// ```rust
+/// use tantivy::columnar::{export_batches, Column};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST, STORED, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT | STORED);
+/// let year = schema_builder.add_u64_field("year", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind", year => 2007u64))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib", year => 1965u64))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let batches = export_batches(&searcher, &AllQuery, &[title, year])?;
+/// assert_eq!(batches.len(), 1);
+/// assert_eq!(batches[0].num_rows(), 2);
+/// assert_eq!(batches[0].column("year"), Some(&Column::U64(vec![Some(2007), Some(1965)])));
+/// # Ok(())
+/// # }
+/// ```
+pub fn export_batches(
+    searcher: &Searcher,
+    query: &dyn Query,
+    fields: &[Field],
+) -> crate::Result<Vec<ColumnBatch>> {
+    let mut docs_per_segment: Vec<Vec<DocId>> = vec![Vec::new(); searcher.segment_readers().len()];
+    for doc_address in searcher.docs_matching(query)? {
+        docs_per_segment[doc_address.segment_ord as usize].push(doc_address.doc_id);
+    }
+    docs_per_segment
+        .iter()
+        .enumerate()
+        .map(|(segment_ord, docs)| {
+            export_segment_batch(searcher, segment_ord as SegmentOrdinal, docs, fields)
+        })
+        .collect()
+}
+
+fn export_segment_batch(
+    searcher: &Searcher,
+    segment_ord: SegmentOrdinal,
+    docs: &[DocId],
+    fields: &[Field],
+) -> crate::Result<ColumnBatch> {
+    let schema = searcher.schema();
+    let segment_reader = searcher.segment_reader(segment_ord);
+    let column_sources: Vec<ColumnSource> = fields
+        .iter()
+        .map(|&field| ColumnSource::open(segment_reader, field))
+        .collect::<crate::Result<_>>()?;
+    let mut columns: Vec<Column> = fields
+        .iter()
+        .map(|&field| {
+            Column::for_field_type(schema.get_field_entry(field).field_type(), docs.len())
+        })
+        .collect();
+    let presences: Vec<Option<Presence>> = column_sources
+        .iter()
+        .zip(fields)
+        .map(|(column_source, &field)| match column_source {
+            ColumnSource::Store => Ok(None),
+            _ => Presence::open(segment_reader, field).map(Some),
+        })
+        .collect::<crate::Result<_>>()?;
+    let requires_store = column_sources
+        .iter()
+        .any(|column_source| matches!(column_source, ColumnSource::Store))
+        || presences
+            .iter()
+            .any(|presence_opt| matches!(presence_opt, Some(Presence::Stored)));
+    for &doc in docs {
+        let stored_doc: Option<Document> = if requires_store {
+            Some(searcher.doc(DocAddress::new(segment_ord, doc))?)
+        } else {
+            None
+        };
+        for (((column_source, presence_opt), column), &field) in column_sources
+            .iter()
+            .zip(&presences)
+            .zip(columns.iter_mut())
+            .zip(fields)
+        {
+            let has_value = presence_opt
+                .as_ref()
+                .map(|presence| presence.has_value(doc, stored_doc.as_ref(), field))
+                .unwrap_or(true);
+            match (column_source, column) {
+                (ColumnSource::U64(reader), Column::U64(values)) => {
+                    values.push(has_value.then(|| reader.get(doc)))
+                }
+                (ColumnSource::I64(reader), Column::I64(values)) => {
+                    values.push(has_value.then(|| reader.get(doc)))
+                }
+                (ColumnSource::F64(reader), Column::F64(values)) => {
+                    values.push(has_value.then(|| reader.get(doc)))
+                }
+                (ColumnSource::Bool(reader), Column::Bool(values)) => {
+                    values.push(has_value.then(|| reader.get(doc)))
+                }
+                (ColumnSource::Date(reader), Column::Date(values)) => {
+                    values.push(has_value.then(|| reader.get(doc)))
+                }
+                (ColumnSource::Bytes(reader), Column::Bytes(values)) => {
+                    values.push(has_value.then(|| reader.get_bytes(doc).to_vec()))
+                }
+                (ColumnSource::Store, column) => column.push_value(
+                    stored_doc
+                        .as_ref()
+                        .and_then(|stored_doc| stored_doc.get_first(field)),
+                ),
+                _ => unreachable!("the column type is derived from the field type"),
+            }
+        }
+    }
+    let named_columns = fields
+        .iter()
+        .map(|&field| schema.get_field_name(field).to_string())
+        .zip(columns)
+        .collect();
+    ColumnBatch::new(named_columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export_batches;
+    use crate::columnar::Column;
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{
+        Cardinality, IndexRecordOption, NumericOptions, Schema, FAST, INDEXED, STORED, STRING,
+    };
+    use crate::{Index, TantivyError, Term};
+
+    #[test]
+    fn test_export_batches() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let score = schema_builder.add_f64_field("score", FAST);
+        let title = schema_builder.add_text_field("title", STRING | STORED);
+        let count = schema_builder.add_i64_field("count", STORED);
+        let payload = schema_builder.add_bytes_field("payload", FAST);
+        let tags = schema_builder.add_u64_field(
+            "tags",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(
+            doc!(id => 1u64, score => 0.5f64, title => "a", count => -1i64, payload => vec![1u8]),
+        )?;
+        index_writer.add_document(doc!(id => 2u64, score => 1.5f64, payload => vec![2u8, 3u8]))?;
+        index_writer.add_document(
+            doc!(id => 3u64, score => 2.5f64, title => "c", count => 3i64, payload => vec![]),
+        )?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(id, 3));
+        index_writer
+            .add_document(doc!(id => 4u64, score => 3.5f64, title => "d", payload => vec![4u8]))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let batches = export_batches(&searcher, &AllQuery, &[id, score, title, count, payload])?;
+        assert_eq!(batches.len(), 2);
+        let batch = batches.iter().find(|batch| batch.num_rows() == 2).unwrap();
+        assert_eq!(batch.num_columns(), 5);
+        assert_eq!(
+            batch.column("id"),
+            Some(&Column::U64(vec![Some(1), Some(2)]))
+        );
+        assert_eq!(
+            batch.column("score"),
+            Some(&Column::F64(vec![Some(0.5), Some(1.5)]))
+        );
+        assert_eq!(
+            batch.column("title"),
+            Some(&Column::Str(vec![Some("a".to_string()), None]))
+        );
+        assert_eq!(
+            batch.column("count"),
+            Some(&Column::I64(vec![Some(-1), None]))
+        );
+        assert_eq!(
+            batch.column("payload"),
+            Some(&Column::Bytes(vec![Some(vec![1]), Some(vec![2, 3])]))
+        );
+
+        let term_query = TermQuery::new(Term::from_field_u64(id, 4), IndexRecordOption::Basic);
+        let batches = export_batches(&searcher, &term_query, &[title])?;
+        let num_rows: Vec<usize> = batches.iter().map(|batch| batch.num_rows()).collect();
+        assert_eq!(num_rows.iter().sum::<usize>(), 1);
+
+        assert!(matches!(
+            export_batches(&searcher, &AllQuery, &[tags]),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_batches_missing_fast_values() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let indexed = schema_builder.add_u64_field("indexed", INDEXED | FAST);
+        let stored = schema_builder.add_i64_field("stored", STORED | FAST);
+        let fast_only = schema_builder.add_f64_field("fast_only", FAST);
+        let payload = schema_builder.add_bytes_field("payload", STORED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(
+            doc!(indexed => 0u64, stored => 0i64, fast_only => 0.5f64, payload => vec![]),
+        )?;
+        index_writer.add_document(doc!())?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let batches = export_batches(&searcher, &AllQuery, &[indexed, stored, fast_only, payload])?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(
+            batches[0].column("indexed"),
+            Some(&Column::U64(vec![Some(0), None]))
+        );
+        assert_eq!(
+            batches[0].column("stored"),
+            Some(&Column::I64(vec![Some(0), None]))
+        );
+        assert_eq!(
+            batches[0].column("fast_only"),
+            Some(&Column::F64(vec![Some(0.5), Some(0.0)]))
+        );
+        assert_eq!(
+            batches[0].column("payload"),
+            Some(&Column::Bytes(vec![Some(vec![]), None]))
+        );
+        Ok(())
+    }
+}
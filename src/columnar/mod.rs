@@ -0,0 +1,27 @@
+//! Columnar representation of documents.
+//!
+//! A [`ColumnBatch`] holds the values of a few fields for a set of documents, with one
+//! [`Column`] per field. Its layout mirrors the one of Arrow record batches, so converting
+//! it to Arrow arrays, or writing it to Parquet, does not require going through documents.
+//!
+//! [`export_batches`] materializes fields of the documents matching a query, reading fast
+//! fields directly and only going through the doc store for fields that are stored but not
+//! fast. Conversely,
+//! [`IndexWriter::add_column_batch_as_documents`](crate::IndexWriter::add_column_batch_as_documents)
+//! converts the rows of a batch into documents and indexes them.
+//!
+//! With the `arrow` feature flag, batches convert to and from Arrow record batches, and
+//! [`export_record_batches`] exports the fields of the matching documents as Arrow record
+//! batches.
+
+#[cfg(feature = "arrow")]
+mod arrow;
+mod column;
+mod export;
+
+#[cfg(feature = "arrow")]
+pub(crate) use self::arrow::add_array_to_documents;
+#[cfg(feature = "arrow")]
+pub use self::arrow::export_record_batches;
+pub use self::column::{Column, ColumnBatch};
+pub use self::export::export_batches;
@@ -1,32 +1,53 @@
-use std::collections::HashSet;
-use std::fmt;
-#[cfg(feature = "mmap")]
-use std::path::Path;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::{fmt, io};
+
+use common::BitSet;
 
 use super::segment::Segment;
-use super::IndexSettings;
+use super::{index_archive, IndexSettings};
 use crate::core::{
-    Executor, IndexMeta, SegmentId, SegmentMeta, SegmentMetaInventory, META_FILEPATH,
+    Executor, IndexMeta, SegmentComponent, SegmentId, SegmentMeta, SegmentMetaInventory,
+    META_FILEPATH,
 };
 use crate::directory::error::OpenReadError;
 #[cfg(feature = "mmap")]
 use crate::directory::MmapDirectory;
-use crate::directory::{Directory, ManagedDirectory, RamDirectory, INDEX_WRITER_LOCK};
+use crate::directory::{
+    Directory, DirectoryLock, ManagedDirectory, RamDirectory, TerminatingWrite, INDEX_WRITER_LOCK,
+};
 use crate::error::{DataCorruption, TantivyError};
+use crate::fastfield::{AliveBitSet, FastFieldReader, FastValue};
 use crate::indexer::index_writer::{MAX_NUM_THREAD, MEMORY_ARENA_NUM_BYTES_MIN};
-use crate::indexer::segment_updater::save_new_metas;
+use crate::indexer::segment_updater::{save_metas, save_new_metas};
+use crate::indexer::{merge_filtered_segments, WriterFence};
 use crate::reader::{IndexReader, IndexReaderBuilder};
-use crate::schema::{Field, FieldType, Schema};
+use crate::schema::{Cardinality, Field, FieldType, Schema};
+use crate::space_usage::{ByteCount, IndexDiskUsage, SegmentDiskUsage};
+use crate::store::BlobStore;
 use crate::tokenizer::{TextAnalyzer, TokenizerManager};
-use crate::IndexWriter;
+use crate::{FlushTriggers, IndexWriter, Opstamp, SegmentReader};
 
 fn load_metas(
     directory: &dyn Directory,
     inventory: &SegmentMetaInventory,
 ) -> crate::Result<IndexMeta> {
     let meta_data = directory.atomic_read(&META_FILEPATH)?;
+    parse_metas(meta_data, inventory).or_else(|err| {
+        // `meta.json` may be partially written, while the latest generation of the metas
+        // is always complete.
+        let generation_meta_data = directory
+            .atomic_read_generation_metas()?
+            .ok_or_else(|| err.clone())?;
+        warn!("Meta file is corrupted, falling back to its latest generation.");
+        parse_metas(generation_meta_data, inventory).map_err(|_| err)
+    })
+}
+
+fn parse_metas(meta_data: Vec<u8>, inventory: &SegmentMetaInventory) -> crate::Result<IndexMeta> {
     let meta_string = String::from_utf8(meta_data).map_err(|_utf8_err| {
         error!("Meta data is not valid utf8.");
         DataCorruption::new(
@@ -115,11 +136,11 @@ impl IndexBuilder {
     ///
     /// The index will be allocated in anonymous memory.
     /// This should only be used for unit tests.
+    ///
+    /// Returns a `SchemaError` if the schema is invalid.
     pub fn create_in_ram(self) -> Result<Index, TantivyError> {
         let ram_directory = RamDirectory::create();
-        Ok(self
-            .create(ram_directory)
-            .expect("Creating a RAMDirectory should never fail"))
+        self.create(ram_directory)
     }
 
     /// Creates a new index in a given filepath.
@@ -176,6 +197,7 @@ impl IndexBuilder {
     ///
     /// If a directory previously existed, it will be erased.
     fn create<T: Into<Box<dyn Directory>>>(self, dir: T) -> crate::Result<Index> {
+        self.get_expect_schema()?.check_facet_count_fields()?;
         let dir = dir.into();
         let directory = ManagedDirectory::wrap(dir)?;
         save_new_metas(
@@ -195,11 +217,18 @@ impl IndexBuilder {
 #[derive(Clone)]
 pub struct Index {
     directory: ManagedDirectory,
-    schema: Schema,
+    // Shared by the clones of the index, as the dynamic fields extend it.
+    schema: Arc<RwLock<Schema>>,
     settings: IndexSettings,
     executor: Arc<Executor>,
     tokenizers: TokenizerManager,
     inventory: SegmentMetaInventory,
+    // Commits retained for external consumers, by opstamp.
+    // `None` as long as the retention of generations is not enabled.
+    retained_generations: Arc<Mutex<Option<BTreeMap<Opstamp, IndexMeta>>>>,
+    // Seed and number of the segment ids generated so far, when they are deterministic.
+    deterministic_segment_ids: Arc<Mutex<Option<(u64, u64)>>>,
+    blob_store: Option<Arc<dyn BlobStore>>,
 }
 
 impl Index {
@@ -224,6 +253,11 @@ impl Index {
         self.executor.as_ref()
     }
 
+    /// Returns the search executor, shared with the searchers of the index.
+    pub(crate) fn shared_search_executor(&self) -> Arc<Executor> {
+        self.executor.clone()
+    }
+
     /// Replace the default single thread search executor pool
     /// by a thread pool with a given number of threads.
     pub fn set_multithread_executor(&mut self, num_threads: usize) -> crate::Result<()> {
@@ -243,6 +277,10 @@ impl Index {
     /// The index will be allocated in anonymous memory.
     /// This is useful for indexing small set of documents
     /// for instances like unit test or temporary in memory index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the schema is invalid, see [`IndexBuilder::create_in_ram`].
     pub fn create_in_ram(schema: Schema) -> Index {
         IndexBuilder::new().schema(schema).create_in_ram().unwrap()
     }
@@ -303,7 +341,7 @@ impl Index {
         metas: &IndexMeta,
         inventory: SegmentMetaInventory,
     ) -> Index {
-        let schema = metas.schema.clone();
+        let schema = Arc::new(RwLock::new(metas.schema.clone()));
         Index {
             settings: metas.index_settings.clone(),
             directory,
@@ -311,6 +349,9 @@ impl Index {
             tokenizers: TokenizerManager::default(),
             executor: Arc::new(Executor::single_thread()),
             inventory,
+            retained_generations: Arc::default(),
+            deterministic_segment_ids: Arc::default(),
+            blob_store: None,
         }
     }
 
@@ -324,36 +365,23 @@ impl Index {
         &self.tokenizers
     }
 
+    /// Sets the blob store the large stored values are written to and read from (See
+    /// [`IndexSettings::docstore_blob_threshold`]).
+    ///
+    /// The blob store is not persisted: it has to be set every time the index is opened,
+    /// before creating its writer and its reader.
+    pub fn set_blob_store<B: BlobStore>(&mut self, blob_store: B) {
+        self.blob_store = Some(Arc::new(blob_store));
+    }
+
+    /// Accessor for the blob store.
+    pub fn blob_store(&self) -> Option<&Arc<dyn BlobStore>> {
+        self.blob_store.as_ref()
+    }
+
     /// Helper to access the tokenizer associated to a specific field.
     pub fn tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
-        let field_entry = self.schema.get_field_entry(field);
-        let field_type = field_entry.field_type();
-        let tokenizer_manager: &TokenizerManager = self.tokenizers();
-        let indexing_options_opt = match field_type {
-            FieldType::JsonObject(options) => options.get_text_indexing_options(),
-            FieldType::Str(options) => options.get_indexing_options(),
-            _ => {
-                return Err(TantivyError::SchemaError(format!(
-                    "{:?} is not a text field.",
-                    field_entry.name()
-                )))
-            }
-        };
-        let indexing_options = indexing_options_opt.ok_or_else(|| {
-            TantivyError::InvalidArgument(format!(
-                "No indexing options set for field {:?}",
-                field_entry
-            ))
-        })?;
-
-        tokenizer_manager
-            .get(indexing_options.tokenizer())
-            .ok_or_else(|| {
-                TantivyError::InvalidArgument(format!(
-                    "No Tokenizer found for field {:?}",
-                    field_entry
-                ))
-            })
+        tokenizer_for_field(&self.schema(), self.tokenizers(), field)
     }
 
     /// Create a default `IndexReader` for the given index.
@@ -435,8 +463,62 @@ impl Index {
         num_threads: usize,
         overall_memory_arena_in_bytes: usize,
     ) -> crate::Result<IndexWriter> {
-        let directory_lock = self
-            .directory
+        self.writer_with_flush_triggers(
+            num_threads,
+            overall_memory_arena_in_bytes,
+            FlushTriggers::default(),
+        )
+    }
+
+    /// Creates a multithreaded writer, whose indexing threads flush their segment
+    /// upon `flush_triggers`, on top of their memory budget.
+    ///
+    /// See [`Index::writer_with_num_threads`] for the other arguments,
+    /// and [`IndexWriter::set_flush_triggers`] to adjust the triggers afterwards.
+    pub fn writer_with_flush_triggers(
+        &self,
+        num_threads: usize,
+        overall_memory_arena_in_bytes: usize,
+        flush_triggers: FlushTriggers,
+    ) -> crate::Result<IndexWriter> {
+        let directory_lock = self.acquire_index_writer_lock()?;
+        let memory_arena_in_bytes_per_thread = overall_memory_arena_in_bytes / num_threads;
+        IndexWriter::new(
+            self,
+            num_threads,
+            memory_arena_in_bytes_per_thread,
+            flush_triggers,
+            directory_lock,
+            WriterFence::default(),
+        )
+    }
+
+    /// Creates a writer without any indexing thread.
+    ///
+    /// Documents are indexed by the thread adding them, in a segment with a budget of
+    /// `memory_arena_in_bytes`. This avoids handing the documents over to an indexing thread,
+    /// which dominates the latency of adding a few documents before each commit,
+    /// at the expense of indexing throughput.
+    ///
+    /// # Errors
+    /// See [`Index::writer_with_num_threads`].
+    pub fn single_threaded_writer(
+        &self,
+        memory_arena_in_bytes: usize,
+    ) -> crate::Result<IndexWriter> {
+        let directory_lock = self.acquire_index_writer_lock()?;
+        IndexWriter::new(
+            self,
+            0,
+            memory_arena_in_bytes,
+            FlushTriggers::default(),
+            directory_lock,
+            WriterFence::default(),
+        )
+    }
+
+    fn acquire_index_writer_lock(&self) -> crate::Result<DirectoryLock> {
+        self.directory
             .acquire_lock(&INDEX_WRITER_LOCK)
             .map_err(|err| {
                 TantivyError::LockFailure(
@@ -448,14 +530,7 @@ impl Index {
                             .to_string(),
                     ),
                 )
-            })?;
-        let memory_arena_in_bytes_per_thread = overall_memory_arena_in_bytes / num_threads;
-        IndexWriter::new(
-            self,
-            num_threads,
-            memory_arena_in_bytes_per_thread,
-            directory_lock,
-        )
+            })
     }
 
     /// Helper to create an index writer for tests.
@@ -501,7 +576,73 @@ impl Index {
     ///
     /// The schema is actually cloned.
     pub fn schema(&self) -> Schema {
-        self.schema.clone()
+        self.schema
+            .read()
+            .expect("Schema lock poisoned. This should never happen.")
+            .clone()
+    }
+
+    /// Adds the fields created from the dynamic templates of the schema for `field_names`
+    /// to the schema of the index, and returns the resulting schema.
+    ///
+    /// The fields are visible to all of the clones of the index, and persisted upon the
+    /// next commit.
+    pub(crate) fn add_dynamic_fields<'a>(
+        &self,
+        field_names: impl IntoIterator<Item = &'a str> + Clone,
+    ) -> Schema {
+        let schema = self.schema();
+        if schema.with_dynamic_fields(field_names.clone()).is_none() {
+            return schema;
+        }
+        let mut schema_wlock = self
+            .schema
+            .write()
+            .expect("Schema lock poisoned. This should never happen.");
+        // The schema may have been extended in the meantime.
+        if let Some(extended_schema) = schema_wlock.with_dynamic_fields(field_names) {
+            *schema_wlock = extended_schema;
+        }
+        schema_wlock.clone()
+    }
+
+    /// Marks `field` as deprecated in the schema of the index, and returns the resulting
+    /// schema.
+    ///
+    /// The deprecation is visible to all of the clones of the index, and persisted upon the
+    /// next commit.
+    pub(crate) fn deprecate_field(&self, field: Field) -> Schema {
+        let mut schema_wlock = self
+            .schema
+            .write()
+            .expect("Schema lock poisoned. This should never happen.");
+        if !schema_wlock.get_field_entry(field).is_deprecated() {
+            *schema_wlock = schema_wlock.with_deprecated_field(field);
+        }
+        schema_wlock.clone()
+    }
+
+    /// Adopts the schema of a commit, if it has fields the schema of the index does not have,
+    /// and the deprecations of its fields.
+    ///
+    /// This makes the dynamic fields added and the fields deprecated by a writer of another
+    /// process visible.
+    pub(crate) fn refresh_schema(&self, committed_schema: &Schema) {
+        let mut schema_wlock = self
+            .schema
+            .write()
+            .expect("Schema lock poisoned. This should never happen.");
+        if committed_schema.num_fields() > schema_wlock.num_fields() {
+            *schema_wlock = committed_schema.clone();
+        }
+        for (field, field_entry) in committed_schema.fields() {
+            if field_entry.is_deprecated()
+                && field.field_id() < schema_wlock.num_fields() as u32
+                && !schema_wlock.get_field_entry(field).is_deprecated()
+            {
+                *schema_wlock = schema_wlock.with_deprecated_field(field);
+            }
+        }
     }
 
     /// Returns the list of segments that are searchable
@@ -520,12 +661,45 @@ impl Index {
 
     /// Creates a new segment.
     pub fn new_segment(&self) -> Segment {
-        let segment_meta = self
-            .inventory
-            .new_segment_meta(SegmentId::generate_random(), 0);
+        let segment_id = self
+            .next_deterministic_segment_id()
+            .unwrap_or_else(SegmentId::generate_random);
+        let segment_meta = self.inventory.new_segment_meta(segment_id, 0);
         self.segment(segment_meta)
     }
 
+    /// Makes the ids of the new segments a sequence generated from `seed`, rather than random.
+    ///
+    /// Building the same stream of documents into two indexes created with the same settings
+    /// then yields byte-identical segment files, e.g. to cache prebuilt indexes by their
+    /// content. This requires the documents to be indexed in a deterministic order and
+    /// split in segments deterministically: use a writer with a single indexing thread, or
+    /// [`Index::single_threaded_writer`], and either the `NoMergePolicy` or
+    /// [`IndexWriter::wait_merging_threads`] before reading the files.
+    ///
+    /// The ids of the segments already in the directory are skipped. The sequence is shared by
+    /// this `Index` and its clones, including the one held by the `IndexWriter`.
+    pub fn set_deterministic_segment_ids(&self, seed: u64) {
+        *self.deterministic_segment_ids.lock().unwrap() = Some((seed, 0));
+    }
+
+    fn next_deterministic_segment_id(&self) -> Option<SegmentId> {
+        let mut deterministic_segment_ids = self.deterministic_segment_ids.lock().unwrap();
+        let (seed, num_generated) = deterministic_segment_ids.as_mut()?;
+        let managed_files = self.directory.list_managed_files();
+        loop {
+            let segment_id = SegmentId::from_seed(*seed, *num_generated);
+            *num_generated += 1;
+            let uuid_string = segment_id.uuid_string();
+            let is_used = managed_files
+                .iter()
+                .any(|path| path.to_string_lossy().starts_with(&uuid_string));
+            if !is_used {
+                return Some(segment_id);
+            }
+        }
+    }
+
     /// Return a reference to the index directory.
     pub fn directory(&self) -> &ManagedDirectory {
         &self.directory
@@ -551,6 +725,293 @@ impl Index {
             .collect())
     }
 
+    /// Returns the size of the files of the searchable segments, per segment and per
+    /// segment component, along with the size of `meta.json`.
+    ///
+    /// Sizes are the ones reported by the directory, so this does not require opening a
+    /// reader.
+    pub fn disk_usage(&self) -> crate::Result<IndexDiskUsage> {
+        let mut disk_usage = IndexDiskUsage::new(self.file_num_bytes(&META_FILEPATH)?);
+        for segment_meta in self.searchable_segment_metas()? {
+            let mut segment_disk_usage =
+                SegmentDiskUsage::new(segment_meta.id(), segment_meta.num_docs());
+            for &component in SegmentComponent::all() {
+                if component == SegmentComponent::Delete && !segment_meta.has_deletes() {
+                    continue;
+                }
+                let num_bytes = self.file_num_bytes(&segment_meta.relative_path(component))?;
+                segment_disk_usage.set_component(component, num_bytes);
+            }
+            disk_usage.add_segment(segment_disk_usage);
+        }
+        Ok(disk_usage)
+    }
+
+    /// Returns the size of a file of the directory, or 0 if it does not exist.
+    fn file_num_bytes(&self, path: &Path) -> crate::Result<ByteCount> {
+        match self.directory.file_num_bytes(path) {
+            Ok(num_bytes) => Ok(num_bytes),
+            Err(OpenReadError::FileDoesNotExist(_)) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Exports the last commit of the index to a single archive file.
+    ///
+    /// The archive contains the schema, the settings and all of the segment files, along with
+    /// checksums. It does not depend on the directory implementation, so it can be imported
+    /// in any `Directory` with [`Index::import`].
+    pub fn export<P: AsRef<Path>>(&self, archive_path: P) -> crate::Result<()> {
+        let mut archive = io::BufWriter::new(File::create(archive_path)?);
+        index_archive::write_archive(self, &mut archive)?;
+        archive
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+
+    /// Imports an archive created with [`Index::export`] into an empty directory and
+    /// opens the resulting index.
+    ///
+    /// The checksum of each file is verified while it is written. `meta.json` is written last,
+    /// so if the import fails the directory does not contain an index, and can be discarded.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::InvalidArgument` if the directory already contains an index,
+    /// `TantivyError::DataCorruption` if the archive is damaged, and
+    /// `TantivyError::IncompatibleIndex` if it was written by an incompatible version of
+    /// tantivy.
+    pub fn import<P: AsRef<Path>, T: Into<Box<dyn Directory>>>(
+        archive_path: P,
+        directory: T,
+    ) -> crate::Result<Index> {
+        let directory = directory.into();
+        if Index::exists(&*directory)? {
+            return Err(TantivyError::InvalidArgument(
+                "Cannot import an archive in a directory already containing an index.".to_string(),
+            ));
+        }
+        let directory = ManagedDirectory::wrap(directory)?;
+        let mut archive = io::BufReader::new(File::open(archive_path)?);
+        index_archive::read_archive(&mut archive, &directory)?;
+        let inventory = SegmentMetaInventory::default();
+        let metas = load_metas(&directory, &inventory)?;
+        Ok(Index::open_from_metas(directory, &metas, inventory))
+    }
+
+    /// Splits the last commit of the index into as many new indexes as there are
+    /// `output_directories`, each document going to the index of rank
+    /// `routing_fn(value)`, given the value of the single-valued fast field `field`.
+    ///
+    /// The alive documents routed to each index are merged into a single segment, the stored
+    /// values being streamed from the doc stores of the segments. The deleted documents are
+    /// dropped. The new indexes have the schema and the settings of the index.
+    ///
+    /// The output directories are assumed to be empty.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::SchemaError` if `field` is not a single-valued fast field of
+    /// type `T`, and `TantivyError::InvalidArgument` if `routing_fn` returns a rank greater
+    /// or equal to the number of output directories.
+    ///
+    /// # Warning
+    /// As for [`merge_indices`](crate::merge_indices), this method does not check whether an
+    /// `IndexWriter` is running on the index.
+    pub fn split<T: FastValue, D: Into<Box<dyn Directory>>>(
+        &self,
+        field: Field,
+        output_directories: Vec<D>,
+        routing_fn: impl Fn(T) -> usize,
+    ) -> crate::Result<Vec<Index>> {
+        let num_indexes = output_directories.len();
+        let schema = self.schema();
+        let field_entry = schema.get_field_entry(field);
+        if T::fast_field_cardinality(field_entry.field_type()) != Some(Cardinality::SingleValue) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a single-valued {:?} fast field.",
+                field_entry.name(),
+                T::to_type()
+            )));
+        }
+        let segments = self.searchable_segments()?;
+        // Documents routed to each index, by segment.
+        let mut routed_bitsets: Vec<Vec<BitSet>> = vec![Vec::new(); num_indexes];
+        for segment in &segments {
+            let segment_reader = SegmentReader::open(segment)?;
+            let ff_reader = segment_reader
+                .fast_fields()
+                .typed_fast_field_reader::<T>(field)?;
+            let mut segment_bitsets =
+                vec![BitSet::with_max_value(segment_reader.max_doc()); num_indexes];
+            for doc in segment_reader.doc_ids_alive() {
+                let rank = routing_fn(ff_reader.get(doc));
+                let segment_bitset = segment_bitsets.get_mut(rank).ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!(
+                        "The routing function returned {}, but there are only {} output \
+                         directories.",
+                        rank, num_indexes
+                    ))
+                })?;
+                segment_bitset.insert(doc);
+            }
+            for (index_bitsets, segment_bitset) in routed_bitsets.iter_mut().zip(segment_bitsets) {
+                index_bitsets.push(segment_bitset);
+            }
+        }
+        output_directories
+            .into_iter()
+            .zip(routed_bitsets)
+            .map(|(output_directory, index_bitsets)| {
+                if index_bitsets.iter().all(|bitset| bitset.len() == 0) {
+                    return Index::create(output_directory, schema.clone(), self.settings.clone());
+                }
+                let alive_bitsets = index_bitsets
+                    .iter()
+                    .map(|bitset| Some(AliveBitSet::from_bitset(bitset)))
+                    .collect();
+                merge_filtered_segments(
+                    &segments,
+                    self.settings.clone(),
+                    alive_bitsets,
+                    output_directory,
+                )
+            })
+            .collect()
+    }
+
+    /// Adds the segments of the last commit of another index to this index, and commits them.
+    ///
+    /// The files of the segments are copied, under fresh segment ids, so the other index is
+    /// left untouched. Their deleted documents stay deleted. The added segments are then merged
+    /// like any other segment, by the merge policy of the next `IndexWriter`, or explicitly with
+    /// [`IndexWriter::merge`] on the returned segment ids. This lets several builders index
+    /// shards in parallel, before fusing them into a single index.
+    ///
+    /// If `check_schema` is true, the other index must have the same schema and sort the
+    /// documents of its segments by the same field. Otherwise, the caller is responsible for
+    /// the compatibility of the schemas.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::LockFailure` if an `IndexWriter` is running on this index, and
+    /// `TantivyError::SchemaError` if the schemas are checked and are not compatible.
+    pub fn add_segments_from(
+        &self,
+        other: &Index,
+        check_schema: bool,
+    ) -> crate::Result<Vec<SegmentId>> {
+        let _directory_lock = self.acquire_index_writer_lock()?;
+        if check_schema {
+            if other.schema() != self.schema() {
+                return Err(TantivyError::SchemaError(
+                    "Cannot add the segments of an index with a different schema.".to_string(),
+                ));
+            }
+            if other.settings().sort_by_field != self.settings().sort_by_field {
+                return Err(TantivyError::SchemaError(
+                    "Cannot add the segments of an index sorted by a different field.".to_string(),
+                ));
+            }
+        }
+        let mut index_meta = self.load_metas()?;
+        // The other segment metas are tracked while we hold them,
+        // so their files cannot be garbage collected during the copy.
+        let other_segment_metas = other.searchable_segment_metas()?;
+        let mut segment_ids = Vec::with_capacity(other_segment_metas.len());
+        for other_segment_meta in &other_segment_metas {
+            let mut segment_meta = self
+                .new_segment_meta(SegmentId::generate_random(), other_segment_meta.max_doc())
+                // The opstamps of the other index are meaningless in this one, the documents
+                // of the added segment are considered to be added now.
+                .with_max_opstamp(Some(index_meta.opstamp))
+                .with_attributes(other_segment_meta.attributes().clone());
+            if other_segment_meta.has_deletes() {
+                // The deletes of this index committed so far do not apply to the added segment.
+                segment_meta = segment_meta
+                    .with_delete_meta(other_segment_meta.num_deleted_docs(), index_meta.opstamp);
+            }
+            for &component in SegmentComponent::all() {
+                let path = other_segment_meta.relative_path(component);
+                if component == SegmentComponent::TempStore
+                    || (component == SegmentComponent::Delete && !other_segment_meta.has_deletes())
+                    // Segments written before bloom filters were introduced have none.
+                    || (component == SegmentComponent::BloomFilter
+                        && !other.directory().exists(&path)?)
+                {
+                    continue;
+                }
+                let file_slice = other.directory().open_read(&path)?;
+                let mut write = self
+                    .directory()
+                    .open_write(&segment_meta.relative_path(component))?;
+                write.write_all(file_slice.read_bytes()?.as_slice())?;
+                write.terminate()?;
+            }
+            segment_ids.push(segment_meta.id());
+            index_meta.segments.push(segment_meta);
+        }
+        save_metas(&index_meta, self.directory())?;
+        Ok(segment_ids)
+    }
+
+    /// Enables the retention of generations.
+    ///
+    /// A generation is the state of the index after a commit, identified by the opstamp
+    /// of that commit. Once retention is enabled, the segment files of the last commit and
+    /// of all of the following ones are kept alive, even after their segments got merged
+    /// or their deletes were updated, until the generation is released with
+    /// [`Index::release_generation`]. This makes it possible for an external consumer, e.g.
+    /// a replication shipper, to copy the files of a generation without having them
+    /// garbage collected underneath.
+    ///
+    /// Retention is shared by this `Index` and its clones, including the one held by the
+    /// `IndexWriter`, and has to be enabled before the commits to retain.
+    pub fn enable_generation_retention(&self) -> crate::Result<()> {
+        let mut retained_generations_opt = self.retained_generations.lock().unwrap();
+        if retained_generations_opt.is_none() {
+            let index_meta = self.load_metas()?;
+            let mut retained_generations = BTreeMap::new();
+            retained_generations.insert(index_meta.opstamp, index_meta);
+            *retained_generations_opt = Some(retained_generations);
+        }
+        Ok(())
+    }
+
+    /// Returns the metas of the retained generations, from the oldest to the most recent.
+    ///
+    /// This is empty if retention is not enabled.
+    pub fn retained_generations(&self) -> Vec<IndexMeta> {
+        self.retained_generations
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|retained_generations| retained_generations.values().cloned())
+            .collect()
+    }
+
+    /// Acknowledges the generation identified by `opstamp`, releasing it along with all of
+    /// the older generations.
+    ///
+    /// Their files which are not used by a more recent generation are deleted by the next
+    /// garbage collection.
+    pub fn release_generation(&self, opstamp: Opstamp) {
+        if let Some(retained_generations) = self.retained_generations.lock().unwrap().as_mut() {
+            *retained_generations = retained_generations.split_off(&opstamp.saturating_add(1));
+        }
+    }
+
+    /// Retains the generation described by `index_meta`, if retention is enabled.
+    ///
+    /// Merges save metas without creating a new generation: the metas retained for a
+    /// generation are the ones of its commit.
+    pub(crate) fn retain_generation(&self, index_meta: &IndexMeta) {
+        if let Some(retained_generations) = self.retained_generations.lock().unwrap().as_mut() {
+            retained_generations
+                .entry(index_meta.opstamp)
+                .or_insert_with(|| index_meta.clone());
+        }
+    }
+
     /// Returns the set of corrupted files
     pub fn validate_checksum(&self) -> crate::Result<HashSet<PathBuf>> {
         let managed_files = self.directory.list_managed_files();
@@ -572,6 +1033,38 @@ impl Index {
     }
 }
 
+/// Returns the tokenizer of `tokenizer_manager` associated to the text or json field `field`.
+pub(crate) fn tokenizer_for_field(
+    schema: &Schema,
+    tokenizer_manager: &TokenizerManager,
+    field: Field,
+) -> crate::Result<TextAnalyzer> {
+    let field_entry = schema.get_field_entry(field);
+    let field_type = field_entry.field_type();
+    let indexing_options_opt = match field_type {
+        FieldType::JsonObject(options) => options.get_text_indexing_options(),
+        FieldType::Str(options) => options.get_indexing_options(),
+        _ => {
+            return Err(TantivyError::SchemaError(format!(
+                "{:?} is not a text field.",
+                field_entry.name()
+            )))
+        }
+    };
+    let indexing_options = indexing_options_opt.ok_or_else(|| {
+        TantivyError::InvalidArgument(format!(
+            "No indexing options set for field {:?}",
+            field_entry
+        ))
+    })?;
+
+    tokenizer_manager
+        .get(indexing_options.tokenizer())
+        .ok_or_else(|| {
+            TantivyError::InvalidArgument(format!("No Tokenizer found for field {:?}", field_entry))
+        })
+}
+
 impl fmt::Debug for Index {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Index({:?})", self.directory)
@@ -580,10 +1073,22 @@ impl fmt::Debug for Index {
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::collector::TopDocs;
+    use crate::core::META_FILEPATH;
+    use crate::directory::meta_generations::{meta_generation_filepath, META_GENERATION_FILEPATH};
     use crate::directory::{RamDirectory, WatchCallback};
-    use crate::schema::{Field, Schema, INDEXED, TEXT};
+    use crate::fastfield::FastFieldReader;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::TermQuery;
+    use crate::schema::{Field, IndexRecordOption, Schema, FAST, INDEXED, STORED, TEXT};
     use crate::tokenizer::TokenizerManager;
-    use crate::{Directory, Index, IndexBuilder, IndexReader, IndexSettings, ReloadPolicy};
+    use crate::{
+        Directory, DocAddress, FreshnessPolicy, Index, IndexBuilder, IndexReader, IndexSettings,
+        Opstamp, ReloadPolicy, TantivyError, Term,
+    };
 
     #[test]
     fn test_indexer_for_field() {
@@ -614,6 +1119,279 @@ mod tests {
         assert!(index.tokenizers().get("raw").is_none());
     }
 
+    #[test]
+    fn test_generation_retention() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text => "a"))?;
+        let first_opstamp = index_writer.commit()?;
+        assert!(index.retained_generations().is_empty());
+        index.enable_generation_retention()?;
+        index_writer.add_document(doc!(text => "b"))?;
+        let second_opstamp = index_writer.commit()?;
+        let retained_opstamps: Vec<Opstamp> = index
+            .retained_generations()
+            .iter()
+            .map(|index_meta| index_meta.opstamp)
+            .collect();
+        assert_eq!(retained_opstamps, vec![first_opstamp, second_opstamp]);
+        let retained_files: Vec<PathBuf> = index
+            .searchable_segment_metas()?
+            .iter()
+            .flat_map(|segment_meta| segment_meta.list_files())
+            .filter(|path| index.directory().exists(path).unwrap())
+            .collect();
+        assert!(!retained_files.is_empty());
+
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.garbage_collect_files().wait()?;
+        for path in &retained_files {
+            assert!(index.directory().exists(path)?);
+        }
+
+        index.release_generation(first_opstamp);
+        assert_eq!(index.retained_generations().len(), 1);
+        index.release_generation(second_opstamp);
+        assert!(index.retained_generations().is_empty());
+        index_writer.garbage_collect_files().wait()?;
+        for path in &retained_files {
+            assert!(!index.directory().exists(path)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for id_value in 0..30u64 {
+            index_writer.add_document(doc!(id => id_value, text => format!("doc {}", id_value)))?;
+            if id_value % 10 == 9 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.delete_term(Term::from_field_u64(id, 4));
+        index_writer.delete_term(Term::from_field_u64(id, 25));
+        index_writer.commit()?;
+        assert_eq!(index.searchable_segment_ids()?.len(), 3);
+
+        let output_directories = vec![
+            RamDirectory::create(),
+            RamDirectory::create(),
+            RamDirectory::create(),
+            RamDirectory::create(),
+        ];
+        let shards = index.split(id, output_directories, |id_value: u64| {
+            (id_value % 3) as usize
+        })?;
+        assert_eq!(shards.len(), 4);
+        for (rank, shard) in shards.iter().enumerate() {
+            assert_eq!(shard.schema(), index.schema());
+            let searcher = shard.reader()?.searcher();
+            let mut id_values = Vec::new();
+            for segment_reader in searcher.segment_readers() {
+                let store_reader = segment_reader.get_store_reader(1)?;
+                let ff_reader = segment_reader.fast_fields().u64(id)?;
+                for doc in segment_reader.doc_ids_alive() {
+                    let id_value = ff_reader.get(doc);
+                    let stored_doc = store_reader.get(doc)?;
+                    assert_eq!(
+                        stored_doc.get_first(text).and_then(|value| value.as_text()),
+                        Some(format!("doc {}", id_value).as_str())
+                    );
+                    id_values.push(id_value);
+                }
+            }
+            id_values.sort_unstable();
+            let expected_id_values: Vec<u64> = (0..30u64)
+                .filter(|id_value| (id_value % 3) as usize == rank)
+                .filter(|&id_value| id_value != 4 && id_value != 25)
+                .collect();
+            assert_eq!(id_values, expected_id_values);
+        }
+
+        let output_directories = vec![RamDirectory::create(), RamDirectory::create()];
+        assert!(matches!(
+            index.split(id, output_directories, |id_value: u64| id_value as usize),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        let output_directories = vec![RamDirectory::create()];
+        assert!(matches!(
+            index.split(id, output_directories, |_: i64| 0),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deterministic_segment_ids() -> crate::Result<()> {
+        let build_index = || -> crate::Result<Vec<(PathBuf, Vec<u8>)>> {
+            let mut schema_builder = Schema::builder();
+            let text = schema_builder.add_text_field("text", TEXT | STORED);
+            let index = Index::create_in_ram(schema_builder.build());
+            index.set_deterministic_segment_ids(42);
+            let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+            index_writer.set_merge_policy(Box::new(NoMergePolicy));
+            for commit in 0..3 {
+                for doc in 0..10 {
+                    index_writer.add_document(doc!(text => format!("{commit} {doc}")))?;
+                }
+                index_writer.commit()?;
+            }
+            drop(index_writer);
+            let mut files: Vec<PathBuf> =
+                index.directory().list_managed_files().into_iter().collect();
+            files.push(PathBuf::from(".managed.json"));
+            files.sort();
+            files
+                .into_iter()
+                .map(|path| {
+                    let data = index.directory().atomic_read(&path)?;
+                    Ok((path, data))
+                })
+                .collect()
+        };
+        let files = build_index()?;
+        assert!(files.iter().any(|(path, _)| path.ends_with("meta.json")));
+        assert_eq!(build_index()?, files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_meta_generations() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            throw_away_schema(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        let mut commit_opstamp = 0;
+        for _ in 0..3 {
+            commit_opstamp = index_writer.commit()?;
+        }
+        drop(index_writer);
+        // Index creation and the 3 commits each wrote a generation.
+        assert_eq!(
+            directory.atomic_read(&META_GENERATION_FILEPATH)?,
+            b"4".to_vec()
+        );
+        assert!(directory.exists(&meta_generation_filepath(3))?);
+        assert!(!directory.exists(&meta_generation_filepath(2))?);
+
+        // A partially written meta.json is not read.
+        let meta_data = directory.atomic_read(&META_FILEPATH)?;
+        directory.atomic_write(&META_FILEPATH, &meta_data[..meta_data.len() / 2])?;
+        let index = Index::open(directory)?;
+        assert_eq!(index.load_metas()?.opstamp, commit_opstamp);
+        Ok(())
+    }
+
+    #[test]
+    fn test_meta_generations_corrupt_pointer() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            throw_away_schema(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.commit()?;
+
+        // The garbage collection aborts rather than deleting the generations, and the next
+        // commit restarts at the first generation.
+        directory.atomic_write(&META_GENERATION_FILEPATH, b"not a generation")?;
+        assert!(index_writer.garbage_collect_files().wait().is_err());
+        let commit_opstamp = index_writer.commit()?;
+        assert_eq!(
+            directory.atomic_read(&META_GENERATION_FILEPATH)?,
+            b"1".to_vec()
+        );
+        let meta_data = directory.atomic_read(&META_FILEPATH)?;
+        directory.atomic_write(&META_FILEPATH, &meta_data[..meta_data.len() / 2])?;
+        assert_eq!(
+            Index::open(directory.clone())?.load_metas()?.opstamp,
+            commit_opstamp
+        );
+        directory.atomic_write(&META_FILEPATH, &meta_data)?;
+
+        // A pointer to a missing generation does not hide a partially written meta.json.
+        directory.atomic_write(&META_GENERATION_FILEPATH, b"7")?;
+        let meta_data = directory.atomic_read(&META_FILEPATH)?;
+        directory.atomic_write(&META_FILEPATH, &meta_data[..meta_data.len() / 2])?;
+        assert!(Index::open(directory).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_segments_from() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field("id", INDEXED | STORED);
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let other_index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id => 0u64, text => "hello"))?;
+        index_writer.commit()?;
+        let mut other_index_writer = other_index.writer_for_tests()?;
+        other_index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        other_index_writer.add_document(doc!(id => 1u64, text => "hello"))?;
+        other_index_writer.add_document(doc!(id => 2u64, text => "hello"))?;
+        other_index_writer.commit()?;
+        other_index_writer.add_document(doc!(id => 3u64, text => "hello"))?;
+        other_index_writer.commit()?;
+        other_index_writer.delete_term(Term::from_field_u64(id, 2));
+        other_index_writer.commit()?;
+        assert!(matches!(
+            index.add_segments_from(&other_index, true),
+            Err(TantivyError::LockFailure(..))
+        ));
+        drop(index_writer);
+
+        let segment_ids = index.add_segments_from(&other_index, true)?;
+        assert_eq!(segment_ids.len(), 2);
+        assert_eq!(index.searchable_segment_ids()?.len(), 3);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.delete_term(Term::from_field_u64(id, 3));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let hello_query = TermQuery::new(
+            Term::from_field_text(text, "hello"),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&hello_query, &TopDocs::with_limit(10))?;
+        let mut id_values: Vec<u64> = top_docs
+            .iter()
+            .map(|(_, doc_address)| {
+                let doc = searcher.doc(*doc_address)?;
+                Ok(doc.get_first(id).and_then(|value| value.as_u64()).unwrap())
+            })
+            .collect::<crate::Result<_>>()?;
+        id_values.sort_unstable();
+        assert_eq!(id_values, vec![0, 1]);
+        // The other index is left untouched.
+        assert_eq!(other_index.reader()?.searcher().num_docs(), 2);
+
+        let incompatible_index = Index::create_in_ram(Schema::builder().build());
+        drop(index_writer);
+        assert!(matches!(
+            index.add_segments_from(&incompatible_index, true),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_index_exists() {
         let directory: Box<dyn Directory> = Box::new(RamDirectory::create());
@@ -704,6 +1482,149 @@ mod tests {
         test_index_on_commit_reload_policy_aux(field, &index, &reader)
     }
 
+    #[test]
+    fn test_index_reader_wait_for_opstamp() -> crate::Result<()> {
+        let schema = throw_away_schema();
+        let field = schema.get_field("num_likes").unwrap();
+        let index = Index::create_in_ram(schema);
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommit)
+            .try_into()?;
+        assert!(reader.wait_for(0, Duration::ZERO));
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(field => 1u64))?;
+        let opstamp = writer.commit()?;
+        assert!(reader.wait_for(opstamp, Duration::from_secs(10)));
+        let searcher = reader.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        assert_eq!(searcher.generation().opstamp(), opstamp);
+        assert!(!reader.wait_for(opstamp + 1, Duration::from_millis(10)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_reader_reload_to() -> crate::Result<()> {
+        let schema = throw_away_schema();
+        let field = schema.get_field("num_likes").unwrap();
+        let index = Index::create_in_ram(schema);
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        reader.reload_to(0)?;
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(field => 1u64))?;
+        let opstamp = writer.commit()?;
+        reader.reload_to(opstamp)?;
+        assert_eq!(reader.searcher().generation().opstamp(), opstamp);
+        assert_eq!(reader.searcher().num_docs(), 1);
+
+        writer.add_document(doc!(field => 2u64))?;
+        let prepared_commit = writer.prepare_commit()?;
+        let next_opstamp = prepared_commit.opstamp();
+        assert!(reader.reload_to(next_opstamp).is_err());
+        prepared_commit.commit()?;
+        // The reader is not reloaded for an older commit.
+        reader.reload_to(opstamp)?;
+        assert_eq!(reader.searcher().num_docs(), 1);
+        reader.reload_to(next_opstamp)?;
+        assert_eq!(reader.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_reader_searcher_with_freshness_policy() -> crate::Result<()> {
+        let schema = throw_away_schema();
+        let field = schema.get_field("num_likes").unwrap();
+        let index = Index::create_in_ram(schema);
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(field => 1u64))?;
+        let opstamp = writer.commit()?;
+        assert_eq!(reader.searcher_with(FreshnessPolicy::Any)?.num_docs(), 0);
+        assert_eq!(
+            reader
+                .searcher_with(FreshnessPolicy::AtLeast(opstamp))?
+                .num_docs(),
+            1
+        );
+        writer.add_document(doc!(field => 2u64))?;
+        writer.commit()?;
+        assert_eq!(
+            reader
+                .searcher_with(FreshnessPolicy::AtLeast(opstamp))?
+                .num_docs(),
+            1
+        );
+        assert_eq!(reader.searcher_with(FreshnessPolicy::Reload)?.num_docs(), 2);
+        let uncommitted_opstamp = writer.add_document(doc!(field => 3u64))?;
+        assert!(matches!(
+            reader.searcher_with(FreshnessPolicy::AtLeast(uncommitted_opstamp)),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_reader_max_memory_bytes() -> crate::Result<()> {
+        let schema = throw_away_schema();
+        let field = schema.get_field("num_likes").unwrap();
+        let index = Index::create_in_ram(schema);
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .num_searchers(2)
+            .max_memory_bytes(1)
+            .max_memory_wait(Duration::from_secs(60))
+            .try_into()?;
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(field => 1u64))?;
+        writer.commit()?;
+        reader.reload()?;
+        let old_searcher = reader.searcher();
+        let old_generation_id = old_searcher.generation().generation_id();
+        assert_eq!(reader.memory_usage().generations[&old_generation_id], 0);
+        // Fetching a document caches its decompressed store block.
+        old_searcher.doc(DocAddress::new(0, 0))?;
+        let memory_usage = reader.memory_usage();
+        assert!(memory_usage.generations[&old_generation_id] > 0);
+        assert_eq!(memory_usage.results_cache, 0);
+        assert_eq!(
+            memory_usage.total,
+            memory_usage.generations[&old_generation_id]
+        );
+
+        // Only the generation being replaced is alive, there is nothing to wait for.
+        writer.add_document(doc!(field => 2u64))?;
+        writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().num_docs(), 2);
+        let memory_usage = reader.memory_usage();
+        assert_eq!(memory_usage.generations.len(), 2);
+        assert_eq!(
+            memory_usage.total,
+            memory_usage.generations[&old_generation_id]
+        );
+
+        writer.add_document(doc!(field => 3u64))?;
+        writer.commit()?;
+        let reload_handle = {
+            let reader = reader.clone();
+            std::thread::spawn(move || reader.reload())
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(reader.searcher().num_docs(), 2);
+        assert_eq!(reader.memory_usage().generations.len(), 3);
+        drop(old_searcher);
+        reload_handle.join().unwrap()?;
+        assert_eq!(reader.searcher().num_docs(), 3);
+        Ok(())
+    }
+
     #[cfg(feature = "mmap")]
     mod mmap_specific {
 
@@ -712,8 +1633,38 @@ mod tests {
         use tempfile::TempDir;
 
         use super::*;
+        use crate::directory::MmapDirectory;
         use crate::Directory;
 
+        #[test]
+        fn test_index_export_import() -> crate::Result<()> {
+            let schema = throw_away_schema();
+            let field = schema.get_field("num_likes").unwrap();
+            let index = Index::create_in_ram(schema);
+            let mut writer = index.writer_for_tests()?;
+            for i in 0..10u64 {
+                writer.add_document(doc!(field => i))?;
+            }
+            writer.commit()?;
+            let archive_dir = TempDir::new().unwrap();
+            let archive_path = archive_dir.path().join("index.archive");
+            index.export(&archive_path)?;
+
+            let tempdir = TempDir::new().unwrap();
+            let imported_index =
+                Index::import(&archive_path, MmapDirectory::open(tempdir.path())?)?;
+            assert_eq!(imported_index.schema(), index.schema());
+            assert_eq!(imported_index.reader()?.searcher().num_docs(), 10);
+            assert!(imported_index.validate_checksum()?.is_empty());
+
+            // The destination must not already contain an index.
+            assert!(matches!(
+                Index::import(&archive_path, MmapDirectory::open(tempdir.path())?),
+                Err(crate::TantivyError::InvalidArgument(_))
+            ));
+            Ok(())
+        }
+
         #[test]
         fn test_index_on_commit_reload_policy_mmap() -> crate::Result<()> {
             let schema = throw_away_schema();
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+#[cfg(feature = "mmap")]
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::directory::error::OpenReadError;
+#[cfg(feature = "mmap")]
+use crate::directory::MmapDirectory;
+use crate::directory::{Directory, Lock};
+#[cfg(feature = "mmap")]
+use crate::Index;
+
+/// The aliases file maps alias names to their targets.
+static ALIASES_FILEPATH: Lazy<&'static Path> = Lazy::new(|| Path::new("aliases.json"));
+
+/// The aliases lock serializes the updates of the aliases file,
+/// in this process or in a different process.
+static ALIASES_LOCK: Lazy<Lock> = Lazy::new(|| Lock {
+    filepath: "aliases.lock".into(),
+    is_blocking: true,
+});
+
+#[derive(Default, Serialize, Deserialize)]
+struct AliasesFile {
+    aliases: BTreeMap<String, String>,
+}
+
+/// Registry mapping logical names to indexes.
+///
+/// The registry lives in a directory, next to the directories of the indexes, and maps
+/// each alias to a target: typically the name of the directory of an index.
+/// Updates are written atomically, so that readers either see the former target of an alias
+/// or its new one, which makes it possible to build a new index and then flip the alias
+/// searches go through.
+///
+/// The registry is read again upon each call, so that the updates made by other registries
+/// over the same directory, possibly in other processes, are visible.
+pub struct IndexAliases {
+    directory: Box<dyn Directory>,
+    #[cfg(feature = "mmap")]
+    root_path_opt: Option<PathBuf>,
+}
+
+impl IndexAliases {
+    /// Opens the aliases registry of the directory.
+    pub fn open<D: Into<Box<dyn Directory>>>(directory: D) -> IndexAliases {
+        IndexAliases {
+            directory: directory.into(),
+            #[cfg(feature = "mmap")]
+            root_path_opt: None,
+        }
+    }
+
+    /// Opens the aliases registry of the directory `root_path`, whose targets are the names
+    /// of index directories within `root_path`.
+    #[cfg(feature = "mmap")]
+    pub fn open_in_dir<P: AsRef<Path>>(root_path: P) -> crate::Result<IndexAliases> {
+        let root_path = root_path.as_ref();
+        let directory = MmapDirectory::open(root_path)?;
+        Ok(IndexAliases {
+            directory: Box::new(directory),
+            root_path_opt: Some(root_path.to_path_buf()),
+        })
+    }
+
+    /// Returns all of the aliases, along with their target.
+    pub fn aliases(&self) -> crate::Result<BTreeMap<String, String>> {
+        Ok(self.load()?.aliases)
+    }
+
+    /// Returns the target of the alias, if it exists.
+    pub fn resolve(&self, alias: &str) -> crate::Result<Option<String>> {
+        Ok(self.load()?.aliases.remove(alias))
+    }
+
+    /// Points the alias to `target`, creating the alias if needed.
+    ///
+    /// `target` must be a single file name, with no directory separator.
+    /// Returns the former target of the alias.
+    pub fn set(&self, alias: &str, target: &str) -> crate::Result<Option<String>> {
+        validate_target(target)?;
+        self.update(|aliases| aliases.insert(alias.to_string(), target.to_string()))
+    }
+
+    /// Points the alias to `target`, provided it currently points to `expected_target`.
+    ///
+    /// `expected_target` is `None` when the alias should not exist yet.
+    /// Returns `false`, leaving the alias untouched, if the alias was changed in the meantime.
+    pub fn swap(
+        &self,
+        alias: &str,
+        expected_target: Option<&str>,
+        target: &str,
+    ) -> crate::Result<bool> {
+        validate_target(target)?;
+        self.update(|aliases| {
+            if aliases.get(alias).map(String::as_str) != expected_target {
+                return false;
+            }
+            aliases.insert(alias.to_string(), target.to_string());
+            true
+        })
+    }
+
+    /// Removes the alias, returning its target.
+    pub fn remove(&self, alias: &str) -> crate::Result<Option<String>> {
+        self.update(|aliases| aliases.remove(alias))
+    }
+
+    /// Opens the index the alias points to, if the alias exists.
+    ///
+    /// The target of the alias is the name of the index directory, within the directory
+    /// of the registry.
+    #[cfg(feature = "mmap")]
+    pub fn open_index(&self, alias: &str) -> crate::Result<Option<Index>> {
+        let root_path = self.root_path_opt.as_ref().ok_or_else(|| {
+            crate::TantivyError::InvalidArgument(
+                "Opening the index of an alias requires the registry to be opened with \
+                 `IndexAliases::open_in_dir`."
+                    .to_string(),
+            )
+        })?;
+        self.resolve(alias)?
+            .map(|target| {
+                validate_target(&target)?;
+                Index::open_in_dir(root_path.join(target))
+            })
+            .transpose()
+    }
+
+    fn load(&self) -> crate::Result<AliasesFile> {
+        match self.directory.atomic_read(&ALIASES_FILEPATH) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(OpenReadError::FileDoesNotExist(_)) => Ok(AliasesFile::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn update<T>(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, String>) -> T,
+    ) -> crate::Result<T> {
+        let _aliases_lock = self.directory.acquire_lock(&ALIASES_LOCK)?;
+        let mut aliases_file = self.load()?;
+        let former_aliases = aliases_file.aliases.clone();
+        let res = update(&mut aliases_file.aliases);
+        // A failed swap, or the removal of a missing alias, leaves the file untouched.
+        if aliases_file.aliases != former_aliases {
+            let data = serde_json::to_vec_pretty(&aliases_file)?;
+            self.directory.atomic_write(&ALIASES_FILEPATH, &data)?;
+        }
+        Ok(res)
+    }
+}
+
+/// Checks that the target is the name of an entry of the registry directory, so that
+/// opening the index of an alias cannot escape it.
+fn validate_target(target: &str) -> crate::Result<()> {
+    let mut components = Path::new(target).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(crate::TantivyError::InvalidArgument(format!(
+            "Invalid alias target {:?}: expected a single directory name.",
+            target
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{IndexAliases, ALIASES_FILEPATH};
+    use crate::directory::{Directory, RamDirectory};
+
+    #[test]
+    fn test_index_aliases() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let index_aliases = IndexAliases::open(directory.clone());
+        assert_eq!(index_aliases.resolve("products")?, None);
+        assert_eq!(index_aliases.set("products", "products-1")?, None);
+        assert_eq!(
+            index_aliases.resolve("products")?.as_deref(),
+            Some("products-1")
+        );
+
+        // The updates are visible to the registries over the same directory.
+        let other_index_aliases = IndexAliases::open(directory);
+        assert!(!other_index_aliases.swap("products", None, "products-2")?);
+        assert!(other_index_aliases.swap("products", Some("products-1"), "products-2")?);
+        assert!(!index_aliases.swap("products", Some("products-1"), "products-3")?);
+        assert_eq!(
+            index_aliases.resolve("products")?.as_deref(),
+            Some("products-2")
+        );
+        assert_eq!(index_aliases.aliases()?.len(), 1);
+        assert_eq!(
+            index_aliases.remove("products")?.as_deref(),
+            Some("products-2")
+        );
+        assert!(index_aliases.aliases()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_aliases_invalid_target() -> crate::Result<()> {
+        let index_aliases = IndexAliases::open(RamDirectory::create());
+        for target in ["", "..", "/products", "../products", "products/1", "./products"] {
+            assert!(index_aliases.set("products", target).is_err());
+            assert!(index_aliases.swap("products", None, target).is_err());
+        }
+        assert!(index_aliases.aliases()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_aliases_failed_swap_does_not_write() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let index_aliases = IndexAliases::open(directory.clone());
+        assert!(!index_aliases.swap("products", Some("products-1"), "products-2")?);
+        assert!(!directory.exists(&ALIASES_FILEPATH)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_aliases_concurrent_swaps() -> crate::Result<()> {
+        let index_aliases = Arc::new(IndexAliases::open(RamDirectory::create()));
+        index_aliases.set("alias", "0")?;
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let index_aliases = index_aliases.clone();
+                thread::spawn(move || -> crate::Result<usize> {
+                    let mut num_swaps = 0;
+                    while num_swaps < 10 {
+                        let target = index_aliases.resolve("alias")?.unwrap();
+                        let next_target = (target.parse::<usize>().unwrap() + 1).to_string();
+                        if index_aliases.swap("alias", Some(&target), &next_target)? {
+                            num_swaps += 1;
+                        }
+                    }
+                    Ok(num_swaps)
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        assert_eq!(index_aliases.resolve("alias")?.as_deref(), Some("40"));
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_index_aliases_open_index() -> crate::Result<()> {
+        use crate::schema::{Schema, TEXT};
+        use crate::Index;
+
+        let root = tempfile::TempDir::new()?;
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        for (name, num_docs) in [("index-1", 1), ("index-2", 2)] {
+            std::fs::create_dir(root.path().join(name))?;
+            let index = Index::create_in_dir(root.path().join(name), schema.clone())?;
+            let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+            for _ in 0..num_docs {
+                index_writer.add_document(doc!(text => "a"))?;
+            }
+            index_writer.commit()?;
+        }
+        let index_aliases = IndexAliases::open_in_dir(root.path())?;
+        assert!(index_aliases.open_index("index")?.is_none());
+        index_aliases.set("index", "index-1")?;
+        let index = index_aliases.open_index("index")?.unwrap();
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+        index_aliases.swap("index", Some("index-1"), "index-2")?;
+        let index = index_aliases.open_index("index")?.unwrap();
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+
+        let ram_index_aliases = IndexAliases::open(crate::directory::RamDirectory::create());
+        ram_index_aliases.set("index", "index-1")?;
+        assert!(ram_index_aliases.open_index("index").is_err());
+        Ok(())
+    }
+}
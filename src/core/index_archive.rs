@@ -0,0 +1,298 @@
+//! Portable archive format for a whole index.
+//!
+//! An archive is a single stream containing:
+//! - a magic number and the version of the archive format,
+//! - a json header describing the tantivy version the index was written with and
+//!   the list of the files it contains, along with their length,
+//! - the content of each file (without its footer), followed by its crc32 checksum.
+//!
+//! The first file is always `meta.json`, which holds the schema, the settings and the
+//! list of segments of the index.
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use common::{BinarySerializable, HasLen};
+use crc32fast::Hasher;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{IndexMeta, SegmentComponent, META_FILEPATH};
+use crate::directory::error::Incompatibility;
+use crate::directory::{Directory, FileSlice, ManagedDirectory, TerminatingWrite};
+use crate::error::{DataCorruption, TantivyError};
+use crate::{is_supported_index_format_version, Index, Version};
+
+const ARCHIVE_MAGIC_NUMBER: u32 = 1_414_680_910;
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveHeader {
+    version: Version,
+    files: Vec<ArchivedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedFile {
+    path: PathBuf,
+    num_bytes: u64,
+}
+
+/// Writer computing the crc32 of the bytes written through it.
+struct CrcWriter<W> {
+    hasher: Hasher,
+    underlying: W,
+}
+
+impl<W: Write> CrcWriter<W> {
+    fn wrap(underlying: W) -> CrcWriter<W> {
+        CrcWriter {
+            hasher: Hasher::new(),
+            underlying,
+        }
+    }
+
+    fn finish(self) -> (u32, W) {
+        (self.hasher.finalize(), self.underlying)
+    }
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = self.underlying.write(buf)?;
+        self.hasher.update(&buf[..num_bytes]);
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.underlying.flush()
+    }
+}
+
+/// Writes the last commit of the index as an archive.
+pub(crate) fn write_archive<W: Write>(index: &Index, write: &mut W) -> crate::Result<()> {
+    // The segment metas are tracked by the inventory while we hold them,
+    // so their files cannot be garbage collected during the export.
+    let index_meta: IndexMeta = index.load_metas()?;
+    let meta_json = serde_json::to_vec_pretty(&index_meta)?;
+    let mut files: Vec<(PathBuf, FileSlice)> = Vec::new();
+    for segment_meta in &index_meta.segments {
+        for &component in SegmentComponent::all() {
+            let path = segment_meta.relative_path(component);
+            if component == SegmentComponent::TempStore
+                || (component == SegmentComponent::Delete && !segment_meta.has_deletes())
+                // Segments written before bloom filters were introduced have none.
+                || (component == SegmentComponent::BloomFilter
+                    && !index.directory().exists(&path)?)
+            {
+                continue;
+            }
+            let file_slice = index.directory().open_read(&path)?;
+            files.push((path, file_slice));
+        }
+    }
+
+    let mut archived_files = vec![ArchivedFile {
+        path: META_FILEPATH.to_path_buf(),
+        num_bytes: meta_json.len() as u64,
+    }];
+    archived_files.extend(files.iter().map(|(path, file_slice)| ArchivedFile {
+        path: path.clone(),
+        num_bytes: file_slice.len() as u64,
+    }));
+    let header = ArchiveHeader {
+        version: crate::version().clone(),
+        files: archived_files,
+    };
+    BinarySerializable::serialize(&ARCHIVE_MAGIC_NUMBER, write)?;
+    BinarySerializable::serialize(&ARCHIVE_FORMAT_VERSION, write)?;
+    BinarySerializable::serialize(&serde_json::to_string(&header)?, write)?;
+
+    write_file(write, &meta_json)?;
+    for (_, file_slice) in files {
+        write_file(write, file_slice.read_bytes()?.as_slice())?;
+    }
+    write.flush()?;
+    Ok(())
+}
+
+fn write_file<W: Write>(write: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut crc_writer = CrcWriter::wrap(write);
+    crc_writer.write_all(data)?;
+    let (crc, write) = crc_writer.finish();
+    BinarySerializable::serialize(&crc, write)
+}
+
+/// Reads an archive and writes its files into the directory.
+///
+/// `meta.json` is written last, so that the directory does not contain an index until
+/// all of the segment files have been written and checked.
+pub(crate) fn read_archive<R: Read>(
+    read: &mut R,
+    directory: &ManagedDirectory,
+) -> crate::Result<()> {
+    let magic_number = <u32 as BinarySerializable>::deserialize(read)?;
+    if magic_number != ARCHIVE_MAGIC_NUMBER {
+        return Err(DataCorruption::comment_only("This is not a tantivy index archive.").into());
+    }
+    let archive_format_version = <u32 as BinarySerializable>::deserialize(read)?;
+    if archive_format_version != ARCHIVE_FORMAT_VERSION {
+        return Err(DataCorruption::comment_only(format!(
+            "Unsupported archive format version {}, expected {}.",
+            archive_format_version, ARCHIVE_FORMAT_VERSION
+        ))
+        .into());
+    }
+    let header: ArchiveHeader =
+        serde_json::from_str(&<String as BinarySerializable>::deserialize(read)?)?;
+    if !is_supported_index_format_version(header.version.index_format_version) {
+        return Err(TantivyError::IncompatibleIndex(
+            Incompatibility::IndexMismatch {
+                library_version: crate::version().clone(),
+                index_version: header.version,
+            },
+        ));
+    }
+
+    let mut meta_json = None;
+    for archived_file in &header.files {
+        let path = archived_file.path.as_path();
+        if !is_plain_relative_path(path) {
+            return Err(DataCorruption::new(
+                path.to_path_buf(),
+                "Archived file paths must be relative to the index directory.".to_string(),
+            )
+            .into());
+        }
+        let mut file_read = read.by_ref().take(archived_file.num_bytes);
+        let crc = if path == *META_FILEPATH {
+            let mut crc_writer = CrcWriter::wrap(Vec::new());
+            io::copy(&mut file_read, &mut crc_writer)?;
+            let (crc, data) = crc_writer.finish();
+            meta_json = Some(data);
+            crc
+        } else {
+            let mut crc_writer = CrcWriter::wrap(directory.open_write(path)?);
+            io::copy(&mut file_read, &mut crc_writer)?;
+            let (crc, write_ptr) = crc_writer.finish();
+            write_ptr.terminate()?;
+            crc
+        };
+        if file_read.limit() != 0 {
+            return Err(DataCorruption::new(
+                path.to_path_buf(),
+                "The archive is truncated.".to_string(),
+            )
+            .into());
+        }
+        if <u32 as BinarySerializable>::deserialize(read)? != crc {
+            return Err(DataCorruption::new(
+                path.to_path_buf(),
+                "The checksum of the archived file does not match its content.".to_string(),
+            )
+            .into());
+        }
+    }
+    let meta_json = meta_json.ok_or_else(|| {
+        DataCorruption::new(
+            META_FILEPATH.to_path_buf(),
+            "The archive does not contain the meta file.".to_string(),
+        )
+    })?;
+    directory.sync_directory()?;
+    directory.atomic_write(&META_FILEPATH, &meta_json)?;
+    Ok(())
+}
+
+fn is_plain_relative_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{read_archive, write_archive};
+    use crate::collector::Count;
+    use crate::directory::{ManagedDirectory, RamDirectory};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STORED, TEXT};
+    use crate::{Index, TantivyError, Term};
+
+    fn build_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let num = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b", num => 1u64))?;
+        index_writer.add_document(doc!(text => "b c", num => 2u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a c", num => 3u64))?;
+        index_writer.delete_term(Term::from_field_text(text, "b"));
+        index_writer.add_document(doc!(text => "a", num => 4u64))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_archive_roundtrip() -> crate::Result<()> {
+        let index = build_index()?;
+        let mut archive = Vec::new();
+        write_archive(&index, &mut archive)?;
+
+        let ram_directory = RamDirectory::create();
+        let directory = ManagedDirectory::wrap(Box::new(ram_directory.clone()))?;
+        read_archive(&mut Cursor::new(&archive), &directory)?;
+        let imported_index = Index::open(ram_directory)?;
+        assert_eq!(imported_index.schema(), index.schema());
+        assert_eq!(
+            imported_index.searchable_segment_ids()?,
+            index.searchable_segment_ids()?
+        );
+        assert!(imported_index.validate_checksum()?.is_empty());
+
+        let text = index.schema().get_field("text").unwrap();
+        let searcher = imported_index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let term_query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        assert_eq!(searcher.search(&term_query, &Count)?, 2);
+
+        // The imported index can be written to.
+        let mut index_writer = imported_index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_corrupted() -> crate::Result<()> {
+        let index = build_index()?;
+        let mut archive = Vec::new();
+        write_archive(&index, &mut archive)?;
+
+        let mut corrupted_archive = archive.clone();
+        let meta_json_position = archive
+            .windows(b"opstamp".len())
+            .position(|window| window == b"opstamp")
+            .unwrap();
+        corrupted_archive[meta_json_position] ^= 1;
+        let directory = ManagedDirectory::wrap(Box::new(RamDirectory::create()))?;
+        assert!(matches!(
+            read_archive(&mut Cursor::new(&corrupted_archive), &directory),
+            Err(TantivyError::DataCorruption(_))
+        ));
+        assert!(!Index::exists(&directory)?);
+
+        let truncated_archive = &archive[..archive.len() - 10];
+        let directory = ManagedDirectory::wrap(Box::new(RamDirectory::create()))?;
+        assert!(read_archive(&mut Cursor::new(truncated_archive), &directory).is_err());
+        assert!(!Index::exists(&directory)?);
+
+        let directory = ManagedDirectory::wrap(Box::new(RamDirectory::create()))?;
+        assert!(matches!(
+            read_archive(&mut Cursor::new(b"not an archive".to_vec()), &directory),
+            Err(TantivyError::DataCorruption(_))
+        ));
+        Ok(())
+    }
+}
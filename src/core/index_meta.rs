@@ -1,5 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -8,9 +9,10 @@ use serde::{Deserialize, Serialize};
 
 use super::SegmentComponent;
 use crate::core::SegmentId;
+use crate::directory::error::Incompatibility;
 use crate::schema::Schema;
 use crate::store::Compressor;
-use crate::{Inventory, Opstamp, TrackedObject};
+use crate::{Inventory, Opstamp, TrackedObject, INDEX_FORMAT_VERSION};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct DeleteMeta {
@@ -39,6 +41,12 @@ impl SegmentMetaInventory {
             max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: None,
+            index_format_version: Some(INDEX_FORMAT_VERSION),
+            max_opstamp: None,
+            component_format_versions: SegmentComponent::all()
+                .map(|component| (component.name().to_string(), component.format_version()))
+                .collect(),
+            attributes: BTreeMap::new(),
         };
         SegmentMeta::from(self.inventory.track(inner))
     }
@@ -116,11 +124,11 @@ impl SegmentMeta {
             .include_temp_doc_store
             .load(std::sync::atomic::Ordering::Relaxed)
         {
-            SegmentComponent::iterator()
+            SegmentComponent::all()
                 .map(|component| self.relative_path(*component))
                 .collect::<HashSet<PathBuf>>()
         } else {
-            SegmentComponent::iterator()
+            SegmentComponent::all()
                 .filter(|comp| *comp != &SegmentComponent::TempStore)
                 .map(|component| self.relative_path(*component))
                 .collect::<HashSet<PathBuf>>()
@@ -142,6 +150,7 @@ impl SegmentMeta {
             SegmentComponent::FastFields => ".fast".to_string(),
             SegmentComponent::FieldNorms => ".fieldnorm".to_string(),
             SegmentComponent::Delete => format!(".{}.del", self.delete_opstamp().unwrap_or(0)),
+            SegmentComponent::BloomFilter => ".bloom".to_string(),
         });
         PathBuf::from(path)
     }
@@ -169,6 +178,73 @@ impl SegmentMeta {
             .map(|delete_meta| delete_meta.opstamp)
     }
 
+    /// Returns the index format version the segment was written with.
+    ///
+    /// Returns `None` for segments written by a version of tantivy that did not
+    /// record it. The format version of each file is also available in its footer.
+    pub fn index_format_version(&self) -> Option<u32> {
+        self.tracked.index_format_version
+    }
+
+    /// Returns the opstamp of the last document added to the segment, or the highest one of
+    /// the segments merged into it.
+    ///
+    /// Returns `None` for segments written by a version of tantivy that did not record it.
+    pub fn max_opstamp(&self) -> Option<Opstamp> {
+        self.tracked.max_opstamp
+    }
+
+    /// Returns the format version the component of the segment was written with.
+    ///
+    /// Returns `None` for segments written by a version of tantivy that did not
+    /// record it.
+    pub fn component_format_version(&self, component: SegmentComponent) -> Option<u32> {
+        self.tracked
+            .component_format_versions
+            .get(component.name())
+            .copied()
+    }
+
+    /// Checks that the format version the component was written with, if it was recorded,
+    /// can be read by this version of tantivy.
+    pub(crate) fn check_component_format_version(
+        &self,
+        component: SegmentComponent,
+    ) -> Result<(), Incompatibility> {
+        self.check_component_format_version_within(
+            component,
+            component.oldest_supported_format_version()..=component.format_version(),
+        )
+    }
+
+    /// Checks that the format version the component was written with, if it was recorded, is
+    /// within the range of versions a reader supports.
+    pub(crate) fn check_component_format_version_within(
+        &self,
+        component: SegmentComponent,
+        supported_format_versions: RangeInclusive<u32>,
+    ) -> Result<(), Incompatibility> {
+        if let Some(component_format_version) = self.component_format_version(component) {
+            if !supported_format_versions.contains(&component_format_version) {
+                return Err(Incompatibility::ComponentFormatMismatch {
+                    segment_id: self.id(),
+                    component,
+                    library_format_version: *supported_format_versions.end(),
+                    component_format_version,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the user attributes of the segment, e.g. the pipeline or the tenant its
+    /// documents come from.
+    ///
+    /// See `IndexWriter::set_segment_attributes`.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.tracked.attributes
+    }
+
     /// Returns true iff the segment meta contains
     /// delete information.
     pub fn has_deletes(&self) -> bool {
@@ -187,6 +263,10 @@ impl SegmentMeta {
             max_doc,
             deletes: None,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
+            index_format_version: inner_meta.index_format_version,
+            max_opstamp: inner_meta.max_opstamp,
+            component_format_versions: inner_meta.component_format_versions.clone(),
+            attributes: inner_meta.attributes.clone(),
         });
         SegmentMeta { tracked }
     }
@@ -207,6 +287,42 @@ impl SegmentMeta {
             max_doc: inner_meta.max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: Some(delete_meta),
+            index_format_version: inner_meta.index_format_version,
+            max_opstamp: inner_meta.max_opstamp,
+            component_format_versions: inner_meta.component_format_versions.clone(),
+            attributes: inner_meta.attributes.clone(),
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Records the opstamp of the last document added to the segment.
+    #[must_use]
+    pub(crate) fn with_max_opstamp(self, max_opstamp: Option<Opstamp>) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            deletes: inner_meta.deletes.clone(),
+            index_format_version: inner_meta.index_format_version,
+            max_opstamp,
+            component_format_versions: inner_meta.component_format_versions.clone(),
+            attributes: inner_meta.attributes.clone(),
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Replaces the user attributes of the segment.
+    #[must_use]
+    pub fn with_attributes(self, attributes: BTreeMap<String, String>) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            deletes: inner_meta.deletes.clone(),
+            index_format_version: inner_meta.index_format_version,
+            max_opstamp: inner_meta.max_opstamp,
+            component_format_versions: inner_meta.component_format_versions.clone(),
+            attributes,
         });
         SegmentMeta { tracked }
     }
@@ -222,6 +338,21 @@ struct InnerSegmentMeta {
     #[serde(skip)]
     #[serde(default = "default_temp_store")]
     pub(crate) include_temp_doc_store: Arc<AtomicBool>,
+    /// Index format version the segment was written with.
+    /// It is missing for segments written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_format_version: Option<u32>,
+    /// Opstamp of the last document added to the segment.
+    /// It is missing for segments written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_opstamp: Option<Opstamp>,
+    /// Format version of each component, keyed by component name.
+    /// It is empty for segments written before it was recorded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    component_format_versions: BTreeMap<String, u32>,
+    /// User attributes of the segment.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, String>,
 }
 fn default_temp_store() -> Arc<AtomicBool> {
     Arc::new(AtomicBool::new(false))
@@ -251,6 +382,13 @@ pub struct IndexSettings {
     #[serde(default = "default_docstore_blocksize")]
     /// The size of each block that will be compressed and written to disk
     pub docstore_blocksize: usize,
+    /// The size in bytes above which the stored text and bytes values are written to the
+    /// blob store of the index, rather than to the doc store (See
+    /// [`BlobStore`](crate::store::BlobStore)).
+    ///
+    /// Indexing fails if it is set, but the index has no blob store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docstore_blob_threshold: Option<usize>,
 }
 
 /// Must be a function to be compatible with serde defaults
@@ -264,6 +402,7 @@ impl Default for IndexSettings {
             sort_by_field: None,
             docstore_compression: Compressor::default(),
             docstore_blocksize: default_docstore_blocksize(),
+            docstore_blob_threshold: None,
         }
     }
 }
@@ -445,8 +584,10 @@ mod tests {
                 }),
                 docstore_compression: crate::store::Compressor::Zstd(ZstdCompressor {
                     compression_level: Some(4),
+                    dictionary_size: None,
                 }),
                 docstore_blocksize: 1_000_000,
+                docstore_blob_threshold: None,
             },
             segments: Vec::new(),
             schema,
@@ -1,12 +1,13 @@
 use std::io;
 
-use common::BinarySerializable;
+use common::{BinarySerializable, BitSet};
 
 use crate::directory::FileSlice;
 use crate::positions::PositionReader;
 use crate::postings::{BlockSegmentPostings, SegmentPostings, TermInfo};
 use crate::schema::{IndexRecordOption, Term};
 use crate::termdict::TermDictionary;
+use crate::DocId;
 
 /// The inverted index reader is in charge of accessing
 /// the inverted index associated to a specific field.
@@ -26,6 +27,7 @@ pub struct InvertedIndexReader {
     postings_file_slice: FileSlice,
     positions_file_slice: FileSlice,
     record_option: IndexRecordOption,
+    has_payloads: bool,
     total_num_tokens: u64,
 }
 
@@ -36,6 +38,7 @@ impl InvertedIndexReader {
         postings_file_slice: FileSlice,
         positions_file_slice: FileSlice,
         record_option: IndexRecordOption,
+        has_payloads: bool,
     ) -> io::Result<InvertedIndexReader> {
         let (total_num_tokens_slice, postings_body) = postings_file_slice.split(8);
         let total_num_tokens = u64::deserialize(&mut total_num_tokens_slice.read_bytes()?)?;
@@ -44,6 +47,7 @@ impl InvertedIndexReader {
             postings_file_slice: postings_body,
             positions_file_slice,
             record_option,
+            has_payloads,
             total_num_tokens,
         })
     }
@@ -56,6 +60,7 @@ impl InvertedIndexReader {
             postings_file_slice: FileSlice::empty(),
             positions_file_slice: FileSlice::empty(),
             record_option,
+            has_payloads: false,
             total_num_tokens: 0u64,
         }
     }
@@ -70,6 +75,23 @@ impl InvertedIndexReader {
         &self.termdict
     }
 
+    /// Returns the documents containing at least one term of the field, deleted or not.
+    pub(crate) fn docs_with_terms(&self, max_doc: DocId) -> io::Result<BitSet> {
+        let mut docs_with_terms = BitSet::with_max_value(max_doc);
+        let mut term_stream = self.termdict.stream()?;
+        while term_stream.advance() {
+            let mut block_postings = self
+                .read_block_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+            while !block_postings.docs().is_empty() {
+                for &doc in block_postings.docs() {
+                    docs_with_terms.insert(doc);
+                }
+                block_postings.advance();
+            }
+        }
+        Ok(docs_with_terms)
+    }
+
     /// Resets the block segment to another position of the postings
     /// file.
     ///
@@ -151,6 +173,7 @@ impl InvertedIndexReader {
         Ok(SegmentPostings::from_block_postings(
             block_postings,
             position_reader,
+            self.has_payloads,
         ))
     }
 
@@ -1,5 +1,7 @@
 mod executor;
 pub mod index;
+mod index_aliases;
+mod index_archive;
 mod index_meta;
 mod inverted_index_reader;
 pub mod searcher;
@@ -7,6 +9,7 @@ mod segment;
 mod segment_component;
 mod segment_id;
 mod segment_reader;
+mod slow_query;
 
 use std::path::Path;
 
@@ -14,15 +17,18 @@ use once_cell::sync::Lazy;
 
 pub use self::executor::Executor;
 pub use self::index::{Index, IndexBuilder};
+pub use self::index_aliases::IndexAliases;
 pub use self::index_meta::{
     IndexMeta, IndexSettings, IndexSortByField, Order, SegmentMeta, SegmentMetaInventory,
 };
 pub use self::inverted_index_reader::InvertedIndexReader;
-pub use self::searcher::{Searcher, SearcherGeneration};
+pub use self::searcher::{FieldStats, Searcher, SearcherGeneration};
 pub use self::segment::Segment;
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
+pub use self::slow_query::SlowQuery;
+pub(crate) use self::slow_query::SlowQueryHook;
 
 /// The meta file contains all the information about the list of segments and the schema
 /// of the index.
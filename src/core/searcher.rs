@@ -1,13 +1,33 @@
-use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::sync::Arc;
+use std::time::Instant;
 use std::{fmt, io};
 
+use common::BitSet;
+use serde::{Deserialize, Serialize};
+
 use crate::collector::Collector;
-use crate::core::{Executor, SegmentReader};
-use crate::query::Query;
-use crate::schema::{Document, Schema, Term};
-use crate::space_usage::SearcherSpaceUsage;
+use crate::core::index::tokenizer_for_field;
+use crate::core::{Executor, InvertedIndexReader, SegmentReader, SlowQuery, SlowQueryHook};
+use crate::fastfield::{
+    check_fast_value_type, AliveBitSet, DynamicFastFieldReader, FastFieldReader, FastValue,
+};
+use crate::postings::BlockSegmentPostings;
+use crate::query::{Query, QueryProfile, Scorer};
+use crate::reader::{GenerationMemory, ResultsCache};
+use crate::schema::{Document, Field, FieldType, IndexRecordOption, Schema, Term};
+use crate::space_usage::{ByteCount, SearcherSpaceUsage};
 use crate::store::{CacheStats, StoreReader};
-use crate::{DocAddress, Index, Opstamp, SegmentId, TrackedObject};
+use crate::termdict::TermMerger;
+use crate::tokenizer::{TextAnalyzer, TokenizerManager};
+use crate::{
+    DocAddress, DocId, DocSet, Index, Opstamp, SegmentId, SegmentOrdinal, TantivyError,
+    TrackedObject, TERMINATED,
+};
+
+// Segment ordinal, along with the positions and the doc ids of the addresses of the segment.
+type SegmentDocIds = (usize, Vec<usize>, Vec<DocId>);
 
 /// Identifies the searcher generation accessed by a [Searcher].
 ///
@@ -28,22 +48,32 @@ use crate::{DocAddress, Index, Opstamp, SegmentId, TrackedObject};
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SearcherGeneration {
     segments: BTreeMap<SegmentId, Option<Opstamp>>,
+    segment_num_bytes: BTreeMap<SegmentId, ByteCount>,
     generation_id: u64,
+    opstamp: Opstamp,
+    memory: GenerationMemory,
 }
 
 impl SearcherGeneration {
     pub(crate) fn from_segment_readers(
         segment_readers: &[SegmentReader],
         generation_id: u64,
+        opstamp: Opstamp,
+        memory: GenerationMemory,
     ) -> Self {
         let mut segment_id_to_del_opstamp = BTreeMap::new();
+        let mut segment_num_bytes = BTreeMap::new();
         for segment_reader in segment_readers {
             segment_id_to_del_opstamp
                 .insert(segment_reader.segment_id(), segment_reader.delete_opstamp());
+            segment_num_bytes.insert(segment_reader.segment_id(), segment_reader.num_bytes());
         }
         Self {
             segments: segment_id_to_del_opstamp,
+            segment_num_bytes,
             generation_id,
+            opstamp,
+            memory,
         }
     }
 
@@ -52,10 +82,54 @@ impl SearcherGeneration {
         self.generation_id
     }
 
+    /// Returns the opstamp of the commit loaded by this generation.
+    ///
+    /// All of the operations with a lower opstamp are visible to its searchers.
+    pub fn opstamp(&self) -> Opstamp {
+        self.opstamp
+    }
+
     /// Return a `(SegmentId -> DeleteOpstamp)` mapping.
     pub fn segments(&self) -> &BTreeMap<SegmentId, Option<Opstamp>> {
         &self.segments
     }
+
+    /// Returns the number of bytes of segment data held open by the generation.
+    ///
+    /// See [`SegmentReader::num_bytes`].
+    pub fn num_bytes(&self) -> ByteCount {
+        self.segment_num_bytes.values().sum()
+    }
+
+    /// Returns the number of bytes of segment data held open for each segment.
+    pub fn segment_num_bytes(&self) -> &BTreeMap<SegmentId, ByteCount> {
+        &self.segment_num_bytes
+    }
+
+    /// Returns the heap bytes held by the doc store caches of the searchers of the generation.
+    pub(crate) fn memory(&self) -> &GenerationMemory {
+        &self.memory
+    }
+}
+
+/// Statistics about the indexed content of a field, aggregated
+/// over all of the segments of a [Searcher].
+///
+/// See [`Searcher::field_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FieldStats {
+    /// Number of alive documents with at least one token indexed in the field.
+    pub doc_count: u64,
+    /// Sum of the term frequencies of all of the terms of the field, in the alive documents.
+    ///
+    /// The tokens of the deleted documents are subtracted using their fieldnorms,
+    /// which are approximate for documents with more than 40 tokens.
+    pub sum_total_term_freq: u64,
+    /// Number of distinct terms in the field, across all segments.
+    pub num_unique_terms: u64,
+    /// Average number of tokens per document having a value for the field,
+    /// or `0.0` if no document has a value.
+    pub average_field_length: f64,
 }
 
 /// Holds a list of `SegmentReader`s ready for search.
@@ -64,10 +138,15 @@ impl SearcherGeneration {
 /// the destruction of the `Searcher`.
 pub struct Searcher {
     schema: Schema,
-    index: Index,
+    index_opt: Option<Index>,
+    executor: Arc<Executor>,
+    tokenizers: TokenizerManager,
     segment_readers: Vec<SegmentReader>,
     store_readers: Vec<StoreReader>,
     generation: TrackedObject<SearcherGeneration>,
+    slow_query_hook_opt: Option<SlowQueryHook>,
+    // The results cache of the reader the searcher belongs to, if any.
+    results_cache_opt: Option<Arc<ResultsCache>>,
 }
 
 impl Searcher {
@@ -78,24 +157,96 @@ impl Searcher {
         segment_readers: Vec<SegmentReader>,
         generation: TrackedObject<SearcherGeneration>,
         doc_store_cache_size: usize,
+        slow_query_hook_opt: Option<SlowQueryHook>,
+        results_cache_opt: Option<Arc<ResultsCache>>,
+    ) -> io::Result<Searcher> {
+        let executor = index.shared_search_executor();
+        let tokenizers = index.tokenizers().clone();
+        Searcher::build(
+            schema,
+            Some(index),
+            executor,
+            tokenizers,
+            segment_readers,
+            generation,
+            doc_store_cache_size,
+            slow_query_hook_opt,
+            results_cache_opt,
+        )
+    }
+
+    /// Creates a `Searcher` over segments which do not belong to an `Index`, searching in
+    /// the calling thread.
+    pub(crate) fn without_index(
+        schema: Schema,
+        tokenizers: TokenizerManager,
+        segment_readers: Vec<SegmentReader>,
+        generation: TrackedObject<SearcherGeneration>,
+        doc_store_cache_size: usize,
+    ) -> io::Result<Searcher> {
+        Searcher::build(
+            schema,
+            None,
+            Arc::new(Executor::single_thread()),
+            tokenizers,
+            segment_readers,
+            generation,
+            doc_store_cache_size,
+            None,
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        schema: Schema,
+        index_opt: Option<Index>,
+        executor: Arc<Executor>,
+        tokenizers: TokenizerManager,
+        segment_readers: Vec<SegmentReader>,
+        generation: TrackedObject<SearcherGeneration>,
+        doc_store_cache_size: usize,
+        slow_query_hook_opt: Option<SlowQueryHook>,
+        results_cache_opt: Option<Arc<ResultsCache>>,
     ) -> io::Result<Searcher> {
         let store_readers: Vec<StoreReader> = segment_readers
             .iter()
-            .map(|segment_reader| segment_reader.get_store_reader(doc_store_cache_size))
+            .map(|segment_reader| {
+                let store_reader = segment_reader.get_store_reader(doc_store_cache_size)?;
+                Ok(store_reader.with_generation_memory(generation.memory().clone()))
+            })
             .collect::<io::Result<Vec<_>>>()?;
 
         Ok(Searcher {
             schema,
-            index,
+            index_opt,
+            executor,
+            tokenizers,
             segment_readers,
             store_readers,
             generation,
+            slow_query_hook_opt,
+            results_cache_opt,
         })
     }
 
     /// Returns the `Index` associated to the `Searcher`
-    pub fn index(&self) -> &Index {
-        &self.index
+    ///
+    /// Returns `None` if the searcher is the searcher of a [`MemoryIndex`](crate::MemoryIndex),
+    /// which has no `Index`. Queries and collectors should rely on
+    /// [`Searcher::schema`] and [`Searcher::tokenizers`] instead.
+    pub fn index(&self) -> Option<&Index> {
+        self.index_opt.as_ref()
+    }
+
+    /// Returns the tokenizers the documents were indexed with.
+    pub fn tokenizers(&self) -> &TokenizerManager {
+        &self.tokenizers
+    }
+
+    /// Helper to access the tokenizer associated to a specific field.
+    pub fn tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
+        tokenizer_for_field(&self.schema, &self.tokenizers, field)
     }
 
     /// [SearcherGeneration] which identifies the version of the snapshot held by this `Searcher`.
@@ -103,6 +254,11 @@ impl Searcher {
         self.generation.as_ref()
     }
 
+    /// Returns the results cache of the reader the searcher belongs to, if it has one.
+    pub(crate) fn results_cache(&self) -> Option<&ResultsCache> {
+        self.results_cache_opt.as_deref()
+    }
+
     /// Fetches a document from tantivy's store given a `DocAddress`.
     ///
     /// The searcher uses the segment ordinal to route the
@@ -112,6 +268,45 @@ impl Searcher {
         store_reader.get(doc_address.doc_id)
     }
 
+    /// Fetches the documents of the given `DocAddress`es, in the same order.
+    ///
+    /// Unlike calling [`Searcher::doc`] for each address, the documents are grouped by segment
+    /// and by store block, so that each block is decompressed at most once, e.g. when fetching
+    /// the documents of the top hits of a search.
+    pub fn docs(&self, doc_addresses: &[DocAddress]) -> crate::Result<Vec<Document>> {
+        let mut docs = Vec::with_capacity(doc_addresses.len());
+        for (segment_ord, ords, doc_ids) in self.group_by_segment(doc_addresses)? {
+            let store_reader = &self.store_readers[segment_ord];
+            docs.extend(ords.into_iter().zip(store_reader.get_many(&doc_ids)?));
+        }
+        docs.sort_by_key(|&(ord, _)| ord);
+        Ok(docs.into_iter().map(|(_, doc)| doc).collect())
+    }
+
+    /// Groups the addresses by segment, keeping their positions in `doc_addresses`.
+    fn group_by_segment(&self, doc_addresses: &[DocAddress]) -> crate::Result<Vec<SegmentDocIds>> {
+        let mut groups: Vec<(Vec<usize>, Vec<DocId>)> =
+            vec![Default::default(); self.store_readers.len()];
+        for (ord, doc_address) in doc_addresses.iter().enumerate() {
+            let (ords, doc_ids) = groups
+                .get_mut(doc_address.segment_ord as usize)
+                .ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!(
+                        "Segment ordinal {} is out of range.",
+                        doc_address.segment_ord
+                    ))
+                })?;
+            ords.push(ord);
+            doc_ids.push(doc_address.doc_id);
+        }
+        Ok(groups
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (ords, _))| !ords.is_empty())
+            .map(|(segment_ord, (ords, doc_ids))| (segment_ord, ords, doc_ids))
+            .collect())
+    }
+
     /// The cache stats for the underlying store reader.
     ///
     /// Aggregates the sum for each segment store reader.
@@ -131,6 +326,22 @@ impl Searcher {
         store_reader.get_async(doc_address.doc_id).await
     }
 
+    /// Fetches the documents of the given `DocAddress`es in an asynchronous manner, see
+    /// [`Searcher::docs`].
+    #[cfg(feature = "quickwit")]
+    pub async fn docs_async(&self, doc_addresses: &[DocAddress]) -> crate::Result<Vec<Document>> {
+        let mut docs = Vec::with_capacity(doc_addresses.len());
+        for (segment_ord, ords, doc_ids) in self.group_by_segment(doc_addresses)? {
+            let store_reader = &self.store_readers[segment_ord];
+            docs.extend(
+                ords.into_iter()
+                    .zip(store_reader.get_many_async(&doc_ids).await?),
+            );
+        }
+        docs.sort_by_key(|&(ord, _)| ord);
+        Ok(docs.into_iter().map(|(_, doc)| doc).collect())
+    }
+
     /// Access the schema associated to the index of this searcher.
     pub fn schema(&self) -> &Schema {
         &self.schema
@@ -156,6 +367,177 @@ impl Searcher {
         Ok(total_doc_freq)
     }
 
+    /// Returns statistics about the indexed content of `field`.
+    ///
+    /// The number of unique terms is computed by merging the term dictionaries
+    /// of all of the segments, so that a term present in several segments is only
+    /// counted once.
+    ///
+    /// If the field has fieldnorms, they are used to count the documents having
+    /// a value and the tokens of the deleted documents. Otherwise, all of the posting
+    /// lists of the field need to be read.
+    pub fn field_stats(&self, field: Field) -> crate::Result<FieldStats> {
+        let inverted_indexes = self
+            .segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(field))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let mut doc_count = 0u64;
+        let mut sum_total_term_freq = 0u64;
+        for (segment_reader, inverted_index) in self.segment_readers.iter().zip(&inverted_indexes) {
+            let (segment_doc_count, deleted_num_tokens) =
+                segment_doc_stats(segment_reader, field, inverted_index)?;
+            doc_count += u64::from(segment_doc_count);
+            sum_total_term_freq += inverted_index.total_num_tokens() - deleted_num_tokens;
+        }
+        let streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| inverted_index.terms().stream())
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut term_merger = TermMerger::new(streams);
+        let mut num_unique_terms = 0u64;
+        while term_merger.advance() {
+            num_unique_terms += 1;
+        }
+        let average_field_length = if doc_count == 0 {
+            0.0
+        } else {
+            sum_total_term_freq as f64 / doc_count as f64
+        };
+        Ok(FieldStats {
+            doc_count,
+            sum_total_term_freq,
+            num_unique_terms,
+            average_field_length,
+        })
+    }
+
+    /// Returns the `top_n` terms of `field` with the highest document frequency,
+    /// sorted by decreasing document frequency.
+    ///
+    /// The term streams of all segments are merged, and the document frequencies of
+    /// a term are summed over the segments containing it. Ties are broken by
+    /// term order.
+    ///
+    /// As for [`Searcher::doc_freq`], deleted documents are included in the document
+    /// frequencies.
+    pub fn top_terms(&self, field: Field, top_n: usize) -> crate::Result<Vec<(Term, u64)>> {
+        if top_n == 0 {
+            return Ok(Vec::new());
+        }
+        let inverted_indexes = self
+            .segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(field))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| inverted_index.terms().stream())
+            .collect::<io::Result<Vec<_>>>()?;
+        let value_type = self.schema.get_field_entry(field).field_type().value_type();
+        let mut term_merger = TermMerger::new(streams);
+        // Min-heap on `(doc_freq, Reverse(term))`: its head is the worst term kept so far.
+        let mut heap: BinaryHeap<Reverse<(u64, Reverse<Vec<u8>>)>> =
+            BinaryHeap::with_capacity(top_n);
+        while term_merger.advance() {
+            let doc_freq: u64 = term_merger
+                .current_segment_ords_and_term_infos()
+                .map(|(_, term_info)| u64::from(term_info.doc_freq))
+                .sum();
+            if heap.len() < top_n {
+                heap.push(Reverse((doc_freq, Reverse(term_merger.key().to_vec()))));
+            } else if let Some(mut head) = heap.peek_mut() {
+                // Terms are visited in increasing order, so on ties the term
+                // already in the heap wins.
+                if doc_freq > (head.0).0 {
+                    *head = Reverse((doc_freq, Reverse(term_merger.key().to_vec())));
+                }
+            }
+        }
+        Ok(heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((doc_freq, Reverse(term_bytes)))| {
+                (
+                    Term::create_bytes_term(value_type, field, &term_bytes),
+                    doc_freq,
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the terms of `field` contained by at least `min_doc_count` of the alive
+    /// documents matching `filter`, along with their number of such documents, sorted by term.
+    ///
+    /// The documents matching the filter are computed once per segment as a bitset, which
+    /// the posting lists are then intersected with. The terms whose document frequency is
+    /// lower than `min_doc_count` are skipped without reading their posting lists.
+    ///
+    /// This makes it possible to suggest the values of a string field which refine the
+    /// results of a query, such as the brands of the products matching it.
+    pub fn terms_matching(
+        &self,
+        field: Field,
+        filter: &dyn Query,
+        min_doc_count: u64,
+    ) -> crate::Result<Vec<(Term, u64)>> {
+        let inverted_indexes = self
+            .segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(field))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let mut filter_bitsets = Vec::with_capacity(self.segment_readers.len());
+        for segment_reader in &self.segment_readers {
+            let mut filter_bitset = filter.to_bitset(self, segment_reader)?;
+            if let Some(alive_bitset) = segment_reader.alive_bitset() {
+                for doc in 0..segment_reader.max_doc() {
+                    if alive_bitset.is_deleted(doc) {
+                        filter_bitset.remove(doc);
+                    }
+                }
+            }
+            filter_bitsets.push(filter_bitset);
+        }
+        let streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| inverted_index.terms().stream())
+            .collect::<io::Result<Vec<_>>>()?;
+        let value_type = self.schema.get_field_entry(field).field_type().value_type();
+        let mut term_merger = TermMerger::new(streams);
+        let mut block_postings = BlockSegmentPostings::empty();
+        let mut terms = Vec::new();
+        while term_merger.advance() {
+            let doc_freq: u64 = term_merger
+                .current_segment_ords_and_term_infos()
+                .map(|(_, term_info)| u64::from(term_info.doc_freq))
+                .sum();
+            if doc_freq < min_doc_count.max(1) {
+                continue;
+            }
+            let mut doc_count = 0u64;
+            for (segment_ord, term_info) in term_merger.current_segment_ords_and_term_infos() {
+                let filter_bitset = &filter_bitsets[segment_ord];
+                inverted_indexes[segment_ord]
+                    .reset_block_postings_from_terminfo(&term_info, &mut block_postings)?;
+                while !block_postings.docs().is_empty() {
+                    doc_count += block_postings
+                        .docs()
+                        .iter()
+                        .filter(|&&doc| filter_bitset.contains(doc))
+                        .count() as u64;
+                    block_postings.advance();
+                }
+            }
+            if doc_count >= min_doc_count.max(1) {
+                terms.push((
+                    Term::create_bytes_term(value_type, field, term_merger.key()),
+                    doc_count,
+                ));
+            }
+        }
+        Ok(terms)
+    }
+
     /// Return the list of segment readers
     pub fn segment_readers(&self) -> &[SegmentReader] {
         &self.segment_readers
@@ -185,7 +567,7 @@ impl Searcher {
         query: &dyn Query,
         collector: &C,
     ) -> crate::Result<C::Fruit> {
-        let executor = self.index.search_executor();
+        let executor = self.executor.as_ref();
         self.search_with_executor(query, collector, executor)
     }
 
@@ -207,16 +589,323 @@ impl Searcher {
         collector: &C,
         executor: &Executor,
     ) -> crate::Result<C::Fruit> {
+        let segment_readers: Vec<(usize, &SegmentReader)> =
+            self.segment_readers().iter().enumerate().collect();
+        self.search_segments(query, collector, executor, segment_readers)
+    }
+
+    /// Collects the segments of `segment_readers`, given with their ordinal, and reports the
+    /// search to the slow query hook, if any, whether it succeeded or failed.
+    fn search_segments<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+        executor: &Executor,
+        segment_readers: Vec<(usize, &SegmentReader)>,
+    ) -> crate::Result<C::Fruit> {
+        let start = Instant::now();
+        let num_segments_searched = segment_readers.len();
+        let _search_span = enter_span!("search", num_segments = num_segments_searched);
+        // Instants at which the weight and collect phases ended, if they did.
+        let mut phase_ends = [None; 2];
+        let result = (|| {
+            let scoring_enabled = collector.requires_scoring();
+            let weight = query.weight(self, scoring_enabled)?;
+            phase_ends[0] = Some(Instant::now());
+            let fruits = executor.map(
+                |(segment_ord, segment_reader)| {
+                    // The segments are collected on the threads of the executor, which do
+                    // not inherit the current span.
+                    let _span = enter_span!(
+                        parent: _search_span.id(),
+                        "collect_segment",
+                        segment_ord,
+                        segment_id = %segment_reader.segment_id(),
+                        max_doc = segment_reader.max_doc(),
+                    );
+                    collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
+                },
+                segment_readers.into_iter(),
+            )?;
+            phase_ends[1] = Some(Instant::now());
+            collector.merge_fruits(fruits)
+        })();
+        self.report_if_slow::<C>(
+            query,
+            start,
+            phase_ends,
+            num_segments_searched,
+            result.as_ref().err(),
+        );
+        result
+    }
+
+    /// Invokes the slow query hook, if any, when the search that started at `start` took
+    /// longer than its threshold.
+    ///
+    /// `phase_ends` are the instants at which the weight and collect phases ended. A phase
+    /// which did not end failed: the search ended with it, and the following phases took
+    /// no time.
+    fn report_if_slow<C: Collector>(
+        &self,
+        query: &dyn Query,
+        start: Instant,
+        phase_ends: [Option<Instant>; 2],
+        num_segments_searched: usize,
+        error_opt: Option<&TantivyError>,
+    ) {
+        let slow_query_hook = if let Some(slow_query_hook) = &self.slow_query_hook_opt {
+            slow_query_hook
+        } else {
+            return;
+        };
+        let end = Instant::now();
+        if !slow_query_hook.is_slow(end - start) {
+            return;
+        }
+        let weight_done = phase_ends[0].unwrap_or(end);
+        let collect_done = phase_ends[1].unwrap_or(end);
+        slow_query_hook.report(&SlowQuery {
+            query: query.canonical_form(),
+            collector: std::any::type_name::<C>(),
+            weight_duration: weight_done - start,
+            collect_duration: collect_done - weight_done,
+            merge_duration: end - collect_done,
+            num_segments_searched,
+            num_segments: self.segment_readers.len(),
+            error: error_opt.cloned(),
+        });
+    }
+
+    /// Same as [`search(...)`](#method.search), but only collects the segments passing
+    /// `segment_filter`.
+    ///
+    /// The other segments are skipped before any scorer is built, which makes it cheap to
+    /// search a single tenant or time range of an index whose segments are partitioned
+    /// by their [attributes](SegmentReader::attributes), or by the range of
+    /// a fast field.
+    ///
+    /// The weight of the query is still built from the statistics of all of the segments.
+    pub fn search_with_segment_filter<C: Collector, F>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+        segment_filter: F,
+    ) -> crate::Result<C::Fruit>
+    where
+        F: Fn(&SegmentReader) -> bool + Sync,
+    {
+        let segment_readers: Vec<(usize, &SegmentReader)> = self
+            .segment_readers()
+            .iter()
+            .enumerate()
+            .filter(|(_, segment_reader)| segment_filter(segment_reader))
+            .collect();
+        self.search_segments(query, collector, self.executor.as_ref(), segment_readers)
+    }
+
+    /// Same as [`search(...)`](#method.search), but also returns statistics about the
+    /// execution of each node of the query.
+    ///
+    /// The returned [`QueryProfile`] is a tree mirroring the structure of the query:
+    /// for each clause of a boolean query, it reports the number of documents evaluated
+    /// and matched, and the time spent in its scorers.
+    ///
+    /// Profiling adds some overhead to each document evaluation, and it disables
+    /// some optimizations relying on the concrete type of the scorers (e.g. BlockWAND).
+    /// It is meant to diagnose slow queries, not to serve regular traffic.
+    pub fn search_with_profile<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<(C::Fruit, QueryProfile)> {
         let scoring_enabled = collector.requires_scoring();
-        let weight = query.weight(self, scoring_enabled)?;
+        let weight = query.profiled_weight(self, scoring_enabled)?;
+        let executor = self.executor.as_ref();
         let segment_readers = self.segment_readers();
         let fruits = executor.map(
             |(segment_ord, segment_reader)| {
-                collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
+                collector.collect_segment(&weight, segment_ord as u32, segment_reader)
             },
             segment_readers.iter().enumerate(),
         )?;
-        collector.merge_fruits(fruits)
+        let fruit = collector.merge_fruits(fruits)?;
+        Ok((fruit, weight.profile()))
+    }
+
+    /// Returns an iterator over the addresses of all of the documents matching `query`.
+    ///
+    /// Documents are returned lazily, in `DocAddress` order, and without computing
+    /// scores. Unlike collecting with a `TopDocs` with a huge limit, memory usage does
+    /// not depend on the number of matching documents, which makes it appropriate
+    /// to export millions of doc ids.
+    ///
+    /// The scorers of all segments are created upfront, so that errors are
+    /// reported by this method rather than during the iteration.
+    pub fn docs_matching(
+        &self,
+        query: &dyn Query,
+    ) -> crate::Result<impl Iterator<Item = DocAddress>> {
+        let weight = query.weight(self, false)?;
+        let segment_matches = self
+            .segment_readers
+            .iter()
+            .enumerate()
+            .map(|(segment_ord, segment_reader)| {
+                Ok(SegmentMatches {
+                    segment_ord: segment_ord as SegmentOrdinal,
+                    scorer: weight.scorer(segment_reader, 1.0)?,
+                    alive_bitset: segment_reader.alive_bitset().cloned(),
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(segment_matches.into_iter().flatten())
+    }
+
+    /// Same as [`docs_matching(...)`](#method.docs_matching), but also returns the value
+    /// of the fast field `field` for each matching document.
+    ///
+    /// Returns an error if the field is not a fast field of type `TFastValue`.
+    pub fn docs_matching_with_fast_field<TFastValue: FastValue>(
+        &self,
+        query: &dyn Query,
+        field: Field,
+    ) -> crate::Result<impl Iterator<Item = (DocAddress, TFastValue)>> {
+        check_fast_value_type::<TFastValue>(self.schema.get_field_entry(field))?;
+        let fast_field_readers: Vec<DynamicFastFieldReader<TFastValue>> = self
+            .segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.fast_fields().typed_fast_field_reader(field))
+            .collect::<crate::Result<_>>()?;
+        Ok(self.docs_matching(query)?.map(move |doc_address| {
+            let value =
+                fast_field_readers[doc_address.segment_ord as usize].get(doc_address.doc_id);
+            (doc_address, value)
+        }))
+    }
+
+    /// Returns the address of the alive document having `key` as primary key.
+    ///
+    /// The lookup relies on the term dictionary of the primary key field, see
+    /// [`NumericOptions::set_primary_key`](crate::schema::NumericOptions::set_primary_key).
+    /// If several alive documents share the same key, the first one is returned.
+    ///
+    /// Returns an error if the schema has no primary key, or if `key` is not a term
+    /// of the primary key field.
+    pub fn doc_address_for_key(&self, key: &Term) -> crate::Result<Option<DocAddress>> {
+        let primary_key_field = self.primary_key_field()?;
+        if key.field() != primary_key_field {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Term {:?} is not a term of the primary key field {:?}",
+                key,
+                self.schema.get_field_name(primary_key_field)
+            )));
+        }
+        for (segment_ord, segment_reader) in self.segment_readers.iter().enumerate() {
+            if !segment_reader.may_contain_primary_key(key) {
+                continue;
+            }
+            let inverted_index = segment_reader.inverted_index(primary_key_field)?;
+            let mut postings = match inverted_index.read_postings(key, IndexRecordOption::Basic)? {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let mut doc = postings.doc();
+            while doc != TERMINATED {
+                if !segment_reader.is_deleted(doc) {
+                    return Ok(Some(DocAddress::new(segment_ord as SegmentOrdinal, doc)));
+                }
+                doc = postings.advance();
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the primary key of the document at `doc_address`.
+    ///
+    /// The key is read from the fast field of the primary key field, see
+    /// [`NumericOptions::set_primary_key`](crate::schema::NumericOptions::set_primary_key).
+    ///
+    /// Returns an error if the schema has no primary key.
+    pub fn key_for_doc(&self, doc_address: DocAddress) -> crate::Result<Term> {
+        let primary_key_field = self.primary_key_field()?;
+        let value_type = self
+            .schema
+            .get_field_entry(primary_key_field)
+            .field_type()
+            .value_type();
+        let fast_field_reader = self
+            .segment_reader(doc_address.segment_ord)
+            .fast_fields()
+            .u64_lenient(primary_key_field)?;
+        let val = fast_field_reader.get(doc_address.doc_id);
+        Ok(Term::create_bytes_term(
+            value_type,
+            primary_key_field,
+            &val.to_be_bytes(),
+        ))
+    }
+
+    /// Returns an alive document containing `term`, along with its address, e.g. to get a
+    /// document by its primary key or by any other unique id.
+    ///
+    /// Segments are visited newest first, ordered by the opstamp of the last document added to
+    /// them (see [`SegmentMeta::max_opstamp`](crate::SegmentMeta::max_opstamp)), and the last
+    /// alive document of the first segment containing the term is returned. Segments written
+    /// before this opstamp was recorded are visited last. When `term` is a primary key, the
+    /// segments not containing it are skipped thanks to their bloom filter.
+    ///
+    /// The opstamps of the documents themselves are not recorded: if several alive documents
+    /// contain `term`, the one returned is not necessarily the most recently added. A merged
+    /// segment is as recent as the most recent of the segments merged into it, whatever the
+    /// age of its documents, and sorting the index by a field (see
+    /// [`IndexSettings::sort_by_field`](crate::IndexSettings::sort_by_field)) reorders the
+    /// documents of each segment. Deleting the previous documents of a term when adding a new
+    /// one keeps a single alive document per term.
+    ///
+    /// Returns an error if the field of `term` is not indexed.
+    pub fn get_doc_by_term(&self, term: &Term) -> crate::Result<Option<(DocAddress, Document)>> {
+        let field_entry = self.schema.get_field_entry(term.field());
+        if !field_entry.is_indexed() {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not indexed.",
+                field_entry.name()
+            )));
+        }
+        let mut segment_ords: Vec<usize> = (0..self.segment_readers.len()).collect();
+        segment_ords.sort_by_key(|&segment_ord| {
+            Reverse((self.segment_readers[segment_ord].max_opstamp(), segment_ord))
+        });
+        for segment_ord in segment_ords {
+            let segment_reader = &self.segment_readers[segment_ord];
+            if !segment_reader.may_contain_primary_key(term) {
+                continue;
+            }
+            let inverted_index = segment_reader.inverted_index(term.field())?;
+            let mut postings = match inverted_index.read_postings(term, IndexRecordOption::Basic)? {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let mut last_alive_doc_opt = None;
+            let mut doc = postings.doc();
+            while doc != TERMINATED {
+                if !segment_reader.is_deleted(doc) {
+                    last_alive_doc_opt = Some(doc);
+                }
+                doc = postings.advance();
+            }
+            if let Some(last_alive_doc) = last_alive_doc_opt {
+                let doc_address = DocAddress::new(segment_ord as SegmentOrdinal, last_alive_doc);
+                return Ok(Some((doc_address, self.doc(doc_address)?)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn primary_key_field(&self) -> crate::Result<Field> {
+        self.schema.primary_key_field().ok_or_else(|| {
+            TantivyError::SchemaError("The schema does not have a primary key field.".to_string())
+        })
     }
 
     /// Summarize total space usage of this searcher.
@@ -229,6 +918,98 @@ impl Searcher {
     }
 }
 
+/// Iterates over the alive documents of a segment matched by a scorer.
+struct SegmentMatches {
+    segment_ord: SegmentOrdinal,
+    scorer: Box<dyn Scorer>,
+    alive_bitset: Option<AliveBitSet>,
+}
+
+impl Iterator for SegmentMatches {
+    type Item = DocAddress;
+
+    fn next(&mut self) -> Option<DocAddress> {
+        loop {
+            let doc = self.scorer.doc();
+            if doc == TERMINATED {
+                return None;
+            }
+            self.scorer.advance();
+            if self
+                .alive_bitset
+                .as_ref()
+                .map(|alive_bitset| alive_bitset.is_alive(doc))
+                .unwrap_or(true)
+            {
+                return Some(DocAddress::new(self.segment_ord, doc));
+            }
+        }
+    }
+}
+
+/// Returns the number of alive documents of the segment having a value for `field`, and the
+/// number of tokens of `field` in its deleted documents.
+fn segment_doc_stats(
+    segment_reader: &SegmentReader,
+    field: Field,
+    inverted_index: &InvertedIndexReader,
+) -> crate::Result<(u32, u64)> {
+    // Fieldnorms are only recorded for text fields.
+    let is_text_field = matches!(
+        segment_reader.schema().get_field_entry(field).field_type(),
+        FieldType::Str(_)
+    );
+    let fieldnorm_reader_opt = if is_text_field {
+        segment_reader.fieldnorms_readers().get_field(field)?
+    } else {
+        None
+    };
+    if let Some(fieldnorm_reader) = fieldnorm_reader_opt {
+        let doc_count = segment_reader
+            .doc_ids_alive()
+            .filter(|&doc| fieldnorm_reader.fieldnorm_id(doc) > 0)
+            .count();
+        let deleted_num_tokens = segment_reader
+            .alive_bitset()
+            .map(|alive_bitset| {
+                (0..segment_reader.max_doc())
+                    .filter(|&doc| alive_bitset.is_deleted(doc))
+                    .map(|doc| u64::from(fieldnorm_reader.fieldnorm(doc)))
+                    .sum()
+            })
+            .unwrap_or(0);
+        return Ok((doc_count as u32, deleted_num_tokens));
+    }
+    let alive_bitset_opt = segment_reader.alive_bitset();
+    let mut docs_with_value = BitSet::with_max_value(segment_reader.max_doc());
+    let mut deleted_num_tokens = 0u64;
+    let mut block_postings = BlockSegmentPostings::empty();
+    let mut term_stream = inverted_index.terms().stream()?;
+    while term_stream.advance() {
+        inverted_index
+            .reset_block_postings_from_terminfo(term_stream.value(), &mut block_postings)?;
+        while !block_postings.docs().is_empty() {
+            let freqs = block_postings.freqs();
+            for (i, &doc) in block_postings.docs().iter().enumerate() {
+                docs_with_value.insert(doc);
+                let is_deleted = alive_bitset_opt
+                    .map(|alive_bitset| alive_bitset.is_deleted(doc))
+                    .unwrap_or(false);
+                if is_deleted {
+                    // Without term frequencies, each posting is a single token.
+                    deleted_num_tokens += freqs.get(i).copied().map_or(1, u64::from);
+                }
+            }
+            block_postings.advance();
+        }
+    }
+    let doc_count = segment_reader
+        .doc_ids_alive()
+        .filter(|&doc| docs_with_value.contains(doc))
+        .count();
+    Ok((doc_count as u32, deleted_num_tokens))
+}
+
 impl fmt::Debug for Searcher {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let segment_ids = self
@@ -239,3 +1020,448 @@ impl fmt::Debug for Searcher {
         write!(f, "Searcher({:?})", segment_ids)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, Query, TermQuery};
+    use crate::schema::{
+        IndexRecordOption, NumericOptions, Schema, FAST, INDEXED, STORED, STRING, TEXT,
+    };
+    use crate::{DocAddress, Index, SegmentReader, SlowQuery, TantivyError, Term};
+
+    #[test]
+    fn test_searcher_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..6u64 {
+            index_writer.add_document(doc!(num => i))?;
+            if i % 2 == 1 {
+                index_writer.commit()?;
+            }
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let doc_addresses = vec![
+            DocAddress::new(2, 1),
+            DocAddress::new(0, 0),
+            DocAddress::new(2, 0),
+            DocAddress::new(1, 1),
+        ];
+        let docs = searcher.docs(&doc_addresses)?;
+        assert_eq!(docs.len(), 4);
+        for (doc_address, doc) in doc_addresses.iter().zip(&docs) {
+            assert_eq!(doc, &searcher.doc(*doc_address)?);
+        }
+        assert!(searcher.docs(&[])?.is_empty());
+        assert!(searcher.docs(&[DocAddress::new(3, 0)]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_with_segment_filter() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for tenant in ["a", "b", "a"] {
+            index_writer.set_segment_attributes(BTreeMap::from([(
+                "tenant".to_string(),
+                tenant.to_string(),
+            )]));
+            index_writer.add_document(doc!(num => 1u64))?;
+            index_writer.add_document(doc!(num => 2u64))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let is_tenant = |tenant: &'static str| {
+            move |segment_reader: &SegmentReader| {
+                segment_reader
+                    .attributes()
+                    .get("tenant")
+                    .map(String::as_str)
+                    == Some(tenant)
+            }
+        };
+        assert_eq!(
+            searcher.search_with_segment_filter(&AllQuery, &Count, is_tenant("a"))?,
+            4
+        );
+        let term_query = TermQuery::new(Term::from_field_u64(num, 2u64), IndexRecordOption::Basic);
+        let top_docs = searcher.search_with_segment_filter(
+            &term_query,
+            &TopDocs::with_limit(3),
+            is_tenant("b"),
+        )?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(
+            searcher
+                .segment_reader(top_docs[0].1.segment_ord)
+                .attributes()["tenant"],
+            "b"
+        );
+        assert_eq!(
+            searcher.search_with_segment_filter(&AllQuery, &Count, is_tenant("c"))?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slow_query_hook() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let not_indexed = schema_builder.add_u64_field("not_indexed", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..4u64 {
+            index_writer.add_document(doc!(num => i % 2))?;
+            index_writer.commit()?;
+        }
+        let slow_queries: Arc<Mutex<Vec<SlowQuery>>> = Arc::default();
+        let reader = index
+            .reader_builder()
+            .slow_query_hook(Duration::ZERO, {
+                let slow_queries = slow_queries.clone();
+                move |slow_query: &SlowQuery| slow_queries.lock().unwrap().push(slow_query.clone())
+            })
+            .try_into()?;
+        let searcher = reader.searcher();
+        let term_query = TermQuery::new(Term::from_field_u64(num, 1u64), IndexRecordOption::Basic);
+        assert_eq!(searcher.search(&term_query, &Count)?, 2);
+        searcher.search_with_segment_filter(&AllQuery, &Count, |segment_reader| {
+            segment_reader.segment_id() == searcher.segment_reader(0).segment_id()
+        })?;
+        {
+            let slow_queries = slow_queries.lock().unwrap();
+            assert_eq!(slow_queries.len(), 2);
+            assert_eq!(slow_queries[0].query, term_query.canonical_form());
+            assert_eq!(slow_queries[0].collector, std::any::type_name::<Count>());
+            assert_eq!(slow_queries[0].num_segments_searched, 4);
+            assert_eq!(slow_queries[0].num_segments, 4);
+            assert!(slow_queries[0].duration() >= slow_queries[0].collect_duration);
+            assert!(slow_queries[0].error.is_none());
+            assert_eq!(slow_queries[1].num_segments_searched, 1);
+        }
+
+        // Failed searches are reported with their error.
+        let failing_query = TermQuery::new(
+            Term::from_field_u64(not_indexed, 1u64),
+            IndexRecordOption::Basic,
+        );
+        assert!(searcher.search(&failing_query, &Count).is_err());
+        {
+            let slow_queries = slow_queries.lock().unwrap();
+            assert_eq!(slow_queries.len(), 3);
+            assert!(matches!(
+                slow_queries[2].error,
+                Some(TantivyError::SchemaError(_))
+            ));
+            assert_eq!(slow_queries[2].collect_duration, Duration::ZERO);
+            assert_eq!(slow_queries[2].merge_duration, Duration::ZERO);
+        }
+
+        // Searches faster than the threshold are not reported.
+        let searcher = index
+            .reader_builder()
+            .slow_query_hook(Duration::from_secs(3600), {
+                let slow_queries = slow_queries.clone();
+                move |slow_query: &SlowQuery| slow_queries.lock().unwrap().push(slow_query.clone())
+            })
+            .try_into()?
+            .searcher();
+        assert_eq!(searcher.search(&term_query, &Count)?, 2);
+        assert_eq!(slow_queries.lock().unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_stats() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b c", num => 1u64))?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a d", num => 2u64))?;
+        index_writer.add_document(doc!(num => 1u64))?;
+        index_writer.add_document(doc!(text => "e", num => 3u64))?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(text, "e"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let text_stats = searcher.field_stats(text)?;
+        assert_eq!(text_stats.doc_count, 3);
+        assert_eq!(text_stats.sum_total_term_freq, 7);
+        assert_eq!(text_stats.num_unique_terms, 5);
+        assert!((text_stats.average_field_length - 7.0 / 3.0).abs() < 1e-6);
+
+        let num_stats = searcher.field_stats(num)?;
+        assert_eq!(num_stats.doc_count, 3);
+        assert_eq!(num_stats.sum_total_term_freq, 3);
+        // The terms of the deleted documents remain in the term dictionaries.
+        assert_eq!(num_stats.num_unique_terms, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_terms() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b c"))?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a d"))?;
+        index_writer.add_document(doc!(text => "c e"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_terms: Vec<(String, u64)> = searcher
+            .top_terms(text, 3)?
+            .into_iter()
+            .map(|(term, doc_freq)| (term.as_str().unwrap().to_string(), doc_freq))
+            .collect();
+        assert_eq!(
+            top_terms,
+            vec![
+                ("a".to_string(), 3),
+                ("b".to_string(), 2),
+                ("c".to_string(), 2)
+            ]
+        );
+        assert!(searcher.top_terms(text, 0)?.is_empty());
+        assert_eq!(searcher.top_terms(text, 10)?.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_terms_matching() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let brand = schema_builder.add_text_field("brand", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(category => "shoes", brand => "acme"))?;
+        index_writer.add_document(doc!(category => "shoes", brand => "globex"))?;
+        index_writer.add_document(doc!(category => "hats", brand => "initech"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(category => "shoes", brand => "acme"))?;
+        index_writer.add_document(doc!(category => "shoes", brand => "umbrella"))?;
+        index_writer.add_document(doc!(category => "hats", brand => "acme"))?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(brand, "umbrella"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let shoes_query = TermQuery::new(
+            Term::from_field_text(category, "shoes"),
+            IndexRecordOption::Basic,
+        );
+        let terms_matching = |min_doc_count: u64| -> crate::Result<Vec<(String, u64)>> {
+            Ok(searcher
+                .terms_matching(brand, &shoes_query, min_doc_count)?
+                .into_iter()
+                .map(|(term, doc_count)| (term.as_str().unwrap().to_string(), doc_count))
+                .collect())
+        };
+        assert_eq!(
+            terms_matching(0)?,
+            vec![("acme".to_string(), 2), ("globex".to_string(), 1)]
+        );
+        assert_eq!(terms_matching(2)?, vec![("acme".to_string(), 2)]);
+        assert!(terms_matching(3)?.is_empty());
+        assert_eq!(searcher.terms_matching(brand, &AllQuery, 1)?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_docs_matching() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let num = schema_builder.add_u64_field("num", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a", num => 10u64))?;
+        index_writer.add_document(doc!(text => "b", num => 11u64))?;
+        index_writer.add_document(doc!(text => "a c", num => 12u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a", num => 13u64))?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(text, "c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let doc_addresses: Vec<DocAddress> = searcher.docs_matching(&query)?.collect();
+        assert_eq!(
+            doc_addresses,
+            vec![DocAddress::new(0, 0), DocAddress::new(1, 0)]
+        );
+        let values: Vec<(DocAddress, u64)> = searcher
+            .docs_matching_with_fast_field(&query, num)?
+            .collect();
+        assert_eq!(
+            values,
+            vec![(DocAddress::new(0, 0), 10), (DocAddress::new(1, 0), 13)]
+        );
+        assert_eq!(searcher.docs_matching(&AllQuery)?.count(), 3);
+        assert!(searcher
+            .docs_matching_with_fast_field::<i64>(&query, num)
+            .is_err());
+        assert!(searcher
+            .docs_matching_with_fast_field::<u64>(&query, text)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_doc_by_term() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_text_field("id", STRING | STORED);
+        let version = schema_builder.add_u64_field("version", STORED);
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id => "a", version => 1u64))?;
+        index_writer.add_document(doc!(id => "b", version => 1u64))?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(id, "a"));
+        index_writer.add_document(doc!(id => "a", version => 2u64))?;
+        index_writer.add_document(doc!(id => "c", version => 1u64))?;
+        index_writer.add_document(doc!(id => "c", version => 2u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let version_of =
+            |key: &str| -> crate::Result<Option<u64>> {
+                let doc_opt = searcher.get_doc_by_term(&Term::from_field_text(id, key))?;
+                Ok(doc_opt
+                    .and_then(|(_, doc)| doc.get_first(version).and_then(|value| value.as_u64())))
+            };
+        assert_eq!(version_of("a")?, Some(2));
+        assert_eq!(version_of("b")?, Some(1));
+        // The last document of the segment is returned.
+        assert_eq!(version_of("c")?, Some(2));
+        assert_eq!(version_of("d")?, None);
+        let (doc_address, _) = searcher
+            .get_doc_by_term(&Term::from_field_text(id, "b"))?
+            .unwrap();
+        assert_eq!(
+            searcher.doc(doc_address)?.get_first(id).unwrap().as_text(),
+            Some("b")
+        );
+        assert!(searcher
+            .get_doc_by_term(&Term::from_field_u64(version, 1))
+            .is_err());
+        assert!(searcher
+            .get_doc_by_term(&Term::from_field_text(text, "a"))?
+            .is_none());
+
+        // Without deletes, the document of the most recent segment is returned, whatever the
+        // order of the segments.
+        for version_val in 1u64..=4 {
+            index_writer.add_document(doc!(id => "e", version => version_val))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let version_of_e = searcher
+            .get_doc_by_term(&Term::from_field_text(id, "e"))?
+            .and_then(|(_, doc)| doc.get_first(version).and_then(|value| value.as_u64()));
+        assert_eq!(version_of_e, Some(4));
+        assert!(searcher
+            .segment_readers()
+            .iter()
+            .all(|segment_reader| segment_reader.max_opstamp().is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_primary_key_lookup() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_i64_field("id", NumericOptions::default().set_primary_key());
+        let text = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        assert_eq!(schema.primary_key_field(), Some(id));
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id => -1i64, text => "a"))?;
+        index_writer.add_document(doc!(id => 2i64, text => "b"))?;
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_i64(id, 2));
+        index_writer.add_document(doc!(id => 2i64, text => "b2"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let doc_address = searcher
+            .doc_address_for_key(&Term::from_field_i64(id, 2))?
+            .unwrap();
+        assert_eq!(doc_address, DocAddress::new(1, 0));
+        assert_eq!(
+            searcher.key_for_doc(doc_address)?,
+            Term::from_field_i64(id, 2)
+        );
+        let doc_address = searcher
+            .doc_address_for_key(&Term::from_field_i64(id, -1))?
+            .unwrap();
+        assert_eq!(searcher.key_for_doc(doc_address)?.as_i64(), Some(-1));
+        assert_eq!(
+            searcher.doc_address_for_key(&Term::from_field_i64(id, 3))?,
+            None
+        );
+        assert!(searcher
+            .doc_address_for_key(&Term::from_field_text(text, "a"))
+            .is_err());
+
+        // The second segment is skipped without reading its term dictionary.
+        assert!(searcher
+            .segment_reader(1)
+            .may_contain_primary_key(&Term::from_field_i64(id, 2)));
+        assert!(!searcher
+            .segment_reader(1)
+            .may_contain_primary_key(&Term::from_field_i64(id, -1)));
+        assert!(searcher
+            .segment_reader(1)
+            .may_contain_primary_key(&Term::from_field_text(text, "a")));
+        assert!(searcher.segment_reader(1).space_usage()?.bloom_filter() > 0);
+
+        // Merged segments have a filter of the keys of the merged segments.
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        for key in [-1i64, 2] {
+            assert!(searcher
+                .segment_reader(0)
+                .may_contain_primary_key(&Term::from_field_i64(id, key)));
+        }
+        assert!(!searcher
+            .segment_reader(0)
+            .may_contain_primary_key(&Term::from_field_i64(id, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_primary_key_missing() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader()?.searcher();
+        assert!(searcher
+            .doc_address_for_key(&Term::from_field_u64(id, 1))
+            .is_err());
+        Ok(())
+    }
+}
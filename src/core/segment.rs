@@ -76,7 +76,13 @@ impl Segment {
     }
 
     /// Open one of the component file for a *regular* read.
+    ///
+    /// Returns `OpenReadError::IncompatibleIndex` if the component was written with a format
+    /// version this version of tantivy cannot read.
     pub fn open_read(&self, component: SegmentComponent) -> Result<FileSlice, OpenReadError> {
+        self.meta
+            .check_component_format_version(component)
+            .map_err(OpenReadError::IncompatibleIndex)?;
         let path = self.relative_path(component);
         self.index.directory().open_read(&path)
     }
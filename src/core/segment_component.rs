@@ -4,7 +4,7 @@ use std::slice;
 /// Each component is stored in its own file,
 /// using the pattern `segment_uuid`.`component_extension`,
 /// except the delete component that takes an `segment_uuid`.`delete_opstamp`.`component_extension`
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum SegmentComponent {
     /// Postings (or inverted list). Sorted lists of document ids, associated to terms
     Postings,
@@ -27,10 +27,13 @@ pub enum SegmentComponent {
     /// Bitset describing which document of the segment is alive.
     /// (It was representing deleted docs but changed to represent alive docs from v0.17)
     Delete,
+    /// Bloom filter of the terms of the primary key field. It is only written if the schema
+    /// has a primary key field, and is not listed by `SegmentComponent::iterator`.
+    BloomFilter,
 }
 
 impl SegmentComponent {
-    /// Iterates through the components.
+    /// Iterates through the components, except for the optional `BloomFilter`.
     pub fn iterator() -> slice::Iter<'static, SegmentComponent> {
         static SEGMENT_COMPONENTS: [SegmentComponent; 8] = [
             SegmentComponent::Postings,
@@ -44,4 +47,73 @@ impl SegmentComponent {
         ];
         SEGMENT_COMPONENTS.iter()
     }
+
+    /// Iterates through all of the components, including the optional `BloomFilter`.
+    pub fn all() -> slice::Iter<'static, SegmentComponent> {
+        static ALL_SEGMENT_COMPONENTS: [SegmentComponent; 9] = [
+            SegmentComponent::Postings,
+            SegmentComponent::Positions,
+            SegmentComponent::FastFields,
+            SegmentComponent::FieldNorms,
+            SegmentComponent::Terms,
+            SegmentComponent::Store,
+            SegmentComponent::TempStore,
+            SegmentComponent::Delete,
+            SegmentComponent::BloomFilter,
+        ];
+        ALL_SEGMENT_COMPONENTS.iter()
+    }
+
+    /// Name of the component, as recorded in the segment meta.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            SegmentComponent::Postings => "postings",
+            SegmentComponent::Positions => "positions",
+            SegmentComponent::FastFields => "fast_fields",
+            SegmentComponent::FieldNorms => "fieldnorms",
+            SegmentComponent::Terms => "terms",
+            SegmentComponent::Store => "store",
+            SegmentComponent::TempStore => "temp_store",
+            SegmentComponent::Delete => "delete",
+            SegmentComponent::BloomFilter => "bloom_filter",
+        }
+    }
+
+    /// Format version the component is written with.
+    ///
+    /// It is bumped whenever the file format of the component changes, independently of the
+    /// other components, and recorded in the meta of the segments.
+    pub fn format_version(self) -> u32 {
+        match self {
+            // 2: the dense posting lists of the fields without frequencies are bitmaps.
+            SegmentComponent::Postings => 2,
+            // 2: the blocks may be compressed with a dictionary, and the large values may be
+            // references to a blob store.
+            SegmentComponent::Store | SegmentComponent::TempStore => 2,
+            SegmentComponent::Positions
+            | SegmentComponent::FastFields
+            | SegmentComponent::FieldNorms
+            | SegmentComponent::Terms
+            | SegmentComponent::Delete
+            | SegmentComponent::BloomFilter => 1,
+        }
+    }
+
+    /// Oldest format version of the component this version of tantivy can read.
+    ///
+    /// When the format of a component evolves, the reader of its previous format is kept,
+    /// so that upgrading tantivy does not require to reindex.
+    pub fn oldest_supported_format_version(self) -> u32 {
+        match self {
+            SegmentComponent::Postings
+            | SegmentComponent::Positions
+            | SegmentComponent::FastFields
+            | SegmentComponent::FieldNorms
+            | SegmentComponent::Terms
+            | SegmentComponent::Store
+            | SegmentComponent::TempStore
+            | SegmentComponent::Delete
+            | SegmentComponent::BloomFilter => 1,
+        }
+    }
 }
@@ -49,6 +49,15 @@ impl SegmentId {
         SegmentId(create_uuid())
     }
 
+    /// Returns the `ordinal`-th id of the sequence of pseudo-random ids generated from `seed`.
+    pub(crate) fn from_seed(seed: u64, ordinal: u64) -> SegmentId {
+        let mut state = seed ^ ordinal.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+        bytes[8..].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+        SegmentId(uuid::Builder::from_random_bytes(bytes).into_uuid())
+    }
+
     /// Returns a shorter identifier of the segment.
     ///
     /// We are using UUID4, so only 6 bits are fixed,
@@ -76,6 +85,14 @@ impl SegmentId {
     }
 }
 
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 /// Error type used when parsing a `SegmentId` from a string fails.
 pub struct SegmentIdParseError(uuid::Error);
 
@@ -1,19 +1,22 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
 use std::{fmt, io};
 
+use common::HasLen;
 use fail::fail_point;
 
-use crate::core::{InvertedIndexReader, Segment, SegmentComponent, SegmentId};
+use crate::core::{IndexSortByField, InvertedIndexReader, Segment, SegmentComponent, SegmentId};
+use crate::directory::error::{Incompatibility, OpenReadError};
 use crate::directory::{CompositeFile, FileSlice};
-use crate::error::DataCorruption;
+use crate::error::{DataCorruption, TantivyError};
 use crate::fastfield::{intersect_alive_bitsets, AliveBitSet, FacetReader, FastFieldReaders};
 use crate::fieldnorm::{FieldNormReader, FieldNormReaders};
-use crate::schema::{Field, FieldType, IndexRecordOption, Schema};
-use crate::space_usage::SegmentSpaceUsage;
-use crate::store::StoreReader;
+use crate::postings::BloomFilter;
+use crate::schema::{Field, FieldType, IndexRecordOption, Schema, Term};
+use crate::space_usage::{ByteCount, SegmentSpaceUsage};
+use crate::store::{BlobStore, StoreReader};
 use crate::termdict::TermDictionary;
-use crate::{DocId, Opstamp};
+use crate::{is_supported_index_format_version, DocId, Opstamp, INDEX_FORMAT_VERSION};
 
 /// Entry point to access all of the datastructures of the `Segment`
 ///
@@ -31,6 +34,9 @@ pub struct SegmentReader {
 
     segment_id: SegmentId,
     delete_opstamp: Option<Opstamp>,
+    max_opstamp: Option<Opstamp>,
+    attributes: Arc<BTreeMap<String, String>>,
+    sort_by_field: Option<IndexSortByField>,
 
     max_doc: DocId,
     num_docs: DocId,
@@ -42,8 +48,13 @@ pub struct SegmentReader {
     fieldnorm_readers: FieldNormReaders,
 
     store_file: FileSlice,
+    store_format_version: u32,
+    blob_store: Option<Arc<dyn BlobStore>>,
     alive_bitset_opt: Option<AliveBitSet>,
+    bloom_filter_opt: Option<BloomFilter>,
     schema: Schema,
+    // Checksum of the term dictionary file, found in its footer.
+    termdict_checksum: u32,
 }
 
 impl SegmentReader {
@@ -134,7 +145,12 @@ impl SegmentReader {
 
     /// Accessor to the segment's `StoreReader`.
     pub fn get_store_reader(&self, cache_size: usize) -> io::Result<StoreReader> {
-        StoreReader::open(self.store_file.clone(), cache_size)
+        Ok(StoreReader::open_with_format_version(
+            self.store_file.clone(),
+            cache_size,
+            self.store_format_version,
+        )?
+        .with_blob_store(self.blob_store.clone()))
     }
 
     /// Open a new segment for reading.
@@ -147,63 +163,142 @@ impl SegmentReader {
         segment: &Segment,
         custom_bitset: Option<AliveBitSet>,
     ) -> crate::Result<SegmentReader> {
-        let termdict_file = segment.open_read(SegmentComponent::Terms)?;
+        if let Some(segment_format_version) = segment.meta().index_format_version() {
+            if !is_supported_index_format_version(segment_format_version) {
+                return Err(TantivyError::IncompatibleIndex(
+                    Incompatibility::SegmentFormatMismatch {
+                        segment_id: segment.id(),
+                        library_format_version: INDEX_FORMAT_VERSION,
+                        segment_format_version,
+                    },
+                ));
+            }
+        }
+        let reader = SegmentReader::open_files(
+            |component| segment.open_read(component),
+            segment.schema(),
+            segment.id(),
+            segment.meta().max_doc(),
+        )?;
+        let termdict_checksum = segment
+            .index()
+            .directory()
+            .footer_crc(&segment.relative_path(SegmentComponent::Terms))?;
+
+        let original_bitset = open_alive_bitset(segment)?;
+        let alive_bitset_opt = intersect_alive_bitset(original_bitset, custom_bitset);
+
+        Ok(SegmentReader {
+            num_docs: num_alive_docs(alive_bitset_opt.as_ref(), reader.max_doc),
+            alive_bitset_opt,
+            delete_opstamp: segment.meta().delete_opstamp(),
+            max_opstamp: segment.meta().max_opstamp(),
+            attributes: Arc::new(segment.meta().attributes().clone()),
+            sort_by_field: segment.index().settings().sort_by_field.clone(),
+            blob_store: segment.index().blob_store().cloned(),
+            // The segments written before the format versions were recorded have the first
+            // format.
+            store_format_version: segment
+                .meta()
+                .component_format_version(SegmentComponent::Store)
+                .unwrap_or(1),
+            termdict_checksum,
+            ..reader
+        })
+    }
+
+    /// Opens a reader on the components of a segment without deletes, as returned by
+    /// `open_read`.
+    ///
+    /// This is how the in-memory segment of a [`MemoryIndex`](crate::MemoryIndex) is read.
+    pub(crate) fn open_files(
+        open_read: impl Fn(SegmentComponent) -> Result<FileSlice, OpenReadError>,
+        schema: Schema,
+        segment_id: SegmentId,
+        max_doc: DocId,
+    ) -> crate::Result<SegmentReader> {
+        let termdict_file = open_read(SegmentComponent::Terms)?;
         let termdict_composite = CompositeFile::open(&termdict_file)?;
 
-        let store_file = segment.open_read(SegmentComponent::Store)?;
+        let store_file = open_read(SegmentComponent::Store)?;
 
         fail_point!("SegmentReader::open#middle");
 
-        let postings_file = segment.open_read(SegmentComponent::Postings)?;
+        let postings_file = open_read(SegmentComponent::Postings)?;
         let postings_composite = CompositeFile::open(&postings_file)?;
 
         let positions_composite = {
-            if let Ok(positions_file) = segment.open_read(SegmentComponent::Positions) {
+            if let Ok(positions_file) = open_read(SegmentComponent::Positions) {
                 CompositeFile::open(&positions_file)?
             } else {
                 CompositeFile::empty()
             }
         };
 
-        let schema = segment.schema();
+        let bloom_filter_opt = match open_read(SegmentComponent::BloomFilter) {
+            Ok(bloom_filter_file) => BloomFilter::open(bloom_filter_file.read_bytes()?)?,
+            // The segments of schemas without a primary key field, or written before bloom
+            // filters were introduced, have none.
+            Err(OpenReadError::FileDoesNotExist(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
 
-        let fast_fields_data = segment.open_read(SegmentComponent::FastFields)?;
+        let fast_fields_data = open_read(SegmentComponent::FastFields)?;
         let fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
         let fast_fields_readers =
             Arc::new(FastFieldReaders::new(schema.clone(), fast_fields_composite));
-        let fieldnorm_data = segment.open_read(SegmentComponent::FieldNorms)?;
+        let fieldnorm_data = open_read(SegmentComponent::FieldNorms)?;
         let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
 
-        let original_bitset = if segment.meta().has_deletes() {
-            let alive_doc_file_slice = segment.open_read(SegmentComponent::Delete)?;
-            let alive_doc_data = alive_doc_file_slice.read_bytes()?;
-            Some(AliveBitSet::open(alive_doc_data))
-        } else {
-            None
-        };
-
-        let alive_bitset_opt = intersect_alive_bitset(original_bitset, custom_bitset);
-
-        let max_doc = segment.meta().max_doc();
-        let num_docs = alive_bitset_opt
-            .as_ref()
-            .map(|alive_bitset| alive_bitset.num_alive_docs() as u32)
-            .unwrap_or(max_doc);
-
         Ok(SegmentReader {
             inv_idx_reader_cache: Default::default(),
-            num_docs,
+            num_docs: max_doc,
             max_doc,
             termdict_composite,
             postings_composite,
             fast_fields_readers,
             fieldnorm_readers,
-            segment_id: segment.id(),
-            delete_opstamp: segment.meta().delete_opstamp(),
+            segment_id,
+            delete_opstamp: None,
+            max_opstamp: None,
+            attributes: Default::default(),
+            sort_by_field: None,
             store_file,
-            alive_bitset_opt,
+            store_format_version: SegmentComponent::Store.format_version(),
+            blob_store: None,
+            alive_bitset_opt: None,
+            bloom_filter_opt,
             positions_composite,
             schema,
+            termdict_checksum: 0,
+        })
+    }
+
+    /// Opens a reader on a newer version of the segment read by this reader.
+    ///
+    /// If the deletes of the segment did not change, this reader is returned as is.
+    /// Otherwise, only the alive bitset is re-read, and the other datastructures,
+    /// including the term dictionaries already opened, are shared with this reader.
+    ///
+    /// They are only shared if the checksum of the term dictionary file did not change.
+    pub(crate) fn reopen(&self, segment: &Segment) -> crate::Result<SegmentReader> {
+        debug_assert_eq!(self.segment_id, segment.id());
+        if self.delete_opstamp == segment.meta().delete_opstamp() {
+            return Ok(self.clone());
+        }
+        let termdict_checksum = segment
+            .index()
+            .directory()
+            .footer_crc(&segment.relative_path(SegmentComponent::Terms))?;
+        if termdict_checksum != self.termdict_checksum {
+            return SegmentReader::open(segment);
+        }
+        let alive_bitset_opt = open_alive_bitset(segment)?;
+        Ok(SegmentReader {
+            num_docs: num_alive_docs(alive_bitset_opt.as_ref(), self.max_doc),
+            alive_bitset_opt,
+            delete_opstamp: segment.meta().delete_opstamp(),
+            ..self.clone()
         })
     }
 
@@ -273,6 +368,7 @@ impl SegmentReader {
             postings_file,
             positions_file,
             record_option,
+            field_type.has_payloads(),
         )?);
 
         // by releasing the lock in between, we may end up opening the inverting index
@@ -295,6 +391,24 @@ impl SegmentReader {
         self.delete_opstamp
     }
 
+    /// Returns the opstamp of the last document added to the segment, see
+    /// [`SegmentMeta::max_opstamp`](crate::SegmentMeta::max_opstamp).
+    pub fn max_opstamp(&self) -> Option<Opstamp> {
+        self.max_opstamp
+    }
+
+    /// Returns the user attributes of the segment, see
+    /// [`SegmentMeta::attributes`](crate::SegmentMeta::attributes).
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Returns the field the documents of the segment are sorted by, see
+    /// [`IndexSettings::sort_by_field`](crate::IndexSettings::sort_by_field).
+    pub fn sort_by_field(&self) -> Option<&IndexSortByField> {
+        self.sort_by_field.as_ref()
+    }
+
     /// Returns the bitset representing the alive `DocId`s.
     pub fn alive_bitset(&self) -> Option<&AliveBitSet> {
         self.alive_bitset_opt.as_ref()
@@ -317,6 +431,42 @@ impl SegmentReader {
         }
     }
 
+    /// Returns false if no document of the segment has the given primary key.
+    ///
+    /// This checks the bloom filter of the terms of the primary key field of the segment,
+    /// without reading its term dictionary: it also returns true for about 1% of the absent
+    /// keys. It always returns true for the terms of other fields.
+    pub fn may_contain_primary_key(&self, key: &Term) -> bool {
+        match &self.bloom_filter_opt {
+            Some(bloom_filter) if self.schema.primary_key_field() == Some(key.field()) => {
+                bloom_filter.may_contain(key.value_bytes())
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the number of bytes of segment data held open by the reader.
+    ///
+    /// Unlike [`SegmentReader::space_usage`], this does not open the doc store.
+    pub fn num_bytes(&self) -> ByteCount {
+        self.termdict_composite.space_usage().total()
+            + self.postings_composite.space_usage().total()
+            + self.positions_composite.space_usage().total()
+            + self.fast_fields_readers.space_usage().total()
+            + self.fieldnorm_readers.space_usage().total()
+            + self.store_file.len()
+            + self
+                .alive_bitset_opt
+                .as_ref()
+                .map(AliveBitSet::space_usage)
+                .unwrap_or(0)
+            + self
+                .bloom_filter_opt
+                .as_ref()
+                .map(BloomFilter::num_bytes)
+                .unwrap_or(0)
+    }
+
     /// Summarize total space usage of this segment.
     pub fn space_usage(&self) -> io::Result<SegmentSpaceUsage> {
         Ok(SegmentSpaceUsage::new(
@@ -331,6 +481,10 @@ impl SegmentReader {
                 .as_ref()
                 .map(AliveBitSet::space_usage)
                 .unwrap_or(0),
+            self.bloom_filter_opt
+                .as_ref()
+                .map(BloomFilter::num_bytes)
+                .unwrap_or(0),
         ))
     }
 }
@@ -356,11 +510,30 @@ impl fmt::Debug for SegmentReader {
     }
 }
 
+fn open_alive_bitset(segment: &Segment) -> crate::Result<Option<AliveBitSet>> {
+    if !segment.meta().has_deletes() {
+        return Ok(None);
+    }
+    let alive_doc_file_slice = segment.open_read(SegmentComponent::Delete)?;
+    let alive_doc_data = alive_doc_file_slice.read_bytes()?;
+    Ok(Some(AliveBitSet::open(alive_doc_data)))
+}
+
+fn num_alive_docs(alive_bitset_opt: Option<&AliveBitSet>, max_doc: DocId) -> DocId {
+    alive_bitset_opt
+        .map(|alive_bitset| alive_bitset.num_alive_docs() as u32)
+        .unwrap_or(max_doc)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::core::Index;
-    use crate::schema::{Schema, Term, STORED, TEXT};
-    use crate::DocId;
+    use std::sync::Arc;
+
+    use crate::core::{Index, SegmentComponent, META_FILEPATH};
+    use crate::directory::error::{Incompatibility, OpenReadError};
+    use crate::directory::Directory;
+    use crate::schema::{Schema, Term, Value, STORED, TEXT};
+    use crate::{DocAddress, DocId, ReloadPolicy, TantivyError, INDEX_FORMAT_VERSION};
 
     #[test]
     fn test_num_alive() -> crate::Result<()> {
@@ -388,6 +561,95 @@ mod test {
         assert_eq!(4, searcher.segment_reader(0).max_doc());
         Ok(())
     }
+    #[test]
+    fn test_term_dictionaries_shared_across_reloads() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(name => "tantivy"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let inverted_index = segment_reader.inverted_index(name)?;
+
+        index_writer.add_document(doc!(name => "horse"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let new_searcher = reader.searcher();
+        assert_eq!(new_searcher.segment_readers().len(), 2);
+        for new_segment_reader in new_searcher.segment_readers() {
+            let new_inverted_index = new_segment_reader.inverted_index(name)?;
+            assert_eq!(
+                Arc::ptr_eq(&inverted_index, &new_inverted_index),
+                new_segment_reader.segment_id() == segment_reader.segment_id()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unchanged_segment_readers_reused_across_reloads() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(name => "tantivy"))?;
+        index_writer.add_document(doc!(name => "horse"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        let segment_reader = searcher.segment_reader(0);
+
+        index_writer.add_document(doc!(name => "jockey"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let new_searcher = reader.searcher();
+        let new_segment_reader = new_searcher
+            .segment_readers()
+            .iter()
+            .find(|new_segment_reader| {
+                new_segment_reader.segment_id() == segment_reader.segment_id()
+            })
+            .unwrap();
+        assert!(std::ptr::eq(
+            segment_reader.fast_fields(),
+            new_segment_reader.fast_fields()
+        ));
+
+        // Only the alive bitset is re-read after deletes.
+        index_writer.delete_term(Term::from_field_text(name, "horse"));
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher_after_delete = reader.searcher();
+        let segment_reader_after_delete = searcher_after_delete
+            .segment_readers()
+            .iter()
+            .find(|new_segment_reader| {
+                new_segment_reader.segment_id() == segment_reader.segment_id()
+            })
+            .unwrap();
+        assert!(std::ptr::eq(
+            segment_reader.fast_fields(),
+            segment_reader_after_delete.fast_fields()
+        ));
+        assert_eq!(segment_reader_after_delete.num_docs(), 1);
+        assert!(segment_reader_after_delete.is_deleted(1));
+        // Readers of the previous generations are unaffected.
+        assert_eq!(segment_reader.num_docs(), 2);
+        assert!(!segment_reader.is_deleted(1));
+        Ok(())
+    }
+
     #[test]
     fn test_alive_docs_iterator() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -419,4 +681,135 @@ mod test {
         assert_eq!(vec![0u32, 2u32], docs);
         Ok(())
     }
+
+    #[test]
+    fn test_segment_index_format_version() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(name => "tantivy"))?;
+        index_writer.commit()?;
+        drop(index_writer);
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(
+            segment_metas[0].index_format_version(),
+            Some(INDEX_FORMAT_VERSION)
+        );
+
+        let meta_json: serde_json::Value =
+            serde_json::from_slice(&index.directory().atomic_read(&META_FILEPATH)?)?;
+        let write_meta_with_version = |version: Option<u32>| {
+            let mut meta_json = meta_json.clone();
+            let segment_json = meta_json["segments"][0].as_object_mut().unwrap();
+            assert_eq!(
+                segment_json["index_format_version"],
+                serde_json::json!(INDEX_FORMAT_VERSION)
+            );
+            match version {
+                Some(version) => {
+                    segment_json.insert("index_format_version".to_string(), version.into());
+                }
+                None => {
+                    segment_json.remove("index_format_version");
+                }
+            }
+            index
+                .directory()
+                .atomic_write(&META_FILEPATH, &serde_json::to_vec(&meta_json).unwrap())
+        };
+
+        // Segments written before the version was recorded are still readable.
+        write_meta_with_version(None)?;
+        assert_eq!(
+            index.searchable_segment_metas()?[0].index_format_version(),
+            None
+        );
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+
+        write_meta_with_version(Some(99))?;
+        assert!(matches!(
+            index.reader(),
+            Err(TantivyError::IncompatibleIndex(
+                Incompatibility::SegmentFormatMismatch {
+                    library_format_version: INDEX_FORMAT_VERSION,
+                    segment_format_version: 99,
+                    ..
+                }
+            ))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_segment_component_format_version() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(name => "tantivy"))?;
+        index_writer.commit()?;
+        drop(index_writer);
+        let segment_metas = index.searchable_segment_metas()?;
+        for component in SegmentComponent::iterator() {
+            assert_eq!(
+                segment_metas[0].component_format_version(*component),
+                Some(component.format_version())
+            );
+        }
+        // A reader of the first format of the postings and of the doc store, e.g. an older
+        // version of tantivy, rejects them.
+        for component in [SegmentComponent::Postings, SegmentComponent::Store] {
+            assert!(matches!(
+                segment_metas[0].check_component_format_version_within(component, 1..=1),
+                Err(Incompatibility::ComponentFormatMismatch {
+                    library_format_version: 1,
+                    component_format_version: 2,
+                    ..
+                })
+            ));
+        }
+
+        let meta_json: serde_json::Value =
+            serde_json::from_slice(&index.directory().atomic_read(&META_FILEPATH)?)?;
+        let write_meta_with_versions = |versions: serde_json::Value| {
+            let mut meta_json = meta_json.clone();
+            meta_json["segments"][0]["component_format_versions"] = versions;
+            index
+                .directory()
+                .atomic_write(&META_FILEPATH, &serde_json::to_vec(&meta_json).unwrap())
+        };
+
+        // Segments written before the versions were recorded are still readable.
+        write_meta_with_versions(serde_json::json!({}))?;
+        assert_eq!(
+            index.searchable_segment_metas()?[0]
+                .component_format_version(SegmentComponent::Store),
+            None
+        );
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+
+        // The first formats are still readable.
+        write_meta_with_versions(serde_json::json!({ "postings": 1, "store": 1 }))?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        assert_eq!(
+            searcher.doc(DocAddress::new(0, 0))?.get_first(name),
+            Some(&Value::Str("tantivy".to_string()))
+        );
+
+        // The versions are checked as each component is opened.
+        write_meta_with_versions(serde_json::json!({ "store": 99 }))?;
+        assert!(matches!(
+            index.reader(),
+            Err(TantivyError::OpenReadError(OpenReadError::IncompatibleIndex(
+                Incompatibility::ComponentFormatMismatch {
+                    component: SegmentComponent::Store,
+                    component_format_version: 99,
+                    ..
+                }
+            )))
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,72 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::TantivyError;
+
+/// Description of a search that took longer than the threshold of the slow query hook, see
+/// [`IndexReaderBuilder::slow_query_hook`](crate::IndexReaderBuilder::slow_query_hook).
+///
+/// Failed searches are reported as well, along with their error.
+#[derive(Clone, Debug)]
+pub struct SlowQuery {
+    /// The canonical form of the query, see
+    /// [`Query::canonical_form`](crate::query::Query::canonical_form).
+    pub query: String,
+    /// The type name of the collector.
+    pub collector: &'static str,
+    /// Time spent building the weight of the query.
+    pub weight_duration: Duration,
+    /// Time spent collecting the segments.
+    pub collect_duration: Duration,
+    /// Time spent merging the fruits of the segments.
+    pub merge_duration: Duration,
+    /// Number of segments collected.
+    pub num_segments_searched: usize,
+    /// Number of segments of the searcher.
+    pub num_segments: usize,
+    /// The error the search failed with, or `None` if it succeeded.
+    ///
+    /// The phases following the failed one are reported with a zero duration.
+    pub error: Option<TantivyError>,
+}
+
+impl SlowQuery {
+    /// Returns the total duration of the search.
+    pub fn duration(&self) -> Duration {
+        self.weight_duration + self.collect_duration + self.merge_duration
+    }
+}
+
+/// Callback invoked with the searches slower than a threshold.
+#[derive(Clone)]
+pub(crate) struct SlowQueryHook {
+    threshold: Duration,
+    callback: Arc<dyn Fn(&SlowQuery) + Send + Sync>,
+}
+
+impl SlowQueryHook {
+    pub fn new(threshold: Duration, callback: Arc<dyn Fn(&SlowQuery) + Send + Sync>) -> Self {
+        SlowQueryHook {
+            threshold,
+            callback,
+        }
+    }
+
+    /// Returns true if a search of `duration` should be reported.
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        duration >= self.threshold
+    }
+
+    pub fn report(&self, slow_query: &SlowQuery) {
+        (self.callback)(slow_query);
+    }
+}
+
+impl fmt::Debug for SlowQueryHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowQueryHook")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
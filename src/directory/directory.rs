@@ -7,6 +7,7 @@ use std::{fmt, io, thread};
 
 use crate::directory::directory_lock::Lock;
 use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::meta_generations;
 use crate::directory::{FileHandle, FileSlice, WatchCallback, WatchHandle, WritePtr};
 
 /// Retry the logic of acquiring locks is pretty simple.
@@ -180,6 +181,22 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
     /// The file may or may not previously exist.
     fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
 
+    /// Atomically replaces `meta.json` with the metas of a commit.
+    ///
+    /// The metas are first written to a new generation file, `meta.json.<generation>`, and
+    /// the pointer file `meta.json.generation` is updated to point to it, so that a reader
+    /// finding a partially written `meta.json`, e.g. after a crash, can fall back to
+    /// [`Directory::atomic_read_generation_metas`].
+    fn atomic_write_metas(&self, metas: &[u8]) -> io::Result<()> {
+        meta_generations::write_metas(self, metas)
+    }
+
+    /// Reads the metas of the latest generation written by [`Directory::atomic_write_metas`],
+    /// or returns `None` if no generation was written yet.
+    fn atomic_read_generation_metas(&self) -> Result<Option<Vec<u8>>, OpenReadError> {
+        meta_generations::read_generation_metas(self)
+    }
+
     /// Sync the directory.
     ///
     /// This call is required to ensure that newly created files are
@@ -2,7 +2,8 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::{fmt, io};
 
-use crate::Version;
+use crate::core::SegmentComponent;
+use crate::{SegmentId, Version};
 
 /// Error while trying to acquire a directory lock.
 #[derive(Debug, Clone, Error)]
@@ -104,6 +105,28 @@ pub enum Incompatibility {
         /// Version the index was built with
         index_version: Version,
     },
+    /// The format of a segment, as recorded in the index meta, isn't supported by
+    /// this version of the library
+    SegmentFormatMismatch {
+        /// Id of the segment
+        segment_id: SegmentId,
+        /// Index format version used by the library
+        library_format_version: u32,
+        /// Index format version the segment was written with
+        segment_format_version: u32,
+    },
+    /// The format of a component of a segment, as recorded in the index meta, isn't
+    /// supported by this version of the library
+    ComponentFormatMismatch {
+        /// Id of the segment
+        segment_id: SegmentId,
+        /// Component of the segment
+        component: SegmentComponent,
+        /// Format version of the component used by the library
+        library_format_version: u32,
+        /// Format version the component was written with
+        component_format_version: u32,
+    },
 }
 
 impl fmt::Debug for Incompatibility {
@@ -140,6 +163,37 @@ impl fmt::Debug for Incompatibility {
                 );
                 write!(f, "{}. {}", err, advice)?;
             }
+            Incompatibility::SegmentFormatMismatch {
+                segment_id,
+                library_format_version,
+                segment_format_version,
+            } => {
+                write!(
+                    f,
+                    "Segment {} was written with index format {}, library index format: {}. \
+                     Change tantivy to a version compatible with index format {}.",
+                    segment_id.short_uuid_string(),
+                    segment_format_version,
+                    library_format_version,
+                    segment_format_version
+                )?;
+            }
+            Incompatibility::ComponentFormatMismatch {
+                segment_id,
+                component,
+                library_format_version,
+                component_format_version,
+            } => {
+                write!(
+                    f,
+                    "Component {:?} of segment {} was written with format {}, library format: \
+                     {}. Change tantivy to a version compatible with this format.",
+                    component,
+                    segment_id.short_uuid_string(),
+                    component_format_version,
+                    library_format_version
+                )?;
+            }
         }
 
         Ok(())
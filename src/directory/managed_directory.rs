@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockWriteGuard};
@@ -53,7 +53,9 @@ fn save_managed_paths(
     directory: &dyn Directory,
     wlock: &RwLockWriteGuard<'_, MetaInformation>,
 ) -> io::Result<()> {
-    let mut w = serde_json::to_vec(&wlock.managed_paths)?;
+    // The paths are sorted so that the file does not depend on the iteration order of the set.
+    let managed_paths: BTreeSet<&PathBuf> = wlock.managed_paths.iter().collect();
+    let mut w = serde_json::to_vec(&managed_paths)?;
     writeln!(&mut w)?;
     directory.atomic_write(&MANAGED_FILEPATH, &w[..])?;
     Ok(())
@@ -109,6 +111,15 @@ impl ManagedDirectory {
     pub fn garbage_collect<L: FnOnce() -> HashSet<PathBuf>>(
         &mut self,
         get_living_files: L,
+    ) -> crate::Result<GarbageCollectionResult> {
+        self.try_garbage_collect(|| Ok(get_living_files()))
+    }
+
+    /// Garbage collect unused files, like [`ManagedDirectory::garbage_collect`], aborting the
+    /// collection if the list of living files cannot be computed.
+    pub(crate) fn try_garbage_collect<L: FnOnce() -> crate::Result<HashSet<PathBuf>>>(
+        &mut self,
+        get_living_files: L,
     ) -> crate::Result<GarbageCollectionResult> {
         info!("Garbage collect");
         let mut files_to_delete = vec![];
@@ -137,7 +148,7 @@ impl ManagedDirectory {
             // 4) gc removes a file that was useful for process B, before process B opened it.
             match self.acquire_lock(&META_LOCK) {
                 Ok(_meta_lock) => {
-                    let living_files = get_living_files();
+                    let living_files = get_living_files()?;
                     for managed_path in &meta_informations_rlock.managed_paths {
                         if !living_files.contains(managed_path) {
                             files_to_delete.push(managed_path.clone());
@@ -256,6 +267,21 @@ impl ManagedDirectory {
         Ok(footer.crc() == crc)
     }
 
+    /// Returns the checksum recorded in the footer of a managed file.
+    ///
+    /// Only the footer is read, so this is cheap even for large files.
+    pub(crate) fn footer_crc(&self, path: &Path) -> result::Result<u32, OpenReadError> {
+        let reader = self.directory.open_read(path)?;
+        let (footer, _) = Footer::extract_footer(reader)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(footer.crc())
+    }
+
+    /// Returns the size of a file, including its footer if it has one.
+    pub(crate) fn file_num_bytes(&self, path: &Path) -> result::Result<usize, OpenReadError> {
+        Ok(self.directory.get_file_handle(path)?.len())
+    }
+
     /// List all managed files
     pub fn list_managed_files(&self) -> HashSet<PathBuf> {
         let managed_paths = self
@@ -0,0 +1,108 @@
+//! Generation-numbered copies of `meta.json`.
+//!
+//! Each commit writes its metas to `meta.json.<generation>` first, then points
+//! `meta.json.generation` to it, and only then replaces `meta.json`. A reader finding a
+//! partially written `meta.json`, e.g. on a directory whose atomic writes are not atomic or
+//! after a crash, falls back to the generation pointed to, which is always complete.
+//!
+//! `meta.json` itself keeps the whole metas, so that the indexes remain readable by the
+//! older versions of tantivy.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+
+use crate::core::META_FILEPATH;
+use crate::directory::error::OpenReadError;
+use crate::Directory;
+
+/// The pointer file holds the latest generation of the metas, in decimal.
+pub(crate) static META_GENERATION_FILEPATH: Lazy<&'static Path> =
+    Lazy::new(|| Path::new("meta.json.generation"));
+
+/// Number of generations of the metas kept in the directory.
+const NUM_KEPT_GENERATIONS: u64 = 2;
+
+/// Returns the path of the metas of the generation.
+pub(crate) fn meta_generation_filepath(generation: u64) -> PathBuf {
+    PathBuf::from(format!("meta.json.{generation}"))
+}
+
+fn parse_meta_generation(generation_data: &[u8]) -> Option<u64> {
+    std::str::from_utf8(generation_data)
+        .ok()
+        .and_then(|generation_str| generation_str.trim().parse::<u64>().ok())
+        .filter(|&generation| generation > 0)
+}
+
+/// Returns the latest generation of the metas, or `None` if no generation was written yet,
+/// i.e. for a new index or for an index written by an older version of tantivy.
+///
+/// A pointer file which cannot be read, or does not hold a generation, is an error.
+pub(crate) fn read_meta_generation<D: Directory + ?Sized>(
+    directory: &D,
+) -> Result<Option<u64>, OpenReadError> {
+    let generation_data = match directory.atomic_read(&META_GENERATION_FILEPATH) {
+        Ok(generation_data) => generation_data,
+        Err(OpenReadError::FileDoesNotExist(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let generation = parse_meta_generation(&generation_data).ok_or_else(|| {
+        OpenReadError::wrap_io_error(
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The meta generation pointer does not hold a generation.",
+            ),
+            META_GENERATION_FILEPATH.to_path_buf(),
+        )
+    })?;
+    Ok(Some(generation))
+}
+
+/// Returns the metas of the latest generation, or `None` if no generation was written yet.
+pub(crate) fn read_generation_metas<D: Directory + ?Sized>(
+    directory: &D,
+) -> Result<Option<Vec<u8>>, OpenReadError> {
+    match read_meta_generation(directory)? {
+        Some(generation) => Ok(Some(
+            directory.atomic_read(&meta_generation_filepath(generation))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Writes the metas as a new generation, points the pointer file to it, then replaces
+/// `meta.json`.
+///
+/// A pointer file which does not hold a generation restarts the generations at the first
+/// one, rather than failing every commit to come.
+pub(crate) fn write_metas<D: Directory + ?Sized>(directory: &D, metas: &[u8]) -> io::Result<()> {
+    let generation = match directory.atomic_read(&META_GENERATION_FILEPATH) {
+        Ok(generation_data) => match parse_meta_generation(&generation_data) {
+            Some(generation) => generation + 1,
+            None => {
+                warn!("The meta generation pointer is corrupted, restarting the generations.");
+                1
+            }
+        },
+        Err(OpenReadError::FileDoesNotExist(_)) => 1,
+        Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err)),
+    };
+    directory.atomic_write(&meta_generation_filepath(generation), metas)?;
+    directory.atomic_write(&META_GENERATION_FILEPATH, generation.to_string().as_bytes())?;
+    directory.atomic_write(&META_FILEPATH, metas)
+}
+
+/// Returns the pointer file and the files of the kept generations, so that the garbage
+/// collector only deletes the older generations.
+pub(crate) fn list_meta_generation_files<D: Directory + ?Sized>(
+    directory: &D,
+) -> Result<Vec<PathBuf>, OpenReadError> {
+    let mut files = vec![META_GENERATION_FILEPATH.to_path_buf()];
+    if let Some(generation) = read_meta_generation(directory)? {
+        let first_kept_generation = generation.saturating_sub(NUM_KEPT_GENERATIONS - 1).max(1);
+        files.extend((first_kept_generation..=generation).map(meta_generation_filepath));
+    }
+    Ok(files)
+}
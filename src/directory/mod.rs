@@ -9,6 +9,7 @@ mod file_slice;
 mod file_watcher;
 mod footer;
 mod managed_directory;
+pub(crate) mod meta_generations;
 mod ram_directory;
 mod watch_event_router;
 
@@ -26,6 +27,7 @@ pub use ownedbytes::OwnedBytes;
 pub(crate) use self::composite_file::{CompositeFile, CompositeWrite};
 pub use self::directory::{Directory, DirectoryClone, DirectoryLock};
 pub use self::directory_lock::{Lock, INDEX_WRITER_LOCK, META_LOCK};
+pub(crate) use self::meta_generations::list_meta_generation_files;
 pub(crate) use self::file_slice::{ArcBytes, WeakArcBytes};
 pub use self::file_slice::{FileHandle, FileSlice};
 pub use self::ram_directory::RamDirectory;
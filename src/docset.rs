@@ -9,6 +9,11 @@ use crate::DocId;
 /// to compare [u32; 4].
 pub const TERMINATED: DocId = std::i32::MAX as u32;
 
+/// Number of doc ids of the blocks pushed to collectors that do not require scoring.
+///
+/// See [`DocSet::fill_buffer`].
+pub const COLLECT_BLOCK_BUFFER_LEN: usize = 64;
+
 /// Represents an iterable set of sorted doc ids.
 pub trait DocSet: Send {
     /// Goes to the next element.
@@ -121,6 +126,10 @@ impl<'a> DocSet for &'a mut dyn DocSet {
         (**self).seek(target)
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        (**self).fill_buffer(buffer)
+    }
+
     fn doc(&self) -> u32 {
         (**self).doc()
     }
@@ -149,6 +158,11 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         unboxed.seek(target)
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        let unboxed: &mut TDocSet = self.borrow_mut();
+        unboxed.fill_buffer(buffer)
+    }
+
     fn doc(&self) -> DocId {
         let unboxed: &TDocSet = self.borrow();
         unboxed.doc()
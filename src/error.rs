@@ -98,10 +98,20 @@ pub enum TantivyError {
     /// Index incompatible with current version of Tantivy.
     #[error("{0:?}")]
     IncompatibleIndex(Incompatibility),
+    /// The document violates the constraints of a field of the schema.
+    #[error("Invalid document: '{0}'")]
+    InvalidDocument(#[from] schema::ValidationError),
     /// An internal error occurred. This is are internal states that should not be reached.
     /// e.g. a datastructure is incorrectly inititalized.
     #[error("Internal error: '{0}'")]
     InternalError(String),
+    /// The memory budget of a query was exceeded.
+    #[error("Memory limit exceeded: '{0}'")]
+    MemoryLimitExceeded(String),
+    /// The operation was cancelled before it completed, e.g. a merge cancelled by
+    /// `IndexWriter::rollback`.
+    #[error("Cancelled: '{0}'")]
+    Cancelled(String),
 }
 
 #[cfg(feature = "quickwit")]
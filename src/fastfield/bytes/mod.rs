@@ -37,6 +37,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bytes_owned() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let bytes_field = schema_builder.add_bytes_field("bytesfield", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(bytes_field=>vec![1u8, 2]))?;
+        index_writer.add_document(doc!())?;
+        index_writer.add_document(doc!(bytes_field=>vec![3u8; 100_000]))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let bytes_reader = searcher
+            .segment_reader(0)
+            .fast_fields()
+            .bytes(bytes_field)?;
+        let owned_bytes: Vec<_> = (0..3)
+            .map(|doc| bytes_reader.get_bytes_owned(doc))
+            .collect();
+        drop(bytes_reader);
+        assert_eq!(owned_bytes[0].as_slice(), &[1u8, 2]);
+        assert!(owned_bytes[1].is_empty());
+        assert_eq!(owned_bytes[2].as_slice(), vec![3u8; 100_000].as_slice());
+        Ok(())
+    }
+
     fn create_index_for_test<T: Into<BytesOptions>>(
         byte_options: T,
     ) -> crate::Result<impl Deref<Target = Searcher>> {
@@ -12,6 +12,9 @@ use crate::DocId;
 ///
 /// Reading the value for a document is done by reading the start index for it,
 /// and the start index for the next document, and keeping the bytes in between.
+///
+/// The bytes are never copied: with a memory mapped directory, the values of a document
+/// are a slice of the memory mapped file, however large they are.
 #[derive(Clone)]
 pub struct BytesFastFieldReader {
     idx_reader: DynamicFastFieldReader<u64>,
@@ -39,6 +42,16 @@ impl BytesFastFieldReader {
         &self.values.as_slice()[start..stop]
     }
 
+    /// Returns the bytes associated to the given `doc`, as `OwnedBytes` sharing the data of
+    /// the reader.
+    ///
+    /// Unlike [`BytesFastFieldReader::get_bytes`], the returned bytes can outlive the reader,
+    /// e.g. to be kept by a custom scorer, without being copied.
+    pub fn get_bytes_owned(&self, doc: DocId) -> OwnedBytes {
+        let (start, stop) = self.range(doc);
+        self.values.slice(start..stop)
+    }
+
     /// Returns the length of the bytes associated to the given `doc`
     pub fn num_bytes(&self, doc: DocId) -> usize {
         let (start, stop) = self.range(doc);
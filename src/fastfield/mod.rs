@@ -24,11 +24,14 @@ pub use self::alive_bitset::{intersect_alive_bitsets, write_alive_bitset, AliveB
 pub use self::bytes::{BytesFastFieldReader, BytesFastFieldWriter};
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
-pub use self::multivalued::{MultiValuedFastFieldReader, MultiValuedFastFieldWriter};
+pub use self::multivalued::{
+    MultiValueMode, MultiValuedFastFieldReader, MultiValuedFastFieldWriter,
+};
 pub use self::reader::{DynamicFastFieldReader, FastFieldReader};
 pub use self::readers::FastFieldReaders;
-pub(crate) use self::readers::{type_and_cardinality, FastType};
+pub(crate) use self::readers::{check_fast_value_type, type_and_cardinality, FastType};
 pub use self::serializer::{CompositeFastFieldSerializer, FastFieldDataAccess, FastFieldStats};
+pub(crate) use self::writer::fast_field_default_value;
 pub use self::writer::{FastFieldsWriter, IntFastFieldWriter};
 use crate::schema::{Cardinality, FieldType, Type, Value};
 use crate::{DateTime, DocId};
@@ -82,6 +85,14 @@ pub trait FastValue: Clone + Copy + Send + Sync + PartialOrd + 'static {
 
     /// Returns the `schema::Type` for this FastValue.
     fn to_type() -> Type;
+
+    /// Returns the sum of two values, or `None` if the values of this type cannot be added,
+    /// e.g. dates.
+    ///
+    /// Integers saturate at the bounds of their type.
+    fn sum(&self, _other: &Self) -> Option<Self> {
+        None
+    }
 }
 
 impl FastValue for u64 {
@@ -108,6 +119,10 @@ impl FastValue for u64 {
     fn to_type() -> Type {
         Type::U64
     }
+
+    fn sum(&self, other: &Self) -> Option<Self> {
+        Some(self.saturating_add(*other))
+    }
 }
 
 impl FastValue for i64 {
@@ -133,6 +148,10 @@ impl FastValue for i64 {
     fn to_type() -> Type {
         Type::I64
     }
+
+    fn sum(&self, other: &Self) -> Option<Self> {
+        Some(self.saturating_add(*other))
+    }
 }
 
 impl FastValue for f64 {
@@ -158,6 +177,10 @@ impl FastValue for f64 {
     fn to_type() -> Type {
         Type::F64
     }
+
+    fn sum(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
 }
 
 impl FastValue for bool {
@@ -1,7 +1,7 @@
 mod reader;
 mod writer;
 
-pub use self::reader::MultiValuedFastFieldReader;
+pub use self::reader::{MultiValueMode, MultiValuedFastFieldReader};
 pub use self::writer::MultiValuedFastFieldWriter;
 
 #[cfg(test)]
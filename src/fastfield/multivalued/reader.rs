@@ -3,6 +3,20 @@ use std::ops::Range;
 use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue, MultiValueLength};
 use crate::DocId;
 
+/// Selects the single value of a document from the values of a multivalued fast field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MultiValueMode {
+    /// The lowest value of the document.
+    Min,
+    /// The highest value of the document.
+    Max,
+    /// The sum of the values of the document, see [`FastValue::sum`].
+    Sum,
+    /// The first value stored for the document. The values of numeric fields are stored in
+    /// the order they were added to the document.
+    First,
+}
+
 /// Reader for a multivalued `u64` fast field.
 ///
 /// The reader is implemented as two `u64` fast field.
@@ -51,6 +65,27 @@ impl<Item: FastValue> MultiValuedFastFieldReader<Item> {
         self.get_vals_for_range(range, vals);
     }
 
+    /// Returns the single value of the document selected by `mode`, or `None` if the document
+    /// has no value, or if its values cannot be summed.
+    pub fn get_val_with_mode(&self, doc: DocId, mode: MultiValueMode) -> Option<Item> {
+        let mut vals = self.range(doc).map(|idx| {
+            let mut val = [Item::make_zero()];
+            self.vals_reader.get_range(idx, &mut val);
+            val[0]
+        });
+        let first = vals.next()?;
+        match mode {
+            MultiValueMode::Min => {
+                Some(vals.fold(first, |min, val| if val < min { val } else { min }))
+            }
+            MultiValueMode::Max => {
+                Some(vals.fold(first, |max, val| if val > max { val } else { max }))
+            }
+            MultiValueMode::Sum => vals.try_fold(first, |sum, val| sum.sum(&val)),
+            MultiValueMode::First => Some(first),
+        }
+    }
+
     /// Returns the minimum value for this fast field.
     ///
     /// The min value does not take in account of possible
@@ -95,6 +130,7 @@ impl<Item: FastValue> MultiValueLength for MultiValuedFastFieldReader<Item> {
 #[cfg(test)]
 mod tests {
 
+    use super::MultiValueMode;
     use crate::core::Index;
     use crate::schema::{Cardinality, Facet, FacetOptions, NumericOptions, Schema};
 
@@ -179,4 +215,43 @@ mod tests {
         assert_eq!(field_reader.max_value(), 6);
         Ok(())
     }
+
+    #[test]
+    fn test_multifastfield_reader_val_with_mode() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let item_field = schema_builder.add_f64_field(
+            "items",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            item_field => 2.5f64,
+            item_field => 4.0f64,
+            item_field => -1.5f64,
+        ))?;
+        index_writer.add_document(doc!())?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let field_reader = searcher.segment_reader(0).fast_fields().f64s(item_field)?;
+
+        assert_eq!(
+            field_reader.get_val_with_mode(0, MultiValueMode::Min),
+            Some(-1.5)
+        );
+        assert_eq!(
+            field_reader.get_val_with_mode(0, MultiValueMode::Max),
+            Some(4.0)
+        );
+        assert_eq!(
+            field_reader.get_val_with_mode(0, MultiValueMode::Sum),
+            Some(5.0)
+        );
+        assert_eq!(
+            field_reader.get_val_with_mode(0, MultiValueMode::First),
+            Some(2.5)
+        );
+        assert_eq!(field_reader.get_val_with_mode(1, MultiValueMode::Max), None);
+        Ok(())
+    }
 }
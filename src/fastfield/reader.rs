@@ -181,6 +181,16 @@ impl<Item: FastValue, C: FastFieldCodecReader> FastFieldReaderCodecWrapper<Item,
         Item::from_u64(self.reader.get_u64(doc, self.bytes.as_slice()))
     }
 
+    /// Returns the codec reader, holding the parameters of the encoding.
+    pub(crate) fn codec_reader(&self) -> &C {
+        &self.reader
+    }
+
+    /// Returns the encoded data, as read by the codec reader.
+    pub(crate) fn data(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
     /// Internally `multivalued` also use SingleValue Fast fields.
     /// It works as follows... A first column contains the list of start index
     /// for each document, a second column contains the actual values.
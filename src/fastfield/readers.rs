@@ -3,7 +3,7 @@ use crate::directory::{CompositeFile, FileSlice};
 use crate::fastfield::{
     BytesFastFieldReader, FastFieldNotAvailableError, FastValue, MultiValuedFastFieldReader,
 };
-use crate::schema::{Cardinality, Field, FieldType, Schema};
+use crate::schema::{Cardinality, Field, FieldEntry, FieldType, Schema};
 use crate::space_usage::PerFieldSpaceUsage;
 use crate::{DateTime, TantivyError};
 
@@ -50,6 +50,30 @@ pub(crate) fn type_and_cardinality(field_type: &FieldType) -> Option<(FastType,
     }
 }
 
+/// Checks that the field of `field_entry` is a fast field of type `TFastValue`, whatever its
+/// cardinality.
+pub(crate) fn check_fast_value_type<TFastValue: FastValue>(
+    field_entry: &FieldEntry,
+) -> crate::Result<()> {
+    if !field_entry.is_fast() {
+        return Err(TantivyError::SchemaError(format!(
+            "Field {:?} is not a fast field.",
+            field_entry.name()
+        )));
+    }
+    let requested_type = TFastValue::to_type();
+    let field_schema_type = field_entry.field_type().value_type();
+    if requested_type != field_schema_type {
+        return Err(TantivyError::SchemaError(format!(
+            "Field {:?} is of type {:?}!={:?}",
+            field_entry.name(),
+            requested_type,
+            field_schema_type
+        )));
+    }
+    Ok(())
+}
+
 impl FastFieldReaders {
     pub(crate) fn new(schema: Schema, fast_fields_composite: CompositeFile) -> FastFieldReaders {
         FastFieldReaders {
@@ -22,7 +22,8 @@ pub struct FastFieldsWriter {
     bytes_value_writers: Vec<BytesFastFieldWriter>,
 }
 
-fn fast_field_default_value(field_entry: &FieldEntry) -> u64 {
+/// Returns the value recorded for the documents without a value in a single valued fast field.
+pub(crate) fn fast_field_default_value(field_entry: &FieldEntry) -> u64 {
     match *field_entry.field_type() {
         FieldType::I64(_) | FieldType::Date(_) => common::i64_to_u64(0i64),
         FieldType::F64(_) => common::f64_to_u64(0.0f64),
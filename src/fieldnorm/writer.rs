@@ -22,7 +22,10 @@ impl FieldNormsWriter {
         schema
             .fields()
             .filter_map(|(field, field_entry)| {
-                if field_entry.is_indexed() && field_entry.has_fieldnorms() {
+                if field_entry.is_indexed()
+                    && field_entry.has_fieldnorms()
+                    && !field_entry.is_deprecated()
+                {
                     Some(field)
                 } else {
                     None
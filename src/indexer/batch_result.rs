@@ -0,0 +1,43 @@
+use thiserror::Error;
+
+use crate::schema::{DocParsingError, ValidationError};
+use crate::Opstamp;
+
+/// Reason why a document of a batch was not added.
+#[derive(Debug, Error, PartialEq)]
+pub enum DocumentError {
+    /// The document could not be parsed.
+    #[error("{0}")]
+    Parsing(#[from] DocParsingError),
+    /// The document violates the constraints of the schema.
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+}
+
+/// Document of a batch that was not added.
+#[derive(Debug, PartialEq)]
+pub struct DocumentFailure {
+    /// Position of the document in the batch.
+    pub position: usize,
+    /// Reason why the document was not added.
+    pub error: DocumentError,
+}
+
+/// Outcome of a batch of documents handed to the `IndexWriter`, in which the invalid
+/// documents do not prevent the valid ones from being added.
+#[derive(Debug, PartialEq)]
+pub struct BatchResult {
+    /// Opstamp of the batch, 1 greater than the opstamp of its last added document.
+    pub opstamp: Opstamp,
+    /// Number of documents added.
+    pub num_added: usize,
+    /// Documents that were not added, by increasing position.
+    pub failures: Vec<DocumentFailure>,
+}
+
+impl BatchResult {
+    /// Returns true if all of the documents of the batch were added.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use crate::{IndexMeta, Opstamp};
+
+type PreCommitHook = Arc<dyn Fn(Opstamp, Option<&str>) -> crate::Result<()> + Send + Sync>;
+type PostPublishHook = Arc<dyn Fn(&IndexMeta) + Send + Sync>;
+
+/// Callbacks run synchronously by the segment updater around each commit.
+#[derive(Clone, Default)]
+pub(crate) struct CommitHooks {
+    pre_commit_hooks: Vec<PreCommitHook>,
+    post_publish_hooks: Vec<PostPublishHook>,
+}
+
+impl CommitHooks {
+    pub fn add_pre_commit_hook(&mut self, pre_commit_hook: PreCommitHook) {
+        self.pre_commit_hooks.push(pre_commit_hook);
+    }
+
+    pub fn add_post_publish_hook(&mut self, post_publish_hook: PostPublishHook) {
+        self.post_publish_hooks.push(post_publish_hook);
+    }
+
+    /// Runs the pre-commit hooks in registration order, stopping at the first error.
+    pub fn pre_commit(&self, opstamp: Opstamp, payload: Option<&str>) -> crate::Result<()> {
+        for pre_commit_hook in &self.pre_commit_hooks {
+            pre_commit_hook(opstamp, payload)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the post-publish hooks in registration order.
+    pub fn post_publish(&self, index_meta: &IndexMeta) {
+        for post_publish_hook in &self.post_publish_hooks {
+            post_publish_hook(index_meta);
+        }
+    }
+}
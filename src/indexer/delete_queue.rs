@@ -246,6 +246,7 @@ impl DeleteCursor {
 mod tests {
 
     use super::{DeleteOperation, DeleteQueue};
+    use crate::indexer::operation::DeleteTarget;
     use crate::schema::{Field, Term};
 
     #[test]
@@ -256,7 +257,7 @@ mod tests {
             let field = Field::from_field_id(1u32);
             DeleteOperation {
                 opstamp: i as u64,
-                term: Term::from_field_u64(field, i as u64),
+                target: DeleteTarget::Term(Term::from_field_u64(field, i as u64)),
             }
         };
 
@@ -0,0 +1,59 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Conditions upon which an indexing thread flushes the segment it is building,
+/// in addition to its memory budget.
+///
+/// Flushing makes the segment part of the next commit, so bounding segments by document
+/// count or by age makes their size predictable, regardless of how fast documents come in.
+///
+/// The batches of operations sent by `IndexWriter::run` are never split across segments,
+/// so a segment may hold a few more documents than `max_docs`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FlushTriggers {
+    /// Flushes the segment once it holds that many documents.
+    pub max_docs: Option<u32>,
+    /// Flushes the segment once that much time elapsed since its first document was added.
+    pub max_age: Option<Duration>,
+}
+
+impl FlushTriggers {
+    /// Flushes segments once they hold `max_docs` documents.
+    #[must_use]
+    pub fn with_max_docs(mut self, max_docs: u32) -> FlushTriggers {
+        self.max_docs = Some(max_docs);
+        self
+    }
+
+    /// Flushes segments once their first document was added `max_age` ago.
+    #[must_use]
+    pub fn with_max_age(mut self, max_age: Duration) -> FlushTriggers {
+        self.max_age = Some(max_age);
+        self
+    }
+}
+
+/// Flush triggers shared by the `IndexWriter` and its indexing threads,
+/// so that they can be adjusted at runtime.
+#[derive(Clone, Default)]
+pub(crate) struct SharedFlushTriggers(Arc<RwLock<FlushTriggers>>);
+
+impl SharedFlushTriggers {
+    pub fn new(flush_triggers: FlushTriggers) -> SharedFlushTriggers {
+        SharedFlushTriggers(Arc::new(RwLock::new(flush_triggers)))
+    }
+
+    pub fn get(&self) -> FlushTriggers {
+        *self
+            .0
+            .read()
+            .expect("Flush triggers lock poisoned. This should never happen.")
+    }
+
+    pub fn set(&self, flush_triggers: FlushTriggers) {
+        *self
+            .0
+            .write()
+            .expect("Flush triggers lock poisoned. This should never happen.") = flush_triggers;
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+/// Whether a commit synchronizes the directory once its `meta.json` is written, making the
+/// commit durable.
+///
+/// The files written by a commit are always synchronized before its `meta.json` is written, so
+/// that `meta.json` never points to files lost by a crash. Skipping the synchronization that
+/// follows trades durability for commit latency: after a crash, the index may come back to an
+/// earlier commit, but it is never corrupted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FsyncPolicy {
+    /// Every commit synchronizes the directory once its `meta.json` is written.
+    #[default]
+    Always,
+    /// Commits never synchronize the directory once their `meta.json` is written.
+    Never,
+    /// A commit synchronizes the directory once its `meta.json` is written if the last such
+    /// synchronization is at least that old.
+    Interval(Duration),
+}
+
+/// Decides which commits synchronize the directory once their `meta.json` is written,
+/// according to an `FsyncPolicy`.
+#[derive(Default)]
+pub(crate) struct FsyncScheduler {
+    fsync_policy: FsyncPolicy,
+    last_sync_opt: Option<Instant>,
+}
+
+impl FsyncScheduler {
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.fsync_policy
+    }
+
+    pub fn set_fsync_policy(&mut self, fsync_policy: FsyncPolicy) {
+        self.fsync_policy = fsync_policy;
+    }
+
+    /// Returns true if the commit about to be made should synchronize the directory once its
+    /// `meta.json` is written.
+    pub fn should_sync(&mut self) -> bool {
+        let now = Instant::now();
+        let should_sync = match self.fsync_policy {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Never => false,
+            FsyncPolicy::Interval(interval) => self
+                .last_sync_opt
+                .map(|last_sync| now.duration_since(last_sync) >= interval)
+                .unwrap_or(true),
+        };
+        if should_sync {
+            self.last_sync_opt = Some(now);
+        }
+        should_sync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{FsyncPolicy, FsyncScheduler};
+
+    #[test]
+    fn test_fsync_scheduler() {
+        let mut fsync_scheduler = FsyncScheduler::default();
+        assert_eq!(fsync_scheduler.fsync_policy(), FsyncPolicy::Always);
+        assert!(fsync_scheduler.should_sync());
+        assert!(fsync_scheduler.should_sync());
+        fsync_scheduler.set_fsync_policy(FsyncPolicy::Never);
+        assert!(!fsync_scheduler.should_sync());
+        fsync_scheduler.set_fsync_policy(FsyncPolicy::Interval(Duration::from_millis(50)));
+        assert!(!fsync_scheduler.should_sync());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(fsync_scheduler.should_sync());
+        assert!(!fsync_scheduler.should_sync());
+    }
+}
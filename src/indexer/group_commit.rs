@@ -0,0 +1,201 @@
+use std::sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard};
+
+use crate::{IndexWriter, Opstamp, TantivyError};
+
+#[derive(Default)]
+struct GroupCommitState {
+    // Whether a thread is currently committing on behalf of the others.
+    committing: bool,
+    // Number of commits completed so far.
+    num_commits: u64,
+    // Result of the last completed commit.
+    last_commit_res: Option<crate::Result<Opstamp>>,
+}
+
+/// `IndexWriter` shared by several threads, coalescing their concurrent commits.
+///
+/// Documents are added through [`GroupCommitWriter::index_writer`].
+/// When several threads call [`GroupCommitWriter::commit`] while a commit is in progress,
+/// a single commit is then run on behalf of all of them, and they all get its opstamp,
+/// instead of running one commit each, one after the other.
+///
+/// A commit only returns once all of the operations sent by the calling thread before calling
+/// it are committed.
+pub struct GroupCommitWriter {
+    index_writer: RwLock<IndexWriter>,
+    state: Mutex<GroupCommitState>,
+    commit_done: Condvar,
+}
+
+impl GroupCommitWriter {
+    /// Wraps an index writer to share it between threads.
+    pub fn wrap(index_writer: IndexWriter) -> GroupCommitWriter {
+        GroupCommitWriter {
+            index_writer: RwLock::new(index_writer),
+            state: Mutex::new(GroupCommitState::default()),
+            commit_done: Condvar::new(),
+        }
+    }
+
+    /// Returns the underlying index writer, to add or delete documents.
+    ///
+    /// Commits wait for the returned guard to be dropped.
+    pub fn index_writer(&self) -> RwLockReadGuard<'_, IndexWriter> {
+        self.index_writer
+            .read()
+            .expect("Index writer lock poisoned. This should never happen.")
+    }
+
+    /// Commits the operations sent so far, along with those of the threads committing
+    /// concurrently.
+    ///
+    /// Returns the opstamp of the commit, or its error, which is shared by all of the
+    /// threads it was run on behalf of.
+    /// If the commit panics, these threads get an error instead.
+    pub fn commit(&self) -> crate::Result<Opstamp> {
+        let mut state = self.lock_state();
+        // A commit in progress may have started before the operations of this thread were sent,
+        // so we need the commit following it.
+        let target_num_commits = state.num_commits + if state.committing { 2 } else { 1 };
+        loop {
+            if state.num_commits >= target_num_commits {
+                return state
+                    .last_commit_res
+                    .clone()
+                    .expect("A commit was completed, so its result is known.");
+            }
+            if !state.committing {
+                break;
+            }
+            state = self
+                .commit_done
+                .wait(state)
+                .expect("Group commit lock poisoned. This should never happen.");
+        }
+        state.committing = true;
+        drop(state);
+
+        let mut committing_guard = CommittingGuard {
+            group_commit_writer: self,
+            commit_res: None,
+        };
+        let commit_res = self
+            .index_writer
+            .write()
+            .expect("Index writer lock poisoned. This should never happen.")
+            .commit();
+        committing_guard.commit_res = Some(commit_res.clone());
+        commit_res
+    }
+
+    /// Returns the underlying index writer.
+    pub fn into_inner(self) -> IndexWriter {
+        self.index_writer
+            .into_inner()
+            .expect("Index writer lock poisoned. This should never happen.")
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, GroupCommitState> {
+        self.state
+            .lock()
+            .expect("Group commit lock poisoned. This should never happen.")
+    }
+}
+
+// Publishes the result of the commit run on behalf of the waiting threads when dropped.
+// If the commit panicked, they get an error instead of waiting forever.
+struct CommittingGuard<'a> {
+    group_commit_writer: &'a GroupCommitWriter,
+    commit_res: Option<crate::Result<Opstamp>>,
+}
+
+impl Drop for CommittingGuard<'_> {
+    fn drop(&mut self) {
+        let commit_res = self.commit_res.take().unwrap_or_else(|| {
+            Err(TantivyError::ErrorInThread(
+                "The commit panicked.".to_string(),
+            ))
+        });
+        let mut state = self.group_commit_writer.lock_state();
+        state.committing = false;
+        state.num_commits += 1;
+        state.last_commit_res = Some(commit_res);
+        drop(state);
+        self.group_commit_writer.commit_done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{CommittingGuard, GroupCommitWriter};
+    use crate::indexer::NoMergePolicy;
+    use crate::schema::{Schema, TEXT};
+    use crate::{Index, TantivyError};
+
+    #[test]
+    fn test_group_commit() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let group_commit_writer = Arc::new(GroupCommitWriter::wrap(index_writer));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let group_commit_writer = group_commit_writer.clone();
+                thread::spawn(move || -> crate::Result<()> {
+                    for _ in 0..10 {
+                        let opstamp = group_commit_writer
+                            .index_writer()
+                            .add_document(doc!(text_field => "a"))?;
+                        let commit_opstamp = group_commit_writer.commit()?;
+                        assert!(commit_opstamp > opstamp);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().num_docs(), 80);
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_commit_panic() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let group_commit_writer = GroupCommitWriter::wrap(index.writer_for_tests()?);
+        group_commit_writer.lock_state().committing = true;
+        let panic_res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _committing_guard = CommittingGuard {
+                group_commit_writer: &group_commit_writer,
+                commit_res: None,
+            };
+            panic!("commit panic");
+        }));
+        assert!(panic_res.is_err());
+        {
+            let state = group_commit_writer.lock_state();
+            assert!(!state.committing);
+            assert!(matches!(
+                state.last_commit_res,
+                Some(Err(TantivyError::ErrorInThread(_)))
+            ));
+        }
+        group_commit_writer
+            .index_writer()
+            .add_document(doc!(text_field => "a"))?;
+        group_commit_writer.commit()?;
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().num_docs(), 1);
+        Ok(())
+    }
+}
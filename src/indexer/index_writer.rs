@@ -1,15 +1,23 @@
+use std::collections::BTreeMap;
 use std::ops::Range;
-use std::sync::Arc;
-use std::thread;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{mem, thread};
 
 use common::BitSet;
+use crossbeam_channel::RecvTimeoutError;
 use smallvec::smallvec;
 
+use super::lock_takeover::{acquire_writer_lock_with_takeover, WriterFence};
 use super::operation::{AddOperation, UserOperation};
 use super::segment_updater::SegmentUpdater;
-use super::{AddBatch, AddBatchReceiver, AddBatchSender, PreparedCommit};
-use crate::core::{Index, Segment, SegmentComponent, SegmentId, SegmentMeta, SegmentReader};
+use super::{AddBatch, AddBatchReceiver, AddBatchSender, PausedIndexWriter, PreparedCommit};
+use crate::columnar::ColumnBatch;
+use crate::core::{
+    Index, IndexMeta, Segment, SegmentComponent, SegmentId, SegmentMeta, SegmentReader,
+};
 use crate::directory::{DirectoryLock, GarbageCollectionResult, TerminatingWrite};
 use crate::docset::{DocSet, TERMINATED};
 use crate::error::TantivyError;
@@ -17,10 +25,16 @@ use crate::fastfield::write_alive_bitset;
 use crate::indexer::delete_queue::{DeleteCursor, DeleteQueue};
 use crate::indexer::doc_opstamp_mapping::DocToOpstampMapping;
 use crate::indexer::index_writer_status::IndexWriterStatus;
-use crate::indexer::operation::DeleteOperation;
+use crate::indexer::operation::{DeleteOperation, DeleteTarget};
 use crate::indexer::stamper::Stamper;
-use crate::indexer::{MergePolicy, SegmentEntry, SegmentWriter};
-use crate::schema::{Document, IndexRecordOption, Term};
+use crate::indexer::version_checker::{version_from_term, Change, VersionChecker};
+use crate::indexer::{
+    BatchResult, DocumentError, DocumentFailure, FlushTriggers, FsyncPolicy, LiveDocStats,
+    MergePolicy, RunningMerge, SegmentEntry, SegmentWriter, SharedFlushTriggers,
+};
+use crate::schema::{
+    DocParsingError, Document, Field, FieldValue, IndexRecordOption, Schema, Term, Value,
+};
 use crate::{FutureResult, Opstamp};
 
 // Size of the margin for the `memory_arena`. A segment is closed when the remaining memory
@@ -59,6 +73,12 @@ pub struct IndexWriter {
     index: Index,
 
     memory_arena_in_bytes_per_thread: usize,
+    flush_triggers: SharedFlushTriggers,
+
+    // Set iff the writer has no indexing thread.
+    direct_indexer_opt: Option<Mutex<DirectIndexer>>,
+
+    version_checker: Mutex<VersionChecker>,
 
     workers_join_handle: Vec<JoinHandle<crate::Result<()>>>,
 
@@ -92,17 +112,24 @@ fn compute_deleted_bitset(
 
         // A delete operation should only affect
         // document that were inserted before it.
-        let inverted_index = segment_reader.inverted_index(delete_op.term.field())?;
-        if let Some(mut docset) =
-            inverted_index.read_postings(&delete_op.term, IndexRecordOption::Basic)?
-        {
-            let mut doc_matching_deleted_term = docset.doc();
-            while doc_matching_deleted_term != TERMINATED {
-                if doc_opstamps.is_deleted(doc_matching_deleted_term, delete_op.opstamp) {
-                    alive_bitset.remove(doc_matching_deleted_term);
-                    might_have_changed = true;
+        let terms = delete_op.terms();
+        if let Some(first_term) = terms.first() {
+            // The terms of an operation share their field, so its inverted index
+            // is only opened once.
+            let inverted_index = segment_reader.inverted_index(first_term.field())?;
+            for term in terms {
+                if let Some(mut docset) =
+                    inverted_index.read_postings(term, IndexRecordOption::Basic)?
+                {
+                    let mut doc_matching_deleted_term = docset.doc();
+                    while doc_matching_deleted_term != TERMINATED {
+                        if doc_opstamps.is_deleted(doc_matching_deleted_term, delete_op.opstamp) {
+                            alive_bitset.remove(doc_matching_deleted_term);
+                            might_have_changed = true;
+                        }
+                        doc_matching_deleted_term = docset.advance();
+                    }
                 }
-                doc_matching_deleted_term = docset.advance();
             }
         }
         delete_cursor.advance();
@@ -167,38 +194,128 @@ pub(crate) fn advance_deletes(
     Ok(())
 }
 
+// Returns the reason why the segment being built should be flushed, if any.
+//
+// The age of the segment is checked by the callers.
+fn flush_reason(
+    segment_writer: &SegmentWriter,
+    memory_budget: usize,
+    flush_triggers: FlushTriggers,
+) -> Option<&'static str> {
+    if segment_writer.mem_usage() >= memory_budget - MARGIN_IN_BYTES {
+        return Some("Buffer limit");
+    }
+    if let Some(max_docs) = flush_triggers.max_docs {
+        if segment_writer.max_doc() >= max_docs {
+            return Some("Document limit");
+        }
+    }
+    None
+}
+
 fn index_documents(
     memory_budget: usize,
+    flush_triggers: &SharedFlushTriggers,
     segment: Segment,
-    grouped_document_iterator: &mut dyn Iterator<Item = AddBatch>,
-    segment_updater: &mut SegmentUpdater,
+    first_document_group: AddBatch,
+    document_receiver: &AddBatchReceiver,
+    segment_updater: &SegmentUpdater,
     mut delete_cursor: DeleteCursor,
 ) -> crate::Result<()> {
-    let schema = segment.schema();
-
-    let mut segment_writer = SegmentWriter::for_segment(memory_budget, segment.clone(), schema)?;
-    for document_group in grouped_document_iterator {
+    let mut segment = segment;
+    let mut segment_writer =
+        SegmentWriter::for_segment(memory_budget, segment.clone(), segment.schema())?;
+    let mut start = Instant::now();
+    let mut document_group_opt = Some(first_document_group);
+    while let Some(document_group) = document_group_opt {
+        if has_new_fields(&segment_writer, &document_group) {
+            // Dynamic fields were added to the schema since the segment was created,
+            // so its documents go to a new segment.
+            let new_segment = segment.index().new_segment();
+            let new_segment_writer = SegmentWriter::for_segment(
+                memory_budget,
+                new_segment.clone(),
+                new_segment.schema(),
+            )?;
+            let mut new_delete_cursor = delete_cursor.clone();
+            new_delete_cursor.skip_to(document_group[0].opstamp);
+            finalize_segment(
+                mem::replace(&mut segment, new_segment),
+                mem::replace(&mut segment_writer, new_segment_writer),
+                segment_updater,
+                mem::replace(&mut delete_cursor, new_delete_cursor),
+            )?;
+            start = Instant::now();
+        }
         for doc in document_group {
             segment_writer.add_document(doc)?;
         }
-        let mem_usage = segment_writer.mem_usage();
-        if mem_usage >= memory_budget - MARGIN_IN_BYTES {
+        let flush_triggers = flush_triggers.get();
+        if let Some(reason) = flush_reason(&segment_writer, memory_budget, flush_triggers) {
             info!(
-                "Buffer limit reached, flushing segment with maxdoc={}.",
+                "{} reached, flushing segment with maxdoc={}.",
+                reason,
                 segment_writer.max_doc()
             );
             break;
         }
+        document_group_opt = if let Some(max_age) = flush_triggers.max_age {
+            // Under continuous ingestion, `recv_deadline` keeps returning the batches that are
+            // already queued, even past the deadline.
+            if start.elapsed() >= max_age {
+                info!(
+                    "Age limit reached, flushing segment with maxdoc={}.",
+                    segment_writer.max_doc()
+                );
+                break;
+            }
+            match document_receiver.recv_deadline(start + max_age) {
+                Ok(document_group) => Some(document_group),
+                Err(RecvTimeoutError::Timeout) => {
+                    info!(
+                        "Age limit reached, flushing segment with maxdoc={}.",
+                        segment_writer.max_doc()
+                    );
+                    break;
+                }
+                Err(RecvTimeoutError::Disconnected) => None,
+            }
+        } else {
+            document_receiver.recv().ok()
+        };
     }
 
+    finalize_segment(segment, segment_writer, segment_updater, delete_cursor)
+}
+
+/// Returns true if documents of the group have values for fields added to the schema
+/// after the segment writer was created.
+fn has_new_fields(segment_writer: &SegmentWriter, document_group: &AddBatch) -> bool {
+    let num_fields = segment_writer.schema().num_fields();
+    document_group.iter().any(|add_operation| {
+        add_operation
+            .document
+            .field_values()
+            .iter()
+            .any(|field_value| field_value.field().field_id() as usize >= num_fields)
+    })
+}
+
+fn finalize_segment(
+    segment: Segment,
+    segment_writer: SegmentWriter,
+    segment_updater: &SegmentUpdater,
+    mut delete_cursor: DeleteCursor,
+) -> crate::Result<()> {
     if !segment_updater.is_alive() {
         return Ok(());
     }
 
     let max_doc = segment_writer.max_doc();
+    let _span = enter_span!("finalize_segment", segment_id = %segment.id(), max_doc);
 
-    // this is ensured by the call to peek before starting
-    // the worker thread.
+    // this is ensured by only creating a segment once a
+    // non-empty batch is available.
     assert!(max_doc > 0);
 
     let doc_opstamps: Vec<Opstamp> = segment_writer.finalize()?;
@@ -207,7 +324,11 @@ fn index_documents(
 
     let alive_bitset_opt = apply_deletes(&segment_with_max_doc, &mut delete_cursor, &doc_opstamps)?;
 
-    let meta = segment_with_max_doc.meta().clone();
+    let meta = segment_with_max_doc
+        .meta()
+        .clone()
+        .with_max_opstamp(doc_opstamps.iter().copied().max())
+        .with_attributes(segment_updater.segment_attributes());
     meta.untrack_temp_docstore();
     // update segment_updater inventory to remove tempstore
     let segment_entry = SegmentEntry::new(meta, delete_cursor, alive_bitset_opt);
@@ -215,6 +336,81 @@ fn index_documents(
     Ok(())
 }
 
+/// Indexes documents on the thread adding them, for writers without indexing threads.
+///
+/// This avoids the channel hop and the thread handoff of the indexing pipeline,
+/// which dominate the latency when committing every few documents.
+struct DirectIndexer {
+    memory_budget: usize,
+    index: Index,
+    segment_updater: SegmentUpdater,
+    delete_cursor: DeleteCursor,
+    // The segment being built, along with the instant its first document was added.
+    segment_writer_opt: Option<(Segment, SegmentWriter, Instant)>,
+}
+
+impl DirectIndexer {
+    fn index_documents(
+        &mut self,
+        document_group: AddBatch,
+        flush_triggers: FlushTriggers,
+    ) -> crate::Result<()> {
+        if document_group.is_empty() {
+            return Ok(());
+        }
+        if let Some((_, segment_writer, _)) = &self.segment_writer_opt {
+            if has_new_fields(segment_writer, &document_group) {
+                self.flush()?;
+            }
+        }
+        let (_, segment_writer, start) = match &mut self.segment_writer_opt {
+            Some(segment_writer) => segment_writer,
+            segment_writer_opt @ None => {
+                self.delete_cursor.skip_to(document_group[0].opstamp);
+                let segment = self.index.new_segment();
+                let segment_writer = SegmentWriter::for_segment(
+                    self.memory_budget,
+                    segment.clone(),
+                    segment.schema(),
+                )?;
+                segment_writer_opt.insert((segment, segment_writer, Instant::now()))
+            }
+        };
+        for doc in document_group {
+            segment_writer.add_document(doc)?;
+        }
+        let reason_opt =
+            flush_reason(segment_writer, self.memory_budget, flush_triggers).or_else(|| {
+                flush_triggers
+                    .max_age
+                    .filter(|&max_age| start.elapsed() >= max_age)
+                    .map(|_| "Age limit")
+            });
+        if let Some(reason) = reason_opt {
+            info!(
+                "{} reached, flushing segment with maxdoc={}.",
+                reason,
+                segment_writer.max_doc()
+            );
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Adds the segment being built, if any, to the uncommitted segments.
+    fn flush(&mut self) -> crate::Result<()> {
+        if let Some((segment, segment_writer, _)) = self.segment_writer_opt.take() {
+            finalize_segment(
+                segment,
+                segment_writer,
+                &self.segment_updater,
+                self.delete_cursor.clone(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 /// `doc_opstamps` is required to be non-empty.
 fn apply_deletes(
     segment: &Segment,
@@ -262,7 +458,8 @@ impl IndexWriter {
     /// it is safe to manually delete the lockfile.
     ///
     /// `num_threads` specifies the number of indexing workers that
-    /// should work at the same time.
+    /// should work at the same time. With 0 workers, documents are indexed
+    /// on the thread adding them.
     /// # Errors
     /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
     /// If the memory arena per thread is too small or too big, returns
@@ -271,7 +468,9 @@ impl IndexWriter {
         index: &Index,
         num_threads: usize,
         memory_arena_in_bytes_per_thread: usize,
+        flush_triggers: FlushTriggers,
         directory_lock: DirectoryLock,
+        writer_fence: WriterFence,
     ) -> crate::Result<IndexWriter> {
         if memory_arena_in_bytes_per_thread < MEMORY_ARENA_NUM_BYTES_MIN {
             let err_msg = format!(
@@ -296,13 +495,42 @@ impl IndexWriter {
 
         let stamper = Stamper::new(current_opstamp);
 
-        let segment_updater =
-            SegmentUpdater::create(index.clone(), stamper.clone(), &delete_queue.cursor())?;
+        let segment_updater = SegmentUpdater::create(
+            index.clone(),
+            stamper.clone(),
+            &delete_queue.cursor(),
+            writer_fence,
+        )?;
+
+        let direct_indexer_opt = if num_threads == 0 {
+            Some(Mutex::new(DirectIndexer {
+                memory_budget: memory_arena_in_bytes_per_thread,
+                index: index.clone(),
+                segment_updater: segment_updater.clone(),
+                delete_cursor: delete_queue.cursor(),
+                segment_writer_opt: None,
+            }))
+        } else {
+            None
+        };
+
+        // The changes tracked by the version checker are kept until their commit is published.
+        let published_opstamp = Arc::new(AtomicU64::new(current_opstamp));
+        let hook_published_opstamp = published_opstamp.clone();
+        segment_updater
+            .commit_hooks_mut()
+            .add_post_publish_hook(Arc::new(move |index_meta: &IndexMeta| {
+                hook_published_opstamp.fetch_max(index_meta.opstamp, Ordering::SeqCst);
+            }));
+        let version_checker = VersionChecker::new(index.clone(), published_opstamp);
 
         let mut index_writer = IndexWriter {
             _directory_lock: Some(directory_lock),
 
             memory_arena_in_bytes_per_thread,
+            flush_triggers: SharedFlushTriggers::new(flush_triggers),
+            direct_indexer_opt,
+            version_checker: Mutex::new(version_checker),
             index: index.clone(),
 
             index_writer_status: IndexWriterStatus::from(document_receiver),
@@ -324,6 +552,44 @@ impl IndexWriter {
         Ok(index_writer)
     }
 
+    /// Creates a multithreaded writer, taking the writer lock over from its holder if the
+    /// holder stopped sending heartbeats for `takeover_timeout`.
+    ///
+    /// The returned writer sends a heartbeat every quarter of `takeover_timeout` while it is
+    /// alive, so that a crashed writer process does not leave the index unwritable until its
+    /// lock file is removed manually. All of the processes writing the index should open their
+    /// writers this way, with the same timeout: a lock held by a writer that does not send
+    /// heartbeats is never taken over.
+    ///
+    /// A writer whose lock was taken over, e.g. after it stalled for longer than
+    /// `takeover_timeout`, stops sending heartbeats, and its commits, the publication of its
+    /// merges and its garbage collections fail with `TantivyError::LockFailure`. Dropping it
+    /// leaves the lock to the writer which took it over.
+    ///
+    /// See [`Index::writer_with_num_threads`] for the other arguments.
+    ///
+    /// # Errors
+    /// If the lock holder keeps sending heartbeats, or does not send any, returns
+    /// `TantivyError::LockFailure`. If `takeover_timeout` is zero, returns
+    /// `TantivyError::InvalidArgument`.
+    pub fn open_with_lock_takeover(
+        index: &Index,
+        num_threads: usize,
+        overall_memory_arena_in_bytes: usize,
+        takeover_timeout: Duration,
+    ) -> crate::Result<IndexWriter> {
+        let (directory_lock, writer_fence) =
+            acquire_writer_lock_with_takeover(index.directory(), takeover_timeout)?;
+        IndexWriter::new(
+            index,
+            num_threads,
+            overall_memory_arena_in_bytes / num_threads,
+            FlushTriggers::default(),
+            directory_lock,
+            writer_fence,
+        )
+    }
+
     fn drop_sender(&mut self) {
         let (sender, _receiver) = crossbeam_channel::bounded(1);
         self.operation_sender = sender;
@@ -400,43 +666,40 @@ impl IndexWriter {
         let document_receiver_clone = self.operation_receiver()?;
         let index_writer_bomb = self.index_writer_status.create_bomb();
 
-        let mut segment_updater = self.segment_updater.clone();
+        let segment_updater = self.segment_updater.clone();
 
         let mut delete_cursor = self.delete_queue.cursor();
 
         let mem_budget = self.memory_arena_in_bytes_per_thread;
+        let flush_triggers = self.flush_triggers.clone();
         let index = self.index.clone();
         let join_handle: JoinHandle<crate::Result<()>> = thread::Builder::new()
             .name(format!("thrd-tantivy-index{}", self.worker_id))
             .spawn(move || {
                 loop {
-                    let mut document_iterator = document_receiver_clone
-                        .clone()
-                        .into_iter()
-                        .filter(|batch| !batch.is_empty())
-                        .peekable();
-
-                    // The peeking here is to avoid creating a new segment's files
-                    // if no document are available.
-                    //
-                    // This is a valid guarantee as the peeked document now belongs to
-                    // our local iterator.
-                    if let Some(batch) = document_iterator.peek() {
-                        assert!(!batch.is_empty());
-                        delete_cursor.skip_to(batch[0].opstamp);
+                    // Waiting for the first non-empty batch here is to avoid creating
+                    // a new segment's files if no document are available.
+                    let first_batch_opt = document_receiver_clone
+                        .iter()
+                        .find(|batch| !batch.is_empty());
+                    let first_batch = if let Some(first_batch) = first_batch_opt {
+                        delete_cursor.skip_to(first_batch[0].opstamp);
+                        first_batch
                     } else {
                         // No more documents.
                         // It happens when there is a commit, or if the `IndexWriter`
                         // was dropped.
                         index_writer_bomb.defuse();
                         return Ok(());
-                    }
+                    };
 
                     index_documents(
                         mem_budget,
+                        &flush_triggers,
                         index.new_segment(),
-                        &mut document_iterator,
-                        &mut segment_updater,
+                        first_batch,
+                        &document_receiver_clone,
+                        &segment_updater,
                         delete_cursor.clone(),
                     )?;
                 }
@@ -456,6 +719,38 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
 
+    /// Accessor to the flush triggers.
+    pub fn flush_triggers(&self) -> FlushTriggers {
+        self.flush_triggers.get()
+    }
+
+    /// Setter for the flush triggers.
+    ///
+    /// The new triggers apply right away, including to the segments
+    /// the indexing threads are currently building.
+    pub fn set_flush_triggers(&self, flush_triggers: FlushTriggers) {
+        self.flush_triggers.set(flush_triggers);
+    }
+
+    /// Accessor to the attributes given to the flushed segments.
+    pub fn segment_attributes(&self) -> BTreeMap<String, String> {
+        self.segment_updater.segment_attributes()
+    }
+
+    /// Setter for the attributes given to the flushed segments, e.g. the pipeline or the
+    /// tenant their documents come from.
+    ///
+    /// The attributes are persisted in the `meta.json` file, and available through
+    /// [`SegmentMeta::attributes`] to the merge policy and to
+    /// [`Searcher::search_with_segment_filter`](crate::Searcher::search_with_segment_filter).
+    /// They apply to the segments flushed from then on, including the segments the indexing
+    /// threads are currently building: commit before changing them to tag exactly the
+    /// documents added afterwards. A merged segment keeps the attributes all of the merged
+    /// segments share.
+    pub fn set_segment_attributes(&self, attributes: BTreeMap<String, String>) {
+        self.segment_updater.set_segment_attributes(attributes);
+    }
+
     fn start_workers(&mut self) -> crate::Result<()> {
         for _ in 0..self.num_threads {
             self.add_indexing_worker()?;
@@ -505,6 +800,7 @@ impl IndexWriter {
     /// }
     /// ```
     pub fn delete_all_documents(&self) -> crate::Result<Opstamp> {
+        self.lock_version_checker().delete_all();
         // Delete segments
         self.segment_updater.remove_all_segments();
         // Return new stamp - reverted stamp
@@ -521,6 +817,68 @@ impl IndexWriter {
         segment_updater.start_merge(merge_operation)
     }
 
+    /// Returns a handle over each of the merges currently running, whether they were
+    /// started by the merge policy or through [`IndexWriter::merge`].
+    ///
+    /// The handles give the segments being merged and the progress of the merge, and
+    /// make it possible to wait for a specific merge or to cancel it.
+    pub fn running_merges(&self) -> Vec<RunningMerge> {
+        self.segment_updater.running_merges()
+    }
+
+    /// Returns the policy deciding which commits synchronize the directory once their
+    /// `meta.json` is written.
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.segment_updater.fsync_scheduler().fsync_policy()
+    }
+
+    /// Sets the policy deciding which commits synchronize the directory, trading durability
+    /// for commit latency. See [`FsyncPolicy`].
+    pub fn set_fsync_policy(&self, fsync_policy: FsyncPolicy) {
+        self.segment_updater
+            .fsync_scheduler()
+            .set_fsync_policy(fsync_policy);
+    }
+
+    /// Registers a hook run by each commit before its segments are committed, with the opstamp
+    /// and the payload of the commit, e.g. to flush external data that the commit refers to.
+    ///
+    /// The hooks run synchronously on the thread of the segment updater, in registration
+    /// order. If a hook fails, the commit fails with its error, and the metas are not written.
+    pub fn add_pre_commit_hook(
+        &self,
+        pre_commit_hook: impl Fn(Opstamp, Option<&str>) -> crate::Result<()> + Send + Sync + 'static,
+    ) {
+        self.segment_updater
+            .commit_hooks_mut()
+            .add_pre_commit_hook(Arc::new(pre_commit_hook));
+    }
+
+    /// Registers a hook run with the new metas whenever they are published, that is right after
+    /// `meta.json` is written by a commit or by the end of a merge, e.g. to notify a replication
+    /// process exactly when a new generation becomes visible.
+    ///
+    /// The hooks run synchronously on the thread of the segment updater, in registration
+    /// order, so they should be quick.
+    pub fn add_post_publish_hook(
+        &self,
+        post_publish_hook: impl Fn(&IndexMeta) + Send + Sync + 'static,
+    ) {
+        self.segment_updater
+            .commit_hooks_mut()
+            .add_post_publish_hook(Arc::new(post_publish_hook));
+    }
+
+    /// Returns the numbers of alive and deleted documents of the segments of the last commit,
+    /// and of the segments flushed since then.
+    ///
+    /// Unlike the statistics of a searcher, they take the uncommitted segments into account,
+    /// without requiring a reload. The documents still buffered by the indexing threads, and the
+    /// deletes not applied to the segments yet, are not counted.
+    pub fn live_doc_stats(&self) -> LiveDocStats {
+        self.segment_updater.live_doc_stats()
+    }
+
     /// Closes the current document channel send.
     /// and replace all the channels by new ones.
     ///
@@ -561,7 +919,9 @@ impl IndexWriter {
             &self.index,
             self.num_threads,
             self.memory_arena_in_bytes_per_thread,
+            self.flush_triggers.get(),
             directory_lock,
+            self.segment_updater.writer_fence().clone(),
         )?;
 
         // the current `self` is dropped right away because of this call.
@@ -615,22 +975,13 @@ impl IndexWriter {
         // This will move uncommitted segments to the state of
         // committed segments.
         info!("Preparing commit");
+        let _span = enter_span!("prepare_commit");
 
-        // this will drop the current document channel
-        // and recreate a new one.
-        self.recreate_document_channel();
-
-        let former_workers_join_handle = std::mem::take(&mut self.workers_join_handle);
-
-        for worker_handle in former_workers_join_handle {
-            let indexing_worker_result = worker_handle
-                .join()
-                .map_err(|e| TantivyError::ErrorInThread(format!("{:?}", e)))?;
-            indexing_worker_result?;
-            self.add_indexing_worker()?;
-        }
+        self.stop_workers()?;
+        self.start_workers()?;
 
         let commit_opstamp = self.stamper.stamp();
+        self.lock_version_checker().prepare_commit(commit_opstamp);
         let prepared_commit = PreparedCommit::new(self, commit_opstamp);
         info!("Prepared commit {}", commit_opstamp);
         Ok(prepared_commit)
@@ -653,6 +1004,57 @@ impl IndexWriter {
         self.prepare_commit()?.commit()
     }
 
+    /// Pauses the writer, e.g. to back up or replicate the index without dropping the writer.
+    ///
+    /// The pending documents are flushed to uncommitted segments, as they would be by
+    /// `prepare_commit`, and the indexing threads terminate, releasing their memory. No new merge
+    /// is started, and the running merges are waited for. The writer resumes when the
+    /// returned [`PausedIndexWriter`] is resumed or dropped.
+    ///
+    /// Unlike a commit, the pause does not publish the pending documents: they are committed
+    /// or rolled back after the writer is resumed, as usual.
+    pub fn pause(&mut self) -> crate::Result<PausedIndexWriter<'_>> {
+        info!("Pausing the index writer");
+        self.segment_updater.pause_merges();
+        let paused_index_writer = PausedIndexWriter::new(self);
+        // If draining fails, dropping `paused_index_writer` resumes the writer.
+        paused_index_writer.index_writer.stop_workers()?;
+        paused_index_writer
+            .index_writer
+            .segment_updater
+            .wait_merging_thread()?;
+        Ok(paused_index_writer)
+    }
+
+    /// Flushes the pending documents, and waits for the indexing threads to terminate.
+    fn stop_workers(&mut self) -> crate::Result<()> {
+        if let Some(direct_indexer) = &self.direct_indexer_opt {
+            direct_indexer
+                .lock()
+                .expect("Direct indexer lock poisoned. This should never happen.")
+                .flush()?;
+        }
+        // Dropping the document channel terminates the indexing threads once they have
+        // flushed their segments.
+        self.recreate_document_channel();
+        for worker_handle in std::mem::take(&mut self.workers_join_handle) {
+            let indexing_worker_result = worker_handle
+                .join()
+                .map_err(|e| TantivyError::ErrorInThread(format!("{:?}", e)))?;
+            indexing_worker_result?;
+        }
+        Ok(())
+    }
+
+    /// Restarts the indexing threads and the merges stopped by `pause`.
+    pub(crate) fn resume(&mut self) -> crate::Result<()> {
+        info!("Resuming the index writer");
+        while self.workers_join_handle.len() < self.num_threads {
+            self.add_indexing_worker()?;
+        }
+        self.segment_updater.resume_merges().wait()
+    }
+
     pub(crate) fn segment_updater(&self) -> &SegmentUpdater {
         &self.segment_updater
     }
@@ -666,10 +1068,120 @@ impl IndexWriter {
     /// Like adds, the deletion itself will be visible
     /// only after calling `commit()`.
     pub fn delete_term(&self, term: Term) -> Opstamp {
-        let opstamp = self.stamper.stamp();
-        let delete_operation = DeleteOperation { opstamp, term };
-        self.delete_queue.push(delete_operation);
-        opstamp
+        let changes = Change::delete(&self.index.schema(), term.clone());
+        self.track_changes(changes, || {
+            let opstamp = self.stamper.stamp();
+            let delete_operation = DeleteOperation {
+                opstamp,
+                target: DeleteTarget::Term(term),
+            };
+            self.delete_queue.push(delete_operation);
+            opstamp
+        })
+    }
+
+    /// Delete all documents containing any of the given values in `field`.
+    ///
+    /// This is equivalent to calling `delete_term` for each of the values, but enqueues a
+    /// single operation, with a single opstamp, whose terms are looked up together in each
+    /// segment. Values that do not map to a single term, like json objects, are ignored.
+    pub fn delete_terms(&self, field: Field, values: impl IntoIterator<Item = Value>) -> Opstamp {
+        let mut terms: Vec<Term> = values
+            .into_iter()
+            .filter_map(|value| Term::from_field_value(field, &value))
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        let schema = self.index.schema();
+        let changes: Vec<Change> = terms
+            .iter()
+            .filter_map(|term| Change::delete(&schema, term.clone()))
+            .collect();
+        self.track_changes(changes, || {
+            let opstamp = self.stamper.stamp();
+            let delete_operation = DeleteOperation {
+                opstamp,
+                target: DeleteTarget::TermSet(terms),
+            };
+            self.delete_queue.push(delete_operation);
+            opstamp
+        })
+    }
+
+    /// Replaces the document with the key `key` by `document`, provided the current version
+    /// of that document is `expected_version`.
+    ///
+    /// `key` is a term of the primary key field of the schema, see
+    /// [`NumericOptions::set_primary_key`](crate::schema::NumericOptions::set_primary_key), and
+    /// `expected_version` is a term of the field holding the version of the documents, which
+    /// must be a single-valued u64 or i64 fast field. The version is read from that fast field.
+    ///
+    /// The current version takes into account all of the operations of this writer, and not
+    /// only the previous calls to this method: the documents added, the deletes and the updates
+    /// made since the last commit, and those of the commits which are not published yet. A
+    /// document added since the last commit is considered to have no version once documents are
+    /// deleted by a term of another field than the primary key, until the next commit.
+    ///
+    /// This makes it possible for several producers to update the same documents with
+    /// optimistic concurrency: each producer reads a document along with its version, and the
+    /// update of a producer is rejected if another producer updated the document in the
+    /// meantime.
+    ///
+    /// Returns the opstamp of the update, or `None` if the current version does not match and
+    /// the document was not updated.
+    pub fn update_document_if(
+        &self,
+        key: Term,
+        expected_version: Term,
+        document: Document,
+    ) -> crate::Result<Option<Opstamp>> {
+        let schema = self.index.schema();
+        let key_field = schema.primary_key_field().ok_or_else(|| {
+            TantivyError::SchemaError("The schema does not have a primary key.".to_string())
+        })?;
+        if key.field() != key_field {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Term {:?} is not a term of the primary key field {:?}",
+                key,
+                schema.get_field_name(key_field)
+            )));
+        }
+        let version = version_from_term(&schema, &expected_version)?;
+        let mut version_checker = self.lock_version_checker();
+        if !version_checker.has_version(&key, expected_version.field(), version)? {
+            return Ok(None);
+        }
+        let user_operations = vec![UserOperation::Delete(key), UserOperation::Add(document)];
+        let (user_operations, changes) = self.prepare_operations(user_operations)?;
+        let opstamp = self.send_operations(user_operations)?;
+        version_checker.record(changes);
+        Ok(Some(opstamp))
+    }
+
+    fn lock_version_checker(&self) -> MutexGuard<'_, VersionChecker> {
+        self.version_checker
+            .lock()
+            .expect("Version checker lock poisoned. This should never happen.")
+    }
+
+    /// Runs `operations`, and records the `changes` they make to the documents with a primary
+    /// key, so that `update_document_if` sees them.
+    ///
+    /// The version checker is locked while the operations are stamped, so that the changes are
+    /// recorded in the order of their opstamps.
+    fn track_changes<T>(
+        &self,
+        changes: impl IntoIterator<Item = Change>,
+        operations: impl FnOnce() -> T,
+    ) -> T {
+        let mut changes = changes.into_iter().peekable();
+        if changes.peek().is_none() {
+            return operations();
+        }
+        let mut version_checker = self.lock_version_checker();
+        let result = operations();
+        version_checker.record(changes);
+        result
     }
 
     /// Returns the opstamp of the last successful commit.
@@ -683,6 +1195,109 @@ impl IndexWriter {
         self.committed_opstamp
     }
 
+    /// Parses a json document, as `Schema::parse_document` does, once the fields created from
+    /// the dynamic templates of the schema for its unknown field names are added to the schema
+    /// of the index.
+    ///
+    /// The fields added are persisted in the schema of `meta.json` upon the next commit.
+    pub fn parse_document(&self, doc_json: &str) -> crate::Result<Document> {
+        let (schema, json_obj) = self.parse_json_object(doc_json)?;
+        Ok(schema.json_object_to_doc(json_obj)?)
+    }
+
+    /// Marks `field` as deprecated, to reclaim the space it uses without reindexing.
+    ///
+    /// The values of the field are ignored by the documents added from now on, and the
+    /// merges, including the ones triggered by `merge`, drop its postings, fieldnorms, fast
+    /// field and stored values from the segments they write. The deprecation is persisted
+    /// in the schema of `meta.json` upon the next commit.
+    ///
+    /// Returns an error if the field is the primary key of the schema, or the field the index
+    /// is sorted by.
+    pub fn deprecate_field(&self, field: Field) -> crate::Result<()> {
+        let schema = self.index.schema();
+        let field_entry = schema.get_field_entry(field);
+        let is_sort_field = matches!(
+            &self.index.settings().sort_by_field,
+            Some(sort_by_field) if sort_by_field.field == field_entry.name()
+        );
+        if field_entry.is_primary_key() || is_sort_field {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The field {:?} cannot be deprecated, as it is the primary key or the sort field \
+                 of the index.",
+                field_entry.name()
+            )));
+        }
+        self.index.deprecate_field(field);
+        Ok(())
+    }
+
+    /// Parses and adds a batch of json documents, rejecting the documents with fields that
+    /// are neither in the schema nor created from one of its dynamic templates.
+    ///
+    /// The valid documents are added as with [`add_documents`](#method.add_documents).
+    /// The rejected documents are reported in the returned `BatchResult`, instead of having
+    /// their unknown fields silently dropped as `parse_document` does, and do not add any
+    /// field to the schema.
+    pub fn add_json_documents<'a>(
+        &self,
+        docs_json: impl IntoIterator<Item = &'a str>,
+    ) -> crate::Result<BatchResult> {
+        let documents = docs_json
+            .into_iter()
+            .map(|doc_json| Ok(self.parse_json_document_strict(doc_json)?));
+        self.add_valid_documents(documents)
+    }
+
+    /// Parses a json document, rejecting it if it has fields that are neither in the schema
+    /// nor created from one of its dynamic templates.
+    ///
+    /// The document is validated before the fields created from the dynamic templates are
+    /// added to the schema of the index, so that rejected documents do not add any field.
+    fn parse_json_document_strict(&self, doc_json: &str) -> Result<Document, DocParsingError> {
+        let json_obj: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(doc_json).map_err(|_| DocParsingError::invalid_json(doc_json))?;
+        let schema = self.index.schema();
+        let extended_schema = schema
+            .with_dynamic_fields(json_obj.keys().map(String::as_str))
+            .unwrap_or(schema);
+        let document = extended_schema.json_object_to_doc_strict(json_obj)?;
+        let field_names = document
+            .field_values()
+            .iter()
+            .map(|field_value| extended_schema.get_field_name(field_value.field()));
+        let index_schema = self.index.add_dynamic_fields(field_names);
+        if index_schema == extended_schema {
+            return Ok(document);
+        }
+        // Fields were added to the schema of the index in the meantime, which may number
+        // the dynamic fields of the document differently.
+        Ok(Document::from(
+            document
+                .into_iter()
+                .filter_map(|field_value| {
+                    let field_name = extended_schema.get_field_name(field_value.field());
+                    let field = index_schema.get_field(field_name)?;
+                    Some(FieldValue::new(field, field_value.value))
+                })
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /// Parses a json object, and adds the fields created from the dynamic templates
+    /// for its keys to the schema of the index.
+    fn parse_json_object(
+        &self,
+        doc_json: &str,
+    ) -> Result<(Schema, serde_json::Map<String, serde_json::Value>), DocParsingError> {
+        let json_obj: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(doc_json).map_err(|_| DocParsingError::invalid_json(doc_json))?;
+        let schema = self
+            .index
+            .add_dynamic_fields(json_obj.keys().map(String::as_str));
+        Ok((schema, json_obj))
+    }
+
     /// Adds a document.
     ///
     /// If the indexing pipeline is full, this call may block.
@@ -690,10 +1305,15 @@ impl IndexWriter {
     /// The opstamp is an increasing `u64` that can
     /// be used by the client to align commits with its own
     /// document queue.
-    pub fn add_document(&self, document: Document) -> crate::Result<Opstamp> {
-        let opstamp = self.stamper.stamp();
-        self.send_add_documents_batch(smallvec![AddOperation { opstamp, document }])?;
-        Ok(opstamp)
+    pub fn add_document(&self, mut document: Document) -> crate::Result<Opstamp> {
+        let schema = self.index.schema();
+        schema.prepare_document(&mut document)?;
+        let changes = Change::add(&schema, &document);
+        self.track_changes(changes, || {
+            let opstamp = self.stamper.stamp();
+            self.send_add_documents_batch(smallvec![AddOperation { opstamp, document }])?;
+            Ok(opstamp)
+        })
     }
 
     /// Gets a range of stamps from the stamper and "pops" the last stamp
@@ -730,18 +1350,50 @@ impl IndexWriter {
         I: IntoIterator<Item = UserOperation>,
         I::IntoIter: ExactSizeIterator,
     {
-        let user_operations_it = user_operations.into_iter();
-        let count = user_operations_it.len() as u64;
+        let user_operations: Vec<UserOperation> = user_operations.into_iter().collect();
+        let (user_operations, changes) = self.prepare_operations(user_operations)?;
+        self.track_changes(changes, || self.send_operations(user_operations))
+    }
+
+    /// Checks the documents of `user_operations`, and returns the operations along with the
+    /// changes they make to the documents with a primary key.
+    ///
+    /// The documents are checked before any operation is run, so that the group is rejected as
+    /// a whole.
+    fn prepare_operations(
+        &self,
+        mut user_operations: Vec<UserOperation>,
+    ) -> crate::Result<(Vec<UserOperation>, Vec<Change>)> {
+        let schema = self.index.schema();
+        let mut changes = Vec::new();
+        for user_op in &mut user_operations {
+            let change_opt = match user_op {
+                UserOperation::Add(document) => {
+                    schema.prepare_document(document)?;
+                    Change::add(&schema, document)
+                }
+                UserOperation::Delete(term) => Change::delete(&schema, term.clone()),
+            };
+            changes.extend(change_opt);
+        }
+        Ok((user_operations, changes))
+    }
+
+    fn send_operations(&self, user_operations: Vec<UserOperation>) -> crate::Result<Opstamp> {
+        let count = user_operations.len() as u64;
         if count == 0 {
             return Ok(self.stamper.stamp());
         }
         let (batch_opstamp, stamps) = self.get_batch_opstamps(count);
 
         let mut adds = AddBatch::default();
-        for (user_op, opstamp) in user_operations_it.zip(stamps) {
+        for (user_op, opstamp) in user_operations.into_iter().zip(stamps) {
             match user_op {
                 UserOperation::Delete(term) => {
-                    let delete_operation = DeleteOperation { opstamp, term };
+                    let delete_operation = DeleteOperation {
+                        opstamp,
+                        target: DeleteTarget::Term(term),
+                    };
                     self.delete_queue.push(delete_operation);
                 }
                 UserOperation::Add(document) => {
@@ -754,7 +1406,214 @@ impl IndexWriter {
         Ok(batch_opstamp)
     }
 
+    /// Adds a large batch of documents, splitting it across the indexing threads.
+    ///
+    /// Like for [`run`](#method.run), the documents receive in-order, contiguous
+    /// opstamps and the batch itself is given an opstamp that is 1 greater than the
+    /// last document. This `batch_opstamp` is the return value.
+    ///
+    /// Unlike `run`, the documents are not guaranteed to end up in the same segment:
+    /// the batch is split in as many chunks as there are indexing threads, and each chunk
+    /// is sent to the indexing pipeline as a single operation, avoiding the overhead
+    /// of sending the documents one by one.
+    /// The whole batch becomes visible to readers at the next `commit()`.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    pub fn add_documents_parallel(&self, mut documents: Vec<Document>) -> crate::Result<Opstamp> {
+        let schema = self.index.schema();
+        for document in &mut documents {
+            schema.prepare_document(document)?;
+        }
+        self.send_documents_parallel(documents)
+    }
+
+    /// Adds a batch of documents, skipping the documents that violate the constraints
+    /// of the schema instead of rejecting the whole batch.
+    ///
+    /// The valid documents are added as with
+    /// [`add_documents_parallel`](#method.add_documents_parallel), and the returned
+    /// `BatchResult` lists the other ones, along with their position in the batch.
+    ///
+    /// # Errors
+    /// The batch fails as a whole only if the indexing pipeline fails.
+    pub fn add_documents(&self, documents: Vec<Document>) -> crate::Result<BatchResult> {
+        self.add_valid_documents(documents.into_iter().map(Ok))
+    }
+
+    fn add_valid_documents(
+        &self,
+        documents: impl Iterator<Item = Result<Document, DocumentError>>,
+    ) -> crate::Result<BatchResult> {
+        // The documents are parsed before the schema is read, as parsing them may add
+        // dynamic fields to it.
+        let documents: Vec<Result<Document, DocumentError>> = documents.collect();
+        let schema = self.index.schema();
+        let mut valid_documents = Vec::new();
+        let mut failures = Vec::new();
+        for (position, document_res) in documents.into_iter().enumerate() {
+            let document_res = document_res.and_then(|mut document| {
+                schema.prepare_document(&mut document)?;
+                Ok(document)
+            });
+            match document_res {
+                Ok(document) => valid_documents.push(document),
+                Err(error) => failures.push(DocumentFailure { position, error }),
+            }
+        }
+        let num_added = valid_documents.len();
+        let opstamp = self.send_documents_parallel(valid_documents)?;
+        Ok(BatchResult {
+            opstamp,
+            num_added,
+            failures,
+        })
+    }
+
+    fn send_documents_parallel(&self, documents: Vec<Document>) -> crate::Result<Opstamp> {
+        let schema = self.index.schema();
+        let changes: Vec<Change> = documents
+            .iter()
+            .filter_map(|document| Change::add(&schema, document))
+            .collect();
+        self.track_changes(changes, || self.send_documents_in_chunks(documents))
+    }
+
+    fn send_documents_in_chunks(&self, documents: Vec<Document>) -> crate::Result<Opstamp> {
+        let count = documents.len() as u64;
+        if count == 0 {
+            return Ok(self.stamper.stamp());
+        }
+        let (batch_opstamp, stamps) = self.get_batch_opstamps(count);
+        let chunk_len = documents.len().div_ceil(self.num_threads.max(1));
+        let mut adds = AddBatch::with_capacity(chunk_len);
+        for (document, opstamp) in documents.into_iter().zip(stamps) {
+            adds.push(AddOperation { opstamp, document });
+            if adds.len() == chunk_len {
+                self.send_add_documents_batch(std::mem::replace(
+                    &mut adds,
+                    AddBatch::with_capacity(chunk_len),
+                ))?;
+            }
+        }
+        if !adds.is_empty() {
+            self.send_add_documents_batch(adds)?;
+        }
+        Ok(batch_opstamp)
+    }
+
+    /// Indexes the rows of a column batch, one document per row.
+    ///
+    /// This is a convenience wrapper: each row is converted into a [`Document`], and the
+    /// documents then go through the regular indexing path. It spares building the documents
+    /// by hand, but is not faster than indexing them directly.
+    ///
+    /// `mapping` associates the name of a column of the batch to the field its values
+    /// are indexed in. Columns which are not mapped are ignored, and missing values are
+    /// skipped. String columns can be mapped to facet fields, in which case they have to
+    /// contain facet paths, and to json fields, in which case they have to contain
+    /// serialized json objects.
+    ///
+    /// The documents are sent as in
+    /// [`add_documents_parallel`](#method.add_documents_parallel), whose opstamp semantics
+    /// apply.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::InvalidArgument` if a mapped column is missing from the batch,
+    /// or if a value cannot be parsed, and `TantivyError::SchemaError` if the type of a
+    /// column does not match the type of its field.
+    pub fn add_column_batch_as_documents(
+        &self,
+        batch: &ColumnBatch,
+        mapping: &[(&str, Field)],
+    ) -> crate::Result<Opstamp> {
+        let schema = self.index.schema();
+        let mut mapped_columns = Vec::with_capacity(mapping.len());
+        for &(column_name, field) in mapping {
+            let column = batch.column(column_name).ok_or_else(|| {
+                TantivyError::InvalidArgument(format!(
+                    "Column {:?} is missing from the batch.",
+                    column_name
+                ))
+            })?;
+            let field_entry = schema.get_field_entry(field);
+            if !column.is_compatible_with(field_entry.field_type()) {
+                return Err(TantivyError::SchemaError(format!(
+                    "Column {:?} cannot be indexed in field {:?} of type {:?}.",
+                    column_name,
+                    field_entry.name(),
+                    field_entry.field_type().value_type()
+                )));
+            }
+            mapped_columns.push((column, field, field_entry.field_type()));
+        }
+        let documents = (0..batch.num_rows())
+            .map(|row| {
+                let mut document = Document::default();
+                for &(column, field, field_type) in &mapped_columns {
+                    if let Some(value) = column.value(row, field_type)? {
+                        document.add_field_value(field, value);
+                    }
+                }
+                Ok(document)
+            })
+            .collect::<crate::Result<Vec<Document>>>()?;
+        self.add_documents_parallel(documents)
+    }
+
+    /// Indexes the rows of an Arrow record batch, one document per row.
+    ///
+    /// `mapping` associates the name of a column of the batch to the field its values are
+    /// indexed in, as in
+    /// [`add_column_batch_as_documents`](#method.add_column_batch_as_documents). The Arrow
+    /// columns are read directly, without going through a [`ColumnBatch`]: the type of each
+    /// column is checked against its field once, and its values are then converted in a
+    /// single pass over the column. The rows still go through the regular indexing path as
+    /// documents.
+    ///
+    /// Integers and floats are widened to 64 bits, timestamps are truncated to seconds,
+    /// and large strings and binaries are accepted.
+    ///
+    /// The documents are sent as in
+    /// [`add_documents_parallel`](#method.add_documents_parallel), whose opstamp semantics
+    /// apply.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::InvalidArgument` if a mapped column is missing from the batch,
+    /// or if a value cannot be parsed, and `TantivyError::SchemaError` if the type of a
+    /// column does not match the type of its field. No document is indexed upon an error.
+    #[cfg(feature = "arrow")]
+    pub fn add_record_batch(
+        &self,
+        batch: &arrow_array::RecordBatch,
+        mapping: &[(&str, Field)],
+    ) -> crate::Result<Opstamp> {
+        let schema = self.index.schema();
+        let mut documents = vec![Document::default(); batch.num_rows()];
+        for &(column_name, field) in mapping {
+            let column = batch.column_by_name(column_name).ok_or_else(|| {
+                TantivyError::InvalidArgument(format!(
+                    "Column {:?} is missing from the batch.",
+                    column_name
+                ))
+            })?;
+            let field_entry = schema.get_field_entry(field);
+            crate::columnar::add_array_to_documents(
+                column.as_ref(),
+                field,
+                field_entry,
+                &mut documents,
+            )?;
+        }
+        self.add_documents_parallel(documents)
+    }
+
     fn send_add_documents_batch(&self, add_ops: AddBatch) -> crate::Result<()> {
+        if let Some(direct_indexer) = &self.direct_indexer_opt {
+            return direct_indexer
+                .lock()
+                .expect("Direct indexer lock poisoned. This should never happen.")
+                .index_documents(add_ops, self.flush_triggers.get());
+        }
         if self.index_writer_status.is_alive() && self.operation_sender.send(add_ops).is_ok() {
             Ok(())
         } else {
@@ -775,25 +1634,34 @@ impl Drop for IndexWriter {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::{HashMap, HashSet};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     use proptest::prelude::*;
     use proptest::prop_oneof;
     use proptest::strategy::Strategy;
 
     use super::super::operation::UserOperation;
-    use crate::collector::TopDocs;
+    use crate::collector::{Count, TopDocs};
+    use crate::columnar::{Column, ColumnBatch};
     use crate::directory::error::LockError;
+    use crate::directory::RamDirectory;
     use crate::error::*;
     use crate::fastfield::FastFieldReader;
-    use crate::indexer::NoMergePolicy;
+    use crate::indexer::merge_policy::tests::MergeWheneverPossible;
+    use crate::indexer::{DocCounts, FsyncPolicy, LiveDocStats, NoMergePolicy};
     use crate::query::{QueryParser, TermQuery};
     use crate::schema::{
-        self, Cardinality, Facet, FacetOptions, IndexRecordOption, NumericOptions,
-        TextFieldIndexing, TextOptions, FAST, INDEXED, STORED, STRING, TEXT,
+        self, Cardinality, DocParsingError, Facet, FacetOptions, FieldConstraints, FieldType,
+        IndexRecordOption, NumericOptions, TextFieldIndexing, TextOptions, ValidationError, FAST,
+        INDEXED, STORED, STRING, TEXT,
     };
     use crate::store::DOCSTORE_CACHE_CAPACITY;
-    use crate::{DocAddress, Index, IndexSettings, IndexSortByField, Order, ReloadPolicy, Term};
+    use crate::{
+        DocAddress, DocumentError, DocumentFailure, FlushTriggers, Index, IndexSettings,
+        IndexSortByField, IndexWriter, Order, ReloadPolicy, Term,
+    };
 
     const LOREM: &str = "Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
                          eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad \
@@ -804,24 +1672,505 @@ mod tests {
                          mollit anim id est laborum.";
 
     #[test]
-    fn test_operations_group() {
-        // an operations group with 2 items should cause 3 opstamps 0, 1, and 2.
+    fn test_flush_triggers_max_docs() -> crate::Result<()> {
         let mut schema_builder = schema::Schema::builder();
-        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let text_field = schema_builder.add_text_field("text", TEXT);
         let index = Index::create_in_ram(schema_builder.build());
-        let index_writer = index.writer_for_tests().unwrap();
-        let operations = vec![
-            UserOperation::Add(doc!(text_field=>"a")),
-            UserOperation::Add(doc!(text_field=>"b")),
-        ];
-        let batch_opstamp1 = index_writer.run(operations).unwrap();
-        assert_eq!(batch_opstamp1, 2u64);
+        let mut index_writer = index.writer_with_flush_triggers(
+            1,
+            10_000_000,
+            FlushTriggers::default().with_max_docs(10),
+        )?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..25 {
+            index_writer.add_document(doc!(text_field => "a"))?;
+        }
+        index_writer.commit()?;
+        let mut num_docs_per_segment: Vec<u32> = index
+            .searchable_segment_metas()?
+            .iter()
+            .map(|segment_meta| segment_meta.max_doc())
+            .collect();
+        num_docs_per_segment.sort_unstable();
+        assert_eq!(num_docs_per_segment, vec![5, 10, 10]);
+
+        // Triggers can be adjusted at runtime.
+        index_writer.set_flush_triggers(FlushTriggers::default());
+        assert_eq!(index_writer.flush_triggers(), FlushTriggers::default());
+        for _ in 0..25 {
+            index_writer.add_document(doc!(text_field => "a"))?;
+        }
+        index_writer.commit()?;
+        assert_eq!(index.searchable_segment_metas()?.len(), 4);
+        Ok(())
     }
 
     #[test]
-    fn test_no_need_to_rewrite_delete_file_if_no_new_deletes() {
+    fn test_live_doc_stats() -> crate::Result<()> {
         let mut schema_builder = schema::Schema::builder();
-        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_flush_triggers(
+            1,
+            10_000_000,
+            FlushTriggers::default().with_max_docs(2),
+        )?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        assert_eq!(index_writer.live_doc_stats(), LiveDocStats::default());
+        for text in ["a", "b", "c", "d"] {
+            index_writer.add_document(doc!(text_field => text))?;
+        }
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit()?;
+        let committed = DocCounts {
+            num_segments: 2,
+            num_docs: 3,
+            num_deleted_docs: 1,
+        };
+        assert_eq!(index_writer.live_doc_stats().committed, committed);
+
+        // The segments flushed by the flush trigger are counted before the commit.
+        index_writer.add_document(doc!(text_field => "e"))?;
+        index_writer.add_document(doc!(text_field => "f"))?;
+        let start = std::time::Instant::now();
+        while index_writer.live_doc_stats().uncommitted.num_docs < 2 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let live_doc_stats = index_writer.live_doc_stats();
+        assert_eq!(live_doc_stats.committed, committed);
+        assert_eq!(
+            live_doc_stats.total(),
+            DocCounts {
+                num_segments: 3,
+                num_docs: 5,
+                num_deleted_docs: 1,
+            }
+        );
+        index_writer.commit()?;
+        assert_eq!(
+            index_writer.live_doc_stats().uncommitted,
+            DocCounts::default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_resume() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(2, 12_000_000)?;
+        index_writer.set_merge_policy(Box::new(MergeWheneverPossible));
+        for text in ["a", "b", "c", "d"] {
+            index_writer.add_document(doc!(text_field => text))?;
+            index_writer.commit()?;
+        }
+        index_writer.add_document(doc!(text_field => "e"))?;
+
+        let paused_index_writer = index_writer.pause()?;
+        // The pending document is flushed, and no merge is running.
+        assert!(paused_index_writer.index_writer.running_merges().is_empty());
+        let live_doc_stats = paused_index_writer.index_writer.live_doc_stats();
+        assert_eq!(live_doc_stats.uncommitted.num_docs, 1);
+        assert_eq!(live_doc_stats.total().num_docs, 5);
+        let managed_files = paused_index_writer.index().directory().list_managed_files();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(
+            paused_index_writer.index().directory().list_managed_files(),
+            managed_files
+        );
+        paused_index_writer.resume()?;
+
+        index_writer.add_document(doc!(text_field => "f"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 6);
+
+        // Dropping the paused writer resumes it as well.
+        drop(index_writer.pause()?);
+        index_writer.add_document(doc!(text_field => "g"))?;
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 7);
+        assert_eq!(searcher.segment_readers().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_segment_attributes() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let attributes = |batch: &str| {
+            BTreeMap::from([
+                ("tier".to_string(), "hot".to_string()),
+                ("batch".to_string(), batch.to_string()),
+            ])
+        };
+        assert!(index_writer.segment_attributes().is_empty());
+        index_writer.set_segment_attributes(attributes("1"));
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.commit()?;
+        index_writer.set_segment_attributes(attributes("2"));
+        assert_eq!(index_writer.segment_attributes(), attributes("2"));
+        index_writer.add_document(doc!(text_field => "b"))?;
+        index_writer.commit()?;
+
+        // The attributes are persisted in meta.json.
+        let mut segment_metas =
+            Index::open(index.directory().clone())?.searchable_segment_metas()?;
+        segment_metas.sort_by_key(|segment_meta| segment_meta.attributes()["batch"].clone());
+        assert_eq!(segment_metas[0].attributes(), &attributes("1"));
+        assert_eq!(segment_metas[1].attributes(), &attributes("2"));
+
+        let segment_ids: Vec<crate::SegmentId> = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect();
+        let merged_segment_meta = index_writer.merge(&segment_ids).wait()?;
+        assert_eq!(
+            merged_segment_meta.attributes(),
+            &BTreeMap::from([("tier".to_string(), "hot".to_string())])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fsync_policy_never() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        assert_eq!(index_writer.fsync_policy(), FsyncPolicy::Always);
+        index_writer.set_fsync_policy(FsyncPolicy::Never);
+        assert_eq!(index_writer.fsync_policy(), FsyncPolicy::Never);
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().num_docs(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_hooks() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let pre_commit_events = events.clone();
+        index_writer.add_pre_commit_hook(move |opstamp, payload| {
+            if payload == Some("fail") {
+                return Err(TantivyError::InternalError(
+                    "sidecar flush failed".to_string(),
+                ));
+            }
+            pre_commit_events
+                .lock()
+                .unwrap()
+                .push(format!("pre-commit {opstamp}"));
+            Ok(())
+        });
+        let post_publish_events = events.clone();
+        index_writer.add_post_publish_hook(move |index_meta| {
+            post_publish_events
+                .lock()
+                .unwrap()
+                .push(format!("post-publish {}", index_meta.opstamp));
+        });
+        index_writer.add_document(doc!(text_field => "a"))?;
+        let opstamp = index_writer.commit()?;
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                format!("pre-commit {opstamp}"),
+                format!("post-publish {opstamp}")
+            ]
+        );
+
+        index_writer.add_document(doc!(text_field => "b"))?;
+        let mut prepared_commit = index_writer.prepare_commit()?;
+        prepared_commit.set_payload("fail");
+        assert!(matches!(
+            prepared_commit.commit(),
+            Err(TantivyError::InternalError(_))
+        ));
+        assert_eq!(events.lock().unwrap().len(), 2);
+        assert_eq!(index.load_metas()?.opstamp, opstamp);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_triggers_max_age() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_flush_triggers(
+            1,
+            10_000_000,
+            FlushTriggers::default().with_max_age(Duration::from_millis(50)),
+        )?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"))?;
+        std::thread::sleep(Duration::from_millis(500));
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.commit()?;
+        assert_eq!(index.searchable_segment_metas()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_triggers_max_age_continuous_ingestion() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_flush_triggers(
+            1,
+            10_000_000,
+            FlushTriggers::default().with_max_age(Duration::from_millis(50)),
+        )?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let start = Instant::now();
+        // The documents are queued faster than they are indexed.
+        while start.elapsed() < Duration::from_millis(300) {
+            index_writer.add_document(doc!(text_field => "a"))?;
+        }
+        index_writer.commit()?;
+        assert!(index.searchable_segment_metas()?.len() >= 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_threaded_writer() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index.reader()?;
+        let mut index_writer = index.single_threaded_writer(10_000_000)?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let term_a = Term::from_field_text(text_field, "a");
+        let term_query = TermQuery::new(term_a.clone(), IndexRecordOption::Basic);
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field => "a"))?;
+            index_writer.add_document(doc!(text_field => "b"))?;
+            index_writer.commit()?;
+            reader.reload()?;
+        }
+        assert_eq!(reader.searcher().search(&term_query, &Count)?, 3);
+        assert_eq!(index.searchable_segment_metas()?.len(), 3);
+
+        // Deletes apply to the documents added before them, in the same segment or not.
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.delete_term(term_a);
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().search(&term_query, &Count)?, 1);
+        assert_eq!(reader.searcher().num_docs(), 4);
+
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.rollback()?;
+        index_writer.add_document(doc!(text_field => "a"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().search(&term_query, &Count)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_writer_is_send_sync() {
+        // The writer of `Index::single_threaded_writer` holds its segment writer.
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<IndexWriter>();
+    }
+
+    #[test]
+    fn test_single_threaded_writer_flush_triggers() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.single_threaded_writer(10_000_000)?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.set_flush_triggers(FlushTriggers::default().with_max_docs(10));
+        for _ in 0..25 {
+            index_writer.add_document(doc!(text_field => "a"))?;
+        }
+        index_writer.commit()?;
+        let mut segment_max_docs: Vec<u32> = index
+            .searchable_segment_metas()?
+            .iter()
+            .map(|segment_meta| segment_meta.max_doc())
+            .collect();
+        segment_max_docs.sort_unstable();
+        assert_eq!(segment_max_docs, vec![5, 10, 10]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_operations_group() {
+        // an operations group with 2 items should cause 3 opstamps 0, 1, and 2.
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_writer = index.writer_for_tests().unwrap();
+        let operations = vec![
+            UserOperation::Add(doc!(text_field=>"a")),
+            UserOperation::Add(doc!(text_field=>"b")),
+        ];
+        let batch_opstamp1 = index_writer.run(operations).unwrap();
+        assert_eq!(batch_opstamp1, 2u64);
+    }
+
+    #[test]
+    fn test_add_documents_parallel() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        let documents: Vec<schema::Document> = (0..1_000)
+            .map(|i| doc!(text_field => format!("doc{}", i)))
+            .collect();
+        let batch_opstamp = index_writer.add_documents_parallel(documents)?;
+        assert_eq!(batch_opstamp, 1_000u64);
+        assert_eq!(index_writer.add_documents_parallel(Vec::new())?, 1_001u64);
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().num_docs(), 0);
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.num_docs(), 1_000);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "doc999"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&term_query, &Count)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_column_batch_as_documents() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let category_field = schema_builder.add_facet_field("category", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let batch = ColumnBatch::new(vec![
+            (
+                "doc_id".to_string(),
+                Column::U64(vec![Some(1), Some(2), None]),
+            ),
+            (
+                "name".to_string(),
+                Column::Str(vec![
+                    Some("first".to_string()),
+                    None,
+                    Some("third".to_string()),
+                ]),
+            ),
+            (
+                "path".to_string(),
+                Column::Str(vec![Some("/a/b".to_string()), Some("/a".to_string()), None]),
+            ),
+            ("ignored".to_string(), Column::Bool(vec![None; 3])),
+        ])?;
+        let mapping = [
+            ("doc_id", id_field),
+            ("name", title_field),
+            ("path", category_field),
+        ];
+        index_writer.add_column_batch_as_documents(&batch, &mapping)?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        let count =
+            |term: Term| searcher.search(&TermQuery::new(term, IndexRecordOption::Basic), &Count);
+        assert_eq!(count(Term::from_field_u64(id_field, 2))?, 1);
+        assert_eq!(count(Term::from_field_text(title_field, "third"))?, 1);
+        assert_eq!(
+            count(Term::from_facet(category_field, &Facet::from("/a/b")))?,
+            1
+        );
+
+        assert!(matches!(
+            index_writer.add_column_batch_as_documents(&batch, &[("missing", id_field)]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            index_writer.add_column_batch_as_documents(&batch, &[("name", id_field)]),
+            Err(TantivyError::SchemaError(_))
+        ));
+        assert!(matches!(
+            index_writer.add_column_batch_as_documents(&batch, &[("name", category_field)]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_add_record_batch() -> crate::Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, LargeStringArray, RecordBatch, StringArray, UInt32Array};
+
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let category_field = schema_builder.add_facet_field("category", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let ids = UInt32Array::from(vec![Some(1), Some(2), None]);
+        let names = StringArray::from(vec![Some("first"), None, Some("third")]);
+        let paths = LargeStringArray::from(vec![Some("/a/b"), Some("/a"), None]);
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("doc_id", Arc::new(ids)),
+            ("name", Arc::new(names)),
+            ("path", Arc::new(paths)),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mapping = [
+            ("doc_id", id_field),
+            ("name", title_field),
+            ("path", category_field),
+        ];
+        assert_eq!(index_writer.add_record_batch(&batch, &mapping)?, 3);
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        let count =
+            |term: Term| searcher.search(&TermQuery::new(term, IndexRecordOption::Basic), &Count);
+        assert_eq!(count(Term::from_field_u64(id_field, 2))?, 1);
+        assert_eq!(count(Term::from_field_text(title_field, "third"))?, 1);
+        assert_eq!(
+            count(Term::from_facet(category_field, &Facet::from("/a/b")))?,
+            1
+        );
+
+        assert!(matches!(
+            index_writer.add_record_batch(&batch, &[("missing", id_field)]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            index_writer.add_record_batch(&batch, &[("name", id_field)]),
+            Err(TantivyError::SchemaError(_))
+        ));
+        assert!(matches!(
+            index_writer.add_record_batch(&batch, &[("name", category_field)]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_need_to_rewrite_delete_file_if_no_new_deletes() {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
         let index = Index::create_in_ram(schema_builder.build());
 
         let mut index_writer = index.writer_for_tests().unwrap();
@@ -957,7 +2306,8 @@ mod tests {
         assert_eq!(
             format!("{:?}", index_writer.get_merge_policy()),
             "LogMergePolicy { min_num_segments: 8, max_docs_before_merge: 10000000, \
-             min_layer_size: 10000, level_log_size: 0.75, del_docs_ratio_before_merge: 1.0 }"
+             min_layer_size: 10000, level_log_size: 0.75, del_docs_ratio_before_merge: 1.0, \
+             docstore_stack_on_merge: true }"
         );
         let merge_policy = Box::new(NoMergePolicy::default());
         index_writer.set_merge_policy(merge_policy);
@@ -1283,6 +2633,426 @@ mod tests {
         assert!(commit_again.is_ok());
     }
 
+    #[test]
+    fn test_delete_terms() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in 0..10u64 {
+            index_writer.add_document(doc!(id_field => id))?;
+        }
+        index_writer.commit()?;
+        for id in 10..20u64 {
+            index_writer.add_document(doc!(id_field => id))?;
+        }
+        let opstamp = index_writer.delete_terms(
+            id_field,
+            [3u64, 12, 3, 15, 100]
+                .into_iter()
+                .map(schema::Value::U64)
+                .chain(std::iter::once(schema::Value::JsonObject(
+                    Default::default(),
+                ))),
+        );
+        // The delete only affects the documents added before it,
+        // and only takes a single opstamp.
+        assert_eq!(
+            index_writer.add_document(doc!(id_field => 3u64))?,
+            opstamp + 1
+        );
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 18);
+        let count = |id: u64| {
+            let term_query =
+                TermQuery::new(Term::from_field_u64(id_field, id), IndexRecordOption::Basic);
+            searcher.search(&term_query, &Count).unwrap()
+        };
+        assert_eq!(count(3), 1);
+        assert_eq!(count(12), 0);
+        assert_eq!(count(15), 0);
+        assert_eq!(count(4), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document_if() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field =
+            schema_builder.add_u64_field("id", NumericOptions::default().set_primary_key());
+        let version_field = schema_builder.add_u64_field("version", FAST | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id_field => 1u64, version_field => 1u64))?;
+        index_writer.commit()?;
+
+        let key = Term::from_field_u64(id_field, 1);
+        let version = |version: u64| Term::from_field_u64(version_field, version);
+        let update = |version: u64| doc!(id_field => 1u64, version_field => version);
+        // Two producers read the version 1, and try to update the document.
+        assert!(index_writer
+            .update_document_if(key.clone(), version(1), update(2))?
+            .is_some());
+        assert!(index_writer
+            .update_document_if(key.clone(), version(1), update(3))?
+            .is_none());
+        assert!(index_writer
+            .update_document_if(key.clone(), version(2), update(3))?
+            .is_some());
+        index_writer.commit()?;
+        assert!(index_writer
+            .update_document_if(key.clone(), version(2), update(4))?
+            .is_none());
+        assert!(index_writer
+            .update_document_if(key.clone(), version(3), update(4))?
+            .is_some());
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        let term_query = TermQuery::new(key.clone(), IndexRecordOption::Basic);
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(2))?;
+        let doc = searcher.doc(top_docs[0].1)?;
+        assert_eq!(doc.get_first(version_field).unwrap().as_u64(), Some(4));
+
+        index_writer.delete_all_documents()?;
+        assert!(index_writer
+            .update_document_if(key, version(4), update(5))?
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document_if_sees_plain_operations() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field =
+            schema_builder.add_u64_field("id", NumericOptions::default().set_primary_key());
+        let version_field = schema_builder.add_i64_field("version", FAST);
+        let category_field = schema_builder.add_text_field("category", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let key = |id: u64| Term::from_field_u64(id_field, id);
+        let version = |version: i64| Term::from_field_i64(version_field, version);
+        let document = |id: u64, version: i64, category: &str| {
+            let mut document = doc!(id_field => id, version_field => version);
+            document.add_text(category_field, category);
+            document
+        };
+        index_writer.add_document(document(1, 1, "a"))?;
+        index_writer.add_document(document(2, 1, "b"))?;
+        index_writer.commit()?;
+
+        // Documents added and deleted since the last commit.
+        index_writer.add_document(document(1, 2, "a"))?;
+        assert!(index_writer
+            .update_document_if(key(1), version(1), document(1, 3, "a"))?
+            .is_none());
+        index_writer.delete_term(key(1));
+        assert!(index_writer
+            .update_document_if(key(1), version(2), document(1, 3, "a"))?
+            .is_none());
+        index_writer.run([UserOperation::Add(document(1, 4, "a"))])?;
+        assert!(index_writer
+            .update_document_if(key(1), version(4), document(1, 5, "a"))?
+            .is_some());
+
+        // Documents deleted by a term of another field.
+        index_writer.delete_term(Term::from_field_text(category_field, "b"));
+        assert!(index_writer
+            .update_document_if(key(2), version(1), document(2, 2, "b"))?
+            .is_none());
+        assert!(index_writer
+            .update_document_if(key(1), version(5), document(1, 6, "a"))?
+            .is_none());
+        index_writer.commit()?;
+        assert!(index_writer
+            .update_document_if(key(1), version(5), document(1, 6, "a"))?
+            .is_some());
+
+        // The changes of a commit are seen until the commit is published.
+        index_writer.add_documents_parallel(vec![document(3, 1, "c")])?;
+        let commit_future = index_writer.prepare_commit()?.commit_future();
+        assert!(index_writer
+            .update_document_if(key(3), version(1), document(3, 2, "c"))?
+            .is_some());
+        commit_future.wait()?;
+        assert!(index_writer
+            .update_document_if(key(3), version(1), document(3, 3, "c"))?
+            .is_none());
+        assert!(index_writer
+            .update_document_if(key(3), version(2), document(3, 3, "c"))?
+            .is_some());
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document_if_invalid_fields() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field =
+            schema_builder.add_u64_field("id", NumericOptions::default().set_primary_key());
+        let other_id_field = schema_builder.add_u64_field("other_id", INDEXED);
+        let version_field = schema_builder.add_u64_field("version", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_writer = index.writer_for_tests()?;
+        let document = doc!(id_field => 1u64, version_field => 1u64);
+        assert!(matches!(
+            index_writer.update_document_if(
+                Term::from_field_u64(other_id_field, 1),
+                Term::from_field_u64(id_field, 1),
+                document.clone()
+            ),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            index_writer.update_document_if(
+                Term::from_field_u64(id_field, 1),
+                Term::from_field_u64(version_field, 1),
+                document
+            ),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_document_default_values_and_constraints() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let count_field = schema_builder.add_u64_field("count", INDEXED | STORED);
+        schema_builder.set_default_value(count_field, 1u64);
+        schema_builder.set_constraints(count_field, FieldConstraints::default().set_max(10.0));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"))?;
+        assert!(matches!(
+            index_writer.add_document(doc!(text_field => "b", count_field => 11u64)),
+            Err(TantivyError::InvalidDocument(_))
+        ));
+        // A group containing an invalid document is rejected as a whole.
+        assert!(matches!(
+            index_writer.run([
+                UserOperation::Add(doc!(text_field => "c")),
+                UserOperation::Add(doc!(count_field => 12u64)),
+            ]),
+            Err(TantivyError::InvalidDocument(_))
+        ));
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        let term_query = TermQuery::new(
+            Term::from_field_u64(count_field, 1),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&term_query, &Count)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_document_dynamic_fields() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.add_dynamic_template("*_i", FieldType::I64(NumericOptions::from(INDEXED)));
+        schema_builder.add_dynamic_template("*_txt", FieldType::Str(TEXT | STORED));
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000)?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"))?;
+        let doc = index_writer.parse_document(r#"{"text": "b", "count_i": 3, "other": 1}"#)?;
+        index_writer.add_document(doc)?;
+        index_writer.commit()?;
+        let doc = index_writer.parse_document(r#"{"title_txt": "hello", "count_i": 4}"#)?;
+        index_writer.add_document(doc)?;
+        index_writer.commit()?;
+
+        let schema = index.schema();
+        let count_field = schema.get_field("count_i").unwrap();
+        let title_field = schema.get_field("title_txt").unwrap();
+        assert!(schema.get_field("other").is_none());
+        assert_eq!(schema.num_fields(), 3);
+
+        // The dynamic fields are persisted with the commit.
+        let reopened_index = Index::open(directory)?;
+        assert_eq!(reopened_index.schema(), schema);
+        let searcher = reopened_index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let count_query = |count: i64| {
+            TermQuery::new(
+                Term::from_field_i64(count_field, count),
+                IndexRecordOption::Basic,
+            )
+        };
+        assert_eq!(searcher.search(&count_query(3), &Count)?, 1);
+        assert_eq!(searcher.search(&count_query(4), &Count)?, 1);
+        let title_query = TermQuery::new(
+            Term::from_field_text(title_field, "hello"),
+            IndexRecordOption::WithFreqs,
+        );
+        assert_eq!(searcher.search(&title_query, &Count)?, 1);
+
+        // Segments created before the dynamic fields can be merged with more recent ones.
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.search(&count_query(3), &Count)?, 1);
+        assert_eq!(searcher.search(&title_query, &Count)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_json_documents_rejects_unknown_fields() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.add_dynamic_template("*_i", FieldType::I64(NumericOptions::from(INDEXED)));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let batch_result = index_writer.add_json_documents([
+            r#"{"text": "a"}"#,
+            r#"{"text": "b", "unknown": "c", "rejected_i": 2}"#,
+            r#"{"text": "d", "count_i": 1}"#,
+            r#"{"text": "#,
+        ])?;
+        assert_eq!(batch_result.num_added, 2);
+        assert_eq!(batch_result.failures.len(), 2);
+        assert_eq!(
+            batch_result.failures[0],
+            DocumentFailure {
+                position: 1,
+                error: DocumentError::Parsing(DocParsingError::UnknownField("unknown".to_string())),
+            }
+        );
+        assert!(matches!(
+            batch_result.failures[1],
+            DocumentFailure {
+                position: 3,
+                error: DocumentError::Parsing(DocParsingError::InvalidJson(_)),
+            }
+        ));
+        // The rejected documents do not add fields to the schema.
+        assert!(index.schema().get_field("count_i").is_some());
+        assert!(index.schema().get_field("rejected_i").is_none());
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "b"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&term_query, &Count)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_documents_batch_result() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let count_field = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.set_constraints(count_field, FieldConstraints::default().set_max(10.0));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let batch_result = index_writer.add_documents(vec![
+            doc!(count_field => 1u64),
+            doc!(count_field => 11u64),
+            doc!(count_field => 2u64),
+        ])?;
+        assert!(!batch_result.is_complete());
+        assert_eq!(batch_result.num_added, 2);
+        assert_eq!(batch_result.failures.len(), 1);
+        assert_eq!(batch_result.failures[0].position, 1);
+        assert!(matches!(
+            batch_result.failures[0].error,
+            DocumentError::Validation(ValidationError::OutOfRange { .. })
+        ));
+        let opstamp = index_writer.add_document(doc!(count_field => 3u64))?;
+        assert_eq!(opstamp, batch_result.opstamp + 1);
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 3);
+
+        let batch_result = index_writer.add_documents(Vec::new())?;
+        assert!(batch_result.is_complete());
+        assert_eq!(batch_result.num_added, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deprecate_field() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let legacy_field = schema_builder.add_text_field("legacy", TEXT | STORED);
+        let legacy_num_field = schema_builder.add_u64_field("legacy_num", FAST | STORED);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for i in 0..4u64 {
+            index_writer.add_document(doc!(
+                title_field => "hello",
+                legacy_field => "old",
+                legacy_num_field => i,
+            ))?;
+            index_writer.commit()?;
+        }
+        index_writer.deprecate_field(legacy_field)?;
+        index_writer.deprecate_field(legacy_num_field)?;
+        index_writer.add_document(doc!(title_field => "hello", legacy_field => "old"))?;
+        index_writer.commit()?;
+
+        let reopened_schema = Index::open(directory)?.schema();
+        assert!(reopened_schema
+            .get_field_entry(legacy_field)
+            .is_deprecated());
+        assert!(reopened_schema
+            .get_field_entry(legacy_num_field)
+            .is_deprecated());
+        assert!(!reopened_schema.get_field_entry(title_field).is_deprecated());
+
+        let legacy_query = TermQuery::new(
+            Term::from_field_text(legacy_field, "old"),
+            IndexRecordOption::Basic,
+        );
+        let reader = index.reader()?;
+        // The values of the documents added since the deprecation are ignored.
+        assert_eq!(reader.searcher().search(&legacy_query, &Count)?, 4);
+
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 5);
+        assert_eq!(searcher.search(&legacy_query, &Count)?, 0);
+        let segment_reader = &searcher.segment_readers()[0];
+        assert!(segment_reader.fast_fields().u64(legacy_num_field).is_err());
+        let doc = searcher.doc(DocAddress::new(0, 0))?;
+        assert_eq!(
+            doc.get_first(title_field).and_then(|value| value.as_text()),
+            Some("hello")
+        );
+        assert!(doc.get_first(legacy_field).is_none());
+        assert!(doc.get_first(legacy_num_field).is_none());
+        let title_query = TermQuery::new(
+            Term::from_field_text(title_field, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&title_query, &Count)?, 5);
+        Ok(())
+    }
+
     #[test]
     fn test_delete_with_sort_by_field() -> crate::Result<()> {
         let mut schema_builder = schema::Schema::builder();
@@ -0,0 +1,61 @@
+use std::ops::AddAssign;
+
+use crate::indexer::SegmentEntry;
+
+/// Numbers of documents of a set of segments.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DocCounts {
+    /// Number of segments.
+    pub num_segments: usize,
+    /// Number of alive documents.
+    pub num_docs: u64,
+    /// Number of deleted documents, not reclaimed by a merge yet.
+    pub num_deleted_docs: u64,
+}
+
+impl DocCounts {
+    pub(crate) fn from_segment_entries(segment_entries: &[SegmentEntry]) -> DocCounts {
+        let mut doc_counts = DocCounts::default();
+        for segment_entry in segment_entries {
+            let max_doc = segment_entry.meta().max_doc() as u64;
+            // The alive bitset holds the deletes not written in the segment meta yet.
+            let num_docs = match segment_entry.alive_bitset() {
+                Some(alive_bitset) => alive_bitset.len() as u64,
+                None => segment_entry.meta().num_docs() as u64,
+            };
+            doc_counts += DocCounts {
+                num_segments: 1,
+                num_docs,
+                num_deleted_docs: max_doc - num_docs,
+            };
+        }
+        doc_counts
+    }
+}
+
+impl AddAssign for DocCounts {
+    fn add_assign(&mut self, other: DocCounts) {
+        self.num_segments += other.num_segments;
+        self.num_docs += other.num_docs;
+        self.num_deleted_docs += other.num_deleted_docs;
+    }
+}
+
+/// Numbers of documents of the segments of an `IndexWriter`, returned by
+/// [`IndexWriter::live_doc_stats`](crate::IndexWriter::live_doc_stats).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LiveDocStats {
+    /// Documents of the segments of the last commit.
+    pub committed: DocCounts,
+    /// Documents of the segments flushed since the last commit.
+    pub uncommitted: DocCounts,
+}
+
+impl LiveDocStats {
+    /// Returns the documents of all of the segments.
+    pub fn total(&self) -> DocCounts {
+        let mut total = self.committed;
+        total += self.uncommitted;
+        total
+    }
+}
@@ -0,0 +1,370 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::directory::error::{LockError, OpenReadError};
+use crate::directory::{Directory, DirectoryLock, INDEX_WRITER_LOCK};
+use crate::TantivyError;
+
+/// The file the holder of the writer lock rewrites periodically, to show it is alive.
+///
+/// Like the lock files, it starts with a "." so that it is not managed.
+static HEARTBEAT_FILEPATH: Lazy<&'static Path> =
+    Lazy::new(|| Path::new(".tantivy-writer.heartbeat"));
+
+/// Longest wait between two attempts to acquire the lock.
+const MAX_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shortest wait between two attempts to acquire the lock, or between two heartbeats.
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Acquires the writer lock, taking it over from its holder if the heartbeat of the holder
+/// did not change for `takeover_timeout`, and sends heartbeats while the returned lock is
+/// held.
+///
+/// Fails as soon as the heartbeat of the holder changes. A lock held by a writer that does not
+/// send heartbeats is never taken over.
+///
+/// The returned fence tells whether the lock was taken over in turn by another writer.
+pub(crate) fn acquire_writer_lock_with_takeover(
+    directory: &dyn Directory,
+    takeover_timeout: Duration,
+) -> crate::Result<(DirectoryLock, WriterFence)> {
+    if takeover_timeout.is_zero() {
+        return Err(TantivyError::InvalidArgument(
+            "The lock takeover timeout must be positive.".to_string(),
+        ));
+    }
+    let retry_interval = (takeover_timeout / 10).clamp(MIN_INTERVAL, MAX_RETRY_INTERVAL);
+    let heartbeat_interval = (takeover_timeout / 4).max(MIN_INTERVAL);
+    let mut last_heartbeat_opt: Option<Vec<u8>> = None;
+    let start = Instant::now();
+    let mut took_over = false;
+    loop {
+        match directory.acquire_lock(&INDEX_WRITER_LOCK) {
+            Ok(directory_lock) => {
+                let writer_fence = WriterFence::new(directory);
+                let heartbeat = Heartbeat::start(writer_fence.clone(), heartbeat_interval)?;
+                let heartbeat_lock = HeartbeatLock {
+                    heartbeat_opt: Some(heartbeat),
+                    directory_lock_opt: Some(directory_lock),
+                };
+                return Ok((DirectoryLock::from(Box::new(heartbeat_lock)), writer_fence));
+            }
+            Err(LockError::LockBusy) if !took_over => {}
+            Err(lock_error) => {
+                return Err(TantivyError::LockFailure(
+                    lock_error,
+                    Some("Failed to acquire the index lock after taking it over.".to_string()),
+                ));
+            }
+        }
+        let heartbeat = read_heartbeat(directory)?;
+        match &last_heartbeat_opt {
+            None => {
+                last_heartbeat_opt = Some(heartbeat);
+            }
+            Some(last_heartbeat) if *last_heartbeat != heartbeat => {
+                return Err(live_writer_error());
+            }
+            Some(_) => {
+                if start.elapsed() >= takeover_timeout {
+                    warn!(
+                        "The writer holding the index lock sent no heartbeat for {:?}, taking the \
+                         lock over.",
+                        takeover_timeout
+                    );
+                    // The holder, or another writer taking the lock over, may have written a
+                    // heartbeat since the last check, so it is read again right before the
+                    // lock file is deleted.
+                    if read_heartbeat(directory)? != heartbeat {
+                        return Err(live_writer_error());
+                    }
+                    // The lock file may have been deleted by its holder in the meantime.
+                    let _ = directory.delete(&INDEX_WRITER_LOCK.filepath);
+                    took_over = true;
+                    continue;
+                }
+            }
+        }
+        thread::sleep(retry_interval);
+    }
+}
+
+fn read_heartbeat(directory: &dyn Directory) -> crate::Result<Vec<u8>> {
+    directory.atomic_read(&HEARTBEAT_FILEPATH).map_err(|_| {
+        TantivyError::LockFailure(
+            LockError::LockBusy,
+            Some(
+                "The index lock is held by a writer that does not send heartbeats, so it cannot \
+                 be taken over."
+                    .to_string(),
+            ),
+        )
+    })
+}
+
+fn live_writer_error() -> TantivyError {
+    TantivyError::LockFailure(
+        LockError::LockBusy,
+        Some("The index lock is held by a live writer.".to_string()),
+    )
+}
+
+/// Tells whether a writer still owns the writer lock it acquired with
+/// [`acquire_writer_lock_with_takeover`], i.e. whether the heartbeat file still holds its own
+/// heartbeats.
+///
+/// The default fence, for the writers holding a plain writer lock, never fails.
+#[derive(Clone, Default)]
+pub(crate) struct WriterFence(Option<Arc<InnerWriterFence>>);
+
+struct InnerWriterFence {
+    directory: Box<dyn Directory>,
+    owner_id: Uuid,
+    superseded: AtomicBool,
+}
+
+impl WriterFence {
+    fn new(directory: &dyn Directory) -> WriterFence {
+        WriterFence(Some(Arc::new(InnerWriterFence {
+            directory: directory.box_clone(),
+            owner_id: Uuid::new_v4(),
+            superseded: AtomicBool::new(false),
+        })))
+    }
+
+    /// Fails if the lock was taken over by another writer.
+    ///
+    /// The writer is expected to check its fence right before publishing anything to the
+    /// index, i.e. before writing the metas and before collecting the garbage files.
+    pub fn check(&self) -> crate::Result<()> {
+        let inner = match &self.0 {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+        if inner.superseded.load(Ordering::SeqCst) {
+            return Err(superseded_writer_error());
+        }
+        let heartbeat_prefix = format!("{}:", inner.owner_id);
+        let is_owner = match inner.directory.atomic_read(&HEARTBEAT_FILEPATH) {
+            Ok(heartbeat) => heartbeat.starts_with(heartbeat_prefix.as_bytes()),
+            Err(OpenReadError::FileDoesNotExist(_)) => false,
+            Err(err) => return Err(err.into()),
+        };
+        if !is_owner {
+            inner.superseded.store(true, Ordering::SeqCst);
+            return Err(superseded_writer_error());
+        }
+        Ok(())
+    }
+
+    /// Returns true if the lock was taken over by another writer. A heartbeat file which
+    /// cannot be read does not tell the lock was taken over.
+    fn is_taken_over(&self) -> bool {
+        let _ = self.check();
+        self.0
+            .as_ref()
+            .map(|inner| inner.superseded.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+}
+
+fn superseded_writer_error() -> TantivyError {
+    TantivyError::LockFailure(
+        LockError::LockBusy,
+        Some("The index lock was taken over by another writer.".to_string()),
+    )
+}
+
+/// Writer lock along with the heartbeats sent while it is held.
+struct HeartbeatLock {
+    heartbeat_opt: Option<Heartbeat>,
+    directory_lock_opt: Option<DirectoryLock>,
+}
+
+impl Drop for HeartbeatLock {
+    fn drop(&mut self) {
+        // The heartbeats stop before the lock is released.
+        let taken_over = self
+            .heartbeat_opt
+            .take()
+            .map(Heartbeat::stop)
+            .unwrap_or(false);
+        if taken_over {
+            // The lock file now belongs to the writer which took the lock over.
+            std::mem::forget(self.directory_lock_opt.take());
+        }
+    }
+}
+
+/// Thread rewriting the heartbeat file periodically, until stopped or until the lock is
+/// taken over by another writer.
+struct Heartbeat {
+    directory: Box<dyn Directory>,
+    writer_fence: WriterFence,
+    stop_sender: Option<Sender<()>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    fn start(writer_fence: WriterFence, interval: Duration) -> crate::Result<Heartbeat> {
+        let inner = writer_fence
+            .0
+            .clone()
+            .expect("A heartbeat requires a writer fence. This is a bug, please report.");
+        let mut write_heartbeat = {
+            let inner = inner.clone();
+            let mut num_heartbeats = 0u64;
+            move || {
+                num_heartbeats += 1;
+                let heartbeat = format!("{}:{num_heartbeats}", inner.owner_id);
+                inner
+                    .directory
+                    .atomic_write(&HEARTBEAT_FILEPATH, heartbeat.as_bytes())
+            }
+        };
+        // The first heartbeat tells the writers waiting for the lock that it changed hands.
+        write_heartbeat()?;
+        let (stop_sender, stop_receiver) = bounded::<()>(0);
+        let thread_writer_fence = writer_fence.clone();
+        let join_handle = thread::Builder::new()
+            .name("writer_heartbeat".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(interval) {
+                    // Writing a heartbeat over the one of the writer which took the lock over
+                    // would make both writers believe they own the lock.
+                    if thread_writer_fence.is_taken_over() {
+                        error!("The index lock was taken over by another writer.");
+                        return;
+                    }
+                    if let Err(io_error) = write_heartbeat() {
+                        error!("Failed to write the writer heartbeat. {:?}", io_error);
+                    }
+                }
+            })?;
+        Ok(Heartbeat {
+            directory: inner.directory.box_clone(),
+            writer_fence,
+            stop_sender: Some(stop_sender),
+            join_handle: Some(join_handle),
+        })
+    }
+
+    /// Stops the heartbeats, and returns true if the lock was taken over by another writer.
+    fn stop(mut self) -> bool {
+        // Disconnecting the channel stops the thread.
+        self.stop_sender.take();
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+        let taken_over = self.writer_fence.is_taken_over();
+        if !taken_over {
+            if let Err(err) = self.directory.delete(&HEARTBEAT_FILEPATH) {
+                error!("Failed to remove the writer heartbeat. {:?}", err);
+            }
+        }
+        taken_over
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use super::HEARTBEAT_FILEPATH;
+    use crate::directory::{Directory, RamDirectory, INDEX_WRITER_LOCK};
+    use crate::schema::{Schema, STRING};
+    use crate::{Index, IndexWriter, TantivyError};
+
+    #[test]
+    fn test_lock_takeover() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", STRING);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            Default::default(),
+        )?;
+        let timeout = Duration::from_millis(200);
+
+        // The lock of a live writer is not taken over.
+        let mut index_writer =
+            IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, timeout)?;
+        let err = IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, timeout);
+        assert!(matches!(err, Err(TantivyError::LockFailure(..))));
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.commit()?;
+        drop(index_writer);
+        assert!(!directory.exists(&INDEX_WRITER_LOCK.filepath)?);
+        assert!(!directory.exists(&HEARTBEAT_FILEPATH)?);
+
+        // A writer process crashed, leaving its lock and its last heartbeat.
+        directory.open_write(&INDEX_WRITER_LOCK.filepath)?.flush()?;
+        directory.atomic_write(&HEARTBEAT_FILEPATH, b"crashed:1")?;
+        let mut index_writer =
+            IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, timeout)?;
+        index_writer.add_document(doc!(text => "b"))?;
+        index_writer.commit()?;
+        drop(index_writer);
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+
+        // Without heartbeats, the lock is never taken over.
+        let _index_writer = index.writer_for_tests()?;
+        let err = IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, timeout);
+        assert!(matches!(err, Err(TantivyError::LockFailure(..))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_taken_over_fails_writer() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", STRING);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            Default::default(),
+        )?;
+        let timeout = Duration::from_millis(40);
+        let mut index_writer =
+            IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, timeout)?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.commit()?;
+
+        // Another writer took the lock over, e.g. while this one was paused.
+        directory.atomic_write(&HEARTBEAT_FILEPATH, b"other:1")?;
+        index_writer.add_document(doc!(text => "b"))?;
+        assert!(matches!(
+            index_writer.commit(),
+            Err(TantivyError::LockFailure(..))
+        ));
+        assert!(index_writer.garbage_collect_files().wait().is_err());
+        // The heartbeats stopped, and dropping the writer leaves the lock to its new owner.
+        std::thread::sleep(timeout);
+        assert_eq!(directory.atomic_read(&HEARTBEAT_FILEPATH)?, b"other:1");
+        drop(index_writer);
+        assert!(directory.exists(&INDEX_WRITER_LOCK.filepath)?);
+        assert_eq!(directory.atomic_read(&HEARTBEAT_FILEPATH)?, b"other:1");
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_takeover_zero_timeout() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let err = IndexWriter::open_with_lock_takeover(&index, 1, 10_000_000, Duration::ZERO);
+        assert!(matches!(err, Err(TantivyError::InvalidArgument(_))));
+        Ok(())
+    }
+}
@@ -23,6 +23,7 @@ pub struct LogMergePolicy {
     min_layer_size: u32,
     level_log_size: f64,
     del_docs_ratio_before_merge: f32,
+    docstore_stack_on_merge: bool,
 }
 
 impl LogMergePolicy {
@@ -76,6 +77,12 @@ impl LogMergePolicy {
         self.del_docs_ratio_before_merge = del_docs_ratio_before_merge;
     }
 
+    /// Set whether the merges copy the compressed doc store blocks of the segments as they
+    /// are. See [`MergePolicy::docstore_stack_on_merge`].
+    pub fn set_docstore_stack_on_merge(&mut self, docstore_stack_on_merge: bool) {
+        self.docstore_stack_on_merge = docstore_stack_on_merge;
+    }
+
     fn has_segment_above_deletes_threshold(&self, level: &[&SegmentMeta]) -> bool {
         level
             .iter()
@@ -125,6 +132,10 @@ impl MergePolicy for LogMergePolicy {
             .map(|segments| MergeCandidate(segments.iter().map(|&seg| seg.id()).collect()))
             .collect()
     }
+
+    fn docstore_stack_on_merge(&self) -> bool {
+        self.docstore_stack_on_merge
+    }
 }
 
 impl Default for LogMergePolicy {
@@ -135,6 +146,7 @@ impl Default for LogMergePolicy {
             min_layer_size: DEFAULT_MIN_LAYER_SIZE,
             level_log_size: DEFAULT_LEVEL_LOG_SIZE,
             del_docs_ratio_before_merge: DEFAULT_DEL_DOCS_RATIO_BEFORE_MERGE,
+            docstore_stack_on_merge: true,
         }
     }
 }
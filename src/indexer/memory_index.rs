@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use census::Inventory;
+use common::{AntiCallToken, TerminatingWrite};
+
+use super::index_writer::MEMORY_ARENA_NUM_BYTES_MIN;
+use super::operation::AddOperation;
+use super::{SegmentSerializer, SegmentWriter};
+use crate::collector::{Collector, Count};
+use crate::core::{SearcherGeneration, SegmentComponent};
+use crate::directory::error::OpenReadError;
+use crate::directory::{FileSlice, WritePtr};
+use crate::query::Query;
+use crate::reader::GenerationMemory;
+use crate::schema::{Document, Schema};
+use crate::store::{Compressor, DOCSTORE_CACHE_CAPACITY};
+use crate::tokenizer::TokenizerManager;
+use crate::{DocAddress, Index, IndexSettings, Searcher, SegmentId, SegmentReader};
+
+/// A single segment index of a few documents, built in memory and searched right away.
+///
+/// There is no `Directory`, no `IndexWriter`, no commit and no `IndexReader`: the documents
+/// are indexed in the calling thread into a single segment, whose components are kept in
+/// memory and opened as is. This makes it cheap to check which queries match a document,
+/// e.g. to verify highlights or to percolate it, or to run a query against a handful of
+/// documents in a unit test.
+///
+/// The searcher of a `MemoryIndex` has no [`Index`]: queries and collectors calling
+/// [`Searcher::index`] cannot run against it.
+///
+/// ```rust
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, MemoryIndex};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+///
+/// let memory_index = MemoryIndex::for_index(&index, vec![doc!(title => "The Diary of Muadib")])?;
+/// assert!(memory_index.matches(query_parser.parse_query("diary")?.as_ref())?);
+/// assert!(!memory_index.matches(query_parser.parse_query("girl")?.as_ref())?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MemoryIndex {
+    searcher: Searcher,
+}
+
+impl MemoryIndex {
+    /// Indexes the documents with the default tokenizers.
+    pub fn create<I>(schema: Schema, documents: I) -> crate::Result<MemoryIndex>
+    where I: IntoIterator<Item = Document> {
+        MemoryIndex::with_tokenizers(schema, TokenizerManager::default(), documents)
+    }
+
+    /// Indexes the documents with the schema and the tokenizers of `index`.
+    ///
+    /// `index` itself is left untouched.
+    pub fn for_index<I>(index: &Index, documents: I) -> crate::Result<MemoryIndex>
+    where I: IntoIterator<Item = Document> {
+        MemoryIndex::with_tokenizers(index.schema(), index.tokenizers().clone(), documents)
+    }
+
+    /// Indexes the documents, tokenizing their text with `tokenizers`.
+    pub fn with_tokenizers<I>(
+        schema: Schema,
+        tokenizers: TokenizerManager,
+        documents: I,
+    ) -> crate::Result<MemoryIndex>
+    where
+        I: IntoIterator<Item = Document>,
+    {
+        schema.check_facet_count_fields()?;
+        let memory_segment = MemorySegment::default();
+        // The doc store of a handful of documents is not worth compressing.
+        let settings = IndexSettings {
+            docstore_compression: Compressor::None,
+            ..IndexSettings::default()
+        };
+        let segment_serializer =
+            SegmentSerializer::in_memory(memory_segment.clone(), schema.clone(), settings)?;
+        let mut segment_writer = SegmentWriter::in_memory(
+            MEMORY_ARENA_NUM_BYTES_MIN,
+            segment_serializer,
+            schema.clone(),
+            &tokenizers,
+        )?;
+        for (opstamp, mut document) in documents.into_iter().enumerate() {
+            schema.prepare_document(&mut document)?;
+            segment_writer.add_document(AddOperation {
+                opstamp: opstamp as u64,
+                document,
+            })?;
+        }
+        let max_doc = segment_writer.max_doc();
+        segment_writer.finalize()?;
+        let segment_readers = if max_doc > 0 {
+            vec![SegmentReader::open_files(
+                |component| memory_segment.open_read(component),
+                schema.clone(),
+                SegmentId::generate_random(),
+                max_doc,
+            )?]
+        } else {
+            Vec::new()
+        };
+        let searcher_generation =
+            Inventory::default().track(SearcherGeneration::from_segment_readers(
+                &segment_readers,
+                0,
+                0,
+                GenerationMemory::default(),
+            ));
+        let searcher = Searcher::without_index(
+            schema,
+            tokenizers,
+            segment_readers,
+            searcher_generation,
+            DOCSTORE_CACHE_CAPACITY,
+        )?;
+        Ok(MemoryIndex { searcher })
+    }
+
+    /// Returns the searcher of the documents.
+    ///
+    /// The documents are given doc ids following their order, in the segment of ordinal 0.
+    pub(crate) fn searcher(&self) -> &Searcher {
+        &self.searcher
+    }
+
+    /// Returns the number of documents.
+    pub fn num_docs(&self) -> u64 {
+        self.searcher.num_docs()
+    }
+
+    /// Fetches a document, see [`Searcher::doc`].
+    ///
+    /// The documents are given doc ids following their order, in the segment of ordinal 0.
+    pub fn doc(&self, doc_address: DocAddress) -> crate::Result<Document> {
+        self.searcher.doc(doc_address)
+    }
+
+    /// Runs the search, see [`Searcher::search`].
+    pub fn search<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit> {
+        self.searcher.search(query, collector)
+    }
+
+    /// Returns true if the query matches at least one of the documents.
+    pub fn matches(&self, query: &dyn Query) -> crate::Result<bool> {
+        Ok(self.search(query, &Count)? > 0)
+    }
+}
+
+/// The components of the segment of a [`MemoryIndex`], kept in memory.
+#[derive(Clone, Default)]
+pub(crate) struct MemorySegment {
+    components: Arc<Mutex<HashMap<SegmentComponent, FileSlice>>>,
+}
+
+impl MemorySegment {
+    pub(crate) fn open_write(&self, component: SegmentComponent) -> WritePtr {
+        BufWriter::new(Box::new(MemoryComponentWriter {
+            component,
+            data: Vec::new(),
+            components: self.components.clone(),
+        }))
+    }
+
+    pub(crate) fn open_read(
+        &self,
+        component: SegmentComponent,
+    ) -> Result<FileSlice, OpenReadError> {
+        self.components
+            .lock()
+            .unwrap()
+            .get(&component)
+            .cloned()
+            .ok_or_else(|| OpenReadError::FileDoesNotExist(PathBuf::from(component.name())))
+    }
+}
+
+/// Writes a component of a [`MemorySegment`], which can be read once the writer is terminated.
+struct MemoryComponentWriter {
+    component: SegmentComponent,
+    data: Vec<u8>,
+    components: Arc<Mutex<HashMap<SegmentComponent, FileSlice>>>,
+}
+
+impl Write for MemoryComponentWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TerminatingWrite for MemoryComponentWriter {
+    fn terminate_ref(&mut self, _: AntiCallToken) -> io::Result<()> {
+        let data = FileSlice::from(mem::take(&mut self.data));
+        self.components.lock().unwrap().insert(self.component, data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryIndex;
+    use crate::collector::TopDocs;
+    use crate::query::{AllQuery, QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, STORED, STRING, TEXT};
+    use crate::{DocAddress, Term};
+
+    #[test]
+    fn test_memory_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let id = schema_builder.add_text_field("id", STRING);
+        let schema = schema_builder.build();
+        let memory_index = MemoryIndex::create(
+            schema.clone(),
+            vec![
+                doc!(id => "a", title => "The Diary of a Young Girl"),
+                doc!(id => "b", title => "The Diary of Muadib"),
+            ],
+        )?;
+        assert_eq!(memory_index.num_docs(), 2);
+        assert!(memory_index.searcher().index().is_none());
+        let id_query = TermQuery::new(Term::from_field_text(id, "b"), IndexRecordOption::Basic);
+        let top_docs = memory_index.search(&id_query, &TopDocs::with_limit(2))?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(top_docs[0].1, DocAddress::new(0, 1));
+        let doc = memory_index.doc(top_docs[0].1)?;
+        assert_eq!(
+            doc.get_first(title).unwrap().as_text(),
+            Some("The Diary of Muadib")
+        );
+
+        let query_parser = QueryParser::new(schema.clone(), vec![title], Default::default());
+        assert!(memory_index.matches(query_parser.parse_query("\"young girl\"")?.as_ref())?);
+        assert!(!memory_index.matches(query_parser.parse_query("\"girl young\"")?.as_ref())?);
+
+        let empty_memory_index = MemoryIndex::create(schema, Vec::new())?;
+        assert!(!empty_memory_index.matches(&AllQuery)?);
+        Ok(())
+    }
+}
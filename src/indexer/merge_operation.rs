@@ -1,7 +1,14 @@
 use std::collections::HashSet;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::{Inventory, Opstamp, SegmentId, TrackedObject};
+use crate::{Inventory, Opstamp, SegmentId, SegmentMeta, TantivyError, TrackedObject};
+
+/// Number of passes over the documents done by a merge: fieldnorms, postings,
+/// fast fields and stored fields.
+pub(crate) const NUM_MERGE_PASSES: u64 = 4;
 
 #[derive(Default)]
 pub(crate) struct MergeOperationInventory(Inventory<InnerMergeOperation>);
@@ -24,6 +31,96 @@ impl MergeOperationInventory {
         }
         segment_in_merge
     }
+
+    /// Requests the cancellation of all of the tracked merge operations.
+    pub fn cancel_all(&self) {
+        for merge_op in self.list() {
+            merge_op.state.cancel();
+        }
+    }
+
+    /// Returns a handle over each of the tracked merge operations.
+    pub fn running_merges(&self) -> Vec<RunningMerge> {
+        self.list()
+            .iter()
+            .map(|merge_op| RunningMerge {
+                target_opstamp: merge_op.target_opstamp,
+                segment_ids: merge_op.segment_ids.clone(),
+                state: merge_op.state.clone(),
+            })
+            .collect()
+    }
+}
+
+/// State of a merge, shared by its `MergeOperation`, the `IndexMerger` writing it and
+/// the `RunningMerge` handles given to the user.
+#[derive(Default)]
+pub(crate) struct MergeState {
+    cancelled: AtomicBool,
+    start: Mutex<Option<Instant>>,
+    num_docs: AtomicU64,
+    num_docs_processed: AtomicU64,
+    outcome: Mutex<Option<crate::Result<SegmentMeta>>>,
+    outcome_cond: Condvar,
+    // Set once a `MergeOutcomeGuard` is responsible for recording the outcome.
+    guarded: AtomicBool,
+}
+
+impl MergeState {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Marks the merge as started. `num_docs` is the number of documents of the merged
+    /// segment.
+    pub fn start(&self, num_docs: u32) {
+        self.num_docs.store(num_docs as u64, Ordering::Relaxed);
+        *self.start.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn set_num_docs_processed(&self, num_docs_processed: u64) {
+        self.num_docs_processed
+            .store(num_docs_processed, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of the merge and wakes up the threads waiting for it.
+    pub fn finish(&self, outcome: crate::Result<SegmentMeta>) {
+        *self.outcome.lock().unwrap() = Some(outcome);
+        self.outcome_cond.notify_all();
+    }
+
+    // Fails the merge, unless it already has an outcome.
+    fn fail_if_unfinished(&self, reason: &str) {
+        let mut outcome = self.outcome.lock().unwrap();
+        if outcome.is_none() {
+            *outcome = Some(Err(TantivyError::ErrorInThread(reason.to_string())));
+            self.outcome_cond.notify_all();
+        }
+    }
+}
+
+/// Fails the merge when dropped before the merge got an outcome, e.g. because the merging
+/// thread panicked, so that the threads waiting for it do not wait forever.
+///
+/// It is moved to the merging thread, which records the outcome with `MergeState::finish`.
+pub(crate) struct MergeOutcomeGuard(Arc<MergeState>);
+
+impl MergeOutcomeGuard {
+    pub fn new(state: Arc<MergeState>) -> MergeOutcomeGuard {
+        state.guarded.store(true, Ordering::Release);
+        MergeOutcomeGuard(state)
+    }
+}
+
+impl Drop for MergeOutcomeGuard {
+    fn drop(&mut self) {
+        self.0
+            .fail_if_unfinished("The merge ended without an outcome, its thread panicked.");
+    }
 }
 
 /// A `MergeOperation` has two roles.
@@ -46,6 +143,18 @@ pub struct MergeOperation {
 pub(crate) struct InnerMergeOperation {
     target_opstamp: Opstamp,
     segment_ids: Vec<SegmentId>,
+    state: Arc<MergeState>,
+}
+
+impl Drop for InnerMergeOperation {
+    fn drop(&mut self) {
+        // Once the merge started, its `MergeOutcomeGuard` records its outcome, after the
+        // segments are released.
+        if !self.state.guarded.load(Ordering::Acquire) {
+            self.state
+                .fail_if_unfinished("The merge operation was dropped before the merge started.");
+        }
+    }
 }
 
 impl MergeOperation {
@@ -57,6 +166,7 @@ impl MergeOperation {
         let inner_merge_operation = InnerMergeOperation {
             target_opstamp,
             segment_ids,
+            state: Arc::default(),
         };
         MergeOperation {
             inner: inventory.track(inner_merge_operation),
@@ -70,4 +180,120 @@ impl MergeOperation {
     pub fn segment_ids(&self) -> &[SegmentId] {
         &self.inner.segment_ids[..]
     }
+
+    /// Returns true if the cancellation of the merge was requested.
+    ///
+    /// The merging thread checks this flag regularly, and stops as soon as it notices it.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.state.is_cancelled()
+    }
+
+    /// Returns the state of the merge, to be shared with the `IndexMerger`.
+    pub(crate) fn state(&self) -> Arc<MergeState> {
+        self.inner.state.clone()
+    }
+}
+
+/// Handle over a merge operation, as returned by
+/// [`IndexWriter::running_merges`](crate::IndexWriter::running_merges).
+///
+/// It makes it possible to follow the progress of the merge, to wait for its
+/// completion or to cancel it. Holding a `RunningMerge` does not prevent the merge
+/// from completing.
+///
+/// The progress is measured in documents. A merge goes through the documents of the
+/// merged segment once per pass (fieldnorms, postings, fast fields and stored fields),
+/// so it is done when `num_docs_processed()` reaches `num_docs_to_process()`.
+#[derive(Clone)]
+pub struct RunningMerge {
+    target_opstamp: Opstamp,
+    segment_ids: Vec<SegmentId>,
+    state: Arc<MergeState>,
+}
+
+impl RunningMerge {
+    /// Returns the opstamp up to which deletes are applied to the merged segment.
+    pub fn target_opstamp(&self) -> Opstamp {
+        self.target_opstamp
+    }
+
+    /// Returns the ids of the segments being merged.
+    pub fn segment_ids(&self) -> &[SegmentId] {
+        &self.segment_ids[..]
+    }
+
+    /// Returns the number of documents of the merged segment, or 0 if the merge has
+    /// not started writing it yet.
+    pub fn num_docs(&self) -> u32 {
+        self.state.num_docs.load(Ordering::Relaxed) as u32
+    }
+
+    /// Returns the amount of work done so far, in documents.
+    pub fn num_docs_processed(&self) -> u64 {
+        self.state.num_docs_processed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total amount of work of the merge, in documents.
+    pub fn num_docs_to_process(&self) -> u64 {
+        self.state.num_docs.load(Ordering::Relaxed) * NUM_MERGE_PASSES
+    }
+
+    /// Returns the time elapsed since the merge started writing the merged segment,
+    /// or `None` if it has not started yet.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.state
+            .start
+            .lock()
+            .unwrap()
+            .map(|start| start.elapsed())
+    }
+
+    /// Returns an estimation of the time left before the merged segment is written.
+    ///
+    /// The estimation assumes that the rest of the merge goes at the same pace as
+    /// what has been done so far. It is `None` until some progress has been made.
+    pub fn eta(&self) -> Option<Duration> {
+        let elapsed = self.elapsed()?;
+        let num_docs_processed = self.num_docs_processed();
+        if num_docs_processed == 0 {
+            return None;
+        }
+        let num_docs_left = self
+            .num_docs_to_process()
+            .saturating_sub(num_docs_processed);
+        Some(elapsed.mul_f64(num_docs_left as f64 / num_docs_processed as f64))
+    }
+
+    /// Requests the cancellation of the merge.
+    ///
+    /// The merging thread stops as soon as it notices it, and the merged segments are
+    /// left untouched.
+    pub fn cancel(&self) {
+        self.state.cancel();
+    }
+
+    /// Returns true if the cancellation of the merge was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.is_cancelled()
+    }
+
+    /// Returns true if the merge is over, whether it succeeded or not.
+    pub fn is_finished(&self) -> bool {
+        self.state.outcome.lock().unwrap().is_some()
+    }
+
+    /// Blocks until the merge is over and returns the meta of the merged segment.
+    ///
+    /// An error is returned if the merge failed or was cancelled, or if its merge operation
+    /// was dropped before the merge started.
+    pub fn wait(&self) -> crate::Result<SegmentMeta> {
+        let outcome = self
+            .state
+            .outcome_cond
+            .wait_while(self.state.outcome.lock().unwrap(), |outcome| {
+                outcome.is_none()
+            })
+            .unwrap();
+        outcome.as_ref().unwrap().clone()
+    }
 }
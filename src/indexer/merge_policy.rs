@@ -17,6 +17,20 @@ pub trait MergePolicy: marker::Send + marker::Sync + Debug {
     /// This call happens on the segment updater thread, and will block
     /// other segment updates, so all implementations should happen rapidly.
     fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate>;
+
+    /// If true (the default), the merges copy the compressed doc store blocks of the segments
+    /// as they are, rather than decompressing and recompressing their documents. Only the
+    /// blocks holding deleted documents are recompressed.
+    ///
+    /// The blocks of the merged doc store then keep the layout of the source segments: the
+    /// last block of each segment, and the blocks recompressed around the deleted documents,
+    /// may be small. Returning false yields full blocks at the cost of more cpu during merges.
+    ///
+    /// It applies to the merges triggered by the policy, and to those started with
+    /// [`IndexWriter::merge`](crate::IndexWriter::merge) while the policy is set.
+    fn docstore_stack_on_merge(&self) -> bool {
+        true
+    }
 }
 
 /// Never merge segments.
@@ -2,9 +2,10 @@ use std::cmp;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use fastfield_codecs::FastFieldCodecReader;
 use itertools::Itertools;
 use measure_time::debug_time;
-use tantivy_bitpacker::minmax;
+use tantivy_bitpacker::{compute_num_bits, minmax};
 
 use crate::core::{Segment, SegmentReader};
 use crate::docset::{DocSet, TERMINATED};
@@ -15,10 +16,11 @@ use crate::fastfield::{
 };
 use crate::fieldnorm::{FieldNormReader, FieldNormReaders, FieldNormsSerializer, FieldNormsWriter};
 use crate::indexer::doc_id_mapping::{expect_field_id_for_sort_field, SegmentDocIdMapping};
+use crate::indexer::merge_operation::MergeState;
 use crate::indexer::SegmentSerializer;
 use crate::postings::{InvertedIndexSerializer, Postings, SegmentPostings};
 use crate::schema::{Cardinality, Field, FieldType, Schema};
-use crate::store::StoreWriter;
+use crate::store::{retain_fields, StoreWriter};
 use crate::termdict::{TermMerger, TermOrdinal};
 use crate::{
     DocId, IndexSettings, IndexSortByField, InvertedIndexReader, Order, SegmentComponent,
@@ -80,11 +82,19 @@ fn estimate_total_num_tokens(readers: &[SegmentReader], field: Field) -> crate::
     Ok(total_num_tokens)
 }
 
+// Passes of the merge over the documents, used to report its progress.
+const FIELDNORMS_PASS: u64 = 0;
+const POSTINGS_PASS: u64 = 1;
+const FAST_FIELDS_PASS: u64 = 2;
+const STORE_PASS: u64 = 3;
+
 pub struct IndexMerger {
     index_settings: IndexSettings,
     schema: Schema,
     pub(crate) readers: Vec<SegmentReader>,
     max_doc: u32,
+    merge_state: Option<Arc<MergeState>>,
+    docstore_stack_on_merge: bool,
 }
 
 fn compute_min_max_val(
@@ -218,9 +228,55 @@ impl IndexMerger {
             schema,
             readers,
             max_doc,
+            merge_state: None,
+            docstore_stack_on_merge: true,
         })
     }
 
+    /// Sets the state of the merge operation, through which the progress of `write` is
+    /// reported.
+    ///
+    /// Once the merge is cancelled, `write` stops as soon as possible and returns an error.
+    /// Sets whether the compressed doc store blocks of the segments are copied as they are,
+    /// see `MergePolicy::docstore_stack_on_merge`.
+    pub(crate) fn set_docstore_stack_on_merge(&mut self, docstore_stack_on_merge: bool) {
+        self.docstore_stack_on_merge = docstore_stack_on_merge;
+    }
+
+    pub(crate) fn set_merge_state(&mut self, merge_state: Arc<MergeState>) {
+        merge_state.start(self.max_doc);
+        self.merge_state = Some(merge_state);
+    }
+
+    /// Reports that the pass `pass_ord` of the merge went through `num_docs_in_pass`
+    /// documents.
+    fn report_progress(&self, pass_ord: u64, num_docs_in_pass: u64) {
+        if let Some(merge_state) = self.merge_state.as_ref() {
+            merge_state.set_num_docs_processed(pass_ord * self.max_doc as u64 + num_docs_in_pass);
+        }
+    }
+
+    /// Reports the progress of a pass that processes the fields one after the other.
+    fn report_fields_progress(&self, pass_ord: u64, num_fields_done: usize, num_fields: usize) {
+        let num_docs_in_pass = if num_fields == 0 {
+            self.max_doc as u64
+        } else {
+            self.max_doc as u64 * num_fields_done as u64 / num_fields as u64
+        };
+        self.report_progress(pass_ord, num_docs_in_pass);
+    }
+
+    fn check_cancelled(&self) -> crate::Result<()> {
+        if let Some(merge_state) = self.merge_state.as_ref() {
+            if merge_state.is_cancelled() {
+                return Err(crate::TantivyError::Cancelled(
+                    "Merge cancelled".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn sort_readers_by_min_sort_field(
         readers: Vec<SegmentReader>,
         sort_by_field: &IndexSortByField,
@@ -252,14 +308,24 @@ impl IndexMerger {
     ) -> crate::Result<()> {
         let fields = FieldNormsWriter::fields_with_fieldnorm(&self.schema);
         let mut fieldnorms_data = Vec::with_capacity(self.max_doc as usize);
-        for field in fields {
+        let num_fields = fields.len();
+        for (field_ord, field) in fields.into_iter().enumerate() {
+            self.check_cancelled()?;
+            self.report_fields_progress(FIELDNORMS_PASS, field_ord, num_fields);
             fieldnorms_data.clear();
 
+            // The segments created before a dynamic field was added to the schema
+            // have no fieldnorms for it.
             let fieldnorms_readers: Vec<FieldNormReader> = self
                 .readers
                 .iter()
-                .map(|reader| reader.get_fieldnorms_reader(field))
-                .collect::<Result<_, _>>()?;
+                .map(|reader| {
+                    Ok(reader
+                        .fieldnorms_readers()
+                        .get_field(field)?
+                        .unwrap_or_else(|| FieldNormReader::constant(reader.max_doc(), 0)))
+                })
+                .collect::<crate::Result<_>>()?;
             for (doc_id, reader_ordinal) in doc_id_mapping.iter() {
                 let fieldnorms_reader = &fieldnorms_readers[*reader_ordinal as usize];
                 let fieldnorm_id = fieldnorms_reader.fieldnorm_id(*doc_id);
@@ -268,6 +334,7 @@ impl IndexMerger {
 
             fieldnorms_serializer.serialize_field(field, &fieldnorms_data[..])?;
         }
+        self.report_fields_progress(FIELDNORMS_PASS, num_fields, num_fields);
         fieldnorms_serializer.close()?;
         Ok(())
     }
@@ -280,7 +347,13 @@ impl IndexMerger {
     ) -> crate::Result<()> {
         debug_time!("write-fast-fields");
 
-        for (field, field_entry) in self.schema.fields() {
+        let num_fields = self.schema.num_fields();
+        for (field_ord, (field, field_entry)) in self.schema.fields().enumerate() {
+            self.check_cancelled()?;
+            self.report_fields_progress(FAST_FIELDS_PASS, field_ord, num_fields);
+            if field_entry.is_deprecated() {
+                continue;
+            }
             let field_type = field_entry.field_type();
             match field_type {
                 FieldType::Facet(_) | FieldType::Str(_) if field_type.is_fast() => {
@@ -357,6 +430,16 @@ impl IndexMerger {
             })
             .collect::<Vec<_>>();
 
+        if self.write_stacked_bitpacked_fast_field(
+            field,
+            fast_field_serializer,
+            &fast_field_readers,
+            doc_id_mapping,
+            (min_value, max_value),
+        )? {
+            return Ok(());
+        }
+
         let stats = FastFieldStats {
             min_value,
             max_value,
@@ -396,6 +479,62 @@ impl IndexMerger {
         Ok(())
     }
 
+    /// Writes a single value fast field by stacking the bitpacked columns of the segments, rather
+    /// than decoding every value through the doc id mapping and the codec selection.
+    ///
+    /// This is only possible if the documents are neither deleted nor reordered, if all of the
+    /// columns are bitpacked, and if the merged value range does not require more bits than the
+    /// widest of them. The columns encoded with the merged min value and number of bits are
+    /// copied without being unpacked, the others are shifted value by value.
+    ///
+    /// Returns false, without writing anything, if the field requires the generic merge.
+    fn write_stacked_bitpacked_fast_field(
+        &self,
+        field: Field,
+        fast_field_serializer: &mut CompositeFastFieldSerializer,
+        fast_field_readers: &[DynamicFastFieldReader<u64>],
+        doc_id_mapping: &SegmentDocIdMapping,
+        (min_value, max_value): (u64, u64),
+    ) -> crate::Result<bool> {
+        if !doc_id_mapping.is_trivial() || self.readers.iter().any(SegmentReader::has_deletes) {
+            return Ok(false);
+        }
+        let bitpacked_readers: Vec<_> = fast_field_readers
+            .iter()
+            .filter_map(|fast_field_reader| match fast_field_reader {
+                DynamicFastFieldReader::Bitpacked(bitpacked_reader) => Some(bitpacked_reader),
+                _ => None,
+            })
+            .collect();
+        if bitpacked_readers.len() != fast_field_readers.len() {
+            return Ok(false);
+        }
+        let num_bits = compute_num_bits(max_value - min_value);
+        let max_num_bits = bitpacked_readers
+            .iter()
+            .map(|bitpacked_reader| bitpacked_reader.codec_reader().num_bits())
+            .max()
+            .unwrap_or(0);
+        if num_bits > max_num_bits {
+            return Ok(false);
+        }
+        debug!("stack-bitpacked-fast-field");
+        let mut serializer =
+            fast_field_serializer.new_u64_fast_field(field, min_value, max_value)?;
+        for (reader, bitpacked_reader) in self.readers.iter().zip(bitpacked_readers) {
+            let codec_reader = bitpacked_reader.codec_reader();
+            if codec_reader.min_value() == min_value && codec_reader.num_bits() == num_bits {
+                serializer.add_packed_vals(bitpacked_reader.data(), reader.max_doc() as u64)?;
+            } else {
+                for doc in 0..reader.max_doc() {
+                    serializer.add_val(bitpacked_reader.get(doc))?;
+                }
+            }
+        }
+        serializer.close_field()?;
+        Ok(true)
+    }
+
     /// Checks if the readers are disjunct for their sort property and in the correct order to be
     /// able to just stack them.
     pub(crate) fn is_disjunct_and_sorted_on_sort_property(
@@ -830,6 +969,7 @@ impl IndexMerger {
     ) -> crate::Result<Option<TermOrdinalMapping>> {
         debug_time!("write-postings-for-field");
         let mut positions_buffer: Vec<u32> = Vec::with_capacity(1_000);
+        let mut payloads_buffer: Vec<u32> = Vec::new();
         let mut delta_computer = DeltaComputer::new();
 
         let mut max_term_ords: Vec<TermOrdinal> = Vec::new();
@@ -904,6 +1044,7 @@ impl IndexMerger {
         let mut doc_id_and_positions = vec![];
 
         while merged_terms.advance() {
+            self.check_cancelled()?;
             segment_postings_containing_the_term.clear();
             let term_bytes: &[u8] = merged_terms.key();
 
@@ -960,6 +1101,7 @@ impl IndexMerger {
                         // there is at least one document.
                         let term_freq = segment_postings.term_freq();
                         segment_postings.positions(&mut positions_buffer);
+                        segment_postings.payloads(&mut payloads_buffer);
                         // if doc_id_mapping exists, the doc_ids are reordered, they are
                         // not just stacked. The field serializer expects monotonically increasing
                         // doc_ids, so we collect and sort them first, before writing.
@@ -972,10 +1114,16 @@ impl IndexMerger {
                                 remapped_doc_id,
                                 term_freq,
                                 positions_buffer.to_vec(),
+                                payloads_buffer.to_vec(),
                             ));
                         } else {
                             let delta_positions = delta_computer.compute_delta(&positions_buffer);
-                            field_serializer.write_doc(remapped_doc_id, term_freq, delta_positions);
+                            field_serializer.write_doc_with_payloads(
+                                remapped_doc_id,
+                                term_freq,
+                                delta_positions,
+                                &payloads_buffer,
+                            );
                         }
                     }
 
@@ -983,11 +1131,16 @@ impl IndexMerger {
                 }
             }
             if !doc_id_mapping.is_trivial() {
-                doc_id_and_positions.sort_unstable_by_key(|&(doc_id, _, _)| doc_id);
+                doc_id_and_positions.sort_unstable_by_key(|&(doc_id, _, _, _)| doc_id);
 
-                for (doc_id, term_freq, positions) in &doc_id_and_positions {
+                for (doc_id, term_freq, positions, payloads) in &doc_id_and_positions {
                     let delta_positions = delta_computer.compute_delta(positions);
-                    field_serializer.write_doc(*doc_id, *term_freq, delta_positions);
+                    field_serializer.write_doc_with_payloads(
+                        *doc_id,
+                        *term_freq,
+                        delta_positions,
+                        payloads,
+                    );
                 }
                 doc_id_and_positions.clear();
             }
@@ -1005,9 +1158,11 @@ impl IndexMerger {
         doc_id_mapping: &SegmentDocIdMapping,
     ) -> crate::Result<HashMap<Field, TermOrdinalMapping>> {
         let mut term_ordinal_mappings = HashMap::new();
-        for (field, field_entry) in self.schema.fields() {
+        let num_fields = self.schema.num_fields();
+        for (field_ord, (field, field_entry)) in self.schema.fields().enumerate() {
+            self.report_fields_progress(POSTINGS_PASS, field_ord, num_fields);
             let fieldnorm_reader = fieldnorm_readers.get_field(field)?;
-            if field_entry.is_indexed() {
+            if field_entry.is_indexed() && !field_entry.is_deprecated() {
                 if let Some(term_ordinal_mapping) = self.write_postings_for_field(
                     field,
                     field_entry.field_type(),
@@ -1019,6 +1174,7 @@ impl IndexMerger {
                 }
             }
         }
+        self.report_fields_progress(POSTINGS_PASS, num_fields, num_fields);
         Ok(term_ordinal_mappings)
     }
 
@@ -1030,6 +1186,28 @@ impl IndexMerger {
         debug_time!("write-storable-fields");
         debug!("write-storable-field");
 
+        // The stored values of the deprecated fields are dropped, which requires
+        // deserializing the documents. The references to the blobs of the values kept are
+        // copied as is.
+        let drops_stored_fields = self
+            .schema
+            .fields()
+            .any(|(_, field_entry)| field_entry.is_deprecated() && field_entry.is_stored());
+        let mut retained_doc_bytes = Vec::new();
+        let mut store_doc_bytes = |store_writer: &mut StoreWriter, doc_bytes: &[u8]| {
+            if drops_stored_fields {
+                retained_doc_bytes.clear();
+                retain_fields(
+                    doc_bytes,
+                    |field| !self.schema.get_field_entry(field).is_deprecated(),
+                    &mut retained_doc_bytes,
+                )?;
+                store_writer.store_bytes(&retained_doc_bytes)
+            } else {
+                store_writer.store_bytes(doc_bytes)
+            }
+        };
+
         if !doc_id_mapping.is_trivial() {
             debug!("non-trivial-doc-id-mapping");
 
@@ -1045,11 +1223,14 @@ impl IndexMerger {
                 .map(|(i, store)| store.iter_raw(self.readers[i].alive_bitset()))
                 .collect();
 
-            for (old_doc_id, reader_ordinal) in doc_id_mapping.iter() {
+            for (num_docs_stored, (old_doc_id, reader_ordinal)) in doc_id_mapping.iter().enumerate()
+            {
+                self.check_cancelled()?;
+                self.report_progress(STORE_PASS, num_docs_stored as u64);
                 let doc_bytes_it = &mut document_iterators[*reader_ordinal as usize];
                 if let Some(doc_bytes_res) = doc_bytes_it.next() {
                     let doc_bytes = doc_bytes_res?;
-                    store_writer.store_bytes(&doc_bytes)?;
+                    store_doc_bytes(store_writer, doc_bytes.as_slice())?;
                 } else {
                     return Err(DataCorruption::comment_only(&format!(
                         "unexpected missing document in docstore on merge, doc id {:?}",
@@ -1060,9 +1241,11 @@ impl IndexMerger {
             }
         } else {
             debug!("trivial-doc-id-mapping");
+            let mut num_docs_stored = 0u64;
             for reader in &self.readers {
+                self.check_cancelled()?;
                 let store_reader = reader.get_store_reader(1)?;
-                if reader.has_deletes()
+                if !self.docstore_stack_on_merge
                     // If there is not enough data in the store, we avoid stacking in order to
                     // avoid creating many small blocks in the doc store. Once we have 5 full blocks,
                     // we start stacking. In the worst case 2/7 of the blocks would be very small.
@@ -1078,16 +1261,44 @@ impl IndexMerger {
                     // take 7 in order to not walk over all checkpoints.
                     || store_reader.block_checkpoints().take(7).count() < 6
                     || store_reader.decompressor() != store_writer.compressor().into()
+                    || drops_stored_fields
                 {
                     for doc_bytes_res in store_reader.iter_raw(reader.alive_bitset()) {
+                        self.check_cancelled()?;
+                        self.report_progress(STORE_PASS, num_docs_stored);
                         let doc_bytes = doc_bytes_res?;
-                        store_writer.store_bytes(&doc_bytes)?;
+                        store_doc_bytes(store_writer, doc_bytes.as_slice())?;
+                        num_docs_stored += 1;
+                    }
+                } else if let Some(alive_bitset) = reader.alive_bitset() {
+                    // Only the blocks holding deleted documents are recompressed.
+                    for checkpoint in store_reader.block_checkpoints() {
+                        self.check_cancelled()?;
+                        self.report_progress(STORE_PASS, num_docs_stored);
+                        if checkpoint
+                            .doc_range
+                            .clone()
+                            .all(|doc_id| alive_bitset.is_alive(doc_id))
+                        {
+                            store_writer.stack_block(&store_reader, &checkpoint)?;
+                            num_docs_stored += checkpoint.doc_range.len() as u64;
+                            continue;
+                        }
+                        for doc_id in checkpoint.doc_range {
+                            if alive_bitset.is_alive(doc_id) {
+                                let doc_bytes = store_reader.get_document_bytes(doc_id)?;
+                                store_doc_bytes(store_writer, doc_bytes.as_slice())?;
+                                num_docs_stored += 1;
+                            }
+                        }
                     }
                 } else {
                     store_writer.stack(store_reader)?;
+                    num_docs_stored += reader.num_docs() as u64;
                 }
             }
         }
+        self.report_progress(STORE_PASS, self.max_doc as u64);
         Ok(())
     }
 
@@ -1109,14 +1320,13 @@ impl IndexMerger {
         } else {
             self.get_doc_id_from_concatenated_data()?
         };
+        self.check_cancelled()?;
         debug!("write-fieldnorms");
         if let Some(fieldnorms_serializer) = serializer.extract_fieldnorms_serializer() {
             self.write_fieldnorms(fieldnorms_serializer, &doc_id_mapping)?;
         }
         debug!("write-postings");
-        let fieldnorm_data = serializer
-            .segment()
-            .open_read(SegmentComponent::FieldNorms)?;
+        let fieldnorm_data = serializer.open_read(SegmentComponent::FieldNorms)?;
         let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
         let term_ord_mappings = self.write_postings(
             serializer.get_postings_serializer(),
@@ -1147,11 +1357,12 @@ mod tests {
     };
     use crate::collector::{Count, FacetCollector};
     use crate::core::Index;
-    use crate::fastfield::FastFieldReader;
+    use crate::fastfield::{DynamicFastFieldReader, FastFieldReader};
+    use crate::indexer::NoMergePolicy;
     use crate::query::{AllQuery, BooleanQuery, Scorer, TermQuery};
     use crate::schema::{
         Cardinality, Document, Facet, FacetOptions, IndexRecordOption, NumericOptions, Term,
-        TextFieldIndexing, INDEXED, TEXT,
+        TextFieldIndexing, INDEXED, STORED, TEXT,
     };
     use crate::time::OffsetDateTime;
     use crate::{
@@ -2114,6 +2325,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_merge_stacks_bitpacked_fast_fields() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let int_field = schema_builder.add_u64_field("intvals", FAST | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        // One segment does not share the min value of the others, and the numbers of values
+        // of the segments are not multiples of 8.
+        for (num_docs, offset) in [(13u64, 0u64), (21, 0), (17, 10)] {
+            for i in 0..num_docs {
+                index_writer.add_document(doc!(int_field => offset + (i * 37) % 50 * 2))?;
+            }
+            index_writer.commit()?;
+        }
+        let reader = index.reader()?;
+        for segment_reader in reader.searcher().segment_readers() {
+            assert!(matches!(
+                segment_reader.fast_fields().u64(int_field)?,
+                DynamicFastFieldReader::Bitpacked(_)
+            ));
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.max_doc(), 51);
+        let fast_field_reader = segment_reader.fast_fields().u64(int_field)?;
+        assert!(matches!(
+            fast_field_reader,
+            DynamicFastFieldReader::Bitpacked(_)
+        ));
+        assert_eq!(fast_field_reader.min_value(), 0);
+        assert_eq!(fast_field_reader.max_value(), 106);
+        for doc in 0..segment_reader.max_doc() {
+            let stored_doc = searcher.doc(DocAddress::new(0, doc))?;
+            assert_eq!(
+                stored_doc.get_first(int_field).and_then(|val| val.as_u64()),
+                Some(fast_field_reader.get(doc))
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_max_doc() {
         // this is the first time I write a unit test for a constant.
@@ -1,18 +1,28 @@
+mod batch_result;
+mod commit_hooks;
 pub mod delete_queue;
 
 pub mod demuxer;
 pub mod doc_id_mapping;
 mod doc_opstamp_mapping;
+mod flush_triggers;
+mod fsync_policy;
+mod group_commit;
 pub mod index_writer;
 mod index_writer_status;
 mod json_term_writer;
+mod live_doc_stats;
+mod lock_takeover;
 mod log_merge_policy;
+mod memory_index;
 mod merge_operation;
 pub mod merge_policy;
 pub mod merger;
 mod merger_sorted_index_test;
 pub mod operation;
+mod paused_index_writer;
 pub mod prepared_commit;
+mod segment_bulk_builder;
 mod segment_entry;
 mod segment_manager;
 mod segment_register;
@@ -20,18 +30,31 @@ pub mod segment_serializer;
 pub mod segment_updater;
 mod segment_writer;
 mod stamper;
+mod version_checker;
 
 use crossbeam_channel as channel;
 use smallvec::SmallVec;
 
+pub use self::batch_result::{BatchResult, DocumentError, DocumentFailure};
+pub(crate) use self::commit_hooks::CommitHooks;
+pub use self::flush_triggers::FlushTriggers;
+pub(crate) use self::flush_triggers::SharedFlushTriggers;
+pub use self::fsync_policy::FsyncPolicy;
+pub(crate) use self::fsync_policy::FsyncScheduler;
+pub use self::group_commit::GroupCommitWriter;
 pub use self::index_writer::IndexWriter;
 pub(crate) use self::json_term_writer::{
     convert_to_fast_value_and_get_term, set_string_and_get_terms, JsonTermWriter,
 };
+pub use self::live_doc_stats::{DocCounts, LiveDocStats};
+pub(crate) use self::lock_takeover::WriterFence;
 pub use self::log_merge_policy::LogMergePolicy;
-pub use self::merge_operation::MergeOperation;
+pub use self::memory_index::MemoryIndex;
+pub use self::merge_operation::{MergeOperation, RunningMerge};
 pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy};
+pub use self::paused_index_writer::PausedIndexWriter;
 pub use self::prepared_commit::PreparedCommit;
+pub use self::segment_bulk_builder::SegmentBulkBuilder;
 pub use self::segment_entry::SegmentEntry;
 pub use self::segment_manager::SegmentManager;
 pub use self::segment_serializer::SegmentSerializer;
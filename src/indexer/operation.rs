@@ -1,18 +1,39 @@
 use crate::schema::{Document, Term};
 use crate::Opstamp;
 
+/// Documents deleted by a `DeleteOperation`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DeleteTarget {
+    /// The documents containing the term.
+    Term(Term),
+    /// The documents containing any of the terms.
+    ///
+    /// The terms belong to the same field, and are sorted and deduplicated.
+    TermSet(Vec<Term>),
+}
+
 /// Timestamped Delete operation.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct DeleteOperation {
     pub opstamp: Opstamp,
-    pub term: Term,
+    pub target: DeleteTarget,
+}
+
+impl DeleteOperation {
+    /// Returns the terms of the documents deleted by the operation.
+    pub fn terms(&self) -> &[Term] {
+        match &self.target {
+            DeleteTarget::Term(term) => std::slice::from_ref(term),
+            DeleteTarget::TermSet(terms) => terms,
+        }
+    }
 }
 
 impl Default for DeleteOperation {
     fn default() -> Self {
         DeleteOperation {
             opstamp: 0u64,
-            term: Term::new(),
+            target: DeleteTarget::Term(Term::new()),
         }
     }
 }
@@ -0,0 +1,46 @@
+use super::IndexWriter;
+use crate::Index;
+
+/// An `IndexWriter` paused by [`IndexWriter::pause`].
+///
+/// While it is paused, the writer has no indexing thread nor running merge, and the
+/// documents added before the pause sit in finalized uncommitted segments, so that the
+/// files of the index do not change: this is a consistent point to back the index up or to
+/// replicate it. No document can be added, as the writer is mutably borrowed.
+///
+/// The writer resumes when [`PausedIndexWriter::resume`] is called, or when the
+/// `PausedIndexWriter` is dropped.
+pub struct PausedIndexWriter<'a> {
+    pub(crate) index_writer: &'a mut IndexWriter,
+    is_resumed: bool,
+}
+
+impl<'a> PausedIndexWriter<'a> {
+    pub(crate) fn new(index_writer: &'a mut IndexWriter) -> PausedIndexWriter<'a> {
+        PausedIndexWriter {
+            index_writer,
+            is_resumed: false,
+        }
+    }
+
+    /// Accessor to the index of the paused writer.
+    pub fn index(&self) -> &Index {
+        self.index_writer.index()
+    }
+
+    /// Restarts the indexing threads and the merges.
+    pub fn resume(mut self) -> crate::Result<()> {
+        self.is_resumed = true;
+        self.index_writer.resume()
+    }
+}
+
+impl<'a> Drop for PausedIndexWriter<'a> {
+    fn drop(&mut self) {
+        if !self.is_resumed {
+            if let Err(err) = self.index_writer.resume() {
+                error!("Failed to resume the index writer: {:?}", err);
+            }
+        }
+    }
+}
@@ -35,6 +35,7 @@ impl<'a> PreparedCommit<'a> {
     /// Proceeds to commit.
     /// See `.commit_future()`.
     pub fn commit(self) -> crate::Result<Opstamp> {
+        let _span = enter_span!("commit", opstamp = self.opstamp);
         self.commit_future().wait()
     }
 
@@ -0,0 +1,255 @@
+use super::index_writer::{
+    MARGIN_IN_BYTES, MEMORY_ARENA_NUM_BYTES_MAX, MEMORY_ARENA_NUM_BYTES_MIN,
+};
+use super::merger::IndexMerger;
+use super::operation::AddOperation;
+use super::segment_updater::{merged_max_opstamp, save_metas};
+use super::{SegmentSerializer, SegmentWriter};
+use crate::core::{IndexMeta, Segment, SegmentMeta};
+use crate::directory::{Directory, INDEX_WRITER_LOCK};
+use crate::error::TantivyError;
+use crate::schema::Document;
+use crate::{Index, Opstamp};
+
+/// Builds segments directly from an iterator of documents, and commits them.
+///
+/// This is a dedicated path for the initial construction of an index:
+/// there are no indexing threads, no channels and no delete queue.
+/// Documents are indexed in the calling thread, in a new segment every time
+/// the memory budget is reached. Segments are sorted according to the
+/// [`IndexSettings`](../struct.IndexSettings.html) of the index.
+///
+/// The segments built are added to the segments of the last commit.
+/// The builder holds the index writer lock while building, so it cannot run
+/// concurrently with an `IndexWriter`.
+///
+/// ```rust
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, SegmentBulkBuilder};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let documents = (0..1_000).map(|i| doc!(title => format!("The Diary of Muadib, volume {}", i)));
+/// SegmentBulkBuilder::new(&index)
+///     .merge_segments(true)
+///     .build(documents)?;
+/// let searcher = index.reader()?.searcher();
+/// assert_eq!(searcher.num_docs(), 1_000);
+/// assert_eq!(searcher.segment_readers().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SegmentBulkBuilder {
+    index: Index,
+    memory_budget: usize,
+    merge_segments: bool,
+}
+
+impl SegmentBulkBuilder {
+    /// Creates a bulk builder for the given index, with a memory budget of 100MB.
+    pub fn new(index: &Index) -> SegmentBulkBuilder {
+        SegmentBulkBuilder {
+            index: index.clone(),
+            memory_budget: 100_000_000,
+            merge_segments: false,
+        }
+    }
+
+    /// Sets the memory budget, in bytes, of the segment being built.
+    ///
+    /// Once it is reached, the segment is flushed and a new one is started.
+    ///
+    /// # Panics
+    /// If the budget is lower than 3MB or greater than 4GB.
+    #[must_use]
+    pub fn memory_budget(mut self, memory_budget: usize) -> SegmentBulkBuilder {
+        assert!(
+            (MEMORY_ARENA_NUM_BYTES_MIN..=MEMORY_ARENA_NUM_BYTES_MAX).contains(&memory_budget),
+            "The memory budget must be between {} and {} bytes.",
+            MEMORY_ARENA_NUM_BYTES_MIN,
+            MEMORY_ARENA_NUM_BYTES_MAX
+        );
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// If true, all of the segments built are merged into a single segment before
+    /// the commit. Defaults to false.
+    ///
+    /// The files of the intermediate segments are deleted by the next garbage collection
+    /// of an `IndexWriter`.
+    #[must_use]
+    pub fn merge_segments(mut self, merge_segments: bool) -> SegmentBulkBuilder {
+        self.merge_segments = merge_segments;
+        self
+    }
+
+    /// Indexes all of the documents and commits them.
+    ///
+    /// Documents are given contiguous opstamps following the opstamp of the last commit.
+    /// Returns the opstamp of the commit.
+    pub fn build<I>(self, documents: I) -> crate::Result<Opstamp>
+    where I: IntoIterator<Item = Document> {
+        let _directory_lock = self
+            .index
+            .directory()
+            .acquire_lock(&INDEX_WRITER_LOCK)
+            .map_err(|err| {
+                TantivyError::LockFailure(
+                    err,
+                    Some(
+                        "Failed to acquire index lock. There is already an `IndexWriter` working \
+                         on this `Directory`."
+                            .to_string(),
+                    ),
+                )
+            })?;
+        let index_meta = self.index.load_metas()?;
+        let mut opstamp = index_meta.opstamp;
+        let mut documents = documents.into_iter().peekable();
+        let mut new_segment_metas = Vec::new();
+        while documents.peek().is_some() {
+            let segment = self.index.new_segment();
+            let mut segment_writer = SegmentWriter::for_segment(
+                self.memory_budget,
+                segment.clone(),
+                self.index.schema(),
+            )?;
+            for mut document in documents.by_ref() {
+                self.index.schema().prepare_document(&mut document)?;
+                opstamp += 1;
+                segment_writer.add_document(AddOperation { opstamp, document })?;
+                if segment_writer.mem_usage() >= self.memory_budget - MARGIN_IN_BYTES {
+                    break;
+                }
+            }
+            let max_doc = segment_writer.max_doc();
+            segment_writer.finalize()?;
+            let segment_meta = segment
+                .with_max_doc(max_doc)
+                .meta()
+                .clone()
+                .with_max_opstamp(Some(opstamp));
+            segment_meta.untrack_temp_docstore();
+            new_segment_metas.push(segment_meta);
+        }
+        if self.merge_segments && new_segment_metas.len() > 1 {
+            new_segment_metas = vec![self.merge(&new_segment_metas)?];
+        }
+        let commit_opstamp = opstamp + 1;
+        let mut segments = index_meta.segments;
+        segments.extend(new_segment_metas);
+        save_metas(
+            &IndexMeta {
+                index_settings: index_meta.index_settings,
+                segments,
+                schema: index_meta.schema,
+                opstamp: commit_opstamp,
+                payload: None,
+            },
+            self.index.directory(),
+        )?;
+        Ok(commit_opstamp)
+    }
+
+    fn merge(&self, segment_metas: &[SegmentMeta]) -> crate::Result<SegmentMeta> {
+        let segments: Vec<Segment> = segment_metas
+            .iter()
+            .map(|segment_meta| self.index.segment(segment_meta.clone()))
+            .collect();
+        let merger = IndexMerger::open(
+            self.index.schema(),
+            self.index.settings().clone(),
+            &segments[..],
+        )?;
+        let merged_segment = self.index.new_segment();
+        let segment_serializer = SegmentSerializer::for_segment(merged_segment.clone(), true)?;
+        let num_docs = merger.write(segment_serializer)?;
+        Ok(self
+            .index
+            .new_segment_meta(merged_segment.id(), num_docs)
+            .with_max_opstamp(merged_max_opstamp(segment_metas.iter())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SegmentBulkBuilder;
+    use crate::collector::Count;
+    use crate::fastfield::FastFieldReader;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STORED, TEXT};
+    use crate::{Index, IndexSettings, IndexSortByField, Order, Term};
+
+    #[test]
+    fn test_bulk_builder() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "existing"))?;
+        index_writer.commit()?;
+        drop(index_writer);
+
+        let documents = (0..20_000u64)
+            .map(|i| doc!(text => format!("doc{} a{} b{} c{} {}", i, i, i, i, i % 2)));
+        let opstamp = SegmentBulkBuilder::new(&index)
+            .memory_budget(3_000_000)
+            .build(documents)?;
+        let index_meta = index.load_metas()?;
+        assert_eq!(index_meta.opstamp, opstamp);
+        assert!(index_meta.segments.len() > 2);
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 20_001);
+        let term_query = TermQuery::new(Term::from_field_text(text, "1"), IndexRecordOption::Basic);
+        assert_eq!(searcher.search(&term_query, &Count)?, 10_000);
+
+        // The index can still be written to afterwards.
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "after"))?;
+        assert!(index_writer.commit()? > opstamp);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_builder_merge_sorted() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let num = schema_builder.add_u64_field("num", FAST);
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(IndexSettings {
+                sort_by_field: Some(IndexSortByField {
+                    field: "num".to_string(),
+                    order: Order::Asc,
+                }),
+                ..Default::default()
+            })
+            .create_in_ram()?;
+        // Unique terms make sure several segments are built before the merge.
+        let documents = (0..20_000u64)
+            .rev()
+            .map(|i| doc!(num => i, text => format!("a{} b{} c{} d{}", i, i, i, i)));
+        SegmentBulkBuilder::new(&index)
+            .memory_budget(3_000_000)
+            .merge_segments(true)
+            .build(documents)?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let fast_field_reader = searcher.segment_reader(0).fast_fields().u64(num)?;
+        assert_eq!(fast_field_reader.get(0), 0);
+        assert_eq!(fast_field_reader.get(19_999), 19_999);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_builder_locked() -> crate::Result<()> {
+        let index = Index::create_in_ram(Schema::builder().build());
+        let _index_writer = index.writer_for_tests()?;
+        assert!(SegmentBulkBuilder::new(&index).build(Vec::new()).is_err());
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use super::segment_register::SegmentRegister;
 use crate::core::{SegmentId, SegmentMeta};
 use crate::error::TantivyError;
 use crate::indexer::delete_queue::DeleteCursor;
-use crate::indexer::SegmentEntry;
+use crate::indexer::{DocCounts, LiveDocStats, SegmentEntry};
 
 #[derive(Default)]
 struct SegmentRegisters {
@@ -211,6 +211,16 @@ impl SegmentManager {
         Ok(segments_status)
     }
 
+    pub fn live_doc_stats(&self) -> LiveDocStats {
+        let registers_lock = self.read();
+        LiveDocStats {
+            committed: DocCounts::from_segment_entries(&registers_lock.committed.segment_entries()),
+            uncommitted: DocCounts::from_segment_entries(
+                &registers_lock.uncommitted.segment_entries(),
+            ),
+        }
+    }
+
     pub fn committed_segment_metas(&self) -> Vec<SegmentMeta> {
         self.remove_empty_segments();
         let registers_lock = self.read();
@@ -1,48 +1,134 @@
 use crate::core::{Segment, SegmentComponent};
+use crate::directory::{FileSlice, WritePtr};
 use crate::fastfield::CompositeFastFieldSerializer;
 use crate::fieldnorm::FieldNormsSerializer;
+use crate::indexer::memory_index::MemorySegment;
 use crate::postings::InvertedIndexSerializer;
-use crate::store::StoreWriter;
+use crate::schema::Schema;
+use crate::store::{BlobWriter, StoreWriter};
+use crate::IndexSettings;
+
+/// Where the components of the serialized segment are written.
+enum SerializerTarget {
+    /// The files of a segment of an index.
+    Segment(Segment),
+    /// The in-memory segment of a [`MemoryIndex`](crate::MemoryIndex).
+    Memory(MemorySegment),
+}
+
+impl SerializerTarget {
+    fn open_write(&mut self, component: SegmentComponent) -> crate::Result<WritePtr> {
+        match self {
+            SerializerTarget::Segment(segment) => Ok(segment.open_write(component)?),
+            SerializerTarget::Memory(memory_segment) => Ok(memory_segment.open_write(component)),
+        }
+    }
+
+    fn open_read(&self, component: SegmentComponent) -> crate::Result<FileSlice> {
+        match self {
+            SerializerTarget::Segment(segment) => Ok(segment.open_read(component)?),
+            SerializerTarget::Memory(memory_segment) => Ok(memory_segment.open_read(component)?),
+        }
+    }
+}
 
 /// Segment serializer is in charge of laying out on disk
 /// the data accumulated and sorted by the `SegmentWriter`.
 pub struct SegmentSerializer {
-    segment: Segment,
+    target: SerializerTarget,
+    settings: IndexSettings,
     pub(crate) store_writer: StoreWriter,
     fast_field_serializer: CompositeFastFieldSerializer,
     fieldnorms_serializer: Option<FieldNormsSerializer>,
     postings_serializer: InvertedIndexSerializer,
 }
 
+fn create_blob_writer(segment: &Segment) -> crate::Result<Option<BlobWriter>> {
+    let blob_threshold = match segment.index().settings().docstore_blob_threshold {
+        Some(blob_threshold) => blob_threshold,
+        None => return Ok(None),
+    };
+    let blob_store = segment.index().blob_store().cloned().ok_or_else(|| {
+        crate::TantivyError::InvalidArgument(
+            "The docstore blob threshold is set, but the index has no blob store.".to_string(),
+        )
+    })?;
+    let key_prefix = segment.id().uuid_string();
+    Ok(Some(BlobWriter::new(
+        blob_store,
+        blob_threshold,
+        key_prefix,
+    )))
+}
+
 impl SegmentSerializer {
     /// Creates a new `SegmentSerializer`.
-    pub fn for_segment(
-        mut segment: Segment,
-        is_in_merge: bool,
-    ) -> crate::Result<SegmentSerializer> {
+    pub fn for_segment(segment: Segment, is_in_merge: bool) -> crate::Result<SegmentSerializer> {
+        let settings = segment.index().settings().clone();
         // If the segment is going to be sorted, we stream the docs first to a temporary file.
         // In the merge case this is not necessary because we can kmerge the already sorted
         // segments
-        let remapping_required = segment.index().settings().sort_by_field.is_some() && !is_in_merge;
+        let remapping_required = settings.sort_by_field.is_some() && !is_in_merge;
+        let blob_writer = create_blob_writer(&segment)?;
+        let schema = segment.schema();
+        SegmentSerializer::new(
+            SerializerTarget::Segment(segment),
+            schema,
+            settings,
+            remapping_required,
+            blob_writer,
+        )
+    }
+
+    /// Creates a `SegmentSerializer` writing the components of `memory_segment`.
+    ///
+    /// The documents are not sorted, and no blob store is used.
+    pub(crate) fn in_memory(
+        memory_segment: MemorySegment,
+        schema: Schema,
+        settings: IndexSettings,
+    ) -> crate::Result<SegmentSerializer> {
+        SegmentSerializer::new(
+            SerializerTarget::Memory(memory_segment),
+            schema,
+            settings,
+            false,
+            None,
+        )
+    }
+
+    fn new(
+        mut target: SerializerTarget,
+        schema: Schema,
+        settings: IndexSettings,
+        remapping_required: bool,
+        blob_writer: Option<BlobWriter>,
+    ) -> crate::Result<SegmentSerializer> {
         let store_component = if remapping_required {
             SegmentComponent::TempStore
         } else {
             SegmentComponent::Store
         };
-        let store_write = segment.open_write(store_component)?;
+        let store_write = target.open_write(store_component)?;
 
-        let fast_field_write = segment.open_write(SegmentComponent::FastFields)?;
+        let fast_field_write = target.open_write(SegmentComponent::FastFields)?;
         let fast_field_serializer = CompositeFastFieldSerializer::from_write(fast_field_write)?;
 
-        let fieldnorms_write = segment.open_write(SegmentComponent::FieldNorms)?;
+        let fieldnorms_write = target.open_write(SegmentComponent::FieldNorms)?;
         let fieldnorms_serializer = FieldNormsSerializer::from_write(fieldnorms_write)?;
 
-        let postings_serializer = InvertedIndexSerializer::open(&mut segment)?;
-        let compressor = segment.index().settings().docstore_compression;
-        let blocksize = segment.index().settings().docstore_blocksize;
+        let postings_serializer =
+            InvertedIndexSerializer::from_writes(schema, |component| target.open_write(component))?;
+        let store_writer = StoreWriter::new(
+            store_write,
+            settings.docstore_compression,
+            settings.docstore_blocksize,
+        )?
+        .with_blob_writer(blob_writer);
         Ok(SegmentSerializer {
-            segment,
-            store_writer: StoreWriter::new(store_write, compressor, blocksize)?,
+            target,
+            settings,
+            store_writer,
             fast_field_serializer,
             fieldnorms_serializer: Some(fieldnorms_serializer),
             postings_serializer,
@@ -54,12 +140,19 @@ impl SegmentSerializer {
         self.store_writer.mem_usage()
     }
 
-    pub fn segment(&self) -> &Segment {
-        &self.segment
+    /// Returns the settings the segment is written with.
+    pub(crate) fn settings(&self) -> &IndexSettings {
+        &self.settings
+    }
+
+    /// Opens a component of the segment being written for reading.
+    pub(crate) fn open_read(&self, component: SegmentComponent) -> crate::Result<FileSlice> {
+        self.target.open_read(component)
     }
 
-    pub fn segment_mut(&mut self) -> &mut Segment {
-        &mut self.segment
+    /// Opens a component of the segment being written for writing.
+    pub(crate) fn open_write(&mut self, component: SegmentComponent) -> crate::Result<WritePtr> {
+        self.target.open_write(component)
     }
 
     /// Accessor to the `PostingsSerializer`.
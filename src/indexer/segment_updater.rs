@@ -1,10 +1,10 @@
 use std::borrow::BorrowMut;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::io::Write;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
 
 use fail::fail_point;
 use rayon::{ThreadPool, ThreadPoolBuilder};
@@ -13,17 +13,19 @@ use super::segment_manager::SegmentManager;
 use crate::core::{
     Index, IndexMeta, IndexSettings, Segment, SegmentId, SegmentMeta, META_FILEPATH,
 };
-use crate::directory::{Directory, DirectoryClone, GarbageCollectionResult};
+use crate::directory::{
+    list_meta_generation_files, Directory, DirectoryClone, GarbageCollectionResult, META_LOCK,
+};
 use crate::fastfield::AliveBitSet;
 use crate::indexer::delete_queue::DeleteCursor;
 use crate::indexer::index_writer::advance_deletes;
-use crate::indexer::merge_operation::MergeOperationInventory;
+use crate::indexer::merge_operation::{MergeOperationInventory, MergeOutcomeGuard};
 use crate::indexer::merger::IndexMerger;
 use crate::indexer::segment_manager::SegmentsStatus;
 use crate::indexer::stamper::Stamper;
 use crate::indexer::{
-    DefaultMergePolicy, MergeCandidate, MergeOperation, MergePolicy, SegmentEntry,
-    SegmentSerializer,
+    CommitHooks, DefaultMergePolicy, FsyncScheduler, LiveDocStats, MergeCandidate, MergeOperation,
+    MergePolicy, RunningMerge, SegmentEntry, SegmentSerializer, WriterFence,
 };
 use crate::schema::Schema;
 use crate::{FutureResult, Opstamp};
@@ -66,8 +68,24 @@ pub fn save_new_metas(
 /// - it success, and `meta.json` is written
 /// and flushed.
 ///
+/// The metas are written to a new generation file beforehand, which readers fall back to
+/// if they find `meta.json` partially written.
+///
 /// This method is not part of tantivy's public API
-fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()> {
+pub(crate) fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()> {
+    save_metas_with_sync(metas, directory, true)
+}
+
+/// Save the index meta file, only synchronizing the directory afterwards, which makes the new
+/// `meta.json` durable, if `sync_metas` is true.
+///
+/// The directory is always synchronized before `meta.json` is written, so that it never points
+/// to files lost by a crash.
+fn save_metas_with_sync(
+    metas: &IndexMeta,
+    directory: &dyn Directory,
+    sync_metas: bool,
+) -> crate::Result<()> {
     info!("save metas");
     let mut buffer = serde_json::to_vec_pretty(metas)?;
     // Just adding a new line at the end of the buffer.
@@ -79,7 +97,14 @@ fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()>
         )
     )));
     directory.sync_directory()?;
-    directory.atomic_write(&META_FILEPATH, &buffer[..])?;
+    {
+        // The garbage collector lists the generations of the metas under the meta lock.
+        let _meta_lock = directory.acquire_lock(&META_LOCK)?;
+        directory.atomic_write_metas(&buffer[..])?;
+    }
+    if sync_metas {
+        directory.sync_directory()?;
+    }
     debug!("Saved metas {:?}", serde_json::to_string_pretty(&metas));
     Ok(())
 }
@@ -108,19 +133,63 @@ fn garbage_collect_files(
     segment_updater: SegmentUpdater,
 ) -> crate::Result<GarbageCollectionResult> {
     info!("Running garbage collection");
+    segment_updater.writer_fence.check()?;
     let mut index = segment_updater.index.clone();
-    index
-        .directory_mut()
-        .garbage_collect(move || segment_updater.list_files())
+    let directory = index.directory().box_clone();
+    index.directory_mut().try_garbage_collect(move || {
+        let mut files = segment_updater.list_files();
+        // A generation pointer which cannot be read aborts the collection, rather than letting
+        // it delete the generations of the metas.
+        files.extend(list_meta_generation_files(directory.as_ref())?);
+        Ok(files)
+    })
+}
+
+/// Returns the attributes shared by all of the segments, with the same value.
+///
+/// These are the attributes of the segment merging them.
+fn common_attributes<'a>(
+    mut segment_metas: impl Iterator<Item = &'a SegmentMeta>,
+) -> BTreeMap<String, String> {
+    let mut attributes = segment_metas
+        .next()
+        .map(|segment_meta| segment_meta.attributes().clone())
+        .unwrap_or_default();
+    for segment_meta in segment_metas {
+        attributes.retain(|key, value| segment_meta.attributes().get(key) == Some(value));
+    }
+    attributes
+}
+
+/// Returns the highest opstamp of the documents of the segments.
+///
+/// This is the max opstamp of the segment merging them.
+pub(crate) fn merged_max_opstamp<'a>(
+    segment_metas: impl Iterator<Item = &'a SegmentMeta>,
+) -> Option<Opstamp> {
+    segment_metas.filter_map(SegmentMeta::max_opstamp).max()
 }
 
 /// Merges a list of segments the list of segment givens in the `segment_entries`.
 /// This function happens in the calling thread and is computationally expensive.
+///
+/// The merge stops early, returning an error, if the merge operation gets cancelled.
 fn merge(
     index: &Index,
     mut segment_entries: Vec<SegmentEntry>,
-    target_opstamp: Opstamp,
+    merge_operation: &MergeOperation,
+    docstore_stack_on_merge: bool,
 ) -> crate::Result<SegmentEntry> {
+    let target_opstamp = merge_operation.target_opstamp();
+    let _span = enter_span!(
+        "merge",
+        segment_ids = ?merge_operation.segment_ids(),
+        target_opstamp,
+        max_doc = segment_entries
+            .iter()
+            .map(|segment_entry| segment_entry.meta().max_doc())
+            .sum::<u32>(),
+    );
     // first we need to apply deletes to our segment.
     let merged_segment = index.new_segment();
 
@@ -138,8 +207,10 @@ fn merge(
         .collect();
 
     // An IndexMerger is like a "view" of our merged segments.
-    let merger: IndexMerger =
+    let mut merger: IndexMerger =
         IndexMerger::open(index.schema(), index.settings().clone(), &segments[..])?;
+    merger.set_merge_state(merge_operation.state());
+    merger.set_docstore_stack_on_merge(docstore_stack_on_merge);
 
     // ... we just serialize this index merger in our new segment to merge the segments.
     let segment_serializer = SegmentSerializer::for_segment(merged_segment.clone(), true)?;
@@ -148,7 +219,18 @@ fn merge(
 
     let merged_segment_id = merged_segment.id();
 
-    let segment_meta = index.new_segment_meta(merged_segment_id, num_docs);
+    let segment_meta = index
+        .new_segment_meta(merged_segment_id, num_docs)
+        .with_max_opstamp(merged_max_opstamp(
+            segment_entries
+                .iter()
+                .map(|segment_entry| segment_entry.meta()),
+        ))
+        .with_attributes(common_attributes(
+            segment_entries
+                .iter()
+                .map(|segment_entry| segment_entry.meta()),
+        ));
     Ok(SegmentEntry::new(segment_meta, delete_cursor, None))
 }
 
@@ -252,7 +334,14 @@ pub fn merge_filtered_segments<T: Into<Box<dyn Directory>>>(
     let segment_serializer = SegmentSerializer::for_segment(merged_segment, true)?;
     let num_docs = merger.write(segment_serializer)?;
 
-    let segment_meta = merged_index.new_segment_meta(merged_segment_id, num_docs);
+    let segment_meta = merged_index
+        .new_segment_meta(merged_segment_id, num_docs)
+        .with_max_opstamp(merged_max_opstamp(
+            segments.iter().map(|segment| segment.meta()),
+        ))
+        .with_attributes(common_attributes(
+            segments.iter().map(|segment| segment.meta()),
+        ));
 
     let stats = format!(
         "Segments Merge: [{}]",
@@ -295,8 +384,13 @@ pub(crate) struct InnerSegmentUpdater {
     segment_manager: SegmentManager,
     merge_policy: RwLock<Arc<dyn MergePolicy>>,
     killed: AtomicBool,
+    merges_paused: AtomicBool,
+    segment_attributes: RwLock<BTreeMap<String, String>>,
     stamper: Stamper,
     merge_operations: MergeOperationInventory,
+    fsync_scheduler: Mutex<FsyncScheduler>,
+    commit_hooks: RwLock<CommitHooks>,
+    writer_fence: WriterFence,
 }
 
 impl SegmentUpdater {
@@ -304,6 +398,7 @@ impl SegmentUpdater {
         index: Index,
         stamper: Stamper,
         delete_cursor: &DeleteCursor,
+        writer_fence: WriterFence,
     ) -> crate::Result<SegmentUpdater> {
         let segments = index.searchable_segment_metas()?;
         let segment_manager = SegmentManager::from_segments(segments, delete_cursor);
@@ -334,11 +429,22 @@ impl SegmentUpdater {
             segment_manager,
             merge_policy: RwLock::new(Arc::new(DefaultMergePolicy::default())),
             killed: AtomicBool::new(false),
+            merges_paused: AtomicBool::new(false),
+            segment_attributes: Default::default(),
             stamper,
             merge_operations: Default::default(),
+            fsync_scheduler: Default::default(),
+            commit_hooks: Default::default(),
+            writer_fence,
         })))
     }
 
+    /// Fails the commits, the publication of the merges and the garbage collection once the
+    /// writer lock was taken over by another writer.
+    pub(crate) fn writer_fence(&self) -> &WriterFence {
+        &self.writer_fence
+    }
+
     pub fn get_merge_policy(&self) -> Arc<dyn MergePolicy> {
         self.merge_policy.read().unwrap().clone()
     }
@@ -348,6 +454,14 @@ impl SegmentUpdater {
         *self.merge_policy.write().unwrap() = arc_merge_policy;
     }
 
+    pub fn segment_attributes(&self) -> BTreeMap<String, String> {
+        self.segment_attributes.read().unwrap().clone()
+    }
+
+    pub fn set_segment_attributes(&self, attributes: BTreeMap<String, String>) {
+        *self.segment_attributes.write().unwrap() = attributes;
+    }
+
     fn schedule_task<T: 'static + Send, F: FnOnce() -> crate::Result<T> + 'static + Send>(
         &self,
         task: F,
@@ -374,19 +488,40 @@ impl SegmentUpdater {
         })
     }
 
-    /// Orders `SegmentManager` to remove all segments
+    /// Orders `SegmentManager` to remove all segments, and cancels the running merges
+    /// as their result would be discarded.
     pub(crate) fn remove_all_segments(&self) {
+        self.merge_operations.cancel_all();
         self.segment_manager.remove_all_segments();
     }
 
+    /// Marks the segment updater as killed, and cancels the running merges.
     pub fn kill(&mut self) {
         self.killed.store(true, Ordering::Release);
+        self.merge_operations.cancel_all();
     }
 
     pub fn is_alive(&self) -> bool {
         !self.killed.load(Ordering::Acquire)
     }
 
+    /// Prevents the merge policy from starting new merges, until `resume_merges` is called.
+    ///
+    /// The running merges go on.
+    pub(crate) fn pause_merges(&self) {
+        self.merges_paused.store(true, Ordering::Release);
+    }
+
+    /// Lets the merge policy start merges again, and considers the merge candidates.
+    pub(crate) fn resume_merges(&self) -> FutureResult<()> {
+        self.merges_paused.store(false, Ordering::Release);
+        let segment_updater = self.clone();
+        self.schedule_task(move || {
+            segment_updater.consider_merge_options();
+            Ok(())
+        })
+    }
+
     /// Apply deletes up to the target opstamp to all segments.
     ///
     /// The method returns copies of the segment entries,
@@ -406,6 +541,7 @@ impl SegmentUpdater {
         commit_message: Option<String>,
     ) -> crate::Result<()> {
         if self.is_alive() {
+            self.writer_fence.check()?;
             let index = &self.index;
             let directory = index.directory();
             let mut commited_segment_metas = self.segment_manager.committed_segment_metas();
@@ -423,7 +559,10 @@ impl SegmentUpdater {
             // from the different drives.
             //
             // Segment 1 from disk 1, Segment 1 from disk 2, etc.
-            commited_segment_metas.sort_by_key(|segment_meta| -(segment_meta.max_doc() as i32));
+            // Ties are broken by segment id, so that `meta.json` does not depend on the order of
+            // the segment register.
+            commited_segment_metas
+                .sort_by_key(|segment_meta| (-(segment_meta.max_doc() as i32), segment_meta.id()));
             let index_meta = IndexMeta {
                 index_settings: index.settings().clone(),
                 segments: commited_segment_metas,
@@ -431,9 +570,12 @@ impl SegmentUpdater {
                 opstamp,
                 payload: commit_message,
             };
+            let sync_metas = self.fsync_scheduler().should_sync();
             // TODO add context to the error.
-            save_metas(&index_meta, directory.box_clone().borrow_mut())?;
+            save_metas_with_sync(&index_meta, directory.box_clone().borrow_mut(), sync_metas)?;
+            index.retain_generation(&index_meta);
             self.store_meta(&index_meta);
+            self.commit_hooks().post_publish(&index_meta);
         }
         Ok(())
     }
@@ -465,6 +607,11 @@ impl SegmentUpdater {
     ) -> FutureResult<Opstamp> {
         let segment_updater: SegmentUpdater = self.clone();
         self.schedule_task(move || {
+            let _span = enter_span!("publish_commit", opstamp);
+            segment_updater.writer_fence.check()?;
+            segment_updater
+                .commit_hooks()
+                .pre_commit(opstamp, payload.as_deref())?;
             let segment_entries = segment_updater.purge_deletes(opstamp)?;
             segment_updater.segment_manager.commit(segment_entries);
             segment_updater.save_metas(opstamp, payload)?;
@@ -521,28 +668,36 @@ impl SegmentUpdater {
                     "Starting the merge failed for the following reason. This is not fatal. {}",
                     err
                 );
+                merge_operation.state().finish(Err(err.clone()));
                 return err.into();
             }
         };
 
         info!("Starting merge  - {:?}", merge_operation.segment_ids());
+        let docstore_stack_on_merge = self.get_merge_policy().docstore_stack_on_merge();
 
         let (scheduled_result, merging_future_send) =
             FutureResult::create("Merge operation failed.");
 
+        // Fails the merge if the merging thread panics or never runs.
+        let outcome_guard = MergeOutcomeGuard::new(merge_operation.state());
         self.merge_thread_pool.spawn(move || {
+            let _outcome_guard = outcome_guard;
             // The fact that `merge_operation` is moved here is important.
             // Its lifetime is used to track how many merging thread are currently running,
             // as well as which segment is currently in merge and therefore should not be
             // candidate for another merge.
+            let merge_state = merge_operation.state();
             match merge(
                 &segment_updater.index,
                 segment_entries,
-                merge_operation.target_opstamp(),
+                &merge_operation,
+                docstore_stack_on_merge,
             ) {
                 Ok(after_merge_segment_entry) => {
                     let segment_meta_res =
                         segment_updater.end_merge(merge_operation, after_merge_segment_entry);
+                    merge_state.finish(segment_meta_res.clone());
                     let _send_result = merging_future_send.send(segment_meta_res);
                 }
                 Err(merge_error) => {
@@ -551,8 +706,12 @@ impl SegmentUpdater {
                         merge_operation.segment_ids().to_vec(),
                         merge_error
                     );
+                    let is_cancelled = merge_operation.is_cancelled();
+                    // The segments are released before the waiters get notified.
+                    drop(merge_operation);
+                    merge_state.finish(Err(merge_error.clone()));
                     let _send_result = merging_future_send.send(Err(merge_error));
-                    assert!(!cfg!(test), "Merge failed.");
+                    assert!(is_cancelled || !cfg!(test), "Merge failed.");
                 }
             }
         });
@@ -560,6 +719,36 @@ impl SegmentUpdater {
         scheduled_result
     }
 
+    /// Returns a handle over each of the merge operations that are currently
+    /// running or about to be started.
+    pub(crate) fn running_merges(&self) -> Vec<RunningMerge> {
+        self.merge_operations.running_merges()
+    }
+
+    pub(crate) fn fsync_scheduler(&self) -> MutexGuard<'_, FsyncScheduler> {
+        self.fsync_scheduler
+            .lock()
+            .expect("Fsync scheduler lock poisoned. This should never happen.")
+    }
+
+    /// Returns a copy of the commit hooks, so that the lock is not held while they run.
+    fn commit_hooks(&self) -> CommitHooks {
+        self.commit_hooks
+            .read()
+            .expect("Commit hooks lock poisoned. This should never happen.")
+            .clone()
+    }
+
+    pub(crate) fn commit_hooks_mut(&self) -> RwLockWriteGuard<'_, CommitHooks> {
+        self.commit_hooks
+            .write()
+            .expect("Commit hooks lock poisoned. This should never happen.")
+    }
+
+    pub(crate) fn live_doc_stats(&self) -> LiveDocStats {
+        self.segment_manager.live_doc_stats()
+    }
+
     pub(crate) fn get_mergeable_segments(&self) -> (Vec<SegmentMeta>, Vec<SegmentMeta>) {
         let merge_segment_ids: HashSet<SegmentId> = self.merge_operations.segment_in_merge();
         self.segment_manager
@@ -567,6 +756,9 @@ impl SegmentUpdater {
     }
 
     fn consider_merge_options(&self) {
+        if self.merges_paused.load(Ordering::Acquire) {
+            return;
+        }
         let (committed_segments, uncommitted_segments) = self.get_mergeable_segments();
 
         // Committed segments cannot be merged with uncommitted_segments.
@@ -677,16 +869,21 @@ impl SegmentUpdater {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
     use super::merge_indices;
     use crate::collector::TopDocs;
     use crate::directory::RamDirectory;
     use crate::fastfield::AliveBitSet;
+    use crate::indexer::merge_operation::MergeState;
     use crate::indexer::merge_policy::tests::MergeWheneverPossible;
     use crate::indexer::merger::IndexMerger;
     use crate::indexer::segment_updater::merge_filtered_segments;
+    use crate::indexer::{NoMergePolicy, SegmentSerializer};
     use crate::query::QueryParser;
     use crate::schema::*;
-    use crate::{Directory, DocAddress, Index, Segment};
+    use crate::{Directory, DocAddress, Index, Segment, TantivyError};
 
     #[test]
     fn test_delete_during_merge() -> crate::Result<()> {
@@ -849,6 +1046,125 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cancelled_merge() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field=>"a"))?;
+            index_writer.commit()?;
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+
+        let segment_updater = index_writer.segment_updater();
+        let merge_operation = segment_updater.make_merge_operation(&segment_ids);
+        assert!(!merge_operation.is_cancelled());
+        segment_updater.merge_operations.cancel_all();
+        assert!(merge_operation.is_cancelled());
+        assert!(matches!(
+            segment_updater.start_merge(merge_operation).wait(),
+            Err(TantivyError::Cancelled(_))
+        ));
+        segment_updater.wait_merging_thread()?;
+        assert!(segment_updater
+            .merge_operations
+            .segment_in_merge()
+            .is_empty());
+        assert_eq!(index.searchable_segment_ids()?.len(), 3);
+
+        // The segments can still be merged.
+        index_writer.merge(&segment_ids).wait()?;
+        assert_eq!(index.searchable_segment_ids()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_merger_cancel_flag() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field=>"a"))?;
+            index_writer.commit()?;
+        }
+        let segments = index.searchable_segments()?;
+        let mut merger = IndexMerger::open(index.schema(), index.settings().clone(), &segments)?;
+        let merge_state = Arc::new(MergeState::default());
+        merge_state.cancel();
+        merger.set_merge_state(merge_state);
+        let segment_serializer = SegmentSerializer::for_segment(index.new_segment(), true)?;
+        assert!(matches!(
+            merger.write(segment_serializer),
+            Err(TantivyError::Cancelled(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_running_merges() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field=>"a"))?;
+            index_writer.commit()?;
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        assert!(index_writer.running_merges().is_empty());
+
+        let segment_updater = index_writer.segment_updater();
+        let merge_operation = segment_updater.make_merge_operation(&segment_ids[..2]);
+        let running_merges = index_writer.running_merges();
+        assert_eq!(running_merges.len(), 1);
+        let running_merge = &running_merges[0];
+        assert_eq!(running_merge.segment_ids(), &segment_ids[..2]);
+        assert!(running_merge.elapsed().is_none());
+        assert!(running_merge.eta().is_none());
+        assert!(!running_merge.is_finished());
+
+        segment_updater.start_merge(merge_operation);
+        let segment_meta = running_merge.wait()?;
+        assert_eq!(segment_meta.num_docs(), 2);
+        assert!(running_merge.is_finished());
+        assert_eq!(running_merge.num_docs(), 2);
+        assert_eq!(running_merge.num_docs_to_process(), 8);
+        assert_eq!(running_merge.num_docs_processed(), 8);
+        assert_eq!(running_merge.eta(), Some(Duration::ZERO));
+        assert!(index_writer.running_merges().is_empty());
+
+        // Cancelling a specific merge.
+        let segment_ids = index.searchable_segment_ids()?;
+        let merge_operation = segment_updater.make_merge_operation(&segment_ids);
+        let running_merge = index_writer.running_merges().pop().unwrap();
+        running_merge.cancel();
+        assert!(merge_operation.is_cancelled());
+        segment_updater.start_merge(merge_operation);
+        assert!(matches!(
+            running_merge.wait(),
+            Err(TantivyError::Cancelled(_))
+        ));
+        assert!(index_writer.running_merges().is_empty());
+        assert_eq!(index.searchable_segment_ids()?.len(), 2);
+
+        // Dropping a merge operation before it starts fails its waiters.
+        let segment_ids = index.searchable_segment_ids()?;
+        let merge_operation = segment_updater.make_merge_operation(&segment_ids);
+        let running_merge = index_writer.running_merges().pop().unwrap();
+        drop(merge_operation);
+        assert!(matches!(
+            running_merge.wait(),
+            Err(TantivyError::ErrorInThread(_))
+        ));
+        assert!(running_merge.is_finished());
+        Ok(())
+    }
+
     #[test]
     fn test_merge_segments() -> crate::Result<()> {
         let mut indices = vec![];
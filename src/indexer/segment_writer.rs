@@ -9,10 +9,10 @@ use crate::postings::{
     compute_table_size, serialize_postings, IndexingContext, IndexingPosition,
     PerFieldPostingsWriter, PostingsWriter,
 };
-use crate::schema::{FieldEntry, FieldType, FieldValue, Schema, Term, Value};
+use crate::schema::{Field, FieldEntry, FieldType, FieldValue, Schema, Term, Value};
 use crate::store::{StoreReader, StoreWriter};
 use crate::tokenizer::{
-    BoxTokenStream, FacetTokenizer, PreTokenizedStream, TextAnalyzer, Tokenizer,
+    BoxTokenStream, FacetTokenizer, PreTokenizedStream, TextAnalyzer, Tokenizer, TokenizerManager,
 };
 use crate::{DocId, Document, Opstamp, SegmentComponent};
 
@@ -64,7 +64,7 @@ pub struct SegmentWriter {
     pub(crate) doc_opstamps: Vec<Opstamp>,
     per_field_text_analyzers: Vec<TextAnalyzer>,
     term_buffer: Term,
-    schema: Schema,
+    pub(crate) schema: Schema,
 }
 
 impl SegmentWriter {
@@ -85,6 +85,27 @@ impl SegmentWriter {
         let tokenizer_manager = segment.index().tokenizers().clone();
         let table_size = compute_initial_table_size(memory_budget_in_bytes)?;
         let segment_serializer = SegmentSerializer::for_segment(segment, false)?;
+        SegmentWriter::new(table_size, segment_serializer, schema, &tokenizer_manager)
+    }
+
+    /// Creates a `SegmentWriter` for the in-memory segment of a
+    /// [`MemoryIndex`](crate::MemoryIndex), tokenizing the text with `tokenizer_manager`.
+    pub(crate) fn in_memory(
+        memory_budget_in_bytes: usize,
+        segment_serializer: SegmentSerializer,
+        schema: Schema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> crate::Result<SegmentWriter> {
+        let table_size = compute_initial_table_size(memory_budget_in_bytes)?;
+        SegmentWriter::new(table_size, segment_serializer, schema, tokenizer_manager)
+    }
+
+    fn new(
+        table_size: usize,
+        segment_serializer: SegmentSerializer,
+        schema: Schema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> crate::Result<SegmentWriter> {
         let per_field_postings_writers = PerFieldPostingsWriter::for_schema(&schema);
         let per_field_text_analyzers = schema
             .fields()
@@ -126,8 +147,6 @@ impl SegmentWriter {
         self.fieldnorms_writer.fill_up_to_max_doc(self.max_doc);
         let mapping: Option<DocIdMapping> = self
             .segment_serializer
-            .segment()
-            .index()
             .settings()
             .sort_by_field
             .clone()
@@ -303,7 +322,8 @@ impl SegmentWriter {
     ///
     /// As a user, you should rather use `IndexWriter`'s add_document.
     pub fn add_document(&mut self, add_operation: AddOperation) -> crate::Result<()> {
-        let doc = add_operation.document;
+        let mut doc = add_operation.document;
+        copy_field_values(&mut doc, &self.schema);
         self.doc_opstamps.push(add_operation.opstamp);
         self.fast_field_writers.add_document(&doc);
         self.index_document(&doc)?;
@@ -324,6 +344,11 @@ impl SegmentWriter {
         self.max_doc
     }
 
+    /// Returns the schema of the segment.
+    pub(crate) fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     /// Number of documents in the index.
     /// Deleted documents are not counted.
     ///
@@ -335,6 +360,23 @@ impl SegmentWriter {
     }
 }
 
+/// Adds the values of the fields that are copied to other fields to these fields.
+fn copy_field_values(doc: &mut Document, schema: &Schema) {
+    let copied_values: Vec<(Field, Value)> = doc
+        .field_values()
+        .iter()
+        .flat_map(|field_value| {
+            schema
+                .copy_to_fields(field_value.field())
+                .iter()
+                .map(move |&target| (target, field_value.value().clone()))
+        })
+        .collect();
+    for (target, value) in copied_values {
+        doc.add_field_value(target, value);
+    }
+}
+
 /// This method is used as a trick to workaround the borrow checker
 /// Writes a view of a segment by pushing information
 /// to the `SegmentSerializer`.
@@ -353,9 +395,7 @@ fn remap_and_write(
     if let Some(fieldnorms_serializer) = serializer.extract_fieldnorms_serializer() {
         fieldnorms_writer.serialize(fieldnorms_serializer, doc_id_map)?;
     }
-    let fieldnorm_data = serializer
-        .segment()
-        .open_read(SegmentComponent::FieldNorms)?;
+    let fieldnorm_data = serializer.open_read(SegmentComponent::FieldNorms)?;
     let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
     let term_ord_map = serialize_postings(
         ctx,
@@ -375,22 +415,15 @@ fn remap_and_write(
     debug!("resort-docstore");
     // finalize temp docstore and create version, which reflects the doc_id_map
     if let Some(doc_id_map) = doc_id_map {
-        let store_write = serializer
-            .segment_mut()
-            .open_write(SegmentComponent::Store)?;
-        let compressor = serializer.segment().index().settings().docstore_compression;
-        let block_size = serializer.segment().index().settings().docstore_blocksize;
+        let store_write = serializer.open_write(SegmentComponent::Store)?;
+        let compressor = serializer.settings().docstore_compression;
+        let block_size = serializer.settings().docstore_blocksize;
         let old_store_writer = std::mem::replace(
             &mut serializer.store_writer,
             StoreWriter::new(store_write, compressor, block_size)?,
         );
         old_store_writer.close()?;
-        let store_read = StoreReader::open(
-            serializer
-                .segment()
-                .open_read(SegmentComponent::TempStore)?,
-            50,
-        )?;
+        let store_read = StoreReader::open(serializer.open_read(SegmentComponent::TempStore)?, 50)?;
         for old_doc_id in doc_id_map.iter_old_doc_ids() {
             let doc_bytes = store_read.get_document_bytes(old_doc_id)?;
             serializer.get_store_writer().store_bytes(&doc_bytes)?;
@@ -431,7 +464,7 @@ mod tests {
     use crate::collector::Count;
     use crate::indexer::json_term_writer::JsonTermWriter;
     use crate::postings::TermInfo;
-    use crate::query::PhraseQuery;
+    use crate::query::{PhraseQuery, TermQuery};
     use crate::schema::{IndexRecordOption, Schema, Type, STORED, STRING, TEXT};
     use crate::time::format_description::well_known::Rfc3339;
     use crate::time::OffsetDateTime;
@@ -461,6 +494,7 @@ mod tests {
                 position: 0,
                 text: String::from("A"),
                 position_length: 1,
+                payload: 0,
             }],
         };
 
@@ -476,6 +510,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_copy_to() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let title_raw = schema_builder.add_text_field("title_raw", STRING);
+        schema_builder.copy_to(title, title_raw);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(title => "Hello World", title => "Tantivy"))?;
+        writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |term: Term| {
+            let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+            searcher.search(&term_query, &Count).unwrap()
+        };
+        assert_eq!(count(Term::from_field_text(title, "hello")), 1);
+        assert_eq!(count(Term::from_field_text(title_raw, "Hello World")), 1);
+        assert_eq!(count(Term::from_field_text(title_raw, "Tantivy")), 1);
+        assert_eq!(count(Term::from_field_text(title_raw, "hello")), 0);
+        // The copies are not stored, as `title_raw` is not stored.
+        let doc = searcher.doc(DocAddress::new(0, 0))?;
+        assert_eq!(doc.field_values().len(), 2);
+        Ok(())
+    }
+
     #[test]
     fn test_json_indexing() {
         let mut schema_builder = Schema::builder();
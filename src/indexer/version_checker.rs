@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::fastfield::{type_and_cardinality, FastFieldReader, FastType, FastValue};
+use crate::schema::{
+    Cardinality, Document, Field, FieldEntry, IndexRecordOption, Schema, Term, Value,
+};
+use crate::{
+    DocId, DocSet, Index, IndexReader, Opstamp, ReloadPolicy, SegmentReader, TantivyError,
+    TERMINATED,
+};
+
+/// A change made by an operation of the index writer to the documents with a primary key.
+pub(crate) enum Change {
+    /// A document with the key was added, with the values of its version fields.
+    Add(Term, Vec<(Field, u64)>),
+    /// The documents with the key were deleted.
+    Delete(Term),
+    /// The documents with a term of another field than the primary key were deleted.
+    DeleteByOtherTerm(Term),
+}
+
+impl Change {
+    /// Returns the change made by adding `document`, if the schema has a primary key and the
+    /// document has a value for it.
+    pub fn add(schema: &Schema, document: &Document) -> Option<Change> {
+        let key_field = schema.primary_key_field()?;
+        let key = Term::from_field_value(key_field, document.get_first(key_field)?)?;
+        let mut versions: Vec<(Field, u64)> = Vec::new();
+        for field_value in document.field_values() {
+            let field = field_value.field();
+            let version = match field_value.value() {
+                Value::U64(val) => val.to_u64(),
+                Value::I64(val) => val.to_u64(),
+                _ => continue,
+            };
+            let is_first_value = versions
+                .iter()
+                .all(|&(other_field, _)| other_field != field);
+            if is_first_value && is_version_field(schema.get_field_entry(field)) {
+                versions.push((field, version));
+            }
+        }
+        Some(Change::Add(key, versions))
+    }
+
+    /// Returns the change made by deleting the documents with the term `term`, if the schema
+    /// has a primary key.
+    pub fn delete(schema: &Schema, term: Term) -> Option<Change> {
+        if schema.primary_key_field()? == term.field() {
+            Some(Change::Delete(term))
+        } else {
+            Some(Change::DeleteByOtherTerm(term))
+        }
+    }
+}
+
+/// Returns true iff the values of the field can be used as versions by
+/// `IndexWriter::update_document_if`, that is if it is a single-valued u64 or i64 fast field.
+pub(crate) fn is_version_field(field_entry: &FieldEntry) -> bool {
+    matches!(
+        type_and_cardinality(field_entry.field_type()),
+        Some((FastType::U64 | FastType::I64, Cardinality::SingleValue))
+    )
+}
+
+/// Current state of a document changed since the last published commit.
+enum ChangedDocument {
+    /// The document was deleted.
+    Deleted,
+    /// The document was added, with the values of its version fields.
+    Added(Vec<(Field, u64)>),
+    /// The document was added, and may have been deleted by a term of another field since.
+    Unknown,
+}
+
+/// The changes made between two commits.
+#[derive(Default)]
+struct Changes {
+    // Whether the documents of the previous commits were all deleted.
+    all_deleted: bool,
+    documents: HashMap<Term, ChangedDocument>,
+    // Terms of other fields than the primary key the documents were deleted with.
+    deleted_terms: Vec<Term>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        !self.all_deleted && self.documents.is_empty() && self.deleted_terms.is_empty()
+    }
+}
+
+/// Checks the version of the documents updated by `IndexWriter::update_document_if`.
+///
+/// The current version of a document is looked up among the changes made by the operations
+/// of the writer which are not published yet, and then in the last published commit. The
+/// changes are kept until the commit they belong to is published, as the commit may still be
+/// running when the next operations are checked.
+pub(crate) struct VersionChecker {
+    index: Index,
+    // Reader over the last published commit, opened upon the first check.
+    reader_opt: Option<IndexReader>,
+    reader_is_stale: bool,
+    // Opstamp of the last published commit, set by the post-publish hook of the writer.
+    published_opstamp: Arc<AtomicU64>,
+    // Changes of the prepared commits which were not published yet, with their opstamp.
+    committing: Vec<(Opstamp, Changes)>,
+    // Changes made since the last prepared commit.
+    pending: Changes,
+}
+
+impl VersionChecker {
+    pub fn new(index: Index, published_opstamp: Arc<AtomicU64>) -> VersionChecker {
+        VersionChecker {
+            index,
+            reader_opt: None,
+            reader_is_stale: false,
+            published_opstamp,
+            committing: Vec::new(),
+            pending: Changes::default(),
+        }
+    }
+
+    /// Returns true iff the current document with the key `key` has the version `version`
+    /// in the field `version_field`.
+    ///
+    /// A document added since the last published commit, and which may have been deleted
+    /// since by a term of another field than the primary key, is considered to have no version.
+    pub fn has_version(
+        &mut self,
+        key: &Term,
+        version_field: Field,
+        version: u64,
+    ) -> crate::Result<bool> {
+        self.forget_published_changes();
+        let changes_newest_first =
+            std::iter::once(&self.pending).chain(self.committing.iter().rev().map(|(_, c)| c));
+        for changes in changes_newest_first {
+            match changes.documents.get(key) {
+                Some(ChangedDocument::Added(versions)) => {
+                    return Ok(versions.contains(&(version_field, version)));
+                }
+                Some(ChangedDocument::Deleted | ChangedDocument::Unknown) => return Ok(false),
+                None => {}
+            }
+            if changes.all_deleted {
+                return Ok(false);
+            }
+        }
+        let deleted_terms: Vec<&Term> = std::iter::once(&self.pending)
+            .chain(self.committing.iter().map(|(_, changes)| changes))
+            .flat_map(|changes| changes.deleted_terms.iter())
+            .collect();
+        let reader = match &mut self.reader_opt {
+            Some(reader) => {
+                if self.reader_is_stale {
+                    reader.reload()?;
+                }
+                reader
+            }
+            reader_opt @ None => reader_opt.insert(
+                self.index
+                    .reader_builder()
+                    .reload_policy(ReloadPolicy::Manual)
+                    .num_searchers(1)
+                    .try_into()?,
+            ),
+        };
+        self.reader_is_stale = false;
+        let searcher = reader.searcher();
+        for segment_reader in searcher.segment_readers() {
+            if let Some(doc) = segment_find_doc(segment_reader, key, &deleted_terms)? {
+                let fast_field_reader = segment_reader.fast_fields().u64_lenient(version_field)?;
+                return Ok(fast_field_reader.get(doc) == version);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Records the changes made by operations of the writer.
+    pub fn record(&mut self, changes: impl IntoIterator<Item = Change>) {
+        for change in changes {
+            match change {
+                Change::Add(key, versions) => {
+                    let document = ChangedDocument::Added(versions);
+                    self.pending.documents.insert(key, document);
+                }
+                Change::Delete(key) => {
+                    self.pending.documents.insert(key, ChangedDocument::Deleted);
+                }
+                Change::DeleteByOtherTerm(term) => {
+                    let all_changes = std::iter::once(&mut self.pending)
+                        .chain(self.committing.iter_mut().map(|(_, changes)| changes));
+                    for changes in all_changes {
+                        for document in changes.documents.values_mut() {
+                            if let ChangedDocument::Added(_) = document {
+                                *document = ChangedDocument::Unknown;
+                            }
+                        }
+                    }
+                    self.pending.deleted_terms.push(term);
+                }
+            }
+        }
+    }
+
+    /// Records that the changes made so far belong to the commit of opstamp `commit_opstamp`.
+    pub fn prepare_commit(&mut self, commit_opstamp: Opstamp) {
+        if !self.pending.is_empty() {
+            let changes = mem::take(&mut self.pending);
+            self.committing.push((commit_opstamp, changes));
+        }
+    }
+
+    /// Records that all documents were deleted, forgetting the changes made since the last
+    /// prepared commit.
+    pub fn delete_all(&mut self) {
+        self.pending = Changes {
+            all_deleted: true,
+            ..Changes::default()
+        };
+    }
+
+    // Forgets the changes of the commits published since the last check, which the reader
+    // sees once reloaded.
+    fn forget_published_changes(&mut self) {
+        let published_opstamp = self.published_opstamp.load(Ordering::SeqCst);
+        let num_committing = self.committing.len();
+        self.committing
+            .retain(|&(commit_opstamp, _)| commit_opstamp > published_opstamp);
+        if self.committing.len() != num_committing {
+            self.reader_is_stale = true;
+        }
+    }
+}
+
+/// Checks the term of the version of `update_document_if`, returning the version.
+pub(crate) fn version_from_term(schema: &Schema, version: &Term) -> crate::Result<u64> {
+    let field_entry = schema.get_field_entry(version.field());
+    if !is_version_field(field_entry) || field_entry.field_type().value_type() != version.typ() {
+        return Err(TantivyError::SchemaError(format!(
+            "The version field {:?} is not a single-valued u64 or i64 fast field.",
+            field_entry.name()
+        )));
+    }
+    let version_bytes: [u8; 8] = version.value_bytes().try_into().map_err(|_| {
+        TantivyError::InvalidArgument(format!("Term {:?} is not a version.", version))
+    })?;
+    Ok(u64::from_be_bytes(version_bytes))
+}
+
+// Returns the alive document with the key `key` which is not deleted by one of the terms
+// `deleted_terms`, if any.
+fn segment_find_doc(
+    segment_reader: &SegmentReader,
+    key: &Term,
+    deleted_terms: &[&Term],
+) -> crate::Result<Option<DocId>> {
+    if !segment_reader.may_contain_primary_key(key) {
+        return Ok(None);
+    }
+    let mut key_postings = match segment_reader
+        .inverted_index(key.field())?
+        .read_postings(key, IndexRecordOption::Basic)?
+    {
+        Some(key_postings) => key_postings,
+        None => return Ok(None),
+    };
+    let mut doc = key_postings.doc();
+    while doc != TERMINATED {
+        if !segment_reader.is_deleted(doc)
+            && !is_deleted_by_terms(segment_reader, doc, deleted_terms)?
+        {
+            return Ok(Some(doc));
+        }
+        doc = key_postings.advance();
+    }
+    Ok(None)
+}
+
+fn is_deleted_by_terms(
+    segment_reader: &SegmentReader,
+    doc: DocId,
+    deleted_terms: &[&Term],
+) -> crate::Result<bool> {
+    for &deleted_term in deleted_terms {
+        let postings_opt = segment_reader
+            .inverted_index(deleted_term.field())?
+            .read_postings(deleted_term, IndexRecordOption::Basic)?;
+        if let Some(mut postings) = postings_opt {
+            if postings.seek(doc) == doc {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
@@ -232,6 +232,7 @@ pub mod tokenizer;
 
 pub mod aggregation;
 pub mod collector;
+pub mod columnar;
 pub mod directory;
 pub mod fastfield;
 pub mod fieldnorm;
@@ -245,7 +246,9 @@ pub mod termdict;
 
 mod reader;
 
-pub use self::reader::{IndexReader, IndexReaderBuilder, ReloadPolicy, Warmer};
+pub use self::reader::{
+    FreshnessPolicy, IndexReader, IndexReaderBuilder, ReaderMemoryUsage, ReloadPolicy, Warmer,
+};
 mod snippet;
 pub use self::snippet::{Snippet, SnippetGenerator};
 
@@ -257,22 +260,37 @@ pub use common::{f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64, HasLen};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
-pub use self::docset::{DocSet, TERMINATED};
+pub use self::docset::{DocSet, COLLECT_BLOCK_BUFFER_LEN, TERMINATED};
 pub use crate::core::{
-    Executor, Index, IndexBuilder, IndexMeta, IndexSettings, IndexSortByField, InvertedIndexReader,
-    Order, Searcher, SearcherGeneration, Segment, SegmentComponent, SegmentId, SegmentMeta,
-    SegmentReader,
+    Executor, FieldStats, Index, IndexAliases, IndexBuilder, IndexMeta, IndexSettings,
+    IndexSortByField, InvertedIndexReader, Order, Searcher, SearcherGeneration, Segment,
+    SegmentComponent, SegmentId, SegmentMeta, SegmentReader, SlowQuery,
 };
 pub use crate::directory::Directory;
 pub use crate::indexer::demuxer::*;
 pub use crate::indexer::operation::UserOperation;
-pub use crate::indexer::{merge_filtered_segments, merge_indices, IndexWriter, PreparedCommit};
+pub use crate::indexer::{
+    merge_filtered_segments, merge_indices, BatchResult, DocCounts, DocumentError, DocumentFailure,
+    FlushTriggers, FsyncPolicy, GroupCommitWriter, IndexWriter, LiveDocStats, MemoryIndex,
+    PausedIndexWriter, PreparedCommit, RunningMerge, SegmentBulkBuilder,
+};
 pub use crate::postings::Postings;
 pub use crate::reader::LeasedItem;
 pub use crate::schema::{Document, Term};
 
 /// Index format version.
-const INDEX_FORMAT_VERSION: u32 = 4;
+const INDEX_FORMAT_VERSION: u32 = 5;
+
+/// Oldest index format version this version of tantivy can read.
+///
+/// When the format evolves, the readers of the formats between this version and
+/// `INDEX_FORMAT_VERSION` are kept, so that upgrading tantivy does not require to reindex.
+const OLDEST_SUPPORTED_INDEX_FORMAT_VERSION: u32 = 4;
+
+/// Returns true iff segments written with the given index format version can be read.
+pub(crate) fn is_supported_index_format_version(index_format_version: u32) -> bool {
+    (OLDEST_SUPPORTED_INDEX_FORMAT_VERSION..=INDEX_FORMAT_VERSION).contains(&index_format_version)
+}
 
 /// Structure version for the index.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -63,6 +63,28 @@ macro_rules! doc(
     };
 );
 
+/// Enters a `tracing` span at the info level, which is exited when the returned guard is
+/// dropped.
+///
+/// Without the `tracing` feature, the span is not created and its fields are not evaluated.
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($($args:tt)*) => {
+        tracing::info_span!($($args)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($args:tt)*) => {
+        $crate::macros::DisabledSpan
+    };
+}
+
+/// Guard returned by `enter_span!` without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct DisabledSpan;
+
 #[cfg(test)]
 mod test {
     use crate::schema::{Schema, FAST, TEXT};
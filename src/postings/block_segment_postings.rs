@@ -77,6 +77,23 @@ fn split_into_skips_and_postings(
     Ok((Some(skip_data), postings_data))
 }
 
+fn open_skip_reader(
+    doc_freq: u32,
+    skip_data_opt: Option<OwnedBytes>,
+    postings_data: &OwnedBytes,
+    record_option: IndexRecordOption,
+) -> SkipReader {
+    match skip_data_opt {
+        // Posting lists with at least one full block always have skip data,
+        // an empty one marks a posting list encoded as a bitmap.
+        Some(skip_data) if skip_data.is_empty() => {
+            SkipReader::for_bitmap(postings_data.clone(), doc_freq)
+        }
+        Some(skip_data) => SkipReader::new(skip_data, doc_freq, record_option),
+        None => SkipReader::new(OwnedBytes::empty(), doc_freq, record_option),
+    }
+}
+
 impl BlockSegmentPostings {
     pub(crate) fn open(
         doc_freq: u32,
@@ -92,10 +109,7 @@ impl BlockSegmentPostings {
 
         let bytes = data.read_bytes()?;
         let (skip_data_opt, postings_data) = split_into_skips_and_postings(doc_freq, bytes)?;
-        let skip_reader = match skip_data_opt {
-            Some(skip_data) => SkipReader::new(skip_data, doc_freq, record_option),
-            None => SkipReader::new(OwnedBytes::empty(), doc_freq, record_option),
-        };
+        let skip_reader = open_skip_reader(doc_freq, skip_data_opt, &postings_data, record_option);
 
         let mut block_segment_postings = BlockSegmentPostings {
             doc_decoder: BlockDecoder::with_val(TERMINATED),
@@ -167,14 +181,17 @@ impl BlockSegmentPostings {
     pub(crate) fn reset(&mut self, doc_freq: u32, postings_data: OwnedBytes) -> io::Result<()> {
         let (skip_data_opt, postings_data) =
             split_into_skips_and_postings(doc_freq, postings_data)?;
-        self.data = postings_data;
         self.block_max_score_cache = None;
         self.loaded_offset = std::usize::MAX;
-        if let Some(skip_data) = skip_data_opt {
-            self.skip_reader.reset(skip_data, doc_freq);
-        } else {
-            self.skip_reader.reset(OwnedBytes::empty(), doc_freq);
+        match skip_data_opt {
+            Some(skip_data) if skip_data.is_empty() => {
+                self.skip_reader
+                    .reset_bitmap(postings_data.clone(), doc_freq);
+            }
+            Some(skip_data) => self.skip_reader.reset(skip_data, doc_freq),
+            None => self.skip_reader.reset(OwnedBytes::empty(), doc_freq),
         }
+        self.data = postings_data;
         self.doc_freq = doc_freq;
         self.load_block();
         Ok(())
@@ -317,6 +334,14 @@ impl BlockSegmentPostings {
                     num_docs as usize,
                 );
             }
+            BlockInfo::Bitmap { num_docs } => {
+                self.doc_decoder.uncompress_bitmap(
+                    self.data.as_slice(),
+                    offset as DocId,
+                    num_docs as usize,
+                    TERMINATED,
+                );
+            }
         }
     }
 
@@ -353,7 +378,7 @@ mod tests {
     use crate::docset::{DocSet, TERMINATED};
     use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
     use crate::postings::postings::Postings;
-    use crate::postings::SegmentPostings;
+    use crate::postings::{BlockInfo, SegmentPostings};
     use crate::schema::{IndexRecordOption, Schema, Term, INDEXED};
     use crate::DocId;
 
@@ -418,7 +443,7 @@ mod tests {
         doc_ids.push(130);
         {
             let block_segments = build_block_postings(&doc_ids)?;
-            let mut docset = SegmentPostings::from_block_postings(block_segments, None);
+            let mut docset = SegmentPostings::from_block_postings(block_segments, None, false);
             assert_eq!(docset.seek(128), 129);
             assert_eq!(docset.doc(), 129);
             assert_eq!(docset.advance(), 130);
@@ -427,7 +452,7 @@ mod tests {
         }
         {
             let block_segments = build_block_postings(&doc_ids).unwrap();
-            let mut docset = SegmentPostings::from_block_postings(block_segments, None);
+            let mut docset = SegmentPostings::from_block_postings(block_segments, None, false);
             assert_eq!(docset.seek(129), 129);
             assert_eq!(docset.doc(), 129);
             assert_eq!(docset.advance(), 130);
@@ -436,7 +461,7 @@ mod tests {
         }
         {
             let block_segments = build_block_postings(&doc_ids)?;
-            let mut docset = SegmentPostings::from_block_postings(block_segments, None);
+            let mut docset = SegmentPostings::from_block_postings(block_segments, None, false);
             assert_eq!(docset.doc(), 0);
             assert_eq!(docset.seek(131), TERMINATED);
             assert_eq!(docset.doc(), TERMINATED);
@@ -469,6 +494,73 @@ mod tests {
         Ok(block_postings)
     }
 
+    #[test]
+    fn test_bitmap_block_segment_postings() -> crate::Result<()> {
+        let docs: Vec<DocId> = (0..1_000).filter(|doc| doc % 7 != 3).collect();
+        let mut block_postings = build_block_postings(&docs)?;
+        assert!(matches!(
+            block_postings.skip_reader.block_info(),
+            BlockInfo::Bitmap { .. }
+        ));
+        assert_eq!(block_postings.doc_freq() as usize, docs.len());
+        let mut decoded_docs = Vec::new();
+        while !block_postings.docs().is_empty() {
+            decoded_docs.extend_from_slice(block_postings.docs());
+            block_postings.advance();
+        }
+        assert_eq!(decoded_docs, docs);
+
+        for target in [0, 3, 424, 998, 999] {
+            let mut postings =
+                SegmentPostings::from_block_postings(build_block_postings(&docs)?, None, false);
+            let expected = docs.iter().cloned().find(|&doc| doc >= target).unwrap();
+            assert_eq!(postings.seek(target), expected);
+            let expected_tail: Vec<DocId> =
+                docs.iter().cloned().filter(|&doc| doc > expected).collect();
+            let mut tail = Vec::new();
+            while postings.advance() != TERMINATED {
+                tail.push(postings.doc());
+            }
+            assert_eq!(tail, expected_tail);
+        }
+        let mut postings =
+            SegmentPostings::from_block_postings(build_block_postings(&docs)?, None, false);
+        assert_eq!(postings.seek(1_000), TERMINATED);
+
+        // Sparse posting lists are still bitpacked.
+        let sparse_docs: Vec<DocId> = (0..1_000).map(|doc| doc * 10).collect();
+        let block_postings = build_block_postings(&sparse_docs)?;
+        assert!(matches!(
+            block_postings.skip_reader.block_info(),
+            BlockInfo::BitPacked { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_block_segment_postings_known_doc_freq() {
+        // The doc freq is known upfront when merging, and the bitmap of the sparse posting
+        // lists is dropped before it gets large.
+        let dense_docs: Vec<DocId> = (0..1_000).filter(|doc| doc % 7 != 3).collect();
+        let sparse_docs: Vec<DocId> = (0..1_000).map(|doc| doc * 1_000).collect();
+        for (docs, is_bitmap) in [(dense_docs, true), (sparse_docs, false)] {
+            let mut postings = SegmentPostings::create_from_docs(&docs);
+            assert_eq!(
+                matches!(
+                    postings.block_cursor.skip_reader.block_info(),
+                    BlockInfo::Bitmap { .. }
+                ),
+                is_bitmap
+            );
+            let mut decoded_docs = Vec::new();
+            while postings.doc() != TERMINATED {
+                decoded_docs.push(postings.doc());
+                postings.advance();
+            }
+            assert_eq!(decoded_docs, docs);
+        }
+    }
+
     #[test]
     fn test_block_segment_postings_seek() -> crate::Result<()> {
         let mut docs = vec![0];
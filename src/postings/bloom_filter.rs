@@ -0,0 +1,131 @@
+use std::hash::Hasher;
+use std::io::{self, Write};
+
+use common::BinarySerializable;
+use fnv::FnvHasher;
+use ownedbytes::OwnedBytes;
+
+// With 10 bits per key and 7 hash functions, about 1% of the absent keys are false positives.
+const NUM_BITS_PER_KEY: u64 = 10;
+const NUM_HASHES: u32 = 7;
+
+// The hash of the key is stable across versions, as it is persisted through the bits it sets.
+fn hash_key(key: &[u8]) -> (u64, u64) {
+    let mut hasher = FnvHasher::default();
+    hasher.write(key);
+    let hash = hasher.finish();
+    let h1 = u64::from(murmurhash32::murmurhash2(key));
+    // The step between the bits of the key is odd, hence never 0.
+    (h1, hash | 1)
+}
+
+fn bit_positions(key_hash: (u64, u64), num_bits: u64) -> impl Iterator<Item = u64> {
+    let (h1, h2) = key_hash;
+    (0..u64::from(NUM_HASHES)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+/// Collects the keys of a segment, and serializes the bloom filter of these keys.
+#[derive(Default)]
+pub(crate) struct BloomFilterBuilder {
+    key_hashes: Vec<(u64, u64)>,
+}
+
+impl BloomFilterBuilder {
+    pub fn insert(&mut self, key: &[u8]) {
+        self.key_hashes.push(hash_key(key));
+    }
+
+    /// Serializes the filter. Nothing is written if no key was inserted.
+    pub fn serialize<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        if self.key_hashes.is_empty() {
+            return Ok(());
+        }
+        let num_words = (self.key_hashes.len() as u64 * NUM_BITS_PER_KEY).div_ceil(64);
+        let num_bits = num_words * 64;
+        let mut words = vec![0u64; num_words as usize];
+        for &key_hash in &self.key_hashes {
+            for bit in bit_positions(key_hash, num_bits) {
+                words[(bit / 64) as usize] |= 1u64 << (bit % 64);
+            }
+        }
+        num_bits.serialize(write)?;
+        for word in words {
+            word.serialize(write)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bloom filter of the keys of a segment.
+///
+/// [`BloomFilter::may_contain`] never returns false for a key of the segment.
+#[derive(Clone)]
+pub(crate) struct BloomFilter {
+    num_bits: u64,
+    words: OwnedBytes,
+}
+
+impl BloomFilter {
+    /// Opens a filter serialized by [`BloomFilterBuilder::serialize`], or returns `None` if
+    /// there was no key to filter.
+    pub fn open(mut data: OwnedBytes) -> io::Result<Option<BloomFilter>> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let num_bits = u64::deserialize(&mut data)?;
+        if num_bits == 0 || data.len() as u64 * 8 != num_bits {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Bloom filter size does not match its number of bits.",
+            ));
+        }
+        Ok(Some(BloomFilter {
+            num_bits,
+            words: data,
+        }))
+    }
+
+    /// Returns false if the key is definitely not in the segment.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        bit_positions(hash_key(key), self.num_bits).all(|bit| {
+            let word_offset = (bit / 64) as usize * 8;
+            let mut word_bytes = &self.words.as_slice()[word_offset..word_offset + 8];
+            let word = u64::deserialize(&mut word_bytes).unwrap_or(0);
+            word & (1u64 << (bit % 64)) != 0
+        })
+    }
+
+    /// Number of bytes used by the filter.
+    pub fn num_bytes(&self) -> usize {
+        self.words.len() + 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ownedbytes::OwnedBytes;
+
+    use super::{BloomFilter, BloomFilterBuilder};
+
+    #[test]
+    fn test_bloom_filter() -> std::io::Result<()> {
+        let mut builder = BloomFilterBuilder::default();
+        let mut empty_data = Vec::new();
+        builder.serialize(&mut empty_data)?;
+        assert!(BloomFilter::open(OwnedBytes::new(empty_data))?.is_none());
+
+        for key in 0u64..1_000 {
+            builder.insert(&key.to_be_bytes());
+        }
+        let mut data = Vec::new();
+        builder.serialize(&mut data)?;
+        let bloom_filter = BloomFilter::open(OwnedBytes::new(data))?.unwrap();
+        assert!((0u64..1_000).all(|key| bloom_filter.may_contain(&key.to_be_bytes())));
+        let num_false_positives = (1_000u64..11_000)
+            .filter(|key| bloom_filter.may_contain(&key.to_be_bytes()))
+            .count();
+        assert!(num_false_positives < 300);
+        assert!(BloomFilter::open(OwnedBytes::new(vec![0u8; 12])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Bitmap encoding of posting lists.
+//!
+//! The bitmap is a sequence of little endian `u64` words, the bit `doc % 64` of the
+//! word `doc / 64` being set iff the document belongs to the posting list.
+//! It stops at the word containing the last document.
+
+const NUM_BITS_PER_WORD: usize = 64;
+const NUM_BYTES_PER_WORD: usize = 8;
+
+/// Returns the number of bytes of the bitmap of a posting list, given its last document.
+pub fn bitmap_num_bytes(last_doc: u32) -> usize {
+    (last_doc as usize / NUM_BITS_PER_WORD + 1) * NUM_BYTES_PER_WORD
+}
+
+/// Appends the bitmap of the sorted documents `docs` to `output`.
+#[cfg(test)]
+pub fn compress_bitmap(docs: &[u32], output: &mut Vec<u8>) {
+    let mut bitmap_writer = BitmapWriter::default();
+    for &doc in docs {
+        bitmap_writer.insert(doc);
+    }
+    bitmap_writer.serialize_into(output);
+}
+
+/// Builds the bitmap of a posting list, one sorted document at a time.
+///
+/// It takes one bit per document up to the last document inserted.
+#[derive(Default)]
+pub struct BitmapWriter {
+    words: Vec<u64>,
+    num_docs: usize,
+}
+
+impl BitmapWriter {
+    pub fn insert(&mut self, doc: u32) {
+        let word_ord = doc as usize / NUM_BITS_PER_WORD;
+        if word_ord >= self.words.len() {
+            self.words.resize(word_ord + 1, 0u64);
+        }
+        self.words[word_ord] |= 1u64 << (doc as usize % NUM_BITS_PER_WORD);
+        self.num_docs += 1;
+    }
+
+    /// Returns the number of documents inserted.
+    pub fn num_docs(&self) -> usize {
+        self.num_docs
+    }
+
+    /// Returns the number of bytes of the bitmap.
+    pub fn num_bytes(&self) -> usize {
+        self.words.len() * NUM_BYTES_PER_WORD
+    }
+
+    /// Appends the bitmap to `output`.
+    pub fn serialize_into(&self, output: &mut Vec<u8>) {
+        for word in &self.words {
+            output.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// Clears the bitmap, keeping its allocated memory.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.num_docs = 0;
+    }
+}
+
+#[inline]
+fn num_words(bitmap: &[u8]) -> usize {
+    bitmap.len() / NUM_BYTES_PER_WORD
+}
+
+#[inline]
+fn word(bitmap: &[u8], word_ord: usize) -> u64 {
+    let mut word_bytes = [0u8; NUM_BYTES_PER_WORD];
+    word_bytes.copy_from_slice(&bitmap[word_ord * NUM_BYTES_PER_WORD..][..NUM_BYTES_PER_WORD]);
+    u64::from_le_bytes(word_bytes)
+}
+
+/// Returns the first word to scan to find the documents greater or equal to `first_doc`,
+/// along with its ordinal.
+#[inline]
+fn first_word(bitmap: &[u8], first_doc: u32) -> Option<(usize, u64)> {
+    let word_ord = first_doc as usize / NUM_BITS_PER_WORD;
+    if word_ord >= num_words(bitmap) {
+        return None;
+    }
+    let mask = u64::MAX << (first_doc as usize % NUM_BITS_PER_WORD);
+    Some((word_ord, word(bitmap, word_ord) & mask))
+}
+
+/// Counts the documents greater or equal to `first_doc`, stopping at `max_num_docs`.
+///
+/// Returns the number of documents found, and the last of them.
+/// Only the popcount of the words is computed, so this is much cheaper than
+/// decoding the documents.
+pub fn bitmap_block_last_doc(bitmap: &[u8], first_doc: u32, max_num_docs: usize) -> (usize, u32) {
+    let (mut word_ord, mut current_word) = if let Some(first_word) = first_word(bitmap, first_doc) {
+        first_word
+    } else {
+        return (0, 0);
+    };
+    let mut num_docs = 0;
+    let mut last_doc = 0;
+    loop {
+        let num_docs_in_word = current_word.count_ones() as usize;
+        if num_docs + num_docs_in_word >= max_num_docs {
+            for _ in num_docs + 1..max_num_docs {
+                current_word &= current_word - 1;
+            }
+            let last_doc = word_ord * NUM_BITS_PER_WORD + current_word.trailing_zeros() as usize;
+            return (max_num_docs, last_doc as u32);
+        }
+        if num_docs_in_word > 0 {
+            num_docs += num_docs_in_word;
+            last_doc = word_ord * NUM_BITS_PER_WORD + NUM_BITS_PER_WORD
+                - 1
+                - current_word.leading_zeros() as usize;
+        }
+        word_ord += 1;
+        if word_ord == num_words(bitmap) {
+            return (num_docs, last_doc as u32);
+        }
+        current_word = word(bitmap, word_ord);
+    }
+}
+
+/// Decodes the documents greater or equal to `first_doc`, until `output` is full.
+///
+/// Returns the number of documents decoded.
+pub fn uncompress_bitmap(bitmap: &[u8], first_doc: u32, output: &mut [u32]) -> usize {
+    let (mut word_ord, mut current_word) = if let Some(first_word) = first_word(bitmap, first_doc) {
+        first_word
+    } else {
+        return 0;
+    };
+    let mut num_docs = 0;
+    while num_docs < output.len() {
+        if current_word == 0 {
+            word_ord += 1;
+            if word_ord == num_words(bitmap) {
+                break;
+            }
+            current_word = word(bitmap, word_ord);
+            continue;
+        }
+        output[num_docs] = (word_ord * NUM_BITS_PER_WORD) as u32 + current_word.trailing_zeros();
+        current_word &= current_word - 1;
+        num_docs += 1;
+    }
+    num_docs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bitmap_block_last_doc, bitmap_num_bytes, compress_bitmap, uncompress_bitmap};
+
+    #[test]
+    fn test_bitmap_roundtrip() {
+        let docs: Vec<u32> = (0..1_000u32).filter(|doc| doc % 3 != 1).collect();
+        let mut bitmap = Vec::new();
+        compress_bitmap(&docs, &mut bitmap);
+        assert_eq!(bitmap.len(), bitmap_num_bytes(999));
+        assert_eq!(bitmap.len(), 128);
+
+        let mut output = [0u32; 128];
+        assert_eq!(uncompress_bitmap(&bitmap, 0, &mut output), 128);
+        assert_eq!(&output[..], &docs[..128]);
+        assert_eq!(uncompress_bitmap(&bitmap, 100, &mut output), 128);
+        let first = docs.iter().position(|&doc| doc >= 100).unwrap();
+        assert_eq!(&output[..], &docs[first..][..128]);
+        assert_eq!(uncompress_bitmap(&bitmap, 900, &mut output), 67);
+        assert_eq!(&output[..67], &docs[docs.len() - 67..]);
+        assert_eq!(uncompress_bitmap(&bitmap, 1_000, &mut output), 0);
+        assert_eq!(uncompress_bitmap(&bitmap, 100_000, &mut output), 0);
+    }
+
+    #[test]
+    fn test_bitmap_block_last_doc() {
+        let docs: Vec<u32> = (0..1_000u32).filter(|doc| doc % 3 != 1).collect();
+        let mut bitmap = Vec::new();
+        compress_bitmap(&docs, &mut bitmap);
+        assert_eq!(bitmap_block_last_doc(&bitmap, 0, 128), (128, docs[127]));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 0, 1), (1, 0));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 1, 1), (1, 2));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 64, 2), (2, 66));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 900, 128), (67, 999));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 999, 128), (1, 999));
+        assert_eq!(bitmap_block_last_doc(&bitmap, 1_000, 128).0, 0);
+    }
+}
@@ -4,8 +4,11 @@ use common::FixedSize;
 pub const COMPRESSION_BLOCK_SIZE: usize = BitPacker4x::BLOCK_LEN;
 const COMPRESSED_BLOCK_MAX_SIZE: usize = COMPRESSION_BLOCK_SIZE * u32::SIZE_IN_BYTES;
 
+mod bitmap;
 mod vint;
 
+pub(crate) use self::bitmap::{bitmap_block_last_doc, bitmap_num_bytes, BitmapWriter};
+
 /// Returns the size in bytes of a compressed block, given `num_bits`.
 pub fn compressed_block_size(num_bits: u8) -> usize {
     (num_bits as usize) * COMPRESSION_BLOCK_SIZE / 8
@@ -88,6 +91,24 @@ impl BlockDecoder {
             .decompress(compressed_data, &mut self.output, num_bits)
     }
 
+    /// Decodes the `num_docs` first documents of the bitmap that are greater or
+    /// equal to `first_doc`, padding the rest of the block with `padding`.
+    pub(crate) fn uncompress_bitmap(
+        &mut self,
+        bitmap: &[u8],
+        first_doc: u32,
+        num_docs: usize,
+        padding: u32,
+    ) {
+        self.output_len = num_docs;
+        let num_decoded =
+            bitmap::uncompress_bitmap(bitmap, first_doc, &mut self.output[..num_docs]);
+        debug_assert_eq!(num_decoded, num_docs);
+        self.output[num_docs..]
+            .iter_mut()
+            .for_each(|el| *el = padding);
+    }
+
     #[inline]
     pub fn output_array(&self) -> &[u32] {
         &self.output[..self.output_len]
@@ -1,8 +1,10 @@
 //! Postings module (also called inverted index)
 
 mod block_search;
+mod bloom_filter;
 
 pub(crate) use self::block_search::branchless_binary_search;
+pub(crate) use self::bloom_filter::BloomFilter;
 
 mod block_segment_postings;
 pub(crate) mod compression;
@@ -51,9 +53,10 @@ pub mod tests {
     use crate::indexer::SegmentWriter;
     use crate::query::Scorer;
     use crate::schema::{
-        Field, IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions, INDEXED, TEXT,
+        Document, Field, IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions, INDEXED,
+        TEXT,
     };
-    use crate::tokenizer::{SimpleTokenizer, MAX_TOKEN_LEN};
+    use crate::tokenizer::{PreTokenizedString, SimpleTokenizer, Token, MAX_TOKEN_LEN};
     use crate::{DocId, HasLen, Score};
 
     #[test]
@@ -151,6 +154,108 @@ pub mod tests {
         Ok(())
     }
 
+    fn pre_tokenized_with_payloads(words: &[(&str, u32)]) -> PreTokenizedString {
+        let tokens = words
+            .iter()
+            .enumerate()
+            .map(|(position, &(word, payload))| Token {
+                position,
+                text: word.to_string(),
+                payload,
+                ..Token::default()
+            })
+            .collect();
+        PreTokenizedString {
+            text: String::new(),
+            tokens,
+        }
+    }
+
+    #[test]
+    pub fn test_pre_tokenized_payloads() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_payloads(true),
+        );
+        let title = schema_builder.add_text_field("title", text_options);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for segment in 0u32..2u32 {
+            for doc in 0u32..200u32 {
+                let payload = segment * 1_000 + doc;
+                let mut document = Document::default();
+                document.add_pre_tokenized_text(
+                    title,
+                    pre_tokenized_with_payloads(&[("a", payload), ("b", 7), ("a", payload + 1)]),
+                );
+                document.add_text(body, "a b a");
+                index_writer.add_document(document)?;
+            }
+            index_writer.commit()?;
+        }
+        let check_payloads = |index: &Index| -> crate::Result<()> {
+            let searcher = index.reader()?.searcher();
+            let mut positions = Vec::new();
+            let mut payloads = Vec::new();
+            let mut first_payloads = Vec::new();
+            for segment_reader in searcher.segment_readers() {
+                let inverted_index = segment_reader.inverted_index(title)?;
+                let mut postings = inverted_index
+                    .read_postings(
+                        &Term::from_field_text(title, "a"),
+                        IndexRecordOption::WithFreqsAndPositions,
+                    )?
+                    .unwrap();
+                while postings.doc() != TERMINATED {
+                    postings.positions(&mut positions);
+                    assert_eq!(&positions[..], &[0, 2]);
+                    postings.payloads(&mut payloads);
+                    assert_eq!(payloads.len(), 2);
+                    assert_eq!(payloads[1], payloads[0] + 1);
+                    first_payloads.push(payloads[0]);
+                    postings.advance();
+                }
+
+                let mut postings = inverted_index
+                    .read_postings(
+                        &Term::from_field_text(title, "b"),
+                        IndexRecordOption::WithFreqsAndPositions,
+                    )?
+                    .unwrap();
+                assert_eq!(postings.seek(150), 150);
+                postings.payloads(&mut payloads);
+                assert_eq!(&payloads[..], &[7]);
+                postings.positions(&mut positions);
+                assert_eq!(&positions[..], &[1]);
+
+                let mut postings = segment_reader
+                    .inverted_index(body)?
+                    .read_postings(
+                        &Term::from_field_text(body, "a"),
+                        IndexRecordOption::WithFreqsAndPositions,
+                    )?
+                    .unwrap();
+                postings.payloads(&mut payloads);
+                assert!(payloads.is_empty());
+            }
+            first_payloads.sort_unstable();
+            let expected_payloads: Vec<u32> = (0..200).chain(1_000..1_200).collect();
+            assert_eq!(first_payloads, expected_payloads);
+            Ok(())
+        };
+        check_payloads(&index)?;
+
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(index.searchable_segment_ids()?.len(), 1);
+        check_payloads(&index)?;
+        Ok(())
+    }
+
     #[test]
     pub fn test_index_max_length_token() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -1,6 +1,9 @@
 use crate::postings::json_postings_writer::JsonPostingsWriter;
 use crate::postings::postings_writer::SpecializedPostingsWriter;
-use crate::postings::recorder::{NothingRecorder, TermFrequencyRecorder, TfAndPositionRecorder};
+use crate::postings::recorder::{
+    ImpactRecorder, NothingRecorder, TermFrequencyRecorder, TfAndPositionRecorder,
+    TfPositionAndPayloadRecorder,
+};
 use crate::postings::PostingsWriter;
 use crate::schema::{Field, FieldEntry, FieldType, IndexRecordOption, Schema};
 
@@ -36,9 +39,15 @@ fn posting_writer_from_field_entry(field_entry: &FieldEntry) -> Box<dyn Postings
                 IndexRecordOption::Basic => {
                     SpecializedPostingsWriter::<NothingRecorder>::default().into()
                 }
+                IndexRecordOption::WithFreqs if indexing_options.has_impacts() => {
+                    SpecializedPostingsWriter::<ImpactRecorder>::default().into()
+                }
                 IndexRecordOption::WithFreqs => {
                     SpecializedPostingsWriter::<TermFrequencyRecorder>::default().into()
                 }
+                IndexRecordOption::WithFreqsAndPositions if indexing_options.has_payloads() => {
+                    SpecializedPostingsWriter::<TfPositionAndPayloadRecorder>::default().into()
+                }
                 IndexRecordOption::WithFreqsAndPositions => {
                     SpecializedPostingsWriter::<TfAndPositionRecorder>::default().into()
                 }
@@ -23,4 +23,13 @@ pub trait Postings: DocSet + 'static {
     fn positions(&mut self, output: &mut Vec<u32>) {
         self.positions_with_offset(0u32, output);
     }
+
+    /// Returns the payloads of the tokens at each of the positions of the term
+    /// in the given document, in the same order as `positions`.
+    ///
+    /// The output vector will be resized to the `term_freq`, or emptied if the field
+    /// does not store payloads.
+    fn payloads(&mut self, output: &mut Vec<u32>) {
+        output.clear();
+    }
 }
@@ -116,7 +116,7 @@ pub(crate) struct IndexingPosition {
 /// and building a `Segment` in anonymous memory.
 ///
 /// `PostingsWriter` writes in a `MemoryArena`.
-pub(crate) trait PostingsWriter {
+pub(crate) trait PostingsWriter: Send {
     /// Record that a document contains a term at a given position.
     ///
     /// * doc  - the document id
@@ -132,6 +132,21 @@ pub(crate) trait PostingsWriter {
         ctx: &mut IndexingContext,
     ) -> UnorderedTermId;
 
+    /// Same as `subscribe`, also recording the payload of the token at the given position.
+    ///
+    /// The payload is ignored if the field does not store payloads.
+    fn subscribe_with_payload(
+        &mut self,
+        doc: DocId,
+        pos: u32,
+        payload: u32,
+        term: &Term,
+        ctx: &mut IndexingContext,
+    ) -> UnorderedTermId {
+        let _ = payload;
+        self.subscribe(doc, pos, term, ctx)
+    }
+
     /// Serializes the postings on disk.
     /// The actual serialization format is handled by the `PostingsSerializer`.
     fn serialize(
@@ -170,7 +185,13 @@ pub(crate) trait PostingsWriter {
             term_buffer.append_bytes(token.text.as_bytes());
             let start_position = indexing_position.end_position + token.position as u32;
             end_position = start_position + token.position_length as u32;
-            let unordered_term_id = self.subscribe(doc_id, start_position, term_buffer, ctx);
+            let unordered_term_id = self.subscribe_with_payload(
+                doc_id,
+                start_position,
+                token.payload,
+                term_buffer,
+                ctx,
+            );
             if let Some(term_id_fast_field_writer) = term_id_fast_field_writer_opt.as_mut() {
                 term_id_fast_field_writer.add_val(unordered_term_id);
             }
@@ -228,6 +249,17 @@ impl<Rec: Recorder> PostingsWriter for SpecializedPostingsWriter<Rec> {
         position: u32,
         term: &Term,
         ctx: &mut IndexingContext,
+    ) -> UnorderedTermId {
+        self.subscribe_with_payload(doc, position, 0u32, term, ctx)
+    }
+
+    fn subscribe_with_payload(
+        &mut self,
+        doc: DocId,
+        position: u32,
+        payload: u32,
+        term: &Term,
+        ctx: &mut IndexingContext,
     ) -> UnorderedTermId {
         debug_assert!(term.as_slice().len() >= 4);
         self.total_num_tokens += 1;
@@ -240,11 +272,13 @@ impl<Rec: Recorder> PostingsWriter for SpecializedPostingsWriter<Rec> {
                     recorder.new_doc(doc, arena);
                 }
                 recorder.record_position(position, arena);
+                recorder.record_payload(payload, arena);
                 recorder
             } else {
                 let mut recorder = Rec::default();
                 recorder.new_doc(doc, arena);
                 recorder.record_position(position, arena);
+                recorder.record_payload(payload, arena);
                 recorder
             }
         }) as UnorderedTermId
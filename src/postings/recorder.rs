@@ -56,7 +56,7 @@ impl<'a> Iterator for VInt32Reader<'a> {
 ///   * the document id
 ///   * the term frequency
 ///   * the term positions
-pub(crate) trait Recorder: Copy + Default + 'static {
+pub(crate) trait Recorder: Copy + Default + Send + 'static {
     /// Returns the current document
     fn current_doc(&self) -> u32;
     /// Starts recording information about a new document
@@ -65,6 +65,10 @@ pub(crate) trait Recorder: Copy + Default + 'static {
     /// Record the position of a term. For each document,
     /// this method will be called `term_freq` times.
     fn record_position(&mut self, position: u32, arena: &mut MemoryArena);
+    /// Record the payload of the token at the last recorded position.
+    ///
+    /// Recorders which do not store payloads ignore it.
+    fn record_payload(&mut self, _payload: u32, _arena: &mut MemoryArena) {}
     /// Close the document. It will help record the term frequency.
     fn close_doc(&mut self, arena: &mut MemoryArena);
     /// Pushes the postings information to the serializer.
@@ -218,6 +222,85 @@ impl Recorder for TermFrequencyRecorder {
     }
 }
 
+/// Recorder encoding document ids, and the impact of the term in each document
+/// in place of its term frequency.
+///
+/// The impact is the sum of the payloads of the tokens of the term.
+#[derive(Clone, Copy)]
+pub struct ImpactRecorder {
+    stack: ExpUnrolledLinkedList,
+    current_doc: DocId,
+    current_impact: u32,
+    term_doc_freq: u32,
+}
+
+impl Default for ImpactRecorder {
+    fn default() -> Self {
+        ImpactRecorder {
+            stack: ExpUnrolledLinkedList::new(),
+            current_doc: 0,
+            current_impact: 0u32,
+            term_doc_freq: 0u32,
+        }
+    }
+}
+
+impl Recorder for ImpactRecorder {
+    fn current_doc(&self) -> DocId {
+        self.current_doc
+    }
+
+    fn new_doc(&mut self, doc: DocId, arena: &mut MemoryArena) {
+        self.term_doc_freq += 1;
+        self.current_doc = doc;
+        self.stack.writer(arena).write_u32_vint(doc);
+    }
+
+    fn record_position(&mut self, _position: u32, _arena: &mut MemoryArena) {}
+
+    fn record_payload(&mut self, payload: u32, _arena: &mut MemoryArena) {
+        self.current_impact = self.current_impact.saturating_add(payload);
+    }
+
+    fn close_doc(&mut self, arena: &mut MemoryArena) {
+        self.stack.writer(arena).write_u32_vint(self.current_impact);
+        self.current_impact = 0;
+    }
+
+    fn serialize(
+        &self,
+        arena: &MemoryArena,
+        doc_id_map: Option<&DocIdMapping>,
+        serializer: &mut FieldSerializer<'_>,
+        buffer_lender: &mut BufferLender,
+    ) {
+        let buffer = buffer_lender.lend_u8();
+        self.stack.read_to_end(arena, buffer);
+        let mut u32_it = VInt32Reader::new(&buffer[..]);
+        if let Some(doc_id_map) = doc_id_map {
+            let mut doc_id_and_impact = vec![];
+            while let Some(old_doc_id) = u32_it.next() {
+                let impact = u32_it.next().unwrap_or(self.current_impact);
+                doc_id_and_impact.push((doc_id_map.get_new_doc_id(old_doc_id), impact));
+            }
+            doc_id_and_impact.sort_unstable_by_key(|&(doc_id, _)| doc_id);
+
+            for (doc_id, impact) in doc_id_and_impact {
+                serializer.write_doc(doc_id, impact, &[][..]);
+            }
+        } else {
+            while let Some(doc) = u32_it.next() {
+                let impact = u32_it.next().unwrap_or(self.current_impact);
+                serializer.write_doc(doc, impact, &[][..]);
+            }
+        }
+    }
+
+    fn term_doc_freq(&self) -> Option<u32> {
+        Some(self.term_doc_freq)
+    }
+}
+
 /// Recorder encoding term frequencies as well as positions.
 #[derive(Clone, Copy)]
 pub struct TfAndPositionRecorder {
@@ -304,6 +387,114 @@ impl Recorder for TfAndPositionRecorder {
     }
 }
 
+/// Recorder encoding term frequencies, positions, as well as the payload
+/// of the token at each position.
+#[derive(Clone, Copy)]
+pub struct TfPositionAndPayloadRecorder {
+    stack: ExpUnrolledLinkedList,
+    current_doc: DocId,
+    term_doc_freq: u32,
+}
+
+impl Default for TfPositionAndPayloadRecorder {
+    fn default() -> Self {
+        TfPositionAndPayloadRecorder {
+            stack: ExpUnrolledLinkedList::new(),
+            current_doc: u32::max_value(),
+            term_doc_freq: 0u32,
+        }
+    }
+}
+
+impl Recorder for TfPositionAndPayloadRecorder {
+    fn current_doc(&self) -> DocId {
+        self.current_doc
+    }
+
+    fn new_doc(&mut self, doc: DocId, arena: &mut MemoryArena) {
+        self.current_doc = doc;
+        self.term_doc_freq += 1u32;
+        self.stack.writer(arena).write_u32_vint(doc);
+    }
+
+    fn record_position(&mut self, position: u32, arena: &mut MemoryArena) {
+        self.stack
+            .writer(arena)
+            .write_u32_vint(position.wrapping_add(1u32));
+    }
+
+    fn record_payload(&mut self, payload: u32, arena: &mut MemoryArena) {
+        // The payload always follows its position, so it cannot be mistaken
+        // for `POSITION_END`.
+        self.stack.writer(arena).write_u32_vint(payload);
+    }
+
+    fn close_doc(&mut self, arena: &mut MemoryArena) {
+        self.stack.writer(arena).write_u32_vint(POSITION_END);
+    }
+
+    fn serialize(
+        &self,
+        arena: &MemoryArena,
+        doc_id_map: Option<&DocIdMapping>,
+        serializer: &mut FieldSerializer<'_>,
+        buffer_lender: &mut BufferLender,
+    ) {
+        let (buffer_u8, buffer_positions) = buffer_lender.lend_all();
+        self.stack.read_to_end(arena, buffer_u8);
+        let mut u32_it = VInt32Reader::new(&buffer_u8[..]);
+        let mut buffer_payloads = Vec::new();
+        let mut doc_id_positions_and_payloads = vec![];
+        while let Some(doc) = u32_it.next() {
+            let mut prev_position_plus_one = 1u32;
+            buffer_positions.clear();
+            buffer_payloads.clear();
+            loop {
+                match u32_it.next() {
+                    Some(POSITION_END) | None => {
+                        break;
+                    }
+                    Some(position_plus_one) => {
+                        let delta_position = position_plus_one - prev_position_plus_one;
+                        buffer_positions.push(delta_position);
+                        buffer_payloads.push(u32_it.next().unwrap_or(0u32));
+                        prev_position_plus_one = position_plus_one;
+                    }
+                }
+            }
+            if let Some(doc_id_map) = doc_id_map {
+                doc_id_positions_and_payloads.push((
+                    doc_id_map.get_new_doc_id(doc),
+                    buffer_positions.to_vec(),
+                    buffer_payloads.clone(),
+                ));
+            } else {
+                serializer.write_doc_with_payloads(
+                    doc,
+                    buffer_positions.len() as u32,
+                    buffer_positions,
+                    &buffer_payloads,
+                );
+            }
+        }
+        if doc_id_map.is_some() {
+            doc_id_positions_and_payloads.sort_unstable_by_key(|&(doc_id, _, _)| doc_id);
+            for (doc_id, positions, payloads) in doc_id_positions_and_payloads {
+                serializer.write_doc_with_payloads(
+                    doc_id,
+                    positions.len() as u32,
+                    &positions,
+                    &payloads,
+                );
+            }
+        }
+    }
+
+    fn term_doc_freq(&self) -> Option<u32> {
+        Some(self.term_doc_freq)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -17,6 +17,7 @@ pub struct SegmentPostings {
     pub(crate) block_cursor: BlockSegmentPostings,
     cur: usize,
     position_reader: Option<PositionReader>,
+    has_payloads: bool,
 }
 
 impl SegmentPostings {
@@ -26,6 +27,7 @@ impl SegmentPostings {
             block_cursor: BlockSegmentPostings::empty(),
             cur: 0,
             position_reader: None,
+            has_payloads: false,
         }
     }
 
@@ -86,7 +88,7 @@ impl SegmentPostings {
             IndexRecordOption::Basic,
         )
         .unwrap();
-        SegmentPostings::from_block_postings(block_segment_postings, None)
+        SegmentPostings::from_block_postings(block_segment_postings, None, false)
     }
 
     /// Helper functions to create `SegmentPostings` for tests.
@@ -134,7 +136,7 @@ impl SegmentPostings {
             IndexRecordOption::WithFreqs,
         )
         .unwrap();
-        SegmentPostings::from_block_postings(block_segment_postings, None)
+        SegmentPostings::from_block_postings(block_segment_postings, None, false)
     }
 
     /// Reads a Segment postings from an &[u8]
@@ -142,14 +144,42 @@ impl SegmentPostings {
     /// * `len` - number of document in the posting lists.
     /// * `data` - data array. The complete data is not necessarily used.
     /// * `freq_handler` - the freq handler is in charge of decoding frequencies and/or positions
+    /// * `has_payloads` - whether the payloads of the document are stored after its positions
     pub(crate) fn from_block_postings(
         segment_block_postings: BlockSegmentPostings,
         position_reader: Option<PositionReader>,
+        has_payloads: bool,
     ) -> SegmentPostings {
         SegmentPostings {
             block_cursor: segment_block_postings,
             cur: 0, // cursor within the block
             position_reader,
+            has_payloads,
+        }
+    }
+
+    /// Reads the values stored in the positions file for the current document.
+    ///
+    /// These are the position deltas, followed by the payloads if the field has payloads.
+    /// Returns false if positions are not available.
+    fn read_position_values(&mut self, output: &mut Vec<u32>) -> bool {
+        let term_freq = self.term_freq() as usize;
+        let num_values_per_position = if self.has_payloads { 2 } else { 1 };
+        if let Some(position_reader) = self.position_reader.as_mut() {
+            let read_offset = self.block_cursor.position_offset()
+                + (self.block_cursor.freqs()[..self.cur]
+                    .iter()
+                    .cloned()
+                    .sum::<u32>() as u64);
+            output.resize(term_freq * num_values_per_position, 0u32);
+            position_reader.read(
+                read_offset * num_values_per_position as u64,
+                &mut output[..],
+            );
+            true
+        } else {
+            output.clear();
+            false
         }
     }
 }
@@ -198,6 +228,26 @@ impl DocSet for SegmentPostings {
         doc
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        // Docs are copied block by block, straight from the decoded block.
+        let mut num_filled = 0;
+        while num_filled < buffer.len() {
+            let block_docs = &self.block_cursor.docs()[self.cur..];
+            if block_docs.is_empty() {
+                break;
+            }
+            let num_docs = block_docs.len().min(buffer.len() - num_filled);
+            buffer[num_filled..][..num_docs].copy_from_slice(&block_docs[..num_docs]);
+            num_filled += num_docs;
+            self.cur += num_docs;
+            if self.cur == COMPRESSION_BLOCK_SIZE {
+                self.cur = 0;
+                self.block_cursor.advance();
+            }
+        }
+        num_filled
+    }
+
     /// Return the current document's `DocId`.
     #[inline]
     fn doc(&self) -> DocId {
@@ -238,22 +288,23 @@ impl Postings for SegmentPostings {
     }
 
     fn positions_with_offset(&mut self, offset: u32, output: &mut Vec<u32>) {
-        let term_freq = self.term_freq() as usize;
-        if let Some(position_reader) = self.position_reader.as_mut() {
-            let read_offset = self.block_cursor.position_offset()
-                + (self.block_cursor.freqs()[..self.cur]
-                    .iter()
-                    .cloned()
-                    .sum::<u32>() as u64);
-            output.resize(term_freq, 0u32);
-            position_reader.read(read_offset, &mut output[..]);
+        if self.read_position_values(output) {
+            output.truncate(self.term_freq() as usize);
             let mut cum = offset;
             for output_mut in output.iter_mut() {
                 cum += *output_mut;
                 *output_mut = cum;
             }
-        } else {
+        }
+    }
+
+    fn payloads(&mut self, output: &mut Vec<u32>) {
+        if !self.has_payloads {
             output.clear();
+            return;
+        }
+        if self.read_position_values(output) {
+            output.drain(..self.term_freq() as usize);
         }
     }
 }
@@ -267,6 +318,7 @@ mod tests {
     use crate::docset::{DocSet, TERMINATED};
     use crate::fastfield::AliveBitSet;
     use crate::postings::postings::Postings;
+    use crate::DocId;
 
     #[test]
     fn test_empty_segment_postings() {
@@ -299,4 +351,35 @@ mod tests {
             AliveBitSet::for_test_from_deleted_docs(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], 12);
         assert_eq!(docs.doc_freq_given_deletes(&all_deleted), 0);
     }
+
+    fn test_fill_buffer_aux(num_docs: u32) {
+        let docs: Vec<DocId> = (0..num_docs).map(|i| i * 3).collect();
+        let mut postings = SegmentPostings::create_from_docs(&docs);
+        let mut buffer = [0u32; 100];
+        let mut filled_docs = Vec::new();
+        if num_docs > 1 {
+            // Advancing and filling the buffer can be mixed.
+            filled_docs.push(postings.doc());
+            assert_eq!(postings.advance(), 3);
+        }
+        loop {
+            let num_filled = postings.fill_buffer(&mut buffer);
+            filled_docs.extend_from_slice(&buffer[..num_filled]);
+            if num_filled < buffer.len() {
+                break;
+            }
+        }
+        assert_eq!(filled_docs, docs);
+        assert_eq!(postings.doc(), TERMINATED);
+        assert_eq!(postings.fill_buffer(&mut buffer), 0);
+    }
+
+    #[test]
+    fn test_fill_buffer() {
+        test_fill_buffer_aux(0);
+        test_fill_buffer_aux(1);
+        test_fill_buffer_aux(101);
+        test_fill_buffer_aux(256);
+        test_fill_buffer_aux(300);
+    }
 }
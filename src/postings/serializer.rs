@@ -1,26 +1,37 @@
 use std::cmp::Ordering;
 use std::io::{self, Write};
 
-use common::{BinarySerializable, CountingWriter, VInt};
+use common::{BinarySerializable, CountingWriter, TerminatingWrite, VInt};
 use fail::fail_point;
 
+use super::bloom_filter::BloomFilterBuilder;
 use super::TermInfo;
 use crate::core::Segment;
 use crate::directory::{CompositeWrite, WritePtr};
 use crate::fieldnorm::FieldNormReader;
 use crate::positions::PositionSerializer;
-use crate::postings::compression::{BlockEncoder, VIntEncoder, COMPRESSION_BLOCK_SIZE};
+use crate::postings::compression::{
+    bitmap_num_bytes, BitmapWriter, BlockEncoder, VIntEncoder, COMPRESSION_BLOCK_SIZE,
+};
 use crate::postings::skip::SkipSerializer;
 use crate::query::Bm25Weight;
-use crate::schema::{Field, FieldEntry, FieldType, IndexRecordOption, Schema};
+use crate::schema::{
+    Field, FieldEntry, FieldType, IndexRecordOption, Schema, DEFAULT_LENGTH_NORMALIZATION,
+};
 use crate::termdict::{TermDictionaryBuilder, TermOrdinal};
-use crate::{DocId, Score};
+use crate::{DocId, Score, SegmentComponent};
+
+// Upper bound of the size of a doc in the bitpacked encoding of a posting list without term
+// frequencies: at most 4 bytes in a full block along with its share of the skip data, and
+// at most 5 bytes in the last, vint encoded, block.
+const MAX_ENCODED_NUM_BYTES_PER_DOC: usize = 5;
 
 /// `InvertedIndexSerializer` is in charge of serializing
 /// postings on disk, in the
 /// * `.idx` (inverted index)
 /// * `.pos` (positions file)
 /// * `.term` (term dictionary)
+/// * `.bloom` (bloom filter of the terms of the primary key field)
 ///
 /// `PostingsWriter` are in charge of pushing the data to the
 /// serializer.
@@ -51,18 +62,42 @@ pub struct InvertedIndexSerializer {
     terms_write: CompositeWrite<WritePtr>,
     postings_write: CompositeWrite<WritePtr>,
     positions_write: CompositeWrite<WritePtr>,
+    bloom_filter_write_opt: Option<WritePtr>,
+    bloom_filter_builder: BloomFilterBuilder,
     schema: Schema,
 }
 
 impl InvertedIndexSerializer {
     /// Open a new `InvertedIndexSerializer` for the given segment
     pub fn open(segment: &mut Segment) -> crate::Result<InvertedIndexSerializer> {
-        use crate::SegmentComponent::{Positions, Postings, Terms};
+        let schema = segment.schema();
+        InvertedIndexSerializer::from_writes(schema, |component| {
+            Ok(segment.open_write(component)?)
+        })
+    }
+
+    /// Creates an `InvertedIndexSerializer` writing each of its components to the write
+    /// returned by `open_write`.
+    pub(crate) fn from_writes(
+        schema: Schema,
+        mut open_write: impl FnMut(SegmentComponent) -> crate::Result<WritePtr>,
+    ) -> crate::Result<InvertedIndexSerializer> {
+        use crate::SegmentComponent::{BloomFilter, Positions, Postings, Terms};
+        let has_primary_key = schema
+            .fields()
+            .any(|(_, field_entry)| field_entry.is_primary_key());
+        let bloom_filter_write_opt = if has_primary_key {
+            Some(open_write(BloomFilter)?)
+        } else {
+            None
+        };
         let inv_index_serializer = InvertedIndexSerializer {
-            terms_write: CompositeWrite::wrap(segment.open_write(Terms)?),
-            postings_write: CompositeWrite::wrap(segment.open_write(Postings)?),
-            positions_write: CompositeWrite::wrap(segment.open_write(Positions)?),
-            schema: segment.schema(),
+            terms_write: CompositeWrite::wrap(open_write(Terms)?),
+            postings_write: CompositeWrite::wrap(open_write(Postings)?),
+            positions_write: CompositeWrite::wrap(open_write(Positions)?),
+            bloom_filter_write_opt,
+            bloom_filter_builder: BloomFilterBuilder::default(),
+            schema,
         };
         Ok(inv_index_serializer)
     }
@@ -82,6 +117,11 @@ impl InvertedIndexSerializer {
         let postings_write = self.postings_write.for_field(field);
         let positions_write = self.positions_write.for_field(field);
         let field_type: FieldType = (*field_entry.field_type()).clone();
+        let bloom_filter_builder_opt = if field_entry.is_primary_key() {
+            Some(&mut self.bloom_filter_builder)
+        } else {
+            None
+        };
         FieldSerializer::create(
             &field_type,
             total_num_tokens,
@@ -89,6 +129,7 @@ impl InvertedIndexSerializer {
             postings_write,
             positions_write,
             fieldnorm_reader,
+            bloom_filter_builder_opt,
         )
     }
 
@@ -97,6 +138,10 @@ impl InvertedIndexSerializer {
         self.terms_write.close()?;
         self.postings_write.close()?;
         self.positions_write.close()?;
+        if let Some(mut bloom_filter_write) = self.bloom_filter_write_opt {
+            self.bloom_filter_builder.serialize(&mut bloom_filter_write)?;
+            bloom_filter_write.terminate()?;
+        }
         Ok(())
     }
 }
@@ -107,6 +152,8 @@ pub struct FieldSerializer<'a> {
     term_dictionary_builder: TermDictionaryBuilder<&'a mut CountingWriter<WritePtr>>,
     postings_serializer: PostingsSerializer<&'a mut CountingWriter<WritePtr>>,
     positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<WritePtr>>>,
+    bloom_filter_builder_opt: Option<&'a mut BloomFilterBuilder>,
+    has_payloads: bool,
     current_term_info: TermInfo,
     term_open: bool,
     num_terms: TermOrdinal,
@@ -120,6 +167,7 @@ impl<'a> FieldSerializer<'a> {
         postings_write: &'a mut CountingWriter<WritePtr>,
         positions_write: &'a mut CountingWriter<WritePtr>,
         fieldnorm_reader: Option<FieldNormReader>,
+        bloom_filter_builder_opt: Option<&'a mut BloomFilterBuilder>,
     ) -> io::Result<FieldSerializer<'a>> {
         total_num_tokens.serialize(postings_write)?;
         let index_record_option = field_type
@@ -130,12 +178,14 @@ impl<'a> FieldSerializer<'a> {
             .as_ref()
             .map(|ff_reader| (total_num_tokens as Score / ff_reader.num_docs() as Score))
             .unwrap_or(0.0);
-        let postings_serializer = PostingsSerializer::new(
+        let mut postings_serializer = PostingsSerializer::new(
             postings_write,
             average_fieldnorm,
             index_record_option,
             fieldnorm_reader,
         );
+        postings_serializer.impacts = field_type.has_impacts();
+        postings_serializer.length_normalization = field_type.length_normalization();
         let positions_serializer_opt = if index_record_option.has_positions() {
             Some(PositionSerializer::new(positions_write))
         } else {
@@ -146,6 +196,8 @@ impl<'a> FieldSerializer<'a> {
             term_dictionary_builder,
             postings_serializer,
             positions_serializer_opt,
+            bloom_filter_builder_opt,
+            has_payloads: field_type.has_payloads(),
             current_term_info: TermInfo::default(),
             term_open: false,
             num_terms: TermOrdinal::default(),
@@ -181,6 +233,9 @@ impl<'a> FieldSerializer<'a> {
         self.postings_serializer.clear();
         self.current_term_info = self.current_term_info();
         self.term_dictionary_builder.insert_key(term)?;
+        if let Some(bloom_filter_builder) = self.bloom_filter_builder_opt.as_mut() {
+            bloom_filter_builder.insert(term);
+        }
         let term_ordinal = self.num_terms;
         self.num_terms += 1;
         self.postings_serializer.new_term(term_doc_freq);
@@ -197,11 +252,36 @@ impl<'a> FieldSerializer<'a> {
     /// Term frequencies and positions may be ignored by the serializer depending
     /// on the configuration of the field in the `Schema`.
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32, position_deltas: &[u32]) {
+        self.write_doc_with_payloads(doc_id, term_freq, position_deltas, &[]);
+    }
+
+    /// Same as `write_doc`, with the payloads of the term at each of its positions.
+    ///
+    /// For fields storing payloads, they are written right after the position deltas
+    /// of the document. Missing payloads are recorded as `0`. Payloads are ignored
+    /// for other fields.
+    pub fn write_doc_with_payloads(
+        &mut self,
+        doc_id: DocId,
+        term_freq: u32,
+        position_deltas: &[u32],
+        payloads: &[u32],
+    ) {
         self.current_term_info.doc_freq += 1;
         self.postings_serializer.write_doc(doc_id, term_freq);
         if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
             assert_eq!(term_freq as usize, position_deltas.len());
             positions_serializer.write_positions_delta(position_deltas);
+            if self.has_payloads {
+                if payloads.is_empty() {
+                    for _ in 0..term_freq {
+                        positions_serializer.write_positions_delta(&[0u32]);
+                    }
+                } else {
+                    assert_eq!(term_freq as usize, payloads.len());
+                    positions_serializer.write_positions_delta(payloads);
+                }
+            }
         }
     }
 
@@ -300,9 +380,21 @@ pub struct PostingsSerializer<W: Write> {
 
     postings_write: Vec<u8>,
     skip_write: SkipSerializer,
+    // Bitmap of the docs of the current term, built when the term frequencies are not
+    // recorded, so that the posting list can be encoded as a bitmap if it is dense.
+    // It takes at most 1 bit per doc of the segment. When the doc freq of the term is known
+    // upfront, it is also dropped as soon as it gets larger than the bitpacked encoding of the
+    // term can be, so that it takes at most 5 bytes per doc of the term.
+    term_bitmap: BitmapWriter,
+    tracks_term_bitmap: bool,
+    term_doc_freq: u32,
 
     mode: IndexRecordOption,
     fieldnorm_reader: Option<FieldNormReader>,
+    // Term frequencies are impacts, see `TextFieldIndexing::set_impacts`.
+    impacts: bool,
+    // Strength of the length normalization of the block-max scores.
+    length_normalization: Score,
 
     bm25_weight: Option<Bm25Weight>,
     avg_fieldnorm: Score, /* Average number of term in the field for that segment.
@@ -324,11 +416,16 @@ impl<W: Write> PostingsSerializer<W> {
 
             postings_write: Vec::new(),
             skip_write: SkipSerializer::new(),
+            term_bitmap: BitmapWriter::default(),
+            tracks_term_bitmap: false,
+            term_doc_freq: 0,
 
             last_doc_id_encoded: 0u32,
             mode,
 
             fieldnorm_reader,
+            impacts: false,
+            length_normalization: DEFAULT_LENGTH_NORMALIZATION,
             bm25_weight: None,
             avg_fieldnorm,
         }
@@ -336,6 +433,11 @@ impl<W: Write> PostingsSerializer<W> {
 
     pub fn new_term(&mut self, term_doc_freq: u32) {
         self.bm25_weight = None;
+        self.term_doc_freq = term_doc_freq;
+        // Only the posting lists with a full block may be encoded as a bitmap. The doc freq is
+        // 0 if it is unknown, which is the case when indexing without term frequencies.
+        self.tracks_term_bitmap = !self.mode.has_freq()
+            && (term_doc_freq == 0 || term_doc_freq >= COMPRESSION_BLOCK_SIZE as u32);
 
         if !self.mode.has_freq() {
             return;
@@ -352,11 +454,14 @@ impl<W: Write> PostingsSerializer<W> {
             return;
         }
 
-        self.bm25_weight = Some(Bm25Weight::for_one_term(
-            term_doc_freq as u64,
-            num_docs_in_segment,
-            self.avg_fieldnorm,
-        ));
+        self.bm25_weight = Some(
+            Bm25Weight::for_one_term(
+                term_doc_freq as u64,
+                num_docs_in_segment,
+                self.avg_fieldnorm,
+            )
+            .with_length_normalization(self.length_normalization),
+        );
     }
 
     fn write_block(&mut self) {
@@ -384,7 +489,17 @@ impl<W: Write> PostingsSerializer<W> {
                 self.skip_write.write_total_term_freq(sum_freq);
             }
             let mut blockwand_params = (0u8, 0u32);
-            if let Some(bm25_weight) = self.bm25_weight.as_ref() {
+            if self.impacts {
+                // The score only depends on the impact.
+                let max_impact = self
+                    .block
+                    .term_freqs()
+                    .iter()
+                    .cloned()
+                    .max()
+                    .unwrap_or(0u32);
+                blockwand_params = (0u8, max_impact);
+            } else if let Some(bm25_weight) = self.bm25_weight.as_ref() {
                 if let Some(fieldnorm_reader) = self.fieldnorm_reader.as_ref() {
                     let docs = self.block.doc_ids().iter().cloned();
                     let term_freqs = self.block.term_freqs().iter().cloned();
@@ -413,6 +528,15 @@ impl<W: Write> PostingsSerializer<W> {
     }
 
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32) {
+        if self.tracks_term_bitmap {
+            let max_encoded_num_bytes = self.term_doc_freq as usize * MAX_ENCODED_NUM_BYTES_PER_DOC;
+            if self.term_doc_freq == 0 || bitmap_num_bytes(doc_id) < max_encoded_num_bytes {
+                self.term_bitmap.insert(doc_id);
+            } else {
+                self.tracks_term_bitmap = false;
+                self.term_bitmap.clear();
+            }
+        }
         self.block.append_doc(doc_id, term_freq);
         if self.block.is_full() {
             self.write_block();
@@ -448,12 +572,27 @@ impl<W: Write> PostingsSerializer<W> {
         }
         if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
             let skip_data = self.skip_write.data();
-            VInt(skip_data.len() as u64).serialize(&mut self.output_write)?;
-            self.output_write.write_all(skip_data)?;
+            let encoded_num_bytes = skip_data.len() + self.postings_write.len();
+            let is_dense = self.tracks_term_bitmap
+                && self.term_bitmap.num_docs() == doc_freq as usize
+                && self.term_bitmap.num_bytes() < encoded_num_bytes;
+            if is_dense {
+                // Dense posting lists are both smaller and faster to seek into when
+                // encoded as a bitmap. They are marked by empty skip data, which
+                // regular posting lists with a full block never have.
+                self.postings_write.clear();
+                self.term_bitmap.serialize_into(&mut self.postings_write);
+                VInt(0u64).serialize(&mut self.output_write)?;
+            } else {
+                VInt(skip_data.len() as u64).serialize(&mut self.output_write)?;
+                self.output_write.write_all(skip_data)?;
+            }
         }
         self.output_write.write_all(&self.postings_write[..])?;
         self.skip_write.clear();
         self.postings_write.clear();
+        self.term_bitmap.clear();
+        self.tracks_term_bitmap = false;
         self.bm25_weight = None;
         Ok(())
     }
@@ -470,6 +609,8 @@ impl<W: Write> PostingsSerializer<W> {
 
     fn clear(&mut self) {
         self.block.clear();
+        self.term_bitmap.clear();
+        self.tracks_term_bitmap = false;
         self.last_doc_id_encoded = 0;
     }
 }
@@ -1,7 +1,9 @@
 use std::convert::TryInto;
 
 use crate::directory::OwnedBytes;
-use crate::postings::compression::{compressed_block_size, COMPRESSION_BLOCK_SIZE};
+use crate::postings::compression::{
+    bitmap_block_last_doc, compressed_block_size, COMPRESSION_BLOCK_SIZE,
+};
 use crate::query::Bm25Weight;
 use crate::schema::IndexRecordOption;
 use crate::{DocId, Score, TERMINATED};
@@ -79,6 +81,8 @@ pub(crate) struct SkipReader {
     block_info: BlockInfo,
 
     position_offset: u64,
+    // The posting list is encoded as a bitmap, held by `owned_read`.
+    is_bitmap: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Copy, Debug)]
@@ -93,6 +97,13 @@ pub(crate) enum BlockInfo {
     VInt {
         num_docs: u32,
     },
+    // Block of a posting list encoded as a bitmap.
+    //
+    // The block is made of the `num_docs` first documents of the bitmap that are
+    // greater or equal to the `byte_offset` of the skip reader.
+    Bitmap {
+        num_docs: u32,
+    },
 }
 
 impl Default for BlockInfo {
@@ -116,6 +127,7 @@ impl SkipReader {
             byte_offset: 0,
             remaining_docs: doc_freq,
             position_offset: 0u64,
+            is_bitmap: false,
         };
         if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
             skip_reader.read_block_info();
@@ -123,6 +135,17 @@ impl SkipReader {
         skip_reader
     }
 
+    /// Creates a skip reader over a posting list encoded as a bitmap.
+    ///
+    /// Blocks are not materialized in the bitmap: a block is made of the
+    /// `COMPRESSION_BLOCK_SIZE` next documents, starting from the target of the
+    /// last seek. Only the last block may be incomplete.
+    pub fn for_bitmap(bitmap: OwnedBytes, doc_freq: u32) -> SkipReader {
+        let mut skip_reader = SkipReader::new(OwnedBytes::empty(), 0, IndexRecordOption::Basic);
+        skip_reader.reset_bitmap(bitmap, doc_freq);
+        skip_reader
+    }
+
     pub fn reset(&mut self, data: OwnedBytes, doc_freq: u32) {
         self.last_doc_in_block = if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
             0
@@ -135,11 +158,47 @@ impl SkipReader {
         self.byte_offset = 0;
         self.remaining_docs = doc_freq;
         self.position_offset = 0u64;
+        self.is_bitmap = false;
         if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
             self.read_block_info();
         }
     }
 
+    pub fn reset_bitmap(&mut self, bitmap: OwnedBytes, doc_freq: u32) {
+        self.last_doc_in_previous_block = 0u32;
+        self.owned_read = bitmap;
+        self.remaining_docs = doc_freq;
+        self.position_offset = 0u64;
+        self.is_bitmap = true;
+        self.read_bitmap_block(0);
+    }
+
+    // Positions the reader on the block starting at `first_doc`.
+    fn read_bitmap_block(&mut self, first_doc: DocId) {
+        let (num_docs, last_doc) = bitmap_block_last_doc(
+            self.owned_read.as_slice(),
+            first_doc,
+            COMPRESSION_BLOCK_SIZE,
+        );
+        if num_docs == 0 {
+            self.remaining_docs = 0;
+            self.byte_offset = usize::MAX;
+            self.last_doc_in_block = TERMINATED;
+            self.block_info = BlockInfo::VInt { num_docs: 0 };
+            return;
+        }
+        self.byte_offset = first_doc as usize;
+        // Like for regular posting lists, the last block is open-ended.
+        self.last_doc_in_block = if num_docs == COMPRESSION_BLOCK_SIZE {
+            last_doc
+        } else {
+            TERMINATED
+        };
+        self.block_info = BlockInfo::Bitmap {
+            num_docs: num_docs as u32,
+        };
+    }
+
     // Returns the block max score for this block if available.
     //
     // The block max score is available for all full bitpacked block,
@@ -151,7 +210,7 @@ impl SkipReader {
                 block_wand_term_freq,
                 ..
             } => Some(bm25_weight.score(block_wand_fieldnorm_id, block_wand_term_freq)),
-            BlockInfo::VInt { .. } => None,
+            BlockInfo::VInt { .. } | BlockInfo::Bitmap { .. } => None,
         }
     }
 
@@ -227,6 +286,10 @@ impl SkipReader {
         if self.last_doc_in_block() >= target {
             return false;
         }
+        if self.is_bitmap {
+            self.read_bitmap_block(target);
+            return true;
+        }
         loop {
             self.advance();
             if self.last_doc_in_block() >= target {
@@ -252,6 +315,12 @@ impl SkipReader {
                 self.remaining_docs = 0;
                 self.byte_offset = std::usize::MAX;
             }
+            BlockInfo::Bitmap { .. } => {
+                self.last_doc_in_previous_block = self.last_doc_in_block;
+                // The last block ends with `TERMINATED`, which is past the end of the bitmap.
+                self.read_bitmap_block(self.last_doc_in_block + 1);
+                return;
+            }
         }
         self.last_doc_in_previous_block = self.last_doc_in_block;
         if self.remaining_docs >= COMPRESSION_BLOCK_SIZE as u32 {
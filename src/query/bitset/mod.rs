@@ -1,3 +1,6 @@
+use std::borrow::Borrow;
+use std::sync::Arc;
+
 use common::{BitSet, TinySet};
 
 use crate::docset::{DocSet, TERMINATED};
@@ -12,26 +15,21 @@ use crate::DocId;
 ///
 /// TODO: Consider implementing a `BitTreeSet` in order to advance faster
 /// when the bitset is sparse
-pub struct BitSetDocSet {
-    docs: BitSet,
+///
+/// The bitset can be owned, or shared through an `Arc<BitSet>`.
+pub struct BitSetDocSet<TBitSet = BitSet> {
+    docs: TBitSet,
     cursor_bucket: u32, //< index associated to the current tiny bitset
     cursor_tinybitset: TinySet,
     doc: u32,
 }
 
-impl BitSetDocSet {
-    fn go_to_bucket(&mut self, bucket_addr: u32) {
-        self.cursor_bucket = bucket_addr;
-        self.cursor_tinybitset = self.docs.tinyset(bucket_addr);
-    }
-}
-
-impl From<BitSet> for BitSetDocSet {
-    fn from(docs: BitSet) -> BitSetDocSet {
-        let first_tiny_bitset = if docs.max_value() == 0 {
+impl<TBitSet: Borrow<BitSet> + Send> BitSetDocSet<TBitSet> {
+    fn new(docs: TBitSet) -> BitSetDocSet<TBitSet> {
+        let first_tiny_bitset = if docs.borrow().max_value() == 0 {
             TinySet::empty()
         } else {
-            docs.tinyset(0)
+            docs.borrow().tinyset(0)
         };
         let mut docset = BitSetDocSet {
             docs,
@@ -42,15 +40,36 @@ impl From<BitSet> for BitSetDocSet {
         docset.advance();
         docset
     }
+
+    fn go_to_bucket(&mut self, bucket_addr: u32) {
+        self.cursor_bucket = bucket_addr;
+        self.cursor_tinybitset = self.docs.borrow().tinyset(bucket_addr);
+    }
 }
 
-impl DocSet for BitSetDocSet {
+impl From<BitSet> for BitSetDocSet {
+    fn from(docs: BitSet) -> BitSetDocSet {
+        BitSetDocSet::new(docs)
+    }
+}
+
+impl From<Arc<BitSet>> for BitSetDocSet<Arc<BitSet>> {
+    fn from(docs: Arc<BitSet>) -> BitSetDocSet<Arc<BitSet>> {
+        BitSetDocSet::new(docs)
+    }
+}
+
+impl<TBitSet: Borrow<BitSet> + Send> DocSet for BitSetDocSet<TBitSet> {
     fn advance(&mut self) -> DocId {
         if let Some(lower) = self.cursor_tinybitset.pop_lowest() {
             self.doc = (self.cursor_bucket as u32 * 64u32) | lower;
             return self.doc;
         }
-        if let Some(cursor_bucket) = self.docs.first_non_empty_bucket(self.cursor_bucket + 1) {
+        if let Some(cursor_bucket) = self
+            .docs
+            .borrow()
+            .first_non_empty_bucket(self.cursor_bucket + 1)
+        {
             self.go_to_bucket(cursor_bucket);
             let lower = self.cursor_tinybitset.pop_lowest().unwrap();
             self.doc = (cursor_bucket * 64u32) | lower;
@@ -62,7 +81,7 @@ impl DocSet for BitSetDocSet {
     }
 
     fn seek(&mut self, target: DocId) -> DocId {
-        if target >= self.docs.max_value() {
+        if target >= self.docs.borrow().max_value() {
             self.doc = TERMINATED;
             return TERMINATED;
         }
@@ -81,6 +100,26 @@ impl DocSet for BitSetDocSet {
         }
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        let mut num_filled = 0;
+        while num_filled < buffer.len() && self.doc != TERMINATED {
+            buffer[num_filled] = self.doc;
+            num_filled += 1;
+            // The rest of the current bucket is drained without going through `advance`.
+            let bucket_start = self.cursor_bucket * 64u32;
+            while num_filled < buffer.len() {
+                if let Some(lower) = self.cursor_tinybitset.pop_lowest() {
+                    buffer[num_filled] = bucket_start | lower;
+                    num_filled += 1;
+                } else {
+                    break;
+                }
+            }
+            self.advance();
+        }
+        num_filled
+    }
+
     /// Returns the current document
     fn doc(&self) -> DocId {
         self.doc
@@ -91,7 +130,7 @@ impl DocSet for BitSetDocSet {
     /// but we don't have access to any better
     /// value.
     fn size_hint(&self) -> u32 {
-        self.docs.len() as u32
+        self.docs.borrow().len() as u32
     }
 }
 
@@ -168,6 +207,25 @@ mod tests {
         test_go_through_sequential(&[1, 2, 3, 4, 95, 96, 97, 98, 99]);
     }
 
+    #[test]
+    fn test_docbitset_fill_buffer() {
+        let docs = [1, 2, 3, 63, 64, 65, 200, 999];
+        let mut docset = create_docbitset(&docs, 1_000u32);
+        let mut buffer = [0u32; 3];
+        let mut filled_docs = Vec::new();
+        loop {
+            let num_filled = docset.fill_buffer(&mut buffer);
+            filled_docs.extend_from_slice(&buffer[..num_filled]);
+            if num_filled < buffer.len() {
+                break;
+            }
+        }
+        assert_eq!(filled_docs, docs);
+        assert_eq!(docset.doc(), TERMINATED);
+        let mut empty_docset = create_docbitset(&[], 1_000u32);
+        assert_eq!(empty_docset.fill_buffer(&mut buffer), 0);
+    }
+
     #[test]
     fn test_docbitset_skip() {
         {
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::{fmt, io};
+
+use common::BitSet;
+use fnv::FnvHasher;
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::core::{Searcher, SegmentReader};
+use crate::query::explanation::does_not_match;
+use crate::query::{BitSetDocSet, ConstScorer, EmptyScorer, Explanation, Query, Scorer, Weight};
+use crate::{DocId, Score, SegmentId, SegmentOrdinal};
+
+/// Query matching the documents of precomputed per-segment bitsets.
+///
+/// It makes it possible to evaluate an expensive filter once per reload
+/// (see [`Query::to_bitset`](./trait.Query.html#method.to_bitset)), and
+/// to intersect it cheaply with many queries afterwards.
+/// The bitsets are shared, and not copied, by the scorers.
+///
+/// Segments without a bitset do not match any document.
+/// All of the matched documents get the score 1.0.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::{BitSetQuery, BooleanQuery, Occur, RegexQuery, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.add_document(doc!(title => "A Dairy Cow"))?;
+/// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// // Computed once...
+/// let filter = BitSetQuery::for_query(&searcher, &RegexQuery::from_pattern("d[ai]+ry", title)?)?;
+///
+/// // ... and reused for many queries.
+/// let query = BooleanQuery::new(vec![
+///     (Occur::Must, Box::new(filter.clone())),
+///     (Occur::Must, Box::new(TermQuery::new(Term::from_field_text(title, "the"), IndexRecordOption::Basic))),
+/// ]);
+/// assert_eq!(searcher.search(&query, &Count)?, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BitSetQuery {
+    bitsets: HashMap<SegmentId, Arc<BitSet>>,
+}
+
+impl BitSetQuery {
+    /// Creates a new `BitSetQuery` from per-segment bitsets.
+    pub fn new(bitsets: HashMap<SegmentId, Arc<BitSet>>) -> BitSetQuery {
+        BitSetQuery { bitsets }
+    }
+
+    /// Materializes the documents matching `query` for all of the segments of the searcher.
+    pub fn for_query(searcher: &Searcher, query: &dyn Query) -> crate::Result<BitSetQuery> {
+        let bitsets = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| {
+                let bitset = query.to_bitset(searcher, segment_reader)?;
+                Ok((segment_reader.segment_id(), Arc::new(bitset)))
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(BitSetQuery { bitsets })
+    }
+}
+
+impl fmt::Debug for BitSetQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BitSetQuery(num_segments={})", self.bitsets.len())
+    }
+}
+
+impl Query for BitSetQuery {
+    fn weight(&self, _: &Searcher, _: bool) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(BitSetWeight {
+            bitsets: self.bitsets.clone(),
+        }))
+    }
+
+    fn canonical_form(&self) -> String {
+        // The documents of each segment are identified by a 128-bit hash of their bitset,
+        // so that two different bitsets are very unlikely to share the same canonical form.
+        let mut segment_bitsets: Vec<String> = self
+            .bitsets
+            .iter()
+            .map(|(segment_id, bitset)| {
+                format!("{}:{:032x}", segment_id.uuid_string(), bitset_hash(bitset))
+            })
+            .collect();
+        segment_bitsets.sort();
+        format!("BitSetQuery({})", segment_bitsets.join(", "))
+    }
+}
+
+// Computes a 128-bit hash of a bitset, made of two 64-bit hashes of its serialized form
+// seeded differently. Like `Query::fingerprint`, it is stable across processes.
+fn bitset_hash(bitset: &BitSet) -> u128 {
+    let mut hashers = [FnvHasher::default(), FnvHasher::default()];
+    for (seed, hasher) in hashers.iter_mut().enumerate() {
+        hasher.write_u8(seed as u8);
+    }
+    bitset
+        .serialize(&mut HashingWriter(&mut hashers))
+        .expect("Writing to a hasher should never fail.");
+    (u128::from(hashers[0].finish()) << 64) | u128::from(hashers[1].finish())
+}
+
+// Feeds the written bytes to several hashers, without buffering them.
+struct HashingWriter<'a>(&'a mut [FnvHasher]);
+
+impl io::Write for HashingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for hasher in self.0.iter_mut() {
+            hasher.write(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Weight associated to the `BitSetQuery` query.
+pub struct BitSetWeight {
+    bitsets: HashMap<SegmentId, Arc<BitSet>>,
+}
+
+impl Weight for BitSetWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if let Some(bitset) = self.bitsets.get(&reader.segment_id()) {
+            let docset = BitSetDocSet::from(bitset.clone());
+            Ok(Box::new(ConstScorer::new(docset, boost)))
+        } else {
+            Ok(Box::new(EmptyScorer))
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let matches = self
+            .bitsets
+            .get(&reader.segment_id())
+            .map(|bitset| doc < bitset.max_value() && bitset.contains(doc))
+            .unwrap_or(false);
+        if !matches {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("BitSetQuery", 1.0))
+    }
+}
+
+/// Collects the documents matched by a query into a [`BitSetQuery`].
+///
+/// Like the fruits of the other collectors, the `BitSetQuery` can be cached by the results
+/// cache of the reader.
+#[derive(Debug)]
+pub(crate) struct BitSetCollector;
+
+impl Collector for BitSetCollector {
+    type Fruit = BitSetQuery;
+    type Child = BitSetSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<BitSetSegmentCollector> {
+        Ok(BitSetSegmentCollector {
+            segment_id: segment_reader.segment_id(),
+            bitset: BitSet::with_max_value(segment_reader.max_doc()),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_bitsets: Vec<(SegmentId, BitSet)>,
+    ) -> crate::Result<BitSetQuery> {
+        let bitsets = segment_bitsets
+            .into_iter()
+            .map(|(segment_id, bitset)| (segment_id, Arc::new(bitset)))
+            .collect();
+        Ok(BitSetQuery { bitsets })
+    }
+}
+
+pub(crate) struct BitSetSegmentCollector {
+    segment_id: SegmentId,
+    bitset: BitSet,
+}
+
+impl SegmentCollector for BitSetSegmentCollector {
+    type Fruit = (SegmentId, BitSet);
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.bitset.insert(doc);
+    }
+
+    fn harvest(self) -> (SegmentId, BitSet) {
+        (self.segment_id, self.bitset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::BitSetQuery;
+    use crate::collector::Count;
+    use crate::query::{Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_to_bitset_and_bitset_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.add_document(doc!(text => "b"))?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let segment_reader = searcher.segment_reader(0);
+        let bitset = term_query.to_bitset(&searcher, segment_reader)?;
+        assert_eq!(bitset.max_value(), segment_reader.max_doc());
+        assert!(bitset.contains(0));
+        assert!(!bitset.contains(1));
+        assert!(bitset.contains(2));
+
+        let bitset_query = BitSetQuery::for_query(&searcher, &term_query)?;
+        assert_eq!(searcher.search(&bitset_query, &Count)?, 3);
+        assert!(bitset_query
+            .explain(&searcher, DocAddress::new(0, 2))
+            .is_ok());
+        assert!(bitset_query
+            .explain(&searcher, DocAddress::new(0, 1))
+            .is_err());
+        let other_term_query =
+            TermQuery::new(Term::from_field_text(text, "b"), IndexRecordOption::Basic);
+        assert_eq!(
+            BitSetQuery::for_query(&searcher, &term_query)?.canonical_form(),
+            bitset_query.canonical_form()
+        );
+        assert_ne!(
+            BitSetQuery::for_query(&searcher, &other_term_query)?.canonical_form(),
+            bitset_query.canonical_form()
+        );
+
+        let mut bitsets = HashMap::new();
+        bitsets.insert(segment_reader.segment_id(), Arc::new(bitset));
+        let first_segment_only = BitSetQuery::new(bitsets);
+        assert_eq!(searcher.search(&first_segment_only, &Count)?, 2);
+        assert_eq!(
+            searcher.search(&BitSetQuery::new(HashMap::new()), &Count)?,
+            0
+        );
+        Ok(())
+    }
+}
@@ -2,10 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::fieldnorm::FieldNormReader;
 use crate::query::Explanation;
+use crate::schema::DEFAULT_LENGTH_NORMALIZATION;
 use crate::{Score, Searcher, Term};
 
 const K1: Score = 1.2;
-const B: Score = 0.75;
 
 pub(crate) fn idf(doc_freq: u64, doc_count: u64) -> Score {
     assert!(doc_count >= doc_freq, "{} >= {}", doc_count, doc_freq);
@@ -13,15 +13,15 @@ pub(crate) fn idf(doc_freq: u64, doc_count: u64) -> Score {
     (1.0 + x).ln()
 }
 
-fn cached_tf_component(fieldnorm: u32, average_fieldnorm: Score) -> Score {
-    K1 * (1.0 - B + B * fieldnorm as Score / average_fieldnorm)
+fn cached_tf_component(fieldnorm: u32, average_fieldnorm: Score, b: Score) -> Score {
+    K1 * (1.0 - b + b * fieldnorm as Score / average_fieldnorm)
 }
 
-fn compute_tf_cache(average_fieldnorm: Score) -> [Score; 256] {
+fn compute_tf_cache(average_fieldnorm: Score, b: Score) -> [Score; 256] {
     let mut cache: [Score; 256] = [0.0; 256];
     for (fieldnorm_id, cache_mut) in cache.iter_mut().enumerate() {
         let fieldnorm = FieldNormReader::id_to_fieldnorm(fieldnorm_id as u8);
-        *cache_mut = cached_tf_component(fieldnorm, average_fieldnorm);
+        *cache_mut = cached_tf_component(fieldnorm, average_fieldnorm, b);
     }
     cache
 }
@@ -38,6 +38,10 @@ pub struct Bm25Weight {
     weight: Score,
     cache: [Score; 256],
     average_fieldnorm: Score,
+    // Strength of the length normalization.
+    b: Score,
+    // The term frequencies are impacts, and the score is proportional to them.
+    impacts: bool,
 }
 
 impl Bm25Weight {
@@ -47,6 +51,22 @@ impl Bm25Weight {
             weight: self.weight * boost,
             cache: self.cache,
             average_fieldnorm: self.average_fieldnorm,
+            b: self.b,
+            impacts: self.impacts,
+        }
+    }
+
+    /// Weight for a field storing impacts in place of term frequencies.
+    ///
+    /// The score of a document is the impact of the term, multiplied by the boost.
+    pub fn for_impacts() -> Bm25Weight {
+        Bm25Weight {
+            idf_explain: Explanation::new("<no idf>", 1.0),
+            weight: 1.0,
+            cache: [0.0; 256],
+            average_fieldnorm: 0.0,
+            b: DEFAULT_LENGTH_NORMALIZATION,
+            impacts: true,
         }
     }
 
@@ -61,6 +81,11 @@ impl Bm25Weight {
             );
         }
 
+        let field_type = searcher.schema().get_field_entry(field).field_type();
+        if field_type.has_impacts() {
+            return Ok(Bm25Weight::for_impacts());
+        }
+
         let mut total_num_tokens = 0u64;
         let mut total_num_docs = 0u64;
         for segment_reader in searcher.segment_readers() {
@@ -69,14 +94,11 @@ impl Bm25Weight {
             total_num_docs += u64::from(segment_reader.max_doc());
         }
         let average_fieldnorm = total_num_tokens as Score / total_num_docs as Score;
+        let length_normalization = field_type.length_normalization();
 
-        if terms.len() == 1 {
+        let bm25_weight = if terms.len() == 1 {
             let term_doc_freq = searcher.doc_freq(&terms[0])?;
-            Ok(Bm25Weight::for_one_term(
-                term_doc_freq,
-                total_num_docs,
-                average_fieldnorm,
-            ))
+            Bm25Weight::for_one_term(term_doc_freq, total_num_docs, average_fieldnorm)
         } else {
             let mut idf_sum: Score = 0.0;
             for term in terms {
@@ -84,8 +106,9 @@ impl Bm25Weight {
                 idf_sum += idf(term_doc_freq, total_num_docs);
             }
             let idf_explain = Explanation::new("idf", idf_sum);
-            Ok(Bm25Weight::new(idf_explain, average_fieldnorm))
-        }
+            Bm25Weight::new(idf_explain, average_fieldnorm)
+        };
+        Ok(bm25_weight.with_length_normalization(length_normalization))
     }
 
     pub fn for_one_term(
@@ -109,11 +132,24 @@ impl Bm25Weight {
         Bm25Weight {
             idf_explain,
             weight,
-            cache: compute_tf_cache(average_fieldnorm),
+            cache: compute_tf_cache(average_fieldnorm, DEFAULT_LENGTH_NORMALIZATION),
             average_fieldnorm,
+            b: DEFAULT_LENGTH_NORMALIZATION,
+            impacts: false,
         }
     }
 
+    /// Returns the weight normalizing the term frequencies by the length of the field with
+    /// the strength `b`, see `TextFieldIndexing::set_length_normalization`.
+    pub(crate) fn with_length_normalization(mut self, b: Score) -> Bm25Weight {
+        if b.to_bits() == self.b.to_bits() {
+            return self;
+        }
+        self.cache = compute_tf_cache(self.average_fieldnorm, b);
+        self.b = b;
+        self
+    }
+
     #[inline]
     pub fn score(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
         self.weight * self.tf_factor(fieldnorm_id, term_freq)
@@ -126,6 +162,9 @@ impl Bm25Weight {
     #[inline]
     pub(crate) fn tf_factor(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
         let term_freq = term_freq as Score;
+        if self.impacts {
+            return term_freq;
+        }
         let norm = self.cache[fieldnorm_id as usize];
         term_freq / (term_freq + norm)
     }
@@ -134,6 +173,12 @@ impl Bm25Weight {
         // The explain format is directly copied from Lucene's.
         // (So, Kudos to Lucene)
         let score = self.score(fieldnorm_id, term_freq);
+        if self.impacts {
+            let mut explanation = Explanation::new("TermQuery, product of...", score);
+            explanation.add_const("boost", self.weight);
+            explanation.add_const("impact of the term in the document", term_freq as Score);
+            return explanation;
+        }
 
         let norm = self.cache[fieldnorm_id as usize];
         let term_freq = term_freq as Score;
@@ -146,7 +191,7 @@ impl Bm25Weight {
 
         tf_explanation.add_const("freq, occurrences of term within document", term_freq);
         tf_explanation.add_const("k1, term saturation parameter", K1);
-        tf_explanation.add_const("b, length normalization parameter", B);
+        tf_explanation.add_const("b, length normalization parameter", self.b);
         tf_explanation.add_const(
             "dl, length of field",
             FieldNormReader::id_to_fieldnorm(fieldnorm_id) as Score,
@@ -165,11 +210,49 @@ impl Bm25Weight {
 mod tests {
 
     use super::idf;
-    use crate::{assert_nearly_equals, Score};
+    use crate::collector::TopDocs;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, TEXT};
+    use crate::{assert_nearly_equals, Index, Score, Term};
 
     #[test]
     fn test_idf() {
         let score: Score = 2.0;
         assert_nearly_equals!(idf(1, 2), score.ln());
     }
+
+    #[test]
+    fn test_length_normalization() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let tags_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqs)
+                .set_length_normalization(0.0),
+        );
+        let tags = schema_builder.add_text_field("tags", tags_options);
+        let schema = schema_builder.build();
+        let schema_json = serde_json::to_string(&schema)?;
+        let deserialized_schema: Schema = serde_json::from_str(&schema_json)?;
+        assert_eq!(deserialized_schema, schema);
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a", tags => "a"))?;
+        index_writer.add_document(doc!(text => "a b c d", tags => "a b c d"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let scores = |field| -> crate::Result<Vec<Score>> {
+            let query = TermQuery::new(
+                Term::from_field_text(field, "a"),
+                IndexRecordOption::WithFreqs,
+            );
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(2))?;
+            Ok(top_docs.into_iter().map(|(score, _)| score).collect())
+        };
+        let text_scores = scores(text)?;
+        assert!(text_scores[0] > text_scores[1]);
+        let tags_scores = scores(tags)?;
+        assert_nearly_equals!(tags_scores[0], tags_scores[1]);
+        Ok(())
+    }
 }
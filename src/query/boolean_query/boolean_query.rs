@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 
 use super::boolean_weight::BooleanWeight;
-use crate::query::{Occur, Query, TermQuery, Weight};
+use crate::query::{Occur, ProfiledWeight, Query, TermQuery, Weight};
 use crate::schema::{IndexRecordOption, Term};
 use crate::Searcher;
 
@@ -126,15 +126,18 @@ use crate::Searcher;
 #[derive(Debug)]
 pub struct BooleanQuery {
     subqueries: Vec<(Occur, Box<dyn Query>)>,
+    minimum_number_should_match: usize,
 }
 
 impl Clone for BooleanQuery {
     fn clone(&self) -> Self {
-        self.subqueries
-            .iter()
-            .map(|(occur, subquery)| (*occur, subquery.box_clone()))
-            .collect::<Vec<_>>()
-            .into()
+        BooleanQuery::from(
+            self.subqueries
+                .iter()
+                .map(|(occur, subquery)| (*occur, subquery.box_clone()))
+                .collect::<Vec<_>>(),
+        )
+        .with_minimum_number_should_match(self.minimum_number_should_match)
     }
 }
 
@@ -153,7 +156,29 @@ impl Query for BooleanQuery {
                 Ok((*occur, subquery.weight(searcher, scoring_enabled)?))
             })
             .collect::<crate::Result<_>>()?;
-        Ok(Box::new(BooleanWeight::new(sub_weights, scoring_enabled)))
+        Ok(Box::new(BooleanWeight::new(
+            sub_weights,
+            scoring_enabled,
+            self.minimum_number_should_match,
+        )))
+    }
+
+    fn canonical_form(&self) -> String {
+        // The clauses are commutative.
+        let mut clauses: Vec<String> = self
+            .subqueries
+            .iter()
+            .map(|(occur, subquery)| format!("{}{}", occur, subquery.canonical_form()))
+            .collect();
+        clauses.sort();
+        if self.minimum_number_should_match > 1 {
+            return format!(
+                "BooleanQuery({}, minimum_number_should_match={})",
+                clauses.join(", "),
+                self.minimum_number_should_match
+            );
+        }
+        format!("BooleanQuery({})", clauses.join(", "))
     }
 
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
@@ -161,12 +186,71 @@ impl Query for BooleanQuery {
             subquery.query_terms(terms);
         }
     }
+
+    fn profiled_weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<ProfiledWeight> {
+        let sub_weights = self
+            .subqueries
+            .iter()
+            .map(|(occur, subquery)| {
+                let mut sub_weight = subquery.profiled_weight(searcher, scoring_enabled)?;
+                sub_weight.set_description(format!("{:?} {:?}", occur, subquery));
+                Ok((*occur, sub_weight))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        let children = sub_weights
+            .iter()
+            .map(|(_occur, sub_weight)| sub_weight.profile_handle())
+            .collect();
+        let sub_weights = sub_weights
+            .into_iter()
+            .map(|(occur, sub_weight)| (occur, Box::new(sub_weight) as Box<dyn Weight>))
+            .collect();
+        Ok(ProfiledWeight::new(
+            "BooleanQuery".to_string(),
+            Box::new(BooleanWeight::new(
+                sub_weights,
+                scoring_enabled,
+                self.minimum_number_should_match,
+            )),
+            children,
+        ))
+    }
 }
 
 impl BooleanQuery {
     /// Creates a new boolean query.
     pub fn new(subqueries: Vec<(Occur, Box<dyn Query>)>) -> BooleanQuery {
-        BooleanQuery { subqueries }
+        BooleanQuery {
+            subqueries,
+            minimum_number_should_match: 0,
+        }
+    }
+
+    /// Requires documents to match at least `minimum_number_should_match` of the `Should`
+    /// clauses, e.g. 2 of 5.
+    ///
+    /// The should clauses are then iterated together, counting the clauses matching each
+    /// document, which is much cheaper than the union of all of the combinations of the
+    /// required number of clauses. Values of 0 and 1 leave the query unchanged: the should
+    /// clauses are optional if there are must clauses, and at least one of them has to match
+    /// otherwise.
+    #[must_use]
+    pub fn with_minimum_number_should_match(
+        mut self,
+        minimum_number_should_match: usize,
+    ) -> BooleanQuery {
+        self.minimum_number_should_match = minimum_number_should_match;
+        self
+    }
+
+    /// Returns the minimum number of `Should` clauses a document has to match, see
+    /// [`BooleanQuery::with_minimum_number_should_match`].
+    pub fn minimum_number_should_match(&self) -> usize {
+        self.minimum_number_should_match
     }
 
     /// Returns the intersection of the queries.
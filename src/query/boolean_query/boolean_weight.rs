@@ -7,8 +7,8 @@ use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner, SumWithCoor
 use crate::query::term_query::TermScorer;
 use crate::query::weight::{for_each_pruning_scorer, for_each_scorer};
 use crate::query::{
-    intersect_scorers, EmptyScorer, Exclude, Explanation, Occur, RequiredOptionalScorer, Scorer,
-    Union, Weight,
+    intersect_scorers, Disjunction, EmptyScorer, Exclude, Explanation, Occur,
+    RequiredOptionalScorer, Scorer, Union, Weight,
 };
 use crate::{DocId, Score};
 
@@ -60,13 +60,19 @@ fn into_box_scorer<TScoreCombiner: ScoreCombiner>(scorer: SpecializedScorer) ->
 pub struct BooleanWeight {
     weights: Vec<(Occur, Box<dyn Weight>)>,
     scoring_enabled: bool,
+    minimum_number_should_match: usize,
 }
 
 impl BooleanWeight {
-    pub fn new(weights: Vec<(Occur, Box<dyn Weight>)>, scoring_enabled: bool) -> BooleanWeight {
+    pub fn new(
+        weights: Vec<(Occur, Box<dyn Weight>)>,
+        scoring_enabled: bool,
+        minimum_number_should_match: usize,
+    ) -> BooleanWeight {
         BooleanWeight {
             weights,
             scoring_enabled,
+            minimum_number_should_match,
         }
     }
 
@@ -93,6 +99,10 @@ impl BooleanWeight {
     ) -> crate::Result<SpecializedScorer> {
         let mut per_occur_scorers = self.per_occur_scorers(reader, boost)?;
 
+        if self.minimum_number_should_match > 1 {
+            return self.complex_scorer_with_min_should_match::<TScoreCombiner>(per_occur_scorers);
+        }
+
         let should_scorer_opt: Option<SpecializedScorer> = per_occur_scorers
             .remove(&Occur::Should)
             .map(scorer_union::<TScoreCombiner>);
@@ -141,11 +151,44 @@ impl BooleanWeight {
     }
 }
 
+impl BooleanWeight {
+    // With more than one required should clause, the should clauses are as required as the
+    // must clauses, and they can no longer be turned into a block WAND union.
+    fn complex_scorer_with_min_should_match<TScoreCombiner: ScoreCombiner>(
+        &self,
+        mut per_occur_scorers: HashMap<Occur, Vec<Box<dyn Scorer>>>,
+    ) -> crate::Result<SpecializedScorer> {
+        let should_scorers = per_occur_scorers.remove(&Occur::Should).unwrap_or_default();
+        if should_scorers.len() < self.minimum_number_should_match {
+            return Ok(SpecializedScorer::Other(Box::new(EmptyScorer)));
+        }
+        let should_scorer: Box<dyn Scorer> = Box::new(Disjunction::new(
+            should_scorers,
+            TScoreCombiner::default(),
+            self.minimum_number_should_match,
+        ));
+        let mut required_scorers = per_occur_scorers.remove(&Occur::Must).unwrap_or_default();
+        required_scorers.push(should_scorer);
+        let positive_scorer = intersect_scorers(required_scorers);
+        let scorer = match per_occur_scorers.remove(&Occur::MustNot) {
+            Some(exclude_scorers) => {
+                let exclude_scorer =
+                    into_box_scorer::<DoNothingCombiner>(scorer_union::<DoNothingCombiner>(
+                        exclude_scorers,
+                    ));
+                Box::new(Exclude::new(positive_scorer, exclude_scorer))
+            }
+            None => positive_scorer,
+        };
+        Ok(SpecializedScorer::Other(scorer))
+    }
+}
+
 impl Weight for BooleanWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         if self.weights.is_empty() {
             Ok(Box::new(EmptyScorer))
-        } else if self.weights.len() == 1 {
+        } else if self.weights.len() == 1 && self.minimum_number_should_match <= 1 {
             let &(occur, ref weight) = &self.weights[0];
             if occur == Occur::MustNot {
                 Ok(Box::new(EmptyScorer))
@@ -166,6 +166,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_boolean_query_minimum_number_should_match() -> crate::Result<()> {
+        let (index, text_field) = aux_test_helper()?;
+        let make_term_query = |text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let searcher = index.reader()?.searcher();
+        let matching_docs = |boolean_query: &BooleanQuery| {
+            searcher
+                .search(boolean_query, &TEST_COLLECTOR_WITH_SCORE)
+                .unwrap()
+                .docs()
+                .iter()
+                .map(|doc| doc.doc_id)
+                .collect::<Vec<DocId>>()
+        };
+        let should_clauses = || {
+            vec![
+                (Occur::Should, make_term_query("a")),
+                (Occur::Should, make_term_query("b")),
+                (Occur::Should, make_term_query("d")),
+            ]
+        };
+        {
+            let boolean_query = BooleanQuery::new(should_clauses());
+            assert_eq!(matching_docs(&boolean_query), vec![0, 1, 2, 3, 4]);
+            let boolean_query = boolean_query.with_minimum_number_should_match(1);
+            assert_eq!(matching_docs(&boolean_query), vec![0, 1, 2, 3, 4]);
+        }
+        {
+            let boolean_query =
+                BooleanQuery::new(should_clauses()).with_minimum_number_should_match(2);
+            assert_eq!(boolean_query.minimum_number_should_match(), 2);
+            assert_eq!(matching_docs(&boolean_query), vec![0, 3]);
+            assert_eq!(matching_docs(&boolean_query.clone()), vec![0, 3]);
+            assert_eq!(boolean_query.count(&searcher)?, 2);
+            let scoring_query = BooleanQuery::new(should_clauses());
+            for doc_id in [0, 3] {
+                let doc_address = DocAddress::new(0, doc_id);
+                assert_nearly_equals!(
+                    boolean_query.explain(&searcher, doc_address)?.value(),
+                    scoring_query.explain(&searcher, doc_address)?.value()
+                );
+            }
+        }
+        {
+            let boolean_query =
+                BooleanQuery::new(should_clauses()).with_minimum_number_should_match(3);
+            assert_eq!(matching_docs(&boolean_query), vec![3]);
+            let boolean_query =
+                BooleanQuery::new(should_clauses()).with_minimum_number_should_match(4);
+            assert!(matching_docs(&boolean_query).is_empty());
+        }
+        {
+            let mut clauses = should_clauses();
+            clauses.push((Occur::Must, make_term_query("c")));
+            let boolean_query = BooleanQuery::new(clauses).with_minimum_number_should_match(2);
+            assert_eq!(matching_docs(&boolean_query), vec![0, 3]);
+        }
+        {
+            let mut clauses = should_clauses();
+            clauses.push((Occur::MustNot, make_term_query("c")));
+            let boolean_query = BooleanQuery::new(clauses).with_minimum_number_should_match(2);
+            assert!(matching_docs(&boolean_query).is_empty());
+        }
+        Ok(())
+    }
+
     #[test]
     pub fn test_boolean_query_two_excluded() -> crate::Result<()> {
         let (index, text_field) = aux_test_helper()?;
@@ -12,8 +12,8 @@ use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
 /// The score of each document, is the score of the underlying query multiplied by the `boost`
 /// factor.
 pub struct BoostQuery {
-    query: Box<dyn Query>,
-    boost: Score,
+    pub(crate) query: Box<dyn Query>,
+    pub(crate) boost: Score,
 }
 
 impl BoostQuery {
@@ -49,6 +49,21 @@ impl Query for BoostQuery {
         Ok(boosted_weight)
     }
 
+    fn canonical_form(&self) -> String {
+        // Nested boosts are multiplied, and a boost of 1 is dropped.
+        let mut boost = self.boost;
+        let mut query: &dyn Query = self.query.as_ref();
+        while let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+            boost *= boost_query.boost;
+            query = boost_query.query.as_ref();
+        }
+        if (boost - 1.0).abs() < Score::EPSILON {
+            query.canonical_form()
+        } else {
+            format!("Boost(query={}, boost={})", query.canonical_form(), boost)
+        }
+    }
+
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         self.query.query_terms(terms)
     }
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::query::explanation::does_not_match;
+use crate::query::{Explanation, Query, Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
+
+/// `BoostingQuery` matches the documents of its `positive` query, and demotes those also
+/// matching its `negative` query.
+///
+/// The score of the documents matching the `negative` query is the score of the `positive`
+/// query multiplied by the `negative_boost`, typically between 0 and 1. Unlike a `MustNot`
+/// clause, the documents matching the `negative` query are still returned. The `negative`
+/// query is not scored.
+///
+/// ```rust
+/// use tantivy::collector::TopDocs;
+/// use tantivy::query::{BoostingQuery, Query, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+/// use tantivy::{doc, DocAddress, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "apple pie"))?;
+/// index_writer.add_document(doc!(title => "apple"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let term_query = |text| -> Box<dyn Query> {
+///     Box::new(TermQuery::new(
+///         Term::from_field_text(title, text),
+///         IndexRecordOption::Basic,
+///     ))
+/// };
+/// let query = BoostingQuery::new(term_query("apple"), term_query("pie"), 0.1);
+/// let top_docs = searcher.search(&query, &TopDocs::with_limit(2))?;
+/// assert_eq!(top_docs.len(), 2);
+/// assert_eq!(top_docs[0].1, DocAddress::new(0, 1));
+/// # Ok(())
+/// # }
+/// ```
+pub struct BoostingQuery {
+    positive: Box<dyn Query>,
+    negative: Box<dyn Query>,
+    negative_boost: Score,
+}
+
+impl BoostingQuery {
+    /// Builds a boosting query.
+    pub fn new(
+        positive: Box<dyn Query>,
+        negative: Box<dyn Query>,
+        negative_boost: Score,
+    ) -> BoostingQuery {
+        BoostingQuery {
+            positive,
+            negative,
+            negative_boost,
+        }
+    }
+}
+
+impl Clone for BoostingQuery {
+    fn clone(&self) -> Self {
+        BoostingQuery {
+            positive: self.positive.box_clone(),
+            negative: self.negative.box_clone(),
+            negative_boost: self.negative_boost,
+        }
+    }
+}
+
+impl fmt::Debug for BoostingQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Boosting(positive={:?}, negative={:?}, negative_boost={})",
+            self.positive, self.negative, self.negative_boost
+        )
+    }
+}
+
+impl Query for BoostingQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let positive_weight = self.positive.weight(searcher, scoring_enabled)?;
+        if !scoring_enabled {
+            // The negative query only affects the scores.
+            return Ok(positive_weight);
+        }
+        let negative_weight = self.negative.weight(searcher, false)?;
+        Ok(Box::new(BoostingWeight {
+            positive_weight,
+            negative_weight,
+            negative_boost: self.negative_boost,
+        }))
+    }
+
+    fn canonical_form(&self) -> String {
+        format!(
+            "Boosting(positive={}, negative={}, negative_boost={})",
+            self.positive.canonical_form(),
+            self.negative.canonical_form(),
+            self.negative_boost
+        )
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        self.positive.query_terms(terms)
+    }
+}
+
+struct BoostingWeight {
+    positive_weight: Box<dyn Weight>,
+    negative_weight: Box<dyn Weight>,
+    negative_boost: Score,
+}
+
+impl Weight for BoostingWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let positive_scorer = self.positive_weight.scorer(reader, boost)?;
+        let negative_scorer = self.negative_weight.scorer(reader, 1.0)?;
+        Ok(Box::new(BoostingScorer {
+            positive_scorer,
+            negative_scorer,
+            negative_boost: self.negative_boost,
+        }))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let mut negative_scorer = self.negative_weight.scorer(reader, 1.0)?;
+        let description = if negative_scorer.seek(doc) == doc {
+            format!("Boosting. Negative boost x{} of ...", self.negative_boost)
+        } else {
+            "Boosting. Not matching the negative query ...".to_string()
+        };
+        let mut explanation = Explanation::new(description, scorer.score());
+        explanation.add_detail(self.positive_weight.explain(reader, doc)?);
+        Ok(explanation)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        self.positive_weight.count(reader)
+    }
+}
+
+struct BoostingScorer {
+    positive_scorer: Box<dyn Scorer>,
+    negative_scorer: Box<dyn Scorer>,
+    negative_boost: Score,
+}
+
+impl DocSet for BoostingScorer {
+    fn advance(&mut self) -> DocId {
+        self.positive_scorer.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.positive_scorer.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.positive_scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.positive_scorer.size_hint()
+    }
+}
+
+impl Scorer for BoostingScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.doc();
+        let score = self.positive_scorer.score();
+        if self.negative_scorer.doc() <= doc && self.negative_scorer.seek(doc) == doc {
+            score * self.negative_boost
+        } else {
+            score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoostingQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Index, Term};
+
+    #[test]
+    fn test_boosting_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.add_document(doc!(text => "b"))?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |text_value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text, text_value),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let query = BoostingQuery::new(term_query("a"), term_query("b"), 0.5);
+        assert_eq!(query.count(&searcher)?, 3);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+        let doc_ids: Vec<u32> = top_docs
+            .iter()
+            .map(|(_, doc_address)| doc_address.doc_id)
+            .collect();
+        assert_eq!(doc_ids, vec![1, 0, 3]);
+        let positive_score = |doc_id: u32| {
+            term_query("a")
+                .explain(&searcher, DocAddress::new(0, doc_id))
+                .unwrap()
+                .value()
+        };
+        assert_nearly_equals!(top_docs[0].0, positive_score(1));
+        assert_nearly_equals!(top_docs[1].0, positive_score(0) * 0.5);
+        let explanation = query.explain(&searcher, DocAddress::new(0, 3))?;
+        assert_nearly_equals!(explanation.value(), positive_score(3) * 0.5);
+        assert!(query.explain(&searcher, DocAddress::new(0, 2)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::query::bitset_query::BitSetCollector;
+use crate::query::explanation::does_not_match;
+use crate::query::{ConstScorer, Explanation, Query, Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
+
+/// `ConstantScoreQuery` is a wrapper over a query, giving the same score to all of the
+/// documents it matches.
+///
+/// The underlying query is used as a filter: its weight is built with scoring disabled, which
+/// spares the computation of its scores, and the documents it matches do not depend on the
+/// other clauses of the query.
+///
+/// When the searcher belongs to a reader with a results cache, see
+/// [`IndexReaderBuilder::results_cache_capacity`](crate::IndexReaderBuilder::results_cache_capacity),
+/// the documents matched by the filter are collected into per-segment bitsets, which are
+/// cached along with the fruits of the searches of the current generation: the next queries
+/// with the same filter intersect its bitsets instead of evaluating it again. Otherwise, the
+/// filter is evaluated lazily like any other query.
+///
+/// The query parser turns the `filter(...)` clauses into constant score queries with a score
+/// of 0, so that they restrict the documents matched without affecting their scores, see
+/// [`QueryParser::set_filter_syntax`](crate::query::QueryParser::set_filter_syntax).
+pub struct ConstantScoreQuery {
+    pub(crate) query: Box<dyn Query>,
+    pub(crate) score: Score,
+}
+
+impl ConstantScoreQuery {
+    /// Builds a constant score query.
+    pub fn new(query: Box<dyn Query>, score: Score) -> ConstantScoreQuery {
+        ConstantScoreQuery { query, score }
+    }
+
+    /// Returns the underlying query.
+    pub fn filter(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+
+    /// Returns the score of the matched documents.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+}
+
+impl Clone for ConstantScoreQuery {
+    fn clone(&self) -> Self {
+        ConstantScoreQuery {
+            query: self.query.box_clone(),
+            score: self.score,
+        }
+    }
+}
+
+impl fmt::Debug for ConstantScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ConstantScore(query={:?}, score={})",
+            self.query, self.score
+        )
+    }
+}
+
+impl Query for ConstantScoreQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weight = match searcher.results_cache() {
+            Some(results_cache) => {
+                let filter =
+                    results_cache.search(searcher, self.query.as_ref(), &BitSetCollector)?;
+                filter.weight(searcher, false)?
+            }
+            None => self.query.weight(searcher, false)?,
+        };
+        if !scoring_enabled {
+            return Ok(weight);
+        }
+        Ok(Box::new(ConstantScoreWeight {
+            weight,
+            score: self.score,
+        }))
+    }
+
+    fn canonical_form(&self) -> String {
+        format!(
+            "ConstantScore(query={}, score={})",
+            self.query.canonical_form(),
+            self.score
+        )
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        self.query.query_terms(terms)
+    }
+}
+
+struct ConstantScoreWeight {
+    weight: Box<dyn Weight>,
+    score: Score,
+}
+
+impl Weight for ConstantScoreWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let scorer = self.weight.scorer(reader, 1.0)?;
+        Ok(Box::new(ConstScorer::new(scorer, self.score * boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("ConstantScore", scorer.score()))
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        self.weight.count(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstantScoreQuery;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{BooleanQuery, Occur, Query, QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Index, ReloadPolicy, Term};
+
+    #[test]
+    fn test_constant_score_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.add_document(doc!(text => "a a a"))?;
+        index_writer.add_document(doc!(text => "b"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |text_value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text, text_value),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let query = ConstantScoreQuery::new(term_query("a"), 2.0);
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+        assert_eq!(
+            top_docs,
+            vec![(2.0, DocAddress::new(0, 0)), (2.0, DocAddress::new(0, 1))]
+        );
+        assert_nearly_equals!(
+            query.explain(&searcher, DocAddress::new(0, 1))?.value(),
+            2.0
+        );
+        assert!(query.explain(&searcher, DocAddress::new(0, 2)).is_err());
+
+        // The filter restricts the documents without changing their score.
+        let filtered_query = BooleanQuery::new(vec![
+            (Occur::Must, term_query("b")),
+            (
+                Occur::Must,
+                Box::new(ConstantScoreQuery::new(term_query("a"), 0.0)),
+            ),
+        ]);
+        let top_docs = searcher.search(&filtered_query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 1);
+        assert_nearly_equals!(
+            top_docs[0].0,
+            term_query("b")
+                .explain(&searcher, DocAddress::new(0, 0))?
+                .value()
+        );
+
+        let mut query_parser = QueryParser::for_index(&index, vec![text]);
+        query_parser.set_filter_syntax();
+        let parsed_query = query_parser.parse_query("+b +filter(a)")?;
+        assert_eq!(
+            searcher.search(&parsed_query, &TopDocs::with_limit(10))?,
+            top_docs
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_constant_score_query_cached_filter() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.add_document(doc!(text => "a c"))?;
+        index_writer.add_document(doc!(text => "b c"))?;
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .results_cache_capacity(10)
+            .try_into()?;
+        let mut query_parser = QueryParser::for_index(&index, vec![text]);
+        query_parser.set_filter_syntax();
+        let searcher = reader.searcher();
+
+        let query = query_parser.parse_query("+b +filter(a)")?;
+        assert_eq!(searcher.search(&query, &Count)?, 1);
+        let stats = reader.results_cache_stats();
+        assert_eq!((stats.num_entries, stats.cache_misses), (1, 1));
+        // The bitsets of the filter are reused by another query.
+        let query = query_parser.parse_query("+c +filter(a)")?;
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(top_docs[0].1, DocAddress::new(0, 1));
+        assert_eq!(reader.results_cache_stats().cache_hits, 1);
+        assert_nearly_equals!(
+            query.explain(&searcher, DocAddress::new(0, 1))?.value(),
+            top_docs[0].0
+        );
+
+        // The bitsets of the previous generation are not reused.
+        let num_cache_hits = reader.results_cache_stats().cache_hits;
+        index_writer.delete_term(Term::from_field_text(text, "b"));
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let query = query_parser.parse_query("+b +filter(a)")?;
+        assert_eq!(reader.searcher().search(&query, &Count)?, 1);
+        assert_eq!(reader.results_cache_stats().cache_hits, num_cache_hits);
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::query::explanation::does_not_match;
+use crate::query::score_combiner::{DisjunctionMaxCombiner, DoNothingCombiner};
+use crate::query::{Disjunction, EmptyScorer, Explanation, Query, Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
+
+/// `DisMaxQuery` matches the documents matched by any of its disjuncts, and scores them with
+/// the highest score of the matching disjuncts.
+///
+/// Summing the scores of the disjuncts, as a [`BooleanQuery`](crate::query::BooleanQuery) of
+/// `Should` clauses does, over-rewards the documents repeating the same content in several
+/// fields when searching for the same terms in each field. Taking the maximum scores the
+/// document on its best field instead.
+///
+/// The scores of the other matching disjuncts are multiplied by the `tie_breaker` and added to
+/// the maximum, so that a document matching in several fields still ranks above a document
+/// matching in a single one. A `tie_breaker` of 0 takes the maximum only, and a `tie_breaker`
+/// of 1 sums the scores.
+///
+/// ```rust
+/// use tantivy::collector::TopDocs;
+/// use tantivy::query::{DisMaxQuery, Query, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib", body => "diary"))?;
+/// index_writer.add_document(doc!(title => "A Dairy Cow", body => "diary"))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let term_query = |field, text| -> Box<dyn Query> {
+///     Box::new(TermQuery::new(
+///         Term::from_field_text(field, text),
+///         IndexRecordOption::Basic,
+///     ))
+/// };
+/// let query = DisMaxQuery::new(
+///     vec![term_query(title, "diary"), term_query(body, "diary")],
+///     0.1,
+/// );
+/// let top_docs = searcher.search(&query, &TopDocs::with_limit(2))?;
+/// assert_eq!(top_docs.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DisMaxQuery {
+    disjuncts: Vec<Box<dyn Query>>,
+    tie_breaker: Score,
+}
+
+impl DisMaxQuery {
+    /// Builds a `DisMaxQuery` over the given `disjuncts`.
+    ///
+    /// The `tie_breaker` is expected to be between 0 and 1.
+    pub fn new(disjuncts: Vec<Box<dyn Query>>, tie_breaker: Score) -> DisMaxQuery {
+        DisMaxQuery {
+            disjuncts,
+            tie_breaker,
+        }
+    }
+
+    /// Returns the disjuncts of the query.
+    pub fn disjuncts(&self) -> &[Box<dyn Query>] {
+        &self.disjuncts
+    }
+
+    /// Returns the multiplier of the scores of the disjuncts other than the best one.
+    pub fn tie_breaker(&self) -> Score {
+        self.tie_breaker
+    }
+}
+
+impl Clone for DisMaxQuery {
+    fn clone(&self) -> Self {
+        DisMaxQuery {
+            disjuncts: self
+                .disjuncts
+                .iter()
+                .map(|disjunct| disjunct.box_clone())
+                .collect(),
+            tie_breaker: self.tie_breaker,
+        }
+    }
+}
+
+impl fmt::Debug for DisMaxQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DisMax(disjuncts={:?}, tie_breaker={})",
+            self.disjuncts, self.tie_breaker
+        )
+    }
+}
+
+impl Query for DisMaxQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weights = self
+            .disjuncts
+            .iter()
+            .map(|disjunct| disjunct.weight(searcher, scoring_enabled))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Box::new(DisMaxWeight {
+            weights,
+            tie_breaker: self.tie_breaker,
+            scoring_enabled,
+        }))
+    }
+
+    fn canonical_form(&self) -> String {
+        // The disjuncts are commutative.
+        let mut disjuncts: Vec<String> = self
+            .disjuncts
+            .iter()
+            .map(|disjunct| disjunct.canonical_form())
+            .collect();
+        disjuncts.sort();
+        format!(
+            "DisMax({}, tie_breaker={})",
+            disjuncts.join(", "),
+            self.tie_breaker
+        )
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        for disjunct in &self.disjuncts {
+            disjunct.query_terms(terms);
+        }
+    }
+}
+
+struct DisMaxWeight {
+    weights: Vec<Box<dyn Weight>>,
+    tie_breaker: Score,
+    scoring_enabled: bool,
+}
+
+impl Weight for DisMaxWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let mut scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<crate::Result<Vec<_>>>()?;
+        match scorers.len() {
+            0 => Ok(Box::new(EmptyScorer)),
+            1 => Ok(scorers.pop().unwrap()),
+            _ if self.scoring_enabled => Ok(Box::new(Disjunction::new(
+                scorers,
+                DisjunctionMaxCombiner::with_tie_breaker(self.tie_breaker),
+                1,
+            ))),
+            _ => Ok(Box::new(Disjunction::new(scorers, DoNothingCombiner, 1))),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new(
+            format!(
+                "DisMax. Max of the scores of the matching disjuncts, plus {} times the sum of \
+                 the others",
+                self.tie_breaker
+            ),
+            scorer.score(),
+        );
+        for weight in &self.weights {
+            if let Ok(child_explanation) = weight.explain(reader, doc) {
+                explanation.add_detail(child_explanation);
+            }
+        }
+        Ok(explanation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisMaxQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{BooleanQuery, Occur, Query, TermQuery};
+    use crate::schema::{Field, IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Index, Term};
+
+    fn term_query(field: Field, text: &str) -> Box<dyn Query> {
+        Box::new(TermQuery::new(
+            Term::from_field_text(field, text),
+            IndexRecordOption::WithFreqs,
+        ))
+    }
+
+    #[test]
+    fn test_dis_max_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "albatross", body => "albatross"))?;
+        index_writer.add_document(doc!(title => "albatross"))?;
+        index_writer.add_document(doc!(body => "albatross"))?;
+        index_writer.add_document(doc!(title => "seagull", body => "seagull"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let explain = |query: &dyn Query, doc_id: u32| {
+            query
+                .explain(&searcher, DocAddress::new(0, doc_id))
+                .unwrap()
+                .value()
+        };
+        let disjuncts = || {
+            vec![
+                term_query(title, "albatross"),
+                term_query(body, "albatross"),
+            ]
+        };
+
+        let max_query = DisMaxQuery::new(disjuncts(), 0.0);
+        assert_eq!(max_query.count(&searcher)?, 3);
+        let title_score = explain(&*term_query(title, "albatross"), 0);
+        let body_score = explain(&*term_query(body, "albatross"), 0);
+        assert_nearly_equals!(explain(&max_query, 0), title_score.max(body_score));
+
+        let tie_breaker_query = DisMaxQuery::new(disjuncts(), 0.5);
+        assert_nearly_equals!(
+            explain(&tie_breaker_query, 0),
+            title_score.max(body_score) + 0.5 * title_score.min(body_score)
+        );
+        assert_nearly_equals!(
+            explain(&tie_breaker_query, 1),
+            explain(&*term_query(title, "albatross"), 1)
+        );
+        let explanation = tie_breaker_query.explain(&searcher, DocAddress::new(0, 0))?;
+        assert!(explanation.to_pretty_json().contains(
+            "DisMax. Max of the scores of the matching disjuncts, plus 0.5 times the sum of the \
+             others"
+        ));
+
+        let sum_query = DisMaxQuery::new(disjuncts(), 1.0);
+        let boolean_query = BooleanQuery::new(
+            disjuncts()
+                .into_iter()
+                .map(|disjunct| (Occur::Should, disjunct))
+                .collect(),
+        );
+        assert_nearly_equals!(explain(&sum_query, 0), explain(&boolean_query, 0));
+
+        let top_docs = searcher.search(&tie_breaker_query.clone(), &TopDocs::with_limit(3))?;
+        assert_eq!(top_docs[0].1, DocAddress::new(0, 0));
+        assert_eq!(top_docs.len(), 3);
+        assert_eq!(DisMaxQuery::new(vec![], 0.1).count(&searcher)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dis_max_query_canonical_form() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let query = DisMaxQuery::new(vec![term_query(title, "a"), term_query(body, "a")], 0.1);
+        let swapped_query =
+            DisMaxQuery::new(vec![term_query(body, "a"), term_query(title, "a")], 0.1);
+        assert_eq!(query.canonical_form(), swapped_query.canonical_form());
+        let other_query =
+            DisMaxQuery::new(vec![term_query(title, "a"), term_query(body, "a")], 0.2);
+        assert_ne!(query.canonical_form(), other_query.canonical_form());
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::docset::{DocSet, TERMINATED};
+use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner};
+use crate::query::Scorer;
+use crate::{DocId, Score};
+
+// Wraps a scorer so that the heap of scorers pops the scorer with the lowest doc first.
+struct ScorerWrapper<TScorer> {
+    scorer: TScorer,
+    doc: DocId,
+}
+
+impl<TScorer: Scorer> ScorerWrapper<TScorer> {
+    fn new(scorer: TScorer) -> Self {
+        let doc = scorer.doc();
+        ScorerWrapper { scorer, doc }
+    }
+
+    fn advance(&mut self) -> DocId {
+        self.doc = self.scorer.advance();
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.doc = self.scorer.seek(target);
+        self.doc
+    }
+}
+
+impl<TScorer> PartialEq for ScorerWrapper<TScorer> {
+    fn eq(&self, other: &Self) -> bool {
+        self.doc == other.doc
+    }
+}
+
+impl<TScorer> Eq for ScorerWrapper<TScorer> {}
+
+impl<TScorer> PartialOrd for ScorerWrapper<TScorer> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<TScorer> Ord for ScorerWrapper<TScorer> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.doc.cmp(&self.doc)
+    }
+}
+
+/// Creates a `DocSet` iterating through the documents matched by at least
+/// `minimum_matches_required` of its scorers.
+///
+/// The scores of the matching scorers are combined with `TScoreCombiner`. Unlike
+/// [`Union`](crate::query::Union), which buffers the documents of its scorers by blocks,
+/// the scorers are kept in a heap ordered by their current document. This makes it possible
+/// to count the scorers matching each document.
+pub struct Disjunction<TScorer, TScoreCombiner = DoNothingCombiner> {
+    scorers: BinaryHeap<ScorerWrapper<TScorer>>,
+    minimum_matches_required: usize,
+    score_combiner: TScoreCombiner,
+    doc: DocId,
+    score: Score,
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Disjunction<TScorer, TScoreCombiner> {
+    /// Creates a `Disjunction` matching the documents matched by at least
+    /// `minimum_matches_required` of the `scorers`, or by at least one of them if
+    /// `minimum_matches_required` is 0.
+    pub fn new<I: IntoIterator<Item = TScorer>>(
+        scorers: I,
+        score_combiner: TScoreCombiner,
+        minimum_matches_required: usize,
+    ) -> Self {
+        let scorers = scorers
+            .into_iter()
+            .map(ScorerWrapper::new)
+            .filter(|scorer| scorer.doc != TERMINATED)
+            .collect();
+        let mut disjunction = Disjunction {
+            scorers,
+            minimum_matches_required: minimum_matches_required.max(1),
+            score_combiner,
+            doc: TERMINATED,
+            score: 0.0,
+        };
+        disjunction.advance();
+        disjunction
+    }
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> DocSet
+    for Disjunction<TScorer, TScoreCombiner>
+{
+    fn advance(&mut self) -> DocId {
+        let mut num_matches = 0;
+        while let Some(mut scorer) = self.scorers.pop() {
+            let doc = scorer.doc;
+            if doc == TERMINATED {
+                // The scorer is dropped.
+                continue;
+            }
+            if doc != self.doc {
+                if num_matches >= self.minimum_matches_required {
+                    self.scorers.push(scorer);
+                    self.score = self.score_combiner.score();
+                    return self.doc;
+                }
+                num_matches = 0;
+                self.doc = doc;
+                self.score_combiner.clear();
+            }
+            num_matches += 1;
+            self.score_combiner.update(&mut scorer.scorer);
+            scorer.advance();
+            self.scorers.push(scorer);
+        }
+        if num_matches < self.minimum_matches_required {
+            self.doc = TERMINATED;
+        }
+        self.score = self.score_combiner.score();
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        let scorers = std::mem::take(&mut self.scorers);
+        self.scorers = scorers
+            .into_iter()
+            .filter_map(|mut scorer| {
+                if scorer.doc < target {
+                    scorer.seek(target);
+                }
+                (scorer.doc != TERMINATED).then_some(scorer)
+            })
+            .collect();
+        // No document was visited yet, past `target`.
+        self.doc = TERMINATED;
+        self.advance()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.scorer.size_hint())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Scorer
+    for Disjunction<TScorer, TScoreCombiner>
+{
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Disjunction;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::postings::tests::test_skip_against_unoptimized;
+    use crate::query::score_combiner::SumCombiner;
+    use crate::query::{ConstScorer, Scorer, VecDocSet};
+    use crate::tests::sample_with_seed;
+    use crate::{DocId, Score};
+
+    fn disjunction(
+        docs_list: &[Vec<DocId>],
+        minimum_matches_required: usize,
+    ) -> Disjunction<ConstScorer<VecDocSet>, SumCombiner> {
+        let scorers = docs_list
+            .iter()
+            .map(|docs| ConstScorer::new(VecDocSet::from(docs.clone()), 1.0));
+        Disjunction::new(scorers, SumCombiner::default(), minimum_matches_required)
+    }
+
+    fn collect(
+        mut disjunction: Disjunction<ConstScorer<VecDocSet>, SumCombiner>,
+    ) -> Vec<(DocId, Score)> {
+        let mut docs = Vec::new();
+        while disjunction.doc() != TERMINATED {
+            docs.push((disjunction.doc(), disjunction.score()));
+            disjunction.advance();
+        }
+        docs
+    }
+
+    #[test]
+    fn test_disjunction() {
+        let docs_list = vec![vec![1, 2, 5, 8], vec![2, 3, 8], vec![2, 5, 9, 10]];
+        assert_eq!(
+            collect(disjunction(&docs_list, 1)),
+            vec![
+                (1, 1.0),
+                (2, 3.0),
+                (3, 1.0),
+                (5, 2.0),
+                (8, 2.0),
+                (9, 1.0),
+                (10, 1.0)
+            ]
+        );
+        assert_eq!(
+            collect(disjunction(&docs_list, 2)),
+            vec![(2, 3.0), (5, 2.0), (8, 2.0)]
+        );
+        assert_eq!(collect(disjunction(&docs_list, 3)), vec![(2, 3.0)]);
+        assert!(collect(disjunction(&docs_list, 4)).is_empty());
+        assert_eq!(collect(disjunction(&docs_list, 0)).len(), 7);
+        assert!(collect(disjunction(&[], 1)).is_empty());
+    }
+
+    #[test]
+    fn test_disjunction_skip_random() {
+        let docs_list: Vec<Vec<DocId>> = (0..4)
+            .map(|seed| sample_with_seed(10_000, 0.1, seed))
+            .collect();
+        let targets = sample_with_seed(10_000, 0.005, 10);
+        for minimum_matches_required in 1..=3 {
+            test_skip_against_unoptimized(
+                || Box::new(disjunction(&docs_list, minimum_matches_required)),
+                targets.clone(),
+            );
+        }
+    }
+}
@@ -99,13 +99,13 @@ static LEV_BUILDER: Lazy<HashMap<(u8, bool), LevenshteinAutomatonBuilder>> = Laz
 #[derive(Debug, Clone)]
 pub struct FuzzyTermQuery {
     /// What term are we searching
-    term: Term,
+    pub(crate) term: Term,
     /// How many changes are we going to allow
-    distance: u8,
+    pub(crate) distance: u8,
     /// Should a transposition cost 1 or 2?
-    transposition_cost_one: bool,
+    pub(crate) transposition_cost_one: bool,
     ///
-    prefix: bool,
+    pub(crate) prefix: bool,
 }
 
 impl FuzzyTermQuery {
@@ -3,23 +3,32 @@
 mod all_query;
 mod automaton_weight;
 mod bitset;
+mod bitset_query;
 mod bm25;
 mod boolean_query;
 mod boost_query;
+mod boosting_query;
+mod constant_score_query;
+mod dis_max_query;
+mod disjunction;
 mod empty_query;
 mod exclude;
 mod explanation;
 mod fuzzy_query;
 mod intersection;
 mod more_like_this;
+mod percolator;
 mod phrase_query;
+mod profile;
 mod query;
 mod query_parser;
+mod query_plan;
 mod range_query;
 mod regex_query;
 mod reqopt_scorer;
 mod scorer;
 mod term_query;
+mod terms_lookup_query;
 mod union;
 mod weight;
 
@@ -32,9 +41,14 @@ pub use tantivy_query_grammar::Occur;
 pub use self::all_query::{AllQuery, AllScorer, AllWeight};
 pub use self::automaton_weight::AutomatonWeight;
 pub use self::bitset::BitSetDocSet;
+pub use self::bitset_query::{BitSetQuery, BitSetWeight};
 pub(crate) use self::bm25::Bm25Weight;
 pub use self::boolean_query::BooleanQuery;
 pub use self::boost_query::BoostQuery;
+pub use self::boosting_query::BoostingQuery;
+pub use self::constant_score_query::ConstantScoreQuery;
+pub use self::dis_max_query::DisMaxQuery;
+pub use self::disjunction::Disjunction;
 pub use self::empty_query::{EmptyQuery, EmptyScorer, EmptyWeight};
 pub use self::exclude::Exclude;
 pub use self::explanation::Explanation;
@@ -43,14 +57,18 @@ pub(crate) use self::fuzzy_query::DfaWrapper;
 pub use self::fuzzy_query::FuzzyTermQuery;
 pub use self::intersection::{intersect_scorers, Intersection};
 pub use self::more_like_this::{MoreLikeThisQuery, MoreLikeThisQueryBuilder};
+pub use self::percolator::Percolator;
 pub use self::phrase_query::PhraseQuery;
+pub use self::profile::{ProfileHandle, ProfiledWeight, QueryProfile};
 pub use self::query::{Query, QueryClone};
 pub use self::query_parser::{QueryParser, QueryParserError};
+pub use self::query_plan::{QueryPlan, QueryPlanner};
 pub use self::range_query::RangeQuery;
 pub use self::regex_query::RegexQuery;
 pub use self::reqopt_scorer::RequiredOptionalScorer;
 pub use self::scorer::{ConstScorer, Scorer};
 pub use self::term_query::TermQuery;
+pub use self::terms_lookup_query::TermsLookupQuery;
 pub use self::union::Union;
 #[cfg(test)]
 pub use self::vec_docset::VecDocSet;
@@ -60,9 +78,10 @@ pub use self::weight::Weight;
 mod tests {
     use std::collections::BTreeMap;
 
-    use crate::query::QueryParser;
-    use crate::schema::{Schema, TEXT};
-    use crate::{Index, Term};
+    use crate::collector::TopDocs;
+    use crate::query::{BoostQuery, Query, QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, TantivyError, Term};
 
     #[test]
     fn test_query_terms() {
@@ -119,4 +138,106 @@ mod tests {
             assert_eq!(vec![(&term_a, &false), (&term_b, &false)], terms);
         }
     }
+
+    #[test]
+    fn test_score_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for text in ["a b", "a", "b c", "a a b", "c"] {
+            index_writer.add_document(doc!(text_field => text))?;
+            index_writer.add_document(doc!(text_field => "d"))?;
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "a b c"))?;
+        index_writer.delete_term(Term::from_field_text(text_field, "d"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let all_docs: Vec<DocAddress> = searcher
+            .segment_readers()
+            .iter()
+            .enumerate()
+            .flat_map(|(segment_ord, segment_reader)| {
+                (0..segment_reader.max_doc())
+                    .rev()
+                    .map(move |doc_id| DocAddress::new(segment_ord as u32, doc_id))
+            })
+            .collect();
+        for query_str in ["a", "a b", "+a +b", "a -c", "\"a b\"", "d", "b*"] {
+            let query = query_parser.parse_query(query_str)?;
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(100))?;
+            let scores = query.score_docs(&searcher, &all_docs)?;
+            assert_eq!(
+                scores
+                    .iter()
+                    .filter(|score_opt| score_opt.is_some())
+                    .count(),
+                top_docs.len()
+            );
+            for (score, doc_address) in top_docs {
+                let position = all_docs.iter().position(|doc| *doc == doc_address).unwrap();
+                assert_eq!(scores[position], Some(score));
+                assert_eq!(query.score_doc(&searcher, doc_address)?, Some(score));
+            }
+        }
+
+        let query = query_parser.parse_query("a")?;
+        let max_doc = searcher.segment_reader(0).max_doc();
+        for doc_address in [
+            DocAddress::new(0, max_doc),
+            DocAddress::new(searcher.segment_readers().len() as u32, 0),
+        ] {
+            assert!(matches!(
+                query.score_docs(&searcher, &[DocAddress::new(0, 0), doc_address]),
+                Err(TantivyError::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                query.score_doc(&searcher, doc_address),
+                Err(TantivyError::InvalidArgument(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_form() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let parse_fingerprint = |query_str: &str| -> crate::Result<u64> {
+            Ok(query_parser.parse_query(query_str)?.fingerprint())
+        };
+        assert_eq!(parse_fingerprint("a b")?, parse_fingerprint("b a")?);
+        assert_eq!(
+            parse_fingerprint("+a -(b c)")?,
+            parse_fingerprint("-(c b) +a")?
+        );
+        assert_ne!(parse_fingerprint("+a b")?, parse_fingerprint("a +b")?);
+        assert_ne!(parse_fingerprint("a")?, parse_fingerprint("b")?);
+
+        let term_query = || -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text_field, "a"),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let nested_boosts = BoostQuery::new(Box::new(BoostQuery::new(term_query(), 2.0)), 3.0);
+        assert_eq!(
+            nested_boosts.canonical_form(),
+            BoostQuery::new(term_query(), 6.0).canonical_form()
+        );
+        assert_eq!(
+            BoostQuery::new(term_query(), 1.0).fingerprint(),
+            term_query().fingerprint()
+        );
+        let basic_term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        assert_ne!(basic_term_query.fingerprint(), term_query().fingerprint());
+        Ok(())
+    }
 }
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::query::{BooleanQuery, BoostQuery, Occur, PhraseQuery, Query, TermQuery};
+use crate::schema::{Document, Field, Schema, Term};
+use crate::tokenizer::TokenizerManager;
+use crate::{Index, MemoryIndex};
+
+/// Matches documents against a set of registered queries.
+///
+/// This is the reverse of a search: rather than finding the documents matching a query,
+/// [`Percolator::percolate`] finds the registered queries matching a document, e.g. to
+/// alert the users whose saved searches match a new document, or to route it.
+///
+/// The registered queries are indexed by their terms. Percolating a document indexes it
+/// alone in a [`MemoryIndex`], and only evaluates the queries sharing a term with it, along
+/// with the queries that cannot be pre-filtered by their terms, such as range queries.
+///
+/// ```rust
+/// use tantivy::query::{Percolator, QueryParser};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+///
+/// let mut percolator = Percolator::for_index(&index);
+/// percolator.register(1, query_parser.parse_query("+diary +muadib")?);
+/// percolator.register(2, query_parser.parse_query("girl")?);
+/// let matching_query_ids = percolator.percolate(&doc!(title => "The Diary of Muadib"))?;
+/// assert_eq!(matching_query_ids, vec![1]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Percolator {
+    schema: Schema,
+    tokenizers: TokenizerManager,
+    queries: BTreeMap<u64, Box<dyn Query>>,
+    // Ids of the pre-filtered queries, by field and term value.
+    query_ids_by_term: HashMap<(Field, Vec<u8>), BTreeSet<u64>>,
+    // Ids of the queries evaluated against every document.
+    unfiltered_query_ids: BTreeSet<u64>,
+}
+
+impl Percolator {
+    /// Creates a percolator matching documents of the schema of the index, tokenized by the
+    /// tokenizers of the index.
+    pub fn for_index(index: &Index) -> Percolator {
+        Percolator::new(index.schema(), index.tokenizers().clone())
+    }
+
+    /// Creates a percolator matching documents of `schema`, tokenized by `tokenizers`.
+    pub fn new(schema: Schema, tokenizers: TokenizerManager) -> Percolator {
+        Percolator {
+            schema,
+            tokenizers,
+            queries: BTreeMap::new(),
+            query_ids_by_term: HashMap::new(),
+            unfiltered_query_ids: BTreeSet::new(),
+        }
+    }
+
+    /// Registers a query under `query_id`, replacing the query previously registered under
+    /// the same id.
+    pub fn register(&mut self, query_id: u64, query: Box<dyn Query>) {
+        self.unregister(query_id);
+        match prefilter_terms(query.as_ref()) {
+            Some(terms) => {
+                for term in terms {
+                    self.query_ids_by_term
+                        .entry((term.field(), term.value_bytes().to_vec()))
+                        .or_default()
+                        .insert(query_id);
+                }
+            }
+            None => {
+                self.unfiltered_query_ids.insert(query_id);
+            }
+        }
+        self.queries.insert(query_id, query);
+    }
+
+    /// Unregisters the query registered under `query_id`, and returns it.
+    pub fn unregister(&mut self, query_id: u64) -> Option<Box<dyn Query>> {
+        let query = self.queries.remove(&query_id)?;
+        if !self.unfiltered_query_ids.remove(&query_id) {
+            self.query_ids_by_term.retain(|_, query_ids| {
+                query_ids.remove(&query_id);
+                !query_ids.is_empty()
+            });
+        }
+        Some(query)
+    }
+
+    /// Returns the number of registered queries.
+    pub fn num_queries(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns the ids of the registered queries matching the document, in increasing order.
+    pub fn percolate(&self, document: &Document) -> crate::Result<Vec<u64>> {
+        let memory_index = MemoryIndex::with_tokenizers(
+            self.schema.clone(),
+            self.tokenizers.clone(),
+            std::iter::once(document.clone()),
+        )?;
+        let searcher = memory_index.searcher();
+
+        let mut candidate_query_ids = self.unfiltered_query_ids.clone();
+        let prefiltered_fields: BTreeSet<Field> = self
+            .query_ids_by_term
+            .keys()
+            .map(|(field, _)| *field)
+            .collect();
+        for segment_reader in searcher.segment_readers() {
+            for &field in &prefiltered_fields {
+                let inverted_index = segment_reader.inverted_index(field)?;
+                let mut term_stream = inverted_index.terms().stream()?;
+                while term_stream.advance() {
+                    let term_key = (field, term_stream.key().to_vec());
+                    if let Some(query_ids) = self.query_ids_by_term.get(&term_key) {
+                        candidate_query_ids.extend(query_ids);
+                    }
+                }
+            }
+        }
+
+        let mut matching_query_ids = Vec::new();
+        for query_id in candidate_query_ids {
+            if memory_index.matches(self.queries[&query_id].as_ref())? {
+                matching_query_ids.push(query_id);
+            }
+        }
+        Ok(matching_query_ids)
+    }
+}
+
+/// Returns terms such that the documents matching the query contain at least one of them,
+/// or `None` if the query cannot be pre-filtered by its terms.
+fn prefilter_terms(query: &dyn Query) -> Option<Vec<Term>> {
+    if let Some(boxed_query) = query.downcast_ref::<Box<dyn Query>>() {
+        return prefilter_terms(boxed_query.as_ref());
+    }
+    if let Some(term_query) = query.downcast_ref::<TermQuery>() {
+        return Some(vec![term_query.term().clone()]);
+    }
+    if let Some(phrase_query) = query.downcast_ref::<PhraseQuery>() {
+        return Some(phrase_query.phrase_terms());
+    }
+    if let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+        return prefilter_terms(boost_query.query.as_ref());
+    }
+    if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+        // Any of the mandatory clauses is enough.
+        let must_terms_opt = boolean_query
+            .clauses()
+            .iter()
+            .filter(|(occur, _)| *occur == Occur::Must)
+            .find_map(|(_, subquery)| prefilter_terms(subquery.as_ref()));
+        if must_terms_opt.is_some() {
+            return must_terms_opt;
+        }
+        let has_must_clause = boolean_query
+            .clauses()
+            .iter()
+            .any(|(occur, _)| *occur == Occur::Must);
+        if has_must_clause {
+            return None;
+        }
+        // Otherwise, the documents match one of the optional clauses.
+        let mut should_terms = Vec::new();
+        for (occur, subquery) in boolean_query.clauses() {
+            if *occur == Occur::Should {
+                should_terms.extend(prefilter_terms(subquery.as_ref())?);
+            }
+        }
+        return Some(should_terms);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Percolator;
+    use crate::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery};
+    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::Index;
+
+    #[test]
+    fn test_percolator() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_u64_field("year", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let mut percolator = Percolator::for_index(&index);
+        percolator.register(1, query_parser.parse_query("diary")?);
+        percolator.register(2, query_parser.parse_query("+diary -girl")?);
+        percolator.register(3, query_parser.parse_query("\"young girl\"")?);
+        percolator.register(4, query_parser.parse_query("cow OR girl^2")?);
+        percolator.register(5, Box::new(RangeQuery::new_u64(year, 1900..1950)));
+        // Matches every document, despite its term.
+        let all_or_cow: Box<dyn Query> = Box::new(BooleanQuery::new(vec![
+            (Occur::Should, Box::new(AllQuery)),
+            (Occur::Should, query_parser.parse_query("cow")?),
+        ]));
+        percolator.register(6, all_or_cow);
+        assert_eq!(percolator.num_queries(), 6);
+
+        let diary = doc!(title => "The Diary of a Young Girl", year => 1947u64);
+        assert_eq!(percolator.percolate(&diary)?, vec![1, 3, 4, 5, 6]);
+        let muadib = doc!(title => "The Diary of Muadib", year => 1965u64);
+        assert_eq!(percolator.percolate(&muadib)?, vec![1, 2, 6]);
+        assert_eq!(percolator.percolate(&doc!())?, vec![6]);
+
+        assert!(percolator.unregister(1).is_some());
+        assert!(percolator.unregister(1).is_none());
+        percolator.register(6, query_parser.parse_query("muadib")?);
+        assert_eq!(percolator.percolate(&muadib)?, vec![2, 6]);
+        assert_eq!(percolator.percolate(&diary)?, vec![3, 4, 5]);
+        Ok(())
+    }
+}
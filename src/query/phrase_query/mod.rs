@@ -15,7 +15,8 @@ pub mod tests {
     use crate::collector::tests::{TEST_COLLECTOR_WITHOUT_SCORE, TEST_COLLECTOR_WITH_SCORE};
     use crate::core::Index;
     use crate::query::{QueryParser, Weight};
-    use crate::schema::{Schema, Term, TEXT};
+    use crate::schema::{IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions, TEXT};
+    use crate::tokenizer::{SimpleTokenizer, StopWordFilter, TextAnalyzer};
     use crate::{assert_nearly_equals, DocAddress, DocId, TERMINATED};
 
     pub fn create_index(texts: &[&'static str]) -> crate::Result<Index> {
@@ -333,6 +334,48 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_phrase_query_stop_words() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_indexing = TextFieldIndexing::default()
+            .set_tokenizer("en_stop_words")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let text_options = TextOptions::default().set_indexing_options(text_indexing);
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "en_stop_words",
+            TextAnalyzer::from(SimpleTokenizer).filter(StopWordFilter::default()),
+        );
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(text_field=>"a quick fox"))?;
+            index_writer.add_document(doc!(text_field=>"quick fox"))?;
+            index_writer.add_document(doc!(text_field=>"quick and brown fox"))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let matching_docs = |query: &str, phrase_slop: u32| {
+            let mut query_parser = QueryParser::for_index(&index, vec![text_field]);
+            query_parser.set_phrase_slop(phrase_slop);
+            let query = query_parser.parse_query(query).unwrap();
+            searcher
+                .search(&query, &TEST_COLLECTOR_WITHOUT_SCORE)
+                .unwrap()
+                .docs()
+                .iter()
+                .map(|doc_address| doc_address.doc_id)
+                .collect::<Vec<DocId>>()
+        };
+        assert_eq!(matching_docs(r#""the quick fox""#, 0), vec![0, 1]);
+        assert!(matching_docs(r#""quick the fox""#, 0).is_empty());
+        assert!(matching_docs(r#""quick brown fox""#, 0).is_empty());
+        assert_eq!(matching_docs(r#""quick brown fox""#, 1), vec![2]);
+        assert_eq!(matching_docs(r#""quick fox""#, 2), vec![0, 1, 2]);
+        assert_eq!(matching_docs(r#""quick fox"~2"#, 0), vec![0, 1, 2]);
+        Ok(())
+    }
+
     #[test]
     pub fn test_phrase_query_on_json() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -25,8 +25,8 @@ use crate::schema::{Field, IndexRecordOption, Term};
 #[derive(Clone, Debug)]
 pub struct PhraseQuery {
     field: Field,
-    phrase_terms: Vec<(usize, Term)>,
-    slop: u32,
+    pub(crate) phrase_terms: Vec<(usize, Term)>,
+    pub(crate) slop: u32,
 }
 
 impl PhraseQuery {
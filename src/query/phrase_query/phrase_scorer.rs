@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 
 use crate::docset::{DocSet, TERMINATED};
 use crate::fieldnorm::FieldNormReader;
@@ -46,6 +46,10 @@ impl<TPostings: Postings> DocSet for PostingsWithOffset<TPostings> {
 pub struct PhraseScorer<TPostings: Postings> {
     intersection_docset: Intersection<PostingsWithOffset<TPostings>, PostingsWithOffset<TPostings>>,
     num_terms: usize,
+    // Ordinals of the postings in the intersection, in the order of the terms in the phrase.
+    // The intersection sorts its postings by their number of documents, but the positions of
+    // the terms have to be intersected in the order of the phrase when there is a slop.
+    term_ords: Vec<usize>,
     left: Vec<u32>,
     right: Vec<u32>,
     phrase_count: u32,
@@ -264,9 +268,18 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
                 PostingsWithOffset::new(postings, (max_offset - offset) as u32)
             })
             .collect::<Vec<_>>();
+        let mut intersection_docset = Intersection::new(postings_with_offsets);
+        let mut term_ords: Vec<usize> = (0..num_docsets).collect();
+        if slop > 0 {
+            // The lower the offset in the phrase, the higher the offset added to the positions.
+            term_ords.sort_by_key(|&ord| {
+                Reverse(intersection_docset.docset_mut_specialized(ord).offset)
+            });
+        }
         let mut scorer = PhraseScorer {
-            intersection_docset: Intersection::new(postings_with_offsets),
+            intersection_docset,
             num_terms: num_docsets,
+            term_ords,
             left: Vec::with_capacity(100),
             right: Vec::with_capacity(100),
             phrase_count: 0u32,
@@ -322,14 +335,14 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
     fn compute_phrase_match(&mut self) -> usize {
         {
             self.intersection_docset
-                .docset_mut_specialized(0)
+                .docset_mut_specialized(self.term_ords[0])
                 .positions(&mut self.left);
         }
         let mut intersection_len = self.left.len();
         for i in 1..self.num_terms - 1 {
             {
                 self.intersection_docset
-                    .docset_mut_specialized(i)
+                    .docset_mut_specialized(self.term_ords[i])
                     .positions(&mut self.right);
             }
             intersection_len = if self.has_slop() {
@@ -346,7 +359,7 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
             }
         }
         self.intersection_docset
-            .docset_mut_specialized(self.num_terms - 1)
+            .docset_mut_specialized(self.term_ords[self.num_terms - 1])
             .positions(&mut self.right);
         intersection_len
     }
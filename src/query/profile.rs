@@ -0,0 +1,249 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::query::{Explanation, Scorer, Weight};
+use crate::{DocId, DocSet, Score, SegmentReader, TERMINATED};
+
+/// Statistics gathered while executing a query with
+/// [`Searcher::search_with_profile`](../struct.Searcher.html#method.search_with_profile).
+///
+/// It is organized in trees mirroring the structure of the query: the children of
+/// a boolean query node are the profiles of its clauses.
+///
+/// `.to_pretty_json()` can be useful to print out a human readable
+/// representation of this tree.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryProfile {
+    /// Description of the query node.
+    pub description: String,
+    /// Number of times the scorers of the node were asked to move to a new document
+    /// (`advance` or `seek`), summed over segments.
+    pub docs_evaluated: u64,
+    /// Number of documents the scorers of the node landed on, summed over segments.
+    pub docs_matched: u64,
+    /// Time spent creating the scorers of the node, moving them and computing scores.
+    ///
+    /// This time includes the time spent in the children of the node.
+    pub elapsed: Duration,
+    /// Profiles of the sub-queries.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<QueryProfile>,
+}
+
+impl QueryProfile {
+    /// Returns an indented json representation of the profile tree for debug usage.
+    pub fn to_pretty_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[derive(Default)]
+struct ProfileStats {
+    docs_evaluated: AtomicU64,
+    docs_matched: AtomicU64,
+    elapsed_nanos: AtomicU64,
+}
+
+/// Handle over the statistics of a profiled query node and of its children.
+///
+/// See [`ProfiledWeight::profile_handle`].
+#[derive(Clone)]
+pub struct ProfileHandle {
+    description: String,
+    stats: Arc<ProfileStats>,
+    children: Vec<ProfileHandle>,
+}
+
+impl ProfileHandle {
+    fn to_profile(&self) -> QueryProfile {
+        QueryProfile {
+            description: self.description.clone(),
+            docs_evaluated: self.stats.docs_evaluated.load(Ordering::Relaxed),
+            docs_matched: self.stats.docs_matched.load(Ordering::Relaxed),
+            elapsed: Duration::from_nanos(self.stats.elapsed_nanos.load(Ordering::Relaxed)),
+            children: self
+                .children
+                .iter()
+                .map(ProfileHandle::to_profile)
+                .collect(),
+        }
+    }
+}
+
+/// `Weight` recording statistics about the scorers it creates.
+///
+/// It is returned by [`Query::profiled_weight`](./trait.Query.html#method.profiled_weight).
+pub struct ProfiledWeight {
+    weight: Box<dyn Weight>,
+    node: ProfileHandle,
+}
+
+impl ProfiledWeight {
+    /// Wraps a weight. `children` are the handles of the profiled weights of the
+    /// sub-queries, which are expected to be used by `weight`.
+    pub fn new(
+        description: String,
+        weight: Box<dyn Weight>,
+        children: Vec<ProfileHandle>,
+    ) -> ProfiledWeight {
+        ProfiledWeight {
+            weight,
+            node: ProfileHandle {
+                description,
+                stats: Arc::default(),
+                children,
+            },
+        }
+    }
+
+    /// Returns a handle to the statistics of this weight, to be registered as the child
+    /// of the weight of the parent query.
+    pub fn profile_handle(&self) -> ProfileHandle {
+        self.node.clone()
+    }
+
+    /// Changes the description of the profiled query node.
+    pub fn set_description(&mut self, description: String) {
+        self.node.description = description;
+    }
+
+    /// Returns the statistics gathered so far.
+    pub fn profile(&self) -> QueryProfile {
+        self.node.to_profile()
+    }
+}
+
+impl Weight for ProfiledWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let start = Instant::now();
+        let scorer = self.weight.scorer(reader, boost)?;
+        let mut profiled_scorer = ProfiledScorer {
+            scorer,
+            stats: self.node.stats.clone(),
+            docs_evaluated: 0,
+            docs_matched: 0,
+            elapsed: Duration::default(),
+        };
+        // The scorer is positioned on its first document on creation.
+        profiled_scorer.record(profiled_scorer.scorer.doc(), start);
+        Ok(Box::new(profiled_scorer))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+}
+
+/// Scorer counting the calls made to the scorer it wraps.
+/// The statistics are flushed to the shared `ProfileStats` on drop.
+struct ProfiledScorer {
+    scorer: Box<dyn Scorer>,
+    stats: Arc<ProfileStats>,
+    docs_evaluated: u64,
+    docs_matched: u64,
+    elapsed: Duration,
+}
+
+impl ProfiledScorer {
+    #[inline]
+    fn record(&mut self, doc: DocId, start: Instant) -> DocId {
+        self.elapsed += start.elapsed();
+        self.docs_evaluated += 1;
+        if doc != TERMINATED {
+            self.docs_matched += 1;
+        }
+        doc
+    }
+}
+
+impl DocSet for ProfiledScorer {
+    fn advance(&mut self) -> DocId {
+        let start = Instant::now();
+        let doc = self.scorer.advance();
+        self.record(doc, start)
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        let start = Instant::now();
+        let doc = self.scorer.seek(target);
+        self.record(doc, start)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+}
+
+impl Scorer for ProfiledScorer {
+    fn score(&mut self) -> Score {
+        let start = Instant::now();
+        let score = self.scorer.score();
+        self.elapsed += start.elapsed();
+        score
+    }
+}
+
+impl Drop for ProfiledScorer {
+    fn drop(&mut self) {
+        self.stats
+            .docs_evaluated
+            .fetch_add(self.docs_evaluated, Ordering::Relaxed);
+        self.stats
+            .docs_matched
+            .fetch_add(self.docs_matched, Ordering::Relaxed);
+        self.stats
+            .elapsed_nanos
+            .fetch_add(self.elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::Count;
+    use crate::query::QueryParser;
+    use crate::schema::{Schema, TEXT};
+    use crate::Index;
+
+    #[test]
+    fn test_search_with_profile() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.add_document(doc!(text => "a c"))?;
+        index_writer.add_document(doc!(text => "b c"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "a b c"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![text]).parse_query("+a +b")?;
+        let (count, profile) = searcher.search_with_profile(&query, &Count)?;
+        assert_eq!(count, 2);
+        assert_eq!(profile.description, "BooleanQuery");
+        assert_eq!(profile.docs_matched, 2);
+        assert_eq!(profile.children.len(), 2);
+        assert!(profile.children[0]
+            .description
+            .starts_with("Must TermQuery"));
+        let matched: u64 = profile
+            .children
+            .iter()
+            .map(|child| child.docs_matched)
+            .sum();
+        assert!(matched >= 4);
+        assert!(profile
+            .children
+            .iter()
+            .all(|child| child.children.is_empty()));
+        assert!(profile.to_pretty_json().contains("docs_evaluated"));
+        Ok(())
+    }
+}
@@ -1,12 +1,16 @@
 use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::Hasher;
 
+use common::BitSet;
 use downcast_rs::impl_downcast;
+use fnv::FnvHasher;
 
 use super::Weight;
 use crate::core::searcher::Searcher;
-use crate::query::Explanation;
-use crate::{DocAddress, Term};
+use crate::core::SegmentReader;
+use crate::query::{Explanation, ProfiledWeight};
+use crate::{DocAddress, DocId, Score, SegmentOrdinal, TantivyError, Term};
 
 /// The `Query` trait defines a set of documents and a scoring method
 /// for those documents.
@@ -57,6 +61,54 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
         weight.explain(reader, doc_address.doc_id)
     }
 
+    /// Returns the score of the document, or `None` if it does not match the query.
+    ///
+    /// See [`Weight::score_doc`].
+    fn score_doc(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> crate::Result<Option<Score>> {
+        let reader = segment_reader_checked(searcher, doc_address.segment_ord)?;
+        let weight = self.weight(searcher, true)?;
+        weight.score_doc(reader, doc_address.doc_id)
+    }
+
+    /// Returns the score of each of the documents, in the same order, or `None` for the ones
+    /// that do not match the query.
+    ///
+    /// This makes it possible to score an external set of candidate documents, without
+    /// going through all of the documents matching the query.
+    ///
+    /// Returns an `InvalidArgument` error if a document address is out of the searcher.
+    fn score_docs(
+        &self,
+        searcher: &Searcher,
+        doc_addresses: &[DocAddress],
+    ) -> crate::Result<Vec<Option<Score>>> {
+        let weight = self.weight(searcher, true)?;
+        let mut positions_per_segment: BTreeMap<SegmentOrdinal, Vec<usize>> = BTreeMap::new();
+        for (position, doc_address) in doc_addresses.iter().enumerate() {
+            positions_per_segment
+                .entry(doc_address.segment_ord)
+                .or_default()
+                .push(position);
+        }
+        let mut scores = vec![None; doc_addresses.len()];
+        for (segment_ord, positions) in positions_per_segment {
+            let reader = segment_reader_checked(searcher, segment_ord)?;
+            let docs: Vec<DocId> = positions
+                .iter()
+                .map(|&position| doc_addresses[position].doc_id)
+                .collect();
+            let segment_scores = weight.score_docs(reader, &docs)?;
+            for (position, score_opt) in positions.into_iter().zip(segment_scores) {
+                scores[position] = score_opt;
+            }
+        }
+        Ok(scores)
+    }
+
     /// Returns the number of documents matching the query.
     fn count(&self, searcher: &Searcher) -> crate::Result<usize> {
         let weight = self.weight(searcher, false)?;
@@ -67,12 +119,69 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
         Ok(result)
     }
 
+    /// Returns the set of documents of the segment matching the query.
+    ///
+    /// Deleted documents are not filtered out. The bitset can be turned back into a
+    /// query with [`BitSetQuery`](./struct.BitSetQuery.html), which makes it possible to
+    /// evaluate an expensive filter once and to intersect it with many queries.
+    fn to_bitset(
+        &self,
+        searcher: &Searcher,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<BitSet> {
+        let weight = self.weight(searcher, false)?;
+        let mut bitset = BitSet::with_max_value(segment_reader.max_doc());
+        weight.for_each(segment_reader, &mut |doc, _| bitset.insert(doc))?;
+        Ok(bitset)
+    }
+
+    /// Returns a canonical representation of the query.
+    ///
+    /// Queries with the same canonical form match the same documents with the same scores,
+    /// even if they were written differently, e.g. with their boolean clauses in another order.
+    /// This makes it usable as a cache key, or to deduplicate query logs.
+    ///
+    /// By default, it is the `Debug` representation of the query. Queries combining sub-queries
+    /// should override this method to combine the canonical forms of their sub-queries.
+    fn canonical_form(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    /// Returns a 64-bit hash of the canonical form of the query.
+    ///
+    /// Unlike `std::hash::Hash`, the fingerprint is stable across processes and platforms.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hasher.write(self.canonical_form().as_bytes());
+        hasher.finish()
+    }
+
     /// Extract all of the terms associated to the query and insert them in the
     /// term set given in arguments.
     ///
     /// Each term is associated with a boolean indicating whether
     /// Positions are required or not.
     fn query_terms(&self, _term_set: &mut BTreeMap<Term, bool>) {}
+
+    /// Create the weight associated to a query, instrumented to record
+    /// how its scorers are used.
+    ///
+    /// Queries combining sub-queries should override this method to profile
+    /// their sub-queries as well. By default, the query is profiled as a single node.
+    ///
+    /// See [`Searcher::search_with_profile`](../struct.Searcher.html#method.search_with_profile).
+    fn profiled_weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<ProfiledWeight> {
+        let weight = self.weight(searcher, scoring_enabled)?;
+        Ok(ProfiledWeight::new(
+            format!("{:?}", self),
+            weight,
+            Vec::new(),
+        ))
+    }
 }
 
 /// Implements `box_clone`.
@@ -98,9 +207,21 @@ impl Query for Box<dyn Query> {
         self.as_ref().count(searcher)
     }
 
+    fn canonical_form(&self) -> String {
+        self.as_ref().canonical_form()
+    }
+
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         self.as_ref().query_terms(terms);
     }
+
+    fn profiled_weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<ProfiledWeight> {
+        self.as_ref().profiled_weight(searcher, scoring_enabled)
+    }
 }
 
 impl QueryClone for Box<dyn Query> {
@@ -110,3 +231,20 @@ impl QueryClone for Box<dyn Query> {
 }
 
 impl_downcast!(Query);
+
+// Returns the segment reader of `segment_ord`, or an error if the searcher has no such segment.
+fn segment_reader_checked(
+    searcher: &Searcher,
+    segment_ord: SegmentOrdinal,
+) -> crate::Result<&SegmentReader> {
+    searcher
+        .segment_readers()
+        .get(segment_ord as usize)
+        .ok_or_else(|| {
+            TantivyError::InvalidArgument(format!(
+                "Segment ordinal {} is out of the searcher, which has {} segments.",
+                segment_ord,
+                searcher.segment_readers().len()
+            ))
+        })
+}
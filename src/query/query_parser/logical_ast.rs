@@ -22,6 +22,7 @@ pub enum LogicalAst {
     Clause(Vec<(Occur, LogicalAst)>),
     Leaf(Box<LogicalLiteral>),
     Boost(Box<LogicalAst>, Score),
+    Filter(Box<LogicalAst>),
 }
 
 impl LogicalAst {
@@ -59,6 +60,7 @@ impl fmt::Debug for LogicalAst {
                 Ok(())
             }
             LogicalAst::Boost(ref ast, boost) => write!(formatter, "{:?}^{}", ast, boost),
+            LogicalAst::Filter(ref ast) => write!(formatter, "filter({:?})", ast),
             LogicalAst::Leaf(ref literal) => write!(formatter, "{:?}", literal),
         }
     }
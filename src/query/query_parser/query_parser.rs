@@ -11,8 +11,8 @@ use crate::indexer::{
     convert_to_fast_value_and_get_term, set_string_and_get_terms, JsonTermWriter,
 };
 use crate::query::{
-    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, Occur, PhraseQuery, Query, RangeQuery,
-    TermQuery,
+    AllQuery, BooleanQuery, BoostQuery, ConstantScoreQuery, EmptyQuery, Occur, PhraseQuery, Query,
+    RangeQuery, TermQuery,
 };
 use crate::schema::{
     Facet, FacetParseError, Field, FieldType, IndexRecordOption, Schema, Term, Type,
@@ -84,6 +84,30 @@ pub enum QueryParserError {
     /// The format for the facet field is invalid.
     #[error("The facet field is malformed: {0}")]
     FacetFormatError(#[from] FacetParseError),
+    /// The query has more clauses than allowed, see [`QueryParser::set_max_clause_count`].
+    #[error("The query has {num_clauses} clauses, more than the maximum of {max_clause_count}")]
+    TooManyClauses {
+        /// The number of clauses of the query
+        num_clauses: usize,
+        /// The maximum number of clauses
+        max_clause_count: usize,
+    },
+    /// The query is nested deeper than allowed, see [`QueryParser::set_max_depth`].
+    #[error("The query is nested {depth} levels deep, more than the maximum of {max_depth}")]
+    TooDeep {
+        /// The nesting depth of the query
+        depth: usize,
+        /// The maximum nesting depth
+        max_depth: usize,
+    },
+    /// The query expands to more terms than allowed, see [`QueryParser::set_max_terms`].
+    #[error("The query expands to {num_terms} terms, more than the maximum of {max_terms}")]
+    TooManyTerms {
+        /// The number of terms of the query
+        num_terms: usize,
+        /// The maximum number of terms
+        max_terms: usize,
+    },
 }
 
 /// Recursively remove empty clause from the AST
@@ -161,6 +185,12 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 ///
 /// * all docs query: A plain `*` will match all documents in the index.
 ///
+/// * filters: once enabled with [`set_filter_syntax`](#method.set_filter_syntax),
+///   `filter(...)` restricts the documents matched without contributing to their score, e.g.
+///   `+diary +filter(category:book)`. The clauses of the filter, like the clauses boosted by 0,
+///   are not scored and become a [`ConstantScoreQuery`](crate::query::ConstantScoreQuery),
+///   whose documents are cached by [`IndexReader::search_cached`](crate::IndexReader::search_cached).
+///
 /// Parts of the queries can be boosted by appending `^boostfactor`.
 /// For instance, `"SRE"^2.0 OR devops^0.4` will boost documents containing `SRE` instead of
 /// devops. Negative boosts are not allowed.
@@ -171,6 +201,12 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 ///
 /// Phrase terms support the `~` slop operator which allows to set the phrase's matching
 /// distance in words. `"big wolf"~1` will return documents containing the phrase `"big bad wolf"`.
+/// A default slop can be set for all phrases (See
+/// [`set_phrase_slop(...)`](#method.set_phrase_slop)).
+///
+/// The positions of the words removed by the tokenizer of a field, such as stop words, are kept
+/// as gaps between the terms of the phrase. With a stop word filter, `"the quick fox"` matches
+/// `a quick fox`, since both are indexed as `quick` and `fox`, one position apart.
 #[derive(Clone)]
 pub struct QueryParser {
     schema: Schema,
@@ -179,12 +215,77 @@ pub struct QueryParser {
     tokenizer_manager: TokenizerManager,
     boost: HashMap<Field, Score>,
     field_names: HashMap<String, Field>,
+    max_clause_count: Option<usize>,
+    max_depth: Option<usize>,
+    max_terms: Option<usize>,
+    phrase_slop: u32,
+    filter_syntax: bool,
+}
+
+// Returns how deep the parentheses of the query are nested, without parsing it.
+// The parentheses of phrases and escaped parentheses are ignored.
+fn parenthesis_depth(query: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0;
+    let mut in_phrase = false;
+    let mut chars = query.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_phrase = !in_phrase,
+            _ if in_phrase => {}
+            '\\' => {
+                chars.next();
+            }
+            '(' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+// Returns the nesting depth of the clauses of the user query, 0 for a single leaf.
+fn user_input_depth(user_input_ast: &UserInputAst) -> usize {
+    match user_input_ast {
+        UserInputAst::Clause(children) => {
+            1 + children
+                .iter()
+                .map(|(_, child)| user_input_depth(child))
+                .max()
+                .unwrap_or(0)
+        }
+        UserInputAst::Leaf(_) => 0,
+        UserInputAst::Boost(child, _) => user_input_depth(child),
+    }
+}
+
+// Returns the number of clauses and the number of terms of the query.
+fn count_clauses_and_terms(ast: &LogicalAst) -> (usize, usize) {
+    match ast {
+        LogicalAst::Clause(children) => children.iter().fold(
+            (children.len(), 0),
+            |(num_clauses, num_terms), (_, child)| {
+                let (child_num_clauses, child_num_terms) = count_clauses_and_terms(child);
+                (num_clauses + child_num_clauses, num_terms + child_num_terms)
+            },
+        ),
+        LogicalAst::Leaf(literal) => match literal.as_ref() {
+            LogicalLiteral::Term(_) => (0, 1),
+            LogicalLiteral::Phrase(terms, _) => (0, terms.len()),
+            LogicalLiteral::Range { .. } | LogicalLiteral::All => (0, 0),
+        },
+        LogicalAst::Boost(child, _) | LogicalAst::Filter(child) => count_clauses_and_terms(child),
+    }
 }
 
 fn all_negative(ast: &LogicalAst) -> bool {
     match ast {
         LogicalAst::Leaf(_) => false,
         LogicalAst::Boost(ref child_ast, _) => all_negative(&*child_ast),
+        LogicalAst::Filter(child_ast) => all_negative(child_ast),
         LogicalAst::Clause(children) => children
             .iter()
             .all(|(ref occur, child)| (*occur == Occur::MustNot) || all_negative(child)),
@@ -236,6 +337,11 @@ impl QueryParser {
             conjunction_by_default: false,
             boost: Default::default(),
             field_names,
+            max_clause_count: None,
+            max_depth: None,
+            max_terms: None,
+            phrase_slop: 0,
+            filter_syntax: false,
         }
     }
 
@@ -283,6 +389,56 @@ impl QueryParser {
         self.boost.insert(field, boost);
     }
 
+    /// Sets the maximum number of clauses of the boolean queries built by the parser.
+    ///
+    /// The clauses of all of the nested boolean queries are counted, including those
+    /// created by searching a word in several default fields. Parsing a query with more
+    /// clauses fails with [`QueryParserError::TooManyClauses`].
+    pub fn set_max_clause_count(&mut self, max_clause_count: usize) {
+        self.max_clause_count = Some(max_clause_count);
+    }
+
+    /// Sets the maximum nesting depth of the clauses of a query.
+    ///
+    /// `a b` has a depth of 1, and `a (b (c d))` a depth of 3. Parsing a query nested
+    /// deeper fails with [`QueryParserError::TooDeep`].
+    ///
+    /// The nesting of the parentheses is checked before the query is parsed, so that deeply
+    /// nested queries are rejected without being parsed. A query with more than `max_depth`
+    /// levels of parentheses is then rejected, the reported depth being the one of its
+    /// parentheses.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    /// Sets the maximum number of terms a query expands to.
+    ///
+    /// Each word is expanded to one term per field it is searched in, and each phrase to
+    /// one term per token. Parsing a query expanding to more terms fails with
+    /// [`QueryParserError::TooManyTerms`].
+    pub fn set_max_terms(&mut self, max_terms: usize) {
+        self.max_terms = Some(max_terms);
+    }
+
+    /// Sets the default slop of the phrases.
+    ///
+    /// The slop of a phrase is the highest of this slop and of the slop given in the query
+    /// through the `~` operator. This makes phrases lenient to the position gaps left in the
+    /// documents by the words removed by their tokenizer, e.g. `"quick brown fox"` then
+    /// matches `quick and brown fox` with a stop word filter and a slop of 1.
+    pub fn set_phrase_slop(&mut self, phrase_slop: u32) {
+        self.phrase_slop = phrase_slop;
+    }
+
+    /// Enables the `filter(...)` syntax.
+    ///
+    /// The clauses of `filter(...)` restrict the documents matched without contributing to
+    /// their score, e.g. `+diary +filter(category:book)`. Without this option, `filter` is
+    /// parsed as a regular word.
+    pub fn set_filter_syntax(&mut self) {
+        self.filter_syntax = true;
+    }
+
     /// Parse a query
     ///
     /// Note that `parse_query` returns an error if the input
@@ -300,8 +456,19 @@ impl QueryParser {
 
     /// Parse the user query into an AST.
     fn parse_query_to_logical_ast(&self, query: &str) -> Result<LogicalAst, QueryParserError> {
-        let user_input_ast = tantivy_query_grammar::parse_query(query)
-            .map_err(|_| QueryParserError::SyntaxError(query.to_string()))?;
+        if let Some(max_depth) = self.max_depth {
+            let depth = parenthesis_depth(query);
+            if depth > max_depth {
+                return Err(QueryParserError::TooDeep { depth, max_depth });
+            }
+        }
+        let user_input_ast_res = if self.filter_syntax {
+            tantivy_query_grammar::parse_query_with_filters(query)
+        } else {
+            tantivy_query_grammar::parse_query(query)
+        };
+        let user_input_ast =
+            user_input_ast_res.map_err(|_| QueryParserError::SyntaxError(query.to_string()))?;
         self.compute_logical_ast(user_input_ast)
     }
 
@@ -309,7 +476,14 @@ impl QueryParser {
         &self,
         user_input_ast: UserInputAst,
     ) -> Result<LogicalAst, QueryParserError> {
+        if let Some(max_depth) = self.max_depth {
+            let depth = user_input_depth(&user_input_ast);
+            if depth > max_depth {
+                return Err(QueryParserError::TooDeep { depth, max_depth });
+            }
+        }
         let ast = self.compute_logical_ast_with_occur(user_input_ast)?;
+        self.check_limits(&ast)?;
         if let LogicalAst::Clause(children) = &ast {
             if children.is_empty() {
                 return Ok(ast);
@@ -321,6 +495,30 @@ impl QueryParser {
         Ok(ast)
     }
 
+    fn check_limits(&self, ast: &LogicalAst) -> Result<(), QueryParserError> {
+        if self.max_clause_count.is_none() && self.max_terms.is_none() {
+            return Ok(());
+        }
+        let (num_clauses, num_terms) = count_clauses_and_terms(ast);
+        if let Some(max_clause_count) = self.max_clause_count {
+            if num_clauses > max_clause_count {
+                return Err(QueryParserError::TooManyClauses {
+                    num_clauses,
+                    max_clause_count,
+                });
+            }
+        }
+        if let Some(max_terms) = self.max_terms {
+            if num_terms > max_terms {
+                return Err(QueryParserError::TooManyTerms {
+                    num_terms,
+                    max_terms,
+                });
+            }
+        }
+        Ok(())
+    }
+
     fn compute_boundary_term(
         &self,
         field: Field,
@@ -490,6 +688,7 @@ impl QueryParser {
                     field,
                     json_path,
                     phrase,
+                    slop,
                     &text_analyzer,
                     index_record_option,
                 )
@@ -551,6 +750,10 @@ impl QueryParser {
             }
             UserInputAst::Boost(ast, boost) => {
                 let ast = self.compute_logical_ast_with_occur(*ast)?;
+                // A clause boosted by 0 does not contribute to the score: it is a filter.
+                if boost == 0.0 {
+                    return Ok(LogicalAst::Filter(Box::new(ast)));
+                }
                 Ok(ast.boost(boost as Score))
             }
             UserInputAst::Leaf(leaf) => self.compute_logical_ast_from_leaf(*leaf),
@@ -631,9 +834,8 @@ impl QueryParser {
                     self.compute_path_triplets_for_literal(&literal)?;
                 let mut asts: Vec<LogicalAst> = Vec::new();
                 for (field, json_path, phrase) in term_phrases {
-                    for ast in
-                        self.compute_logical_ast_for_leaf(field, json_path, phrase, literal.slop)?
-                    {
+                    let slop = literal.slop.max(self.phrase_slop);
+                    for ast in self.compute_logical_ast_for_leaf(field, json_path, phrase, slop)? {
                         // Apply some field specific boost defined at the query parser level.
                         let boost = self.field_boost(field);
                         asts.push(LogicalAst::Leaf(Box::new(ast)).boost(boost));
@@ -726,6 +928,7 @@ fn generate_literals_for_json_object(
     field: Field,
     json_path: &str,
     phrase: &str,
+    slop: u32,
     text_analyzer: &TextAnalyzer,
     index_record_option: IndexRecordOption,
 ) -> Result<Vec<LogicalLiteral>, QueryParserError> {
@@ -749,7 +952,7 @@ fn generate_literals_for_json_object(
             field_name.to_string(),
         ));
     }
-    logical_literals.push(LogicalLiteral::Phrase(terms, 0));
+    logical_literals.push(LogicalLiteral::Phrase(terms, slop));
     Ok(logical_literals)
 }
 
@@ -774,6 +977,10 @@ fn convert_to_query(logical_ast: LogicalAst) -> Box<dyn Query> {
             let boosted_query = BoostQuery::new(query, boost);
             Box::new(boosted_query)
         }
+        Some(LogicalAst::Filter(ast)) => {
+            let query = convert_to_query(*ast);
+            Box::new(ConstantScoreQuery::new(query, 0.0))
+        }
         None => Box::new(EmptyQuery),
     }
 }
@@ -784,7 +991,7 @@ mod test {
 
     use super::super::logical_ast::*;
     use super::{QueryParser, QueryParserError};
-    use crate::query::Query;
+    use crate::query::{BooleanQuery, ConstantScoreQuery, Query};
     use crate::schema::{
         FacetOptions, Field, IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions,
         INDEXED, STORED, STRING, TEXT,
@@ -1213,6 +1420,36 @@ mod test {
         assert_eq!(format!("{logical_ast:?}"), "*");
     }
 
+    #[test]
+    pub fn test_parse_query_filter() {
+        let mut query_parser = make_query_parser();
+        query_parser.set_filter_syntax();
+        let logical_ast = query_parser
+            .parse_query_to_logical_ast("+title:a +filter(title:b)")
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", logical_ast),
+            r#"(+Term(type=Str, field=0, "a") +filter(Term(type=Str, field=0, "b")))"#
+        );
+        let query = query_parser
+            .parse_query("+title:a +filter(title:b)")
+            .unwrap();
+        let boolean_query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert!(boolean_query.clauses()[1].1.is::<ConstantScoreQuery>());
+        // A clause boosted by 0 is a filter as well.
+        test_parse_query_to_logical_ast_helper(
+            "+title:a +title:b^0",
+            r#"(+Term(type=Str, field=0, "a") +filter(Term(type=Str, field=0, "b")))"#,
+            false,
+        );
+        // Without the filter syntax, `filter` is a word.
+        test_parse_query_to_logical_ast_helper(
+            "+title:a +filter(title:b)",
+            r#"(+Term(type=Str, field=0, "a") +(Term(type=Str, field=0, "filter") Term(type=Str, field=1, "filter")) Term(type=Str, field=0, "b"))"#,
+            false,
+        );
+    }
+
     #[test]
     pub fn test_parse_query_range_require_a_target_field() {
         let query_parser_error = parse_query_to_logical_ast("[A TO B]", false).err().unwrap();
@@ -1502,6 +1739,54 @@ mod test {
         assert_eq!(&super::locate_splitting_dots(r#"a\..b.c"#), &[3, 5]);
     }
 
+    #[test]
+    pub fn test_query_parser_limits() {
+        let mut query_parser = make_query_parser();
+        query_parser.set_max_clause_count(4);
+        assert_matches!(
+            query_parser.parse_query("a b"),
+            Err(QueryParserError::TooManyClauses {
+                num_clauses: 6,
+                max_clause_count: 4
+            })
+        );
+        assert!(query_parser.parse_query("title:a title:b").is_ok());
+
+        let mut query_parser = make_query_parser();
+        query_parser.set_max_depth(2);
+        assert_matches!(
+            query_parser.parse_query("title:a (title:b (title:c title:d))"),
+            Err(QueryParserError::TooDeep {
+                depth: 3,
+                max_depth: 2
+            })
+        );
+        assert!(query_parser
+            .parse_query("title:a (title:b title:c)")
+            .is_ok());
+        // Deeply nested parentheses are rejected before the query is parsed.
+        let deep_query = format!("{}title:a{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_matches!(
+            query_parser.parse_query(&deep_query),
+            Err(QueryParserError::TooDeep {
+                depth: 100_000,
+                max_depth: 2
+            })
+        );
+        assert!(query_parser.parse_query("title:\"(((a b\"").is_ok());
+
+        let mut query_parser = make_query_parser();
+        query_parser.set_max_terms(2);
+        assert_matches!(
+            query_parser.parse_query("title:\"a b c\""),
+            Err(QueryParserError::TooManyTerms {
+                num_terms: 3,
+                max_terms: 2
+            })
+        );
+        assert!(query_parser.parse_query("title:\"a b\"").is_ok());
+    }
+
     #[test]
     pub fn test_phrase_slop() {
         test_parse_query_to_logical_ast_helper(
@@ -1520,4 +1805,28 @@ mod test {
             false,
         );
     }
+
+    #[test]
+    pub fn test_phrase_slop_default() {
+        let mut query_parser = make_query_parser();
+        query_parser.set_phrase_slop(1);
+        let query_str = |query: &str| {
+            format!(
+                "{:?}",
+                query_parser.parse_query_to_logical_ast(query).unwrap()
+            )
+        };
+        assert_eq!(
+            query_str(r#"title:"a b""#),
+            r#""[(0, Term(type=Str, field=0, "a")), (1, Term(type=Str, field=0, "b"))]"~1"#
+        );
+        assert_eq!(
+            query_str(r#"title:"a b"~3"#),
+            r#""[(0, Term(type=Str, field=0, "a")), (1, Term(type=Str, field=0, "b"))]"~3"#
+        );
+        assert_eq!(
+            query_str(r#"with_stop_words:"the a b""#),
+            r#""[(1, Term(type=Str, field=8, "a")), (2, Term(type=Str, field=8, "b"))]"~1"#
+        );
+    }
 }
@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstantScoreQuery, EmptyQuery, FuzzyTermQuery, Occur,
+    PhraseQuery, Query, RangeQuery, RegexQuery, TermQuery,
+};
+use crate::schema::{Field, IndexRecordOption, Term, Type};
+use crate::{Score, TantivyError};
+
+/// A serializable description of a query, built by a [`QueryPlanner`].
+///
+/// A coordinator can parse and plan a query once, and ship the plan to the processes
+/// searching the shards of an index, which turn it back into a query with their own
+/// `QueryPlanner`.
+///
+/// The plan is serialized as a tree of nodes tagged by their type, e.g.
+/// `{"type": "term", ...}`. Terms refer to their fields by id, so the shards should share the
+/// schema of the coordinator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QueryPlan {
+    root: PlanNode,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PlanNode {
+    All,
+    Empty,
+    Term {
+        term: PlanTerm,
+        index_record_option: IndexRecordOption,
+    },
+    Boolean {
+        clauses: Vec<PlanClause>,
+        #[serde(default)]
+        minimum_number_should_match: usize,
+    },
+    Boost {
+        query: Box<PlanNode>,
+        boost: Score,
+    },
+    ConstantScore {
+        query: Box<PlanNode>,
+        score: Score,
+    },
+    Phrase {
+        terms: Vec<(usize, PlanTerm)>,
+        slop: u32,
+    },
+    Range {
+        field: Field,
+        value_type: u8,
+        left_bound: Bound<Vec<u8>>,
+        right_bound: Bound<Vec<u8>>,
+    },
+    Fuzzy {
+        term: PlanTerm,
+        distance: u8,
+        transposition_cost_one: bool,
+        prefix: bool,
+    },
+    Regex {
+        field: Field,
+        pattern: String,
+    },
+    Custom {
+        name: String,
+        query: serde_json::Value,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PlanOccur {
+    Should,
+    Must,
+    MustNot,
+}
+
+impl From<Occur> for PlanOccur {
+    fn from(occur: Occur) -> PlanOccur {
+        match occur {
+            Occur::Should => PlanOccur::Should,
+            Occur::Must => PlanOccur::Must,
+            Occur::MustNot => PlanOccur::MustNot,
+        }
+    }
+}
+
+impl From<PlanOccur> for Occur {
+    fn from(occur: PlanOccur) -> Occur {
+        match occur {
+            PlanOccur::Should => Occur::Should,
+            PlanOccur::Must => Occur::Must,
+            PlanOccur::MustNot => Occur::MustNot,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PlanClause {
+    occur: PlanOccur,
+    query: PlanNode,
+}
+
+/// Term serialized as its base64 encoded bytes.
+#[derive(Clone, Debug, PartialEq)]
+struct PlanTerm(Term);
+
+// Field id, followed by the type code.
+const TERM_HEADER_NUM_BYTES: usize = 5;
+
+impl Serialize for PlanTerm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(self.0.as_slice()))
+    }
+}
+
+impl<'de> Deserialize<'de> for PlanTerm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<PlanTerm, D::Error> {
+        let term_base64 = String::deserialize(deserializer)?;
+        let term_bytes = base64::decode(&term_base64).map_err(D::Error::custom)?;
+        if term_bytes.len() < TERM_HEADER_NUM_BYTES
+            || Type::from_code(term_bytes[TERM_HEADER_NUM_BYTES - 1]).is_none()
+        {
+            return Err(D::Error::custom("Invalid term bytes."));
+        }
+        Ok(PlanTerm(Term::wrap(term_bytes)))
+    }
+}
+
+type PlanFn =
+    Box<dyn Fn(&dyn Query) -> Option<serde_json::Result<serde_json::Value>> + Send + Sync>;
+type BuildFn = Box<dyn Fn(serde_json::Value) -> serde_json::Result<Box<dyn Query>> + Send + Sync>;
+
+struct CustomQuery {
+    plan: PlanFn,
+    build: BuildFn,
+}
+
+/// Turns queries into serializable [`QueryPlan`]s, and back.
+///
+/// The built-in `AllQuery`, `EmptyQuery`, `TermQuery`, `BooleanQuery`, `BoostQuery`,
+/// `ConstantScoreQuery`, `PhraseQuery`, `RangeQuery`, `FuzzyTermQuery` and `RegexQuery` (when
+/// created from a pattern) are supported out of the box. Other queries can be registered under a
+/// name with [`QueryPlanner::register`], provided they implement `serde::Serialize` and
+/// `serde::Deserialize`.
+///
+/// ```rust
+/// use tantivy::query::{QueryParser, QueryPlan, QueryPlanner};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::Index;
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("+diary -girl")?;
+///
+/// let query_planner = QueryPlanner::default();
+/// let plan_json = serde_json::to_string(&query_planner.plan(&query)?)?;
+/// // ... the plan is sent to another process ...
+/// let plan: QueryPlan = serde_json::from_str(&plan_json)?;
+/// let shipped_query = query_planner.build_query(&plan)?;
+/// assert_eq!(shipped_query.canonical_form(), query.canonical_form());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct QueryPlanner {
+    custom_queries: HashMap<String, CustomQuery>,
+}
+
+impl QueryPlanner {
+    /// Registers a query type under `name`, which identifies it in the plans.
+    ///
+    /// The processes building the queries from the plans should register it under the same
+    /// name. Registering another query type under the same name replaces it.
+    pub fn register<Q>(&mut self, name: &str)
+    where Q: Query + Serialize + DeserializeOwned {
+        let plan: PlanFn =
+            Box::new(|query: &dyn Query| query.downcast_ref::<Q>().map(serde_json::to_value));
+        let build: BuildFn = Box::new(|query_json: serde_json::Value| {
+            let query: Q = serde_json::from_value(query_json)?;
+            Ok(Box::new(query) as Box<dyn Query>)
+        });
+        self.custom_queries
+            .insert(name.to_string(), CustomQuery { plan, build });
+    }
+
+    /// Returns the plan of a query.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::InvalidArgument` if the query, or one of its sub-queries, is
+    /// neither a supported built-in query nor a registered query.
+    pub fn plan(&self, query: &dyn Query) -> crate::Result<QueryPlan> {
+        Ok(QueryPlan {
+            root: self.plan_node(query)?,
+        })
+    }
+
+    fn plan_node(&self, query: &dyn Query) -> crate::Result<PlanNode> {
+        if let Some(boxed_query) = query.downcast_ref::<Box<dyn Query>>() {
+            return self.plan_node(boxed_query.as_ref());
+        }
+        if query.is::<AllQuery>() {
+            return Ok(PlanNode::All);
+        }
+        if query.is::<EmptyQuery>() {
+            return Ok(PlanNode::Empty);
+        }
+        if let Some(term_query) = query.downcast_ref::<TermQuery>() {
+            return Ok(PlanNode::Term {
+                term: PlanTerm(term_query.term().clone()),
+                index_record_option: term_query.index_record_option,
+            });
+        }
+        if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+            let clauses = boolean_query
+                .clauses()
+                .iter()
+                .map(|(occur, subquery)| {
+                    Ok(PlanClause {
+                        occur: PlanOccur::from(*occur),
+                        query: self.plan_node(subquery.as_ref())?,
+                    })
+                })
+                .collect::<crate::Result<_>>()?;
+            return Ok(PlanNode::Boolean {
+                clauses,
+                minimum_number_should_match: boolean_query.minimum_number_should_match(),
+            });
+        }
+        if let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+            return Ok(PlanNode::Boost {
+                query: Box::new(self.plan_node(boost_query.query.as_ref())?),
+                boost: boost_query.boost,
+            });
+        }
+        if let Some(constant_score_query) = query.downcast_ref::<ConstantScoreQuery>() {
+            return Ok(PlanNode::ConstantScore {
+                query: Box::new(self.plan_node(constant_score_query.query.as_ref())?),
+                score: constant_score_query.score,
+            });
+        }
+        if let Some(phrase_query) = query.downcast_ref::<PhraseQuery>() {
+            return Ok(PlanNode::Phrase {
+                terms: phrase_query
+                    .phrase_terms
+                    .iter()
+                    .map(|(offset, term)| (*offset, PlanTerm(term.clone())))
+                    .collect(),
+                slop: phrase_query.slop,
+            });
+        }
+        if let Some(range_query) = query.downcast_ref::<RangeQuery>() {
+            return Ok(PlanNode::Range {
+                field: range_query.field(),
+                value_type: range_query.value_type.to_code(),
+                left_bound: range_query.left_bound.clone(),
+                right_bound: range_query.right_bound.clone(),
+            });
+        }
+        if let Some(fuzzy_query) = query.downcast_ref::<FuzzyTermQuery>() {
+            return Ok(PlanNode::Fuzzy {
+                term: PlanTerm(fuzzy_query.term.clone()),
+                distance: fuzzy_query.distance,
+                transposition_cost_one: fuzzy_query.transposition_cost_one,
+                prefix: fuzzy_query.prefix,
+            });
+        }
+        if let Some(regex_query) = query.downcast_ref::<RegexQuery>() {
+            if let Some(pattern) = &regex_query.pattern {
+                return Ok(PlanNode::Regex {
+                    field: regex_query.field,
+                    pattern: pattern.clone(),
+                });
+            }
+        }
+        for (name, custom_query) in &self.custom_queries {
+            if let Some(query_json) = (custom_query.plan)(query) {
+                return Ok(PlanNode::Custom {
+                    name: name.clone(),
+                    query: query_json?,
+                });
+            }
+        }
+        Err(TantivyError::InvalidArgument(format!(
+            "Cannot plan the query {:?}: it is not supported, nor registered.",
+            query
+        )))
+    }
+
+    /// Builds the query described by a plan.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::InvalidArgument` if the plan contains a query that is not
+    /// registered, or an invalid query.
+    pub fn build_query(&self, plan: &QueryPlan) -> crate::Result<Box<dyn Query>> {
+        self.build_node(&plan.root)
+    }
+
+    fn build_node(&self, node: &PlanNode) -> crate::Result<Box<dyn Query>> {
+        let query: Box<dyn Query> = match node {
+            PlanNode::All => Box::new(AllQuery),
+            PlanNode::Empty => Box::new(EmptyQuery),
+            PlanNode::Term {
+                term,
+                index_record_option,
+            } => Box::new(TermQuery::new(term.0.clone(), *index_record_option)),
+            PlanNode::Boolean {
+                clauses,
+                minimum_number_should_match,
+            } => {
+                let subqueries = clauses
+                    .iter()
+                    .map(|clause| Ok((Occur::from(clause.occur), self.build_node(&clause.query)?)))
+                    .collect::<crate::Result<_>>()?;
+                Box::new(
+                    BooleanQuery::new(subqueries)
+                        .with_minimum_number_should_match(*minimum_number_should_match),
+                )
+            }
+            PlanNode::Boost { query, boost } => {
+                Box::new(BoostQuery::new(self.build_node(query)?, *boost))
+            }
+            PlanNode::ConstantScore { query, score } => {
+                Box::new(ConstantScoreQuery::new(self.build_node(query)?, *score))
+            }
+            PlanNode::Phrase { terms, slop } => {
+                let field = terms.first().map(|(_, term)| term.0.field());
+                if terms.len() < 2 || terms.iter().any(|(_, term)| Some(term.0.field()) != field) {
+                    return Err(TantivyError::InvalidArgument(
+                        "A phrase query requires at least two terms of the same field.".to_string(),
+                    ));
+                }
+                let terms = terms
+                    .iter()
+                    .map(|(offset, term)| (*offset, term.0.clone()))
+                    .collect();
+                Box::new(PhraseQuery::new_with_offset_and_slop(terms, *slop))
+            }
+            PlanNode::Range {
+                field,
+                value_type,
+                left_bound,
+                right_bound,
+            } => {
+                let value_type = Type::from_code(*value_type).ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!("Invalid type code {}.", value_type))
+                })?;
+                let to_term = |value_bytes: &Vec<u8>| {
+                    Term::create_bytes_term(value_type, *field, value_bytes)
+                };
+                Box::new(RangeQuery::new_term_bounds(
+                    *field,
+                    value_type,
+                    &map_bound(left_bound, to_term),
+                    &map_bound(right_bound, to_term),
+                ))
+            }
+            PlanNode::Fuzzy {
+                term,
+                distance,
+                transposition_cost_one,
+                prefix,
+            } => {
+                let term = term.0.clone();
+                Box::new(if *prefix {
+                    FuzzyTermQuery::new_prefix(term, *distance, *transposition_cost_one)
+                } else {
+                    FuzzyTermQuery::new(term, *distance, *transposition_cost_one)
+                })
+            }
+            PlanNode::Regex { field, pattern } => {
+                Box::new(RegexQuery::from_pattern(pattern, *field)?)
+            }
+            PlanNode::Custom { name, query } => {
+                let custom_query = self.custom_queries.get(name).ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!(
+                        "The query {:?} is not registered.",
+                        name
+                    ))
+                })?;
+                (custom_query.build)(query.clone())?
+            }
+        };
+        Ok(query)
+    }
+}
+
+fn map_bound<TFrom, TTo>(bound: &Bound<TFrom>, transform: impl Fn(&TFrom) -> TTo) -> Bound<TTo> {
+    match bound {
+        Bound::Included(value) => Bound::Included(transform(value)),
+        Bound::Excluded(value) => Bound::Excluded(transform(value)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{QueryPlan, QueryPlanner};
+    use crate::collector::Count;
+    use crate::query::{
+        BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, QueryParser,
+        RangeQuery, RegexQuery, TermQuery, Weight,
+    };
+    use crate::schema::{IndexRecordOption, Schema, FAST, INDEXED, TEXT};
+    use crate::{doc, Index, Searcher, Term};
+
+    // Matches the documents containing a term, given its text.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct TextQuery {
+        field: u32,
+        text: String,
+    }
+
+    impl Query for TextQuery {
+        fn weight(
+            &self,
+            searcher: &Searcher,
+            scoring_enabled: bool,
+        ) -> crate::Result<Box<dyn Weight>> {
+            let term =
+                Term::from_field_text(crate::schema::Field::from_field_id(self.field), &self.text);
+            TermQuery::new(term, IndexRecordOption::Basic).weight(searcher, scoring_enabled)
+        }
+
+        fn query_terms(&self, _term_set: &mut BTreeMap<Term, bool>) {}
+    }
+
+    fn ship(query_planner: &QueryPlanner, query: &dyn Query) -> crate::Result<Box<dyn Query>> {
+        let plan_json = serde_json::to_string(&query_planner.plan(query)?)?;
+        let plan: QueryPlan = serde_json::from_str(&plan_json)?;
+        query_planner.build_query(&plan)
+    }
+
+    #[test]
+    fn test_query_plan() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_u64_field("year", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "the diary of a young girl", year => 1947u64))?;
+        index_writer.add_document(doc!(title => "the diary of muadib", year => 1965u64))?;
+        index_writer.add_document(doc!(title => "a dairy cow", year => 1980u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let mut query_planner = QueryPlanner::default();
+        query_planner.register::<TextQuery>("text");
+
+        let mut query_parser = QueryParser::for_index(&index, vec![title]);
+        query_parser.set_filter_syntax();
+        let queries: Vec<Box<dyn Query>> = vec![
+            query_parser.parse_query("+diary -girl")?,
+            query_parser.parse_query("\"diary of\"~1 OR cow^2")?,
+            query_parser.parse_query("year:[1950 TO 1990}")?,
+            query_parser.parse_query("*")?,
+            Box::new(BooleanQuery::new(vec![
+                (
+                    Occur::Should,
+                    Box::new(FuzzyTermQuery::new_prefix(
+                        Term::from_field_text(title, "dia"),
+                        1,
+                        true,
+                    )),
+                ),
+                (
+                    Occur::Must,
+                    Box::new(RegexQuery::from_pattern("d[ai]+ry", title)?),
+                ),
+            ])),
+            Box::new(BoostQuery::new(
+                Box::new(TextQuery {
+                    field: title.field_id(),
+                    text: "cow".to_string(),
+                }),
+                2.0,
+            )),
+            query_parser.parse_query("+diary +filter(year:[1950 TO 1990})")?,
+            Box::new(PhraseQuery::new_with_offset_and_slop(
+                vec![
+                    (0, Term::from_field_text(title, "diary")),
+                    (2, Term::from_field_text(title, "muadib")),
+                ],
+                0,
+            )),
+            Box::new(
+                BooleanQuery::new(
+                    ["diary", "muadib", "cow"]
+                        .iter()
+                        .map(|text| -> (Occur, Box<dyn Query>) {
+                            (
+                                Occur::Should,
+                                Box::new(TermQuery::new(
+                                    Term::from_field_text(title, text),
+                                    IndexRecordOption::Basic,
+                                )),
+                            )
+                        })
+                        .collect(),
+                )
+                .with_minimum_number_should_match(2),
+            ),
+            Box::new(RangeQuery::new_u64(year, 1940..1970)),
+        ];
+        for query in &queries {
+            let shipped_query = ship(&query_planner, query.as_ref())?;
+            assert_eq!(
+                searcher.search(&shipped_query, &Count)?,
+                searcher.search(query, &Count)?
+            );
+            assert!(searcher.search(query, &Count)? > 0);
+        }
+        assert_eq!(searcher.search(&queries[8], &Count)?, 1);
+
+        assert!(QueryPlanner::default()
+            .plan(queries.last().unwrap())
+            .is_ok());
+        assert!(QueryPlanner::default().plan(&queries[5]).is_err());
+        let plan = query_planner.plan(&queries[5])?;
+        assert!(QueryPlanner::default().build_query(&plan).is_err());
+        let invalid_plan: QueryPlan =
+            serde_json::from_str(r#"{"type": "phrase", "terms": [], "slop": 0}"#)?;
+        assert!(query_planner.build_query(&invalid_plan).is_err());
+        Ok(())
+    }
+}
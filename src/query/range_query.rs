@@ -66,9 +66,9 @@ fn map_bound<TFrom, TTo, Transform: Fn(&TFrom) -> TTo>(
 #[derive(Clone, Debug)]
 pub struct RangeQuery {
     field: Field,
-    value_type: Type,
-    left_bound: Bound<Vec<u8>>,
-    right_bound: Bound<Vec<u8>>,
+    pub(crate) value_type: Type,
+    pub(crate) left_bound: Bound<Vec<u8>>,
+    pub(crate) right_bound: Bound<Vec<u8>>,
 }
 
 impl RangeQuery {
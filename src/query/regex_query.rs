@@ -57,7 +57,9 @@ use crate::Searcher;
 #[derive(Debug, Clone)]
 pub struct RegexQuery {
     regex: Arc<Regex>,
-    field: Field,
+    pub(crate) field: Field,
+    // Pattern of the regex, if the query was created from it.
+    pub(crate) pattern: Option<String>,
 }
 
 impl RegexQuery {
@@ -65,7 +67,11 @@ impl RegexQuery {
     pub fn from_pattern(regex_pattern: &str, field: Field) -> crate::Result<Self> {
         let regex = Regex::new(regex_pattern)
             .map_err(|_| TantivyError::InvalidArgument(regex_pattern.to_string()))?;
-        Ok(RegexQuery::from_regex(regex, field))
+        Ok(RegexQuery {
+            regex: Arc::new(regex),
+            field,
+            pattern: Some(regex_pattern.to_string()),
+        })
     }
 
     /// Creates a new RegexQuery from a fully built Regex
@@ -73,6 +79,7 @@ impl RegexQuery {
         RegexQuery {
             regex: regex.into(),
             field,
+            pattern: None,
         }
     }
 
@@ -77,3 +77,40 @@ impl ScoreCombiner for SumWithCoordsCombiner {
         self.score
     }
 }
+
+/// Takes the highest score of different scorers, and adds the other
+/// scores multiplied by a tie breaker.
+#[derive(Default, Clone, Copy)]
+pub struct DisjunctionMaxCombiner {
+    tie_breaker: Score,
+    max: Score,
+    sum: Score,
+}
+
+impl DisjunctionMaxCombiner {
+    /// Creates a combiner multiplying the scores other than the highest one by `tie_breaker`.
+    pub fn with_tie_breaker(tie_breaker: Score) -> Self {
+        DisjunctionMaxCombiner {
+            tie_breaker,
+            max: 0.0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl ScoreCombiner for DisjunctionMaxCombiner {
+    fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
+        let score = scorer.score();
+        self.max = self.max.max(score);
+        self.sum += score;
+    }
+
+    fn clear(&mut self) {
+        self.max = 0.0;
+        self.sum = 0.0;
+    }
+
+    fn score(&self) -> Score {
+        self.max + (self.sum - self.max) * self.tie_breaker
+    }
+}
@@ -12,9 +12,13 @@ mod tests {
     use crate::collector::TopDocs;
     use crate::docset::DocSet;
     use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
-    use crate::query::{Query, QueryParser, Scorer, TermQuery};
-    use crate::schema::{Field, IndexRecordOption, Schema, STRING, TEXT};
-    use crate::{assert_nearly_equals, DocAddress, Index, Term, TERMINATED};
+    use crate::postings::Postings;
+    use crate::query::{BooleanQuery, BoostQuery, Occur, Query, QueryParser, Scorer, TermQuery};
+    use crate::schema::{
+        Document, Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, STRING, TEXT,
+    };
+    use crate::tokenizer::{PreTokenizedString, Token};
+    use crate::{assert_nearly_equals, DocAddress, Index, Score, Term, TERMINATED};
 
     #[test]
     pub fn test_term_query_no_freq() -> crate::Result<()> {
@@ -42,6 +46,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_term_query_docs_only_text_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let indexing = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::Basic)
+            .set_fieldnorms(false);
+        let tags = schema_builder.add_text_field(
+            "tags",
+            TextOptions::default().set_indexing_options(indexing),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(tags => "red red red blue"))?;
+        index_writer.add_document(doc!(tags => "red"))?;
+        index_writer.add_document(doc!(tags => "blue"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![tags]).parse_query("red")?;
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(3))?;
+        assert_eq!(top_docs.len(), 2);
+        assert_nearly_equals!(top_docs[0].0, top_docs[1].0);
+        let postings = searcher
+            .segment_reader(0)
+            .inverted_index(tags)?
+            .read_postings(
+                &Term::from_field_text(tags, "red"),
+                IndexRecordOption::WithFreqs,
+            )?
+            .unwrap();
+        assert_eq!(postings.term_freq(), 1);
+        Ok(())
+    }
+
     #[test]
     pub fn test_term_query_multiple_of_block_len() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -213,4 +250,73 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_term_query_impacts() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqs)
+                .set_impacts(true),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let weighted_tokens = |words: &[(&str, u32)]| PreTokenizedString {
+            text: String::new(),
+            tokens: words
+                .iter()
+                .enumerate()
+                .map(|(position, &(word, payload))| Token {
+                    position,
+                    text: word.to_string(),
+                    payload,
+                    ..Token::default()
+                })
+                .collect(),
+        };
+        for doc in 0u32..300u32 {
+            let mut document = Document::default();
+            document.add_pre_tokenized_text(
+                text_field,
+                weighted_tokens(&[("a", (doc * 7) % 256), ("b", 3), ("b", 2)]),
+            );
+            index_writer.add_document(document)?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "b"),
+            IndexRecordOption::Basic,
+        );
+        let explanation = term_query.explain(&searcher, DocAddress::new(0u32, 10u32))?;
+        assert_nearly_equals!(explanation.value(), 5.0);
+
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(3))?;
+        let top_scores: Vec<Score> = top_docs.iter().map(|(score, _)| *score).collect();
+        assert_eq!(top_scores, vec![255.0, 254.0, 253.0]);
+        assert_eq!(top_docs[0].1, DocAddress::new(0u32, 73u32));
+
+        let boosted_query = BooleanQuery::new(vec![
+            (
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(term_query), 0.5)) as Box<dyn Query>,
+            ),
+            (
+                Occur::Should,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(text_field, "b"),
+                    IndexRecordOption::Basic,
+                )),
+            ),
+        ]);
+        let top_docs = searcher.search(&boosted_query, &TopDocs::with_limit(1))?;
+        assert_nearly_equals!(top_docs[0].0, 255.0 * 0.5 + 5.0);
+        Ok(())
+    }
 }
@@ -17,6 +17,10 @@ use crate::{Searcher, Term};
 /// * `term_freq`  - number of occurrences of the term in the field
 /// * `field norm` - number of tokens in the field.
 ///
+/// On a field storing impacts (See
+/// [`TextFieldIndexing::set_impacts`](crate::schema::TextFieldIndexing::set_impacts)),
+/// the score is the impact of the term in the document instead.
+///
 /// ```rust
 /// use tantivy::collector::{Count, TopDocs};
 /// use tantivy::query::TermQuery;
@@ -58,7 +62,7 @@ use crate::{Searcher, Term};
 #[derive(Clone)]
 pub struct TermQuery {
     term: Term,
-    index_record_option: IndexRecordOption,
+    pub(crate) index_record_option: IndexRecordOption,
 }
 
 impl fmt::Debug for TermQuery {
@@ -96,12 +100,16 @@ impl TermQuery {
             let error_msg = format!("Field {:?} is not indexed.", field_entry.name());
             return Err(crate::TantivyError::SchemaError(error_msg));
         }
+        let has_impacts = field_entry.field_type().has_impacts();
         let bm25_weight = if scoring_enabled {
             Bm25Weight::for_terms(searcher, &[self.term.clone()])?
         } else {
             Bm25Weight::new(Explanation::new("<no score>".to_string(), 1.0f32), 1.0f32)
         };
-        let index_record_option = if scoring_enabled {
+        let index_record_option = if has_impacts && scoring_enabled {
+            // Impacts are stored as term frequencies.
+            IndexRecordOption::WithFreqs
+        } else if scoring_enabled {
             self.index_record_option
         } else {
             IndexRecordOption::Basic
@@ -121,6 +129,11 @@ impl Query for TermQuery {
             self.specialized_weight(searcher, scoring_enabled)?,
         ))
     }
+    fn canonical_form(&self) -> String {
+        // The record option changes the scores.
+        format!("TermQuery({:?}, {:?})", self.term, self.index_record_option)
+    }
+
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         terms.insert(self.term.clone(), false);
     }
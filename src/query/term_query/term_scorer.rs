@@ -106,6 +106,10 @@ impl DocSet for TermScorer {
         self.postings.seek(target)
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        self.postings.fill_buffer(buffer)
+    }
+
     fn doc(&self) -> DocId {
         self.postings.doc()
     }
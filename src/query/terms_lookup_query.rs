@@ -0,0 +1,399 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use common::BitSet;
+
+use crate::core::{Searcher, SegmentReader};
+use crate::fastfield::{fast_field_default_value, type_and_cardinality, FastFieldReader};
+use crate::query::explanation::does_not_match;
+use crate::query::{BitSetDocSet, ConstScorer, EmptyScorer, Explanation, Query, Scorer, Weight};
+use crate::schema::{Cardinality, Field, FieldType, IndexRecordOption, Term};
+use crate::{DocId, DocSet, Score, TantivyError, TERMINATED};
+
+/// `TermsLookupQuery` joins the documents of an index on a key.
+///
+/// It runs its `lookup_query` first, and collects the values of the `from_field` fast field of
+/// the documents it matches. It then matches the documents containing any of these values in
+/// their `to_field`, e.g. the comments of the posts written by a given author, given the
+/// `post_id` fast field of the posts and the `post_id` indexed field of the comments.
+///
+/// The `from_field` can be a numeric, boolean, date, text or facet fast field, and the
+/// `to_field` an indexed field of the same type. All of the matched documents get the score
+/// 1.0.
+///
+/// The documents of the `lookup_query` without a value are skipped. A single valued
+/// `from_field` must hence also be indexed, as its fast field cannot tell them apart from the
+/// documents holding the default value, e.g. 0.
+///
+/// The values are collected once for the whole searcher when the weight of the query is
+/// created, which makes it as expensive as a search of the `lookup_query`.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::{TermQuery, TermsLookupQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, FAST, INDEXED, STRING};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let author = schema_builder.add_text_field("author", STRING);
+/// let post_id = schema_builder.add_u64_field("post_id", FAST | INDEXED);
+/// let comment_of = schema_builder.add_u64_field("comment_of", INDEXED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(author => "paul", post_id => 1u64))?;
+/// index_writer.add_document(doc!(author => "leto", post_id => 2u64))?;
+/// index_writer.add_document(doc!(comment_of => 1u64))?;
+/// index_writer.add_document(doc!(comment_of => 1u64))?;
+/// index_writer.add_document(doc!(comment_of => 2u64))?;
+/// index_writer.commit()?;
+/// let searcher = index.reader()?.searcher();
+///
+/// let posts_of_paul = TermQuery::new(
+///     Term::from_field_text(author, "paul"),
+///     IndexRecordOption::Basic,
+/// );
+/// let comments_of_paul = TermsLookupQuery::new(Box::new(posts_of_paul), post_id, comment_of);
+/// assert_eq!(searcher.search(&comments_of_paul, &Count)?, 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TermsLookupQuery {
+    lookup_query: Box<dyn Query>,
+    from_field: Field,
+    to_field: Field,
+}
+
+impl TermsLookupQuery {
+    /// Creates a new `TermsLookupQuery`, matching the documents whose `to_field` contains a
+    /// value of the `from_field` of the documents matching `lookup_query`.
+    pub fn new(lookup_query: Box<dyn Query>, from_field: Field, to_field: Field) -> Self {
+        TermsLookupQuery {
+            lookup_query,
+            from_field,
+            to_field,
+        }
+    }
+
+    /// Returns the terms of the `to_field` holding the values of the `from_field` of the
+    /// documents matching the lookup query, sorted.
+    pub fn lookup_terms(&self, searcher: &Searcher) -> crate::Result<Vec<Term>> {
+        let schema = searcher.schema();
+        let from_entry = schema.get_field_entry(self.from_field);
+        let to_entry = schema.get_field_entry(self.to_field);
+        let from_type = from_entry.field_type();
+        let is_term_ordinal = matches!(from_type, FieldType::Str(_) | FieldType::Facet(_));
+        let cardinality = type_and_cardinality(from_type)
+            .map(|(_, cardinality)| cardinality)
+            .ok_or_else(|| {
+                TantivyError::SchemaError(format!(
+                    "Field {:?} is not a fast field.",
+                    from_entry.name()
+                ))
+            })?;
+        if cardinality == Cardinality::SingleValue && !from_entry.is_indexed() {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is single valued but not indexed: the documents without a value \
+                 cannot be told apart from those holding the default value.",
+                from_entry.name()
+            )));
+        }
+        if !to_entry.is_indexed() || to_entry.field_type().value_type() != from_type.value_type() {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not an indexed field of the type of {:?}.",
+                to_entry.name(),
+                from_entry.name()
+            )));
+        }
+        let weight = self.lookup_query.weight(searcher, false)?;
+        let mut values: BTreeSet<Vec<u8>> = BTreeSet::new();
+        let mut vals = Vec::new();
+        for segment_reader in searcher.segment_readers() {
+            let mut segment_values: BTreeSet<u64> = BTreeSet::new();
+            let alive_bitset_opt = segment_reader.alive_bitset();
+            let is_alive = |doc: DocId| {
+                alive_bitset_opt
+                    .map(|alive_bitset| alive_bitset.is_alive(doc))
+                    .unwrap_or(true)
+            };
+            match cardinality {
+                Cardinality::SingleValue => {
+                    let ff_reader = segment_reader.fast_fields().u64_lenient(self.from_field)?;
+                    // The documents without a value hold the default value of the fast field.
+                    let default_value = fast_field_default_value(from_entry);
+                    let mut default_value_docs = Vec::new();
+                    weight.for_each_no_score(segment_reader, &mut |docs| {
+                        for &doc in docs.iter().filter(|&&doc| is_alive(doc)) {
+                            let value = ff_reader.get(doc);
+                            if value == default_value {
+                                default_value_docs.push(doc);
+                            } else {
+                                segment_values.insert(value);
+                            }
+                        }
+                    })?;
+                    // Only the postings of the default value tell apart the documents holding it.
+                    let mut default_term = Term::from_field_u64(self.from_field, 0);
+                    default_term.clear_with_type(from_type.value_type());
+                    default_term.append_bytes(&default_value.to_be_bytes());
+                    if contains_any(segment_reader, &default_term, &default_value_docs)? {
+                        segment_values.insert(default_value);
+                    }
+                }
+                Cardinality::MultiValues => {
+                    let ff_reader = segment_reader.fast_fields().u64s_lenient(self.from_field)?;
+                    weight.for_each_no_score(segment_reader, &mut |docs| {
+                        for &doc in docs.iter().filter(|&&doc| is_alive(doc)) {
+                            ff_reader.get_vals(doc, &mut vals);
+                            segment_values.extend(vals.iter().copied());
+                        }
+                    })?;
+                }
+            }
+            if is_term_ordinal {
+                // The values are the ordinals of the terms in the dictionary of the segment.
+                let inverted_index = segment_reader.inverted_index(self.from_field)?;
+                let mut term_bytes = Vec::new();
+                for term_ord in segment_values {
+                    if inverted_index
+                        .terms()
+                        .ord_to_term(term_ord, &mut term_bytes)?
+                    {
+                        values.insert(term_bytes.clone());
+                    }
+                }
+            } else {
+                values.extend(
+                    segment_values
+                        .into_iter()
+                        .map(|value| value.to_be_bytes().to_vec()),
+                );
+            }
+        }
+        let value_type = to_entry.field_type().value_type();
+        Ok(values
+            .into_iter()
+            .map(|value_bytes| {
+                let mut term = Term::from_field_u64(self.to_field, 0);
+                term.clear_with_type(value_type);
+                term.append_bytes(&value_bytes);
+                term
+            })
+            .collect())
+    }
+}
+
+// Returns true if one of the sorted `docs` contains `term`.
+fn contains_any(
+    segment_reader: &SegmentReader,
+    term: &Term,
+    docs: &[DocId],
+) -> crate::Result<bool> {
+    if docs.is_empty() {
+        return Ok(false);
+    }
+    let inverted_index = segment_reader.inverted_index(term.field())?;
+    match inverted_index.read_postings(term, IndexRecordOption::Basic)? {
+        Some(mut postings) => Ok(docs.iter().any(|&doc| postings.seek(doc) == doc)),
+        None => Ok(false),
+    }
+}
+
+impl Clone for TermsLookupQuery {
+    fn clone(&self) -> Self {
+        TermsLookupQuery {
+            lookup_query: self.lookup_query.box_clone(),
+            from_field: self.from_field,
+            to_field: self.to_field,
+        }
+    }
+}
+
+impl fmt::Debug for TermsLookupQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TermsLookup(query={:?}, from_field={}, to_field={})",
+            self.lookup_query,
+            self.from_field.field_id(),
+            self.to_field.field_id()
+        )
+    }
+}
+
+impl Query for TermsLookupQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(TermsLookupWeight {
+            terms: self.lookup_terms(searcher)?,
+        }))
+    }
+
+    fn canonical_form(&self) -> String {
+        format!(
+            "TermsLookup(query={}, from_field={}, to_field={})",
+            self.lookup_query.canonical_form(),
+            self.from_field.field_id(),
+            self.to_field.field_id()
+        )
+    }
+}
+
+struct TermsLookupWeight {
+    terms: Vec<Term>,
+}
+
+impl TermsLookupWeight {
+    fn bitset(&self, reader: &SegmentReader) -> crate::Result<Option<BitSet>> {
+        let field = match self.terms.first() {
+            Some(term) => term.field(),
+            None => return Ok(None),
+        };
+        let inverted_index = reader.inverted_index(field)?;
+        let mut bitset = BitSet::with_max_value(reader.max_doc());
+        for term in &self.terms {
+            if let Some(mut postings) =
+                inverted_index.read_postings(term, IndexRecordOption::Basic)?
+            {
+                let mut doc = postings.doc();
+                while doc != TERMINATED {
+                    bitset.insert(doc);
+                    doc = postings.advance();
+                }
+            }
+        }
+        Ok(Some(bitset))
+    }
+}
+
+impl Weight for TermsLookupWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        match self.bitset(reader)? {
+            Some(bitset) => Ok(Box::new(ConstScorer::new(
+                BitSetDocSet::from(bitset),
+                boost,
+            ))),
+            None => Ok(Box::new(EmptyScorer)),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let matches = self
+            .bitset(reader)?
+            .map(|bitset| bitset.contains(doc))
+            .unwrap_or(false);
+        if !matches {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("TermsLookupQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermsLookupQuery;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, INDEXED, STRING};
+    use crate::{DocAddress, Index, TantivyError, Term};
+
+    #[test]
+    fn test_terms_lookup_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let kind = schema_builder.add_text_field("kind", STRING);
+        let author = schema_builder.add_text_field("author", STRING | FAST);
+        let follows = schema_builder.add_text_field("follows", STRING);
+        let id = schema_builder.add_u64_field("id", FAST | INDEXED);
+        let unindexed_id = schema_builder.add_u64_field("unindexed_id", FAST);
+        let parent_id = schema_builder.add_u64_field("parent_id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(
+            doc!(kind => "post", id => 1u64, unindexed_id => 1u64, author => "paul"),
+        )?;
+        index_writer.add_document(
+            doc!(kind => "post", id => 2u64, unindexed_id => 2u64, author => "leto"),
+        )?;
+        index_writer.commit()?;
+        index_writer.add_document(
+            doc!(kind => "post", id => 3u64, unindexed_id => 3u64, author => "paul"),
+        )?;
+        index_writer.add_document(doc!(parent_id => 1u64, follows => "paul"))?;
+        index_writer.add_document(doc!(parent_id => 2u64, follows => "leto"))?;
+        index_writer.add_document(doc!(parent_id => 3u64, follows => "jessica"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let term_query = |field, text| {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let paul_posts = term_query(author, "paul");
+        let replies = TermsLookupQuery::new(paul_posts.clone(), id, parent_id);
+        assert_eq!(
+            replies.lookup_terms(&searcher)?,
+            vec![
+                Term::from_field_u64(parent_id, 1),
+                Term::from_field_u64(parent_id, 3)
+            ]
+        );
+        assert_eq!(searcher.search(&replies, &Count)?, 2);
+        let top_docs = searcher.search(&replies, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs[0].0, 1.0);
+        let reply_address = top_docs[0].1;
+        assert_eq!(replies.explain(&searcher, reply_address)?.value(), 1.0);
+        let other_address = DocAddress::new(reply_address.segment_ord, 0);
+        assert!(top_docs
+            .iter()
+            .all(|(_, address)| *address != other_address));
+        assert!(replies.explain(&searcher, other_address).is_err());
+
+        // The term ordinals of the text fast fields are specific to each segment.
+        let post_authors = TermsLookupQuery::new(term_query(kind, "post"), author, follows);
+        assert_eq!(searcher.search(&post_authors, &Count)?, 2);
+
+        let no_post = TermsLookupQuery::new(term_query(kind, "comment"), id, parent_id);
+        assert_eq!(searcher.search(&no_post, &Count)?, 0);
+
+        // The posts without an indexed id do not look up the default id 0.
+        index_writer.add_document(doc!(kind => "post", author => "jessica"))?;
+        index_writer.add_document(doc!(parent_id => 0u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let post_replies = TermsLookupQuery::new(term_query(kind, "post"), id, parent_id);
+        assert_eq!(
+            post_replies.lookup_terms(&searcher)?,
+            vec![
+                Term::from_field_u64(parent_id, 1),
+                Term::from_field_u64(parent_id, 2),
+                Term::from_field_u64(parent_id, 3)
+            ]
+        );
+        // A post with the id 0 looks it up.
+        index_writer.add_document(doc!(kind => "post", id => 0u64, author => "alia"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert!(post_replies
+            .lookup_terms(&searcher)?
+            .contains(&Term::from_field_u64(parent_id, 0)));
+
+        // A single valued field which is not indexed cannot tell apart the default value.
+        let unindexed_post_replies =
+            TermsLookupQuery::new(term_query(kind, "post"), unindexed_id, parent_id);
+        assert!(matches!(
+            unindexed_post_replies.lookup_terms(&searcher),
+            Err(TantivyError::SchemaError(_))
+        ));
+
+        let not_fast = TermsLookupQuery::new(Box::new(AllQuery), parent_id, id);
+        assert!(searcher.search(&not_fast, &Count).is_err());
+        let type_mismatch = TermsLookupQuery::new(Box::new(AllQuery), id, follows);
+        assert!(searcher.search(&type_mismatch, &Count).is_err());
+        Ok(())
+    }
+}
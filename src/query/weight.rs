@@ -1,7 +1,7 @@
 use super::Scorer;
 use crate::core::SegmentReader;
 use crate::query::Explanation;
-use crate::{DocId, Score, TERMINATED};
+use crate::{DocId, DocSet, Score, TantivyError, COLLECT_BLOCK_BUFFER_LEN, TERMINATED};
 
 /// Iterates through all of the document matched by the DocSet
 /// `DocSet` and push the scored documents to the collector.
@@ -16,6 +16,24 @@ pub(crate) fn for_each_scorer<TScorer: Scorer + ?Sized>(
     }
 }
 
+/// Iterates through all of the documents matched by the `DocSet`, and pushes
+/// them to the callback by blocks of at most `COLLECT_BLOCK_BUFFER_LEN` doc ids.
+pub(crate) fn for_each_docset<TDocSet: DocSet + ?Sized>(
+    docset: &mut TDocSet,
+    callback: &mut dyn FnMut(&[DocId]),
+) {
+    let mut buffer = [0u32; COLLECT_BLOCK_BUFFER_LEN];
+    loop {
+        let num_docs = docset.fill_buffer(&mut buffer);
+        if num_docs > 0 {
+            callback(&buffer[..num_docs]);
+        }
+        if num_docs < buffer.len() {
+            break;
+        }
+    }
+}
+
 /// Calls `callback` with all of the `(doc, score)` for which score
 /// is exceeding a given threshold.
 ///
@@ -56,6 +74,48 @@ pub trait Weight: Send + Sync + 'static {
     /// Returns an `Explanation` for the given document.
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation>;
 
+    /// Returns the score of the given document, or `None` if it does not match or is deleted.
+    ///
+    /// The scorer seeks the document directly, skipping the blocks of postings before it.
+    fn score_doc(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Score>> {
+        Ok(self.score_docs(reader, &[doc])?.pop().flatten())
+    }
+
+    /// Returns the score of each of the given documents, in the same order, or `None`
+    /// for the ones that do not match or are deleted.
+    ///
+    /// A single scorer seeks the documents by increasing doc id.
+    ///
+    /// Returns an `InvalidArgument` error if a doc id is not lower than the `max_doc` of the
+    /// segment.
+    fn score_docs(
+        &self,
+        reader: &SegmentReader,
+        docs: &[DocId],
+    ) -> crate::Result<Vec<Option<Score>>> {
+        if let Some(&doc) = docs.iter().find(|&&doc| doc >= reader.max_doc()) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Doc id {} is out of the segment, which has a max doc of {}.",
+                doc,
+                reader.max_doc()
+            )));
+        }
+        let mut positions: Vec<usize> = (0..docs.len()).collect();
+        positions.sort_by_key(|&position| docs[position]);
+        let mut scores = vec![None; docs.len()];
+        let mut scorer = self.scorer(reader, 1.0)?;
+        for position in positions {
+            let doc = docs[position];
+            if reader.is_deleted(doc) || scorer.doc() > doc {
+                continue;
+            }
+            if scorer.seek(doc) == doc {
+                scores[position] = Some(scorer.score());
+            }
+        }
+        Ok(scores)
+    }
+
     /// Returns the number documents within the given `SegmentReader`.
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         let mut scorer = self.scorer(reader, 1.0)?;
@@ -78,6 +138,21 @@ pub trait Weight: Send + Sync + 'static {
         Ok(())
     }
 
+    /// Iterates through all of the documents matched by the DocSet, without scoring
+    /// them, and pushes them to the callback by blocks of doc ids.
+    ///
+    /// This is used by collectors that do not require scoring, and saves most of the
+    /// dynamic dispatch of iterating through a `Box<dyn Scorer>` doc by doc.
+    fn for_each_no_score(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(&[DocId]),
+    ) -> crate::Result<()> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        for_each_docset(scorer.as_mut(), callback);
+        Ok(())
+    }
+
     /// Calls `callback` with all of the `(doc, score)` for which score
     /// is exceeding a given threshold.
     ///
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use std::{cmp, fmt, hash};
+
+use crate::core::searcher::SearcherGeneration;
+use crate::space_usage::ByteCount;
+
+/// Heap memory held by an [`IndexReader`](super::IndexReader).
+///
+/// A generation stays alive as long as one of its searchers is in use, so that slow queries
+/// can pin the doc store caches of old generations long after newer generations were loaded.
+///
+/// The bytes accounted for are those allocated on the heap by the reader: the blocks held by
+/// the doc store caches of the searchers, pooled or in use, and the fruits of the results
+/// cache. The segment data read through the directory, e.g. memory-mapped files, is not
+/// accounted for, see [`SegmentReader::num_bytes`](crate::SegmentReader::num_bytes) instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReaderMemoryUsage {
+    /// The number of bytes held by the doc store caches of the searchers of each live
+    /// generation, by generation id.
+    pub generations: BTreeMap<u64, ByteCount>,
+    /// The number of bytes held by the results cache, see
+    /// [`IndexReader::search_cached`](super::IndexReader::search_cached).
+    pub results_cache: ByteCount,
+    /// The number of bytes held by all of the live generations and the results cache.
+    pub total: ByteCount,
+}
+
+impl ReaderMemoryUsage {
+    pub(crate) fn from_generations<'a>(
+        generations: impl IntoIterator<Item = &'a SearcherGeneration>,
+        results_cache: ByteCount,
+    ) -> ReaderMemoryUsage {
+        let mut memory_usage = ReaderMemoryUsage {
+            results_cache,
+            total: results_cache,
+            ..ReaderMemoryUsage::default()
+        };
+        for generation in generations {
+            let num_bytes = generation.memory().num_bytes();
+            memory_usage
+                .generations
+                .insert(generation.generation_id(), num_bytes);
+            memory_usage.total += num_bytes;
+        }
+        memory_usage
+    }
+}
+
+/// Counts the heap bytes held by the doc store caches of the searchers of a generation.
+///
+/// It is shared by the generation and the store readers of its searchers, and signals its
+/// `ReleaseSignal`, if any, when a store reader is dropped and once all of them are dropped.
+#[derive(Clone, Default)]
+pub(crate) struct GenerationMemory(Arc<InnerGenerationMemory>);
+
+#[derive(Default)]
+struct InnerGenerationMemory {
+    num_bytes: AtomicUsize,
+    release_signal_opt: Option<Arc<ReleaseSignal>>,
+}
+
+impl GenerationMemory {
+    pub fn with_release_signal(release_signal: Arc<ReleaseSignal>) -> GenerationMemory {
+        GenerationMemory(Arc::new(InnerGenerationMemory {
+            num_bytes: AtomicUsize::new(0),
+            release_signal_opt: Some(release_signal),
+        }))
+    }
+
+    pub fn num_bytes(&self) -> ByteCount {
+        self.0.num_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn add(&self, num_bytes: usize) {
+        self.0.num_bytes.fetch_add(num_bytes, Ordering::Relaxed);
+    }
+
+    pub fn sub(&self, num_bytes: usize) {
+        self.0.num_bytes.fetch_sub(num_bytes, Ordering::Relaxed);
+    }
+
+    /// Releases the bytes of a dropped cache, and wakes up the threads waiting for memory.
+    pub fn release(&self, num_bytes: usize) {
+        self.sub(num_bytes);
+        self.0.notify_release();
+    }
+}
+
+impl InnerGenerationMemory {
+    fn notify_release(&self) {
+        if let Some(release_signal) = &self.release_signal_opt {
+            release_signal.notify();
+        }
+    }
+}
+
+impl Drop for InnerGenerationMemory {
+    fn drop(&mut self) {
+        self.notify_release();
+    }
+}
+
+// The memory counter does not take part in the identity of a `SearcherGeneration`.
+impl fmt::Debug for GenerationMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GenerationMemory({})", self.num_bytes())
+    }
+}
+
+impl PartialEq for GenerationMemory {
+    fn eq(&self, _other: &GenerationMemory) -> bool {
+        true
+    }
+}
+
+impl Eq for GenerationMemory {}
+
+impl PartialOrd for GenerationMemory {
+    fn partial_cmp(&self, other: &GenerationMemory) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GenerationMemory {
+    fn cmp(&self, _other: &GenerationMemory) -> cmp::Ordering {
+        cmp::Ordering::Equal
+    }
+}
+
+impl hash::Hash for GenerationMemory {
+    fn hash<H: hash::Hasher>(&self, _state: &mut H) {}
+}
+
+/// Wakes up the threads waiting for the memory of a searcher generation to be released.
+#[derive(Default)]
+pub(crate) struct ReleaseSignal {
+    num_releases: Mutex<u64>,
+    released: Condvar,
+}
+
+impl ReleaseSignal {
+    fn notify(&self) {
+        *self.lock() += 1;
+        self.released.notify_all();
+    }
+
+    /// Returns the number of releases so far.
+    pub fn num_releases(&self) -> u64 {
+        *self.lock()
+    }
+
+    /// Waits until more than `num_releases` releases happened, or for `timeout`.
+    pub fn wait_for_release(&self, num_releases: u64, timeout: Duration) {
+        let _ = self
+            .released
+            .wait_timeout_while(self.lock(), timeout, |current_num_releases| {
+                *current_num_releases <= num_releases
+            })
+            .expect("Release signal lock poisoned. This should never happen.");
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, u64> {
+        self.num_releases
+            .lock()
+            .expect("Release signal lock poisoned. This should never happen.")
+    }
+}
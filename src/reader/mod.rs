@@ -1,20 +1,34 @@
+mod memory_usage;
 mod pool;
+mod results_cache;
 mod warming;
 
 use std::convert::TryInto;
-use std::io;
 use std::sync::atomic::AtomicU64;
-use std::sync::{atomic, Arc, Weak};
+use std::sync::{atomic, Arc, Condvar, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+use std::{fmt, io};
 
 pub use warming::Warmer;
 
+pub(crate) use self::memory_usage::GenerationMemory;
+pub use self::memory_usage::ReaderMemoryUsage;
+use self::memory_usage::ReleaseSignal;
 pub use self::pool::LeasedItem;
 use self::pool::Pool;
+pub(crate) use self::results_cache::ResultsCache;
 use self::warming::WarmingState;
+use crate::collector::Collector;
 use crate::core::searcher::SearcherGeneration;
+use crate::core::{SlowQuery, SlowQueryHook};
 use crate::directory::{Directory, WatchCallback, WatchHandle, META_LOCK};
-use crate::store::DOCSTORE_CACHE_CAPACITY;
-use crate::{Index, Inventory, Searcher, SegmentReader, TrackedObject};
+use crate::query::Query;
+use crate::store::{CacheStats, DOCSTORE_CACHE_CAPACITY};
+use crate::{
+    Index, Inventory, Opstamp, Searcher, Segment, SegmentReader, TantivyError, TrackedObject,
+};
+
+const DEFAULT_MAX_MEMORY_WAIT: Duration = Duration::from_secs(10);
 
 /// Defines when a new version of the index should be reloaded.
 ///
@@ -34,6 +48,21 @@ pub enum ReloadPolicy {
     OnCommit, // TODO add NEAR_REAL_TIME(target_ms)
 }
 
+/// Defines how fresh the searcher returned by [`IndexReader::searcher_with`] should be.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FreshnessPolicy {
+    /// Any generation will do: the searcher is taken from the pool, as with
+    /// `IndexReader::searcher()`.
+    Any,
+    /// The searcher should reflect at least the commit of the given opstamp.
+    ///
+    /// The reader is reloaded synchronously if the pooled generation is older. If the last
+    /// commit of the index is still older, an error is returned rather than a stale searcher.
+    AtLeast(Opstamp),
+    /// The reader is reloaded synchronously, so that the searcher reflects the last commit.
+    Reload,
+}
+
 /// [IndexReader] builder
 ///
 /// It makes it possible to configure:
@@ -41,6 +70,9 @@ pub enum ReloadPolicy {
 /// - [ReloadPolicy] defining when new index versions are detected
 /// - [Warmer] implementations
 /// - number of warming threads, for parallelizing warming work
+/// - capacity of the results cache
+/// - memory cap of the live searcher generations
+/// - hook reporting slow searches
 #[derive(Clone)]
 pub struct IndexReaderBuilder {
     num_searchers: usize,
@@ -49,6 +81,10 @@ pub struct IndexReaderBuilder {
     warmers: Vec<Weak<dyn Warmer>>,
     num_warming_threads: usize,
     doc_store_cache_size: usize,
+    results_cache_capacity: usize,
+    max_memory_bytes: Option<usize>,
+    max_memory_wait: Duration,
+    slow_query_hook_opt: Option<SlowQueryHook>,
 }
 
 impl IndexReaderBuilder {
@@ -61,6 +97,10 @@ impl IndexReaderBuilder {
             warmers: Vec::new(),
             num_warming_threads: 1,
             doc_store_cache_size: DOCSTORE_CACHE_CAPACITY,
+            results_cache_capacity: 0,
+            max_memory_bytes: None,
+            max_memory_wait: DEFAULT_MAX_MEMORY_WAIT,
+            slow_query_hook_opt: None,
         }
     }
 
@@ -84,6 +124,20 @@ impl IndexReaderBuilder {
             warming_state,
             searcher_generation_counter: Default::default(),
             searcher_generation_inventory,
+            segment_readers: RwLock::new(Vec::new()),
+            loaded_opstamp: Mutex::new(0),
+            opstamp_loaded: Condvar::new(),
+            results_cache_opt: if self.results_cache_capacity > 0 {
+                Some(Arc::new(ResultsCache::with_capacity(
+                    self.results_cache_capacity,
+                )))
+            } else {
+                None
+            },
+            max_memory_bytes: self.max_memory_bytes,
+            max_memory_wait: self.max_memory_wait,
+            generation_release_signal: Arc::default(),
+            slow_query_hook_opt: self.slow_query_hook_opt,
         };
         inner_reader.reload()?;
         let inner_reader_arc = Arc::new(inner_reader);
@@ -133,6 +187,57 @@ impl IndexReaderBuilder {
         self
     }
 
+    /// Sets the number of fruits kept by the results cache of [IndexReader::search_cached].
+    ///
+    /// The results cache also keeps the bitsets of the filters of the
+    /// [`ConstantScoreQuery`](crate::query::ConstantScoreQuery)s run by the searchers of the
+    /// reader, each filter taking one entry.
+    ///
+    /// The results cache is disabled by default, with a capacity of 0.
+    #[must_use]
+    pub fn results_cache_capacity(mut self, results_cache_capacity: usize) -> IndexReaderBuilder {
+        self.results_cache_capacity = results_cache_capacity;
+        self
+    }
+
+    /// Caps the heap memory held by the reader, see [IndexReader::memory_usage].
+    ///
+    /// A reload that would go over the cap waits for the older generations, pinned by the
+    /// searchers still in use, to be released before publishing the new generation. The
+    /// generation currently published is never waited for, and the new generation is published
+    /// anyway after [IndexReaderBuilder::max_memory_wait].
+    ///
+    /// There is no cap by default.
+    #[must_use]
+    pub fn max_memory_bytes(mut self, max_memory_bytes: usize) -> IndexReaderBuilder {
+        self.max_memory_bytes = Some(max_memory_bytes);
+        self
+    }
+
+    /// Sets how long a reload waits for old generations to be released, when
+    /// [IndexReaderBuilder::max_memory_bytes] is exceeded.
+    ///
+    /// Defaults to 10 seconds.
+    #[must_use]
+    pub fn max_memory_wait(mut self, max_memory_wait: Duration) -> IndexReaderBuilder {
+        self.max_memory_wait = max_memory_wait;
+        self
+    }
+
+    /// Sets a callback invoked with the searches taking `threshold` or longer.
+    ///
+    /// The callback receives a [SlowQuery] describing the query, the collector and the time
+    /// spent in each phase of the search, so that slow queries can be logged or sampled
+    /// without wrapping every call to [Searcher::search]. Failed searches are reported with
+    /// their error. It is invoked by the thread running the search, after the search
+    /// completed, and should hence be cheap.
+    #[must_use]
+    pub fn slow_query_hook<F>(mut self, threshold: Duration, callback: F) -> IndexReaderBuilder
+    where F: Fn(&SlowQuery) + Send + Sync + 'static {
+        self.slow_query_hook_opt = Some(SlowQueryHook::new(threshold, Arc::new(callback)));
+        self
+    }
+
     /// Sets the number of [Searcher] to pool.
     ///
     /// See [IndexReader::searcher()].
@@ -176,40 +281,132 @@ struct InnerIndexReader {
     searcher_pool: Pool<Searcher>,
     searcher_generation_counter: Arc<AtomicU64>,
     searcher_generation_inventory: Inventory<SearcherGeneration>,
+    // Segment readers of the last generation, reused by the next generation
+    // for the segments that did not change.
+    segment_readers: RwLock<Vec<SegmentReader>>,
+    // Opstamp of the commit loaded by the last generation.
+    loaded_opstamp: Mutex<Opstamp>,
+    opstamp_loaded: Condvar,
+    results_cache_opt: Option<Arc<ResultsCache>>,
+    max_memory_bytes: Option<usize>,
+    max_memory_wait: Duration,
+    generation_release_signal: Arc<ReleaseSignal>,
+    slow_query_hook_opt: Option<SlowQueryHook>,
 }
 
 impl InnerIndexReader {
-    /// Opens the freshest segments `SegmentReader`.
+    /// Opens the freshest segments `SegmentReader`, along with the opstamp of their commit.
     ///
     /// This function acquires a lot to prevent GC from removing files
     /// as we are opening our index.
-    fn open_segment_readers(&self) -> crate::Result<Vec<SegmentReader>> {
+    fn open_segment_readers(&self) -> crate::Result<(Opstamp, Vec<SegmentReader>)> {
         // Prevents segment files from getting deleted while we are in the process of opening them
         let _meta_lock = self.index.directory().acquire_lock(&META_LOCK)?;
-        let searchable_segments = self.index.searchable_segments()?;
+        let index_meta = self.index.load_metas()?;
+        self.index.refresh_schema(&index_meta.schema);
+        let searchable_segments: Vec<Segment> = index_meta
+            .segments
+            .into_iter()
+            .map(|segment_meta| self.index.segment(segment_meta))
+            .collect();
+        let previous_segment_readers = self
+            .segment_readers
+            .read()
+            .expect("Segment readers lock poisoned. This should never happen.");
         let segment_readers = searchable_segments
             .iter()
-            .map(SegmentReader::open)
+            .map(|segment| {
+                let previous_segment_reader = previous_segment_readers
+                    .iter()
+                    .find(|segment_reader| segment_reader.segment_id() == segment.id());
+                if let Some(previous_segment_reader) = previous_segment_reader {
+                    previous_segment_reader.reopen(segment)
+                } else {
+                    SegmentReader::open(segment)
+                }
+            })
             .collect::<crate::Result<_>>()?;
-        Ok(segment_readers)
+        Ok((index_meta.opstamp, segment_readers))
     }
 
     fn create_new_searcher_generation(
         &self,
         segment_readers: &[SegmentReader],
+        opstamp: Opstamp,
     ) -> TrackedObject<SearcherGeneration> {
         let generation_id = self
             .searcher_generation_counter
             .fetch_add(1, atomic::Ordering::Relaxed);
-        let searcher_generation =
-            SearcherGeneration::from_segment_readers(segment_readers, generation_id);
+        let searcher_generation = SearcherGeneration::from_segment_readers(
+            segment_readers,
+            generation_id,
+            opstamp,
+            GenerationMemory::with_release_signal(self.generation_release_signal.clone()),
+        );
         self.searcher_generation_inventory
             .track(searcher_generation)
     }
 
+    fn memory_usage(&self) -> ReaderMemoryUsage {
+        let generations = self.searcher_generation_inventory.list();
+        let results_cache_num_bytes = self
+            .results_cache_opt
+            .as_ref()
+            .map(|results_cache| results_cache.num_bytes())
+            .unwrap_or(0);
+        ReaderMemoryUsage::from_generations(
+            generations.iter().map(|generation| &**generation),
+            results_cache_num_bytes,
+        )
+    }
+
+    /// Waits until the live generations, including `new_generation`, fit in the memory cap, or
+    /// until only the generation currently published is left to release.
+    fn wait_for_memory(&self, new_generation: &SearcherGeneration, max_memory_bytes: usize) {
+        let deadline = Instant::now() + self.max_memory_wait;
+        loop {
+            // Read before checking the memory, so that no release is missed while waiting.
+            let num_releases = self.generation_release_signal.num_releases();
+            // The new generation and the one it replaces, which cannot be released yet.
+            if self.searcher_generation_inventory.list().len() <= 2 {
+                return;
+            }
+            let memory_usage = self.memory_usage();
+            if memory_usage.total <= max_memory_bytes {
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                warn!(
+                    "Publishing generation {} although the reader holds {} bytes, over the cap \
+                     of {} bytes.",
+                    new_generation.generation_id(),
+                    memory_usage.total,
+                    max_memory_bytes
+                );
+                return;
+            }
+            self.generation_release_signal
+                .wait_for_release(num_releases, deadline - now);
+        }
+    }
+
     fn reload(&self) -> crate::Result<()> {
-        let segment_readers = self.open_segment_readers()?;
-        let searcher_generation = self.create_new_searcher_generation(&segment_readers);
+        let _span = enter_span!("reload");
+        let (opstamp, segment_readers) = self.open_segment_readers()?;
+        let _warm_span = enter_span!(
+            "create_searchers",
+            opstamp,
+            num_segments = segment_readers.len(),
+            num_docs = segment_readers
+                .iter()
+                .map(|segment_reader| u64::from(segment_reader.num_docs()))
+                .sum::<u64>(),
+        );
+        let searcher_generation = self.create_new_searcher_generation(&segment_readers, opstamp);
+        if let Some(max_memory_bytes) = self.max_memory_bytes {
+            self.wait_for_memory(&searcher_generation, max_memory_bytes);
+        }
         let schema = self.index.schema();
         let searchers: Vec<Searcher> = std::iter::repeat_with(|| {
             Searcher::new(
@@ -218,6 +415,8 @@ impl InnerIndexReader {
                 segment_readers.clone(),
                 searcher_generation.clone(),
                 self.doc_store_cache_size,
+                self.slow_query_hook_opt.clone(),
+                self.results_cache_opt.clone(),
             )
         })
         .take(self.num_searchers)
@@ -225,9 +424,42 @@ impl InnerIndexReader {
         self.warming_state
             .warm_new_searcher_generation(&searchers[0])?;
         self.searcher_pool.publish_new_generation(searchers);
+        if let Some(results_cache) = &self.results_cache_opt {
+            results_cache.set_generation(searcher_generation.generation_id());
+        }
+        *self
+            .segment_readers
+            .write()
+            .expect("Segment readers lock poisoned. This should never happen.") = segment_readers;
+        *self
+            .loaded_opstamp
+            .lock()
+            .expect("Loaded opstamp lock poisoned. This should never happen.") = opstamp;
+        self.opstamp_loaded.notify_all();
         Ok(())
     }
 
+    fn loaded_opstamp(&self) -> Opstamp {
+        *self
+            .loaded_opstamp
+            .lock()
+            .expect("Loaded opstamp lock poisoned. This should never happen.")
+    }
+
+    fn wait_for(&self, opstamp: Opstamp, timeout: Duration) -> bool {
+        let loaded_opstamp = self
+            .loaded_opstamp
+            .lock()
+            .expect("Loaded opstamp lock poisoned. This should never happen.");
+        let (loaded_opstamp, _) = self
+            .opstamp_loaded
+            .wait_timeout_while(loaded_opstamp, timeout, |loaded_opstamp| {
+                *loaded_opstamp < opstamp
+            })
+            .expect("Loaded opstamp lock poisoned. This should never happen.");
+        *loaded_opstamp >= opstamp
+    }
+
     fn searcher(&self) -> LeasedItem<Searcher> {
         self.searcher_pool.acquire()
     }
@@ -278,4 +510,101 @@ impl IndexReader {
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.inner.searcher()
     }
+
+    /// Waits until the searchers reflect the commit of opstamp `opstamp`, or any later commit,
+    /// giving up after `timeout`.
+    ///
+    /// Returns `true` if the searchers returned from then on reflect that commit,
+    /// which makes it possible to read one's writes after committing them.
+    /// With `ReloadPolicy::Manual`, the searchers only change when
+    /// `IndexReader::reload()` is called, possibly by another thread.
+    pub fn wait_for(&self, opstamp: Opstamp, timeout: Duration) -> bool {
+        self.inner.wait_for(opstamp, timeout)
+    }
+
+    /// Makes sure that the searchers reflect the commit of opstamp `opstamp`, the value
+    /// returned by `IndexWriter::commit`, or any later commit.
+    ///
+    /// The reader is reloaded synchronously, unless it already loaded such a commit. Unlike
+    /// [`IndexReader::wait_for`], this does not depend on the `ReloadPolicy`, so that tests
+    /// and read-your-writes requests can check exactly which writes are visible.
+    ///
+    /// # Errors
+    /// Returns the error of the reload, or an error if the last commit of the index is older
+    /// than `opstamp`, e.g. if the commit of `opstamp` was prepared but not committed.
+    pub fn reload_to(&self, opstamp: Opstamp) -> crate::Result<()> {
+        if self.inner.loaded_opstamp() >= opstamp {
+            return Ok(());
+        }
+        self.inner.reload()?;
+        let loaded_opstamp = self.inner.loaded_opstamp();
+        if loaded_opstamp < opstamp {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The last commit of the index has opstamp {}, which is older than {}.",
+                loaded_opstamp, opstamp
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns a searcher at least as fresh as required by `freshness_policy`.
+    ///
+    /// This lets latency-sensitive requests use the pooled generation, while others
+    /// pay for a reload to see the latest commits.
+    ///
+    /// # Errors
+    /// Returns the error of the reload, if one was needed, or an error if the searcher cannot
+    /// be as fresh as `FreshnessPolicy::AtLeast` requires, see [`IndexReader::reload_to`].
+    pub fn searcher_with(
+        &self,
+        freshness_policy: FreshnessPolicy,
+    ) -> crate::Result<LeasedItem<Searcher>> {
+        match freshness_policy {
+            FreshnessPolicy::Any => {}
+            FreshnessPolicy::AtLeast(opstamp) => self.reload_to(opstamp)?,
+            FreshnessPolicy::Reload => self.inner.reload()?,
+        }
+        Ok(self.inner.searcher())
+    }
+
+    /// Runs a search on a searcher of the current generation, returning the cached fruit if
+    /// the same search was already run on this generation.
+    ///
+    /// Searches are identified by the canonical form of the query, see
+    /// [Query::canonical_form](crate::query::Query::canonical_form), the type of the
+    /// collector and its `Debug` representation, which must identify its parameters,
+    /// e.g. `TopDocs(limit=10, offset=0)` for `TopDocs::with_limit(10)`. The cache is emptied
+    /// whenever a new generation is loaded.
+    ///
+    /// Without a results cache, see [IndexReaderBuilder::results_cache_capacity], the search
+    /// is simply run.
+    pub fn search_cached<C: Collector + fmt::Debug + 'static>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit>
+    where
+        C::Fruit: Clone,
+    {
+        let searcher = self.searcher();
+        match &self.inner.results_cache_opt {
+            Some(results_cache) => results_cache.search(&searcher, query, collector),
+            None => searcher.search(query, collector),
+        }
+    }
+
+    /// Returns the heap memory held by the reader.
+    ///
+    /// Searchers still in use keep their generation alive, see [ReaderMemoryUsage].
+    pub fn memory_usage(&self) -> ReaderMemoryUsage {
+        self.inner.memory_usage()
+    }
+
+    /// Returns the number of entries, hits and misses of the results cache.
+    pub fn results_cache_stats(&self) -> CacheStats {
+        match &self.inner.results_cache_opt {
+            Some(results_cache) => results_cache.stats(),
+            None => CacheStats::default(),
+        }
+    }
 }
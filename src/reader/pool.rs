@@ -48,7 +48,7 @@ impl<T> Queue<T> {
 /// released automatically back into the pool on `Drop`.
 pub struct Pool<T> {
     queue: Arc<Queue<GenerationItem<T>>>,
-    freshest_generation: AtomicUsize,
+    freshest_generation: Arc<AtomicUsize>,
     next_generation: AtomicUsize,
 }
 
@@ -57,7 +57,7 @@ impl<T> Pool<T> {
         let queue = Arc::new(Queue::new());
         Pool {
             queue,
-            freshest_generation: AtomicUsize::default(),
+            freshest_generation: Arc::default(),
             next_generation: AtomicUsize::default(),
         }
     }
@@ -144,6 +144,7 @@ impl<T> Pool<T> {
                 return LeasedItem {
                     gen_item: Some(gen_item),
                     recycle_queue: Arc::clone(&self.queue),
+                    freshest_generation: Arc::clone(&self.freshest_generation),
                 };
             } else {
                 // this searcher is obsolete,
@@ -156,10 +157,11 @@ impl<T> Pool<T> {
 /// A LeasedItem holds an object borrowed from a Pool.
 ///
 /// Upon drop, the object is automatically returned
-/// into the pool.
+/// into the pool, unless a newer generation was published in the meantime.
 pub struct LeasedItem<T> {
     gen_item: Option<GenerationItem<T>>,
     recycle_queue: Arc<Queue<GenerationItem<T>>>,
+    freshest_generation: Arc<AtomicUsize>,
 }
 
 impl<T> Deref for LeasedItem<T> {
@@ -187,7 +189,11 @@ impl<T> DerefMut for LeasedItem<T> {
 impl<T> Drop for LeasedItem<T> {
     fn drop(&mut self) {
         if let Some(gen_item) = self.gen_item.take() {
-            self.recycle_queue.push(gen_item);
+            // An obsolete item is dropped right away rather than when it is next popped,
+            // so that it does not keep its generation alive.
+            if gen_item.generation >= self.freshest_generation.load(Ordering::Acquire) {
+                self.recycle_queue.push(gen_item);
+            }
         }
     }
 }
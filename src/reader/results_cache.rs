@@ -0,0 +1,188 @@
+use std::any::{Any, TypeId};
+use std::sync::Mutex;
+use std::{fmt, mem};
+
+use lru::LruCache;
+
+use crate::collector::Collector;
+use crate::query::Query;
+use crate::store::CacheStats;
+use crate::Searcher;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct CacheKey {
+    query_canonical_form: String,
+    collector_type: TypeId,
+    // The `Debug` representation of the collector, identifying its parameters.
+    collector_form: String,
+}
+
+/// LRU cache of the fruits of the searches run on the current searcher generation.
+///
+/// The cache is emptied whenever a new generation is loaded: the opstamp of the commit is not
+/// enough to identify a generation, as merges renumber the documents without changing it.
+pub(crate) struct ResultsCache {
+    inner: Mutex<InnerResultsCache>,
+}
+
+struct InnerResultsCache {
+    generation_id: u64,
+    fruits: LruCache<CacheKey, CachedFruit>,
+    cache_hits: usize,
+    cache_misses: usize,
+}
+
+struct CachedFruit {
+    fruit: Box<dyn Any + Send>,
+    // The bytes held by the entry: its key, and the inline size of its fruit.
+    num_bytes: usize,
+}
+
+impl ResultsCache {
+    pub fn with_capacity(capacity: usize) -> ResultsCache {
+        ResultsCache {
+            inner: Mutex::new(InnerResultsCache {
+                generation_id: 0,
+                fruits: LruCache::new(capacity),
+                cache_hits: 0,
+                cache_misses: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InnerResultsCache> {
+        self.inner
+            .lock()
+            .expect("Results cache lock poisoned. This should never happen.")
+    }
+
+    /// Discards the fruits of the previous generations.
+    pub fn set_generation(&self, generation_id: u64) {
+        let mut inner = self.lock();
+        if inner.generation_id != generation_id {
+            inner.generation_id = generation_id;
+            inner.fruits.clear();
+        }
+    }
+
+    /// Returns the cached fruit of the search, or runs it and caches its fruit.
+    ///
+    /// Searches run on a searcher of another generation than the current one are not cached.
+    pub fn search<C: Collector + fmt::Debug + 'static>(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit>
+    where
+        C::Fruit: Clone,
+    {
+        let generation_id = searcher.generation().generation_id();
+        let key = CacheKey {
+            query_canonical_form: query.canonical_form(),
+            collector_type: TypeId::of::<C>(),
+            collector_form: format!("{:?}", collector),
+        };
+        {
+            let mut inner = self.lock();
+            if inner.generation_id == generation_id {
+                let cached_fruit_opt = inner
+                    .fruits
+                    .get(&key)
+                    .and_then(|cached_fruit| cached_fruit.fruit.downcast_ref::<C::Fruit>())
+                    .cloned();
+                if let Some(cached_fruit) = cached_fruit_opt {
+                    inner.cache_hits += 1;
+                    return Ok(cached_fruit);
+                }
+                inner.cache_misses += 1;
+            }
+        }
+        // The lock is not held during the search.
+        let fruit = searcher.search(query, collector)?;
+        let mut inner = self.lock();
+        if inner.generation_id == generation_id {
+            let num_bytes = key.query_canonical_form.len()
+                + key.collector_form.len()
+                + mem::size_of::<C::Fruit>();
+            let cached_fruit = CachedFruit {
+                fruit: Box::new(fruit.clone()),
+                num_bytes,
+            };
+            inner.fruits.put(key, cached_fruit);
+        }
+        Ok(fruit)
+    }
+
+    /// Returns the number of bytes held by the cached fruits.
+    ///
+    /// The heap memory owned by a fruit, e.g. the hits of `TopDocs`, is not accounted for.
+    pub fn num_bytes(&self) -> usize {
+        self.lock()
+            .fruits
+            .iter()
+            .map(|(_, cached_fruit)| mem::size_of::<CacheKey>() + cached_fruit.num_bytes)
+            .sum()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.lock();
+        CacheStats {
+            num_entries: inner.fruits.len(),
+            cache_hits: inner.cache_hits,
+            cache_misses: inner.cache_misses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, STRING};
+    use crate::{Index, ReloadPolicy, Term};
+
+    #[test]
+    fn test_results_cache() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.add_document(doc!(text => "b"))?;
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .results_cache_capacity(2)
+            .try_into()?;
+        let a_query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+
+        assert_eq!(reader.search_cached(&AllQuery, &Count)?, 2);
+        assert_eq!(reader.search_cached(&AllQuery, &Count)?, 2);
+        assert_eq!(reader.search_cached(&a_query, &Count)?, 1);
+        let top_docs = reader.search_cached(&AllQuery, &TopDocs::with_limit(1))?;
+        assert_eq!(top_docs.len(), 1);
+        let stats = reader.results_cache_stats();
+        assert_eq!(stats.num_entries, 2);
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.cache_misses, 3);
+        assert!(reader.memory_usage().results_cache > 0);
+        // The fruit of the collector with different parameters is not returned.
+        let top_docs = reader.search_cached(&AllQuery, &TopDocs::with_limit(2))?;
+        assert_eq!(top_docs.len(), 2);
+
+        // The fruits of the previous generation are discarded.
+        index_writer.add_document(doc!(text => "a"))?;
+        index_writer.commit()?;
+        assert_eq!(reader.search_cached(&a_query, &Count)?, 1);
+        reader.reload()?;
+        assert_eq!(reader.results_cache_stats().num_entries, 0);
+        assert_eq!(reader.search_cached(&a_query, &Count)?, 2);
+
+        let uncached_reader = index.reader()?;
+        assert_eq!(uncached_reader.search_cached(&a_query, &Count)?, 2);
+        assert_eq!(uncached_reader.results_cache_stats().num_entries, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value as JsonValue;
+
+use super::{Document, Facet, FieldType, Schema, Value};
+use crate::time::format_description::well_known::Rfc3339;
+use crate::time::OffsetDateTime;
+use crate::DateTime;
+
+/// Defines how a [`DocumentDeserializer`] handles values that do not exactly match
+/// the schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocParsingMode {
+    /// Unknown fields, null values and values of the wrong type are errors.
+    Strict,
+    /// Unknown fields and null values are skipped, and values are converted when it is
+    /// unambiguous: strings are parsed for numeric and boolean fields, numbers and booleans
+    /// are converted to strings for text fields, and integers are read as unix timestamps,
+    /// in seconds, for date fields.
+    Lenient,
+}
+
+/// Serializes a document as a map from field names to the list of their values.
+///
+/// Dates are serialized as rfc3339 strings and facets as their path. Bytes are serialized
+/// as base64 strings in human readable formats such as json, and as byte arrays
+/// otherwise. This is the representation read back by [`DocumentDeserializer`].
+pub struct SerializableDocument<'a> {
+    schema: &'a Schema,
+    doc: &'a Document,
+}
+
+impl<'a> SerializableDocument<'a> {
+    /// Wraps a document of the schema.
+    pub fn new(schema: &'a Schema, doc: &'a Document) -> SerializableDocument<'a> {
+        SerializableDocument { schema, doc }
+    }
+}
+
+impl<'a> Serialize for SerializableDocument<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let sorted_field_values = self.doc.get_sorted_field_values();
+        let mut map = serializer.serialize_map(Some(sorted_field_values.len()))?;
+        for (field, values) in sorted_field_values {
+            map.serialize_entry(self.schema.get_field_name(field), &values)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes a document of a schema, with any serde format.
+///
+/// Values are converted to the type of their field: dates are parsed from rfc3339 strings,
+/// facets from their path, and bytes from base64 strings or byte arrays. Text fields accept
+/// pre-tokenized strings. A field may be given a single value or a list of values.
+///
+/// ```rust
+/// use serde::de::DeserializeSeed;
+/// use tantivy::schema::{DocParsingMode, DocumentDeserializer, Schema, SerializableDocument, STORED, TEXT};
+///
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT | STORED);
+/// let payload = schema_builder.add_bytes_field("payload", STORED);
+/// let schema = schema_builder.build();
+///
+/// let mut deserializer = serde_json::Deserializer::from_str(r#"{"title": "Frankenstein", "payload": "AQID"}"#);
+/// let doc = DocumentDeserializer::new(&schema, DocParsingMode::Strict)
+///     .deserialize(&mut deserializer)
+///     .unwrap();
+/// assert_eq!(doc.get_first(payload).unwrap().as_bytes(), Some(&[1u8, 2, 3][..]));
+///
+/// let doc_json = serde_json::to_string(&SerializableDocument::new(&schema, &doc)).unwrap();
+/// assert_eq!(doc_json, r#"{"title":["Frankenstein"],"payload":["AQID"]}"#);
+/// ```
+#[derive(Clone, Copy)]
+pub struct DocumentDeserializer<'a> {
+    schema: &'a Schema,
+    mode: DocParsingMode,
+}
+
+impl<'a> DocumentDeserializer<'a> {
+    /// Creates a deserializer of documents of the schema.
+    pub fn new(schema: &'a Schema, mode: DocParsingMode) -> DocumentDeserializer<'a> {
+        DocumentDeserializer { schema, mode }
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for DocumentDeserializer<'a> {
+    type Value = Document;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Document, D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for DocumentDeserializer<'a> {
+    type Value = Document;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a map from field names to values")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Document, A::Error>
+    where A: MapAccess<'de> {
+        let mut doc = Document::default();
+        while let Some(field_name) = map.next_key::<String>()? {
+            let field = match self.schema.get_field(&field_name) {
+                Some(field) => field,
+                None if self.mode == DocParsingMode::Lenient => {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+                None => {
+                    return Err(de::Error::custom(format!(
+                        "Unknown field {:?}.",
+                        field_name
+                    )));
+                }
+            };
+            let raw_values = match map.next_value::<RawValue>()? {
+                RawValue::List(raw_values) => raw_values,
+                raw_value => vec![raw_value],
+            };
+            let field_type = self.schema.get_field_entry(field).field_type();
+            for raw_value in raw_values {
+                let value = convert_raw_value(raw_value, field_type, self.mode).map_err(|err| {
+                    de::Error::custom(format!(
+                        "The field {:?} could not be parsed: {}",
+                        field_name, err
+                    ))
+                })?;
+                if let Some(value) = value {
+                    doc.add_field_value(field, value);
+                }
+            }
+        }
+        Ok(doc)
+    }
+}
+
+/// Value as given by the serde format, before it is converted to the type of its field.
+///
+/// Objects are kept as json objects, as they hold the values of json fields and pre-tokenized
+/// strings.
+#[derive(Debug)]
+enum RawValue {
+    Null,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Object(serde_json::Map<String, JsonValue>),
+    List(Vec<RawValue>),
+}
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D>(deserializer: D) -> Result<RawValue, D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_any(RawValueVisitor)
+    }
+}
+
+struct RawValueVisitor;
+
+impl<'de> Visitor<'de> for RawValueVisitor {
+    type Value = RawValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a field value")
+    }
+
+    fn visit_bool<E: de::Error>(self, val: bool) -> Result<RawValue, E> {
+        Ok(RawValue::Bool(val))
+    }
+
+    fn visit_i64<E: de::Error>(self, val: i64) -> Result<RawValue, E> {
+        Ok(RawValue::I64(val))
+    }
+
+    fn visit_u64<E: de::Error>(self, val: u64) -> Result<RawValue, E> {
+        Ok(RawValue::U64(val))
+    }
+
+    fn visit_f64<E: de::Error>(self, val: f64) -> Result<RawValue, E> {
+        Ok(RawValue::F64(val))
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> Result<RawValue, E> {
+        Ok(RawValue::Str(val.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, val: String) -> Result<RawValue, E> {
+        Ok(RawValue::Str(val))
+    }
+
+    fn visit_bytes<E: de::Error>(self, val: &[u8]) -> Result<RawValue, E> {
+        Ok(RawValue::Bytes(val.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, val: Vec<u8>) -> Result<RawValue, E> {
+        Ok(RawValue::Bytes(val))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<RawValue, E> {
+        Ok(RawValue::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<RawValue, E> {
+        Ok(RawValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<RawValue, D::Error>
+    where D: Deserializer<'de> {
+        RawValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<RawValue, A::Error>
+    where A: SeqAccess<'de> {
+        let mut raw_values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(raw_value) = seq.next_element()? {
+            raw_values.push(raw_value);
+        }
+        Ok(RawValue::List(raw_values))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<RawValue, A::Error>
+    where A: MapAccess<'de> {
+        let json_map = serde_json::Map::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(RawValue::Object(json_map))
+    }
+}
+
+fn convert_raw_value(
+    raw_value: RawValue,
+    field_type: &FieldType,
+    mode: DocParsingMode,
+) -> Result<Option<Value>, String> {
+    let lenient = mode == DocParsingMode::Lenient;
+    let value = match (field_type, raw_value) {
+        (_, RawValue::Null) if lenient => return Ok(None),
+        (_, RawValue::Null) => return Err("Null values are not allowed".to_string()),
+        (_, RawValue::List(_)) => {
+            return Err("Nested lists of values are not supported".to_string())
+        }
+        (FieldType::U64(_), RawValue::U64(val)) => Value::U64(val),
+        (FieldType::U64(_), RawValue::I64(val)) => {
+            Value::U64(u64::try_from(val).map_err(|_| format!("Expected u64, got {}", val))?)
+        }
+        (FieldType::U64(_), RawValue::Str(text)) if lenient => {
+            Value::U64(parse_text(&text, "u64")?)
+        }
+        (FieldType::I64(_), RawValue::I64(val)) => Value::I64(val),
+        (FieldType::I64(_), RawValue::U64(val)) => {
+            Value::I64(i64::try_from(val).map_err(|_| format!("Expected i64, got {}", val))?)
+        }
+        (FieldType::I64(_), RawValue::Str(text)) if lenient => {
+            Value::I64(parse_text(&text, "i64")?)
+        }
+        (FieldType::F64(_), RawValue::F64(val)) => Value::F64(val),
+        (FieldType::F64(_), RawValue::I64(val)) => Value::F64(val as f64),
+        (FieldType::F64(_), RawValue::U64(val)) => Value::F64(val as f64),
+        (FieldType::F64(_), RawValue::Str(text)) if lenient => {
+            Value::F64(parse_text(&text, "f64")?)
+        }
+        (FieldType::Bool(_), RawValue::Bool(val)) => Value::Bool(val),
+        (FieldType::Bool(_), RawValue::Str(text)) if lenient => {
+            Value::Bool(parse_text(&text, "bool")?)
+        }
+        (FieldType::Date(_), RawValue::Str(text)) => {
+            let date_time = OffsetDateTime::parse(&text, &Rfc3339)
+                .map_err(|_| format!("Expected rfc3339 format, got {:?}", text))?;
+            Value::Date(DateTime::from_utc(date_time))
+        }
+        (FieldType::Date(_), RawValue::I64(unix_timestamp)) if lenient => {
+            Value::Date(DateTime::from_unix_timestamp(unix_timestamp))
+        }
+        (FieldType::Date(_), RawValue::U64(unix_timestamp)) if lenient => {
+            let unix_timestamp = i64::try_from(unix_timestamp)
+                .map_err(|_| format!("Expected rfc3339 format, got {}", unix_timestamp))?;
+            Value::Date(DateTime::from_unix_timestamp(unix_timestamp))
+        }
+        (FieldType::Str(_), RawValue::Str(text)) => Value::Str(text),
+        (FieldType::Str(_), RawValue::Object(json_map)) => {
+            let pre_tokenized = serde_json::from_value(JsonValue::Object(json_map))
+                .map_err(|err| format!("Expected a pre-tokenized string: {}", err))?;
+            Value::PreTokStr(pre_tokenized)
+        }
+        (FieldType::Str(_), RawValue::Bool(val)) if lenient => Value::Str(val.to_string()),
+        (FieldType::Str(_), RawValue::I64(val)) if lenient => Value::Str(val.to_string()),
+        (FieldType::Str(_), RawValue::U64(val)) if lenient => Value::Str(val.to_string()),
+        (FieldType::Str(_), RawValue::F64(val)) if lenient => {
+            // Formatted as json numbers are, e.g. `1.0` rather than `1`.
+            let number = serde_json::Number::from_f64(val)
+                .ok_or_else(|| format!("Expected a finite number, got {}", val))?;
+            Value::Str(number.to_string())
+        }
+        (FieldType::Facet(_), RawValue::Str(text)) => Value::Facet(Facet::from(&text)),
+        (FieldType::Bytes(_), RawValue::Bytes(bytes)) => Value::Bytes(bytes),
+        (FieldType::Bytes(_), RawValue::Str(text)) => {
+            let bytes = base64::decode(&text).map_err(|_| format!("Invalid base64: {:?}", text))?;
+            Value::Bytes(bytes)
+        }
+        (FieldType::JsonObject(_), RawValue::Object(json_map)) => Value::JsonObject(json_map),
+        (field_type, raw_value) => {
+            return Err(format!(
+                "Expected {}, got {:?}",
+                field_type.value_type().name(),
+                raw_value
+            ));
+        }
+    };
+    Ok(Some(value))
+}
+
+/// Parses a value of a field from a string, in lenient mode.
+fn parse_text<T: FromStr>(text: &str, expected: &str) -> Result<T, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("Expected {}, got {:?}", expected, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeSeed;
+
+    use super::{DocParsingMode, DocumentDeserializer, SerializableDocument};
+    use crate::schema::{Document, Facet, Schema, Value, FAST, INDEXED, STORED, TEXT};
+    use crate::tokenizer::{PreTokenizedString, Token};
+    use crate::DateTime;
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.add_u64_field("count", INDEXED | FAST);
+        schema_builder.add_f64_field("score", STORED);
+        schema_builder.add_bool_field("is_read", STORED);
+        schema_builder.add_date_field("date", STORED);
+        schema_builder.add_facet_field("category", STORED);
+        schema_builder.add_bytes_field("payload", STORED);
+        schema_builder.build()
+    }
+
+    fn parse_json(
+        schema: &Schema,
+        mode: DocParsingMode,
+        doc_json: &str,
+    ) -> serde_json::Result<Document> {
+        let mut deserializer = serde_json::Deserializer::from_str(doc_json);
+        DocumentDeserializer::new(schema, mode).deserialize(&mut deserializer)
+    }
+
+    fn full_document(schema: &Schema) -> Document {
+        let field = |name: &str| schema.get_field(name).unwrap();
+        let mut doc = Document::default();
+        doc.add_text(field("title"), "A Modest Proposal");
+        doc.add_pre_tokenized_text(
+            field("title"),
+            PreTokenizedString {
+                text: "modest".to_string(),
+                tokens: vec![Token {
+                    offset_from: 0,
+                    offset_to: 6,
+                    position: 0,
+                    text: "modest".to_string(),
+                    position_length: 1,
+                    payload: 0,
+                }],
+            },
+        );
+        doc.add_u64(field("count"), 4);
+        doc.add_f64(field("score"), 0.5);
+        doc.add_bool(field("is_read"), true);
+        doc.add_date(field("date"), DateTime::from_unix_timestamp(1_000_000));
+        doc.add_facet(field("category"), Facet::from("/essay/satire"));
+        doc.add_bytes(field("payload"), vec![0u8, 255u8]);
+        doc
+    }
+
+    #[test]
+    fn test_document_serde_json_roundtrip() {
+        let schema = test_schema();
+        let doc = full_document(&schema);
+        let doc_json = serde_json::to_string(&SerializableDocument::new(&schema, &doc)).unwrap();
+        let doc_serdeser = parse_json(&schema, DocParsingMode::Strict, &doc_json).unwrap();
+        assert_eq!(doc, doc_serdeser);
+        // The json representation is also the one of `Schema::parse_document`.
+        assert_eq!(schema.parse_document(&doc_json).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_document_serde_strict() {
+        let schema = test_schema();
+        assert!(parse_json(&schema, DocParsingMode::Strict, r#"{"unknown": 1}"#).is_err());
+        assert!(parse_json(&schema, DocParsingMode::Strict, r#"{"count": "4"}"#).is_err());
+        assert!(parse_json(&schema, DocParsingMode::Strict, r#"{"count": null}"#).is_err());
+        assert!(parse_json(&schema, DocParsingMode::Strict, r#"{"date": 1000}"#).is_err());
+        assert!(parse_json(&schema, DocParsingMode::Strict, r#"{"count": [[1]]}"#).is_err());
+        let err = parse_json(&schema, DocParsingMode::Strict, r#"{"payload": "%%"}"#).unwrap_err();
+        assert!(err.to_string().contains("payload"));
+    }
+
+    #[test]
+    fn test_document_serde_lenient() {
+        let schema = test_schema();
+        let field = |name: &str| schema.get_field(name).unwrap();
+        let doc = parse_json(
+            &schema,
+            DocParsingMode::Lenient,
+            r#"{"unknown": {"a": 1}, "title": 12, "count": " 4 ", "score": "0.5",
+                "is_read": "true", "date": 1000000, "category": null}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.get_first(field("title")),
+            Some(&Value::Str("12".to_string()))
+        );
+        assert_eq!(doc.get_first(field("count")), Some(&Value::U64(4)));
+        assert_eq!(doc.get_first(field("score")), Some(&Value::F64(0.5)));
+        assert_eq!(doc.get_first(field("is_read")), Some(&Value::Bool(true)));
+        assert_eq!(
+            doc.get_first(field("date")),
+            Some(&Value::Date(DateTime::from_unix_timestamp(1_000_000)))
+        );
+        assert!(doc.get_first(field("category")).is_none());
+        assert!(parse_json(&schema, DocParsingMode::Lenient, r#"{"count": "four"}"#).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_document_serde_cbor_roundtrip() {
+        let schema = test_schema();
+        let doc = full_document(&schema);
+        let doc_cbor = serde_cbor::to_vec(&SerializableDocument::new(&schema, &doc)).unwrap();
+        let mut deserializer = serde_cbor::Deserializer::from_slice(&doc_cbor);
+        let doc_serdeser = DocumentDeserializer::new(&schema, DocParsingMode::Strict)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(doc, doc_serdeser);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_document_serde_msgpack_roundtrip() {
+        let schema = test_schema();
+        let doc = full_document(&schema);
+        let doc_msgpack =
+            rmp_serde::to_vec_named(&SerializableDocument::new(&schema, &doc)).unwrap();
+        let mut deserializer = rmp_serde::Deserializer::from_read_ref(&doc_msgpack);
+        let doc_serdeser = DocumentDeserializer::new(&schema, DocParsingMode::Strict)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(doc, doc_serdeser);
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{is_valid_field_name, FieldType};
+
+/// Options of the fields created on the fly for the field names matching a pattern.
+///
+/// The pattern contains a single `*` wildcard matching any non-empty sequence of characters,
+/// as in `*_i` or `attr_*`.
+///
+/// See [`SchemaBuilder::add_dynamic_template`](crate::schema::SchemaBuilder::add_dynamic_template).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DynamicTemplate {
+    pattern: String,
+    #[serde(flatten)]
+    field_type: FieldType,
+}
+
+impl DynamicTemplate {
+    /// Creates a new dynamic template.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern does not contain exactly one `*`, or if the fields of the template
+    /// are fast: all of the segments have to contain the fast fields of the schema.
+    pub fn new(pattern: &str, field_type: FieldType) -> DynamicTemplate {
+        assert_eq!(
+            pattern.matches('*').count(),
+            1,
+            "The pattern {:?} should contain exactly one '*'.",
+            pattern
+        );
+        assert!(
+            !field_type.is_fast(),
+            "The fields of the dynamic template {:?} cannot be fast fields.",
+            pattern
+        );
+        DynamicTemplate {
+            pattern: pattern.to_string(),
+            field_type,
+        }
+    }
+
+    /// Returns the pattern of the template.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the type of the fields created from the template.
+    pub fn field_type(&self) -> &FieldType {
+        &self.field_type
+    }
+
+    /// Returns true if the fields named `field_name` should be created from the template.
+    pub fn matches(&self, field_name: &str) -> bool {
+        let (prefix, suffix) = self
+            .pattern
+            .split_once('*')
+            .expect("The pattern contains a '*'.");
+        field_name.len() > prefix.len() + suffix.len()
+            && field_name.starts_with(prefix)
+            && field_name.ends_with(suffix)
+            && is_valid_field_name(field_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynamicTemplate;
+    use crate::schema::{FieldType, NumericOptions, INDEXED};
+
+    #[test]
+    fn test_dynamic_template_matches() {
+        let template = DynamicTemplate::new("*_i", FieldType::I64(NumericOptions::from(INDEXED)));
+        assert!(template.matches("count_i"));
+        assert!(!template.matches("_i"));
+        assert!(!template.matches("count_u"));
+        assert!(!template.matches("-count_i"));
+        let template =
+            DynamicTemplate::new("attr_*", FieldType::I64(NumericOptions::from(INDEXED)));
+        assert!(template.matches("attr_color"));
+        assert!(!template.matches("attr_"));
+        assert!(!template.matches("color"));
+    }
+}
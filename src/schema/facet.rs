@@ -168,6 +168,37 @@ impl Facet {
     pub fn to_path_string(&self) -> String {
         format!("{}", self)
     }
+
+    /// Returns the number of steps of the facet path, 0 for the root facet.
+    pub fn depth(&self) -> usize {
+        if self.is_root() {
+            0
+        } else {
+            self.0.matches(FACET_SEP_CHAR).count() + 1
+        }
+    }
+
+    /// Returns the ancestor of the facet at depth `max_depth`,
+    /// or the facet itself if it is not deeper than that.
+    pub fn truncated(&self, max_depth: usize) -> Facet {
+        if max_depth == 0 {
+            return Facet::root();
+        }
+        match self.0.match_indices(FACET_SEP_CHAR).nth(max_depth - 1) {
+            Some((offset, _)) => Facet(self.0[..offset].to_string()),
+            None => self.clone(),
+        }
+    }
+
+    /// Returns the facet without whitespaces around its steps, and without empty steps.
+    pub fn normalized(&self) -> Facet {
+        Facet::from_path(
+            self.to_path()
+                .into_iter()
+                .map(str::trim)
+                .filter(|step| !step.is_empty()),
+        )
+    }
 }
 
 impl Borrow<str> for Facet {
@@ -249,6 +280,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_depth_truncated_normalized() {
+        assert_eq!(Facet::root().depth(), 0);
+        assert_eq!(Facet::from("/a").depth(), 1);
+        let facet = Facet::from("/a/b/c");
+        assert_eq!(facet.depth(), 3);
+        assert_eq!(facet.truncated(0), Facet::root());
+        assert_eq!(facet.truncated(2), Facet::from("/a/b"));
+        assert_eq!(facet.truncated(3), facet);
+        assert_eq!(facet.truncated(4), facet);
+        assert_eq!(Facet::from("/ a //b/").normalized(), Facet::from("/a/b"));
+        assert_eq!(Facet::from("/ / ").normalized(), Facet::root());
+    }
+
     #[test]
     fn test_facet_display() {
         {
@@ -4,12 +4,27 @@ use serde::{Deserialize, Serialize};
 
 use crate::schema::flags::{IndexedFlag, SchemaFlagList, StoredFlag};
 
+fn is_false(val: &bool) -> bool {
+    !val
+}
+
 /// Define how a facet field should be handled by tantivy.
 ///
 /// Note that a Facet is always indexed and stored as a fastfield.
+///
+/// The limits and the normalization are applied by the `IndexWriter` to the facets of the
+/// documents it is given, before they are indexed and stored.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct FacetOptions {
     stored: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_values: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    normalized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count_field: Option<String>,
 }
 
 impl FacetOptions {
@@ -18,6 +33,72 @@ impl FacetOptions {
         self.stored
     }
 
+    /// Returns the maximum depth of the facets of the field.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Truncates the facets deeper than `max_depth` to their ancestor of depth `max_depth`.
+    ///
+    /// For instance, with a maximum depth of 2, `/electronics/phones/android` is indexed as
+    /// `/electronics/phones`.
+    #[must_use]
+    pub fn set_max_depth(mut self, max_depth: usize) -> FacetOptions {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns the maximum number of facets of the field per document.
+    pub fn max_values(&self) -> Option<usize> {
+        self.max_values
+    }
+
+    /// Only keeps the first `max_values` distinct facets of each document.
+    #[must_use]
+    pub fn set_max_values(mut self, max_values: usize) -> FacetOptions {
+        self.max_values = Some(max_values);
+        self
+    }
+
+    /// Returns true if the facets of the field are normalized.
+    pub fn is_normalized(&self) -> bool {
+        self.normalized
+    }
+
+    /// Normalizes the facets, removing the whitespaces around their steps and their empty steps,
+    /// so that `/electronics// phones /` is indexed as `/electronics/phones`.
+    #[must_use]
+    pub fn set_normalized(mut self) -> FacetOptions {
+        self.normalized = true;
+        self
+    }
+
+    /// Returns the name of the field the number of facets of each document is set in.
+    pub fn count_field(&self) -> Option<&str> {
+        self.count_field.as_deref()
+    }
+
+    /// Sets the number of facets of each document, once the limits are applied, as the value
+    /// of the u64 field `count_field_name`, e.g. to search the documents with more than
+    /// 3 categories with a range query on it.
+    ///
+    /// Creating an index returns a `SchemaError` if the schema has no u64 fast field named
+    /// `count_field_name`.
+    #[must_use]
+    pub fn set_count_field(mut self, count_field_name: &str) -> FacetOptions {
+        self.count_field = Some(count_field_name.to_string());
+        self
+    }
+
+    /// Returns true if the facets of the field are transformed before being indexed,
+    /// or counted.
+    pub(crate) fn needs_preparation(&self) -> bool {
+        self.max_depth.is_some()
+            || self.max_values.is_some()
+            || self.normalized
+            || self.count_field.is_some()
+    }
+
     /// Set the field as stored.
     ///
     /// Only the fields that are set as *stored* are
@@ -37,7 +118,10 @@ impl From<()> for FacetOptions {
 
 impl From<StoredFlag> for FacetOptions {
     fn from(_: StoredFlag) -> Self {
-        FacetOptions { stored: true }
+        FacetOptions {
+            stored: true,
+            ..FacetOptions::default()
+        }
     }
 }
 
@@ -48,6 +132,7 @@ impl<T: Into<FacetOptions>> BitOr<T> for FacetOptions {
         let other = other.into();
         FacetOptions {
             stored: self.stored | other.stored,
+            ..self
         }
     }
 }
@@ -65,7 +150,7 @@ where
 
 impl From<IndexedFlag> for FacetOptions {
     fn from(_: IndexedFlag) -> Self {
-        FacetOptions { stored: false }
+        FacetOptions::default()
     }
 }
 
@@ -0,0 +1,169 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::schema::Value;
+
+/// Rules the values of a field have to follow for a document to be added.
+///
+/// - `min` and `max` bound the values of numeric fields,
+/// - `max_len` bounds the number of characters of text values, and the number of bytes of bytes
+///   values,
+/// - `pattern` is a regular expression text values have to match entirely.
+///
+/// See [`SchemaBuilder::set_constraints`](crate::schema::SchemaBuilder::set_constraints).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldConstraints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_len: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+}
+
+impl FieldConstraints {
+    /// Requires numeric values to be greater or equal to `min`.
+    #[must_use]
+    pub fn set_min(mut self, min: f64) -> FieldConstraints {
+        self.min = Some(min);
+        self
+    }
+
+    /// Requires numeric values to be lower or equal to `max`.
+    #[must_use]
+    pub fn set_max(mut self, max: f64) -> FieldConstraints {
+        self.max = Some(max);
+        self
+    }
+
+    /// Requires text values to have at most `max_len` characters,
+    /// and bytes values to have at most `max_len` bytes.
+    #[must_use]
+    pub fn set_max_len(mut self, max_len: usize) -> FieldConstraints {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Requires text values to entirely match the regular expression `pattern`.
+    #[must_use]
+    pub fn set_pattern(mut self, pattern: &str) -> FieldConstraints {
+        self.pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// Returns true if the constraints do not exclude any value.
+    pub fn is_empty(&self) -> bool {
+        self == &FieldConstraints::default()
+    }
+}
+
+/// Violation of the [`FieldConstraints`] of a field by a document.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ValidationError {
+    /// A numeric value is out of the range of the field.
+    #[error("The value {value} of the field {field:?} is out of the range [{min:?}, {max:?}].")]
+    OutOfRange {
+        /// Name of the field.
+        field: String,
+        /// The value out of range.
+        value: f64,
+        /// Minimum value of the field.
+        min: Option<f64>,
+        /// Maximum value of the field.
+        max: Option<f64>,
+    },
+    /// A text or bytes value is too long.
+    #[error("A value of the field {field:?} has length {len}, exceeding the maximum {max_len}.")]
+    TooLong {
+        /// Name of the field.
+        field: String,
+        /// Length of the value.
+        len: usize,
+        /// Maximum length of the values of the field.
+        max_len: usize,
+    },
+    /// A text value does not match the pattern of the field.
+    #[error("The value {value:?} of the field {field:?} does not match the pattern {pattern:?}.")]
+    PatternMismatch {
+        /// Name of the field.
+        field: String,
+        /// The value not matching the pattern.
+        value: String,
+        /// Pattern of the field.
+        pattern: String,
+    },
+}
+
+/// `FieldConstraints` along with their compiled pattern.
+#[derive(Debug)]
+pub(crate) struct FieldValidator {
+    constraints: FieldConstraints,
+    regex_opt: Option<Regex>,
+}
+
+impl FieldValidator {
+    /// Compiles the constraints, returning an error if their pattern is invalid.
+    pub fn new(constraints: &FieldConstraints) -> Result<FieldValidator, regex::Error> {
+        let regex_opt = constraints
+            .pattern
+            .as_ref()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern)))
+            .transpose()?;
+        Ok(FieldValidator {
+            constraints: constraints.clone(),
+            regex_opt,
+        })
+    }
+
+    pub fn validate(&self, field_name: &str, value: &Value) -> Result<(), ValidationError> {
+        let numeric_value_opt = match value {
+            Value::U64(val) => Some(*val as f64),
+            Value::I64(val) => Some(*val as f64),
+            Value::F64(val) => Some(*val),
+            _ => None,
+        };
+        if let Some(numeric_value) = numeric_value_opt {
+            let below_min = matches!(self.constraints.min, Some(min) if numeric_value < min);
+            let above_max = matches!(self.constraints.max, Some(max) if numeric_value > max);
+            if below_min || above_max {
+                return Err(ValidationError::OutOfRange {
+                    field: field_name.to_string(),
+                    value: numeric_value,
+                    min: self.constraints.min,
+                    max: self.constraints.max,
+                });
+            }
+        }
+        let text_opt = match value {
+            Value::Str(text) => Some(text.as_str()),
+            Value::PreTokStr(pre_tokenized_text) => Some(pre_tokenized_text.text.as_str()),
+            _ => None,
+        };
+        let len_opt = match value {
+            Value::Bytes(bytes) => Some(bytes.len()),
+            _ => text_opt.map(|text| text.chars().count()),
+        };
+        if let (Some(len), Some(max_len)) = (len_opt, self.constraints.max_len) {
+            if len > max_len {
+                return Err(ValidationError::TooLong {
+                    field: field_name.to_string(),
+                    len,
+                    max_len,
+                });
+            }
+        }
+        if let (Some(text), Some(regex)) = (text_opt, &self.regex_opt) {
+            if !regex.is_match(text) {
+                return Err(ValidationError::PatternMismatch {
+                    field: field_name.to_string(),
+                    value: text.to_string(),
+                    pattern: self.constraints.pattern.clone().unwrap_or_default(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
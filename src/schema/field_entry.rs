@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 use crate::schema::bytes_options::BytesOptions;
 use crate::schema::{
-    is_valid_field_name, FacetOptions, FieldType, JsonObjectOptions, NumericOptions, TextOptions,
+    is_valid_field_name, FacetOptions, FieldConstraints, FieldType, JsonObjectOptions,
+    NumericOptions, TextOptions,
 };
 
 /// A `FieldEntry` represents a field and its configuration.
@@ -17,6 +19,21 @@ pub struct FieldEntry {
     name: String,
     #[serde(flatten)]
     field_type: FieldType,
+    /// Names of the fields the values of this field are copied to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    copy_to: Vec<String>,
+    /// Value of the field for the documents without any value for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_value: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "FieldConstraints::is_empty")]
+    constraints: FieldConstraints,
+    /// Deprecated fields are dropped by the merges.
+    #[serde(default, skip_serializing_if = "is_false")]
+    deprecated: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
 }
 
 impl FieldEntry {
@@ -26,6 +43,10 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type,
+            copy_to: Vec::new(),
+            default_value: None,
+            constraints: FieldConstraints::default(),
+            deprecated: false,
         }
     }
 
@@ -84,6 +105,54 @@ impl FieldEntry {
         &self.field_type
     }
 
+    /// Returns the names of the fields the values of this field are copied to.
+    ///
+    /// See [`SchemaBuilder::copy_to`](crate::schema::SchemaBuilder::copy_to).
+    pub fn copy_to(&self) -> &[String] {
+        &self.copy_to
+    }
+
+    /// Returns the value of the field for the documents without any value for it, as json.
+    ///
+    /// See [`SchemaBuilder::set_default_value`](crate::schema::SchemaBuilder::set_default_value).
+    pub fn default_value(&self) -> Option<&JsonValue> {
+        self.default_value.as_ref()
+    }
+
+    pub(crate) fn set_default_value(&mut self, default_value: JsonValue) {
+        self.default_value = Some(default_value);
+    }
+
+    /// Returns the constraints the values of the field have to follow.
+    ///
+    /// See [`SchemaBuilder::set_constraints`](crate::schema::SchemaBuilder::set_constraints).
+    pub fn constraints(&self) -> &FieldConstraints {
+        &self.constraints
+    }
+
+    pub(crate) fn set_constraints(&mut self, constraints: FieldConstraints) {
+        self.constraints = constraints;
+    }
+
+    /// Returns true if the field is deprecated.
+    ///
+    /// The values of a deprecated field are ignored when documents are indexed, and its
+    /// postings, fieldnorms, fast field and stored values are not written to the segments
+    /// resulting from merges.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated
+    }
+
+    pub(crate) fn set_deprecated(&mut self) {
+        self.deprecated = true;
+    }
+
+    pub(crate) fn add_copy_to(&mut self, target_field_name: String) {
+        if !self.copy_to.contains(&target_field_name) {
+            self.copy_to.push(target_field_name);
+        }
+    }
+
     /// Returns true if the field is indexed.
     ///
     /// An indexed field is searchable.
@@ -101,6 +170,18 @@ impl FieldEntry {
         self.field_type.is_fast()
     }
 
+    /// Returns true if the field is the primary key of the schema
+    pub fn is_primary_key(&self) -> bool {
+        match self.field_type {
+            FieldType::U64(ref options)
+            | FieldType::I64(ref options)
+            | FieldType::F64(ref options)
+            | FieldType::Date(ref options)
+            | FieldType::Bool(ref options) => options.is_primary_key(),
+            _ => false,
+        }
+    }
+
     /// Returns true if the field is stored
     pub fn is_stored(&self) -> bool {
         match self.field_type {
@@ -6,12 +6,12 @@ use crate::schema::bytes_options::BytesOptions;
 use crate::schema::facet_options::FacetOptions;
 use crate::schema::{
     Facet, IndexRecordOption, JsonObjectOptions, NumericOptions, TextFieldIndexing, TextOptions,
-    Value,
+    Value, DEFAULT_LENGTH_NORMALIZATION,
 };
 use crate::time::format_description::well_known::Rfc3339;
 use crate::time::OffsetDateTime;
 use crate::tokenizer::PreTokenizedString;
-use crate::DateTime;
+use crate::{DateTime, Score};
 
 /// Possible error that may occur while parsing a field value
 /// At this point the JSON is known to be valid.
@@ -194,6 +194,42 @@ impl FieldType {
         }
     }
 
+    /// Returns true if the field stores the payloads of its tokens alongside their positions.
+    pub(crate) fn has_payloads(&self) -> bool {
+        match self {
+            FieldType::Str(text_options) => text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::has_payloads)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the field stores impacts in place of the term frequencies.
+    pub(crate) fn has_impacts(&self) -> bool {
+        match self {
+            FieldType::Str(text_options) => text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::has_impacts)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Returns the strength of the length normalization of the BM25 scores of the field.
+    pub(crate) fn length_normalization(&self) -> Score {
+        match self {
+            FieldType::Str(text_options) => text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::length_normalization),
+            FieldType::JsonObject(json_object_options) => json_object_options
+                .get_text_indexing_options()
+                .map(TextFieldIndexing::length_normalization),
+            _ => None,
+        }
+        .unwrap_or(DEFAULT_LENGTH_NORMALIZATION)
+    }
+
     /// returns true if the field is fast.
     pub fn is_fast(&self) -> bool {
         match *self {
@@ -478,6 +514,7 @@ mod tests {
                     position: 0,
                     text: String::from("The"),
                     position_length: 1,
+                    payload: 0,
                 },
                 Token {
                     offset_from: 4,
@@ -485,6 +522,7 @@ mod tests {
                     position: 1,
                     text: String::from("Old"),
                     position_length: 1,
+                    payload: 0,
                 },
                 Token {
                     offset_from: 8,
@@ -492,6 +530,7 @@ mod tests {
                     position: 2,
                     text: String::from("Man"),
                     position_length: 1,
+                    payload: 0,
                 },
             ],
         });
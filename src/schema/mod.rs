@@ -107,11 +107,14 @@
 //! ```
 
 mod document;
+mod document_serde;
+mod dynamic_template;
 mod facet;
 mod facet_options;
 mod schema;
 pub(crate) mod term;
 
+mod field_constraints;
 mod field_entry;
 mod field_type;
 mod field_value;
@@ -128,10 +131,14 @@ mod value;
 
 pub use self::bytes_options::BytesOptions;
 pub use self::document::Document;
+pub use self::document_serde::{DocParsingMode, DocumentDeserializer, SerializableDocument};
+pub use self::dynamic_template::DynamicTemplate;
 pub(crate) use self::facet::FACET_SEP_BYTE;
 pub use self::facet::{Facet, FacetParseError};
 pub use self::facet_options::FacetOptions;
 pub use self::field::Field;
+pub(crate) use self::field_constraints::FieldValidator;
+pub use self::field_constraints::{FieldConstraints, ValidationError};
 pub use self::field_entry::FieldEntry;
 pub use self::field_type::{FieldType, Type};
 pub use self::field_value::FieldValue;
@@ -142,8 +149,9 @@ pub use self::named_field_document::NamedFieldDocument;
 pub use self::numeric_options::NumericOptions;
 #[allow(deprecated)]
 pub use self::numeric_options::{Cardinality, IntOptions};
-pub use self::schema::{DocParsingError, Schema, SchemaBuilder};
+pub use self::schema::{DocParsingError, DocumentFormat, Schema, SchemaBuilder};
 pub use self::term::Term;
+pub(crate) use self::text_options::DEFAULT_LENGTH_NORMALIZATION;
 pub use self::text_options::{TextFieldIndexing, TextOptions, STRING, TEXT};
 pub use self::value::Value;
 
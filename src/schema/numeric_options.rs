@@ -30,6 +30,12 @@ pub struct NumericOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     fast: Option<Cardinality>,
     stored: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    primary_key: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
 }
 
 /// For backward compability we add an intermediary to interpret the
@@ -45,6 +51,8 @@ struct NumericOptionsDeser {
     #[serde(default)]
     fast: Option<Cardinality>,
     stored: bool,
+    #[serde(default)]
+    primary_key: bool,
 }
 
 impl From<NumericOptionsDeser> for NumericOptions {
@@ -54,6 +62,7 @@ impl From<NumericOptionsDeser> for NumericOptions {
             fieldnorms: deser.fieldnorms.unwrap_or(deser.indexed),
             fast: deser.fast,
             stored: deser.stored,
+            primary_key: deser.primary_key,
         }
     }
 }
@@ -88,6 +97,11 @@ impl NumericOptions {
         self.fast.is_some()
     }
 
+    /// Returns true iff the field is the primary key of the schema.
+    pub fn is_primary_key(&self) -> bool {
+        self.primary_key
+    }
+
     /// Set the field as stored.
     ///
     /// Only the fields that are set as *stored* are
@@ -132,6 +146,26 @@ impl NumericOptions {
         self
     }
 
+    /// Set the field as the primary key of the schema.
+    ///
+    /// The primary key identifies a document: it makes it possible to look up a document
+    /// from its key with `Searcher::doc_address_for_key`, and the key of a document with
+    /// `Searcher::key_for_doc`, without going through the doc store.
+    ///
+    /// Looking up keys relies on the term dictionary and on the fast field of the field,
+    /// so this also sets the field as indexed and as a single-valued fast field.
+    /// Each segment also stores a bloom filter of its keys, letting lookups skip the segments
+    /// that do not contain the key.
+    /// A schema has at most one primary key: `SchemaBuilder::build` panics if several fields
+    /// are set as primary key.
+    #[must_use]
+    pub fn set_primary_key(mut self) -> NumericOptions {
+        self.primary_key = true;
+        self.indexed = true;
+        self.fast = Some(Cardinality::SingleValue);
+        self
+    }
+
     /// Returns the cardinality of the fastfield.
     ///
     /// If the field has not been declared as a fastfield, then
@@ -154,6 +188,7 @@ impl From<FastFlag> for NumericOptions {
             fieldnorms: false,
             stored: false,
             fast: Some(Cardinality::SingleValue),
+            primary_key: false,
         }
     }
 }
@@ -165,6 +200,7 @@ impl From<StoredFlag> for NumericOptions {
             fieldnorms: false,
             stored: true,
             fast: None,
+            primary_key: false,
         }
     }
 }
@@ -176,6 +212,7 @@ impl From<IndexedFlag> for NumericOptions {
             fieldnorms: true,
             stored: false,
             fast: None,
+            primary_key: false,
         }
     }
 }
@@ -190,6 +227,7 @@ impl<T: Into<NumericOptions>> BitOr<T> for NumericOptions {
             fieldnorms: self.fieldnorms | other.fieldnorms,
             stored: self.stored | other.stored,
             fast: self.fast.or(other.fast),
+            primary_key: self.primary_key | other.primary_key,
         }
     }
 }
@@ -222,7 +260,8 @@ mod tests {
                 indexed: true,
                 fieldnorms: true,
                 fast: None,
-                stored: false
+                stored: false,
+                primary_key: false
             }
         );
     }
@@ -240,7 +279,8 @@ mod tests {
                 indexed: false,
                 fieldnorms: false,
                 fast: None,
-                stored: false
+                stored: false,
+                primary_key: false
             }
         );
     }
@@ -259,7 +299,8 @@ mod tests {
                 indexed: true,
                 fieldnorms: false,
                 fast: None,
-                stored: false
+                stored: false,
+                primary_key: false
             }
         );
     }
@@ -279,8 +320,23 @@ mod tests {
                 indexed: false,
                 fieldnorms: true,
                 fast: None,
-                stored: false
+                stored: false,
+                primary_key: false
             }
         );
     }
+
+    #[test]
+    fn test_int_options_primary_key_serialization() {
+        let int_options = NumericOptions::default().set_primary_key();
+        assert!(int_options.is_indexed());
+        assert!(int_options.is_fast());
+        let json = serde_json::to_string(&int_options).unwrap();
+        assert!(json.contains(r#""primary_key":true"#));
+        let deser: NumericOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(deser, int_options);
+        assert!(!serde_json::to_string(&NumericOptions::default())
+            .unwrap()
+            .contains("primary_key"));
+    }
 }
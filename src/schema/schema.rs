@@ -1,9 +1,11 @@
 use std::collections::{BTreeMap, HashMap};
-use std::fmt;
 use std::sync::Arc;
+use std::{fmt, mem};
 
-use serde::de::{SeqAccess, Visitor};
-use serde::ser::SerializeSeq;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+use serde::de::DeserializeSeed;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{self, Value as JsonValue};
 
@@ -34,6 +36,7 @@ use crate::schema::field_type::ValueParsingError;
 pub struct SchemaBuilder {
     fields: Vec<FieldEntry>,
     fields_map: HashMap<String, Field>,
+    dynamic_templates: Vec<DynamicTemplate>,
 }
 
 impl SchemaBuilder {
@@ -212,24 +215,263 @@ impl SchemaBuilder {
         field
     }
 
+    /// Copies the values of the field `source` to the field `target`.
+    ///
+    /// The values given for `source` are then also indexed, and stored or set as fast field
+    /// values, as configured for `target`, which makes it possible to index the same input
+    /// several ways, e.g. as a raw keyword and as stemmed text.
+    /// The values copied to `target` are not copied again to the targets of `target`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two fields are the same, or if their values are of different types.
+    pub fn copy_to(&mut self, source: Field, target: Field) {
+        assert_ne!(source, target, "A field cannot be copied to itself.");
+        let source_entry = &self.fields[source.field_id() as usize];
+        let target_entry = &self.fields[target.field_id() as usize];
+        assert_eq!(
+            source_entry.field_type().value_type(),
+            target_entry.field_type().value_type(),
+            "The values of {:?} cannot be copied to {:?}, whose values are of a different type.",
+            source_entry.name(),
+            target_entry.name()
+        );
+        let target_field_name = target_entry.name().to_string();
+        self.fields[source.field_id() as usize].add_copy_to(target_field_name);
+    }
+
+    /// Sets the value of the field `field` for the documents added without any value for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value cannot be a value of the field.
+    pub fn set_default_value<T: Into<Value>>(&mut self, field: Field, default_value: T) {
+        let field_entry = &mut self.fields[field.field_id() as usize];
+        let default_value_json = serde_json::to_value(default_value.into())
+            .expect("Serializing a value to json should never fail.");
+        if let Err(err) = field_entry
+            .field_type()
+            .value_from_json(default_value_json.clone())
+        {
+            panic!(
+                "Invalid default value for the field {:?}: {:?}",
+                field_entry.name(),
+                err
+            );
+        }
+        field_entry.set_default_value(default_value_json);
+    }
+
+    /// Sets the constraints the values of the field `field` have to follow.
+    ///
+    /// `IndexWriter::add_document` rejects the documents violating them with
+    /// `TantivyError::InvalidDocument`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern of the constraints is not a valid regular expression.
+    pub fn set_constraints(&mut self, field: Field, constraints: FieldConstraints) {
+        if let Err(err) = FieldValidator::new(&constraints) {
+            panic!("Invalid pattern: {}", err);
+        }
+        self.fields[field.field_id() as usize].set_constraints(constraints);
+    }
+
+    /// Adds a dynamic template: the fields whose name matches `pattern`, such as `*_i`,
+    /// are added to the schema of the index the first time a document parsed with
+    /// `IndexWriter::parse_document` has a value for them.
+    ///
+    /// The templates are tried in the order they were added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern does not contain exactly one `*`, or if the field type is fast.
+    pub fn add_dynamic_template(&mut self, pattern: &str, field_type: FieldType) {
+        self.dynamic_templates
+            .push(DynamicTemplate::new(pattern, field_type));
+    }
+
+    /// Checks that the schema has at most one primary key, and that the default values and the
+    /// constraints of the fields are valid, as the fields of a deserialized schema did not go
+    /// through `set_default_value` and `set_constraints`.
+    fn validate(&self) -> Result<(), String> {
+        let primary_key_names: Vec<&str> = self
+            .fields
+            .iter()
+            .filter(|field_entry| field_entry.is_primary_key())
+            .map(|field_entry| field_entry.name())
+            .collect();
+        if primary_key_names.len() > 1 {
+            return Err(format!(
+                "A schema has at most one primary key, got {:?}.",
+                primary_key_names
+            ));
+        }
+        for field_entry in &self.fields {
+            if field_entry.is_deprecated() {
+                continue;
+            }
+            if let Some(default_value_json) = field_entry.default_value() {
+                if let Err(err) = field_entry
+                    .field_type()
+                    .value_from_json(default_value_json.clone())
+                {
+                    return Err(format!(
+                        "Invalid default value for the field {:?}: {:?}",
+                        field_entry.name(),
+                        err
+                    ));
+                }
+            }
+            match field_entry.field_type() {
+                FieldType::Str(text_options) => {
+                    if let Some(indexing_options) = text_options.get_indexing_options() {
+                        if indexing_options.has_impacts()
+                            && indexing_options.index_option() != IndexRecordOption::WithFreqs
+                        {
+                            return Err(format!(
+                                "The field {:?} stores impacts, which requires the index option \
+                                 WithFreqs, got {:?}.",
+                                field_entry.name(),
+                                indexing_options.index_option()
+                            ));
+                        }
+                    }
+                }
+                FieldType::JsonObject(json_object_options) => {
+                    let has_impacts = json_object_options
+                        .get_text_indexing_options()
+                        .map(TextFieldIndexing::has_impacts)
+                        .unwrap_or(false);
+                    if has_impacts {
+                        return Err(format!(
+                            "The json field {:?} cannot store impacts.",
+                            field_entry.name()
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            if !field_entry.constraints().is_empty() {
+                if let Err(err) = FieldValidator::new(field_entry.constraints()) {
+                    return Err(format!(
+                        "Invalid constraints for the field {:?}: {}",
+                        field_entry.name(),
+                        err
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Finalize the creation of a `Schema`
     /// This will consume your `SchemaBuilder`
+    ///
+    /// # Panics
+    ///
+    /// Panics if several fields are set as primary key, or if a default value or the
+    /// constraints of a field added with `SchemaBuilder::add_field` are invalid.
     pub fn build(self) -> Schema {
+        if let Err(msg) = self.validate() {
+            panic!("{}", msg);
+        }
+        let copy_to_fields = self
+            .fields
+            .iter()
+            .map(|field_entry| {
+                field_entry
+                    .copy_to()
+                    .iter()
+                    .filter_map(|target_field_name| self.fields_map.get(target_field_name))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        let default_values = self
+            .fields
+            .iter()
+            .map(|field_entry| {
+                if field_entry.is_deprecated() {
+                    return None;
+                }
+                let default_value_json = field_entry.default_value()?.clone();
+                let default_value = field_entry
+                    .field_type()
+                    .value_from_json(default_value_json)
+                    .expect("The default values are validated.");
+                Some(default_value)
+            })
+            .collect();
+        let validators = self
+            .fields
+            .iter()
+            .map(|field_entry| {
+                let constraints = field_entry.constraints();
+                if constraints.is_empty() {
+                    return None;
+                }
+                let validator =
+                    FieldValidator::new(constraints).expect("The constraints are validated.");
+                Some(validator)
+            })
+            .collect();
+        let prepared_facet_fields = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter_map(|(field_id, field_entry)| match field_entry.field_type() {
+                FieldType::Facet(facet_options) if facet_options.needs_preparation() => {
+                    let count_field_opt = facet_options
+                        .count_field()
+                        .and_then(|count_field_name| self.fields_map.get(count_field_name))
+                        .filter(|count_field| {
+                            matches!(
+                                self.fields[count_field.field_id() as usize].field_type(),
+                                FieldType::U64(_)
+                            )
+                        })
+                        .cloned();
+                    Some((Field::from_field_id(field_id as u32), count_field_opt))
+                }
+                _ => None,
+            })
+            .collect();
+        let deprecated_fields = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field_entry)| field_entry.is_deprecated())
+            .map(|(field_id, _)| Field::from_field_id(field_id as u32))
+            .collect();
         Schema(Arc::new(InnerSchema {
             fields: self.fields,
             fields_map: self.fields_map,
+            copy_to_fields,
+            default_values,
+            validators,
+            prepared_facet_fields,
+            dynamic_templates: self.dynamic_templates,
+            deprecated_fields,
         }))
     }
 }
 #[derive(Debug)]
 struct InnerSchema {
     fields: Vec<FieldEntry>,
-    fields_map: HashMap<String, Field>, // transient
+    fields_map: HashMap<String, Field>,      // transient
+    copy_to_fields: Vec<Vec<Field>>,         // transient
+    default_values: Vec<Option<Value>>,      // transient
+    validators: Vec<Option<FieldValidator>>, // transient
+    // Facet fields with limits, normalization or a count field, along with their count field.
+    prepared_facet_fields: Vec<(Field, Option<Field>)>, // transient
+    dynamic_templates: Vec<DynamicTemplate>,
+    deprecated_fields: Vec<Field>, // transient
 }
 
 impl PartialEq for InnerSchema {
     fn eq(&self, other: &InnerSchema) -> bool {
-        self.fields == other.fields
+        self.fields == other.fields && self.dynamic_templates == other.dynamic_templates
     }
 }
 
@@ -268,6 +510,67 @@ impl Schema {
         self.get_field_entry(field).name()
     }
 
+    /// Returns the dynamic templates of the schema.
+    pub fn dynamic_templates(&self) -> &[DynamicTemplate] {
+        &self.0.dynamic_templates
+    }
+
+    /// Returns the schema extended with the fields created from the dynamic templates,
+    /// for the names among `field_names` that are not fields of the schema yet.
+    ///
+    /// Returns `None` if no field needs to be created.
+    pub fn with_dynamic_fields<'a>(
+        &self,
+        field_names: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Schema> {
+        let mut schema_builder_opt: Option<SchemaBuilder> = None;
+        for field_name in field_names {
+            if self.get_field(field_name).is_some() {
+                continue;
+            }
+            let template = match self
+                .0
+                .dynamic_templates
+                .iter()
+                .find(|template| template.matches(field_name))
+            {
+                Some(template) => template,
+                None => continue,
+            };
+            let schema_builder = schema_builder_opt.get_or_insert_with(|| SchemaBuilder {
+                fields: self.0.fields.clone(),
+                fields_map: self.0.fields_map.clone(),
+                dynamic_templates: self.0.dynamic_templates.clone(),
+            });
+            if !schema_builder.fields_map.contains_key(field_name) {
+                schema_builder.add_field(FieldEntry::new(
+                    field_name.to_string(),
+                    template.field_type().clone(),
+                ));
+            }
+        }
+        schema_builder_opt.map(SchemaBuilder::build)
+    }
+
+    /// Returns the schema with `field` marked as deprecated.
+    ///
+    /// See [`FieldEntry::is_deprecated`].
+    pub fn with_deprecated_field(&self, field: Field) -> Schema {
+        let mut fields = self.0.fields.clone();
+        fields[field.field_id() as usize].set_deprecated();
+        SchemaBuilder {
+            fields,
+            fields_map: self.0.fields_map.clone(),
+            dynamic_templates: self.0.dynamic_templates.clone(),
+        }
+        .build()
+    }
+
+    /// Returns true if some fields of the schema are deprecated.
+    pub(crate) fn has_deprecated_fields(&self) -> bool {
+        !self.0.deprecated_fields.is_empty()
+    }
+
     /// Returns the number of fields in the schema.
     pub fn num_fields(&self) -> usize {
         self.0.fields.len()
@@ -287,6 +590,157 @@ impl Schema {
         SchemaBuilder::default()
     }
 
+    /// Returns the primary key field of the schema, if any.
+    ///
+    /// See [`NumericOptions::set_primary_key`].
+    pub fn primary_key_field(&self) -> Option<Field> {
+        self.fields()
+            .find(|(_, field_entry)| field_entry.is_primary_key())
+            .map(|(field, _)| field)
+    }
+
+    /// Returns the fields the values of `field` are copied to.
+    ///
+    /// See [`SchemaBuilder::copy_to`].
+    pub fn copy_to_fields(&self, field: Field) -> &[Field] {
+        &self.0.copy_to_fields[field.field_id() as usize]
+    }
+
+    /// Adds the default values of the fields the document has no value for, checks that
+    /// its values follow the constraints of their field, and applies the limits and the
+    /// normalization of the facet fields.
+    ///
+    /// This is called by the `IndexWriter` on each document it is given.
+    pub fn prepare_document(&self, doc: &mut Document) -> Result<(), ValidationError> {
+        if self.has_deprecated_fields() {
+            self.remove_deprecated_fields(doc);
+        }
+        for field_value in doc.field_values() {
+            let field = field_value.field();
+            if let Some(validator) = &self.0.validators[field.field_id() as usize] {
+                validator.validate(self.get_field_name(field), field_value.value())?;
+            }
+        }
+        if !self.0.prepared_facet_fields.is_empty() {
+            self.prepare_facets(doc);
+        }
+        for (field_id, default_value_opt) in self.0.default_values.iter().enumerate() {
+            if let Some(default_value) = default_value_opt {
+                let field = Field::from_field_id(field_id as u32);
+                if doc.get_first(field).is_none() {
+                    doc.add_field_value(field, default_value.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the values of the deprecated fields from the document.
+    pub(crate) fn remove_deprecated_fields(&self, doc: &mut Document) {
+        let field_values: Vec<FieldValue> = mem::take(doc)
+            .into_iter()
+            .filter(|field_value| !self.0.deprecated_fields.contains(&field_value.field()))
+            .collect();
+        *doc = Document::from(field_values);
+    }
+
+    /// Checks that the count field of each facet field is a u64 fast field of the schema.
+    pub(crate) fn check_facet_count_fields(&self) -> crate::Result<()> {
+        for field_entry in &self.0.fields {
+            let count_field_name = match field_entry.field_type() {
+                FieldType::Facet(facet_options) => match facet_options.count_field() {
+                    Some(count_field_name) => count_field_name,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let is_u64_fast_field = |count_field_entry: &FieldEntry| {
+                matches!(count_field_entry.field_type(), FieldType::U64(_))
+                    && count_field_entry.is_fast()
+            };
+            let count_field_entry_opt = self
+                .get_field(count_field_name)
+                .map(|count_field| self.get_field_entry(count_field));
+            if !count_field_entry_opt.into_iter().any(is_u64_fast_field) {
+                return Err(crate::TantivyError::SchemaError(format!(
+                    "The count field {:?} of the facet field {:?} is not a u64 fast field.",
+                    count_field_name,
+                    field_entry.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn prepare_facets(&self, doc: &mut Document) {
+        let mut field_values = Vec::with_capacity(doc.len());
+        let mut facets_per_field: Vec<(Field, Vec<Facet>)> = Vec::new();
+        for field_value in mem::take(doc) {
+            let field = field_value.field();
+            let facet_options = match self.get_field_entry(field).field_type() {
+                FieldType::Facet(facet_options) if facet_options.needs_preparation() => {
+                    facet_options
+                }
+                _ => {
+                    field_values.push(field_value);
+                    continue;
+                }
+            };
+            let mut facet = match field_value.value() {
+                Value::Facet(facet) => facet.clone(),
+                _ => {
+                    field_values.push(field_value);
+                    continue;
+                }
+            };
+            if facet_options.is_normalized() {
+                facet = facet.normalized();
+            }
+            if let Some(max_depth) = facet_options.max_depth() {
+                facet = facet.truncated(max_depth);
+            }
+            let facets = match facets_per_field.iter().position(|(f, _)| *f == field) {
+                Some(pos) => &mut facets_per_field[pos].1,
+                None => {
+                    facets_per_field.push((field, Vec::new()));
+                    &mut facets_per_field.last_mut().unwrap().1
+                }
+            };
+            let is_full = matches!(facet_options.max_values(), Some(max_values) if facets.len() >= max_values);
+            if !is_full && !facets.contains(&facet) {
+                facets.push(facet);
+            }
+        }
+        let mut counts: Vec<(Field, u64)> = Vec::new();
+        for &(facet_field, count_field_opt) in &self.0.prepared_facet_fields {
+            if let Some(count_field) = count_field_opt {
+                let num_facets = facets_per_field
+                    .iter()
+                    .find(|(field, _)| *field == facet_field)
+                    .map_or(0, |(_, facets)| facets.len() as u64);
+                match counts.iter_mut().find(|(field, _)| *field == count_field) {
+                    Some((_, count)) => *count += num_facets,
+                    None => counts.push((count_field, num_facets)),
+                }
+            }
+        }
+        // The counts replace the values given for the count fields.
+        field_values.retain(|field_value| {
+            !counts
+                .iter()
+                .any(|(count_field, _)| *count_field == field_value.field())
+        });
+        for (count_field, count) in counts {
+            field_values.push(FieldValue::new(count_field, Value::U64(count)));
+        }
+        for (field, facets) in facets_per_field {
+            for facet in facets {
+                field_values.push(FieldValue::new(field, Value::Facet(facet)));
+            }
+        }
+        *doc = Document::from(field_values);
+    }
+
     /// Returns the field option associated with a given name.
     pub fn get_field(&self, field_name: &str) -> Option<Field> {
         self.0.fields_map.get(field_name).cloned()
@@ -363,11 +817,179 @@ impl Schema {
         }
         Ok(doc)
     }
+
+    /// Build a document object from a json-object, like `parse_document`,
+    /// but rejecting the documents with fields that are not in the schema instead of
+    /// ignoring their values.
+    pub fn parse_document_strict(&self, doc_json: &str) -> Result<Document, DocParsingError> {
+        let json_obj: serde_json::Map<String, JsonValue> =
+            serde_json::from_str(doc_json).map_err(|_| DocParsingError::invalid_json(doc_json))?;
+        self.json_object_to_doc_strict(json_obj)
+    }
+
+    /// Build a document object from a json-object, like `json_object_to_doc`,
+    /// but rejecting the documents with fields that are not in the schema.
+    pub fn json_object_to_doc_strict(
+        &self,
+        json_obj: serde_json::Map<String, JsonValue>,
+    ) -> Result<Document, DocParsingError> {
+        if let Some(field_name) = json_obj
+            .keys()
+            .find(|field_name| self.get_field(field_name).is_none())
+        {
+            return Err(DocParsingError::UnknownField(field_name.clone()));
+        }
+        self.json_object_to_doc(json_obj)
+    }
+
+    /// Build a document object from a CBOR map.
+    ///
+    /// The values are decoded as with a [`DocumentDeserializer`] in
+    /// [`DocParsingMode::Lenient`] mode: the fields that are not in the schema are ignored,
+    /// and the values of bytes fields are CBOR byte strings or base64 strings.
+    #[cfg(feature = "cbor")]
+    pub fn parse_document_cbor(&self, doc_cbor: &[u8]) -> Result<Document, DocParsingError> {
+        let mut deserializer = serde_cbor::Deserializer::from_slice(doc_cbor);
+        let doc = DocumentDeserializer::new(self, DocParsingMode::Lenient)
+            .deserialize(&mut deserializer)
+            .and_then(|doc| deserializer.end().map(|()| doc))
+            .map_err(|err| DocParsingError::InvalidCbor(err.to_string()))?;
+        Ok(doc)
+    }
+
+    /// Encode the document in CBOR.
+    ///
+    /// Bytes values are encoded as CBOR byte strings. Encoding a document cannot fail.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self, doc: &Document) -> Vec<u8> {
+        serde_cbor::to_vec(&SerializableDocument::new(self, doc))
+            .expect("doc encoding failed. This is a bug")
+    }
+
+    /// Build a document object from a MessagePack map.
+    ///
+    /// The values are decoded as with a [`DocumentDeserializer`] in
+    /// [`DocParsingMode::Lenient`] mode: the fields that are not in the schema are ignored,
+    /// and the values of bytes fields are MessagePack binaries or base64 strings.
+    #[cfg(feature = "msgpack")]
+    pub fn parse_document_msgpack(&self, doc_msgpack: &[u8]) -> Result<Document, DocParsingError> {
+        let mut deserializer = rmp_serde::Deserializer::from_read_ref(doc_msgpack);
+        let doc = DocumentDeserializer::new(self, DocParsingMode::Lenient)
+            .deserialize(&mut deserializer)
+            .map_err(|err| DocParsingError::InvalidMessagePack(err.to_string()))?;
+        Ok(doc)
+    }
+
+    /// Encode the document in MessagePack.
+    ///
+    /// Bytes values are encoded as MessagePack binaries. Encoding a document cannot fail.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self, doc: &Document) -> Vec<u8> {
+        rmp_serde::to_vec_named(&SerializableDocument::new(self, doc))
+            .expect("doc encoding failed. This is a bug")
+    }
+
+    /// Build a document object from a payload in the given format.
+    ///
+    /// Parsing a CBOR or a MessagePack payload fails if the `cbor` or the `msgpack` feature
+    /// flag is not activated.
+    pub fn parse_document_with_format(
+        &self,
+        format: DocumentFormat,
+        payload: &[u8],
+    ) -> Result<Document, DocParsingError> {
+        match format {
+            DocumentFormat::Json => {
+                let doc_json = std::str::from_utf8(payload).map_err(|_| {
+                    DocParsingError::invalid_json(&String::from_utf8_lossy(payload))
+                })?;
+                self.parse_document(doc_json)
+            }
+            DocumentFormat::Cbor => {
+                #[cfg(feature = "cbor")]
+                {
+                    self.parse_document_cbor(payload)
+                }
+                #[cfg(not(feature = "cbor"))]
+                {
+                    Err(DocParsingError::InvalidCbor(
+                        "cbor feature flag not activated".to_string(),
+                    ))
+                }
+            }
+            DocumentFormat::MessagePack => {
+                #[cfg(feature = "msgpack")]
+                {
+                    self.parse_document_msgpack(payload)
+                }
+                #[cfg(not(feature = "msgpack"))]
+                {
+                    Err(DocParsingError::InvalidMessagePack(
+                        "msgpack feature flag not activated".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Encode the document in the given format.
+    ///
+    /// # Panics
+    /// Panics if the format is CBOR or MessagePack and the `cbor` or the `msgpack` feature flag
+    /// is not activated.
+    pub fn to_document_format(&self, doc: &Document, format: DocumentFormat) -> Vec<u8> {
+        match format {
+            DocumentFormat::Json => self.to_json(doc).into_bytes(),
+            DocumentFormat::Cbor => {
+                #[cfg(feature = "cbor")]
+                {
+                    self.to_cbor(doc)
+                }
+                #[cfg(not(feature = "cbor"))]
+                {
+                    panic!("cbor feature flag not activated");
+                }
+            }
+            DocumentFormat::MessagePack => {
+                #[cfg(feature = "msgpack")]
+                {
+                    self.to_msgpack(doc)
+                }
+                #[cfg(not(feature = "msgpack"))]
+                {
+                    panic!("msgpack feature flag not activated");
+                }
+            }
+        }
+    }
+}
+
+/// Encoding of documents, selected per call with
+/// [`Schema::parse_document_with_format`] and [`Schema::to_document_format`].
+///
+/// The binary formats require their feature flag: `cbor` for CBOR, and `msgpack` for
+/// MessagePack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// JSON object, see [`Schema::parse_document`].
+    Json,
+    /// CBOR map, see `Schema::parse_document_cbor`.
+    Cbor,
+    /// MessagePack map, see `Schema::parse_document_msgpack`.
+    MessagePack,
 }
 
 impl Serialize for Schema {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where S: Serializer {
+        // The schemas without dynamic templates keep being serialized as the list of their
+        // fields.
+        if !self.0.dynamic_templates.is_empty() {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("fields", &self.0.fields)?;
+            map.serialize_entry("dynamic_templates", &self.0.dynamic_templates)?;
+            return map.end();
+        }
         let mut seq = serializer.serialize_seq(Some(self.0.fields.len()))?;
         for e in &self.0.fields {
             seq.serialize_element(e)?;
@@ -393,17 +1015,43 @@ impl<'de> Deserialize<'de> for Schema {
                 let mut schema = SchemaBuilder {
                     fields: Vec::with_capacity(seq.size_hint().unwrap_or(0)),
                     fields_map: HashMap::with_capacity(seq.size_hint().unwrap_or(0)),
+                    dynamic_templates: Vec::new(),
                 };
 
                 while let Some(value) = seq.next_element()? {
                     schema.add_field(value);
                 }
+                schema.validate().map_err(de::Error::custom)?;
+                Ok(schema.build())
+            }
 
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where A: MapAccess<'de> {
+                let mut schema = SchemaBuilder::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "fields" => {
+                            for field_entry in map.next_value::<Vec<FieldEntry>>()? {
+                                schema.add_field(field_entry);
+                            }
+                        }
+                        "dynamic_templates" => {
+                            schema.dynamic_templates = map.next_value()?;
+                        }
+                        _ => {
+                            return Err(de::Error::unknown_field(
+                                &key,
+                                &["fields", "dynamic_templates"],
+                            ));
+                        }
+                    }
+                }
+                schema.validate().map_err(de::Error::custom)?;
                 Ok(schema.build())
             }
         }
 
-        deserializer.deserialize_seq(SchemaVisitor)
+        deserializer.deserialize_any(SchemaVisitor)
     }
 }
 
@@ -414,14 +1062,23 @@ pub enum DocParsingError {
     /// The payload given is not valid JSON.
     #[error("The provided string is not valid JSON")]
     InvalidJson(String),
+    /// The payload given is not a valid CBOR map.
+    #[error("The provided payload is not a valid CBOR map: {0}")]
+    InvalidCbor(String),
+    /// The payload given is not a valid MessagePack map.
+    #[error("The provided payload is not a valid MessagePack map: {0}")]
+    InvalidMessagePack(String),
     /// One of the value node could not be parsed.
     #[error("The field '{0:?}' could not be parsed: {1:?}")]
     ValueError(String, ValueParsingError),
+    /// The document has a field that is not in the schema.
+    #[error("The field '{0:?}' is not in the schema")]
+    UnknownField(String),
 }
 
 impl DocParsingError {
     /// Builds a NotJson DocParsingError
-    fn invalid_json(invalid_json: &str) -> Self {
+    pub(crate) fn invalid_json(invalid_json: &str) -> Self {
         let sample_json: String = if invalid_json.len() < 20 {
             invalid_json.to_string()
         } else {
@@ -616,6 +1273,86 @@ mod tests {
         assert_eq!(doc, doc_serdeser);
     }
 
+    #[test]
+    pub fn test_document_with_format_json() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT);
+        let schema = schema_builder.build();
+        let doc = schema
+            .parse_document_with_format(DocumentFormat::Json, br#"{"title": "my title"}"#)
+            .unwrap();
+        let payload = schema.to_document_format(&doc, DocumentFormat::Json);
+        assert_eq!(payload, schema.to_json(&doc).into_bytes());
+        assert_matches!(
+            schema.parse_document_with_format(DocumentFormat::Json, &[0xff, 0xfe]),
+            Err(InvalidJson(_))
+        );
+    }
+
+    #[cfg(any(feature = "cbor", feature = "msgpack"))]
+    fn test_document_binary_format(format: DocumentFormat) {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let payload = schema_builder.add_bytes_field("payload", STORED);
+        let category = schema_builder.add_facet_field("category", FacetOptions::default());
+        let val = schema_builder.add_i64_field("val", STORED);
+        schema_builder.add_date_field("date", STORED);
+        let schema = schema_builder.build();
+        let doc_json = r#"{
+                "title": "my title",
+                "payload": "AQID",
+                "category": "/a/b",
+                "val": [14, -1],
+                "date": "1985-04-12T23:20:50.52Z"
+        }"#;
+        let doc = schema.parse_document(doc_json).unwrap();
+        assert_eq!(doc.get_first(payload), Some(&Value::Bytes(vec![1, 2, 3])));
+
+        let doc_payload = schema.to_document_format(&doc, format);
+        let doc_serdeser = schema
+            .parse_document_with_format(format, &doc_payload)
+            .unwrap();
+        assert_eq!(doc, doc_serdeser);
+        assert_eq!(
+            doc_serdeser.get_first(category),
+            Some(&Value::Facet(Facet::from("/a/b")))
+        );
+        assert_eq!(doc_serdeser.get_all(val).count(), 2);
+        assert_eq!(doc_serdeser.get_first(title), doc.get_first(title));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    pub fn test_document_to_cbor() {
+        test_document_binary_format(DocumentFormat::Cbor);
+        let schema = Schema::builder().build();
+        assert_matches!(
+            schema.parse_document_cbor(b"not cbor"),
+            Err(DocParsingError::InvalidCbor(_))
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    pub fn test_document_to_msgpack() {
+        test_document_binary_format(DocumentFormat::MessagePack);
+        let schema = Schema::builder().build();
+        assert_matches!(
+            schema.parse_document_msgpack(b"not msgpack"),
+            Err(DocParsingError::InvalidMessagePack(_))
+        );
+    }
+
+    #[cfg(not(feature = "msgpack"))]
+    #[test]
+    pub fn test_document_msgpack_without_feature() {
+        let schema = Schema::builder().build();
+        assert_matches!(
+            schema.parse_document_with_format(DocumentFormat::MessagePack, &[0x80]),
+            Err(DocParsingError::InvalidMessagePack(_))
+        );
+    }
+
     #[test]
     pub fn test_document_from_nameddoc() {
         let mut schema_builder = Schema::builder();
@@ -660,6 +1397,22 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    pub fn test_parse_document_strict() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let schema = schema_builder.build();
+        let doc = schema.parse_document_strict(r#"{"title": "a"}"#).unwrap();
+        assert_eq!(doc.get_first(title).unwrap().as_text(), Some("a"));
+        assert_eq!(
+            schema.parse_document_strict(r#"{"title": "a", "author": "b"}"#),
+            Err(DocParsingError::UnknownField("author".to_string()))
+        );
+        assert!(schema
+            .parse_document(r#"{"title": "a", "author": "b"}"#)
+            .is_ok());
+    }
+
     #[test]
     pub fn test_parse_document() {
         let mut schema_builder = Schema::builder();
@@ -805,6 +1558,212 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_schema_copy_to_serialization() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let title_raw = schema_builder.add_text_field("title_raw", STRING);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.copy_to(title, title_raw);
+        schema_builder.copy_to(title, title_raw);
+        let schema = schema_builder.build();
+        assert_eq!(schema.copy_to_fields(title), &[title_raw]);
+        assert!(schema.copy_to_fields(title_raw).is_empty());
+        assert!(schema.copy_to_fields(count).is_empty());
+
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        assert!(schema_json.contains(r#""copy_to":["title_raw"]"#));
+        assert_eq!(schema_json.matches("copy_to").count(), 1);
+        let deserialized_schema: Schema = serde_json::from_str(&schema_json).unwrap();
+        assert_eq!(deserialized_schema, schema);
+        assert_eq!(deserialized_schema.copy_to_fields(title), &[title_raw]);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_schema_copy_to_different_type() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.copy_to(title, count);
+    }
+
+    #[test]
+    pub fn test_schema_default_values_and_constraints() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let status = schema_builder.add_text_field("status", STRING);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.set_default_value(status, "draft");
+        schema_builder.set_default_value(count, 0u64);
+        schema_builder.set_constraints(title, FieldConstraints::default().set_max_len(5));
+        schema_builder.set_constraints(status, FieldConstraints::default().set_pattern("[a-z]+"));
+        schema_builder.set_constraints(
+            count,
+            FieldConstraints::default().set_min(0.0).set_max(10.0),
+        );
+        let schema = schema_builder.build();
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(schema_json.matches("default_value").count(), 2);
+        assert_eq!(schema_json.matches("constraints").count(), 3);
+        let schema: Schema = serde_json::from_str(&schema_json).unwrap();
+
+        let mut doc = doc!(title => "hello");
+        assert!(schema.prepare_document(&mut doc).is_ok());
+        assert_eq!(doc.get_first(status).unwrap().as_text(), Some("draft"));
+        assert_eq!(doc.get_first(count).unwrap().as_u64(), Some(0));
+
+        let mut doc = doc!(title => "hello", count => 3u64);
+        assert!(schema.prepare_document(&mut doc).is_ok());
+        assert_eq!(doc.get_all(count).count(), 1);
+
+        assert_matches!(
+            schema.prepare_document(&mut doc!(title => "hello world")),
+            Err(ValidationError::TooLong {
+                len: 11,
+                max_len: 5,
+                ..
+            })
+        );
+        assert_matches!(
+            schema.prepare_document(&mut doc!(status => "Draft")),
+            Err(ValidationError::PatternMismatch { .. })
+        );
+        assert_matches!(
+            schema.prepare_document(&mut doc!(count => 11u64)),
+            Err(ValidationError::OutOfRange { .. })
+        );
+    }
+
+    #[test]
+    pub fn test_schema_facet_limits() {
+        let mut schema_builder = SchemaBuilder::default();
+        let category = schema_builder.add_facet_field(
+            "category",
+            FacetOptions::default()
+                .set_max_depth(2)
+                .set_max_values(2)
+                .set_normalized()
+                .set_count_field("num_categories"),
+        );
+        let num_categories = schema_builder.add_u64_field("num_categories", INDEXED | FAST);
+        let tag = schema_builder.add_facet_field("tag", INDEXED);
+        let schema = schema_builder.build();
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        assert!(schema_json.contains(r#""max_depth":2"#));
+        let schema: Schema = serde_json::from_str(&schema_json).unwrap();
+
+        let mut doc = Document::default();
+        doc.add_facet(category, "/a/b/c");
+        doc.add_facet(category, "/a/ b /d");
+        doc.add_facet(category, "/e");
+        doc.add_facet(category, "/f");
+        doc.add_facet(tag, "/x/y/z");
+        doc.add_u64(num_categories, 10);
+        schema.prepare_document(&mut doc).unwrap();
+        let categories: Vec<&Value> = doc.get_all(category).collect();
+        assert_eq!(
+            categories,
+            vec![
+                &Value::Facet(Facet::from("/a/b")),
+                &Value::Facet(Facet::from("/e"))
+            ]
+        );
+        assert_eq!(
+            doc.get_first(tag).unwrap(),
+            &Value::Facet(Facet::from("/x/y/z"))
+        );
+        assert_eq!(doc.get_all(num_categories).count(), 1);
+        assert_eq!(doc.get_first(num_categories).unwrap().as_u64(), Some(2));
+
+        let mut doc = Document::default();
+        schema.prepare_document(&mut doc).unwrap();
+        assert_eq!(doc.get_first(num_categories).unwrap().as_u64(), Some(0));
+    }
+
+    #[test]
+    pub fn test_schema_facet_invalid_count_field() {
+        let count_options = FacetOptions::default().set_count_field("num_categories");
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_facet_field("category", count_options.clone());
+        schema_builder.add_u64_field("num_categories", INDEXED);
+        let not_fast_schema = schema_builder.build();
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_facet_field("category", count_options.clone());
+        schema_builder.add_i64_field("num_categories", FAST);
+        let not_u64_schema = schema_builder.build();
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_facet_field("category", count_options);
+        let missing_schema = schema_builder.build();
+        for schema in [not_fast_schema, not_u64_schema, missing_schema] {
+            assert!(matches!(
+                schema.check_facet_count_fields(),
+                Err(crate::TantivyError::SchemaError(_))
+            ));
+            assert!(matches!(
+                crate::Index::builder().schema(schema).create_in_ram(),
+                Err(crate::TantivyError::SchemaError(_))
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_schema_deserialize_invalid_default_value_and_constraints() {
+        let mut schema_builder = SchemaBuilder::default();
+        let status = schema_builder.add_text_field("status", STRING);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.set_default_value(count, 0u64);
+        schema_builder.set_constraints(status, FieldConstraints::default().set_pattern("[a-z]+"));
+        let schema_json = serde_json::to_string(&schema_builder.build()).unwrap();
+        assert!(serde_json::from_str::<Schema>(&schema_json).is_ok());
+
+        let invalid_default_json =
+            schema_json.replace(r#""default_value":0"#, r#""default_value":"zero""#);
+        assert_ne!(invalid_default_json, schema_json);
+        let err = serde_json::from_str::<Schema>(&invalid_default_json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid default value for the field \"count\""));
+
+        let invalid_pattern_json = schema_json.replace("[a-z]+", "[a-z");
+        assert_ne!(invalid_pattern_json, schema_json);
+        let err = serde_json::from_str::<Schema>(&invalid_pattern_json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid constraints for the field \"status\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "A schema has at most one primary key")]
+    pub fn test_schema_several_primary_keys() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_u64_field("id", NumericOptions::default().set_primary_key());
+        schema_builder.add_i64_field("other_id", NumericOptions::default().set_primary_key());
+        schema_builder.build();
+    }
+
+    #[test]
+    #[should_panic(expected = "requires the index option WithFreqs")]
+    pub fn test_schema_impacts_without_freqs() {
+        let mut schema_builder = SchemaBuilder::default();
+        let indexing_options = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+            .set_impacts(true);
+        schema_builder.add_text_field(
+            "splade",
+            TextOptions::default().set_indexing_options(indexing_options),
+        );
+        schema_builder.build();
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_schema_invalid_default_value() {
+        let mut schema_builder = SchemaBuilder::default();
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.set_default_value(count, "zero");
+    }
+
     #[test]
     pub fn test_schema_add_field() {
         let mut schema_builder = SchemaBuilder::default();
@@ -4,7 +4,7 @@ use std::{fmt, str};
 
 use super::Field;
 use crate::fastfield::FastValue;
-use crate::schema::{Facet, Type};
+use crate::schema::{Facet, Type, Value};
 use crate::DateTime;
 
 /// Size (in bytes) of the buffer of a fast value (u64, i64, f64, or date) term.
@@ -90,7 +90,7 @@ impl Term {
         Term::create_bytes_term(Type::Str, field, text.as_bytes())
     }
 
-    fn create_bytes_term(typ: Type, field: Field, bytes: &[u8]) -> Term {
+    pub(crate) fn create_bytes_term(typ: Type, field: Field, bytes: &[u8]) -> Term {
         let mut term = Term(vec![0u8; 5 + bytes.len()]);
         term.set_field(typ, field);
         term.0.extend_from_slice(bytes);
@@ -102,6 +102,25 @@ impl Term {
         Term::create_bytes_term(Type::Bytes, field, bytes)
     }
 
+    /// Builds the term matching a value, as indexed without tokenization.
+    ///
+    /// Returns `None` for pre-tokenized strings and json objects, which do not map
+    /// to a single term.
+    pub fn from_field_value(field: Field, value: &Value) -> Option<Term> {
+        let term = match value {
+            Value::Str(text) => Term::from_field_text(field, text),
+            Value::U64(val) => Term::from_field_u64(field, *val),
+            Value::I64(val) => Term::from_field_i64(field, *val),
+            Value::F64(val) => Term::from_field_f64(field, *val),
+            Value::Bool(val) => Term::from_field_bool(field, *val),
+            Value::Date(val) => Term::from_field_date(field, *val),
+            Value::Facet(facet) => Term::from_facet(field, facet),
+            Value::Bytes(bytes) => Term::from_field_bytes(field, bytes),
+            Value::PreTokStr(_) | Value::JsonObject(_) => return None,
+        };
+        Some(term)
+    }
+
     pub(crate) fn set_field(&mut self, typ: Type, field: Field) {
         self.0.clear();
         self.0
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use super::flags::FastFlag;
 use crate::schema::flags::{SchemaFlagList, StoredFlag};
 use crate::schema::IndexRecordOption;
+use crate::Score;
 
 /// Define how a text field should be handled by tantivy.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
@@ -103,6 +104,11 @@ impl TokenizerName {
 /// - The name of the `Tokenizer` that should be used to process the field.
 /// - Flag indicating, if fieldnorms should be stored (See [fieldnorm](crate::fieldnorm)). Defaults
 ///   to `true`.
+/// - Flag indicating, if token payloads should be stored alongside the positions. Defaults to
+///   `false`.
+/// - Flag indicating, if impacts should be stored in place of the term frequencies. Defaults to
+///   `false`.
+/// - The strength of the length normalization of the BM25 scores. Defaults to `0.75`.
 #[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize)]
 pub struct TextFieldIndexing {
     #[serde(default)]
@@ -111,18 +117,46 @@ pub struct TextFieldIndexing {
     fieldnorms: bool,
     #[serde(default)]
     tokenizer: TokenizerName,
+    #[serde(default, skip_serializing_if = "is_false")]
+    payloads: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    impacts: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length_normalization: Option<LengthNormalization>,
 }
 
+/// Default strength of the length normalization, the `b` parameter of BM25.
+pub(crate) const DEFAULT_LENGTH_NORMALIZATION: Score = 0.75;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+struct LengthNormalization(Score);
+
+impl PartialEq for LengthNormalization {
+    fn eq(&self, other: &LengthNormalization) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for LengthNormalization {}
+
 pub(crate) fn default_fieldnorms() -> bool {
     true
 }
 
+fn is_false(val: &bool) -> bool {
+    !val
+}
+
 impl Default for TextFieldIndexing {
     fn default() -> TextFieldIndexing {
         TextFieldIndexing {
             tokenizer: TokenizerName::default(),
             record: IndexRecordOption::default(),
             fieldnorms: default_fieldnorms(),
+            payloads: false,
+            impacts: false,
+            length_normalization: None,
         }
     }
 }
@@ -154,6 +188,10 @@ impl TextFieldIndexing {
 
     /// Sets which information should be indexed with the tokens.
     ///
+    /// With [`IndexRecordOption::Basic`], the postings of a tokenized field only record
+    /// document ids. This makes them much smaller for fields only used to filter documents,
+    /// such as tags, and the term frequency of every matching document is scored as 1.
+    ///
     /// See [IndexRecordOption](./enum.IndexRecordOption.html) for more detail.
     #[must_use]
     pub fn set_index_option(mut self, index_option: IndexRecordOption) -> TextFieldIndexing {
@@ -167,6 +205,75 @@ impl TextFieldIndexing {
     pub fn index_option(&self) -> IndexRecordOption {
         self.record
     }
+
+    /// Sets whether the payloads of the tokens should be stored.
+    ///
+    /// Payloads are stored alongside the positions, so they are only recorded if the
+    /// index option is [`IndexRecordOption::WithFreqsAndPositions`]. They can be set on
+    /// the tokens of a [`PreTokenizedString`](crate::tokenizer::PreTokenizedString), or by a
+    /// token filter, and read back with
+    /// [`Postings::payloads`](crate::postings::Postings::payloads).
+    #[must_use]
+    pub fn set_payloads(mut self, payloads: bool) -> TextFieldIndexing {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Returns true if and only if token payloads are stored.
+    pub fn has_payloads(&self) -> bool {
+        self.payloads && self.record.has_positions()
+    }
+
+    /// Sets whether impacts should be stored in place of the term frequencies.
+    ///
+    /// The impact of a term in a document is the sum of the payloads of its tokens,
+    /// typically a quantized weight computed by a model for learned sparse retrieval.
+    /// Impacts require the index option [`IndexRecordOption::WithFreqs`], and cannot be
+    /// stored by json fields: building a schema with another combination fails.
+    ///
+    /// The postings then return the impact as their term frequency, and a
+    /// [`TermQuery`](crate::query::TermQuery) on the field scores documents by the impact
+    /// of the term, multiplied by the boost of the query.
+    #[must_use]
+    pub fn set_impacts(mut self, impacts: bool) -> TextFieldIndexing {
+        self.impacts = impacts;
+        self
+    }
+
+    /// Returns true if and only if impacts are stored in place of the term frequencies.
+    pub fn has_impacts(&self) -> bool {
+        self.impacts
+    }
+
+    /// Sets the strength of the length normalization of the BM25 scores, the `b` parameter
+    /// of BM25, between `0` and `1`.
+    ///
+    /// With `0`, the length of the field does not affect the scores, which suits short
+    /// keyword fields such as tags. With `1`, the term frequencies are fully normalized by the
+    /// length of the field relatively to its average length.
+    ///
+    /// The block-max information of the postings is computed with this value, so that it must
+    /// not be changed on an existing index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length normalization is not within `[0, 1]`.
+    #[must_use]
+    pub fn set_length_normalization(mut self, length_normalization: Score) -> TextFieldIndexing {
+        assert!(
+            (0.0..=1.0).contains(&length_normalization),
+            "The length normalization should be within [0, 1]."
+        );
+        self.length_normalization = Some(LengthNormalization(length_normalization));
+        self
+    }
+
+    /// Returns the strength of the length normalization of the BM25 scores.
+    pub fn length_normalization(&self) -> Score {
+        self.length_normalization
+            .map(|length_normalization| length_normalization.0)
+            .unwrap_or(DEFAULT_LENGTH_NORMALIZATION)
+    }
 }
 
 /// The field will be untokenized and indexed.
@@ -175,6 +282,9 @@ pub const STRING: TextOptions = TextOptions {
         tokenizer: TokenizerName::from_static(NO_TOKENIZER_NAME),
         fieldnorms: true,
         record: IndexRecordOption::Basic,
+        payloads: false,
+        impacts: false,
+        length_normalization: None,
     }),
     stored: false,
     fast: false,
@@ -186,6 +296,9 @@ pub const TEXT: TextOptions = TextOptions {
         tokenizer: TokenizerName::from_static(DEFAULT_TOKENIZER_NAME),
         fieldnorms: true,
         record: IndexRecordOption::WithFreqsAndPositions,
+        payloads: false,
+        impacts: false,
+        length_normalization: None,
     }),
     stored: false,
     fast: false,
@@ -48,7 +48,14 @@ impl Serialize for Value {
             Value::Bool(b) => serializer.serialize_bool(b),
             Value::Date(ref date) => time::serde::rfc3339::serialize(&date.into_utc(), serializer),
             Value::Facet(ref facet) => facet.serialize(serializer),
-            Value::Bytes(ref bytes) => serializer.serialize_bytes(bytes),
+            Value::Bytes(ref bytes) => {
+                if serializer.is_human_readable() {
+                    // Same representation as the one expected when parsing json documents.
+                    serializer.serialize_str(&base64::encode(bytes))
+                } else {
+                    serializer.serialize_bytes(bytes)
+                }
+            }
             Value::JsonObject(ref obj) => obj.serialize(serializer),
         }
     }
@@ -12,7 +12,7 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::schema::Field;
-use crate::SegmentComponent;
+use crate::{SegmentComponent, SegmentId};
 
 /// Indicates space usage in bytes
 pub type ByteCount = usize;
@@ -75,6 +75,8 @@ pub struct SegmentSpaceUsage {
     store: StoreSpaceUsage,
 
     deletes: ByteCount,
+    #[serde(default)]
+    bloom_filter: ByteCount,
 
     total: ByteCount,
 }
@@ -90,6 +92,7 @@ impl SegmentSpaceUsage {
         fieldnorms: PerFieldSpaceUsage,
         store: StoreSpaceUsage,
         deletes: ByteCount,
+        bloom_filter: ByteCount,
     ) -> SegmentSpaceUsage {
         let total = termdict.total()
             + postings.total()
@@ -97,7 +100,8 @@ impl SegmentSpaceUsage {
             + fast_fields.total()
             + fieldnorms.total()
             + store.total()
-            + deletes;
+            + deletes
+            + bloom_filter;
         SegmentSpaceUsage {
             num_docs,
             termdict,
@@ -107,6 +111,7 @@ impl SegmentSpaceUsage {
             fieldnorms,
             store,
             deletes,
+            bloom_filter,
             total,
         }
     }
@@ -127,6 +132,7 @@ impl SegmentSpaceUsage {
             SegmentComponent::Store => ComponentSpaceUsage::Store(self.store().clone()),
             SegmentComponent::TempStore => ComponentSpaceUsage::Store(self.store().clone()),
             Delete => Basic(self.deletes()),
+            BloomFilter => Basic(self.bloom_filter()),
         }
     }
 
@@ -170,6 +176,11 @@ impl SegmentSpaceUsage {
         self.deletes
     }
 
+    /// Space usage for the bloom filter of the primary keys
+    pub fn bloom_filter(&self) -> ByteCount {
+        self.bloom_filter
+    }
+
     /// Total space usage in bytes for this segment.
     pub fn total(&self) -> ByteCount {
         self.total
@@ -283,12 +294,142 @@ impl FieldUsage {
     }
 }
 
+/// Represents the size of the files of an index, as reported by its directory.
+///
+/// Unlike [`SearcherSpaceUsage`], which breaks down the space usage of the opened
+/// segments per field, this reports the size of each segment file, including the ones
+/// that are never opened by a searcher.
+/// See [`Index::disk_usage`](../struct.Index.html#method.disk_usage).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexDiskUsage {
+    meta: ByteCount,
+    segments: Vec<SegmentDiskUsage>,
+    total: ByteCount,
+}
+
+impl IndexDiskUsage {
+    pub(crate) fn new(meta: ByteCount) -> IndexDiskUsage {
+        IndexDiskUsage {
+            meta,
+            segments: Vec::new(),
+            total: meta,
+        }
+    }
+
+    pub(crate) fn add_segment(&mut self, segment: SegmentDiskUsage) {
+        self.total += segment.total();
+        self.segments.push(segment);
+    }
+
+    /// Size of the `meta.json` file
+    pub fn meta(&self) -> ByteCount {
+        self.meta
+    }
+
+    /// Per segment disk usage
+    pub fn segments(&self) -> &[SegmentDiskUsage] {
+        &self.segments[..]
+    }
+
+    /// Returns total byte usage of the segment files and of `meta.json`.
+    pub fn total(&self) -> ByteCount {
+        self.total
+    }
+}
+
+/// Represents the size of the files of a segment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentDiskUsage {
+    segment_id: SegmentId,
+    num_docs: u32,
+
+    postings: ByteCount,
+    positions: ByteCount,
+    fast_fields: ByteCount,
+    fieldnorms: ByteCount,
+    termdict: ByteCount,
+    store: ByteCount,
+    deletes: ByteCount,
+    #[serde(default)]
+    bloom_filter: ByteCount,
+}
+
+impl SegmentDiskUsage {
+    pub(crate) fn new(segment_id: SegmentId, num_docs: u32) -> SegmentDiskUsage {
+        SegmentDiskUsage {
+            segment_id,
+            num_docs,
+            postings: 0,
+            positions: 0,
+            fast_fields: 0,
+            fieldnorms: 0,
+            termdict: 0,
+            store: 0,
+            deletes: 0,
+            bloom_filter: 0,
+        }
+    }
+
+    pub(crate) fn set_component(&mut self, component: SegmentComponent, num_bytes: ByteCount) {
+        use self::SegmentComponent::*;
+        match component {
+            Postings => self.postings = num_bytes,
+            Positions => self.positions = num_bytes,
+            FastFields => self.fast_fields = num_bytes,
+            FieldNorms => self.fieldnorms = num_bytes,
+            Terms => self.termdict = num_bytes,
+            Store | TempStore => self.store += num_bytes,
+            Delete => self.deletes = num_bytes,
+            BloomFilter => self.bloom_filter = num_bytes,
+        }
+    }
+
+    /// Size of the file of a given segment component.
+    ///
+    /// The temporary store is accounted for in the store.
+    pub fn component(&self, component: SegmentComponent) -> ByteCount {
+        use self::SegmentComponent::*;
+        match component {
+            Postings => self.postings,
+            Positions => self.positions,
+            FastFields => self.fast_fields,
+            FieldNorms => self.fieldnorms,
+            Terms => self.termdict,
+            Store | TempStore => self.store,
+            Delete => self.deletes,
+            BloomFilter => self.bloom_filter,
+        }
+    }
+
+    /// Id of the segment
+    pub fn segment_id(&self) -> SegmentId {
+        self.segment_id
+    }
+
+    /// Num docs in segment
+    pub fn num_docs(&self) -> u32 {
+        self.num_docs
+    }
+
+    /// Total size of the segment files in bytes
+    pub fn total(&self) -> ByteCount {
+        self.postings
+            + self.positions
+            + self.fast_fields
+            + self.fieldnorms
+            + self.termdict
+            + self.store
+            + self.deletes
+            + self.bloom_filter
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::core::Index;
     use crate::schema::{Field, Schema, FAST, INDEXED, STORED, TEXT};
     use crate::space_usage::{ByteCount, PerFieldSpaceUsage};
-    use crate::Term;
+    use crate::{SegmentComponent, Term};
 
     #[test]
     fn test_empty() {
@@ -477,4 +618,35 @@ mod test {
         assert!(segment_space_usage.deletes() > 0);
         Ok(())
     }
+
+    #[test]
+    fn test_disk_usage() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_u64_field("name", INDEXED | FAST);
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(name => 1u64, text => "hello happy tax payer"))?;
+            index_writer.add_document(doc!(name => 2u64, text => "hello"))?;
+            index_writer.commit()?;
+            index_writer.delete_term(Term::from_field_u64(name, 2u64));
+            index_writer.commit()?;
+        }
+        let disk_usage = index.disk_usage()?;
+        assert!(disk_usage.meta() > 0);
+        assert_eq!(disk_usage.segments().len(), 1);
+        let segment_disk_usage = &disk_usage.segments()[0];
+        assert_eq!(segment_disk_usage.num_docs(), 1);
+        for &component in SegmentComponent::iterator() {
+            assert!(segment_disk_usage.component(component) > 0);
+        }
+        assert_eq!(
+            disk_usage.total(),
+            disk_usage.meta() + segment_disk_usage.total()
+        );
+        let json = serde_json::to_string(&disk_usage)?;
+        assert!(json.contains("fast_fields"));
+        Ok(())
+    }
 }
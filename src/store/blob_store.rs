@@ -0,0 +1,396 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use common::{BinarySerializable, VInt};
+use ownedbytes::OwnedBytes;
+
+use crate::directory::Directory;
+use crate::schema::{Document, Field, FieldValue, Value};
+
+const TEXT_BLOB_CODE: u8 = 0;
+const BYTES_BLOB_CODE: u8 = 1;
+
+/// Marks the references to the blobs of a document, written after its inline values.
+const BLOB_REFS_MARKER: u8 = 0xB1;
+
+/// External storage for the large values of the doc store.
+///
+/// If [`IndexSettings::docstore_blob_threshold`](crate::IndexSettings::docstore_blob_threshold)
+/// is set, the stored text and bytes values larger than the threshold are written to the blob
+/// store of the index (See [`Index::set_blob_store`](crate::Index::set_blob_store)), and the
+/// doc store only keeps their key. This keeps the doc store small, and its blocks fast to
+/// decompress. The values are read back from the blob store when a document is fetched, e.g.
+/// by [`Searcher::doc`](crate::Searcher::doc).
+///
+/// Merges keep the references to the blobs of the merged segments rather than copying the
+/// blobs. The blobs are never deleted by tantivy, even once their documents are deleted.
+///
+/// ```rust
+/// use tantivy::schema::{Schema, STORED};
+/// use tantivy::directory::RamDirectory;
+/// use tantivy::store::DirectoryBlobStore;
+/// use tantivy::{doc, DocAddress, Index, IndexSettings};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let body = schema_builder.add_text_field("body", STORED);
+/// let settings = IndexSettings {
+///     docstore_blob_threshold: Some(1_000),
+///     ..Default::default()
+/// };
+/// let mut index = Index::builder()
+///     .schema(schema_builder.build())
+///     .settings(settings)
+///     .create_in_ram()?;
+/// index.set_blob_store(DirectoryBlobStore::new(RamDirectory::create()));
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(body => "a".repeat(10_000)))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let doc = searcher.doc(DocAddress::new(0, 0))?;
+/// assert_eq!(doc.get_first(body).and_then(|value| value.as_text()).map(str::len), Some(10_000));
+/// # Ok(())
+/// # }
+/// ```
+pub trait BlobStore: Send + Sync + 'static {
+    /// Stores the `blob` under `key`.
+    ///
+    /// The keys are never reused.
+    fn put_blob(&self, key: &str, blob: &[u8]) -> io::Result<()>;
+
+    /// Returns the blob stored under `key`.
+    fn get_blob(&self, key: &str) -> io::Result<OwnedBytes>;
+}
+
+/// `BlobStore` writing each blob to a file of a `Directory`, named after its key.
+///
+/// With an [`MmapDirectory`](crate::directory::MmapDirectory), the blobs are read without
+/// being copied.
+pub struct DirectoryBlobStore {
+    directory: Box<dyn Directory>,
+}
+
+impl DirectoryBlobStore {
+    /// Creates a `DirectoryBlobStore` writing the blobs to `directory`.
+    ///
+    /// The directory should not be the directory of an index, whose garbage collection could
+    /// delete the blobs.
+    pub fn new<D: Directory>(directory: D) -> DirectoryBlobStore {
+        DirectoryBlobStore {
+            directory: Box::new(directory),
+        }
+    }
+}
+
+impl BlobStore for DirectoryBlobStore {
+    fn put_blob(&self, key: &str, blob: &[u8]) -> io::Result<()> {
+        self.directory.atomic_write(Path::new(key), blob)
+    }
+
+    fn get_blob(&self, key: &str) -> io::Result<OwnedBytes> {
+        self.directory
+            .open_read(Path::new(key))
+            .map_err(|err| io::Error::new(io::ErrorKind::NotFound, err))?
+            .read_bytes()
+    }
+}
+
+/// Writes the large values of the documents of a segment to a `BlobStore`.
+pub(crate) struct BlobWriter {
+    blob_store: Arc<dyn BlobStore>,
+    threshold: usize,
+    // The keys are the id of the segment, followed by the number of blobs written before.
+    key_prefix: String,
+    num_blobs: u64,
+}
+
+// The position in the document, field and kind of value of a value written to the blob store.
+#[derive(Debug)]
+struct BlobRef {
+    position: usize,
+    field: Field,
+    code: u8,
+    key: String,
+}
+
+impl BinarySerializable for BlobRef {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        VInt(self.position as u64).serialize(writer)?;
+        self.field.serialize(writer)?;
+        self.code.serialize(writer)?;
+        self.key.serialize(writer)
+    }
+
+    fn deserialize<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let position = VInt::deserialize(reader)?.val() as usize;
+        let field = Field::deserialize(reader)?;
+        let code = u8::deserialize(reader)?;
+        let key = String::deserialize(reader)?;
+        Ok(BlobRef {
+            position,
+            field,
+            code,
+            key,
+        })
+    }
+}
+
+impl BlobWriter {
+    pub fn new(blob_store: Arc<dyn BlobStore>, threshold: usize, key_prefix: String) -> Self {
+        BlobWriter {
+            blob_store,
+            threshold,
+            key_prefix,
+            num_blobs: 0,
+        }
+    }
+
+    fn blob_code_and_bytes<'a>(&self, value: &'a Value) -> Option<(u8, &'a [u8])> {
+        let (code, bytes) = match value {
+            Value::Str(text) => (TEXT_BLOB_CODE, text.as_bytes()),
+            Value::Bytes(bytes) => (BYTES_BLOB_CODE, bytes.as_slice()),
+            _ => return None,
+        };
+        (bytes.len() > self.threshold).then_some((code, bytes))
+    }
+
+    /// Serializes the document, writing its large values to the blob store.
+    ///
+    /// The document is serialized without its large values, followed, if it has any, by
+    /// `BLOB_REFS_MARKER` and the references to their blobs.
+    pub fn serialize(&mut self, document: &Document, output: &mut Vec<u8>) -> io::Result<()> {
+        let field_values = document.field_values();
+        if field_values
+            .iter()
+            .all(|field_value| self.blob_code_and_bytes(field_value.value()).is_none())
+        {
+            return document.serialize(output);
+        }
+        let mut inline_field_values = Vec::with_capacity(field_values.len());
+        let mut blob_refs = Vec::new();
+        for (position, field_value) in field_values.iter().enumerate() {
+            if let Some((code, bytes)) = self.blob_code_and_bytes(field_value.value()) {
+                let key = format!("{}.{}.blob", self.key_prefix, self.num_blobs);
+                self.num_blobs += 1;
+                self.blob_store.put_blob(&key, bytes)?;
+                blob_refs.push(BlobRef {
+                    position,
+                    field: field_value.field(),
+                    code,
+                    key,
+                });
+            } else {
+                inline_field_values.push(field_value.clone());
+            }
+        }
+        serialize_with_blob_refs(&Document::from(inline_field_values), &blob_refs, output)
+    }
+}
+
+fn serialize_with_blob_refs(
+    inline_document: &Document,
+    blob_refs: &[BlobRef],
+    output: &mut Vec<u8>,
+) -> io::Result<()> {
+    inline_document.serialize(output)?;
+    if blob_refs.is_empty() {
+        return Ok(());
+    }
+    BLOB_REFS_MARKER.serialize(output)?;
+    VInt(blob_refs.len() as u64).serialize(output)?;
+    for blob_ref in blob_refs {
+        blob_ref.serialize(output)?;
+    }
+    Ok(())
+}
+
+/// Reads the inline values of a document serialized by [`BlobWriter::serialize`], and the
+/// references to the blobs of its large values.
+fn deserialize_with_blob_refs(doc_bytes: &mut &[u8]) -> io::Result<(Document, Vec<BlobRef>)> {
+    let document = Document::deserialize(doc_bytes)?;
+    if doc_bytes.is_empty() {
+        return Ok((document, Vec::new()));
+    }
+    if u8::deserialize(doc_bytes)? != BLOB_REFS_MARKER {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The document is followed by unexpected bytes.",
+        ));
+    }
+    let num_blob_refs = VInt::deserialize(doc_bytes)?.val() as usize;
+    let blob_refs = (0..num_blob_refs)
+        .map(|_| BlobRef::deserialize(doc_bytes))
+        .collect::<io::Result<Vec<BlobRef>>>()?;
+    Ok((document, blob_refs))
+}
+
+/// Value of a document, either inline or stored in a blob.
+enum DocValue {
+    Inline(FieldValue),
+    Blob(BlobRef),
+}
+
+impl DocValue {
+    fn field(&self) -> Field {
+        match self {
+            DocValue::Inline(field_value) => field_value.field(),
+            DocValue::Blob(blob_ref) => blob_ref.field,
+        }
+    }
+}
+
+/// Returns the values of the document, inline or not, in their original order.
+fn interleave(inline_document: Document, blob_refs: Vec<BlobRef>) -> Vec<DocValue> {
+    let mut inline_field_values = inline_document.into_iter();
+    let mut values = Vec::new();
+    for blob_ref in blob_refs {
+        values.extend(
+            inline_field_values
+                .by_ref()
+                .take(blob_ref.position.saturating_sub(values.len()))
+                .map(DocValue::Inline),
+        );
+        values.push(DocValue::Blob(blob_ref));
+    }
+    values.extend(inline_field_values.map(DocValue::Inline));
+    values
+}
+
+/// Rewrites a document serialized by [`BlobWriter::serialize`] without the values of the
+/// fields not matching `keep_field`.
+///
+/// The references to the blobs of the values kept are copied as is, without reading or
+/// rewriting the blobs, e.g. when merging segments.
+pub(crate) fn retain_fields(
+    doc_bytes: &[u8],
+    keep_field: impl Fn(Field) -> bool,
+    output: &mut Vec<u8>,
+) -> io::Result<()> {
+    let (inline_document, blob_refs) = deserialize_with_blob_refs(&mut &doc_bytes[..])?;
+    let mut inline_field_values = Vec::new();
+    let mut kept_blob_refs = Vec::new();
+    let kept_values = interleave(inline_document, blob_refs)
+        .into_iter()
+        .filter(|value| keep_field(value.field()));
+    for (position, value) in kept_values.enumerate() {
+        match value {
+            DocValue::Inline(field_value) => inline_field_values.push(field_value),
+            DocValue::Blob(blob_ref) => kept_blob_refs.push(BlobRef {
+                position,
+                ..blob_ref
+            }),
+        }
+    }
+    serialize_with_blob_refs(
+        &Document::from(inline_field_values),
+        &kept_blob_refs,
+        output,
+    )
+}
+
+/// Deserializes a document serialized by [`BlobWriter::serialize`], reading its large values
+/// back from the blob store.
+pub(crate) fn deserialize_document(
+    doc_bytes: &mut &[u8],
+    blob_store: Option<&dyn BlobStore>,
+) -> io::Result<Document> {
+    let (inline_document, blob_refs) = deserialize_with_blob_refs(doc_bytes)?;
+    if blob_refs.is_empty() {
+        return Ok(inline_document);
+    }
+    let blob_store = blob_store.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The document has values in a blob store, but the index has no blob store.",
+        )
+    })?;
+    let mut field_values: Vec<FieldValue> = Vec::new();
+    for value in interleave(inline_document, blob_refs) {
+        let blob_ref = match value {
+            DocValue::Inline(field_value) => {
+                field_values.push(field_value);
+                continue;
+            }
+            DocValue::Blob(blob_ref) => blob_ref,
+        };
+        let blob = blob_store.get_blob(&blob_ref.key)?;
+        let value = match blob_ref.code {
+            TEXT_BLOB_CODE => {
+                let text = String::from_utf8(blob.as_slice().to_vec())
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                Value::Str(text)
+            }
+            BYTES_BLOB_CODE => Value::Bytes(blob.as_slice().to_vec()),
+            code => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown blob code {code}."),
+                ));
+            }
+        };
+        field_values.push(FieldValue::new(blob_ref.field, value));
+    }
+    Ok(Document::from(field_values))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::BinarySerializable;
+
+    use super::{deserialize_document, retain_fields, BlobStore, BlobWriter, DirectoryBlobStore};
+    use crate::directory::RamDirectory;
+    use crate::schema::{Document, Schema, STORED};
+
+    #[test]
+    fn test_blob_writer() -> std::io::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", STORED);
+        let bytes = schema_builder.add_bytes_field("bytes", STORED);
+        let num = schema_builder.add_u64_field("num", STORED);
+        let mut document = Document::default();
+        document.add_text(text, "small");
+        document.add_bytes(bytes, vec![7u8; 20]);
+        document.add_u64(num, 3);
+        document.add_text(text, "large".repeat(10));
+        document.add_text(text, "tiny");
+
+        let blob_store = Arc::new(DirectoryBlobStore::new(RamDirectory::create()));
+        let mut blob_writer = BlobWriter::new(blob_store.clone(), 10, "segment".to_string());
+        let mut doc_bytes = Vec::new();
+        blob_writer.serialize(&document, &mut doc_bytes)?;
+        assert_eq!(
+            blob_store.get_blob("segment.0.blob")?.as_slice(),
+            &[7u8; 20]
+        );
+        assert!(blob_store.get_blob("segment.2.blob").is_err());
+
+        let resolved_document =
+            deserialize_document(&mut &doc_bytes[..], Some(blob_store.as_ref()))?;
+        assert_eq!(resolved_document, document);
+        assert!(deserialize_document(&mut &doc_bytes[..], None).is_err());
+
+        // Dropping the values of a field keeps the references to the blobs of the others.
+        let mut retained_doc_bytes = Vec::new();
+        retain_fields(&doc_bytes, |field| field != bytes, &mut retained_doc_bytes)?;
+        let mut expected_document = Document::default();
+        expected_document.add_text(text, "small");
+        expected_document.add_u64(num, 3);
+        expected_document.add_text(text, "large".repeat(10));
+        expected_document.add_text(text, "tiny");
+        let resolved_document =
+            deserialize_document(&mut &retained_doc_bytes[..], Some(blob_store.as_ref()))?;
+        assert_eq!(resolved_document, expected_document);
+        assert!(blob_store.get_blob("segment.2.blob").is_err());
+
+        // Bytes following a document are only read as references to blobs after the marker.
+        let mut doc_bytes = Vec::new();
+        Document::default().serialize(&mut doc_bytes)?;
+        doc_bytes.push(0u8);
+        assert!(deserialize_document(&mut &doc_bytes[..], Some(blob_store.as_ref())).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use std::io;
 
-use zstd::bulk::{compress_to_buffer, decompress_to_buffer};
+use zstd::bulk::{Compressor, Decompressor};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
 use zstd::DEFAULT_COMPRESSION_LEVEL;
 
 #[inline]
@@ -8,6 +9,7 @@ pub fn compress(
     uncompressed: &[u8],
     compressed: &mut Vec<u8>,
     compression_level: Option<i32>,
+    dictionary: Option<&EncoderDictionary<'static>>,
 ) -> io::Result<()> {
     let count_size = std::mem::size_of::<u32>();
     let max_size = zstd::zstd_safe::compress_bound(uncompressed.len()) + count_size;
@@ -15,11 +17,13 @@ pub fn compress(
     compressed.clear();
     compressed.resize(max_size, 0);
 
-    let compressed_size = compress_to_buffer(
-        uncompressed,
-        &mut compressed[count_size..],
-        compression_level.unwrap_or(DEFAULT_COMPRESSION_LEVEL),
-    )?;
+    let mut compressor = if let Some(dictionary) = dictionary {
+        Compressor::with_prepared_dictionary(dictionary)?
+    } else {
+        Compressor::new(compression_level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))?
+    };
+    let compressed_size =
+        compressor.compress_to_buffer(uncompressed, &mut compressed[count_size..])?;
 
     compressed[0..count_size].copy_from_slice(&(uncompressed.len() as u32).to_le_bytes());
     compressed.resize(compressed_size + count_size, 0);
@@ -28,7 +32,11 @@ pub fn compress(
 }
 
 #[inline]
-pub fn decompress(compressed: &[u8], decompressed: &mut Vec<u8>) -> io::Result<()> {
+pub fn decompress(
+    compressed: &[u8],
+    decompressed: &mut Vec<u8>,
+    dictionary: Option<&DecoderDictionary<'static>>,
+) -> io::Result<()> {
     let count_size = std::mem::size_of::<u32>();
     let uncompressed_size = u32::from_le_bytes(
         compressed
@@ -41,7 +49,13 @@ pub fn decompress(compressed: &[u8], decompressed: &mut Vec<u8>) -> io::Result<(
     decompressed.clear();
     decompressed.resize(uncompressed_size, 0);
 
-    let decompressed_size = decompress_to_buffer(&compressed[count_size..], decompressed)?;
+    let mut decompressor = if let Some(dictionary) = dictionary {
+        Decompressor::with_prepared_dictionary(dictionary)?
+    } else {
+        Decompressor::new()?
+    };
+    let decompressed_size =
+        decompressor.decompress_to_buffer(&compressed[count_size..], decompressed)?;
 
     if decompressed_size != uncompressed_size {
         return Err(io::Error::new(
@@ -52,3 +66,24 @@ pub fn decompress(compressed: &[u8], decompressed: &mut Vec<u8>) -> io::Result<(
 
     Ok(())
 }
+
+/// Trains a dictionary of at most `dictionary_size` bytes over the `samples`.
+pub fn train_dictionary(samples: &[&[u8]], dictionary_size: usize) -> io::Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, dictionary_size)
+}
+
+/// Digests the dictionary once, so that it can be reused to compress all of the blocks.
+pub fn prepare_encoder_dictionary(
+    dictionary: &[u8],
+    compression_level: Option<i32>,
+) -> EncoderDictionary<'static> {
+    EncoderDictionary::copy(
+        dictionary,
+        compression_level.unwrap_or(DEFAULT_COMPRESSION_LEVEL),
+    )
+}
+
+/// Digests the dictionary once, so that it can be reused to decompress all of the blocks.
+pub fn prepare_decoder_dictionary(dictionary: &[u8]) -> DecoderDictionary<'static> {
+    DecoderDictionary::copy(dictionary)
+}
@@ -74,10 +74,25 @@ impl<'de> Deserialize<'de> for Compressor {
 }
 
 #[derive(Clone, Default, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
-/// The Zstd compressor, with optional compression level.
+/// The Zstd compressor, with optional compression level and dictionary.
 pub struct ZstdCompressor {
     /// The compression level, if unset defaults to zstd::DEFAULT_COMPRESSION_LEVEL = 3
     pub compression_level: Option<i32>,
+    /// The maximum size of the dictionary trained for each segment, if set.
+    ///
+    /// The dictionary is trained over the first documents of the segment, about 100 times
+    /// its size, and stored along with its doc store. This shrinks stores of many small
+    /// similar documents, such as logs, which a single block compresses poorly.
+    ///
+    /// Until the dictionary is trained, the blocks are buffered uncompressed: each doc store
+    /// being written, i.e. each indexing thread and each merge, holds up to 100 times the
+    /// dictionary size in memory, capped at 8MB. Above 80KB, the dictionary is thus trained
+    /// over fewer documents than recommended.
+    ///
+    /// The blocks stacked on merge keep the dictionary they were compressed with, so a merged
+    /// doc store may hold the dictionaries of several segments.
+    #[serde(default)]
+    pub dictionary_size: Option<usize>,
 }
 
 impl ZstdCompressor {
@@ -92,7 +107,7 @@ impl ZstdCompressor {
 
         let mut compressor = ZstdCompressor::default();
         for option in options.split(',') {
-            let (opt_name, value) = options
+            let (opt_name, value) = option
                 .split_once('=')
                 .ok_or_else(|| format!("no '=' found in option {:?}", option))?;
 
@@ -106,6 +121,15 @@ impl ZstdCompressor {
                     })?;
                     compressor.compression_level = Some(value);
                 }
+                "dictionary_size" => {
+                    let value = value.parse::<usize>().map_err(|err| {
+                        format!(
+                            "Could not parse value {} of option {}, e: {}",
+                            value, opt_name, err
+                        )
+                    })?;
+                    compressor.dictionary_size = Some(value);
+                }
                 _ => {
                     return Err(format!("unknown zstd option {:?}", opt_name));
                 }
@@ -114,10 +138,17 @@ impl ZstdCompressor {
         Ok(compressor)
     }
     fn ser_to_string(&self) -> String {
+        let mut options = Vec::new();
         if let Some(compression_level) = self.compression_level {
-            format!("zstd(compression_level={})", compression_level)
-        } else {
+            options.push(format!("compression_level={}", compression_level));
+        }
+        if let Some(dictionary_size) = self.dictionary_size {
+            options.push(format!("dictionary_size={}", dictionary_size));
+        }
+        if options.is_empty() {
             "zstd".to_string()
+        } else {
+            format!("zstd({})", options.join(","))
         }
     }
 }
@@ -138,12 +169,69 @@ impl Default for Compressor {
     }
 }
 
+/// Compression dictionary trained by [`Compressor::train_dictionary`].
+///
+/// It is digested once, and then reused to compress all of the blocks of the doc store.
+pub(crate) struct EncoderDictionary {
+    bytes: Vec<u8>,
+    #[cfg(feature = "zstd-compression")]
+    zstd: zstd::dict::EncoderDictionary<'static>,
+}
+
+impl EncoderDictionary {
+    /// Returns the serialized dictionary, as stored along with the doc store.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 impl Compressor {
+    /// Returns the maximum size of the dictionary to train for each segment, if any.
+    pub(crate) fn dictionary_size(&self) -> Option<usize> {
+        match self {
+            Self::Zstd(zstd_compressor) => zstd_compressor.dictionary_size,
+            _ => None,
+        }
+    }
+
+    /// Trains a dictionary over the `samples`, if the compressor uses one.
+    pub(crate) fn train_dictionary(
+        &self,
+        samples: &[&[u8]],
+    ) -> io::Result<Option<EncoderDictionary>> {
+        let dictionary_size = if let Some(dictionary_size) = self.dictionary_size() {
+            dictionary_size
+        } else {
+            return Ok(None);
+        };
+        #[cfg(feature = "zstd-compression")]
+        {
+            let compression_level = match self {
+                Self::Zstd(zstd_compressor) => zstd_compressor.compression_level,
+                _ => None,
+            };
+            let bytes = super::compression_zstd_block::train_dictionary(samples, dictionary_size)?;
+            let zstd = super::compression_zstd_block::prepare_encoder_dictionary(
+                &bytes,
+                compression_level,
+            );
+            Ok(Some(EncoderDictionary { bytes, zstd }))
+        }
+        #[cfg(not(feature = "zstd-compression"))]
+        {
+            let _ = (samples, dictionary_size);
+            panic!("zstd-compression feature flag not activated");
+        }
+    }
+
+    /// Compresses `uncompressed`, with the `dictionary`, if any, trained by
+    /// [`Compressor::train_dictionary`].
     #[inline]
     pub(crate) fn compress_into(
         &self,
         uncompressed: &[u8],
         compressed: &mut Vec<u8>,
+        _dictionary: Option<&EncoderDictionary>,
     ) -> io::Result<()> {
         match self {
             Self::None => {
@@ -188,6 +276,7 @@ impl Compressor {
                         uncompressed,
                         compressed,
                         _zstd_compressor.compression_level,
+                        _dictionary.map(|dictionary| &dictionary.zstd),
                     )
                 }
                 #[cfg(not(feature = "zstd-compression"))]
@@ -207,6 +296,7 @@ mod tests {
     fn zstd_serde_roundtrip() {
         let compressor = ZstdCompressor {
             compression_level: Some(15),
+            dictionary_size: None,
         };
 
         assert_eq!(
@@ -214,6 +304,16 @@ mod tests {
             compressor
         );
 
+        let compressor = ZstdCompressor {
+            compression_level: None,
+            dictionary_size: Some(4096),
+        };
+        assert_eq!(compressor.ser_to_string(), "zstd(dictionary_size=4096)");
+        assert_eq!(
+            ZstdCompressor::deser_from_str(&compressor.ser_to_string()).unwrap(),
+            compressor
+        );
+
         assert_eq!(
             ZstdCompressor::deser_from_str(&ZstdCompressor::default().ser_to_string()).unwrap(),
             ZstdCompressor::default()
@@ -232,7 +332,16 @@ mod tests {
         assert_eq!(
             ZstdCompressor::deser_from_str("zstd(compression_level=15)").unwrap(),
             ZstdCompressor {
-                compression_level: Some(15)
+                compression_level: Some(15),
+                dictionary_size: None,
+            }
+        );
+        assert_eq!(
+            ZstdCompressor::deser_from_str("zstd(compression_level=15,dictionary_size=4096)")
+                .unwrap(),
+            ZstdCompressor {
+                compression_level: Some(15),
+                dictionary_size: Some(4096),
             }
         );
         assert_eq!(
@@ -37,6 +37,12 @@ impl From<Compressor> for Decompressor {
     }
 }
 
+/// Compression dictionary of a doc store, digested by [`Decompressor::prepare_dictionary`].
+pub(crate) struct DecoderDictionary {
+    #[cfg(feature = "zstd-compression")]
+    zstd: zstd::dict::DecoderDictionary<'static>,
+}
+
 impl Decompressor {
     pub(crate) fn from_id(id: u8) -> Decompressor {
         match id {
@@ -59,17 +65,39 @@ impl Decompressor {
         }
     }
 
-    pub(crate) fn decompress(&self, compressed_block: &[u8]) -> io::Result<Vec<u8>> {
+    /// Digests the dictionary stored along with the doc store, so that it can be reused
+    /// to decompress all of its blocks.
+    pub(crate) fn prepare_dictionary(&self, dictionary: &[u8]) -> DecoderDictionary {
+        #[cfg(feature = "zstd-compression")]
+        {
+            DecoderDictionary {
+                zstd: super::compression_zstd_block::prepare_decoder_dictionary(dictionary),
+            }
+        }
+        #[cfg(not(feature = "zstd-compression"))]
+        {
+            let _ = dictionary;
+            DecoderDictionary {}
+        }
+    }
+
+    pub(crate) fn decompress(
+        &self,
+        compressed_block: &[u8],
+        dictionary: Option<&DecoderDictionary>,
+    ) -> io::Result<Vec<u8>> {
         let mut decompressed_block = vec![];
-        self.decompress_into(compressed_block, &mut decompressed_block)?;
+        self.decompress_into(compressed_block, &mut decompressed_block, dictionary)?;
         Ok(decompressed_block)
     }
 
+    /// Decompresses `compressed`, with the `dictionary` it was compressed with, if any.
     #[inline]
     pub(crate) fn decompress_into(
         &self,
         compressed: &[u8],
         decompressed: &mut Vec<u8>,
+        _dictionary: Option<&DecoderDictionary>,
     ) -> io::Result<()> {
         match self {
             Self::None => {
@@ -110,7 +138,11 @@ impl Decompressor {
             Self::Zstd => {
                 #[cfg(feature = "zstd-compression")]
                 {
-                    super::compression_zstd_block::decompress(compressed, decompressed)
+                    super::compression_zstd_block::decompress(
+                        compressed,
+                        decompressed,
+                        _dictionary.map(|dictionary| &dictionary.zstd),
+                    )
                 }
                 #[cfg(not(feature = "zstd-compression"))]
                 {
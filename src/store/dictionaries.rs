@@ -0,0 +1,240 @@
+use std::io::{self, Write};
+
+use common::{BinarySerializable, VInt};
+use ownedbytes::OwnedBytes;
+
+use super::decompressors::DecoderDictionary;
+use super::Decompressor;
+
+/// Serializes the compression dictionaries of a doc store, and the blocks each one compresses.
+///
+/// A doc store can hold several dictionaries, as the blocks stacked from other doc stores keep
+/// the dictionary they were compressed with. The blocks are identified by byte ranges: each
+/// range starts at the offset of its first block, and ends where the next range starts.
+///
+/// - number of dictionaries: VInt
+/// - for each dictionary, its length as a VInt, followed by its bytes
+/// - number of ranges: VInt
+/// - for each range, its start offset as a VInt, followed by its dictionary ordinal plus one as
+///   a VInt, or 0 if its blocks are compressed without a dictionary
+///
+/// The blocks before the first range are compressed without a dictionary.
+#[derive(Default)]
+pub(crate) struct DictionariesBuilder {
+    dictionaries: Vec<Vec<u8>>,
+    ranges: Vec<(u64, Option<u32>)>,
+}
+
+impl DictionariesBuilder {
+    /// Registers a dictionary, and returns its ordinal.
+    ///
+    /// The identical dictionaries of the stacked doc stores are only stored once.
+    pub fn add_dictionary(&mut self, dictionary: &[u8]) -> u32 {
+        if let Some(ord) = self
+            .dictionaries
+            .iter()
+            .position(|registered| registered.as_slice() == dictionary)
+        {
+            return ord as u32;
+        }
+        self.dictionaries.push(dictionary.to_vec());
+        self.dictionaries.len() as u32 - 1
+    }
+
+    /// Records that the blocks starting at `start_offset` are compressed with the dictionary
+    /// `dictionary_ord_opt`, until the next call.
+    pub fn use_dictionary(&mut self, start_offset: u64, dictionary_ord_opt: Option<u32>) {
+        if let Some(&(last_start_offset, _)) = self.ranges.last() {
+            if last_start_offset == start_offset {
+                self.ranges.pop();
+            }
+        }
+        let current_dictionary_ord_opt = self
+            .ranges
+            .last()
+            .and_then(|&(_, dictionary_ord_opt)| dictionary_ord_opt);
+        if current_dictionary_ord_opt != dictionary_ord_opt {
+            self.ranges.push((start_offset, dictionary_ord_opt));
+        }
+    }
+
+    /// Serializes the dictionaries, if any block uses one.
+    pub fn serialize_into<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.ranges.is_empty() {
+            return Ok(());
+        }
+        VInt(self.dictionaries.len() as u64).serialize(writer)?;
+        for dictionary in &self.dictionaries {
+            VInt(dictionary.len() as u64).serialize(writer)?;
+            writer.write_all(dictionary)?;
+        }
+        VInt(self.ranges.len() as u64).serialize(writer)?;
+        for &(start_offset, dictionary_ord_opt) in &self.ranges {
+            VInt(start_offset).serialize(writer)?;
+            VInt(dictionary_ord_opt.map(|ord| ord as u64 + 1).unwrap_or(0)).serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The compression dictionaries of a doc store, see [`DictionariesBuilder`].
+#[derive(Default)]
+pub(crate) struct Dictionaries {
+    dictionaries: Vec<(OwnedBytes, DecoderDictionary)>,
+    ranges: Vec<(usize, Option<u32>)>,
+}
+
+impl Dictionaries {
+    /// Reads the dictionaries serialized by [`DictionariesBuilder::serialize_into`].
+    pub fn open(mut data: OwnedBytes, decompressor: Decompressor) -> io::Result<Dictionaries> {
+        if data.is_empty() {
+            return Ok(Dictionaries::default());
+        }
+        let num_dictionaries = VInt::deserialize_u64(&mut data)? as usize;
+        let mut dictionaries = Vec::with_capacity(num_dictionaries);
+        for _ in 0..num_dictionaries {
+            let dictionary_len = VInt::deserialize_u64(&mut data)? as usize;
+            if dictionary_len > data.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The compression dictionary of the doc store is truncated.",
+                ));
+            }
+            let (dictionary_bytes, rest) = data.split(dictionary_len);
+            data = rest;
+            let dictionary = decompressor.prepare_dictionary(dictionary_bytes.as_slice());
+            dictionaries.push((dictionary_bytes, dictionary));
+        }
+        let num_ranges = VInt::deserialize_u64(&mut data)? as usize;
+        let mut ranges = Vec::with_capacity(num_ranges);
+        for _ in 0..num_ranges {
+            let start_offset = VInt::deserialize_u64(&mut data)? as usize;
+            let dictionary_ord_opt = match VInt::deserialize_u64(&mut data)? {
+                0 => None,
+                ord_plus_one if ord_plus_one as usize <= num_dictionaries => {
+                    Some(ord_plus_one as u32 - 1)
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unknown compression dictionary in the doc store.",
+                    ));
+                }
+            };
+            ranges.push((start_offset, dictionary_ord_opt));
+        }
+        Ok(Dictionaries {
+            dictionaries,
+            ranges,
+        })
+    }
+
+    /// Returns true if no block is compressed with a dictionary.
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.dictionaries.is_empty()
+    }
+
+    fn block_dictionary_ord(&self, block_start_offset: usize) -> Option<usize> {
+        let num_ranges_before = self
+            .ranges
+            .partition_point(|&(start_offset, _)| start_offset <= block_start_offset);
+        let (_, dictionary_ord_opt) = *self.ranges.get(num_ranges_before.checked_sub(1)?)?;
+        dictionary_ord_opt.map(|ord| ord as usize)
+    }
+
+    /// Returns the dictionary the block starting at `block_start_offset` is compressed with.
+    pub fn block_dictionary(&self, block_start_offset: usize) -> Option<&DecoderDictionary> {
+        let (_, dictionary) = &self.dictionaries[self.block_dictionary_ord(block_start_offset)?];
+        Some(dictionary)
+    }
+
+    /// Returns the serialized dictionary the block starting at `block_start_offset` is
+    /// compressed with.
+    pub fn block_dictionary_bytes(&self, block_start_offset: usize) -> Option<&OwnedBytes> {
+        let (dictionary_bytes, _) =
+            &self.dictionaries[self.block_dictionary_ord(block_start_offset)?];
+        Some(dictionary_bytes)
+    }
+
+    /// Registers the dictionaries in `builder`, for the blocks of the doc store stacked at
+    /// `start_shift`.
+    pub fn stack_into(&self, builder: &mut DictionariesBuilder, start_shift: u64) {
+        builder.use_dictionary(start_shift, None);
+        for &(start_offset, dictionary_ord_opt) in &self.ranges {
+            let stacked_ord_opt = dictionary_ord_opt.map(|ord| {
+                let (dictionary_bytes, _) = &self.dictionaries[ord as usize];
+                builder.add_dictionary(dictionary_bytes.as_slice())
+            });
+            builder.use_dictionary(start_shift + start_offset as u64, stacked_ord_opt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(builder: &DictionariesBuilder) -> Dictionaries {
+        let mut bytes = Vec::new();
+        builder.serialize_into(&mut bytes).unwrap();
+        Dictionaries::open(OwnedBytes::new(bytes), Decompressor::None).unwrap()
+    }
+
+    fn block_dictionaries(dictionaries: &Dictionaries, offsets: &[usize]) -> Vec<Option<usize>> {
+        offsets
+            .iter()
+            .map(|&offset| {
+                dictionaries.block_dictionary(offset).map(|dictionary| {
+                    dictionaries
+                        .dictionaries
+                        .iter()
+                        .position(|(_, registered)| std::ptr::eq(registered, dictionary))
+                        .unwrap()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionaries_empty() {
+        let mut builder = DictionariesBuilder::default();
+        builder.use_dictionary(0, None);
+        let mut bytes = Vec::new();
+        builder.serialize_into(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+        let dictionaries = open(&builder);
+        assert!(dictionaries.is_empty());
+        assert!(dictionaries.block_dictionary(0).is_none());
+    }
+
+    #[test]
+    fn test_dictionaries_stacked() {
+        let mut stacked_builder = DictionariesBuilder::default();
+        let ord = stacked_builder.add_dictionary(b"stacked");
+        stacked_builder.use_dictionary(10, Some(ord));
+        let stacked = open(&stacked_builder);
+
+        let mut builder = DictionariesBuilder::default();
+        let own_ord = builder.add_dictionary(b"own");
+        builder.use_dictionary(0, Some(own_ord));
+        stacked.stack_into(&mut builder, 100);
+        builder.use_dictionary(200, Some(own_ord));
+        stacked.stack_into(&mut builder, 300);
+        let dictionaries = open(&builder);
+        assert_eq!(dictionaries.dictionaries.len(), 2);
+        assert_eq!(
+            block_dictionaries(&dictionaries, &[0, 50, 100, 110, 150, 200, 300, 310]),
+            vec![
+                Some(0),
+                Some(0),
+                None,
+                Some(1),
+                Some(1),
+                Some(0),
+                None,
+                Some(1)
+            ]
+        );
+    }
+}
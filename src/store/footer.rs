@@ -9,28 +9,37 @@ use crate::directory::FileSlice;
 pub struct DocStoreFooter {
     pub offset: u64,
     pub decompressor: Decompressor,
+    /// The length of the compression dictionaries, stored between the skip index and the footer.
+    ///
+    /// It was reserved before the doc store format version 2, and is ignored when reading
+    /// older doc stores, see [`SegmentComponent::format_version`](crate::SegmentComponent).
+    pub dictionary_len: u64,
 }
 
 /// Serialises the footer to a byte-array
 /// - offset : 8 bytes
 /// - compressor id: 1 byte
-/// - reserved for future use: 15 bytes
+/// - dictionaries length: 8 bytes, reserved before the format version 2
+/// - reserved for future use: 7 bytes
 impl BinarySerializable for DocStoreFooter {
     fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
         BinarySerializable::serialize(&self.offset, writer)?;
         BinarySerializable::serialize(&self.decompressor.get_id(), writer)?;
-        writer.write_all(&[0; 15])?;
+        BinarySerializable::serialize(&self.dictionary_len, writer)?;
+        writer.write_all(&[0; 7])?;
         Ok(())
     }
 
     fn deserialize<R: io::Read>(reader: &mut R) -> io::Result<Self> {
         let offset = u64::deserialize(reader)?;
         let compressor_id = u8::deserialize(reader)?;
-        let mut skip_buf = [0; 15];
+        let dictionary_len = u64::deserialize(reader)?;
+        let mut skip_buf = [0; 7];
         reader.read_exact(&mut skip_buf)?;
         Ok(DocStoreFooter {
             offset,
             decompressor: Decompressor::from_id(compressor_id),
+            dictionary_len,
         })
     }
 }
@@ -40,10 +49,11 @@ impl FixedSize for DocStoreFooter {
 }
 
 impl DocStoreFooter {
-    pub fn new(offset: u64, decompressor: Decompressor) -> Self {
+    pub fn new(offset: u64, decompressor: Decompressor, dictionary_len: u64) -> Self {
         DocStoreFooter {
             offset,
             decompressor,
+            dictionary_len,
         }
     }
 
@@ -69,5 +79,5 @@ fn doc_store_footer_test() {
     // This test is just to safe guard changes on the footer.
     // When the doc store footer is updated, make sure to update also the serialize/deserialize
     // methods
-    assert_eq!(core::mem::size_of::<DocStoreFooter>(), 16);
+    assert_eq!(core::mem::size_of::<DocStoreFooter>(), 24);
 }
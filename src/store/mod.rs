@@ -6,6 +6,8 @@
 //! Internally, documents (or rather their stored fields) are serialized to a buffer.
 //! When the buffer exceeds 16K, the buffer is compressed using `brotli`, `LZ4` or `snappy`
 //! and the resulting block is written to disk.
+//! With `zstd`, the blocks can also be compressed with a dictionary trained over the first
+//! documents of the segment (See [`ZstdCompressor::dictionary_size`]).
 //!
 //! One can then request for a specific `DocId`.
 //! A skip list helps navigating to the right block,
@@ -32,12 +34,16 @@
 //!
 //! !
 
+mod blob_store;
 mod compressors;
 mod decompressors;
+mod dictionaries;
 mod footer;
 mod index;
 mod reader;
 mod writer;
+pub(crate) use self::blob_store::{retain_fields, BlobWriter};
+pub use self::blob_store::{BlobStore, DirectoryBlobStore};
 pub use self::compressors::{Compressor, ZstdCompressor};
 pub use self::decompressors::Decompressor;
 pub(crate) use self::reader::DOCSTORE_CACHE_CAPACITY;
@@ -59,13 +65,17 @@ mod compression_zstd_block;
 #[cfg(test)]
 pub mod tests {
 
+    use std::io;
     use std::path::Path;
 
     use super::*;
     use crate::directory::{Directory, RamDirectory, WritePtr};
     use crate::fastfield::AliveBitSet;
-    use crate::schema::{self, Document, Schema, TextFieldIndexing, TextOptions, STORED, TEXT};
-    use crate::{Index, Term};
+    use crate::indexer::{LogMergePolicy, NoMergePolicy};
+    use crate::schema::{
+        self, Document, Schema, TextFieldIndexing, TextOptions, INDEXED, STORED, TEXT,
+    };
+    use crate::{Index, IndexSettings, Term};
 
     const LOREM: &str = "Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
                          eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad \
@@ -202,6 +212,77 @@ pub mod tests {
         test_store(Compressor::Zstd(ZstdCompressor::default()), BLOCK_SIZE)
     }
 
+    #[cfg(feature = "zstd-compression")]
+    #[test]
+    fn test_store_zstd_dictionary() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let message = schema_builder.add_text_field("message", STORED);
+        let write_logs = |compressor: Compressor, num_docs: usize| -> crate::Result<StoreReader> {
+            let path = Path::new("store");
+            let directory = RamDirectory::create();
+            let mut store_writer = StoreWriter::new(directory.open_write(path)?, compressor, 256)?;
+            for i in 0..num_docs {
+                store_writer.store(&doc!(message => format!(
+                    "level=INFO service=checkout request_id={i} msg=\"payment accepted\" duration_ms={}",
+                    i % 97
+                )))?;
+            }
+            store_writer.close()?;
+            Ok(StoreReader::open(directory.open_read(path)?, 10)?)
+        };
+        let dictionary_compressor = Compressor::Zstd(ZstdCompressor {
+            compression_level: None,
+            dictionary_size: Some(1_024),
+        });
+        let store = write_logs(dictionary_compressor, 2_000)?;
+        assert!(store.has_dictionary());
+        for (i, doc) in store.iter(None).enumerate() {
+            let doc = doc?;
+            let text = doc.get_first(message).unwrap().as_text().unwrap();
+            assert!(text.contains(&format!("request_id={i} ")));
+        }
+        let store_without_dictionary =
+            write_logs(Compressor::Zstd(ZstdCompressor::default()), 2_000)?;
+        assert!(!store_without_dictionary.has_dictionary());
+        assert!(store.space_usage().total() * 2 < store_without_dictionary.space_usage().total());
+
+        // There are too few documents to train a dictionary.
+        let store = write_logs(dictionary_compressor, 3)?;
+        assert!(!store.has_dictionary());
+        assert_eq!(store.iter(None).count(), 3);
+
+        // The stacked blocks keep their dictionary.
+        let directory = RamDirectory::create();
+        let path = Path::new("stacked");
+        let mut store_writer =
+            StoreWriter::new(directory.open_write(path)?, dictionary_compressor, 256)?;
+        store_writer.store(&doc!(message => "first"))?;
+        store_writer.stack(write_logs(dictionary_compressor, 2_000)?)?;
+        store_writer.stack(write_logs(Compressor::Zstd(ZstdCompressor::default()), 10)?)?;
+        store_writer.stack(write_logs(dictionary_compressor, 2_000)?)?;
+        store_writer.store(&doc!(message => "last"))?;
+        store_writer.close()?;
+        let store = StoreReader::open(directory.open_read(path)?, 10)?;
+        assert!(store.has_dictionary());
+        let texts: Vec<String> = store
+            .iter(None)
+            .map(|doc| {
+                let doc = doc?;
+                let text = doc.get_first(message).unwrap().as_text().unwrap();
+                Ok(text.to_string())
+            })
+            .collect::<crate::Result<_>>()?;
+        assert_eq!(texts.len(), 4_012);
+        assert_eq!(texts[0], "first");
+        assert!(texts[1].contains("request_id=0 "));
+        assert!(texts[2_000].contains("request_id=1999 "));
+        assert!(texts[2_001].contains("request_id=0 "));
+        assert!(texts[2_011].contains("request_id=0 "));
+        assert!(texts[4_010].contains("request_id=1999 "));
+        assert_eq!(texts[4_011], "last");
+        Ok(())
+    }
+
     #[test]
     fn test_store_with_delete() -> crate::Result<()> {
         let mut schema_builder = schema::Schema::builder();
@@ -340,6 +421,148 @@ pub mod tests {
         assert_eq!(store.block_checkpoints().count(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_merge_without_stacking() -> crate::Result<()> {
+        let merged_num_blocks = |docstore_stack_on_merge: bool| -> crate::Result<usize> {
+            let mut schema_builder = schema::Schema::builder();
+            let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+            let index = Index::create_in_ram(schema_builder.build());
+            let mut index_writer = index.writer_for_tests()?;
+            let mut merge_policy = LogMergePolicy::default();
+            merge_policy.set_docstore_stack_on_merge(docstore_stack_on_merge);
+            index_writer.set_merge_policy(Box::new(merge_policy));
+            for _ in 0..2 {
+                // put enough data to create enough blocks in the doc store to be considered for
+                // stacking, and a partial last block
+                for _ in 0..230 {
+                    index_writer.add_document(doc!(text_field=> LOREM))?;
+                }
+                index_writer.commit()?;
+            }
+            let segment_ids = index.searchable_segment_ids()?;
+            index_writer.merge(&segment_ids).wait()?;
+            index_writer.wait_merging_threads()?;
+
+            let searcher = index.reader()?.searcher();
+            assert_eq!(searcher.segment_readers().len(), 1);
+            let store = searcher.segment_reader(0).get_store_reader(10)?;
+            assert_eq!(store.iter(None).count(), 460);
+            Ok(store.block_checkpoints().count())
+        };
+        assert!(merged_num_blocks(false)? < merged_num_blocks(true)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_with_deletes_stacks_alive_blocks() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | STORED);
+        let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for id in 0..460u64 {
+            index_writer.add_document(doc!(id_field => id, text_field => LOREM))?;
+        }
+        index_writer.commit()?;
+        let original_blocks: Vec<Vec<u8>> = {
+            let searcher = index.reader()?.searcher();
+            let store = searcher.segment_reader(0).get_store_reader(10)?;
+            store
+                .block_checkpoints()
+                .map(|checkpoint| Ok(store.get_compressed_block(&checkpoint)?.to_vec()))
+                .collect::<io::Result<_>>()?
+        };
+        assert!(original_blocks.len() > 6);
+        index_writer.delete_term(Term::from_field_u64(id_field, 100));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader(10)?;
+        let ids: Vec<u64> = store
+            .iter(None)
+            .map(|doc| Ok(doc?.get_first(id_field).and_then(|id| id.as_u64()).unwrap()))
+            .collect::<crate::Result<_>>()?;
+        assert_eq!(ids, (0..460).filter(|&id| id != 100).collect::<Vec<u64>>());
+        // All of the blocks but the one holding the deleted document are copied as they are.
+        let num_stacked_blocks = store
+            .block_checkpoints()
+            .filter(|checkpoint| {
+                let block = store.get_compressed_block(checkpoint).unwrap();
+                original_blocks.contains(&block.to_vec())
+            })
+            .count();
+        assert_eq!(num_stacked_blocks, original_blocks.len() - 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_with_blob_store() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+        let bytes_field = schema_builder.add_bytes_field("bytes_field", STORED);
+        let settings = IndexSettings {
+            docstore_blob_threshold: Some(100),
+            ..Default::default()
+        };
+        let index_builder = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings);
+        let mut index = index_builder.create_in_ram()?;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(text_field => "small"))?;
+            assert!(index_writer.commit().is_err());
+        }
+
+        let blob_directory = RamDirectory::create();
+        index.set_blob_store(DirectoryBlobStore::new(blob_directory.clone()));
+        let large_text = "large ".repeat(100);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "small", bytes_field => vec![1u8; 200]))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => large_text.clone()))?;
+        index_writer.add_document(doc!(text_field => "deleteme"))?;
+        index_writer.delete_term(Term::from_field_text(text_field, "deleteme"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader(10)?;
+        assert!(store.space_usage().total() < 200);
+        let docs: Vec<Document> = store.iter(None).collect::<crate::Result<_>>()?;
+        assert_eq!(docs.len(), 2);
+        assert!(docs.contains(&doc!(text_field => "small", bytes_field => vec![1u8; 200])));
+        assert!(docs.contains(&doc!(text_field => large_text)));
+        assert_eq!(blob_directory.total_mem_usage(), 800);
+
+        // Merging while dropping the values of a deprecated field copies the references to
+        // the blobs of the other values.
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "other", bytes_field => vec![2u8; 200]))?;
+        index_writer.commit()?;
+        index_writer.deprecate_field(text_field)?;
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(blob_directory.total_mem_usage(), 1_000);
+        let searcher = index.reader()?.searcher();
+        let store = searcher.segment_reader(0).get_store_reader(10)?;
+        let docs: Vec<Document> = store.iter(None).collect::<crate::Result<_>>()?;
+        assert_eq!(docs.len(), 3);
+        assert!(docs.contains(&doc!(bytes_field => vec![1u8; 200])));
+        assert!(docs.contains(&doc!(bytes_field => vec![2u8; 200])));
+        assert!(docs.contains(&Document::default()));
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
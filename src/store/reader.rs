@@ -2,18 +2,22 @@ use std::io;
 use std::iter::Sum;
 use std::ops::AddAssign;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use common::{BinarySerializable, HasLen, VInt};
 use lru::LruCache;
 use ownedbytes::OwnedBytes;
 
+use super::blob_store::deserialize_document;
+use super::dictionaries::{Dictionaries, DictionariesBuilder};
 use super::footer::DocStoreFooter;
 use super::index::SkipIndex;
-use super::Decompressor;
+use super::{BlobStore, Decompressor};
+use crate::core::SegmentComponent;
 use crate::directory::FileSlice;
 use crate::error::DataCorruption;
 use crate::fastfield::AliveBitSet;
+use crate::reader::GenerationMemory;
 use crate::schema::Document;
 use crate::space_usage::StoreSpaceUsage;
 use crate::store::index::Checkpoint;
@@ -21,15 +25,23 @@ use crate::DocId;
 
 pub(crate) const DOCSTORE_CACHE_CAPACITY: usize = 100;
 
+/// First doc store format version whose blocks may be compressed with a dictionary, and
+/// whose documents may hold references to a blob store.
+const DICTIONARY_AND_BLOBS_FORMAT_VERSION: u32 = 2;
+
 type Block = OwnedBytes;
 
 /// Reads document off tantivy's [`Store`](./index.html)
 pub struct StoreReader {
     decompressor: Decompressor,
+    // The compression dictionaries of the blocks, if any.
+    dictionaries: Dictionaries,
     data: FileSlice,
     skip_index: Arc<SkipIndex>,
     space_usage: StoreSpaceUsage,
     cache: BlockCache,
+    blob_store: Option<Arc<dyn BlobStore>>,
+    format_version: u32,
 }
 
 /// The cache for decompressed blocks.
@@ -37,6 +49,8 @@ struct BlockCache {
     cache: Mutex<LruCache<usize, Block>>,
     cache_hits: Arc<AtomicUsize>,
     cache_misses: Arc<AtomicUsize>,
+    // Counts the bytes of the cached blocks.
+    memory: GenerationMemory,
 }
 
 impl BlockCache {
@@ -50,7 +64,10 @@ impl BlockCache {
     }
 
     fn put_into_cache(&self, pos: usize, data: Block) {
-        self.cache.lock().unwrap().put(pos, data);
+        self.memory.add(data.len());
+        if let Some((_, evicted_block)) = self.cache.lock().unwrap().push(pos, data) {
+            self.memory.sub(evicted_block.len());
+        }
     }
 
     fn stats(&self) -> CacheStats {
@@ -74,6 +91,14 @@ impl BlockCache {
     }
 }
 
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        let cache = self.cache.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let num_bytes: usize = cache.iter().map(|(_, block)| block.len()).sum();
+        self.memory.release(num_bytes);
+    }
+}
+
 #[derive(Debug, Default)]
 /// CacheStats for the `StoreReader`.
 pub struct CacheStats {
@@ -108,25 +133,77 @@ impl Sum for CacheStats {
 impl StoreReader {
     /// Opens a store reader
     pub fn open(store_file: FileSlice, cache_size: usize) -> io::Result<StoreReader> {
+        StoreReader::open_with_format_version(
+            store_file,
+            cache_size,
+            SegmentComponent::Store.format_version(),
+        )
+    }
+
+    /// Opens a store reader on a doc store written with the given format version.
+    pub(crate) fn open_with_format_version(
+        store_file: FileSlice,
+        cache_size: usize,
+        format_version: u32,
+    ) -> io::Result<StoreReader> {
         let (footer, data_and_offset) = DocStoreFooter::extract_footer(store_file)?;
+        // The length of the dictionaries is stored in bytes of the footer that were reserved
+        // before the format version 2, and that older doc stores may not have zeroed.
+        let dictionary_len = if format_version >= DICTIONARY_AND_BLOBS_FORMAT_VERSION {
+            footer.dictionary_len
+        } else {
+            0
+        };
 
         let (data_file, offset_index_file) = data_and_offset.split(footer.offset as usize);
-        let index_data = offset_index_file.read_bytes()?;
         let space_usage = StoreSpaceUsage::new(data_file.len(), offset_index_file.len());
+        let (offset_index_file, dictionary_file) =
+            offset_index_file.split_from_end(dictionary_len as usize);
+        let index_data = offset_index_file.read_bytes()?;
         let skip_index = SkipIndex::open(index_data);
+        let dictionaries = Dictionaries::open(dictionary_file.read_bytes()?, footer.decompressor)?;
         Ok(StoreReader {
             decompressor: footer.decompressor,
+            dictionaries,
             data: data_file,
             cache: BlockCache {
                 cache: Mutex::new(LruCache::new(cache_size)),
                 cache_hits: Default::default(),
                 cache_misses: Default::default(),
+                memory: GenerationMemory::default(),
             },
             skip_index: Arc::new(skip_index),
             space_usage,
+            blob_store: None,
+            format_version,
         })
     }
 
+    /// Sets the blob store the large values of the documents are read from.
+    pub(crate) fn with_blob_store(mut self, blob_store: Option<Arc<dyn BlobStore>>) -> Self {
+        self.blob_store = blob_store;
+        self
+    }
+
+    /// Counts the bytes of the cached blocks in the memory of a searcher generation.
+    pub(crate) fn with_generation_memory(mut self, memory: GenerationMemory) -> Self {
+        self.cache.memory = memory;
+        self
+    }
+
+    /// Deserializes a document read by [`StoreReader::get_document_bytes`], reading its large
+    /// values back from the blob store.
+    pub(crate) fn document_from_bytes(&self, doc_bytes: &[u8]) -> io::Result<Document> {
+        if self.format_version < DICTIONARY_AND_BLOBS_FORMAT_VERSION {
+            return Document::deserialize(&mut &doc_bytes[..]);
+        }
+        deserialize_document(&mut &doc_bytes[..], self.blob_store.as_deref())
+    }
+
+    fn deserialize_document(&self, doc_bytes: OwnedBytes) -> crate::Result<Document> {
+        Ok(self.document_from_bytes(doc_bytes.as_slice())?)
+    }
+
     pub(crate) fn block_checkpoints(&self) -> impl Iterator<Item = Checkpoint> + '_ {
         self.skip_index.checkpoints()
     }
@@ -135,6 +212,31 @@ impl StoreReader {
         self.decompressor
     }
 
+    /// Returns true if some blocks are compressed with a dictionary.
+    #[cfg(all(test, feature = "zstd-compression"))]
+    pub(crate) fn has_dictionary(&self) -> bool {
+        !self.dictionaries.is_empty()
+    }
+
+    /// Registers the compression dictionaries of the blocks in `builder`, for the doc store
+    /// they are stacked on at `start_shift`.
+    pub(crate) fn stack_dictionaries_into(
+        &self,
+        builder: &mut DictionariesBuilder,
+        start_shift: u64,
+    ) {
+        self.dictionaries.stack_into(builder, start_shift);
+    }
+
+    fn decompress_block(&self, checkpoint: &Checkpoint, compressed: &[u8]) -> io::Result<Block> {
+        let dictionary = self
+            .dictionaries
+            .block_dictionary(checkpoint.byte_range.start);
+        Ok(OwnedBytes::new(
+            self.decompressor.decompress(compressed, dictionary)?,
+        ))
+    }
+
     /// Returns the cache hit and miss statistics of the store reader.
     pub(crate) fn cache_stats(&self) -> CacheStats {
         self.cache.stats()
@@ -154,10 +256,17 @@ impl StoreReader {
         self.data.read_bytes()
     }
 
-    fn get_compressed_block(&self, checkpoint: &Checkpoint) -> io::Result<OwnedBytes> {
+    pub(crate) fn get_compressed_block(&self, checkpoint: &Checkpoint) -> io::Result<OwnedBytes> {
         self.data.slice(checkpoint.byte_range.clone()).read_bytes()
     }
 
+    /// Returns the serialized dictionary the block is compressed with, if any.
+    pub(crate) fn block_dictionary_bytes(&self, checkpoint: &Checkpoint) -> Option<OwnedBytes> {
+        self.dictionaries
+            .block_dictionary_bytes(checkpoint.byte_range.start)
+            .cloned()
+    }
+
     /// Loads and decompresses a block.
     ///
     /// Advanced API. In most cases use [get](Self::get).
@@ -168,8 +277,7 @@ impl StoreReader {
         }
 
         let compressed_block = self.get_compressed_block(checkpoint)?;
-        let decompressed_block =
-            OwnedBytes::new(self.decompressor.decompress(compressed_block.as_ref())?);
+        let decompressed_block = self.decompress_block(checkpoint, compressed_block.as_ref())?;
 
         self.cache
             .put_into_cache(cache_key, decompressed_block.clone());
@@ -187,8 +295,35 @@ impl StoreReader {
     /// It should not be called to score documents
     /// for instance.
     pub fn get(&self, doc_id: DocId) -> crate::Result<Document> {
-        let mut doc_bytes = self.get_document_bytes(doc_id)?;
-        Ok(Document::deserialize(&mut doc_bytes)?)
+        let doc_bytes = self.get_document_bytes(doc_id)?;
+        self.deserialize_document(doc_bytes)
+    }
+
+    /// Reads the given documents, in the order of `doc_ids`.
+    ///
+    /// The documents are read in increasing doc id order, so that each block is decompressed
+    /// at most once, whatever the size of the cache.
+    pub fn get_many(&self, doc_ids: &[DocId]) -> crate::Result<Vec<Document>> {
+        let mut docs = Vec::with_capacity(doc_ids.len());
+        let mut current_block_opt: Option<(Checkpoint, Block)> = None;
+        for (ord, doc_id) in sorted_by_doc_id(doc_ids) {
+            let block_opt = current_block_opt
+                .take()
+                .filter(|(checkpoint, _)| checkpoint.doc_range.contains(&doc_id));
+            let (checkpoint, block) = match block_opt {
+                Some(current_block) => current_block,
+                None => {
+                    let checkpoint = self.block_checkpoint(doc_id)?;
+                    let block = self.read_block(&checkpoint)?;
+                    (checkpoint, block)
+                }
+            };
+            let doc_bytes =
+                Self::get_document_bytes_from_block(block.clone(), doc_id, &checkpoint)?;
+            docs.push((ord, self.deserialize_document(doc_bytes)?));
+            current_block_opt = Some((checkpoint, block));
+        }
+        Ok(into_original_order(docs))
     }
 
     /// Returns raw bytes of a given document.
@@ -231,10 +366,8 @@ impl StoreReader {
         &'b self,
         alive_bitset: Option<&'a AliveBitSet>,
     ) -> impl Iterator<Item = crate::Result<Document>> + 'b {
-        self.iter_raw(alive_bitset).map(|doc_bytes_res| {
-            let mut doc_bytes = doc_bytes_res?;
-            Ok(Document::deserialize(&mut doc_bytes)?)
-        })
+        self.iter_raw(alive_bitset)
+            .map(|doc_bytes_res| self.deserialize_document(doc_bytes_res?))
     }
 
     /// Iterator over all RawDocuments in their order as they are stored in the doc store.
@@ -348,8 +481,7 @@ impl StoreReader {
             .read_bytes_async()
             .await?;
 
-        let decompressed_block =
-            OwnedBytes::new(self.decompressor.decompress(compressed_block.as_ref())?);
+        let decompressed_block = self.decompress_block(checkpoint, compressed_block.as_ref())?;
 
         self.cache
             .put_into_cache(cache_key, decompressed_block.clone());
@@ -366,9 +498,46 @@ impl StoreReader {
 
     /// Reads raw bytes of a given document. Async version of [get](Self::get).
     pub async fn get_async(&self, doc_id: DocId) -> crate::Result<Document> {
-        let mut doc_bytes = self.get_document_bytes_async(doc_id).await?;
-        Ok(Document::deserialize(&mut doc_bytes)?)
+        let doc_bytes = self.get_document_bytes_async(doc_id).await?;
+        self.deserialize_document(doc_bytes)
     }
+
+    /// Reads the given documents. Async version of [get_many](Self::get_many).
+    pub async fn get_many_async(&self, doc_ids: &[DocId]) -> crate::Result<Vec<Document>> {
+        let mut docs = Vec::with_capacity(doc_ids.len());
+        let mut current_block_opt: Option<(Checkpoint, Block)> = None;
+        for (ord, doc_id) in sorted_by_doc_id(doc_ids) {
+            let block_opt = current_block_opt
+                .take()
+                .filter(|(checkpoint, _)| checkpoint.doc_range.contains(&doc_id));
+            let (checkpoint, block) = match block_opt {
+                Some(current_block) => current_block,
+                None => {
+                    let checkpoint = self.block_checkpoint(doc_id)?;
+                    let block = self.read_block_async(&checkpoint).await?;
+                    (checkpoint, block)
+                }
+            };
+            let doc_bytes =
+                Self::get_document_bytes_from_block(block.clone(), doc_id, &checkpoint)?;
+            docs.push((ord, self.deserialize_document(doc_bytes)?));
+            current_block_opt = Some((checkpoint, block));
+        }
+        Ok(into_original_order(docs))
+    }
+}
+
+/// Returns the doc ids along with their position in `doc_ids`, sorted by doc id.
+fn sorted_by_doc_id(doc_ids: &[DocId]) -> Vec<(usize, DocId)> {
+    let mut sorted_doc_ids: Vec<(usize, DocId)> = doc_ids.iter().copied().enumerate().collect();
+    sorted_doc_ids.sort_by_key(|&(_, doc_id)| doc_id);
+    sorted_doc_ids
+}
+
+/// Puts the documents read in the order of [`sorted_by_doc_id`] back in their original order.
+fn into_original_order(mut docs: Vec<(usize, Document)>) -> Vec<Document> {
+    docs.sort_by_key(|&(ord, _)| ord);
+    docs.into_iter().map(|(_, doc)| doc).collect()
 }
 
 #[cfg(test)]
@@ -431,4 +600,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_store_get_many() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("store");
+        let writer = directory.open_write(path)?;
+        let schema = write_lorem_ipsum_store(writer, 500, Compressor::default(), BLOCK_SIZE);
+        let title = schema.get_field("title").unwrap();
+        let store_file = directory.open_read(path)?;
+        // Without a cache, the blocks are still decompressed once.
+        let store = StoreReader::open(store_file, 0)?;
+
+        let doc_ids = [499, 0, 1, 498, 0];
+        let docs = store.get_many(&doc_ids)?;
+        let titles: Vec<Option<&str>> =
+            docs.iter().map(|doc| get_text_field(doc, &title)).collect();
+        assert_eq!(
+            titles,
+            vec![
+                Some("Doc 499"),
+                Some("Doc 0"),
+                Some("Doc 1"),
+                Some("Doc 498"),
+                Some("Doc 0")
+            ]
+        );
+        assert_eq!(store.cache_stats().cache_misses, 2);
+        assert!(store.get_many(&[500]).is_err());
+        Ok(())
+    }
 }
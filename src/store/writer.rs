@@ -3,8 +3,11 @@ use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
 use std::thread::{self, JoinHandle};
 
 use common::{BinarySerializable, CountingWriter, VInt};
+use ownedbytes::OwnedBytes;
 
-use super::compressors::Compressor;
+use super::blob_store::BlobWriter;
+use super::compressors::{Compressor, EncoderDictionary};
+use super::dictionaries::DictionariesBuilder;
 use super::footer::DocStoreFooter;
 use super::index::SkipIndexBuilder;
 use super::{Decompressor, StoreReader};
@@ -13,6 +16,11 @@ use crate::schema::Document;
 use crate::store::index::Checkpoint;
 use crate::DocId;
 
+// The dictionary is trained over about 100 times its size of documents, as recommended by zstd.
+const DICTIONARY_SAMPLE_SIZE_RATIO: usize = 100;
+// The blocks are buffered uncompressed until the dictionary is trained, so the sample is capped.
+const MAX_DICTIONARY_SAMPLE_NUM_BYTES: usize = 8 * 1024 * 1024;
+
 /// Write tantivy's [`Store`](./index.html)
 ///
 /// Contrary to the other components of `tantivy`,
@@ -26,6 +34,7 @@ pub struct StoreWriter {
     num_docs_in_current_block: DocId,
     intermediary_buffer: Vec<u8>,
     current_block: Vec<u8>,
+    blob_writer: Option<BlobWriter>,
 
     // the channel to send data to the compressor thread.
     compressor_sender: SyncSender<BlockCompressorMessage>,
@@ -36,6 +45,7 @@ pub struct StoreWriter {
 enum BlockCompressorMessage {
     AddBlock(DocumentBlock),
     Stack(StoreReader),
+    StackBlock(CompressedBlock),
 }
 
 impl StoreWriter {
@@ -60,11 +70,14 @@ impl StoreWriter {
             while let Ok(packet) = block_receiver.recv() {
                 match packet {
                     BlockCompressorMessage::AddBlock(block) => {
-                        block_compressor.compress_block_and_write(block)?;
+                        block_compressor.add_block(block)?;
                     }
                     BlockCompressorMessage::Stack(store_reader) => {
                         block_compressor.stack(store_reader)?;
                     }
+                    BlockCompressorMessage::StackBlock(block) => {
+                        block_compressor.stack_block(block)?;
+                    }
                 }
             }
             block_compressor.close()?;
@@ -77,11 +90,18 @@ impl StoreWriter {
             num_docs_in_current_block: 0,
             intermediary_buffer: Vec::new(),
             current_block: Vec::new(),
+            blob_writer: None,
             compressor_sender: block_sender,
             compressor_thread_handle: thread_join_handle,
         })
     }
 
+    /// Sets the writer of the large values of the documents to a blob store.
+    pub(crate) fn with_blob_writer(mut self, blob_writer: Option<BlobWriter>) -> StoreWriter {
+        self.blob_writer = blob_writer;
+        self
+    }
+
     pub(crate) fn compressor(&self) -> Compressor {
         self.compressor
     }
@@ -124,7 +144,11 @@ impl StoreWriter {
     /// of documents.
     pub fn store(&mut self, stored_document: &Document) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        stored_document.serialize(&mut self.intermediary_buffer)?;
+        if let Some(blob_writer) = self.blob_writer.as_mut() {
+            blob_writer.serialize(stored_document, &mut self.intermediary_buffer)?;
+        } else {
+            stored_document.serialize(&mut self.intermediary_buffer)?;
+        }
         // calling store bytes would be preferable for code reuse, but then we can't use
         // intermediary_buffer due to the borrow checker
         // a new buffer costs ~1% indexing performance
@@ -154,6 +178,8 @@ impl StoreWriter {
     /// This method is an optimization compared to iterating over the documents
     /// in the store and adding them one by one, as the store's data will
     /// not be decompressed and then recompressed.
+    ///
+    /// The stacked blocks keep the compression dictionary they were compressed with, if any.
     pub fn stack(&mut self, store_reader: StoreReader) -> io::Result<()> {
         // We flush the current block first before stacking
         self.send_current_block_to_compressor()?;
@@ -164,6 +190,26 @@ impl StoreWriter {
         Ok(())
     }
 
+    /// Stacks a single block of a store reader on top of the documents written so far,
+    /// without decompressing and recompressing it.
+    pub(crate) fn stack_block(
+        &mut self,
+        store_reader: &StoreReader,
+        checkpoint: &Checkpoint,
+    ) -> io::Result<()> {
+        // We flush the current block first before stacking
+        self.send_current_block_to_compressor()?;
+        let block = CompressedBlock {
+            data: store_reader.get_compressed_block(checkpoint)?,
+            num_docs_in_block: checkpoint.doc_range.end - checkpoint.doc_range.start,
+            dictionary: store_reader.block_dictionary_bytes(checkpoint),
+        };
+        self.compressor_sender
+            .send(BlockCompressorMessage::StackBlock(block))
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        Ok(())
+    }
+
     /// Finalized the store writer.
     ///
     /// Compress the last unfinished block if any,
@@ -187,6 +233,14 @@ pub struct BlockCompressor {
     offset_index_writer: SkipIndexBuilder,
     intermediary_buffer: Vec<u8>,
     writer: CountingWriter<WritePtr>,
+    // The first blocks, kept uncompressed until they are large enough to train the
+    // dictionary, if the compressor uses one.
+    sample_blocks: Option<Vec<DocumentBlock>>,
+    num_sample_bytes: usize,
+    dictionary: Option<EncoderDictionary>,
+    dictionary_ord: Option<u32>,
+    // The dictionaries of the blocks, including those of the stacked doc stores.
+    dictionaries: DictionariesBuilder,
 }
 
 struct DocumentBlock {
@@ -194,6 +248,13 @@ struct DocumentBlock {
     num_docs_in_block: DocId,
 }
 
+// A block copied from another doc store, with the dictionary it is compressed with.
+struct CompressedBlock {
+    data: OwnedBytes,
+    num_docs_in_block: DocId,
+    dictionary: Option<OwnedBytes>,
+}
+
 impl BlockCompressor {
     fn new(compressor: Compressor, writer: WritePtr) -> Self {
         Self {
@@ -202,16 +263,66 @@ impl BlockCompressor {
             offset_index_writer: SkipIndexBuilder::new(),
             intermediary_buffer: Vec::new(),
             writer: CountingWriter::wrap(writer),
+            sample_blocks: compressor.dictionary_size().map(|_| Vec::new()),
+            num_sample_bytes: 0,
+            dictionary: None,
+            dictionary_ord: None,
+            dictionaries: DictionariesBuilder::default(),
+        }
+    }
+
+    fn add_block(&mut self, block: DocumentBlock) -> io::Result<()> {
+        if let Some(sample_blocks) = self.sample_blocks.as_mut() {
+            self.num_sample_bytes += block.data.len();
+            sample_blocks.push(block);
+            let dictionary_size = self.compressor.dictionary_size().unwrap_or(0);
+            let sample_num_bytes = dictionary_size
+                .saturating_mul(DICTIONARY_SAMPLE_SIZE_RATIO)
+                .min(MAX_DICTIONARY_SAMPLE_NUM_BYTES);
+            if self.num_sample_bytes >= sample_num_bytes {
+                self.train_dictionary()?;
+            }
+            return Ok(());
         }
+        self.compress_block_and_write(block)
+    }
+
+    /// Trains the dictionary over the documents of the sampled blocks, and writes them.
+    fn train_dictionary(&mut self) -> io::Result<()> {
+        let sample_blocks = if let Some(sample_blocks) = self.sample_blocks.take() {
+            sample_blocks
+        } else {
+            return Ok(());
+        };
+        let samples: Vec<&[u8]> = sample_blocks
+            .iter()
+            .flat_map(|block| block_documents(&block.data))
+            .collect();
+        // The training fails if there are too few documents, in which case the blocks are
+        // compressed without a dictionary.
+        self.dictionary = self.compressor.train_dictionary(&samples).ok().flatten();
+        self.dictionary_ord = self
+            .dictionary
+            .as_ref()
+            .map(|dictionary| self.dictionaries.add_dictionary(dictionary.as_bytes()));
+        for block in sample_blocks {
+            self.compress_block_and_write(block)?;
+        }
+        Ok(())
     }
 
     fn compress_block_and_write(&mut self, block: DocumentBlock) -> io::Result<()> {
         assert!(block.num_docs_in_block > 0);
         self.intermediary_buffer.clear();
-        self.compressor
-            .compress_into(&block.data[..], &mut self.intermediary_buffer)?;
+        self.compressor.compress_into(
+            &block.data[..],
+            &mut self.intermediary_buffer,
+            self.dictionary.as_ref(),
+        )?;
 
         let start_offset = self.writer.written_bytes() as usize;
+        self.dictionaries
+            .use_dictionary(start_offset as u64, self.dictionary_ord);
         self.writer.write_all(&self.intermediary_buffer)?;
         let end_offset = self.writer.written_bytes() as usize;
 
@@ -232,8 +343,10 @@ impl BlockCompressor {
     /// in the store and adding them one by one, as the store's data will
     /// not be decompressed and then recompressed.
     fn stack(&mut self, store_reader: StoreReader) -> io::Result<()> {
+        self.write_sample_blocks()?;
         let doc_shift = self.first_doc_in_block;
         let start_shift = self.writer.written_bytes() as usize;
+        store_reader.stack_dictionaries_into(&mut self.dictionaries, start_shift as u64);
 
         // just bulk write all of the block of the given reader.
         self.writer
@@ -250,13 +363,60 @@ impl BlockCompressor {
         }
         Ok(())
     }
+
+    fn stack_block(&mut self, block: CompressedBlock) -> io::Result<()> {
+        self.write_sample_blocks()?;
+        let start_offset = self.writer.written_bytes() as usize;
+        let dictionary_ord_opt = block
+            .dictionary
+            .map(|dictionary| self.dictionaries.add_dictionary(dictionary.as_slice()));
+        self.dictionaries
+            .use_dictionary(start_offset as u64, dictionary_ord_opt);
+        self.writer.write_all(block.data.as_slice())?;
+        let end_offset = self.writer.written_bytes() as usize;
+        self.register_checkpoint(Checkpoint {
+            doc_range: self.first_doc_in_block..self.first_doc_in_block + block.num_docs_in_block,
+            byte_range: start_offset..end_offset,
+        });
+        Ok(())
+    }
+
+    // The sampled blocks are written before the stacked ones, with a dictionary trained over
+    // fewer documents.
+    fn write_sample_blocks(&mut self) -> io::Result<()> {
+        if let Some(sample_blocks) = self.sample_blocks.as_ref() {
+            if !sample_blocks.is_empty() {
+                self.train_dictionary()?;
+            }
+        }
+        Ok(())
+    }
+
     fn close(mut self) -> io::Result<()> {
+        self.train_dictionary()?;
         let header_offset: u64 = self.writer.written_bytes() as u64;
-        let docstore_footer =
-            DocStoreFooter::new(header_offset, Decompressor::from(self.compressor));
+        let mut dictionaries_bytes = Vec::new();
+        self.dictionaries.serialize_into(&mut dictionaries_bytes)?;
+        let docstore_footer = DocStoreFooter::new(
+            header_offset,
+            Decompressor::from(self.compressor),
+            dictionaries_bytes.len() as u64,
+        );
 
         self.offset_index_writer.serialize_into(&mut self.writer)?;
+        self.writer.write_all(&dictionaries_bytes)?;
         docstore_footer.serialize(&mut self.writer)?;
         self.writer.terminate()
     }
 }
+
+// Splits a block into its serialized documents.
+fn block_documents(mut block: &[u8]) -> Vec<&[u8]> {
+    let mut documents = Vec::new();
+    while let Ok(doc_num_bytes) = VInt::deserialize(&mut block) {
+        let (document, rest) = block.split_at(doc_num_bytes.val() as usize);
+        documents.push(document);
+        block = rest;
+    }
+    documents
+}
@@ -80,6 +80,7 @@ mod tests {
                     position: 0,
                     text: String::from("A"),
                     position_length: 1,
+                    payload: 0,
                 },
                 Token {
                     offset_from: 2,
@@ -87,6 +88,7 @@ mod tests {
                     position: 1,
                     text: String::from("a"),
                     position_length: 1,
+                    payload: 0,
                 },
             ],
         };
@@ -23,6 +23,18 @@ pub struct Token {
     pub text: String,
     /// Is the length expressed in term of number of original tokens.
     pub position_length: usize,
+    /// Payload attached to the token.
+    ///
+    /// It is only recorded for fields indexed with payloads (See
+    /// [`TextFieldIndexing::set_payloads`](crate::schema::TextFieldIndexing::set_payloads)),
+    /// and can then be read back at scoring time with
+    /// [`Postings::payloads`](crate::postings::Postings::payloads).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub payload: u32,
+}
+
+fn is_zero(payload: &u32) -> bool {
+    *payload == 0
 }
 
 impl Default for Token {
@@ -33,6 +45,7 @@ impl Default for Token {
             position: usize::max_value(),
             text: String::with_capacity(200),
             position_length: 1,
+            payload: 0,
         }
     }
 }
@@ -300,6 +313,7 @@ mod test {
             offset_to: 3,
             text: "abc".to_string(),
             position_length: 1,
+            payload: 0,
         };
         let t2 = t1.clone();
 